@@ -0,0 +1,151 @@
+// Baseline timings for the performance sensitive bits of the engine.
+// Run with `cargo bench`. Benchmarks that need a GPU are skipped if no adapter is available.
+
+use std::path::Path;
+
+use cgmath::{Vector3, Quaternion, Rotation3, Deg};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    font,
+    model::ModelData,
+    renderer::FieldBackground,
+    transform::Transform,
+    ui::{UiBatch, WHITE},
+    world::World
+};
+
+const TEST_FIELD_IMAGE: &str = "fields/test_field.png";
+const TEST_MODEL: &str = "models/test_prop.gltf";
+const ENTITY_COUNTS: [usize; 3] = [1_000, 5_000, 20_000];
+
+fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let runtime = tokio::runtime::Runtime::new().ok()?;
+    runtime.block_on(async {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false
+        }).await?;
+        adapter.request_device(&wgpu::DeviceDescriptor::default(), None).await.ok()
+    })
+}
+
+fn populated_world(count: usize) -> World {
+    let mut world = World::new();
+    for i in 0..count {
+        let entity = world.spawn();
+        let mut transform = Transform::from_position(Vector3::new(i as f32, 0.0, (i % 100) as f32));
+        transform.rotation = Quaternion::from_angle_y(Deg(i as f32));
+        world.insert(entity, transform);
+    }
+    world
+}
+
+fn image_loading(c: &mut Criterion) {
+    let path = Path::new(TEST_FIELD_IMAGE);
+    let image = FieldBackground::load_image(path);
+
+    let mut group = c.benchmark_group("field_background");
+    group.throughput(Throughput::Bytes(image.as_raw().len() as u64));
+
+    group.bench_function("png_decode", |b| {
+        b.iter(|| FieldBackground::load_image(black_box(path)))
+    });
+
+    match headless_device() {
+        Some((device, queue)) => {
+            group.bench_function("upload", |b| {
+                b.iter(|| {
+                    let background = FieldBackground::from_image(&device, &queue, black_box(&image));
+                    device.poll(wgpu::Maintain::Wait);
+                    background
+                })
+            });
+        },
+        None => eprintln!("No GPU adapter available, skipping upload benchmark.")
+    }
+
+    group.finish();
+}
+
+// Parsing a glTF file and decoding its embedded buffers and textures, as a field does for
+// each of its models.
+fn model_loading(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let assets = AssetServer::new(".");
+    let size = std::fs::metadata(TEST_MODEL).unwrap().len();
+
+    let mut group = c.benchmark_group("model");
+    group.throughput(Throughput::Bytes(size));
+
+    group.bench_function("gltf_load", |b| {
+        b.iter(|| runtime.block_on(ModelData::load(&assets, black_box(TEST_MODEL))).unwrap())
+    });
+
+    group.finish();
+}
+
+fn frame_building(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame_building");
+
+    // Per instance model matrices, as they'd be written into an instance buffer.
+    for count in ENTITY_COUNTS {
+        let world = populated_world(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("instance_matrices", count), &world, |b, world| {
+            let mut instances: Vec<[[f32; 4]; 4]> = Vec::with_capacity(count);
+            b.iter(|| {
+                instances.clear();
+                instances.extend(world.query::<Transform>().map(|(_, transform)| -> [[f32; 4]; 4] { transform.matrix().into() }));
+                black_box(&instances);
+            })
+        });
+    }
+
+    // A screen full of text.
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("ui_text", |b| {
+        let mut batch = UiBatch::new();
+        let line = "The quick brown fox jumps over the lazy dog 0123456789";
+        b.iter(|| {
+            batch.clear();
+            for i in 0..80 {
+                batch.text(0.0, i as f32 * 10.0, 1.0, black_box(line), WHITE);
+            }
+        })
+    });
+
+    group.bench_function("font_atlas", |b| {
+        b.iter(font::build_atlas)
+    });
+
+    group.finish();
+}
+
+fn world_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("world");
+
+    for count in ENTITY_COUNTS {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("spawn", count), &count, |b, count| {
+            b.iter(|| populated_world(*count))
+        });
+
+        let mut world = populated_world(count);
+        group.bench_function(BenchmarkId::new("query_mut", count), |b| {
+            b.iter(|| {
+                for (_, transform) in world.query_mut::<Transform>() {
+                    transform.position.y += 0.01;
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, image_loading, model_loading, frame_building, world_queries);
+criterion_main!(benches);
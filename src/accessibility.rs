@@ -0,0 +1,124 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::ui::WindowSkin;
+
+pub const MIN_TEXT_SCALE: f32 = 1.0;
+// Much bigger and menus stop fitting on the screen.
+pub const MAX_TEXT_SCALE: f32 = 2.0;
+
+// How fast message text types itself out.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+    // No typing out, the whole message shows straight away.
+    Instant
+}
+
+impl TextSpeed {
+    pub const ALL: [TextSpeed; 4] = [TextSpeed::Slow, TextSpeed::Normal, TextSpeed::Fast, TextSpeed::Instant];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextSpeed::Slow => "slow",
+            TextSpeed::Normal => "normal",
+            TextSpeed::Fast => "fast",
+            TextSpeed::Instant => "instant"
+        }
+    }
+
+    // None for instant.
+    pub fn chars_per_second(&self) -> Option<f32> {
+        match self {
+            TextSpeed::Slow => Some(20.0),
+            TextSpeed::Normal => Some(40.0),
+            TextSpeed::Fast => Some(80.0),
+            TextSpeed::Instant => None
+        }
+    }
+
+    // How many of a message's characters are showing after it's been typing for `elapsed`.
+    pub fn visible_chars(&self, elapsed: Duration, len: usize) -> usize {
+        match self.chars_per_second() {
+            Some(speed) => ((elapsed.as_secs_f32() * speed) as usize).min(len),
+            None => len
+        }
+    }
+}
+
+impl fmt::Display for TextSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TextSpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TextSpeed::ALL.into_iter()
+            .find(|speed| speed.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown text speed \"{}\", expected slow, normal, fast or instant", s.trim()))
+    }
+}
+
+// Resource with the player's accessibility settings. Menus and messages read it every frame,
+// so changes show straight away.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accessibility {
+    text_scale: f32,
+    pub text_speed: TextSpeed,
+    // Solid black windows and brighter text.
+    pub high_contrast: bool,
+    // Screen shake and flashes. Off for players who are sensitive to them.
+    pub screen_effects: bool,
+    // Subtitles in movies and voiced scenes, in the player's language.
+    pub subtitles: bool
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            text_scale: MIN_TEXT_SCALE,
+            text_speed: TextSpeed::default(),
+            high_contrast: false,
+            screen_effects: true,
+            subtitles: true
+        }
+    }
+}
+
+impl Accessibility {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // How much bigger than normal menu and message text is drawn.
+    pub fn text_scale(&self) -> f32 {
+        self.text_scale
+    }
+
+    // Clamped between MIN_TEXT_SCALE and MAX_TEXT_SCALE.
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.text_scale = if scale.is_nan() { MIN_TEXT_SCALE } else { scale.clamp(MIN_TEXT_SCALE, MAX_TEXT_SCALE) };
+    }
+
+    pub fn skin(&self) -> WindowSkin {
+        if self.high_contrast {
+            WindowSkin::HIGH_CONTRAST
+        } else {
+            WindowSkin::DEFAULT
+        }
+    }
+
+    // How strong a shake or flash should be, which is nothing with screen effects off.
+    pub fn effect_strength(&self, strength: f32) -> f32 {
+        if self.screen_effects {
+            strength
+        } else {
+            0.0
+        }
+    }
+}
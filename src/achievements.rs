@@ -0,0 +1,248 @@
+use std::{collections::{BTreeSet, VecDeque}, fs, io, path::{Path, PathBuf}, time::Duration};
+
+use instant::Instant;
+
+use crate::accessibility::Accessibility;
+use crate::flags::FlagEvent;
+use crate::logging::targets;
+use crate::platform::Platform;
+use crate::renderer::SCREEN_WIDTH;
+use crate::ui::UiBatch;
+
+const TOAST_TIME: Duration = Duration::from_secs(4);
+const TOAST_WIDTH: f32 = 280.0;
+const TOAST_MARGIN: f32 = 8.0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AchievementCondition {
+    // A flag reaching at least a value.
+    Flag { flag: String, value: i32 },
+    // A one off event, like winning a battle.
+    Event(String)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AchievementDefinition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: AchievementCondition
+}
+
+impl AchievementDefinition {
+    // Definitions are blocks of "key = value" lines under an [ACHIEVEMENT_ID] header, e.g.
+    //
+    // [FIRST_STEPS]
+    // name = First Steps
+    // description = Finish the introduction.
+    // flag = intro.complete
+    // value = 1
+    //
+    // Use "event = name" instead of flag for achievements unlocked by an event.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        struct Partial {
+            id: String,
+            name: Option<String>,
+            description: String,
+            flag: Option<String>,
+            value: i32,
+            event: Option<String>
+        }
+
+        fn finish(partial: Partial) -> Result<AchievementDefinition, String> {
+            let condition = match (partial.flag, partial.event) {
+                (Some(flag), None) => AchievementCondition::Flag { flag, value: partial.value },
+                (None, Some(event)) => AchievementCondition::Event(event),
+                _ => return Err(format!("{} needs either a flag or an event", partial.id))
+            };
+            Ok(AchievementDefinition {
+                name: partial.name.unwrap_or_else(|| partial.id.clone()),
+                id: partial.id,
+                description: partial.description,
+                condition
+            })
+        }
+
+        let mut definitions = Vec::new();
+        let mut current: Option<Partial> = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some(partial) = current.take() {
+                    definitions.push(finish(partial)?);
+                }
+                current = Some(Partial {
+                    id: id.trim().to_string(),
+                    name: None,
+                    description: String::new(),
+                    flag: None,
+                    value: 1,
+                    event: None
+                });
+                continue;
+            }
+
+            let partial = current.as_mut().ok_or_else(|| format!("Line {}: expected an [ACHIEVEMENT_ID] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "name" => partial.name = Some(value.to_string()),
+                "description" => partial.description = value.to_string(),
+                "flag" => partial.flag = Some(value.to_string()),
+                "value" => partial.value = value.parse().map_err(|_| format!("Line {}: bad value \"{}\"", number + 1, value))?,
+                "event" => partial.event = Some(value.to_string()),
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        if let Some(partial) = current {
+            definitions.push(finish(partial)?);
+        }
+        Ok(definitions)
+    }
+}
+
+// Watches flags and events, unlocks achievements and shows a toast for each one. Unlocked
+// achievements are saved locally whatever the platform, and passed on to the platform so
+// storefront backends can mirror them.
+pub struct Achievements {
+    definitions: Vec<AchievementDefinition>,
+    unlocked: BTreeSet<String>,
+
+    // Where unlocked achievements are saved, if anywhere.
+    save_path: Option<PathBuf>,
+
+    toasts: VecDeque<(usize, Option<Instant>)>
+}
+
+impl Achievements {
+    pub fn new(definitions: Vec<AchievementDefinition>) -> Self {
+        Self {
+            definitions,
+            unlocked: BTreeSet::new(),
+            save_path: None,
+            toasts: VecDeque::new()
+        }
+    }
+
+    // Load previously unlocked achievements and keep saving them to the same file. Anything
+    // already unlocked is passed on to the platform again in case it missed it last time.
+    pub fn load_local(&mut self, path: &Path, platform: &mut dyn Platform) {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                self.unlocked = text.lines().map(str::trim).filter(|id| !id.is_empty()).map(str::to_string).collect();
+                for id in &self.unlocked {
+                    platform.unlock_achievement(id);
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => tracing::warn!(target: targets::ENGINE, "Couldn't read achievements from {}: {}", path.display(), e)
+        }
+        self.save_path = Some(path.to_path_buf());
+    }
+
+    fn save_local(&self) {
+        let path = match &self.save_path {
+            Some(path) => path,
+            None => return
+        };
+
+        let mut text = String::new();
+        for id in &self.unlocked {
+            text.push_str(id);
+            text.push('\n');
+        }
+        let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path, text));
+        if let Err(e) = result {
+            tracing::warn!(target: targets::ENGINE, "Couldn't save achievements to {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn definitions(&self) -> &[AchievementDefinition] {
+        &self.definitions
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    pub fn unlock(&mut self, id: &str, platform: &mut dyn Platform) {
+        let index = match self.definitions.iter().position(|definition| definition.id == id) {
+            Some(index) => index,
+            None => {
+                tracing::warn!(target: targets::ENGINE, "No achievement called {}", id);
+                return;
+            }
+        };
+        if !self.unlocked.insert(id.to_string()) {
+            return;
+        }
+
+        tracing::info!(target: targets::ENGINE, "Unlocked achievement {}", id);
+        platform.unlock_achievement(id);
+        self.toasts.push_back((index, None));
+        self.save_local();
+    }
+
+    // Check flag changes against the unlock conditions.
+    pub fn update(&mut self, events: &[FlagEvent], platform: &mut dyn Platform) {
+        let unlocked: Vec<String> = self.definitions.iter()
+            .filter(|definition| match &definition.condition {
+                AchievementCondition::Flag { flag, value } =>
+                    events.iter().any(|event| &event.name == flag && event.value >= *value),
+                AchievementCondition::Event(_) => false
+            })
+            .map(|definition| definition.id.clone())
+            .collect();
+        for id in unlocked {
+            self.unlock(&id, platform);
+        }
+    }
+
+    // Something happened that an achievement might be waiting for.
+    pub fn notify_event(&mut self, event: &str, platform: &mut dyn Platform) {
+        let unlocked: Vec<String> = self.definitions.iter()
+            .filter(|definition| definition.condition == AchievementCondition::Event(event.to_string()))
+            .map(|definition| definition.id.clone())
+            .collect();
+        for id in unlocked {
+            self.unlock(&id, platform);
+        }
+    }
+
+    // Show the toast for the most recent unlock in the top right of the screen.
+    pub fn build_toasts(&mut self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        let now = Instant::now();
+        while let Some((_, Some(shown))) = self.toasts.front() {
+            if now.duration_since(*shown) < TOAST_TIME {
+                break;
+            }
+            self.toasts.pop_front();
+        }
+
+        let (index, shown) = match self.toasts.front_mut() {
+            Some(toast) => toast,
+            None => return
+        };
+        shown.get_or_insert(now);
+
+        let definition = &self.definitions[*index];
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        let line_height = UiBatch::line_height(scale);
+        let title = "Achievement unlocked";
+        // Wide enough for the text when it's been made bigger, but no wider than the screen.
+        let text_width = UiBatch::measure_text(scale, title).0.max(UiBatch::measure_text(scale, &definition.name).0);
+        let width = TOAST_WIDTH.max(text_width + TOAST_MARGIN * 2.0).min(SCREEN_WIDTH as f32 - TOAST_MARGIN * 2.0);
+        let x = SCREEN_WIDTH as f32 - width - TOAST_MARGIN;
+        let y = TOAST_MARGIN;
+
+        batch.rect(x, y, width, line_height * 2.0 + TOAST_MARGIN * 2.0, skin.window);
+        batch.text(x + TOAST_MARGIN, y + TOAST_MARGIN, scale, title, skin.highlight);
+        batch.text(x + TOAST_MARGIN, y + TOAST_MARGIN + line_height, scale, &definition.name, skin.text);
+    }
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use crate::world::{World, Entity};
+
+// A named moment in a clip, like a footstep landing or the frame an attack connects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationEventMarker {
+    pub time: Duration,
+    pub name: String
+}
+
+// A timeline that can be played on an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub duration: Duration,
+    pub looping: bool,
+    // Kept in time order.
+    events: Vec<AnimationEventMarker>
+}
+
+impl AnimationClip {
+    pub fn new(name: &str, duration: Duration, looping: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            duration,
+            looping,
+            events: Vec::new()
+        }
+    }
+
+    // Mark a moment in the clip, e.g. "footstep" at 0.3s. Times past the end are clamped to it.
+    pub fn with_event(mut self, time: Duration, name: &str) -> Self {
+        let time = time.min(self.duration);
+        let index = self.events.partition_point(|event| event.time <= time);
+        self.events.insert(index, AnimationEventMarker {
+            time,
+            name: name.to_string()
+        });
+        self
+    }
+
+    pub fn events(&self) -> &[AnimationEventMarker] {
+        &self.events
+    }
+}
+
+// An event that an AnimationPlayer passed. Audio, particles and battle damage watch for these
+// so they happen in time with the animation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub name: String
+}
+
+// Resource collecting the events fired since they were last taken.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationEvents {
+    events: Vec<AnimationEvent>
+}
+
+impl AnimationEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Events since the last call, in the order they happened.
+    pub fn take_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// Component playing a clip on an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationPlayer {
+    clip: Option<AnimationClip>,
+    time: Duration,
+    // Whether the clip has been moved on since it was started.
+    started: bool,
+    // 1 is normal speed.
+    pub speed: f32
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self {
+            clip: None,
+            time: Duration::ZERO,
+            started: false,
+            speed: 1.0
+        }
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start a clip from the beginning. Events right at the start fire on the next update.
+    pub fn play(&mut self, clip: AnimationClip) {
+        self.clip = Some(clip);
+        self.time = Duration::ZERO;
+        self.started = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.clip = None;
+        self.time = Duration::ZERO;
+    }
+
+    pub fn clip(&self) -> Option<&AnimationClip> {
+        self.clip.as_ref()
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    // Whether the clip still has further to go. Looping clips never finish, other clips stay on
+    // their last frame once they have.
+    pub fn is_playing(&self) -> bool {
+        match &self.clip {
+            Some(clip) => clip.looping || !self.started || self.time < clip.duration,
+            None => false
+        }
+    }
+
+    // Move the clip on, calling back with every event passed on the way, in order. Looping
+    // clips pass their events again each time round, however many times that is in one go.
+    pub fn advance(&mut self, delta: Duration, mut on_event: impl FnMut(&AnimationClip, &AnimationEventMarker)) {
+        if !self.is_playing() {
+            return;
+        }
+        let clip = match &self.clip {
+            Some(clip) => clip,
+            None => return
+        };
+
+        // Scaling goes through floats, so it's skipped at normal speed to keep times exact.
+        let mut remaining = if self.speed == 1.0 { delta } else { delta.mul_f32(self.speed.max(0.0)) };
+        // Events right at the start of a lap count, unless the lap is already under way.
+        let mut include_start = !self.started;
+        self.started = true;
+        loop {
+            if clip.looping && !clip.duration.is_zero() && self.time >= clip.duration {
+                self.time = Duration::ZERO;
+                include_start = true;
+            }
+
+            let to = (self.time + remaining).min(clip.duration);
+            for event in &clip.events {
+                let after_start = if include_start { event.time >= self.time } else { event.time > self.time };
+                if after_start && event.time <= to {
+                    on_event(clip, event);
+                }
+            }
+            remaining -= to - self.time;
+            self.time = to;
+            include_start = false;
+
+            if remaining.is_zero() || !clip.looping || clip.duration.is_zero() {
+                break;
+            }
+        }
+    }
+}
+
+// Move every entity's animation on, putting the events they pass in the AnimationEvents
+// resource. Returns true while anything is still playing.
+pub fn update_animations(world: &mut World, delta: Duration) -> bool {
+    let mut fired = Vec::new();
+    let mut playing = false;
+    for (entity, player) in world.query_mut::<AnimationPlayer>() {
+        player.advance(delta, |clip, event| fired.push(AnimationEvent {
+            entity,
+            clip: clip.name.clone(),
+            name: event.name.clone()
+        }));
+        playing |= player.is_playing();
+    }
+
+    if !fired.is_empty() {
+        if world.resource::<AnimationEvents>().is_none() {
+            world.insert_resource(AnimationEvents::new());
+        }
+        if let Some(events) = world.resource_mut::<AnimationEvents>() {
+            events.events.extend(fired);
+        }
+    }
+    playing
+}
@@ -0,0 +1,312 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt, path::PathBuf, sync::{Arc, Mutex}};
+
+use crate::logging::targets;
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(String, std::io::Error),
+    Fetch(String, String),
+    Decode(String, String)
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(path, e) => write!(f, "Couldn't read {}: {}", path, e),
+            AssetError::Fetch(path, e) => write!(f, "Couldn't fetch {}: {}", path, e),
+            AssetError::Decode(path, e) => write!(f, "Couldn't decode {}: {}", path, e)
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// Loads game data. On desktop it reads files relative to a root directory, on the web it
+// fetches them relative to a base URL. Everything goes through here so the rest of the
+// engine doesn't have to care which. Mods are mounted over the root on desktop, see mods.rs.
+//
+// Assets can also come in a version for each language, like signs with writing on them or voiced
+// lines, kept under localized/<language>/ with the same path they'd have otherwise, e.g.
+// "localized/ja/textures/sign.png" for "textures/sign.png". Loading the plain path picks the
+// player's language if there's a version for it, then the same language for any region, e.g.
+// "localized/pt/" for "pt-BR", then the plain one. Data files aren't localized this way, text
+// has its own.
+#[derive(Clone, Debug)]
+pub struct AssetServer {
+    root: PathBuf,
+    // Mod folders, in the order they go on, so later ones win.
+    mods: Vec<PathBuf>,
+    // Shared by every clone, so changing the language changes it everywhere.
+    language: Arc<Mutex<String>>,
+    // Shared by every clone, so it doesn't matter which one did the loading.
+    dependencies: Arc<Mutex<AssetDependencies>>,
+    // Whatever this server loads is recorded as needed by this, see for_owner.
+    owner: Option<String>
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+impl AssetServer {
+    // On the web the root is a URL, e.g. "." to load from next to the page.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            mods: Vec::new(),
+            language: Arc::new(Mutex::new(String::new())),
+            dependencies: Arc::new(Mutex::new(AssetDependencies::default())),
+            owner: None
+        }
+    }
+
+    // Mount mods over the root. Only on desktop, the web build ignores them.
+    pub fn with_mods(self, mods: Vec<PathBuf>) -> Self {
+        Self { mods, ..self }
+    }
+
+    pub fn mods(&self) -> &[PathBuf] {
+        &self.mods
+    }
+
+    // Which language's versions of assets to load. Empty for only the plain ones. Takes effect
+    // from the next thing that's loaded.
+    pub fn set_language(&self, language: &str) {
+        *self.language.lock().unwrap_or_else(|e| e.into_inner()) = language.trim().to_string();
+    }
+
+    pub fn language(&self) -> String {
+        self.language.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    // The paths to try for `path`, best first, see the top of the file.
+    pub fn localized_paths(&self, path: &str) -> Vec<String> {
+        let language = self.language();
+        let mut paths = Vec::new();
+        if !language.is_empty() && !crate::mods::is_merged(path) {
+            paths.push(format!("localized/{}/{}", language, path));
+            let base = crate::subtitles::base_language(&language);
+            if base != language {
+                paths.push(format!("localized/{}/{}", base, path));
+            }
+        }
+        paths.push(path.to_string());
+        paths
+    }
+
+    // A server that records everything it loads as needed by `owner`, like "field/town" or
+    // "battle/boss". That includes whatever those assets load in turn, like a model's
+    // buffers.
+    pub fn for_owner(&self, owner: &str) -> Self {
+        Self {
+            owner: Some(owner.to_string()),
+            ..self.clone()
+        }
+    }
+
+    pub async fn load_bytes(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        tracing::debug!(target: targets::ASSETS, "Loading {}", path);
+        if let Some(owner) = &self.owner {
+            self.record_dependency(owner, path);
+        }
+        self.read(path).await
+    }
+
+    pub async fn load_image(&self, path: &str) -> Result<image::RgbaImage, AssetError> {
+        let bytes = self.load_bytes(path).await?;
+        image::load_from_memory(&bytes)
+            .map(|image| image.to_rgba8())
+            .map_err(|e| AssetError::Decode(path.to_string(), e.to_string()))
+    }
+
+    // Record that `owner` needs `path`, without loading it. For things that haven't been
+    // loaded yet, like the fields the player hasn't been to.
+    pub fn record_dependency(&self, owner: &str, path: &str) {
+        self.dependencies().record(owner, path);
+    }
+
+    // Everything `owner` needs, in order.
+    pub fn dependencies_of(&self, owner: &str) -> Vec<String> {
+        self.dependencies().of(owner)
+    }
+
+    // Forget everything `owner` needs, and get back the assets nothing else needs any more,
+    // so they can be let go of all at once.
+    pub fn unload(&self, owner: &str) -> Vec<String> {
+        self.dependencies().unload(owner)
+    }
+
+    // Compare what's been recorded against the list of assets the game ships with.
+    pub fn report(&self, manifest: &AssetManifest) -> AssetReport {
+        self.dependencies().report(manifest)
+    }
+
+    fn dependencies(&self) -> std::sync::MutexGuard<'_, AssetDependencies> {
+        // Nothing can be left half changed, so it's fine to carry on after a panic.
+        self.dependencies.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // When a file was last changed, for noticing it's been edited. The latest of the game's copy
+    // and any mod's, in any version for the language.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn modified(&self, path: &str) -> Option<std::time::SystemTime> {
+        let paths = self.localized_paths(path);
+        std::iter::once(&self.root).chain(&self.mods)
+            .flat_map(|dir| paths.iter().map(move |path| dir.join(path)))
+            .filter_map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+            .max()
+    }
+
+    // The last mod's copy if any have one, otherwise the game's, taking the version for the
+    // language from each before its plain one. So a mod's plain copy still wins over the game's
+    // localized one, as it's replaced the asset. Data files are merged from the game's copy up
+    // through every mod's instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn read(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        let read = |dir: &PathBuf| std::fs::read(dir.join(path));
+        if !crate::mods::is_merged(path) {
+            let localized = self.localized_paths(path);
+            for dir in self.mods.iter().rev().chain(std::iter::once(&self.root)) {
+                for candidate in &localized {
+                    if let Ok(bytes) = std::fs::read(dir.join(candidate)) {
+                        return Ok(bytes);
+                    }
+                }
+            }
+            return read(&self.root).map_err(|e| AssetError::Io(path.to_string(), e));
+        }
+
+        // Mods can add data files the game doesn't have.
+        let mut merged = match read(&self.root) {
+            Ok(bytes) => Some(String::from_utf8_lossy(&bytes).to_string()),
+            Err(e) if self.mods.is_empty() => return Err(AssetError::Io(path.to_string(), e)),
+            Err(_) => None
+        };
+        for dir in &self.mods {
+            if let Ok(bytes) = read(dir) {
+                let over = String::from_utf8_lossy(&bytes);
+                merged = Some(crate::mods::merge_data(merged.as_deref().unwrap_or_default(), &over));
+            }
+        }
+        merged.map(String::into_bytes)
+            .ok_or_else(|| AssetError::Io(path.to_string(), std::io::Error::from(std::io::ErrorKind::NotFound)))
+    }
+
+    // The version for the language if there's one, otherwise the plain one.
+    #[cfg(target_arch = "wasm32")]
+    async fn read(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        let localized = self.localized_paths(path);
+        for candidate in &localized[..localized.len() - 1] {
+            if let Ok(bytes) = self.fetch(candidate).await {
+                return Ok(bytes);
+            }
+        }
+        self.fetch(path).await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn fetch(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        let fetch_error = |e: wasm_bindgen::JsValue| AssetError::Fetch(path.to_string(), format!("{:?}", e));
+
+        let url = format!("{}/{}", self.root.display(), path);
+        let mut options = web_sys::RequestInit::new();
+        options.method("GET");
+        options.mode(web_sys::RequestMode::SameOrigin);
+        let request = web_sys::Request::new_with_str_and_init(&url, &options).map_err(fetch_error)?;
+
+        let window = web_sys::window().ok_or_else(|| AssetError::Fetch(path.to_string(), "No window".to_string()))?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request)).await
+            .map_err(fetch_error)?
+            .dyn_into()
+            .map_err(fetch_error)?;
+        if !response.ok() {
+            return Err(AssetError::Fetch(path.to_string(), format!("HTTP {}", response.status())));
+        }
+
+        let buffer = JsFuture::from(response.array_buffer().map_err(fetch_error)?).await.map_err(fetch_error)?;
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+}
+
+// Which assets each owner, like a field or a battle, needs.
+#[derive(Debug, Default)]
+struct AssetDependencies {
+    owners: BTreeMap<String, BTreeSet<String>>
+}
+
+impl AssetDependencies {
+    fn record(&mut self, owner: &str, path: &str) {
+        self.owners.entry(owner.to_string()).or_default().insert(path.to_string());
+    }
+
+    fn of(&self, owner: &str) -> Vec<String> {
+        self.owners.get(owner).map(|paths| paths.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    fn unload(&mut self, owner: &str) -> Vec<String> {
+        let paths = self.owners.remove(owner).unwrap_or_default();
+        paths.into_iter()
+            .filter(|path| !self.owners.values().any(|others| others.contains(path)))
+            .collect()
+    }
+
+    fn report(&self, manifest: &AssetManifest) -> AssetReport {
+        let mut missing = Vec::new();
+        for (owner, paths) in &self.owners {
+            for path in paths {
+                if !manifest.contains(path) {
+                    missing.push(MissingAsset { owner: owner.clone(), path: path.clone() });
+                }
+            }
+        }
+        let unused = manifest.paths.iter()
+            .filter(|path| !self.owners.values().any(|paths| paths.contains(*path)))
+            .cloned()
+            .collect();
+        AssetReport { unused, missing }
+    }
+}
+
+// Every asset the game ships with. One path per line, with # starting a comment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetManifest {
+    paths: BTreeSet<String>
+}
+
+impl AssetManifest {
+    pub fn parse(text: &str) -> Self {
+        let paths = text.lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { paths }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.contains(path)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+}
+
+// An asset something needs that isn't in the manifest, so won't be shipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingAsset {
+    pub owner: String,
+    pub path: String
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetReport {
+    // In the manifest, but nothing's recorded as needing them.
+    pub unused: Vec<String>,
+    pub missing: Vec<MissingAsset>
+}
@@ -0,0 +1,96 @@
+use cgmath::{Matrix4, SquareMatrix};
+
+use crate::hit_reaction;
+use crate::model::ModelData;
+use crate::transform::Transform;
+use crate::world::{World, Entity};
+
+// Attachments can hang off other attachments, like a flame on a torch in someone's hand, but
+// not this deep. Stops a loop of attachments from going round forever.
+const MAX_ATTACHMENT_DEPTH: usize = 16;
+
+// Component for an entity with a model, saying where its named points are relative to it.
+// Skinned models don't bend yet, so sockets on bones stay where the bone is at rest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelSockets {
+    sockets: Vec<(String, Matrix4<f32>)>
+}
+
+impl ModelSockets {
+    pub fn new(sockets: Vec<(String, Matrix4<f32>)>) -> Self {
+        Self { sockets }
+    }
+
+    pub fn from_model(model: &ModelData) -> Self {
+        Self::new(model.sockets.clone())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Matrix4<f32>> {
+        self.sockets.iter()
+            .find(|(socket, _)| socket == name)
+            .map(|(_, transform)| transform)
+    }
+
+    // Move a socket, e.g. to where a bone has swung to. Does nothing if there's no such socket.
+    pub fn set(&mut self, name: &str, transform: Matrix4<f32>) {
+        if let Some((_, socket)) = self.sockets.iter_mut().find(|(socket, _)| socket == name) {
+            *socket = transform;
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sockets.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+// Component that keeps an entity stuck to a socket on another one, like a sword in a hand.
+// The entity's Transform is overwritten every frame by update_attachments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    pub parent: Entity,
+    pub socket: String,
+    // Where the entity sits relative to the socket.
+    pub offset: Transform
+}
+
+impl Attachment {
+    pub fn new(parent: Entity, socket: &str) -> Self {
+        Self {
+            parent,
+            socket: socket.to_string(),
+            offset: Transform::default()
+        }
+    }
+}
+
+// Move everything attached to a socket to where the socket is now. Call once everything that
+// moves the parents, like animation, has had its turn.
+// Sockets the parent doesn't have put the entity at the parent's origin. Entities attached
+// to something that's gone are left where they were.
+pub fn update_attachments(world: &mut World) {
+    let attached: Vec<Entity> = world.query::<Attachment>().map(|(entity, _)| entity).collect();
+    let placed: Vec<(Entity, Transform)> = attached.into_iter()
+        .filter_map(|entity| world_matrix(world, entity, 0).map(|matrix| (entity, Transform::from_matrix(&matrix))))
+        .collect();
+    for (entity, transform) in placed {
+        world.insert(entity, transform);
+    }
+}
+
+// Where an entity is, going up through whatever it's attached to.
+fn world_matrix(world: &World, entity: Entity, depth: usize) -> Option<Matrix4<f32>> {
+    let attachment = match world.get::<Attachment>(entity) {
+        Some(attachment) if depth < MAX_ATTACHMENT_DEPTH => attachment,
+        _ => return world.get::<Transform>(entity).map(|transform| hit_reaction::posed_matrix(world, entity, transform))
+    };
+    if !world.contains(attachment.parent) {
+        return None;
+    }
+
+    let parent = world_matrix(world, attachment.parent, depth + 1)?;
+    let socket = world.get::<ModelSockets>(attachment.parent)
+        .and_then(|sockets| sockets.get(&attachment.socket))
+        .copied()
+        .unwrap_or_else(Matrix4::identity);
+    Some(parent * socket * attachment.offset.matrix())
+}
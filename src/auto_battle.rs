@@ -0,0 +1,213 @@
+// Letting party members fight by themselves, for grinding. Each member has a list of rules,
+// tried in order on their turn, and the first that applies is what they do:
+//
+//   ally hp < 30: skill Cure
+//   ally down: item phoenix_down
+//   always: attack weakest
+//
+// The conditions are "always", "ally hp < percent" for an ally under that much HP and "ally
+// down" for one that's knocked out. The actions are "attack" with "weakest", "strongest" or
+// "first" for who, "skill <name>" and "item <id>". Rules for skills the member can't afford or
+// items that have run out are skipped. Members with auto battle on use their rules, or the
+// whole party can be put on auto at once, and battles can be sped up while they fight.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::battle_ui::{Combatant, Side};
+use crate::inventory::Inventory;
+use crate::party::{PartyMember, Stats, TargetType};
+use crate::pools;
+
+// How much faster battles can be played, in order.
+pub const SPEEDS: [u32; 3] = [1, 2, 4];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GambitCondition {
+    Always,
+    AllyHpBelow(u32),
+    AllyDown
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyPick {
+    Weakest,
+    Strongest,
+    First
+}
+
+impl EnemyPick {
+    pub const ALL: [EnemyPick; 3] = [EnemyPick::Weakest, EnemyPick::Strongest, EnemyPick::First];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyPick::Weakest => "weakest",
+            EnemyPick::Strongest => "strongest",
+            EnemyPick::First => "first"
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GambitAction {
+    Attack(EnemyPick),
+    Skill(String),
+    Item(String)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gambit {
+    pub condition: GambitCondition,
+    pub action: GambitAction
+}
+
+impl Gambit {
+    // What members fight with until they're given rules of their own.
+    pub fn defaults() -> Vec<Gambit> {
+        vec![Gambit { condition: GambitCondition::Always, action: GambitAction::Attack(EnemyPick::Weakest) }]
+    }
+}
+
+impl fmt::Display for Gambit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.condition {
+            GambitCondition::Always => write!(f, "always: ")?,
+            GambitCondition::AllyHpBelow(percent) => write!(f, "ally hp < {}: ", percent)?,
+            GambitCondition::AllyDown => write!(f, "ally down: ")?
+        }
+        match &self.action {
+            GambitAction::Attack(pick) => write!(f, "attack {}", pick.name()),
+            GambitAction::Skill(skill) => write!(f, "skill {}", skill),
+            GambitAction::Item(item) => write!(f, "item {}", item)
+        }
+    }
+}
+
+impl FromStr for Gambit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (condition, action) = s.split_once(':').ok_or_else(|| format!("Expected \"condition: action\", not \"{}\"", s.trim()))?;
+        let words: Vec<&str> = condition.split_whitespace().collect();
+        let condition = match words.as_slice() {
+            ["always"] => GambitCondition::Always,
+            ["ally", "hp", "<", percent] => GambitCondition::AllyHpBelow(
+                percent.trim_end_matches('%').parse().map_err(|_| format!("Bad percent \"{}\"", percent))?
+            ),
+            ["ally", "down"] => GambitCondition::AllyDown,
+            _ => return Err(format!("Unknown condition \"{}\"", condition.trim()))
+        };
+        let action = match action.trim().split_once(' ') {
+            Some(("skill", skill)) => GambitAction::Skill(skill.trim().to_string()),
+            Some(("item", item)) => GambitAction::Item(item.trim().to_string()),
+            Some(("attack", pick)) => GambitAction::Attack(EnemyPick::ALL.into_iter()
+                .find(|known| known.name() == pick.trim())
+                .ok_or_else(|| format!("Unknown target \"{}\", expected weakest, strongest or first", pick.trim()))?),
+            None if action.trim() == "attack" => GambitAction::Attack(EnemyPick::First),
+            _ => return Err(format!("Unknown action \"{}\"", action.trim()))
+        };
+        Ok(Gambit { condition, action })
+    }
+}
+
+// What an auto battling member does on their turn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoCommand {
+    pub action: GambitAction,
+    // As indices into the combatants.
+    pub targets: Vec<usize>
+}
+
+// Go through the member's rules for their turn. `user` is who they are in the combatants, and
+// `stats` has everyone's stats in the same order. None if no rule applies.
+pub fn decide(user: usize, member: &PartyMember, combatants: &[Combatant], stats: &[Stats], inventory: &Inventory) -> Option<AutoCommand> {
+    let side = combatants.get(user)?.side;
+    let hp_percent = |index: usize| stats.get(index).map(|stats| stats.hp as u64 * 100 / stats.max_hp.max(1) as u64).unwrap_or(100);
+    let on_side = |want: Side, down: bool| -> Vec<usize> {
+        (0..combatants.len()).filter(|index| combatants[*index].side == want && combatants[*index].knocked_out == down).collect()
+    };
+    let allies = on_side(side, false);
+    let enemies = on_side(if side == Side::Party { Side::Enemies } else { Side::Party }, false);
+    let pick_enemy = |pick: EnemyPick| -> Option<usize> {
+        let hp = |index: &usize| stats.get(*index).map(|stats| stats.hp).unwrap_or_default();
+        match pick {
+            EnemyPick::Weakest => enemies.iter().copied().min_by_key(hp),
+            EnemyPick::Strongest => enemies.iter().copied().rev().max_by_key(hp),
+            EnemyPick::First => enemies.first().copied()
+        }
+    };
+
+    for gambit in &member.gambits {
+        // Who the condition picked out, if anyone.
+        let chosen = match gambit.condition {
+            GambitCondition::Always => None,
+            GambitCondition::AllyHpBelow(percent) => match allies.iter().copied().filter(|ally| hp_percent(*ally) < percent as u64).min_by_key(|ally| hp_percent(*ally)) {
+                Some(ally) => Some(ally),
+                None => continue
+            },
+            // Summons that are down have left, so there's nobody to bring back.
+            GambitCondition::AllyDown => match on_side(side, true).into_iter().find(|ally| !combatants[*ally].summoned) {
+                Some(ally) => Some(ally),
+                None => continue
+            }
+        };
+        let target = match &gambit.action {
+            GambitAction::Attack(pick) => pick_enemy(*pick).map(|enemy| vec![enemy]),
+            GambitAction::Skill(name) => {
+                let skill = match member.skills.iter().find(|skill| skill.name.eq_ignore_ascii_case(name)) {
+                    Some(skill) if pools::can_afford(member, skill, inventory).is_ok() => skill,
+                    _ => continue
+                };
+                match skill.target {
+                    TargetType::Enemy => pick_enemy(EnemyPick::Weakest).map(|enemy| vec![enemy]),
+                    TargetType::AllEnemies => Some(enemies.clone()).filter(|enemies| !enemies.is_empty()),
+                    TargetType::Ally => chosen.or_else(|| allies.iter().copied().min_by_key(|ally| hp_percent(*ally))).map(|ally| vec![ally]),
+                    TargetType::AllAllies => Some(allies.clone()),
+                    TargetType::User => Some(vec![user])
+                }
+            },
+            GambitAction::Item(id) if inventory.count(id) > 0 => Some(vec![chosen.unwrap_or(user)]),
+            GambitAction::Item(_) => continue
+        };
+        if let Some(targets) = target {
+            return Some(AutoCommand { action: gambit.action.clone(), targets });
+        }
+    }
+    None
+}
+
+// Resource with how auto battling's set up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoBattle {
+    // Everyone fights by themselves, whatever their own setting.
+    pub party: bool,
+    // One of SPEEDS.
+    pub speed: u32
+}
+
+impl Default for AutoBattle {
+    fn default() -> Self {
+        Self { party: false, speed: 1 }
+    }
+}
+
+impl AutoBattle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_auto(&self, member: &PartyMember) -> bool {
+        self.party || member.auto_battle
+    }
+
+    // Go to the next speed up, back round to normal after the fastest.
+    pub fn next_speed(&mut self) -> u32 {
+        let index = SPEEDS.iter().position(|speed| *speed == self.speed).map_or(0, |index| (index + 1) % SPEEDS.len());
+        self.speed = SPEEDS[index];
+        self.speed
+    }
+
+    // How far a battle moves on in a frame of `delta`.
+    pub fn scale(&self, delta: Duration) -> Duration {
+        delta * self.speed.max(1)
+    }
+}
@@ -0,0 +1,159 @@
+// What happened in a battle, once it's over: which enemies were beaten, how many turns it took
+// and what was used, rather than only whether it was won. Quests and scripts go by these, like
+// "defeat the bandit leader" or "win without magic".
+//
+// Whatever runs the battle tallies it up in a BattleReport as it goes, then hands it to record
+// at the end. That puts it in flags, which quest stages, dialogue and achievements already
+// check:
+//
+//   battle.won, battle.lost, battle.fled   how many battles have ended each way
+//   defeated.<enemy>                       how many of an enemy have ever been beaten
+//   battle.last.outcome                    1 won, 2 lost or 3 fled
+//   battle.last.turns                      how many turns the last one took
+//   battle.last.items                      how many items were used in it
+//   battle.last.magic                      how many skills that cost MP were used in it
+//
+// It's also sent as achievement events, see events, and kept in BattleReports for anything
+// that wants the whole report.
+
+use std::collections::BTreeMap;
+
+use crate::flags::GameFlags;
+use crate::party::Skill;
+use crate::play_stats::PlayStats;
+use crate::telemetry::{self, BattleOutcome, TelemetryEvent};
+use crate::world::World;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BattleReport {
+    pub formation: String,
+    pub outcome: BattleOutcome,
+    pub turns: u32,
+    // By enemy id, how many of each.
+    pub defeated: BTreeMap<String, u32>,
+    // By item id.
+    pub items_used: BTreeMap<String, u32>,
+    // By skill name.
+    pub skills_used: BTreeMap<String, u32>,
+    // Skills used that cost MP.
+    pub magic_used: u32
+}
+
+impl BattleReport {
+    // Nothing's happened yet. It's a loss until it's finished otherwise.
+    pub fn new(formation: &str) -> Self {
+        Self {
+            formation: formation.to_string(),
+            outcome: BattleOutcome::Lost,
+            turns: 0,
+            defeated: BTreeMap::new(),
+            items_used: BTreeMap::new(),
+            skills_used: BTreeMap::new(),
+            magic_used: 0
+        }
+    }
+
+    pub fn end_turn(&mut self) {
+        self.turns += 1;
+    }
+
+    pub fn defeat(&mut self, enemy: &str) {
+        *self.defeated.entry(enemy.to_string()).or_default() += 1;
+    }
+
+    pub fn use_item(&mut self, item: &str) {
+        *self.items_used.entry(item.to_string()).or_default() += 1;
+    }
+
+    pub fn use_skill(&mut self, skill: &Skill) {
+        *self.skills_used.entry(skill.name.clone()).or_default() += 1;
+        if skill.mp_cost > 0 {
+            self.magic_used += 1;
+        }
+    }
+
+    pub fn finish(mut self, outcome: BattleOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    pub fn items_used_count(&self) -> u32 {
+        self.items_used.values().sum()
+    }
+
+    // Achievement events, e.g. "battle.won", "battle.won.bandits",
+    // "battle.defeated.bandit_leader", "battle.won_without_magic" and "battle.won_without_items".
+    pub fn events(&self) -> Vec<String> {
+        let mut events = vec![format!("battle.{}", self.outcome), format!("battle.{}.{}", self.outcome, self.formation)];
+        events.extend(self.defeated.keys().map(|enemy| format!("battle.defeated.{}", enemy)));
+        if self.outcome == BattleOutcome::Won {
+            if self.magic_used == 0 {
+                events.push("battle.won_without_magic".to_string());
+            }
+            if self.items_used.is_empty() {
+                events.push("battle.won_without_items".to_string());
+            }
+        }
+        events
+    }
+
+    // Put it in flags, see the top of the file.
+    pub fn apply(&self, flags: &mut GameFlags) {
+        let add = |flags: &mut GameFlags, name: &str, count: u32| {
+            let value = flags.get(name).saturating_add(flag_value(count));
+            flags.set(name, value);
+        };
+        add(flags, &format!("battle.{}", self.outcome), 1);
+        for (enemy, count) in &self.defeated {
+            add(flags, &format!("defeated.{}", enemy), *count);
+        }
+        let outcome = match self.outcome {
+            BattleOutcome::Won => 1,
+            BattleOutcome::Lost => 2,
+            BattleOutcome::Fled => 3
+        };
+        flags.set("battle.last.outcome", outcome);
+        flags.set("battle.last.turns", flag_value(self.turns));
+        flags.set("battle.last.items", flag_value(self.items_used_count()));
+        flags.set("battle.last.magic", flag_value(self.magic_used));
+    }
+}
+
+fn flag_value(count: u32) -> i32 {
+    count.min(i32::MAX as u32) as i32
+}
+
+// Resource collecting reports for battles that have ended, until they're taken.
+#[derive(Clone, Debug, Default)]
+pub struct BattleReports {
+    reports: Vec<BattleReport>
+}
+
+impl BattleReports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reports since the last call, in the order the battles ended.
+    pub fn take_reports(&mut self) -> Vec<BattleReport> {
+        std::mem::take(&mut self.reports)
+    }
+}
+
+// A battle's over. Puts it in flags, play stats and telemetry, and keeps it for whatever
+// takes the reports.
+pub fn record(world: &mut World, report: BattleReport) {
+    if let Some(flags) = world.resource_mut::<GameFlags>() {
+        report.apply(flags);
+    }
+    if report.outcome == BattleOutcome::Won {
+        if let Some(stats) = world.resource_mut::<PlayStats>() {
+            stats.record_battle_won();
+        }
+    }
+    telemetry::record(world, TelemetryEvent::BattleResult { formation: report.formation.clone(), outcome: report.outcome });
+    match world.resource_mut::<BattleReports>() {
+        Some(reports) => reports.reports.push(report),
+        None => world.insert_resource(BattleReports { reports: vec![report] })
+    }
+}
@@ -0,0 +1,101 @@
+// What a battle looks and sounds like: the backdrop behind it and the music that plays. Random
+// encounters pick these from where they happen, so scripts don't have to. Each field has a
+// terrain, and data/battle_scenes.cfg says what each terrain's battles use:
+//
+// [default]
+// backdrop = battles/plains.png
+// music = music/battle.cfg
+//
+// [cave]
+// backdrop = battles/cave.png
+//
+// A field can give its own backdrop or music instead, for places that are one of a kind, and a
+// formation can too, for bosses with their own theme. The formation wins over the field, which
+// wins over its terrain, and anything none of them give comes from [default].
+
+use crate::field::FieldDescriptor;
+
+// Terrain used for fields that don't say, and for anything a terrain leaves out.
+pub const DEFAULT_TERRAIN: &str = "default";
+
+// None leaves it to whatever's picked next.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BattleScene {
+    pub backdrop: Option<String>,
+    pub music: Option<String>
+}
+
+impl BattleScene {
+    // This, with anything it doesn't give taken from `fallback`.
+    pub fn or(&self, fallback: &BattleScene) -> BattleScene {
+        BattleScene {
+            backdrop: self.backdrop.clone().or_else(|| fallback.backdrop.clone()),
+            music: self.music.clone().or_else(|| fallback.music.clone())
+        }
+    }
+
+    // Reads a "backdrop = path" or "music = path" line into the scene. Returns false if it's
+    // some other key, for files with other things in them.
+    pub fn parse_key(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "backdrop" => self.backdrop = Some(value.to_string()),
+            "music" => self.music = Some(value.to_string()),
+            _ => return false
+        }
+        true
+    }
+}
+
+// Resource with the battle scene for each terrain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BattleScenes {
+    terrains: Vec<(String, BattleScene)>
+}
+
+impl BattleScenes {
+    // See the top of the file for what these look like.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terrains: Vec<(String, BattleScene)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(terrain) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                terrains.push((terrain.trim().to_string(), BattleScene::default()));
+                continue;
+            }
+
+            let (_, scene) = terrains.last_mut().ok_or_else(|| format!("Line {}: expected a [terrain] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            if !scene.parse_key(key, value) {
+                return Err(format!("Line {}: unknown key \"{}\"", number + 1, key));
+            }
+        }
+        Ok(Self { terrains })
+    }
+
+    pub fn get(&self, terrain: &str) -> Option<&BattleScene> {
+        self.terrains.iter().find(|(name, _)| name == terrain).map(|(_, scene)| scene)
+    }
+
+    // Every terrain's name, in the order they're in the file.
+    pub fn terrains(&self) -> impl Iterator<Item = &str> {
+        self.terrains.iter().map(|(name, _)| name.as_str())
+    }
+
+    // The scene for a battle with the encounter's own overrides, fought in `field`, or None
+    // if it isn't in a field.
+    pub fn resolve(&self, encounter: &BattleScene, field: Option<&FieldDescriptor>) -> BattleScene {
+        let default = self.get(DEFAULT_TERRAIN).cloned().unwrap_or_default();
+        let field = match field {
+            Some(field) => field,
+            None => return encounter.or(&default)
+        };
+        let terrain = self.get(&field.terrain).cloned().unwrap_or_default();
+        encounter.or(&field.battle_scene).or(&terrain).or(&default)
+    }
+}
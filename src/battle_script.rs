@@ -0,0 +1,271 @@
+// Things that happen part way through a battle, like a boss getting angry at half health or
+// help turning up after a few turns. Each formation can have a script in
+// data/battle_scripts.cfg:
+//
+// [cave_ogre]
+// on = hp ogre 50
+// say = Ogre: You'll pay for that!
+// phase = ogre enraged
+// stat = ogre strength 150
+// spawn = goblin 2 front
+//
+// on = turn 6
+// backdrop = battles/cave_collapsing.png
+// music = music/escape.cfg
+//
+// Each "on" line starts a trigger, with what happens when it goes off after it, in order.
+// Triggers go off once each:
+//
+//   hp <enemy> <percent>  When any of those enemies is at or under that much of their HP
+//   turn <number>         At the start of that turn, counting from 1
+//
+// And what can happen:
+//
+//   say = <line>                       Pause for some dialogue
+//   phase = <enemy> <phase>            Switch the enemy's AI to another phase
+//   stat = <enemy> <stat> <percent>    Scale one of the enemy's stats
+//   spawn = <enemy as in formations>   Bring in reinforcements
+//   backdrop = <path>, music = <path>  Change the battle's scene
+//
+// The turns stop while there's anything left to happen, so dialogue plays out before the next
+// one's taken.
+
+use std::{collections::VecDeque, fmt, str::FromStr};
+
+use crate::formation::{FormationEnemy, Formations};
+use crate::party::Stats;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BattleTrigger {
+    Hp { enemy: String, percent: u32 },
+    Turn(u32)
+}
+
+impl FromStr for BattleTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["hp", enemy, percent] => Ok(BattleTrigger::Hp {
+                enemy: enemy.to_string(),
+                percent: percent.trim_end_matches('%').parse().map_err(|_| format!("bad percent \"{}\"", percent))?
+            }),
+            ["turn", turn] => Ok(BattleTrigger::Turn(turn.parse().map_err(|_| format!("bad turn \"{}\"", turn))?)),
+            _ => Err(format!("Unknown trigger \"{}\", expected \"hp enemy percent\" or \"turn number\"", s.trim()))
+        }
+    }
+}
+
+// The stats a script can change. HP and MP aren't here, since changing them mid-fight is
+// healing or damage rather than a stat change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BattleStat {
+    Strength,
+    Magic,
+    Defence,
+    Speed
+}
+
+impl BattleStat {
+    pub const ALL: [BattleStat; 4] = [BattleStat::Strength, BattleStat::Magic, BattleStat::Defence, BattleStat::Speed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BattleStat::Strength => "strength",
+            BattleStat::Magic => "magic",
+            BattleStat::Defence => "defence",
+            BattleStat::Speed => "speed"
+        }
+    }
+
+    // Scale the stat, e.g. 150 for half as much again.
+    pub fn apply(&self, stats: &mut Stats, percent: u32) {
+        let stat = match self {
+            BattleStat::Strength => &mut stats.strength,
+            BattleStat::Magic => &mut stats.magic,
+            BattleStat::Defence => &mut stats.defence,
+            BattleStat::Speed => &mut stats.speed
+        };
+        *stat = (*stat as u64 * percent as u64 / 100) as u32;
+    }
+}
+
+impl fmt::Display for BattleStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BattleStat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BattleStat::ALL.into_iter()
+            .find(|stat| stat.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown stat \"{}\", expected strength, magic, defence or speed", s.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BattleAction {
+    Say(String),
+    Phase { enemy: String, phase: String },
+    Stat { enemy: String, stat: BattleStat, percent: u32 },
+    Spawn(FormationEnemy),
+    Backdrop(String),
+    Music(String)
+}
+
+impl BattleAction {
+    fn parse(key: &str, value: &str) -> Result<Self, String> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        match (key, words.as_slice()) {
+            ("say", _) => Ok(BattleAction::Say(value.to_string())),
+            ("phase", [enemy, phase]) => Ok(BattleAction::Phase { enemy: enemy.to_string(), phase: phase.to_string() }),
+            ("phase", _) => Err("expected \"phase = enemy phase\"".to_string()),
+            ("stat", [enemy, stat, percent]) => Ok(BattleAction::Stat {
+                enemy: enemy.to_string(),
+                stat: stat.parse()?,
+                percent: percent.trim_end_matches('%').parse().map_err(|_| format!("bad percent \"{}\"", percent))?
+            }),
+            ("stat", _) => Err("expected \"stat = enemy stat percent\"".to_string()),
+            ("spawn", _) => Ok(BattleAction::Spawn(value.parse()?)),
+            ("backdrop", _) => Ok(BattleAction::Backdrop(value.to_string())),
+            ("music", _) => Ok(BattleAction::Music(value.to_string())),
+            _ => Err(format!("unknown key \"{}\"", key))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BattleRule {
+    pub trigger: BattleTrigger,
+    pub actions: Vec<BattleAction>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BattleScript {
+    // The formation it's for.
+    pub formation: String,
+    pub rules: Vec<BattleRule>
+}
+
+impl BattleScript {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut scripts: Vec<BattleScript> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(formation) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                scripts.push(BattleScript { formation: formation.trim().to_string(), rules: Vec::new() });
+                continue;
+            }
+
+            let script = scripts.last_mut().ok_or_else(|| format!("Line {}: expected a [formation] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            if key == "on" {
+                let trigger = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?;
+                script.rules.push(BattleRule { trigger, actions: Vec::new() });
+                continue;
+            }
+            let rule = script.rules.last_mut().ok_or_else(|| format!("Line {}: expected an \"on\" trigger first", number + 1))?;
+            rule.actions.push(BattleAction::parse(key, value).map_err(|e| format!("Line {}: {}", number + 1, e))?);
+        }
+        Ok(scripts)
+    }
+}
+
+// Resource with every formation's script.
+#[derive(Clone, Debug, Default)]
+pub struct BattleScripts {
+    scripts: Vec<BattleScript>
+}
+
+impl BattleScripts {
+    // Fails if a script's for a formation that doesn't exist, so typos are found on load.
+    pub fn new(scripts: Vec<BattleScript>, formations: &Formations) -> Result<Self, String> {
+        for script in &scripts {
+            if formations.get(&script.formation).is_none() {
+                return Err(format!("There's a battle script for {}, which isn't a formation", script.formation));
+            }
+        }
+        Ok(Self { scripts })
+    }
+
+    pub fn get(&self, formation: &str) -> Option<&BattleScript> {
+        self.scripts.iter().find(|script| script.formation == formation)
+    }
+}
+
+// Runs a script through a battle. The battle checks it at the start of each turn and after
+// anything that changes HP, then takes what happens one at a time before carrying on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BattleDirector {
+    rules: Vec<BattleRule>,
+    fired: Vec<bool>,
+    pending: VecDeque<BattleAction>,
+    // Each enemy's AI phase, once it's been changed.
+    phases: Vec<(String, String)>
+}
+
+impl BattleDirector {
+    // None for battles without a script.
+    pub fn new(script: Option<&BattleScript>) -> Self {
+        let rules = script.map(|script| script.rules.clone()).unwrap_or_default();
+        Self {
+            fired: vec![false; rules.len()],
+            rules,
+            pending: VecDeque::new(),
+            phases: Vec::new()
+        }
+    }
+
+    // Set off any triggers that have been met, given the turn and how every enemy's doing.
+    // Returns whether any did.
+    pub fn check(&mut self, turn: u32, enemies: &[(&str, &Stats)]) -> bool {
+        let mut any = false;
+        for (rule, fired) in self.rules.iter().zip(&mut self.fired) {
+            let met = match &rule.trigger {
+                BattleTrigger::Turn(at) => turn >= *at,
+                BattleTrigger::Hp { enemy, percent } => enemies.iter()
+                    .filter(|(id, _)| id == enemy)
+                    .any(|(_, stats)| stats.hp as u64 * 100 <= stats.max_hp as u64 * *percent as u64)
+            };
+            if *fired || !met {
+                continue;
+            }
+            *fired = true;
+            any = true;
+            for action in &rule.actions {
+                if let BattleAction::Phase { enemy, phase } = action {
+                    self.phases.retain(|(existing, _)| existing != enemy);
+                    self.phases.push((enemy.clone(), phase.clone()));
+                }
+                self.pending.push_back(action.clone());
+            }
+        }
+        any
+    }
+
+    // Whether the turns have to wait for what's happening.
+    pub fn is_paused(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // The next thing to happen. Dialogue should be finished with before asking for more.
+    pub fn next_action(&mut self) -> Option<BattleAction> {
+        self.pending.pop_front()
+    }
+
+    // The enemy's AI phase, or None if it's still on its first.
+    pub fn phase(&self, enemy: &str) -> Option<&str> {
+        self.phases.iter().find(|(name, _)| name == enemy).map(|(_, phase)| phase.as_str())
+    }
+}
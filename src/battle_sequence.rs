@@ -0,0 +1,244 @@
+// Playing out a skill once it's been used: the effect over whoever it hits and the damage
+// numbers popping up over them. Skills that hit a group play one effect sized to cover all of
+// them rather than one each, and their numbers come up one after the other, left to right,
+// rather than all at once.
+//
+// The sequence waits for the user's animation to reach its "hit" event before anything lands,
+// then hands back each hit as it lands so the damage is dealt in time with the numbers. Combos
+// wait for everyone using them to get to theirs, see combo.rs. Skills with a timed hit wait
+// for that to be graded too, and their damage is scaled by the grade, see timed_hit.rs.
+
+use std::time::Duration;
+
+use cgmath::{EuclideanSpace, MetricSpace, Point3};
+
+use crate::accessibility::Accessibility;
+use crate::battle_ui::{self, Combatant};
+use crate::camera::Camera;
+use crate::combat::{HitResult, Outcome};
+use crate::screen_effects::Easing;
+use crate::timed_hit::{TimedGrade, TimedHit};
+use crate::tween::{Tween, UiPose};
+use crate::ui::UiBatch;
+
+// The animation event a skill's hits land on.
+pub const HIT_EVENT: &str = "hit";
+// How long after each other the hits on a group land.
+pub const STAGGER: Duration = Duration::from_millis(120);
+// How long each damage number stays up.
+const NUMBER_DURATION: Duration = Duration::from_millis(1000);
+// How far a damage number floats up while it's showing, in pixels.
+const NUMBER_RISE: f32 = 32.0;
+// How much room one combatant takes up, in metres. An effect on one target is this big, and
+// effects on groups are scaled up from it.
+pub const TARGET_RADIUS: f32 = 1.0;
+
+// The space a group of targets takes up, for placing and sizing an effect that covers them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroupBounds {
+    // Between their feet.
+    pub centre: Point3<f32>,
+    // From the centre to the furthest of them, plus room for them.
+    pub radius: f32
+}
+
+impl GroupBounds {
+    // None if there's no one to cover.
+    pub fn new(targets: &[usize], combatants: &[Combatant]) -> Option<Self> {
+        let positions: Vec<Point3<f32>> = targets.iter()
+            .filter_map(|index| combatants.get(*index))
+            .map(|combatant| combatant.position)
+            .collect();
+        if positions.is_empty() {
+            return None;
+        }
+        let centre = Point3::centroid(&positions);
+        let furthest = positions.iter().map(|position| position.distance(centre)).fold(0.0, f32::max);
+        Some(Self { centre, radius: furthest + TARGET_RADIUS })
+    }
+
+    // What to scale an effect made for one target by, to cover the whole group.
+    pub fn effect_scale(&self) -> f32 {
+        self.radius / TARGET_RADIUS
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetHit {
+    // Into the combatants.
+    pub target: usize,
+    pub result: HitResult
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct DamageNumber {
+    target: usize,
+    text: String,
+    critical: bool,
+    // Floating up as it fades.
+    tween: Tween
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkillSequence {
+    // In the order they land.
+    hits: Vec<TargetHit>,
+    effect: Option<GroupBounds>,
+    // Time since the hit event, or None until then.
+    elapsed: Option<Duration>,
+    // How many users' animations have still to get to the hit event.
+    waiting: usize,
+    // How many of the hits have landed.
+    landed: usize,
+    numbers: Vec<DamageNumber>,
+    timed: Option<TimedHit>
+}
+
+impl SkillSequence {
+    // The hits are worked out up front, with combat::resolve, and played out in order from the
+    // leftmost target.
+    pub fn new(mut hits: Vec<TargetHit>, combatants: &[Combatant]) -> Self {
+        let x = |hit: &TargetHit| combatants.get(hit.target).map(|combatant| combatant.position.x).unwrap_or_default();
+        hits.sort_by(|a, b| x(a).total_cmp(&x(b)));
+        let targets: Vec<usize> = hits.iter().map(|hit| hit.target).collect();
+        Self {
+            effect: GroupBounds::new(&targets, combatants),
+            hits,
+            elapsed: None,
+            waiting: 1,
+            landed: 0,
+            numbers: Vec::new(),
+            timed: None
+        }
+    }
+
+    // For combos, used by more than one at once. It starts once every one of them gets to
+    // the hit event.
+    pub fn with_users(mut self, users: usize) -> Self {
+        self.waiting = users.max(1);
+        self
+    }
+
+    // Where the skill's effect goes and how big it is. There's one for the whole group.
+    pub fn effect(&self) -> Option<GroupBounds> {
+        self.effect
+    }
+
+    // The user's animation reached the timed hit event, for skills that have one.
+    pub fn request_timed_hit(&mut self, timed: TimedHit) {
+        if !self.is_started() && self.timed.is_none() {
+            self.timed = Some(timed);
+        }
+    }
+
+    // The button for a timed hit. Returns whether it counted.
+    pub fn press(&mut self) -> bool {
+        self.timed.as_mut().is_some_and(TimedHit::press)
+    }
+
+    pub fn timed_grade(&self) -> Option<TimedGrade> {
+        self.timed.as_ref().and_then(TimedHit::grade)
+    }
+
+    // A user's animation reached the hit event.
+    pub fn start(&mut self) {
+        self.waiting = self.waiting.saturating_sub(1);
+        if self.waiting > 0 {
+            return;
+        }
+        if self.elapsed.is_none() {
+            self.elapsed = Some(Duration::ZERO);
+        }
+        if let Some(timed) = &mut self.timed {
+            timed.hit();
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.elapsed.is_some()
+    }
+
+    // Every hit's landed and all the numbers are gone.
+    pub fn is_finished(&self) -> bool {
+        self.is_started() && self.landed == self.hits.len() && self.numbers.is_empty() && self.timed.as_ref().is_none_or(TimedHit::is_finished)
+    }
+
+    // Move the sequence on, returning the hits that landed, to deal their damage.
+    pub fn update(&mut self, delta: Duration) -> Vec<TargetHit> {
+        for number in &mut self.numbers {
+            number.tween.update(delta);
+        }
+        self.numbers.retain(|number| !number.tween.is_finished());
+        if let Some(timed) = &mut self.timed {
+            timed.update(delta);
+            // Nothing lands until it's graded.
+            if timed.grade().is_none() {
+                return Vec::new();
+            }
+        }
+
+        let elapsed = match &mut self.elapsed {
+            Some(elapsed) => {
+                *elapsed += delta;
+                *elapsed
+            },
+            None => return Vec::new()
+        };
+        let mut landed = Vec::new();
+        while self.landed < self.hits.len() && elapsed >= STAGGER * self.landed as u32 {
+            let mut hit = self.hits[self.landed];
+            if let Some(timed) = &self.timed {
+                hit.result = timed.apply(hit.result);
+            }
+            // Numbers that land late in a long frame start part way through, so they stay in
+            // step with the others.
+            let age = elapsed - STAGGER * self.landed as u32;
+            if age < NUMBER_DURATION {
+                let mut tween = Tween::new(UiPose::REST)
+                    .then(UiPose { alpha: 0.0, ..UiPose::at(0.0, -NUMBER_RISE) }, NUMBER_DURATION, Easing::Linear);
+                tween.update(age);
+                self.numbers.push(DamageNumber {
+                    target: hit.target,
+                    text: damage_text(&hit.result),
+                    critical: hit.result.outcome == Outcome::Critical,
+                    tween
+                });
+            }
+            landed.push(hit);
+            self.landed += 1;
+        }
+        landed
+    }
+
+    // The damage numbers over whoever's been hit, floating up as they fade.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, camera: &Camera, combatants: &[Combatant]) {
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        for number in &self.numbers {
+            let (x, y) = match combatants.get(number.target).and_then(|combatant| battle_ui::head_on_screen(camera, combatant)) {
+                Some(head) => head,
+                None => continue
+            };
+            let color = if number.critical { skin.highlight } else { skin.text };
+            let scale = if number.critical { scale * 1.5 } else { scale };
+            let (width, height) = UiBatch::measure_text(scale, &number.text);
+            batch.set_pose(number.tween.pose(), [x, y]);
+            batch.text(x - width / 2.0, y - height, scale, &number.text, color);
+        }
+        batch.clear_pose();
+
+        // The timed hit's cue over the first one hit.
+        let first = self.hits.first().and_then(|hit| combatants.get(hit.target));
+        if let (Some(timed), Some((x, y))) = (&self.timed, first.and_then(|combatant| battle_ui::head_on_screen(camera, combatant))) {
+            timed.build(batch, accessibility, x, y);
+        }
+    }
+}
+
+fn damage_text(result: &HitResult) -> String {
+    match result.outcome {
+        Outcome::Miss => "Miss".to_string(),
+        Outcome::Hit => result.damage.to_string(),
+        Outcome::Critical => format!("{}!", result.damage)
+    }
+}
@@ -0,0 +1,221 @@
+// The swirl into a battle. When one starts the field's last frame is kept, see
+// Renderer::capture_transition_frame, and post processing twists it away, breaks it into big
+// pixels or shatters it (see post_process.wgsl) over about a second, while the battle's backdrop
+// and music load in the background. Once both are done the battle's revealed, fading in from
+// black. Loading that takes longer than the effect leaves the screen black until it's done.
+//
+// Bosses shatter the screen and everything else swirls, unless the console says otherwise with
+// "battle <formation> pixelate".
+//
+// Nothing runs battles yet, so the backdrop's shown over the field until the player leaves it,
+// and the field's music comes back with them.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::battle_scene::BattleScene;
+use crate::flags::GameFlags;
+use crate::formation::BattleSetup;
+use crate::logging::targets;
+use crate::music::{MusicPlayer, MusicTrack};
+use crate::renderer::{PostProcessSettings, Renderer, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::screen_effects::{Easing, EffectCommand, EffectParam, ScreenEffects};
+use crate::ui::{UiBatch, UiImageId, WHITE};
+use crate::world::World;
+
+// How long the field takes to go, and the battle to fade in after.
+pub const TRANSITION_TIME: Duration = Duration::from_secs(1);
+pub const REVEAL_TIME: Duration = Duration::from_millis(400);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TransitionStyle {
+    // Twists round the middle of the screen as it goes dark.
+    #[default]
+    Swirl,
+    // Breaks up into bigger and bigger pixels.
+    Pixelate,
+    // Cracks into pieces that shrink away.
+    Shatter
+}
+
+impl TransitionStyle {
+    pub const ALL: [TransitionStyle; 3] = [TransitionStyle::Swirl, TransitionStyle::Pixelate, TransitionStyle::Shatter];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransitionStyle::Swirl => "swirl",
+            TransitionStyle::Pixelate => "pixelate",
+            TransitionStyle::Shatter => "shatter"
+        }
+    }
+
+    // Which one post_process.wgsl does, counting from 1 as 0 is none.
+    pub fn shader_index(&self) -> u32 {
+        match self {
+            TransitionStyle::Swirl => 1,
+            TransitionStyle::Pixelate => 2,
+            TransitionStyle::Shatter => 3
+        }
+    }
+
+    // Bosses get something more dramatic.
+    pub fn for_battle(setup: &BattleSetup) -> Self {
+        if setup.can_escape { TransitionStyle::Swirl } else { TransitionStyle::Shatter }
+    }
+}
+
+impl fmt::Display for TransitionStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TransitionStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TransitionStyle::ALL.into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown transition \"{}\", expected swirl, pixelate or shatter", s.trim()))
+    }
+}
+
+// The kept frame and how far it's gone, for post processing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScreenTransition {
+    pub style: TransitionStyle,
+    // From 0 for the frame as it was to 1 for gone.
+    pub progress: f32
+}
+
+// What a battle needs before it can be shown.
+#[derive(Clone, Debug, Default)]
+pub struct BattleAssets {
+    pub backdrop: Option<image::RgbaImage>,
+    pub music: Option<MusicTrack>
+}
+
+impl BattleAssets {
+    pub async fn load(assets: &AssetServer, scene: &BattleScene) -> Result<Self, AssetError> {
+        let backdrop = match &scene.backdrop {
+            Some(path) => Some(assets.load_image(path).await?),
+            None => None
+        };
+        let music = match &scene.music {
+            Some(path) => Some(MusicTrack::load(assets, path).await?),
+            None => None
+        };
+        Ok(Self { backdrop, music })
+    }
+}
+
+// A battle that's on its way in, then on screen.
+pub struct BattleTransition {
+    setup: BattleSetup,
+    style: TransitionStyle,
+    elapsed: Duration,
+    // None until they've loaded.
+    assets: Option<BattleAssets>,
+    revealed: bool,
+    backdrop: Option<(UiImageId, (u32, u32))>,
+    // Put back when the player leaves.
+    field_music: Option<MusicPlayer>
+}
+
+impl BattleTransition {
+    // Start taking the field away. The frame to do it with has to have been captured already.
+    pub fn start(setup: BattleSetup, style: TransitionStyle, settings: &mut PostProcessSettings) -> Self {
+        tracing::info!(target: targets::BATTLE, "Into {} with a {}", setup.formation, style);
+        settings.transition = Some(ScreenTransition { style, progress: 0.0 });
+        Self { setup, style, elapsed: Duration::ZERO, assets: None, revealed: false, backdrop: None, field_music: None }
+    }
+
+    pub fn setup(&self) -> &BattleSetup {
+        &self.setup
+    }
+
+    pub fn style(&self) -> TransitionStyle {
+        self.style
+    }
+
+    pub fn progress(&self) -> f32 {
+        (self.elapsed.as_secs_f32() / TRANSITION_TIME.as_secs_f32()).min(1.0)
+    }
+
+    // Hand over what's been loaded. Anything that didn't load is left out rather than holding
+    // the battle up.
+    pub fn loaded(&mut self, assets: Result<BattleAssets, AssetError>) {
+        self.assets = Some(assets.unwrap_or_else(|e| {
+            tracing::error!(target: targets::ASSETS, "{}", e);
+            BattleAssets::default()
+        }));
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.assets.is_some()
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.revealed
+    }
+
+    // Whether the effect's finished and everything's loaded, so the battle can be shown.
+    pub fn is_ready(&self) -> bool {
+        !self.revealed && self.is_loaded() && self.progress() >= 1.0
+    }
+
+    // Move the effect on. Returns whether there's something new to draw.
+    pub fn update(&mut self, delta: Duration, settings: &mut PostProcessSettings) -> bool {
+        if self.revealed || self.progress() >= 1.0 {
+            return false;
+        }
+        self.elapsed += delta;
+        settings.transition = Some(ScreenTransition { style: self.style, progress: self.progress() });
+        true
+    }
+
+    // Show the battle, swapping the field's music for its own and fading in from black.
+    pub fn reveal(&mut self, world: &mut World, renderer: &mut Renderer) {
+        self.revealed = true;
+        let assets = self.assets.take().unwrap_or_default();
+        if let Some(backdrop) = &assets.backdrop {
+            self.backdrop = Some((renderer.create_ui_image(backdrop), backdrop.dimensions()));
+        }
+        if let Some(track) = assets.music {
+            self.field_music = world.remove_resource::<MusicPlayer>();
+            let music = MusicPlayer::start(track, world.resource::<GameFlags>());
+            world.insert_resource(music);
+        }
+        let settings = renderer.get_post_process_settings_mut();
+        settings.transition = None;
+        if let Some(effects) = world.resource_mut::<ScreenEffects>() {
+            let black = EffectCommand { param: EffectParam::Fade, value: 1.0, duration: Duration::ZERO, easing: Easing::default(), color: Some([0.0, 0.0, 0.0]) };
+            effects.run(&black, settings);
+            effects.run(&EffectCommand { value: 0.0, duration: REVEAL_TIME, easing: Easing::EaseOut, ..black }, settings);
+        }
+    }
+
+    // The backdrop over everything once it's revealed, as big as it'll fit on black.
+    pub fn build(&self, batch: &mut UiBatch) {
+        if !self.revealed {
+            return;
+        }
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, [0.0, 0.0, 0.0, 1.0]);
+        if let Some((id, (width, height))) = self.backdrop {
+            let scale = (SCREEN_WIDTH as f32 / width as f32).min(SCREEN_HEIGHT as f32 / height as f32);
+            let (width, height) = (width as f32 * scale, height as f32 * scale);
+            batch.image((SCREEN_WIDTH as f32 - width) / 2.0, (SCREEN_HEIGHT as f32 - height) / 2.0, width, height, id, WHITE);
+        }
+    }
+
+    // Go back to the field, with its music.
+    pub fn finish(mut self, world: &mut World, renderer: &mut Renderer) {
+        if let Some((id, _)) = self.backdrop.take() {
+            renderer.remove_ui_image(id);
+        }
+        if let Some(music) = self.field_music.take() {
+            world.insert_resource(music);
+        }
+        renderer.get_post_process_settings_mut().transition = None;
+    }
+}
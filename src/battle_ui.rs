@@ -0,0 +1,281 @@
+// Battle widgets: the strip along the top showing who acts next, and the cursor for picking
+// who a command's used on. Nothing runs battles yet, so whatever does will keep the list of
+// combatants and drive these, feeding the picked targets back into the command it was choosing.
+
+use std::time::Duration;
+
+use cgmath::{Point3, Vector3};
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::camera::Camera;
+use crate::party::TargetType;
+use crate::pointer::PointerEvent;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::screen_effects::Easing;
+use crate::tween::{Repeat, Tween, UiPose};
+use crate::ui::UiBatch;
+
+// How long a turn takes at a speed of 1. Faster combatants come round again sooner.
+const TURN_COST: f32 = 100.0;
+// How many turns ahead the strip shows.
+pub const PREVIEW_TURNS: usize = 8;
+
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 4.0;
+// How far above someone's feet the cursor and damage numbers go, in metres.
+const HEAD_HEIGHT: f32 = 1.8;
+// How close the pointer has to be to someone's head to pick them, in pixels.
+const POINTER_RADIUS: f32 = 48.0;
+const CURSOR_SIZE: f32 = 12.0;
+// How long the cursor takes to grow, then as long again to shrink back.
+const PULSE_TIME: Duration = Duration::from_millis(400);
+const PULSE_SCALE: f32 = 1.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Party,
+    Enemies
+}
+
+// Someone in a battle, on either side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Combatant {
+    pub name: String,
+    pub side: Side,
+    pub speed: u32,
+    // Where their feet are on the battlefield.
+    pub position: Point3<f32>,
+    pub knocked_out: bool,
+    // Brought in by a skill for a while rather than one of the party, see summon.rs.
+    pub summoned: bool
+}
+
+// Who acts when. Everyone waits longer the slower they are, and whoever's wait runs out
+// first goes next.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TurnOrder {
+    waits: Vec<f32>
+}
+
+impl TurnOrder {
+    pub fn new(combatants: &[Combatant]) -> Self {
+        Self {
+            waits: combatants.iter().map(wait_for).collect()
+        }
+    }
+
+    // Whose turn it is now, moving time on to it. Anyone knocked out is skipped, and ties go
+    // to whoever's first in the list, which puts the party first.
+    pub fn next(&mut self, combatants: &[Combatant]) -> Option<usize> {
+        self.waits.resize(combatants.len(), TURN_COST);
+        let next = (0..combatants.len())
+            .filter(|index| !combatants[*index].knocked_out)
+            .min_by(|a, b| self.waits[*a].total_cmp(&self.waits[*b]))?;
+        let elapsed = self.waits[next];
+        for wait in &mut self.waits {
+            *wait -= elapsed;
+        }
+        self.waits[next] = wait_for(&combatants[next]);
+        Some(next)
+    }
+
+    // Someone gives up their next turn, like the others in a combo, waiting as long as if
+    // they'd just acted.
+    pub fn use_turn(&mut self, index: usize, combatants: &[Combatant]) {
+        self.waits.resize(combatants.len(), TURN_COST);
+        if let Some(combatant) = combatants.get(index) {
+            self.waits[index] = wait_for(combatant);
+        }
+    }
+
+    // The next few turns, without taking them.
+    pub fn preview(&self, combatants: &[Combatant], count: usize) -> Vec<usize> {
+        let mut order = self.clone();
+        (0..count).map_while(|_| order.next(combatants)).collect()
+    }
+}
+
+fn wait_for(combatant: &Combatant) -> f32 {
+    TURN_COST / combatant.speed.max(1) as f32
+}
+
+// Draw the upcoming turns left to right along the top of the screen, the one acting now
+// highlighted.
+pub fn build_turn_order(batch: &mut UiBatch, accessibility: &Accessibility, combatants: &[Combatant], turns: &[usize]) {
+    let skin = accessibility.skin();
+    let scale = 2.0 * accessibility.text_scale();
+    let height = UiBatch::line_height(scale) + PADDING * 2.0;
+    let mut x = MARGIN;
+    for (turn, index) in turns.iter().enumerate() {
+        let combatant = match combatants.get(*index) {
+            Some(combatant) => combatant,
+            None => continue
+        };
+        let (width, _) = UiBatch::measure_text(scale, &combatant.name);
+        let width = width + PADDING * 2.0;
+        if x + width > SCREEN_WIDTH as f32 - MARGIN {
+            break;
+        }
+        let background = if turn == 0 { skin.selected } else { skin.window };
+        let color = match combatant.side {
+            Side::Party => skin.text,
+            Side::Enemies => skin.highlight
+        };
+        batch.rect(x, MARGIN, width, height, background);
+        batch.text(x + PADDING, MARGIN + PADDING, scale, &combatant.name, color);
+        x += width + PADDING;
+    }
+}
+
+// What happened to the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TargetChoice {
+    // Who to use the command on, as indices into the combatants.
+    Picked(Vec<usize>),
+    // Back to picking the command.
+    Cancelled
+}
+
+// Picks who a command's used on, pointing at them in the world. Only those the command can be
+// used on can be picked, and commands that hit a whole side point at everyone on it at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetCursor {
+    target: TargetType,
+    valid: Vec<usize>,
+    // Into valid.
+    selected: usize,
+    // The arrow growing and shrinking.
+    pulse: Tween
+}
+
+impl TargetCursor {
+    // Start picking targets for `user`. Returns None if there's no one to pick.
+    pub fn new(target: TargetType, user: usize, combatants: &[Combatant]) -> Option<Self> {
+        let side = combatants.get(user)?.side;
+        let valid: Vec<usize> = combatants.iter().enumerate()
+            .filter(|(index, combatant)| match target {
+                // Attacks only make sense on those still standing.
+                TargetType::Enemy | TargetType::AllEnemies => combatant.side != side && !combatant.knocked_out,
+                // Allies can be knocked out, to be brought round.
+                TargetType::Ally | TargetType::AllAllies => combatant.side == side,
+                TargetType::User => *index == user
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if valid.is_empty() {
+            return None;
+        }
+        let pulse = Tween::new(UiPose::REST)
+            .then(UiPose { scale: PULSE_SCALE, ..UiPose::REST }, PULSE_TIME, Easing::EaseInOut)
+            .repeat(Repeat::PingPong);
+        Some(Self { target, valid, selected: 0, pulse })
+    }
+
+    // Everyone who could be picked.
+    pub fn valid(&self) -> &[usize] {
+        &self.valid
+    }
+
+    // Who it's pointing at now.
+    pub fn targets(&self) -> Vec<usize> {
+        if self.target.is_all() {
+            self.valid.clone()
+        } else {
+            vec![self.valid[self.selected]]
+        }
+    }
+
+    // Pulse the arrow. Returns true, as it never stops.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        self.pulse.update(delta)
+    }
+
+    // The arrow keys move between targets, wrapping round, enter picks and escape goes back.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<TargetChoice> {
+        match key {
+            VirtualKeyCode::Left | VirtualKeyCode::Up => self.step(self.valid.len() - 1),
+            VirtualKeyCode::Right | VirtualKeyCode::Down => self.step(1),
+            VirtualKeyCode::Return => return Some(TargetChoice::Picked(self.targets())),
+            VirtualKeyCode::Escape | VirtualKeyCode::Back => return Some(TargetChoice::Cancelled),
+            _ => {}
+        }
+        None
+    }
+
+    // Hovering near someone points at them and clicking near them picks them. Scrolling moves
+    // along like the arrow keys, and a right click goes back.
+    pub fn handle_pointer(&mut self, event: PointerEvent, camera: &Camera, combatants: &[Combatant]) -> Option<TargetChoice> {
+        match event {
+            PointerEvent::Hover { x, y } => {
+                if let Some(index) = self.target_at(x, y, camera, combatants) {
+                    self.selected = index;
+                }
+            },
+            PointerEvent::Click { x, y } => {
+                if let Some(index) = self.target_at(x, y, camera, combatants) {
+                    self.selected = index;
+                    return Some(TargetChoice::Picked(self.targets()));
+                }
+            },
+            PointerEvent::Scroll { steps } => self.step(steps.rem_euclid(self.valid.len() as i32) as usize),
+            PointerEvent::Cancel => return Some(TargetChoice::Cancelled)
+        }
+        None
+    }
+
+    fn step(&mut self, by: usize) {
+        self.selected = (self.selected + by) % self.valid.len();
+    }
+
+    // The valid target whose head is nearest the pointer, if it's close enough.
+    fn target_at(&self, x: f32, y: f32, camera: &Camera, combatants: &[Combatant]) -> Option<usize> {
+        self.valid.iter().enumerate()
+            .filter_map(|(valid, index)| {
+                let (head_x, head_y) = head_on_screen(camera, combatants.get(*index)?)?;
+                let distance = ((head_x - x).powi(2) + (head_y - y).powi(2)).sqrt();
+                Some((valid, distance))
+            })
+            .filter(|(_, distance)| *distance <= POINTER_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(valid, _)| valid)
+    }
+
+    // A small mark over everyone who could be picked, and an arrow and their name over who's
+    // being pointed at.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, camera: &Camera, combatants: &[Combatant]) {
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        let targets = self.targets();
+        for index in &self.valid {
+            let combatant = match combatants.get(*index) {
+                Some(combatant) => combatant,
+                None => continue
+            };
+            let (x, y) = match head_on_screen(camera, combatant) {
+                Some(head) => head,
+                None => continue
+            };
+            if !targets.contains(index) {
+                batch.rect(x - PADDING / 2.0, y - PADDING, PADDING, PADDING, skin.dim_text);
+                continue;
+            }
+
+            // A down arrow, as rows that get narrower towards the point, pulsing from the point.
+            let rows = (CURSOR_SIZE / 2.0) as usize;
+            batch.set_pose(self.pulse.pose(), [x, y]);
+            for row in 0..rows {
+                let width = CURSOR_SIZE * (rows - row) as f32 / rows as f32;
+                batch.rect(x - width / 2.0, y - CURSOR_SIZE + row as f32 * 2.0, width, 2.0, skin.highlight);
+            }
+            batch.clear_pose();
+            let (width, height) = UiBatch::measure_text(scale, &combatant.name);
+            batch.text(x - width / 2.0, y - CURSOR_SIZE - PADDING - height, scale, &combatant.name, skin.text);
+        }
+    }
+}
+
+// Where the space just over someone's head is on the screen, for things that point at them.
+pub fn head_on_screen(camera: &Camera, combatant: &Combatant) -> Option<(f32, f32)> {
+    camera.to_screen(combatant.position + Vector3::unit_y() * HEAD_HEIGHT, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32)
+}
@@ -0,0 +1,279 @@
+// Right to left text, like Arabic and Hebrew. Text is kept in the order it's read, and only
+// put in the order it's drawn, left to right, as each line's drawn. That's a cut down version of
+// the Unicode bidirectional algorithm: runs of right to left letters are reversed, numbers and
+// other left to right text inside them stay the way round they are, and spaces and punctuation
+// go with the text either side of them, or with the line's direction if the two disagree.
+// Brackets in right to left runs are swapped, so "(" still opens.
+//
+// Arabic letters also change shape depending on whether they join the letters either side, so
+// they're swapped for the right presentation forms first, which is what fonts need to have.
+//
+// Which languages go right to left, and whether their menus are mirrored too, is in
+// data/languages.cfg:
+//
+//   [ar]
+//   direction = rtl
+//   mirror_menus = true
+
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft
+}
+
+impl TextDirection {
+    pub const ALL: [TextDirection; 2] = [TextDirection::LeftToRight, TextDirection::RightToLeft];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextDirection::LeftToRight => "ltr",
+            TextDirection::RightToLeft => "rtl"
+        }
+    }
+}
+
+impl fmt::Display for TextDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TextDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TextDirection::ALL.into_iter()
+            .find(|direction| direction.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown text direction \"{}\", expected ltr or rtl", s.trim()))
+    }
+}
+
+// How one language's text and menus are laid out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LanguageLayout {
+    pub language: String,
+    pub direction: TextDirection,
+    // Whether menus are flipped, so they read from the right.
+    pub mirror_menus: bool
+}
+
+impl LanguageLayout {
+    // Left to right, with nothing flipped.
+    pub fn new(language: &str) -> Self {
+        Self { language: language.to_string(), direction: TextDirection::LeftToRight, mirror_menus: false }
+    }
+
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut layouts: Vec<LanguageLayout> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(language) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                layouts.push(LanguageLayout::new(language.trim()));
+                continue;
+            }
+
+            let layout = layouts.last_mut().ok_or_else(|| format!("Line {}: expected a [language] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "direction" => layout.direction = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?,
+                "mirror_menus" => layout.mirror_menus = value.parse().map_err(|_| format!("Line {}: expected true or false, not \"{}\"", number + 1, value))?,
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        Ok(layouts)
+    }
+
+    // The layout for `language`, or the same language in any region, e.g. "ar" for "ar-EG".
+    // Languages that aren't listed are left to right.
+    pub fn pick(layouts: &[LanguageLayout], language: &str) -> LanguageLayout {
+        let base = crate::subtitles::base_language(language);
+        layouts.iter().find(|layout| layout.language.eq_ignore_ascii_case(language))
+            .or_else(|| layouts.iter().find(|layout| layout.language.eq_ignore_ascii_case(base)))
+            .cloned()
+            .unwrap_or_else(|| LanguageLayout::new(language))
+    }
+}
+
+// Whether a character's from a right to left script.
+pub fn is_right_to_left(c: char) -> bool {
+    matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+// Which way a character goes, or None for spaces and punctuation, which go with what's around
+// them.
+fn strong_direction(c: char) -> Option<TextDirection> {
+    if is_right_to_left(c) {
+        Some(TextDirection::RightToLeft)
+    } else if c.is_alphanumeric() {
+        Some(TextDirection::LeftToRight)
+    } else {
+        None
+    }
+}
+
+// The other half of a bracket, for drawing it in right to left text.
+fn mirrored(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '\u{AB}' => '\u{BB}',
+        '\u{BB}' => '\u{AB}',
+        _ => c
+    }
+}
+
+// Put one line in the order it's drawn, for a line going `direction`. Lines without any right
+// to left text in a left to right line are left alone.
+pub fn visual_order(line: &str, direction: TextDirection) -> String {
+    if direction == TextDirection::LeftToRight && !line.chars().any(is_right_to_left) {
+        return line.to_string();
+    }
+
+    // Work out which way each character goes, then give it an embedding level: even levels go
+    // left to right, odd ones right to left, and higher ones are nested in lower ones.
+    let chars: Vec<char> = line.chars().collect();
+    let strong: Vec<Option<TextDirection>> = chars.iter().map(|c| strong_direction(*c)).collect();
+    let base_level = match direction {
+        TextDirection::LeftToRight => 0,
+        TextDirection::RightToLeft => 1
+    };
+    let levels: Vec<u8> = (0..chars.len()).map(|index| {
+        let resolved = strong[index].unwrap_or_else(|| {
+            let before = strong[..index].iter().rev().find_map(|direction| *direction).unwrap_or(direction);
+            let after = strong[index + 1..].iter().find_map(|direction| *direction).unwrap_or(direction);
+            if before == after { before } else { direction }
+        });
+        match (resolved, base_level) {
+            (TextDirection::RightToLeft, _) => 1,
+            (TextDirection::LeftToRight, 0) => 0,
+            (TextDirection::LeftToRight, _) => 2
+        }
+    }).collect();
+
+    // Reverse every run at or above each odd level, from the highest down.
+    let mut order: Vec<usize> = (0..chars.len()).collect();
+    let highest = levels.iter().copied().max().unwrap_or_default();
+    for level in (1..=highest).rev() {
+        let mut start = 0;
+        while start < order.len() {
+            if levels[order[start]] < level {
+                start += 1;
+                continue;
+            }
+            let end = (start..order.len()).find(|&index| levels[order[index]] < level).unwrap_or(order.len());
+            order[start..end].reverse();
+            start = end;
+        }
+    }
+
+    order.into_iter()
+        .map(|index| if levels[index] % 2 == 1 { mirrored(chars[index]) } else { chars[index] })
+        .collect()
+}
+
+// The Arabic letters' presentation forms: the isolated form, then the final, initial and medial
+// ones if it has them. Letters with two only join the letter before them.
+const ARABIC_FORMS: [(char, u32, u32); 36] = [
+    ('\u{0621}', 0xFE80, 1), ('\u{0622}', 0xFE81, 2), ('\u{0623}', 0xFE83, 2), ('\u{0624}', 0xFE85, 2),
+    ('\u{0625}', 0xFE87, 2), ('\u{0626}', 0xFE89, 4), ('\u{0627}', 0xFE8D, 2), ('\u{0628}', 0xFE8F, 4),
+    ('\u{0629}', 0xFE93, 2), ('\u{062A}', 0xFE95, 4), ('\u{062B}', 0xFE99, 4), ('\u{062C}', 0xFE9D, 4),
+    ('\u{062D}', 0xFEA1, 4), ('\u{062E}', 0xFEA5, 4), ('\u{062F}', 0xFEA9, 2), ('\u{0630}', 0xFEAB, 2),
+    ('\u{0631}', 0xFEAD, 2), ('\u{0632}', 0xFEAF, 2), ('\u{0633}', 0xFEB1, 4), ('\u{0634}', 0xFEB5, 4),
+    ('\u{0635}', 0xFEB9, 4), ('\u{0636}', 0xFEBD, 4), ('\u{0637}', 0xFEC1, 4), ('\u{0638}', 0xFEC5, 4),
+    ('\u{0639}', 0xFEC9, 4), ('\u{063A}', 0xFECD, 4), ('\u{0641}', 0xFED1, 4), ('\u{0642}', 0xFED5, 4),
+    ('\u{0643}', 0xFED9, 4), ('\u{0644}', 0xFEDD, 4), ('\u{0645}', 0xFEE1, 4), ('\u{0646}', 0xFEE5, 4),
+    ('\u{0647}', 0xFEE9, 4), ('\u{0648}', 0xFEED, 2), ('\u{0649}', 0xFEEF, 2), ('\u{064A}', 0xFEF1, 4)
+];
+
+// Lam followed by one of these alefs is written as a single letter, isolated or final.
+const LAM_ALEF: [(char, u32); 4] = [('\u{0622}', 0xFEF5), ('\u{0623}', 0xFEF7), ('\u{0625}', 0xFEF9), ('\u{0627}', 0xFEFB)];
+
+const TATWEEL: char = '\u{0640}';
+
+fn form_count(c: char) -> u32 {
+    if c == TATWEEL {
+        return 4;
+    }
+    ARABIC_FORMS.iter().find(|(letter, _, _)| *letter == c).map(|(_, _, forms)| *forms).unwrap_or_default()
+}
+
+// Vowel marks, which sit on the letters and don't get in the way of them joining.
+fn is_mark(c: char) -> bool {
+    matches!(c as u32, 0x064B..=0x065F | 0x0670)
+}
+
+// Swap Arabic letters for the forms they take between the letters around them. Anything else
+// is left as it is.
+pub fn shape(text: &str) -> String {
+    if !text.chars().any(|c| form_count(c) > 0) {
+        return text.to_string();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    // The nearest letter either side, skipping vowel marks.
+    let neighbour = |index: usize, forward: bool| -> Option<char> {
+        let mut index = index;
+        loop {
+            index = if forward { index + 1 } else { index.checked_sub(1)? };
+            match chars.get(index) {
+                Some(c) if is_mark(*c) => continue,
+                other => return other.copied()
+            }
+        }
+    };
+
+    let mut shaped = String::new();
+    let mut skip_alef = false;
+    for (index, &c) in chars.iter().enumerate() {
+        if skip_alef {
+            skip_alef = false;
+            continue;
+        }
+        let forms = form_count(c);
+        if forms == 0 || c == TATWEEL {
+            shaped.push(c);
+            continue;
+        }
+
+        // Joins the letter before if that one joins onwards, and the one after if it can.
+        let joins_before = neighbour(index, false).is_some_and(|before| form_count(before) == 4);
+        let after = neighbour(index, true);
+        let joins_after = forms == 4 && after.is_some_and(|after| form_count(after) > 1);
+
+        let lam_alef = chars.get(index + 1).filter(|_| c == '\u{0644}')
+            .and_then(|next| LAM_ALEF.iter().find(|(alef, _)| alef == next));
+        let code = match lam_alef {
+            Some((_, ligature)) => {
+                skip_alef = true;
+                ligature + joins_before as u32
+            },
+            None => {
+                let start = ARABIC_FORMS.iter().find(|(letter, _, _)| *letter == c).map(|(_, start, _)| *start).unwrap_or_default();
+                start + match (joins_before, joins_after) {
+                    (false, false) => 0,
+                    (true, false) if forms > 1 => 1,
+                    (false, true) => 2,
+                    (true, true) => 3,
+                    _ => 0
+                }
+            }
+        };
+        shaped.push(char::from_u32(code).unwrap_or(c));
+    }
+    shaped
+}
@@ -0,0 +1,49 @@
+// Checks glTF files follow the engine's conventions before they go in the game.
+//
+//     cargo run --bin validate_gltf -- [--field] <file>...
+//
+// Files after --field are checked as fields, ones before it as models. Exits with 1 if any
+// file has errors, so it can go in an export script.
+#![cfg_attr(target_arch = "wasm32", no_main)]
+
+#[cfg(not(target_arch = "wasm32"))]
+use ps_rpg_engine::validate::{validate_gltf, GltfKind};
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let mut kind = GltfKind::Model;
+    let mut files = 0;
+    let mut failed = false;
+    for arg in std::env::args().skip(1) {
+        if arg == "--field" {
+            kind = GltfKind::Field;
+            continue;
+        }
+        files += 1;
+
+        let bytes = match std::fs::read(&arg) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("{}: error: couldn't read it: {}", arg, e);
+                failed = true;
+                continue;
+            }
+        };
+        let report = validate_gltf(&bytes, kind);
+        if report.issues.is_empty() {
+            println!("{}: ok", arg);
+        }
+        for issue in &report.issues {
+            println!("{}: {}", arg, issue);
+        }
+        failed |= report.has_errors();
+    }
+
+    if files == 0 {
+        println!("Usage: validate_gltf [--field] <file>...");
+        std::process::exit(2);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
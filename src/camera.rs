@@ -0,0 +1,90 @@
+use cgmath::{Point3, Vector3, Vector4, Matrix4, Deg, InnerSpace, VectorSpace, perspective};
+
+// wgpu's clip space has z going from 0 to 1 rather than OpenGL's -1 to 1.
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+// The camera a field is viewed through. Fields have fixed cameras that line up with the
+// pre-rendered background, so this normally comes from the field data.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+
+    // Vertical field of view in degrees.
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            eye: Point3::new(0.0, 5.0, 10.0),
+            target: Point3::new(0.0, 0.0, 0.0),
+            up: Vector3::unit_y(),
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0
+        }
+    }
+}
+
+impl Camera {
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn projection_matrix(&self, aspect: f32) -> Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * perspective(Deg(self.fovy), aspect, self.znear, self.zfar)
+    }
+
+    pub fn view_projection_matrix(&self, aspect: f32) -> Matrix4<f32> {
+        self.projection_matrix(aspect) * self.view_matrix()
+    }
+
+    // Where a point in the world ends up on a screen of the given size, in pixels from the top
+    // left, or None if it's behind the camera.
+    pub fn to_screen(&self, point: Point3<f32>, width: f32, height: f32) -> Option<(f32, f32)> {
+        let clip = self.view_projection_matrix(width / height) * Vector4::new(point.x, point.y, point.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        Some(((clip.x / clip.w + 1.0) * 0.5 * width, (1.0 - clip.y / clip.w) * 0.5 * height))
+    }
+
+    // Part way from this camera to `other`, 0 being this one and 1 the other. Everything moves
+    // in a straight line, which is what a dolly between two field cameras wants.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+        let up = self.up.lerp(other.up, t);
+        Camera {
+            eye: self.eye + (other.eye - self.eye) * t,
+            target: self.target + (other.target - self.target) * t,
+            // Opposite ups would cancel out half way, so keep this one's until then.
+            up: if up.magnitude2() > f32::EPSILON { up.normalize() } else { self.up },
+            fovy: self.fovy + (other.fovy - self.fovy) * t,
+            znear: self.znear + (other.znear - self.znear) * t,
+            zfar: self.zfar + (other.zfar - self.zfar) * t
+        }
+    }
+
+    // The camera as seen in a mirror, for drawing reflections. The plane goes through `point`
+    // facing `normal`. Mirroring the up direction as well keeps it a normal camera, but what it
+    // sees comes out flipped left to right compared to the reflection.
+    pub fn reflected(&self, point: Point3<f32>, normal: Vector3<f32>) -> Camera {
+        let normal = normal.normalize();
+        let reflect_point = |p: Point3<f32>| p - normal * 2.0 * (p - point).dot(normal);
+        Camera {
+            eye: reflect_point(self.eye),
+            target: reflect_point(self.target),
+            up: self.up - normal * 2.0 * self.up.dot(normal),
+            ..*self
+        }
+    }
+}
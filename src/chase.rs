@@ -0,0 +1,231 @@
+// NPCs going after the player, or anyone else, like a guard who's spotted them. They find a way
+// round whatever's in the field's way and follow it, finding it again every so often as the
+// target moves. Catching the target sends a catch event, and chasers with a battle start it,
+// for enemies that can't be got away from.
+//
+// Paths are found with A* over a grid laid on the field, with the field's blockers or a 2D
+// field's solid tiles filled in. Once walk meshes are loaded it'd be over their triangles
+// instead.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+
+use crate::field;
+use crate::tilemap::Tilemap;
+use crate::transform::Transform;
+use crate::world::{Entity, World};
+
+// How big the grid's squares are, in metres. 2D fields use their tiles.
+pub const CELL_SIZE: f32 = 0.5;
+// How many squares A* looks at before giving up, so chasing someone who can't be got to
+// doesn't take forever.
+pub const MAX_CELLS: usize = 4096;
+// How often the path's found again, as the target won't stay put.
+pub const REPATH_TIME: Duration = Duration::from_millis(500);
+// How fast chasers go and how close they have to get to catch someone if they aren't told, in
+// metres, and in pixels for 2D fields.
+pub const DEFAULT_SPEED: f32 = 3.0;
+pub const DEFAULT_SPEED_2D: f32 = 64.0;
+pub const DEFAULT_CATCH_RADIUS: f32 = 0.75;
+pub const DEFAULT_CATCH_RADIUS_2D: f32 = 12.0;
+
+// Straight and diagonal steps, tenths of a square so they stay whole numbers.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// Component for someone going after another entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chaser {
+    pub target: Entity,
+    // Metres a second, or pixels in 2D fields.
+    pub speed: f32,
+    pub catch_radius: f32,
+    // The formation or encounter table to fight when they catch the target, if any.
+    pub battle: Option<String>,
+    // Where they're heading next, then the rest of the way.
+    path: Vec<Vector3<f32>>,
+    // Since the path was last found, None before the first time.
+    since_path: Option<Duration>
+}
+
+impl Chaser {
+    pub fn new(target: Entity, speed: f32) -> Self {
+        Self { target, speed, catch_radius: DEFAULT_CATCH_RADIUS, battle: None, path: Vec::new(), since_path: None }
+    }
+
+    // The way they're going, for debugging.
+    pub fn path(&self) -> &[Vector3<f32>] {
+        &self.path
+    }
+}
+
+// Someone catching who they were after.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Catch {
+    pub chaser: Entity,
+    pub target: Entity,
+    pub battle: Option<String>
+}
+
+// Resource collecting catches for whatever starts the battle or cutscene.
+#[derive(Clone, Debug, Default)]
+pub struct CatchEvents {
+    events: Vec<Catch>
+}
+
+impl CatchEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Catches since the last call, in the order they happened.
+    pub fn take_events(&mut self) -> Vec<Catch> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// Find a way from one point to another round anything `blocked` says is in the way, checked at
+// the middle of each square. It ends exactly at `to`, and None means there isn't a way, or not
+// one within MAX_CELLS. Heights stay as they are at `from`, besides the end.
+pub fn find_path(from: Vector3<f32>, to: Vector3<f32>, cell_size: f32, blocked: impl Fn(f32, f32) -> bool) -> Option<Vec<Vector3<f32>>> {
+    let cell_of = |point: Vector3<f32>| ((point.x / cell_size).floor() as i32, (point.z / cell_size).floor() as i32);
+    let middle = |(x, z): (i32, i32)| ((x as f32 + 0.5) * cell_size, (z as f32 + 0.5) * cell_size);
+    let (start, goal) = (cell_of(from), cell_of(to));
+    // The start and goal are never blocked, or standing next to a wall would stop anything.
+    let is_blocked = |cell: (i32, i32)| cell != start && cell != goal && {
+        let (x, z) = middle(cell);
+        blocked(x, z)
+    };
+    let estimate = |(x, z): (i32, i32)| {
+        let (dx, dz) = ((x - goal.0).unsigned_abs(), (z - goal.1).unsigned_abs());
+        dx.max(dz) * STRAIGHT_COST + dx.min(dz) * (DIAGONAL_COST - STRAIGHT_COST)
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+    open.push(Reverse((estimate(start), start)));
+    costs.insert(start, 0);
+    while let Some(Reverse((_, cell))) = open.pop() {
+        if cell == goal {
+            break;
+        }
+        if costs.len() > MAX_CELLS {
+            return None;
+        }
+        let cost = costs[&cell];
+        for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let next = (cell.0 + dx, cell.1 + dz);
+            // No cutting corners round blocked squares.
+            if is_blocked(next) || (dx != 0 && dz != 0 && (is_blocked((cell.0 + dx, cell.1)) || is_blocked((cell.0, cell.1 + dz)))) {
+                continue;
+            }
+            let next_cost = cost + if dx != 0 && dz != 0 { DIAGONAL_COST } else { STRAIGHT_COST };
+            if costs.get(&next).is_some_and(|&known| known <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            came_from.insert(next, cell);
+            open.push(Reverse((next_cost + estimate(next), next)));
+        }
+    }
+    if !costs.contains_key(&goal) {
+        return None;
+    }
+
+    // Back from the goal, keeping only the squares where it turns.
+    let mut cells = vec![goal];
+    while let Some(&previous) = came_from.get(cells.last()?) {
+        cells.push(previous);
+    }
+    cells.reverse();
+    let mut path = Vec::new();
+    for window in cells.windows(3) {
+        let (before, turn, after) = (window[0], window[1], window[2]);
+        if (turn.0 - before.0, turn.1 - before.1) != (after.0 - turn.0, after.1 - turn.1) {
+            let (x, z) = middle(turn);
+            path.push(Vector3::new(x, from.y, z));
+        }
+    }
+    path.push(to);
+    Some(path)
+}
+
+// Find chasers' paths when they're due, move them along, and catch anyone they've got to.
+// Catches go in the CatchEvents resource and the chaser stops. Returns true while anyone's
+// still chasing.
+pub fn update_chasers(world: &mut World, delta: Duration) -> bool {
+    // 2D fields use their tiles for the grid, and x and z are map pixels.
+    let map = world.resource::<Tilemap>();
+    let cell_size = map.map_or(CELL_SIZE, |map| map.tile_width.min(map.tile_height).max(1) as f32);
+    let blockers = field::blockers(world);
+    let blocked = |x: f32, z: f32| map.is_some_and(|map| map.is_solid_at(x, z)) || blockers.iter().any(|blocker| blocker.contains(x, z));
+
+    let mut moves = Vec::new();
+    for (entity, chaser) in world.query::<Chaser>() {
+        let (position, target) = match (world.get::<Transform>(entity), world.get::<Transform>(chaser.target)) {
+            (Some(transform), Some(target)) => (transform.position, target.position),
+            _ => continue
+        };
+        let since_path = chaser.since_path.map(|since| since + delta);
+        let path = match since_path {
+            Some(since) if since < REPATH_TIME => None,
+            // Without a way there, they stand and wait for one.
+            _ => Some(find_path(position, target, cell_size, blocked).unwrap_or_default())
+        };
+        moves.push((entity, target, path));
+    }
+
+    let mut caught = Vec::new();
+    for (entity, target, path) in moves {
+        let (mut chaser, mut transform) = match (world.get::<Chaser>(entity).cloned(), world.get::<Transform>(entity).copied()) {
+            (Some(chaser), Some(transform)) => (chaser, transform),
+            _ => continue
+        };
+        match path {
+            Some(path) => {
+                chaser.path = path;
+                chaser.since_path = Some(Duration::ZERO);
+            },
+            None => chaser.since_path = chaser.since_path.map(|since| since + delta)
+        }
+
+        // Along the path as far as they get this frame, maybe past a turn or two.
+        let mut step = chaser.speed * delta.as_secs_f32();
+        while let Some(&next) = chaser.path.first() {
+            let to_go = next - transform.position;
+            let distance = to_go.magnitude();
+            if distance > f32::EPSILON {
+                transform.rotation = Quaternion::from_angle_y(Rad(to_go.x.atan2(to_go.z)));
+            }
+            if distance > step {
+                transform.position += to_go / distance * step;
+                break;
+            }
+            transform.position = next;
+            step -= distance;
+            chaser.path.remove(0);
+        }
+        world.insert(entity, transform);
+
+        if (target - transform.position).magnitude() <= chaser.catch_radius {
+            world.remove::<Chaser>(entity);
+            caught.push(Catch { chaser: entity, target: chaser.target, battle: chaser.battle });
+        } else {
+            world.insert(entity, chaser);
+        }
+    }
+
+    if !caught.is_empty() {
+        if world.resource::<CatchEvents>().is_none() {
+            world.insert_resource(CatchEvents::new());
+        }
+        if let Some(events) = world.resource_mut::<CatchEvents>() {
+            events.events.extend(caught);
+        }
+    }
+    world.query::<Chaser>().next().is_some()
+}
@@ -0,0 +1,76 @@
+// Cutscenes in the field, with black bars sliding in along the top and bottom like a film.
+// While one's playing the HUD is hidden, that's the interaction prompt, field effects and the
+// hotbar, and the player can't walk, open menus or use anything. The message window still
+// works, for whoever's talking in it. Scripts and the console start and end one with
+// "cinematic on" and "cinematic off".
+//
+// Everything comes back once it's ended: the player can move straight away, and the HUD's back
+// as soon as the bars have slid out of the way.
+
+use std::time::Duration;
+
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::screen_effects::Easing;
+use crate::ui::{Color, UiBatch};
+
+// How tall each bar is, in virtual screen pixels, and how long they take to slide in or out.
+pub const BAR_HEIGHT: f32 = SCREEN_HEIGHT as f32 / 8.0;
+pub const BAR_TIME: Duration = Duration::from_millis(600);
+
+const BAR_COLOR: Color = [0.0, 0.0, 0.0, 1.0];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cinematic {
+    playing: bool,
+    // How far in the bars are, from 0 for gone to 1 for all the way.
+    bars: f32
+}
+
+impl Cinematic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn end(&mut self) {
+        self.playing = false;
+    }
+
+    // Whether the player's locked out.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // Whether the HUD's hidden, which is until the bars have gone again.
+    pub fn hides_hud(&self) -> bool {
+        self.playing || self.bars > 0.0
+    }
+
+    // How tall the bars are now.
+    pub fn bar_height(&self) -> f32 {
+        BAR_HEIGHT * Easing::EaseInOut.apply(self.bars)
+    }
+
+    // Slide the bars in or out. Returns whether they moved.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        let target = if self.playing { 1.0 } else { 0.0 };
+        if self.bars == target {
+            return false;
+        }
+        let step = delta.as_secs_f32() / BAR_TIME.as_secs_f32();
+        self.bars = if self.bars < target { (self.bars + step).min(target) } else { (self.bars - step).max(target) };
+        true
+    }
+
+    pub fn build(&self, batch: &mut UiBatch) {
+        let height = self.bar_height();
+        if height <= 0.0 {
+            return;
+        }
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, height, BAR_COLOR);
+        batch.rect(0.0, SCREEN_HEIGHT as f32 - height, SCREEN_WIDTH as f32, height, BAR_COLOR);
+    }
+}
@@ -0,0 +1,122 @@
+use std::{fmt, str::FromStr};
+
+use cgmath::{Matrix, Matrix3, SquareMatrix};
+
+// The kinds of colour blindness the filters know about, each missing one type of cone.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorBlindness {
+    // No red cones.
+    Protanopia,
+    // No green cones, the most common.
+    Deuteranopia,
+    // No blue cones.
+    Tritanopia
+}
+
+impl ColorBlindness {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBlindness::Protanopia => "protanopia",
+            ColorBlindness::Deuteranopia => "deuteranopia",
+            ColorBlindness::Tritanopia => "tritanopia"
+        }
+    }
+
+    // What someone with it sees, from Machado et al. 2009 at full severity. Works on linear
+    // colours. Written out a row at a time.
+    fn simulation(&self) -> Matrix3<f32> {
+        let rows = match self {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998]
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881]
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900]
+            ]
+        };
+        Matrix3::from(rows).transpose()
+    }
+
+    // Where the colour that can't be seen is moved to, so it shows up as something that can.
+    fn error_shift(&self) -> Matrix3<f32> {
+        let rows = match self {
+            // Lost reds show up as more green and blue.
+            ColorBlindness::Protanopia | ColorBlindness::Deuteranopia => [
+                [0.0, 0.0, 0.0],
+                [0.7, 1.0, 0.0],
+                [0.7, 0.0, 1.0]
+            ],
+            // Lost blues show up as more red and green.
+            ColorBlindness::Tritanopia => [
+                [1.0, 0.0, 0.7],
+                [0.0, 1.0, 0.7],
+                [0.0, 0.0, 0.0]
+            ]
+        };
+        Matrix3::from(rows).transpose()
+    }
+}
+
+// Post process filter for colour blind players. Correct filters (daltonization) shift the
+// colours that would be lost into ones that can be told apart, and simulate filters show
+// what the game looks like with colour blindness, for checking that art still reads.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorFilter {
+    #[default]
+    None,
+    Correct(ColorBlindness),
+    Simulate(ColorBlindness)
+}
+
+impl ColorFilter {
+    pub const ALL: [ColorFilter; 7] = [
+        ColorFilter::None,
+        ColorFilter::Correct(ColorBlindness::Protanopia),
+        ColorFilter::Correct(ColorBlindness::Deuteranopia),
+        ColorFilter::Correct(ColorBlindness::Tritanopia),
+        ColorFilter::Simulate(ColorBlindness::Protanopia),
+        ColorFilter::Simulate(ColorBlindness::Deuteranopia),
+        ColorFilter::Simulate(ColorBlindness::Tritanopia)
+    ];
+
+    // The colour matrix the post process shader applies, to linear colours.
+    pub fn matrix(&self) -> Matrix3<f32> {
+        match self {
+            ColorFilter::None => Matrix3::identity(),
+            ColorFilter::Correct(blindness) => {
+                let lost = Matrix3::identity() - blindness.simulation();
+                Matrix3::identity() + blindness.error_shift() * lost
+            },
+            ColorFilter::Simulate(blindness) => blindness.simulation()
+        }
+    }
+}
+
+impl fmt::Display for ColorFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorFilter::None => f.write_str("off"),
+            ColorFilter::Correct(blindness) => f.write_str(blindness.name()),
+            ColorFilter::Simulate(blindness) => write!(f, "simulate_{}", blindness.name())
+        }
+    }
+}
+
+// Takes the names Display gives, e.g. "deuteranopia" or "simulate_tritanopia".
+impl FromStr for ColorFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorFilter::ALL.into_iter()
+            .find(|filter| filter.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown colour filter \"{}\", expected off, protanopia, deuteranopia, tritanopia or simulate_ and one of those", s.trim()))
+    }
+}
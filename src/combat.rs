@@ -0,0 +1,285 @@
+// How an attack plays out: whether it hits, how much it does, and whether it's a critical,
+// blocked or countered. The numbers are in data/combat.cfg as "key = value" lines, see
+// CombatConfig for what each one does. Games that want different formulas implement
+// CombatRules themselves, overriding just the steps they want to change.
+//
+// Breaking enemies is optional, and off unless "stagger = on" is in there, see stagger.rs.
+
+use crate::formation::RowModifiers;
+use crate::parse::parse_bool;
+use crate::rng::RngStream;
+use crate::timed_hit::TimedGrade;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CombatConfig {
+    // How far damage can stray either way, as a fraction. 0.1 is anywhere from 90% to 110%.
+    pub variance: f32,
+    pub critical_chance: f32,
+    pub critical_multiplier: f32,
+    // The chance to hit when accuracy and evasion are the same.
+    pub base_hit: f32,
+    // How much each point of accuracy over the target's evasion adds to the chance to hit, or
+    // takes off for each point under.
+    pub hit_per_point: f32,
+    // The chance to hit never goes outside these.
+    pub min_hit: f32,
+    pub max_hit: f32,
+    // What damage is multiplied by when it's blocked.
+    pub block_multiplier: f32,
+    // What damage is multiplied by for each timed hit grade, see timed_hit.rs.
+    pub timed_good: f32,
+    pub timed_great: f32,
+    pub timed_perfect: f32,
+    // Whether enemies can be broken, see stagger.rs.
+    pub stagger: bool,
+    // How much break damage it takes to break someone, as a fraction of their max HP.
+    pub break_gauge: f32,
+    // How much of a hit's damage goes on the break gauge, normally and for weaknesses.
+    pub break_normal: f32,
+    pub break_weakness: f32,
+    // What damage is multiplied by while someone's broken.
+    pub break_bonus: f32,
+    // How many turns someone loses to being broken.
+    pub break_turns: u32
+}
+
+impl Default for CombatConfig {
+    fn default() -> Self {
+        Self {
+            variance: 0.1,
+            critical_chance: 0.05,
+            critical_multiplier: 2.0,
+            base_hit: 0.95,
+            hit_per_point: 0.01,
+            min_hit: 0.05,
+            max_hit: 1.0,
+            block_multiplier: 0.5,
+            timed_good: 1.25,
+            timed_great: 1.5,
+            timed_perfect: 2.0,
+            stagger: false,
+            break_gauge: 0.5,
+            break_normal: 0.25,
+            break_weakness: 1.0,
+            break_bonus: 1.5,
+            break_turns: 1
+        }
+    }
+}
+
+impl CombatConfig {
+    // Anything left out keeps its default.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = CombatConfig::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "stagger" => {
+                    config.stagger = parse_bool(value).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+                    continue;
+                },
+                "break_turns" => {
+                    config.break_turns = value.parse().map_err(|_| format!("Line {}: bad {} \"{}\"", number + 1, key, value))?;
+                    continue;
+                },
+                _ => {}
+            }
+            let (field, max) = match key {
+                "variance" => (&mut config.variance, 1.0),
+                "critical_chance" => (&mut config.critical_chance, 1.0),
+                "critical_multiplier" => (&mut config.critical_multiplier, f32::INFINITY),
+                "base_hit" => (&mut config.base_hit, 1.0),
+                "hit_per_point" => (&mut config.hit_per_point, 1.0),
+                "min_hit" => (&mut config.min_hit, 1.0),
+                "max_hit" => (&mut config.max_hit, 1.0),
+                "block_multiplier" => (&mut config.block_multiplier, f32::INFINITY),
+                "timed_good" => (&mut config.timed_good, f32::INFINITY),
+                "timed_great" => (&mut config.timed_great, f32::INFINITY),
+                "timed_perfect" => (&mut config.timed_perfect, f32::INFINITY),
+                "break_gauge" => (&mut config.break_gauge, f32::INFINITY),
+                "break_normal" => (&mut config.break_normal, f32::INFINITY),
+                "break_weakness" => (&mut config.break_weakness, f32::INFINITY),
+                "break_bonus" => (&mut config.break_bonus, f32::INFINITY),
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            };
+            *field = value.parse().ok()
+                .filter(|value| (0.0..=max).contains(value))
+                .ok_or_else(|| format!("Line {}: bad {} \"{}\"", number + 1, key, value))?;
+        }
+        if config.min_hit > config.max_hit {
+            return Err("min_hit is more than max_hit".to_string());
+        }
+        Ok(config)
+    }
+}
+
+// Everything about an attack that decides how it goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attack {
+    // The attacker's strength, or magic for spells.
+    pub attack: u32,
+    // What the skill multiplies damage by, 1 for a plain attack.
+    pub power: f32,
+    pub accuracy: i32,
+    // Added to the chance of a critical, for skills and weapons that are better at them.
+    pub critical_bonus: f32,
+    pub row: RowModifiers,
+    // Spells and the like can't be blocked or countered.
+    pub blockable: bool,
+    pub counterable: bool,
+    // Whether it's something the target's weak to, which breaks them quicker.
+    pub weakness: bool
+}
+
+impl Attack {
+    // A plain attack, which can be blocked and countered and doesn't care about rows.
+    pub fn new(attack: u32, accuracy: i32) -> Self {
+        Self {
+            attack,
+            power: 1.0,
+            accuracy,
+            critical_bonus: 0.0,
+            row: RowModifiers { damage: 1.0, accuracy: 1.0 },
+            blockable: true,
+            counterable: true,
+            weakness: false
+        }
+    }
+}
+
+// Everything about who's being attacked that decides how it goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Defence {
+    pub defence: u32,
+    pub evasion: i32,
+    pub block_chance: f32,
+    pub counter_chance: f32,
+    // Whether they're broken, and take more damage for it.
+    pub broken: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Miss,
+    Hit,
+    Critical
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HitResult {
+    pub outcome: Outcome,
+    pub damage: u32,
+    pub blocked: bool,
+    // Whether the target hits back. The counter's an attack of its own, to be resolved after.
+    pub countered: bool
+}
+
+// Each step of working out an attack. Everything but config has a default that uses it, so a
+// game only needs to override what it does differently.
+pub trait CombatRules {
+    fn config(&self) -> &CombatConfig;
+
+    // Damage before variance, criticals and blocking.
+    fn base_damage(&self, attack: &Attack, defence: &Defence) -> f32 {
+        (attack.attack as f32 * attack.power * 2.0 - defence.defence as f32).max(1.0) * attack.row.damage
+    }
+
+    fn vary_damage(&self, damage: f32, rng: &mut RngStream) -> f32 {
+        let variance = self.config().variance;
+        damage * (1.0 + rng.range_f32(-variance, variance))
+    }
+
+    fn hit_chance(&self, attack: &Attack, defence: &Defence) -> f32 {
+        let config = self.config();
+        let chance = config.base_hit + (attack.accuracy - defence.evasion) as f32 * config.hit_per_point;
+        chance.clamp(config.min_hit, config.max_hit) * attack.row.accuracy
+    }
+
+    fn critical_chance(&self, attack: &Attack, _defence: &Defence) -> f32 {
+        (self.config().critical_chance + attack.critical_bonus).clamp(0.0, 1.0)
+    }
+
+    fn critical_multiplier(&self, _attack: &Attack, _defence: &Defence) -> f32 {
+        self.config().critical_multiplier
+    }
+
+    fn block_chance(&self, attack: &Attack, defence: &Defence) -> f32 {
+        if attack.blockable { defence.block_chance } else { 0.0 }
+    }
+
+    fn block_multiplier(&self, _attack: &Attack, _defence: &Defence) -> f32 {
+        self.config().block_multiplier
+    }
+
+    // Only attacks that land are countered.
+    fn counter_chance(&self, attack: &Attack, defence: &Defence) -> f32 {
+        if attack.counterable { defence.counter_chance } else { 0.0 }
+    }
+
+    fn timed_hit_multiplier(&self, grade: TimedGrade) -> f32 {
+        let config = self.config();
+        match grade {
+            TimedGrade::Miss => 1.0,
+            TimedGrade::Good => config.timed_good,
+            TimedGrade::Great => config.timed_great,
+            TimedGrade::Perfect => config.timed_perfect
+        }
+    }
+
+    // How much a hit fills the target's break gauge.
+    fn break_damage(&self, attack: &Attack, result: &HitResult) -> f32 {
+        let config = self.config();
+        if !config.stagger || result.outcome == Outcome::Miss {
+            return 0.0;
+        }
+        result.damage as f32 * if attack.weakness { config.break_weakness } else { config.break_normal }
+    }
+
+    fn broken_multiplier(&self, _attack: &Attack, _defence: &Defence) -> f32 {
+        self.config().break_bonus
+    }
+}
+
+// The rules as they are in the config, with nothing overridden.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StandardRules(pub CombatConfig);
+
+impl CombatRules for StandardRules {
+    fn config(&self) -> &CombatConfig {
+        &self.0
+    }
+}
+
+// Work out how an attack goes. Each roll's taken in the same order every time, so a battle
+// replays the same from the same seed.
+pub fn resolve(rules: &dyn CombatRules, attack: &Attack, defence: &Defence, rng: &mut RngStream) -> HitResult {
+    if !rng.chance(rules.hit_chance(attack, defence)) {
+        return HitResult { outcome: Outcome::Miss, damage: 0, blocked: false, countered: false };
+    }
+
+    let mut damage = rules.vary_damage(rules.base_damage(attack, defence), rng);
+    if defence.broken {
+        damage *= rules.broken_multiplier(attack, defence);
+    }
+    let outcome = if rng.chance(rules.critical_chance(attack, defence)) {
+        damage *= rules.critical_multiplier(attack, defence);
+        Outcome::Critical
+    } else {
+        Outcome::Hit
+    };
+    let blocked = rng.chance(rules.block_chance(attack, defence));
+    if blocked {
+        damage *= rules.block_multiplier(attack, defence);
+    }
+    let countered = rng.chance(rules.counter_chance(attack, defence));
+    // Anything that lands does at least 1, unless it's blocked completely.
+    let damage = if blocked { damage.round() as u32 } else { (damage.round() as u32).max(1) };
+    HitResult { outcome, damage, blocked, countered }
+}
@@ -0,0 +1,131 @@
+// Dual and triple techs, like Chrono Trigger's: skills two or three party members use together.
+// They're in data/combos.cfg, each under a [Name] header:
+//
+//   [Cross Slash]
+//   description = Aria and Tobin cut across each other.
+//   member = Aria 3
+//   member = Tobin 3
+//   target = enemy
+//
+// with a member line for each of them and the MP it costs them. Everyone in it has to be in
+// the party, still standing and have the MP. It takes all of their turns: whoever's turn it is
+// uses it, and the others give up their next with TurnOrder::use_turn. Their animations play
+// together, and the hits wait until all of them get to theirs, see SkillSequence::with_users.
+
+use crate::party::{Party, TargetType};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComboMember {
+    pub name: String,
+    pub mp_cost: u32
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComboTech {
+    pub name: String,
+    pub description: String,
+    pub members: Vec<ComboMember>,
+    pub target: TargetType
+}
+
+impl ComboTech {
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut techs: Vec<ComboTech> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some(tech) = techs.last() {
+                    check_members(tech)?;
+                }
+                let name = name.trim();
+                if techs.iter().any(|tech| tech.name.eq_ignore_ascii_case(name)) {
+                    return Err(format!("Line {}: there's already a combo called {}", number + 1, name));
+                }
+                techs.push(ComboTech { name: name.to_string(), description: String::new(), members: Vec::new(), target: TargetType::Enemy });
+                continue;
+            }
+
+            let tech = techs.last_mut().ok_or_else(|| format!("Line {}: expected a [Combo name] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "description" => tech.description = value.to_string(),
+                "target" => tech.target = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?,
+                "member" => {
+                    let (name, mp_cost) = match value.rsplit_once(char::is_whitespace) {
+                        Some((name, mp_cost)) => (name.trim(), mp_cost.parse().map_err(|_| format!("Line {}: bad MP cost \"{}\"", number + 1, mp_cost))?),
+                        None => (value, 0)
+                    };
+                    if tech.members.iter().any(|member| member.name.eq_ignore_ascii_case(name)) {
+                        return Err(format!("Line {}: {} is in {} twice", number + 1, name, tech.name));
+                    }
+                    tech.members.push(ComboMember { name: name.to_string(), mp_cost });
+                },
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        if let Some(tech) = techs.last() {
+            check_members(tech)?;
+        }
+        Ok(techs)
+    }
+
+    pub fn involves(&self, name: &str) -> bool {
+        self.members.iter().any(|member| member.name.eq_ignore_ascii_case(name))
+    }
+
+    // Everyone in it, as indices into the party in the order they're listed, or why it can't
+    // be used.
+    pub fn check(&self, party: &Party) -> Result<Vec<usize>, String> {
+        self.members.iter()
+            .map(|member| {
+                let index = party.members.iter().position(|known| known.name.eq_ignore_ascii_case(&member.name))
+                    .ok_or_else(|| format!("{} isn't in the party", member.name))?;
+                let stats = &party.members[index].stats;
+                if stats.is_knocked_out() {
+                    return Err(format!("{} is knocked out", member.name));
+                }
+                if stats.mp < member.mp_cost {
+                    return Err(format!("{} needs {} MP", member.name, member.mp_cost));
+                }
+                Ok(index)
+            })
+            .collect()
+    }
+
+    // Take everyone's MP, if it can be used. Returns who's in it, like check.
+    pub fn pay(&self, party: &mut Party) -> Result<Vec<usize>, String> {
+        let users = self.check(party)?;
+        for (index, member) in users.iter().zip(&self.members) {
+            party.members[*index].stats.mp -= member.mp_cost;
+        }
+        Ok(users)
+    }
+}
+
+fn check_members(tech: &ComboTech) -> Result<(), String> {
+    match tech.members.len() {
+        2 | 3 => Ok(()),
+        count => Err(format!("{} has {} members, combos are for two or three", tech.name, count))
+    }
+}
+
+// Resource with every combo.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComboTechs(pub Vec<ComboTech>);
+
+impl ComboTechs {
+    pub fn get(&self, name: &str) -> Option<&ComboTech> {
+        self.0.iter().find(|tech| tech.name.eq_ignore_ascii_case(name))
+    }
+
+    // The ones someone could start now, for their turn's menu.
+    pub fn usable_by<'a>(&'a self, name: &'a str, party: &'a Party) -> impl Iterator<Item = &'a ComboTech> {
+        self.0.iter().filter(move |tech| tech.involves(name) && tech.check(party).is_ok())
+    }
+}
@@ -0,0 +1,205 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use winit::dpi::{LogicalSize, PhysicalPosition};
+
+use crate::accessibility::Accessibility;
+use crate::color_filter::ColorFilter;
+use crate::depth_of_field::DofQuality;
+use crate::display::{WindowMode, WindowPlacement};
+use crate::logging::targets;
+use crate::mixer::{Bus, BusVolumes};
+use crate::paths;
+pub use crate::parse::parse_bool;
+use crate::player_controller::MovementMode;
+
+// Settings that are remembered between runs. Stored as "key = value" lines so they're easy
+// to edit by hand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub window_mode: WindowMode,
+    // The monitor to go fullscreen on, by name.
+    pub monitor: Option<String>,
+    // Where the window was when it was last windowed.
+    pub window_placement: Option<WindowPlacement>,
+
+    pub vsync: bool,
+    // Frame rate cap, separate from vsync. None for no cap.
+    pub fps_cap: Option<u32>,
+    // Only redraw when something changes.
+    pub low_power: bool,
+    // Model detail. Lower it on slow GPUs to switch to simpler models sooner.
+    pub lod_bias: f32,
+
+    pub accessibility: Accessibility,
+    pub color_filter: ColorFilter,
+    // How smooth depth of field is in battles and photo mode, or off.
+    pub dof_quality: DofQuality,
+    pub volumes: BusVolumes,
+    // What language to show text in, as a language tag like "en" or "pt-BR".
+    pub language: String,
+    // Whether walking goes by the camera or the world.
+    pub movement: MovementMode,
+    // Run unless shift's held, rather than only while it is.
+    pub always_run: bool,
+    // Whether the player's agreed to send telemetry. Off until they do.
+    pub telemetry: bool,
+    // The mods that are on, by folder name, in the order they go on. See mods.rs.
+    pub mods: Vec<String>
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_mode: WindowMode::default(),
+            monitor: None,
+            window_placement: None,
+            vsync: true,
+            fps_cap: None,
+            low_power: false,
+            lod_bias: 1.0,
+            accessibility: Accessibility::default(),
+            color_filter: ColorFilter::None,
+            dof_quality: DofQuality::default(),
+            volumes: BusVolumes::default(),
+            language: "en".to_string(),
+            movement: MovementMode::default(),
+            always_run: false,
+            telemetry: false,
+            mods: Vec::new()
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        paths::config_path()
+    }
+
+    // Load the config, or the defaults if there isn't one yet.
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                tracing::warn!(target: targets::ENGINE, "Couldn't read config {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        let mut config = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    tracing::warn!(target: targets::ENGINE, "{}:{}: expected \"key = value\"", path.display(), number + 1);
+                    continue;
+                }
+            };
+
+            if let Err(e) = config.set(key, value) {
+                tracing::warn!(target: targets::ENGINE, "{}:{}: {}", path.display(), number + 1, e);
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "window_mode" => self.window_mode = value.parse()?,
+            "monitor" => self.monitor = (!value.is_empty()).then(|| value.to_string()),
+            "window_position" => {
+                let (x, y) = parse_pair(value)?;
+                let size = self.window_placement.map(|placement| placement.size).unwrap_or(LogicalSize::new(0.0, 0.0));
+                self.window_placement = Some(WindowPlacement { position: PhysicalPosition::new(x, y), size });
+            },
+            "window_size" => {
+                let (width, height) = parse_pair(value)?;
+                let position = self.window_placement.map(|placement| placement.position).unwrap_or(PhysicalPosition::new(0, 0));
+                self.window_placement = Some(WindowPlacement { position, size: LogicalSize::new(width, height) });
+            },
+            "vsync" => self.vsync = parse_bool(value)?,
+            "fps_cap" => self.fps_cap = match value {
+                "" | "off" | "0" => None,
+                _ => Some(value.parse().map_err(|_| format!("Bad frame rate cap \"{}\"", value))?)
+            },
+            "low_power" => self.low_power = parse_bool(value)?,
+            "lod_bias" => self.lod_bias = value.parse().ok().filter(|bias: &f32| *bias > 0.0).ok_or_else(|| format!("Bad LOD bias \"{}\"", value))?,
+            "text_scale" => self.accessibility.set_text_scale(value.parse().map_err(|_| format!("Bad text scale \"{}\"", value))?),
+            "text_speed" => self.accessibility.text_speed = value.parse()?,
+            "high_contrast" => self.accessibility.high_contrast = parse_bool(value)?,
+            "screen_effects" => self.accessibility.screen_effects = parse_bool(value)?,
+            "subtitles" => self.accessibility.subtitles = parse_bool(value)?,
+            "color_filter" => self.color_filter = value.parse()?,
+            "dof_quality" => self.dof_quality = value.parse()?,
+            "language" if value.is_empty() => return Err("Expected a language".to_string()),
+            "language" => self.language = value.to_string(),
+            "movement" => self.movement = value.parse()?,
+            "always_run" => self.always_run = parse_bool(value)?,
+            "telemetry" => self.telemetry = parse_bool(value)?,
+            "mods" => self.mods = value.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect(),
+            // music_volume, sfx_volume and so on.
+            _ if key.ends_with("_volume") => {
+                let bus: Bus = key.trim_end_matches("_volume").parse().map_err(|_| format!("Unknown setting \"{}\"", key))?;
+                self.volumes.set(bus, value.parse().map_err(|_| format!("Bad volume \"{}\"", value))?);
+            },
+            _ => return Err(format!("Unknown setting \"{}\"", key))
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = String::new();
+        text.push_str(&format!("window_mode = {}\n", self.window_mode));
+        text.push_str(&format!("monitor = {}\n", self.monitor.as_deref().unwrap_or_default()));
+        if let Some(placement) = &self.window_placement {
+            text.push_str(&format!("window_position = {}, {}\n", placement.position.x, placement.position.y));
+            text.push_str(&format!("window_size = {}, {}\n", placement.size.width, placement.size.height));
+        }
+        text.push_str(&format!("vsync = {}\n", self.vsync));
+        text.push_str(&format!("fps_cap = {}\n", self.fps_cap.map(|fps| fps.to_string()).unwrap_or_else(|| "off".to_string())));
+        text.push_str(&format!("low_power = {}\n", self.low_power));
+        text.push_str(&format!("lod_bias = {}\n", self.lod_bias));
+        text.push_str(&format!("text_scale = {}\n", self.accessibility.text_scale()));
+        text.push_str(&format!("text_speed = {}\n", self.accessibility.text_speed));
+        text.push_str(&format!("high_contrast = {}\n", self.accessibility.high_contrast));
+        text.push_str(&format!("screen_effects = {}\n", self.accessibility.screen_effects));
+        text.push_str(&format!("subtitles = {}\n", self.accessibility.subtitles));
+        text.push_str(&format!("color_filter = {}\n", self.color_filter));
+        text.push_str(&format!("dof_quality = {}\n", self.dof_quality));
+        text.push_str(&format!("language = {}\n", self.language));
+        text.push_str(&format!("movement = {}\n", self.movement));
+        text.push_str(&format!("always_run = {}\n", self.always_run));
+        text.push_str(&format!("telemetry = {}\n", self.telemetry));
+        text.push_str(&format!("mods = {}\n", self.mods.join(", ")));
+        for bus in Bus::ALL {
+            text.push_str(&format!("{}_volume = {}\n", bus, self.volumes.get(bus)));
+        }
+        fs::write(path, text)
+    }
+}
+
+// "1, 2" -> (1, 2)
+fn parse_pair<T: std::str::FromStr>(value: &str) -> Result<(T, T), String> {
+    let parse = |part: &str| part.trim().parse::<T>().map_err(|_| format!("Bad number \"{}\"", part.trim()));
+    match value.split_once(',') {
+        Some((a, b)) => Ok((parse(a)?, parse(b)?)),
+        None => Err(format!("Expected two numbers, got \"{}\"", value))
+    }
+}
@@ -0,0 +1,168 @@
+use std::{io::BufRead, sync::mpsc::{self, Receiver}};
+
+use crate::mods;
+
+// A developer console that reads commands typed into the terminal the game was started from.
+// Lines are read on a background thread and picked up by the game loop with poll().
+pub struct Console {
+    receiver: Receiver<String>
+}
+
+// A parsed console line, e.g. "log renderer=debug" is name "log" with args "renderer=debug".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: String
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        Some(Self {
+            name: name.to_lowercase(),
+            args: args.trim().to_string()
+        })
+    }
+}
+
+impl Console {
+    pub fn spawn_stdin() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("console".to_string())
+            .spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    match line {
+                        Ok(line) => if sender.send(line).is_err() { break },
+                        Err(_) => break
+                    }
+                }
+            })
+            .expect("Couldn't start the console thread");
+
+        Self {
+            receiver
+        }
+    }
+
+    // Any commands typed since the last poll.
+    pub fn poll(&self) -> Vec<ConsoleCommand> {
+        self.receiver.try_iter()
+            .filter_map(|line| ConsoleCommand::parse(&line))
+            .collect()
+    }
+}
+
+// A command the console knows, and what goes after its name, with [optional] and <required>
+// parts. The game's commands are run by run_console_command in main.rs; this is the list of them
+// for "help", and for tools and mod makers, see commands_json. A test checks the two agree, so
+// a new command needs adding to both.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub args: &'static str
+}
+
+impl CommandInfo {
+    pub fn find(name: &str) -> Option<&'static CommandInfo> {
+        COMMANDS.iter().find(|info| info.name == name)
+    }
+
+    // How it's typed, e.g. "warp [field] [spawn]".
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() { self.name.to_string() } else { format!("{} {}", self.name, self.args) }
+    }
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo { name: "log", args: "[filter]" },
+    CommandInfo { name: "seed", args: "[seed]" },
+    CommandInfo { name: "window", args: "[mode]" },
+    CommandInfo { name: "monitor", args: "[index or name]" },
+    CommandInfo { name: "fps", args: "[cap]" },
+    CommandInfo { name: "lod", args: "[bias]" },
+    CommandInfo { name: "drawdistance", args: "[distance]" },
+    CommandInfo { name: "vsync", args: "[on/off]" },
+    CommandInfo { name: "lowpower", args: "[on/off]" },
+    CommandInfo { name: "textscale", args: "[scale]" },
+    CommandInfo { name: "textspeed", args: "[speed]" },
+    CommandInfo { name: "highcontrast", args: "[on/off]" },
+    CommandInfo { name: "screeneffects", args: "[on/off]" },
+    CommandInfo { name: "subtitles", args: "[on/off]" },
+    CommandInfo { name: "language", args: "[language]" },
+    CommandInfo { name: "movement", args: "[camera/world]" },
+    CommandInfo { name: "alwaysrun", args: "[on/off]" },
+    CommandInfo { name: "telemetry", args: "[on/off]" },
+    CommandInfo { name: "mods", args: "" },
+    CommandInfo { name: "mod", args: "<name> <on/off>" },
+    CommandInfo { name: "colorfilter", args: "[filter]" },
+    CommandInfo { name: "dof", args: "[quality]" },
+    CommandInfo { name: "focus", args: "[distance]" },
+    CommandInfo { name: "effect", args: "[effect value [seconds] [easing] [#colour]]" },
+    CommandInfo { name: "camera", args: "[name [seconds] [easing] [swap=when]]" },
+    CommandInfo { name: "volume", args: "[bus] [level]" },
+    CommandInfo { name: "music", args: "[layer name volume [beats]] [stinger name]" },
+    CommandInfo { name: "cursor", args: "[style]" },
+    CommandInfo { name: "flag", args: "[name] [value]" },
+    CommandInfo { name: "time", args: "[hh:mm]" },
+    CommandInfo { name: "talk", args: "<npc>" },
+    CommandInfo { name: "say", args: "[overflow=...] [hyphenate=on/off] <text>" },
+    CommandInfo { name: "battle", args: "[formation or table [swirl/pixelate/shatter]]" },
+    CommandInfo { name: "battlescript", args: "<formation> <turn> [enemy=hp% ...]" },
+    CommandInfo { name: "win", args: "<formation or table> [turns=N] [item=id ...] [skill=name ...]" },
+    CommandInfo { name: "auto", args: "[on/off]" },
+    CommandInfo { name: "battlespeed", args: "[speed]" },
+    CommandInfo { name: "steal", args: "<enemy>" },
+    CommandInfo { name: "combo", args: "[name]" },
+    CommandInfo { name: "summon", args: "[id]" },
+    CommandInfo { name: "fieldstatus", args: "[effect steps]" },
+    CommandInfo { name: "darkness", args: "[strength radius/off]" },
+    CommandInfo { name: "cinematic", args: "[on/off]" },
+    CommandInfo { name: "puzzle", args: "[reset [field]]" },
+    CommandInfo { name: "hotbar", args: "[slot skill/item/clear]" },
+    CommandInfo { name: "walk", args: "<steps> [table]" },
+    CommandInfo { name: "statuseffect", args: "<member> <effect> [on/off]" },
+    CommandInfo { name: "rename", args: "<member>" },
+    CommandInfo { name: "minigame", args: "[name [key=value ...] [result=flag]]" },
+    CommandInfo { name: "stats", args: "" },
+    CommandInfo { name: "morph", args: "<entity> [target] [weight] [seconds]" },
+    CommandInfo { name: "hit", args: "<entity> [strength]" },
+    CommandInfo { name: "emote", args: "<entity> <emote> [seconds]" },
+    CommandInfo { name: "guard", args: "<entity> [angle range]" },
+    CommandInfo { name: "chase", args: "<entity> [target] [speed] [battle=formation]" },
+    CommandInfo { name: "mover", args: "[name start/stop/restart]" },
+    CommandInfo { name: "ghost", args: "[record/stop name/play name [loop]/clear]" },
+    CommandInfo { name: "event", args: "<name>" },
+    CommandInfo { name: "achievements", args: "" },
+    CommandInfo { name: "movie", args: "<name>" },
+    CommandInfo { name: "warp", args: "[field] [spawn]" },
+    CommandInfo { name: "preset", args: "[name]" },
+    CommandInfo { name: "shader", args: "<file>" },
+    CommandInfo { name: "assets", args: "[owner]" },
+    CommandInfo { name: "exportsave", args: "<slot> [file]" },
+    CommandInfo { name: "importsave", args: "<slot> [file]" },
+    CommandInfo { name: "commands", args: "[file]" },
+    CommandInfo { name: "help", args: "" }
+];
+
+// The list "help" prints.
+pub fn help() -> String {
+    let usages: Vec<String> = COMMANDS.iter().map(CommandInfo::usage).collect();
+    format!("Commands: {}", usages.join(", "))
+}
+
+// Every command, what it takes and whether mods can run it, as a JSON array.
+pub fn commands_json() -> String {
+    let commands: Vec<serde_json::Value> = COMMANDS.iter().map(|info| serde_json::json!({
+        "name": info.name,
+        "args": info.args,
+        "usage": info.usage(),
+        "mods": mods::allows_command(info.name)
+    })).collect();
+    serde_json::to_string_pretty(&commands).unwrap_or_default()
+}
@@ -0,0 +1,155 @@
+use std::{fmt, str::FromStr};
+
+use winit::{event::WindowEvent, window::Window};
+
+use crate::renderer::window_to_screen;
+use crate::ui::{self, UiBatch};
+
+// The default game cursor. '#' is the outline and '.' the fill.
+const ARROW: [&str; 12] = [
+    "#       ",
+    "##      ",
+    "#.#     ",
+    "#..#    ",
+    "#...#   ",
+    "#....#  ",
+    "#.....# ",
+    "#......#",
+    "#...####",
+    "#.#..#  ",
+    "## #..# ",
+    "    ##  ",
+];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CursorStyle {
+    // The normal hardware cursor.
+    #[default]
+    System,
+    Hidden,
+    // The hardware cursor is hidden and the game draws its own, e.g. in menus.
+    Sprite
+}
+
+impl CursorStyle {
+    pub const ALL: [CursorStyle; 3] = [CursorStyle::System, CursorStyle::Hidden, CursorStyle::Sprite];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CursorStyle::System => "system",
+            CursorStyle::Hidden => "hidden",
+            CursorStyle::Sprite => "sprite"
+        }
+    }
+}
+
+impl fmt::Display for CursorStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CursorStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CursorStyle::ALL.into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown cursor style \"{}\", expected system, hidden or sprite", s.trim()))
+    }
+}
+
+// A small image for the game cursor. Each pixel is drawn as a solid block, so keep it small.
+#[derive(Clone, Debug)]
+pub struct CursorSprite {
+    image: image::RgbaImage,
+    // The pixel that's at the cursor position.
+    hotspot: (u32, u32),
+    scale: f32
+}
+
+impl Default for CursorSprite {
+    fn default() -> Self {
+        Self::arrow()
+    }
+}
+
+impl CursorSprite {
+    pub fn new(image: image::RgbaImage, hotspot: (u32, u32), scale: f32) -> Self {
+        Self {
+            image,
+            hotspot,
+            scale
+        }
+    }
+
+    pub fn arrow() -> Self {
+        Self::new(ui::pixel_sprite(&ARROW, &[('#', [0.0, 0.0, 0.0, 1.0]), ('.', ui::WHITE)]), (0, 0), 2.0)
+    }
+
+    fn build(&self, batch: &mut UiBatch, x: f32, y: f32) {
+        let origin_x = x - self.hotspot.0 as f32 * self.scale;
+        let origin_y = y - self.hotspot.1 as f32 * self.scale;
+        batch.sprite(origin_x, origin_y, self.scale, &self.image, 1.0);
+    }
+}
+
+// Controls what the mouse cursor looks like over the game.
+pub struct Cursor {
+    style: CursorStyle,
+    sprite: CursorSprite,
+
+    // Where the cursor is on the virtual screen, if it's over it.
+    position: Option<(f32, f32)>
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cursor {
+    pub fn new() -> Self {
+        Self {
+            style: CursorStyle::System,
+            sprite: CursorSprite::default(),
+            position: None
+        }
+    }
+
+    pub fn style(&self) -> CursorStyle {
+        self.style
+    }
+
+    pub fn set_style(&mut self, window: &Window, style: CursorStyle) {
+        self.style = style;
+        window.set_cursor_visible(style == CursorStyle::System);
+    }
+
+    pub fn set_sprite(&mut self, sprite: CursorSprite) {
+        self.sprite = sprite;
+    }
+
+    // Where the cursor is on the virtual screen, if it's over it.
+    pub fn position(&self) -> Option<(f32, f32)> {
+        self.position
+    }
+
+    pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.position = window_to_screen(window.inner_size(), *position);
+            },
+            WindowEvent::CursorLeft { .. } => self.position = None,
+            _ => {}
+        }
+    }
+
+    // Draw the game cursor, if it's being used. Do this last so it's on top of everything.
+    pub fn build(&self, batch: &mut UiBatch) {
+        if let (CursorStyle::Sprite, Some((x, y))) = (self.style, self.position) {
+            self.sprite.build(batch, x, y);
+        }
+    }
+}
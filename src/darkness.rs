@@ -0,0 +1,109 @@
+// Dark dungeons, where everything outside a circle of light round the player is dark. Fields
+// with `darkness` in their descriptor have it, saying how dark it gets and how far the light
+// reaches. The circle's drawn in post processing, see post_process.wgsl, so it covers the
+// models and background but not the menus.
+//
+// Light from a skill or item, the "light" event (see field_skill.rs), widens the circle for a
+// while. It grows out and shrinks back rather than jumping.
+
+use std::time::Duration;
+
+use crate::renderer::PostProcessSettings;
+use crate::world::World;
+
+// How much further the light reaches with a light skill or item, in virtual screen pixels, and
+// for how long.
+pub const LIGHT_BOOST: f32 = 120.0;
+pub const LIGHT_BOOST_TIME: Duration = Duration::from_secs(60);
+// How fast the circle grows and shrinks, in pixels a second.
+const RADIUS_SPEED: f32 = 160.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldDarkness {
+    // How dark it is outside the light, from 0 for not at all to 1 for black.
+    pub strength: f32,
+    // How far the light reaches from the player, in virtual screen pixels.
+    pub radius: f32
+}
+
+// Resource for the darkness in the field the player's in. It's only there in dark fields.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Darkness {
+    field: FieldDarkness,
+    // How far the light reaches now, on its way to target_radius.
+    radius: f32,
+    boost: f32,
+    boost_left: Duration
+}
+
+impl Darkness {
+    pub fn new(field: FieldDarkness) -> Self {
+        Self { field, radius: field.radius, boost: 0.0, boost_left: Duration::ZERO }
+    }
+
+    // Light the way, reaching `boost` further for `time`. A bigger or longer one that's already
+    // going is kept.
+    pub fn light(&mut self, boost: f32, time: Duration) {
+        self.boost = self.boost.max(boost);
+        self.boost_left = self.boost_left.max(time);
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    // How far the light should reach, with any boost.
+    pub fn target_radius(&self) -> f32 {
+        self.field.radius + self.boost
+    }
+
+    // How long the boost has left.
+    pub fn boost_left(&self) -> Duration {
+        self.boost_left
+    }
+
+    // Count down the boost and move the circle towards where it should be. Returns whether it
+    // moved, so there's something new to draw.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        self.boost_left = self.boost_left.saturating_sub(delta);
+        if self.boost_left.is_zero() {
+            self.boost = 0.0;
+        }
+        let target = self.target_radius();
+        if self.radius == target {
+            return false;
+        }
+        let step = RADIUS_SPEED * delta.as_secs_f32();
+        self.radius = if self.radius < target { (self.radius + step).min(target) } else { (self.radius - step).max(target) };
+        true
+    }
+
+    // Put the circle round the player, who's at `player` on the screen. All of it's dark if
+    // they're off the screen.
+    pub fn apply(&self, settings: &mut PostProcessSettings, player: Option<(f32, f32)>) {
+        settings.darkness = self.field.strength;
+        match player {
+            Some((x, y)) => {
+                settings.light_position = [x, y];
+                settings.light_radius = self.radius;
+            },
+            None => settings.light_radius = 0.0
+        }
+    }
+}
+
+// Set the darkness up for the field that's just been entered, taking it away if it isn't dark.
+pub fn enter_field(world: &mut World, darkness: Option<FieldDarkness>, settings: &mut PostProcessSettings) {
+    match darkness {
+        Some(darkness) => world.insert_resource(Darkness::new(darkness)),
+        None => {
+            world.remove_resource::<Darkness>();
+            settings.darkness = 0.0;
+        }
+    }
+}
+
+// Move the world's darkness on. Returns whether the light changed size.
+pub fn update_darkness(world: &mut World, delta: Duration) -> bool {
+    world.resource_mut::<Darkness>().is_some_and(|darkness| darkness.update(delta))
+}
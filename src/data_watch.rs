@@ -0,0 +1,72 @@
+// Notices when game data files are edited, so they can be read again while the game's running
+// and numbers can be balanced without restarting. Files are checked every so often rather than
+// watched, which is plenty for a handful of small files.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::assets::AssetServer;
+
+// The data files that can be reloaded.
+pub const DATA_FILES: [&str; 11] = [
+    "data/items.cfg",
+    "data/loot.cfg",
+    "data/formations.cfg",
+    "data/encounters.cfg",
+    "data/battle_scripts.cfg",
+    "data/battle_scenes.cfg",
+    "data/combat.cfg",
+    "data/dialogue.cfg",
+    "data/combos.cfg",
+    "data/summons.cfg",
+    "data/pools.cfg"
+];
+
+// How often the files are checked.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Default)]
+pub struct DataWatcher {
+    files: Vec<(String, Option<SystemTime>)>,
+    last_poll: Option<Instant>,
+    // Changed since they were last taken.
+    changed: Vec<String>
+}
+
+impl DataWatcher {
+    // Start watching the files as they are now.
+    pub fn new(assets: &AssetServer, paths: &[&str]) -> Self {
+        Self {
+            files: paths.iter().map(|path| (path.to_string(), assets.modified(path))).collect(),
+            last_poll: None,
+            changed: Vec::new()
+        }
+    }
+
+    // Check the files again if it's been long enough since the last time.
+    pub fn update(&mut self, now: Instant, assets: &AssetServer) {
+        if self.last_poll.is_some_and(|last| now.saturating_duration_since(last) < POLL_INTERVAL) {
+            return;
+        }
+        self.last_poll = Some(now);
+        self.poll(assets);
+    }
+
+    // Check the files now.
+    pub fn poll(&mut self, assets: &AssetServer) {
+        for (path, modified) in &mut self.files {
+            let now = assets.modified(path);
+            if now != *modified {
+                *modified = now;
+                if !self.changed.contains(path) {
+                    self.changed.push(path.clone());
+                }
+            }
+        }
+    }
+
+    // The files that have changed, if it's a good time to reload them. Otherwise they're kept
+    // until it is, like when a battle's over, so nothing changes under it.
+    pub fn take_changed(&mut self, can_reload: bool) -> Vec<String> {
+        if can_reload { std::mem::take(&mut self.changed) } else { Vec::new() }
+    }
+}
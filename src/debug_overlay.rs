@@ -0,0 +1,169 @@
+use std::{collections::VecDeque, time::Duration};
+
+use instant::Instant;
+
+use crate::renderer::RenderStats;
+use crate::ui::{UiBatch, Color, WHITE};
+
+// How many frames of history to average the timings over.
+const FRAME_HISTORY: usize = 120;
+
+const TEXT_SCALE: f32 = 2.0;
+const PADDING: f32 = 6.0;
+const BACKGROUND_COLOR: Color = [0.0, 0.0, 0.0, 0.6];
+const WARNING_COLOR: Color = [1.0, 0.4, 0.3, 1.0];
+
+// Anything slower than this is highlighted in the overlay.
+const TARGET_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+// Keeps track of how long recent frames took.
+pub struct FrameStats {
+    last_frame_start: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    cpu_times: VecDeque<Duration>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            last_frame_start: None,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            cpu_times: VecDeque::with_capacity(FRAME_HISTORY),
+        }
+    }
+
+    // Call at the start of each frame. Returns the time since the previous frame started.
+    pub fn begin_frame(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = self.last_frame_start.map(|last| now - last).unwrap_or_default();
+        self.last_frame_start = Some(now);
+
+        if !delta.is_zero() {
+            Self::push(&mut self.frame_times, delta);
+        }
+
+        delta
+    }
+
+    // Call once the CPU side of the frame is done, i.e. everything has been submitted.
+    pub fn end_frame(&mut self) {
+        if let Some(start) = self.last_frame_start {
+            Self::push(&mut self.cpu_times, start.elapsed());
+        }
+    }
+
+    fn push(history: &mut VecDeque<Duration>, value: Duration) {
+        if history.len() == FRAME_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(value);
+    }
+
+    fn average(history: &VecDeque<Duration>) -> Duration {
+        if history.is_empty() {
+            return Duration::ZERO;
+        }
+        history.iter().sum::<Duration>() / history.len() as u32
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        Self::average(&self.frame_times)
+    }
+
+    pub fn worst_frame_time(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn average_cpu_time(&self) -> Duration {
+        Self::average(&self.cpu_times)
+    }
+
+    pub fn fps(&self) -> f32 {
+        let average = self.average_frame_time().as_secs_f32();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+}
+
+// Anything else the overlay should show that the renderer doesn't know about.
+pub struct DebugInfo<'a> {
+    pub field: &'a str,
+    pub state: &'a str,
+    pub entities: usize,
+}
+
+// A toggleable panel in the corner of the screen with performance numbers.
+pub struct DebugOverlay {
+    visible: bool
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // Add the overlay to a UI batch, if it's visible.
+    pub fn build(&self, batch: &mut UiBatch, frame_stats: &FrameStats, render_stats: &RenderStats, info: &DebugInfo) {
+        if !self.visible {
+            return;
+        }
+
+        let mut lines: Vec<(String, Color)> = Vec::new();
+
+        let frame_time = frame_stats.average_frame_time();
+        let frame_color = if frame_time > TARGET_FRAME_TIME { WARNING_COLOR } else { WHITE };
+        lines.push((format!("FPS {:.0}", frame_stats.fps()), frame_color));
+        lines.push((format!("Frame {:.2}ms (worst {:.2}ms)", millis(frame_time), millis(frame_stats.worst_frame_time())), frame_color));
+        lines.push((format!("CPU {:.2}ms", millis(frame_stats.average_cpu_time())), WHITE));
+
+        for (name, time) in &render_stats.pass_timings {
+            lines.push((format!("  {} {:.2}ms", name, millis(*time)), WHITE));
+        }
+
+        if !render_stats.gpu_pass_timings.is_empty() {
+            let total: Duration = render_stats.gpu_pass_timings.iter().map(|(_, time)| *time).sum();
+            lines.push((format!("GPU {:.2}ms", millis(total)), WHITE));
+            for (name, time) in &render_stats.gpu_pass_timings {
+                lines.push((format!("  {} {:.2}ms", name, millis(*time)), WHITE));
+            }
+        }
+
+        lines.push((format!("Entities {}", info.entities), WHITE));
+        lines.push((format!("Draw calls {}", render_stats.draw_calls), WHITE));
+        lines.push((format!("Models {} ({} culled), triangles {}", render_stats.model_instances, render_stats.models_culled, render_stats.triangles), WHITE));
+        lines.push((format!("Textures {:.1}MB", render_stats.texture_bytes as f64 / (1024.0 * 1024.0)), WHITE));
+        lines.push((format!("Field {}", info.field), WHITE));
+        lines.push((format!("State {}", info.state), WHITE));
+
+        // Background panel sized to fit the text.
+        let text = lines.iter().map(|(line, _)| line.as_str()).collect::<Vec<_>>().join("\n");
+        let (width, height) = UiBatch::measure_text(TEXT_SCALE, &text);
+        batch.rect(0.0, 0.0, width + PADDING * 2.0, height + PADDING * 2.0, BACKGROUND_COLOR);
+
+        let line_height = UiBatch::line_height(TEXT_SCALE);
+        for (i, (line, color)) in lines.iter().enumerate() {
+            batch.text(PADDING, PADDING + i as f32 * line_height, TEXT_SCALE, line, *color);
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
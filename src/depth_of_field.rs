@@ -0,0 +1,99 @@
+use std::{fmt, str::FromStr};
+
+use cgmath::{InnerSpace, Point3};
+
+use crate::camera::Camera;
+
+// How many samples the blur takes for each pixel. More is smoother and slower. A player
+// setting, since it's the most expensive part of post processing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DofQuality {
+    // Never blur, even when a scene asks for it.
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High
+}
+
+impl DofQuality {
+    pub const ALL: [DofQuality; 4] = [DofQuality::Off, DofQuality::Low, DofQuality::Medium, DofQuality::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DofQuality::Off => "off",
+            DofQuality::Low => "low",
+            DofQuality::Medium => "medium",
+            DofQuality::High => "high"
+        }
+    }
+
+    pub fn samples(&self) -> u32 {
+        match self {
+            DofQuality::Off => 0,
+            DofQuality::Low => 8,
+            DofQuality::Medium => 16,
+            DofQuality::High => 32
+        }
+    }
+}
+
+impl fmt::Display for DofQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DofQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DofQuality::ALL.into_iter()
+            .find(|quality| quality.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown depth of field quality \"{}\", expected off, low, medium or high", s.trim()))
+    }
+}
+
+// Blurs whatever's nearer or further than the focus distance, using the models' depth buffer.
+// The background has no depth, so it counts as being as far away as anything can be. Used in
+// battles and photo mode to make the combatants stand out from the backdrop. Distances go
+// along the way the camera's looking.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthOfField {
+    pub focus_distance: f32,
+    // How far either side of the focus distance is still completely sharp.
+    pub focus_range: f32,
+    // How much further it takes to reach the full blur.
+    pub falloff: f32,
+    // The blur's radius at its strongest, in virtual screen pixels.
+    pub max_blur: f32
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.0,
+            focus_range: 2.0,
+            falloff: 10.0,
+            max_blur: 6.0
+        }
+    }
+}
+
+impl DepthOfField {
+    // Focus on a point, like the middle of the battlefield.
+    pub fn focused_on(camera: &Camera, point: Point3<f32>) -> Self {
+        let forward = (camera.target - camera.eye).normalize();
+        Self {
+            focus_distance: (point - camera.eye).dot(forward).max(0.0),
+            ..Self::default()
+        }
+    }
+
+    // How blurred something this far from the camera is, in pixels. The same sum the post
+    // process shader does.
+    pub fn blur_radius(&self, distance: f32) -> f32 {
+        let out_of_focus = (distance - self.focus_distance).abs() - self.focus_range;
+        (out_of_focus / self.falloff.max(0.0001)).clamp(0.0, 1.0) * self.max_blur
+    }
+}
@@ -0,0 +1,156 @@
+// Which conversation an NPC has, so they can say something new as the story goes on. Each NPC
+// has a list of dialogue trees in data/dialogue.cfg, each with the conditions for it, and
+// talking to them runs the first one whose conditions all hold:
+//
+// [Shopkeeper]
+// tree = shopkeeper.ogre_beaten
+// flag = cave.ogre_beaten
+// stage = main_quest 3-4
+// member = Tobin
+//
+// tree = shopkeeper.night
+// time = 20:00-06:00
+//
+// tree = shopkeeper.hello
+//
+// "tree = id" starts one, and the lines after it are its conditions. "flag = name" needs a flag
+// to be set and "flag = !name" needs it not to be. "stage = flag min-max" needs the flag's value
+// to be in a range, as quests count their stages in a flag. "member = name" needs someone to be
+// in the party and "time = hh:mm-hh:mm" needs it to be that time of day, which can go past
+// midnight. Trees without conditions always match, so the last one's usually what they say
+// the rest of the time.
+
+use crate::flags::GameFlags;
+use crate::game_clock;
+use crate::party::Party;
+
+// What the conditions are checked against.
+#[derive(Clone, Copy, Debug)]
+pub struct DialogueContext<'a> {
+    pub flags: &'a GameFlags,
+    pub party: &'a Party,
+    // Minutes since midnight.
+    pub minute: u32
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DialogueCondition {
+    Flag { flag: String, set: bool },
+    // The flag's value, from min to max, both included.
+    Stage { flag: String, min: i32, max: i32 },
+    Member(String),
+    // From the first time up to the second, in minutes since midnight.
+    Time { from: u32, to: u32 }
+}
+
+impl DialogueCondition {
+    fn parse(key: &str, value: &str) -> Result<Self, String> {
+        match key {
+            "flag" => Ok(match value.strip_prefix('!') {
+                Some(flag) => DialogueCondition::Flag { flag: flag.trim().to_string(), set: false },
+                None => DialogueCondition::Flag { flag: value.to_string(), set: true }
+            }),
+            "stage" => {
+                let (flag, range) = value.split_once(' ').ok_or_else(|| format!("expected \"stage = flag min-max\", not \"{}\"", value))?;
+                let range = range.trim();
+                let bad = || format!("bad stage \"{}\", expected a number or a range like 2-3", range);
+                let (min, max) = range.split_once('-').unwrap_or((range, range));
+                let (min, max) = (min.trim().parse().map_err(|_| bad())?, max.trim().parse().map_err(|_| bad())?);
+                if min > max {
+                    return Err(bad());
+                }
+                Ok(DialogueCondition::Stage { flag: flag.to_string(), min, max })
+            },
+            "member" => Ok(DialogueCondition::Member(value.to_string())),
+            "time" => {
+                let bad = || format!("bad time \"{}\", expected a range like 20:00-06:00", value);
+                let (from, to) = value.split_once('-').ok_or_else(bad)?;
+                Ok(DialogueCondition::Time {
+                    from: game_clock::parse_time(from.trim()).ok_or_else(bad)?,
+                    to: game_clock::parse_time(to.trim()).ok_or_else(bad)?
+                })
+            },
+            _ => Err(format!("unknown key \"{}\", expected tree, flag, stage, member or time", key))
+        }
+    }
+
+    pub fn holds(&self, context: &DialogueContext) -> bool {
+        match self {
+            DialogueCondition::Flag { flag, set } => context.flags.is_set(flag) == *set,
+            DialogueCondition::Stage { flag, min, max } => (*min..=*max).contains(&context.flags.get(flag)),
+            DialogueCondition::Member(name) => context.party.members.iter().any(|member| member.name.eq_ignore_ascii_case(name)),
+            // Ranges that go past midnight are the two ends of the day.
+            DialogueCondition::Time { from, to } if from <= to => (*from..*to).contains(&context.minute),
+            DialogueCondition::Time { from, to } => context.minute >= *from || context.minute < *to
+        }
+    }
+}
+
+// A dialogue tree and when it's the one to run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DialogueChoice {
+    pub tree: String,
+    pub conditions: Vec<DialogueCondition>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NpcDialogue {
+    pub npc: String,
+    // In the order they're tried.
+    pub choices: Vec<DialogueChoice>
+}
+
+impl NpcDialogue {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut npcs: Vec<NpcDialogue> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(npc) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                npcs.push(NpcDialogue { npc: npc.trim().to_string(), choices: Vec::new() });
+                continue;
+            }
+
+            let npc = npcs.last_mut().ok_or_else(|| format!("Line {}: expected an [NPC name] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            if key == "tree" {
+                npc.choices.push(DialogueChoice { tree: value.to_string(), conditions: Vec::new() });
+                continue;
+            }
+            let condition = DialogueCondition::parse(key, value).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+            match npc.choices.last_mut() {
+                Some(choice) => choice.conditions.push(condition),
+                None => return Err(format!("Line {}: expected a \"tree\" before its conditions", number + 1))
+            }
+        }
+        Ok(npcs)
+    }
+
+    // The tree to run when they're talked to, or None if nothing matches.
+    pub fn choose(&self, context: &DialogueContext) -> Option<&str> {
+        self.choices.iter()
+            .find(|choice| choice.conditions.iter().all(|condition| condition.holds(context)))
+            .map(|choice| choice.tree.as_str())
+    }
+}
+
+// Resource with what every NPC has to say.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NpcDialogues(pub Vec<NpcDialogue>);
+
+impl NpcDialogues {
+    pub fn get(&self, npc: &str) -> Option<&NpcDialogue> {
+        self.0.iter().find(|dialogue| dialogue.npc == npc)
+    }
+
+    // The tree to run for an NPC, or None if they've nothing to say.
+    pub fn choose(&self, npc: &str, context: &DialogueContext) -> Option<&str> {
+        self.get(npc)?.choose(context)
+    }
+}
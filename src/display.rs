@@ -0,0 +1,176 @@
+use std::{fmt, str::FromStr};
+
+use winit::{dpi::{LogicalSize, PhysicalPosition}, event_loop::EventLoopWindowTarget, monitor::{MonitorHandle, VideoMode}, window::{Fullscreen, Icon, Window, WindowBuilder}};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::logging::targets;
+
+pub const GAME_TITLE: &str = "PS RPG Engine";
+// The window and taskbar icon. Mods can replace it like any other asset.
+pub const WINDOW_ICON: &str = "icon.png";
+
+// Show where the player is in the title bar, e.g. "PS RPG Engine - Test Field".
+pub fn set_location_title(window: &Window, location: Option<&str>) {
+    match location {
+        Some(location) => window.set_title(&format!("{} - {}", GAME_TITLE, location)),
+        None => window.set_title(GAME_TITLE)
+    }
+}
+
+// Load an image to use as the window and taskbar icon.
+pub async fn load_window_icon(assets: &AssetServer, path: &str) -> Result<Icon, AssetError> {
+    let image = assets.load_image(path).await?;
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height)
+        .map_err(|e| AssetError::Decode(path.to_string(), e.to_string()))
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    // Fullscreen at the desktop resolution. Quick to switch in and out of.
+    Borderless,
+    // Takes over the monitor and changes its resolution.
+    Exclusive
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [WindowMode::Windowed, WindowMode::Borderless, WindowMode::Exclusive];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Exclusive => "exclusive"
+        }
+    }
+}
+
+impl fmt::Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WindowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WindowMode::ALL.into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown window mode \"{}\", expected windowed, borderless or exclusive", s.trim()))
+    }
+}
+
+// Find a monitor by name, falling back to the one the window is on and then the primary one.
+pub fn find_monitor(window: &Window, name: Option<&str>) -> Option<MonitorHandle> {
+    name.and_then(|name| window.available_monitors().find(|monitor| monitor.name().as_deref() == Some(name)))
+        .or_else(|| window.current_monitor())
+        .or_else(|| window.primary_monitor())
+}
+
+// The biggest resolution the monitor supports, at its highest refresh rate.
+fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|mode| {
+        let size = mode.size();
+        (size.width * size.height, mode.refresh_rate_millihertz(), mode.bit_depth())
+    })
+}
+
+// Switch the window to a mode, on the named monitor if it's still plugged in.
+// Returns the monitor the window ended up on.
+pub fn set_window_mode(window: &Window, mode: WindowMode, monitor_name: Option<&str>) -> Option<MonitorHandle> {
+    let monitor = find_monitor(window, monitor_name);
+
+    let fullscreen = match mode {
+        WindowMode::Windowed => None,
+        WindowMode::Borderless => Some(Fullscreen::Borderless(monitor.clone())),
+        WindowMode::Exclusive => match monitor.as_ref().and_then(best_video_mode) {
+            Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+            None => {
+                tracing::warn!(target: targets::ENGINE, "No exclusive video modes available, using borderless instead.");
+                Some(Fullscreen::Borderless(monitor.clone()))
+            }
+        }
+    };
+    window.set_fullscreen(fullscreen);
+
+    tracing::info!(target: targets::ENGINE, "Window mode set to {} on {}.", mode,
+        monitor.as_ref().and_then(|monitor| monitor.name()).unwrap_or_else(|| "unknown monitor".to_string()));
+    monitor
+}
+
+// Where a window was on the desktop. The position is in desktop pixels so it picks out a
+// monitor, the size is logical so the window looks the same on monitors with different DPI.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindowPlacement {
+    pub position: PhysicalPosition<i32>,
+    pub size: LogicalSize<f64>
+}
+
+impl WindowPlacement {
+    // Only meaningful for a window that isn't fullscreen.
+    pub fn of(window: &Window) -> Option<Self> {
+        if window.fullscreen().is_some() {
+            return None;
+        }
+
+        let position = window.outer_position().ok()?;
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            // Minimised.
+            return None;
+        }
+
+        Some(Self {
+            position,
+            size: size.to_logical(window.scale_factor())
+        })
+    }
+}
+
+fn monitor_contains(monitor: &MonitorHandle, point: PhysicalPosition<i32>) -> bool {
+    let origin = monitor.position();
+    let size = monitor.size();
+    point.x >= origin.x && point.y >= origin.y
+        && point.x < origin.x + size.width as i32 && point.y < origin.y + size.height as i32
+}
+
+// Put a new window back where it was last time. If the monitor it was on has gone, the
+// position is left to the OS so the window doesn't end up off screen.
+pub fn restore_placement<T>(builder: WindowBuilder, event_loop: &EventLoopWindowTarget<T>, placement: Option<&WindowPlacement>) -> WindowBuilder {
+    let placement = match placement {
+        Some(placement) => placement,
+        None => return builder
+    };
+
+    let builder = if placement.size.width > 0.0 && placement.size.height > 0.0 {
+        builder.with_inner_size(placement.size)
+    } else {
+        builder
+    };
+    if event_loop.available_monitors().any(|monitor| monitor_contains(&monitor, placement.position)) {
+        builder.with_position(placement.position)
+    } else {
+        tracing::info!(target: targets::ENGINE, "The window's last monitor isn't connected, letting the OS place it.");
+        builder
+    }
+}
+
+// e.g. "DELL U2415 (1920x1200 at 0, 0, 1x)"
+pub fn describe_monitor(monitor: &MonitorHandle) -> String {
+    let size = monitor.size();
+    let position = monitor.position();
+    format!("{} ({}x{} at {}, {}, {}x)", monitor.name().unwrap_or_else(|| "Unnamed".to_string()),
+        size.width, size.height, position.x, position.y, monitor.scale_factor())
+}
+
+// Pick a monitor by its index in the list of monitors or by its name.
+pub fn select_monitor(window: &Window, index_or_name: &str) -> Option<MonitorHandle> {
+    let mut monitors = window.available_monitors();
+    match index_or_name.parse::<usize>() {
+        Ok(index) => monitors.nth(index),
+        Err(_) => monitors.find(|monitor| monitor.name().as_deref() == Some(index_or_name))
+    }
+}
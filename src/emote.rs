@@ -0,0 +1,200 @@
+// Little speech bubbles over characters' heads with a symbol in, like an exclamation mark when
+// an NPC spots the player or a heart when someone's smitten. They're drawn as sprites, facing
+// the camera wherever it is, and go after a while. Scripts show them with the "emote" command,
+// and NPCs show one by themselves when the player walks up to them.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::schedule::ScheduledNpc;
+use crate::transform::Transform;
+use crate::ui::{self, Color, UiBatch};
+use crate::vision::VisionCone;
+use crate::world::{Entity, World};
+
+// How long an emote shows for if it isn't told.
+pub const DEFAULT_DURATION: Duration = Duration::from_millis(1500);
+// How close the player has to get for an NPC to spot them, in metres, and in pixels for 2D
+// fields.
+pub const SPOT_RADIUS: f32 = 3.0;
+pub const SPOT_RADIUS_2D: f32 = 48.0;
+// How far above someone's position the bubble goes, in metres. 2D fields don't have a height.
+pub const HEAD_HEIGHT: f32 = 1.9;
+
+const SCALE: f32 = 2.0;
+// Gap between the head and the bottom of the bubble's tail, in screen pixels.
+const GAP: f32 = 4.0;
+// How long it takes to pop up, and to fade at the end.
+const POP_TIME: Duration = Duration::from_millis(120);
+const FADE_TIME: Duration = Duration::from_millis(250);
+
+// The bubble, with ten by seven of space in the middle for the symbol.
+const BUBBLE_TOP: &str = " ########## ";
+const BUBBLE_BOTTOM: [&str; 3] = [
+    " ###..##### ",
+    "   #.#      ",
+    "   ##       "
+];
+
+const EXCLAMATION: [&str; 7] = ["....xx....", "....xx....", "....xx....", "....xx....", "..........", "....xx....", ".........."];
+const QUESTION: [&str; 7] = ["...xxxx...", "..xx..xx..", "......xx..", ".....xx...", "....xx....", "..........", "....xx...."];
+const HEART: [&str; 7] = ["..xx..xx..", ".xxxxxxxx.", ".xxxxxxxx.", "..xxxxxx..", "...xxxx...", "....xx....", ".........."];
+const SWEAT: [&str; 7] = [".....x....", "....xxx...", "...xxxxx..", "..xxxxxxx.", "..xxxxxxx.", "...xxxxx..", "....xxx..."];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmoteKind {
+    Exclamation,
+    Question,
+    Heart,
+    Sweat
+}
+
+impl EmoteKind {
+    pub const ALL: [EmoteKind; 4] = [EmoteKind::Exclamation, EmoteKind::Question, EmoteKind::Heart, EmoteKind::Sweat];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmoteKind::Exclamation => "exclamation",
+            EmoteKind::Question => "question",
+            EmoteKind::Heart => "heart",
+            EmoteKind::Sweat => "sweat"
+        }
+    }
+
+    fn symbol(&self) -> (&'static [&'static str; 7], Color) {
+        match self {
+            EmoteKind::Exclamation => (&EXCLAMATION, [0.85, 0.1, 0.1, 1.0]),
+            EmoteKind::Question => (&QUESTION, [0.1, 0.3, 0.85, 1.0]),
+            EmoteKind::Heart => (&HEART, [0.95, 0.3, 0.55, 1.0]),
+            EmoteKind::Sweat => (&SWEAT, [0.35, 0.7, 0.95, 1.0])
+        }
+    }
+
+    // The bubble with the symbol in it.
+    pub fn sprite(&self) -> image::RgbaImage {
+        let (symbol, color) = self.symbol();
+        let middle: Vec<String> = symbol.iter().map(|row| format!("#{}#", row)).collect();
+        let rows: Vec<&str> = std::iter::once(BUBBLE_TOP)
+            .chain(middle.iter().map(String::as_str))
+            .chain(BUBBLE_BOTTOM)
+            .collect();
+        ui::pixel_sprite(&rows, &[('#', [0.0, 0.0, 0.0, 1.0]), ('.', ui::WHITE), ('x', color)])
+    }
+}
+
+impl fmt::Display for EmoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EmoteKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EmoteKind::ALL.into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown emote \"{}\", expected exclamation, question, heart or sweat", s.trim()))
+    }
+}
+
+// Component for an emote showing over an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emote {
+    pub kind: EmoteKind,
+    pub duration: Duration,
+    pub elapsed: Duration
+}
+
+impl Emote {
+    pub fn new(kind: EmoteKind, duration: Duration) -> Self {
+        Self { kind, duration, elapsed: Duration::ZERO }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    // How big it's drawn and how see through, as it pops up and fades away.
+    pub fn size_and_alpha(&self) -> (f32, f32) {
+        let size = (self.elapsed.as_secs_f32() / POP_TIME.as_secs_f32()).min(1.0);
+        let left = self.duration.saturating_sub(self.elapsed);
+        let alpha = (left.as_secs_f32() / FADE_TIME.as_secs_f32()).min(1.0);
+        (size, alpha)
+    }
+}
+
+// Marks an NPC that's spotted the player, so they don't keep doing it while the player's
+// still close.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpottedPlayer;
+
+// Show an emote over an entity, replacing any that's already there.
+pub fn show_emote(world: &mut World, entity: Entity, kind: EmoteKind, duration: Duration) {
+    world.insert(entity, Emote::new(kind, duration));
+}
+
+// Move every emote on, taking away the ones that are done. Returns true while any are showing.
+pub fn update_emotes(world: &mut World, delta: Duration) -> bool {
+    let mut finished = Vec::new();
+    for (entity, emote) in world.query_mut::<Emote>() {
+        emote.elapsed += delta;
+        if emote.is_finished() {
+            finished.push(entity);
+        }
+    }
+    for entity in finished {
+        world.remove::<Emote>(entity);
+    }
+    world.query::<Emote>().next().is_some()
+}
+
+// NPCs who the player's just come within `radius` of show an exclamation. They won't again
+// until the player's gone and come back. Guards are left to their vision cones. Returns who
+// spotted them.
+pub fn spot_player(world: &mut World, player: Entity, radius: f32) -> Vec<Entity> {
+    let position = match world.get::<Transform>(player) {
+        Some(transform) => transform.position,
+        None => return Vec::new()
+    };
+    let npcs: Vec<(Entity, Vector3<f32>)> = world.query::<ScheduledNpc>()
+        .filter(|(entity, _)| !world.has::<VisionCone>(*entity))
+        .filter_map(|(entity, _)| Some((entity, world.get::<Transform>(entity)?.position)))
+        .collect();
+
+    let mut spotted = Vec::new();
+    for (npc, npc_position) in npcs {
+        let close = (npc_position - position).magnitude() <= radius;
+        match (close, world.has::<SpottedPlayer>(npc)) {
+            (true, false) => {
+                world.insert(npc, SpottedPlayer);
+                show_emote(world, npc, EmoteKind::Exclamation, DEFAULT_DURATION);
+                spotted.push(npc);
+            },
+            (false, true) => {
+                world.remove::<SpottedPlayer>(npc);
+            },
+            _ => {}
+        }
+    }
+    spotted
+}
+
+// Draw each emote above its entity. `to_screen` is where a point in the world is on the
+// screen, through the field's camera or its 2D scroll.
+pub fn build_emotes(world: &World, batch: &mut UiBatch, to_screen: impl Fn(Vector3<f32>) -> Option<(f32, f32)>) {
+    for (entity, emote) in world.query::<Emote>() {
+        let head = match world.get::<Transform>(entity).and_then(|transform| to_screen(transform.position + Vector3::unit_y() * HEAD_HEIGHT)) {
+            Some(head) => head,
+            None => continue
+        };
+        let (size, alpha) = emote.size_and_alpha();
+        let sprite = emote.kind.sprite();
+        // Pops up from the tail, so it looks like it's coming out of their head.
+        let scale = SCALE * size;
+        let x = head.0 - sprite.width() as f32 * scale / 2.0;
+        let y = head.1 - GAP - sprite.height() as f32 * scale;
+        batch.sprite(x, y, scale, &sprite, alpha);
+    }
+}
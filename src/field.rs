@@ -1,5 +1,612 @@
+use std::collections::HashMap;
+
+use cgmath::{Point3, Vector3};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::attachment::ModelSockets;
+use crate::battle_scene::BattleScene;
+use crate::darkness::FieldDarkness;
+use crate::field_camera::{self, FieldCamera, FieldCameras};
+use crate::field_skill::FieldEventResponse;
+use crate::flags::GameFlags;
+use crate::logging::targets;
+use crate::model::{ModelData, ModelId, ModelInstance, MorphWeights};
+use crate::mixer::{FieldAudio, Mixer};
+use crate::mover::{self, FieldMover, Mover, MoverTrack};
+use crate::pickup::FieldPickup;
+use crate::prop_state::{PropState, PropStates};
+use crate::puzzle::FieldPuzzle;
+use crate::music::{MusicPlayer, MusicTrack};
+use crate::spring_bone::SpringBones;
+use crate::tilemap::Tilemap;
+use crate::renderer::Renderer;
+use crate::transform::Transform;
+use crate::trap::FieldTrap;
+use crate::vision::VisionBlocker;
+use crate::water::{Ripples, Swimmer, WaterRegion};
+use crate::world::{World, Entity, Name};
+
+// What the walk mesh in a field's glTF file has to be called, the mesh or the node it's on.
+pub const WALKMESH_NAME: &str = "walkmesh";
+
+// A model placed in a field, like a chair or a barrel.
+#[derive(Clone, Debug, Default)]
+pub struct FieldProp {
+    // What it's called, for scripts and puzzles. Empty names it after its flag or model.
+    pub name: String,
+    pub model: String,
+    pub transform: Transform,
+    // Props with states show whichever the flag says instead of `model`, see prop_state.rs.
+    pub flag: String,
+    pub states: Vec<PropState>
+}
+
+impl FieldProp {
+    // Its name, or `default` if it hasn't got one.
+    pub fn name_or(&self, default: &str) -> String {
+        if self.name.is_empty() { default.to_string() } else { self.name.clone() }
+    }
+}
+
+// A piece of the background that's in front of the walk area, like a pillar or a railing.
+// It's drawn again over the models wherever it's nearer the camera than they are, so
+// characters go behind it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldOccluder {
+    // The piece cut out of the background art, see through everywhere else.
+    pub image: String,
+    // Where it goes on the screen, in virtual screen pixels.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // How far it is in front of the camera, along the way the camera's looking.
+    pub depth: f32
+}
+
+// A shiny part of the background, like a pond or a mirror. Models are drawn again as seen
+// in its plane, and show through it wherever it's on the screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldReflection {
+    // Where it is on the screen, in virtual screen pixels.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // The plane it reflects in, a point on it and which way it faces.
+    pub point: Point3<f32>,
+    pub normal: Vector3<f32>,
+    // How much of the reflection shows, from 0 to 1. Water is usually well under 1.
+    pub strength: f32,
+    // How far ripples push the reflection about, in virtual screen pixels. 0 for a mirror.
+    pub ripple: f32
+}
+
+// A named place the player can start in a field, like where a door from another field comes
+// out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldSpawn {
+    pub name: String,
+    pub position: Vector3<f32>
+}
+
+// Component for entities that belong to a field, like its props, so they go when the player
+// leaves it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldEntity;
+
+// A way out of a field into another one, like a door or a teleporter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldExit {
+    // The name of the field it leads to, in the FieldMap.
+    pub target: String,
+    // Where it is in this field.
+    pub position: Vector3<f32>
+}
+
+// The background rendered from one of the cameras in a field's glTF.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldAngle {
+    // The camera's node name.
+    pub camera: String,
+    pub background: String
+}
+
+// Everything needed to set up a field. Most of it doesn't come from field data yet.
+#[derive(Clone, Debug, Default)]
 pub struct FieldDescriptor {
     // Walk mesh
-    // Background image
-    // Camera matrix
-}
\ No newline at end of file
+
+    // The field's glTF, for its cameras. Fields without one keep whatever camera was set.
+    pub scene: String,
+    // The pre-rendered background image, for any camera that isn't given its own in `angles`.
+    pub background: String,
+    // Backgrounds for the scene's other cameras. The field starts on the first of these, or
+    // the scene's first camera if there aren't any.
+    pub angles: Vec<FieldAngle>,
+    // A Tiled map (.tmx or .tmj) to draw over the background, which makes this a 2D field.
+    pub tilemap: String,
+    pub props: Vec<FieldProp>,
+    // Lifts and the like, see mover.rs.
+    pub movers: Vec<FieldMover>,
+    pub occluders: Vec<FieldOccluder>,
+    // Walls and the like that guards can't see through and chasers go round, see vision.rs.
+    pub blockers: Vec<VisionBlocker>,
+    pub pickups: Vec<FieldPickup>,
+    // Flags set when skills and items send events here, see field_skill.rs.
+    pub events: Vec<FieldEventResponse>,
+    // Spikes, teleporters and pitfalls, see trap.rs.
+    pub traps: Vec<FieldTrap>,
+    // For dark dungeons, how dark it is and how far the player's light reaches, see darkness.rs.
+    pub darkness: Option<FieldDarkness>,
+    // Flags and props that stay how the player left them, see puzzle.rs.
+    pub puzzle: FieldPuzzle,
+    // Where can be swum, see water.rs.
+    pub water: Vec<WaterRegion>,
+    pub reflections: Vec<FieldReflection>,
+    pub exits: Vec<FieldExit>,
+    pub spawns: Vec<FieldSpawn>,
+    // The music track's .cfg, see music.rs. Empty keeps whatever's already playing.
+    pub music: String,
+    // Muffling and echo for everything that plays in the field.
+    pub audio: FieldAudio,
+    // Which battle scene random encounters here use, see battle_scene.rs. Empty for the default.
+    pub terrain: String,
+    // A backdrop or music for battles here that's different from the terrain's.
+    pub battle_scene: BattleScene,
+
+    // Models further than this from the camera aren't drawn. Busy fields can set this to keep
+    // the frame rate up on slower GPUs.
+    pub max_draw_distance: Option<f32>,
+
+    // Blows spring bones about, like capes and hair. An acceleration, like gravity.
+    pub wind: [f32; 3]
+}
+
+impl FieldDescriptor {
+    // Spawn an entity for each prop. Each model is only loaded once however many props use
+    // it, and the renderer draws all of them together.
+    // Models that were prefetched are taken from `prefetched` instead of being loaded again.
+    pub async fn spawn_props(&self, world: &mut World, renderer: &mut Renderer, assets: &AssetServer, prefetched: &mut PrefetchedAssets) {
+        let mut models = HashMap::new();
+        for prop in &self.props {
+            let paths = std::iter::once(&prop.model).chain(prop.states.iter().map(|state| &state.model));
+            for path in paths.filter(|path| !path.is_empty()) {
+                if !models.contains_key(path) {
+                    let model = match prefetched.load_model(assets, path).await {
+                        Ok(model) => Some((renderer.create_model(&model), model)),
+                        Err(e) => {
+                            tracing::error!(target: targets::ASSETS, "{}", e);
+                            None
+                        }
+                    };
+                    models.insert(path.clone(), model);
+                }
+            }
+
+            // Props with states go in even without a model, as some other state might have one.
+            if !prop.states.is_empty() {
+                let entity = world.spawn();
+                world.insert(entity, Name(prop.name_or(&prop.flag)));
+                world.insert(entity, prop.transform);
+                world.insert(entity, FieldEntity);
+                let state_models = prop.states.iter()
+                    .map(|state| models.get(&state.model).and_then(Option::as_ref).map(|(id, _)| *id))
+                    .collect();
+                if let Some((id, model)) = prop.states.iter().find_map(|state| models.get(&state.model).and_then(Option::as_ref)) {
+                    insert_model(world, entity, *id, model);
+                }
+                world.insert(entity, PropStates::new(&prop.flag, prop.states.clone(), state_models));
+            } else if let Some((id, model)) = models.get(&prop.model).and_then(Option::as_ref) {
+                let entity = world.spawn();
+                world.insert(entity, Name(prop.name_or(&prop.model)));
+                world.insert(entity, prop.transform);
+                world.insert(entity, FieldEntity);
+                insert_model(world, entity, *id, model);
+            }
+        }
+    }
+
+    // Spawn an entity for each mover, starting at the beginning of its track. Animations come
+    // from the field's glTF, and movers whose track won't load are left where they'd start.
+    pub async fn spawn_movers(&self, world: &mut World, renderer: &mut Renderer, assets: &AssetServer, prefetched: &mut PrefetchedAssets) {
+        if self.movers.is_empty() {
+            return;
+        }
+        let animated = self.movers.iter().any(|mover| matches!(mover.track, MoverTrack::Animation(_)));
+        let scene = if animated {
+            match assets.load_bytes(&self.scene).await {
+                Ok(bytes) => Some(bytes),
+                Err(e) => {
+                    tracing::error!(target: targets::ASSETS, "{}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        for field_mover in &self.movers {
+            let keyframes = match (&field_mover.track, &scene) {
+                (MoverTrack::Path { points, speed }, _) => mover::path_keyframes(points, *speed),
+                (MoverTrack::Animation(node), Some(bytes)) => match mover::keyframes_from_gltf(bytes, node) {
+                    Ok(keyframes) => keyframes,
+                    Err(e) => {
+                        tracing::error!(target: targets::ASSETS, "{}: {}", self.scene, e);
+                        Vec::new()
+                    }
+                },
+                (MoverTrack::Animation(_), None) => Vec::new()
+            };
+            let mut transform = Transform::default();
+            if let Some(start) = keyframes.first() {
+                transform.position = start.position;
+                transform.rotation = start.rotation;
+            }
+
+            let entity = world.spawn();
+            world.insert(entity, Name(field_mover.name.clone()));
+            world.insert(entity, transform);
+            world.insert(entity, FieldEntity);
+            let mut moving = Mover::new(keyframes, field_mover.mode);
+            moving.playing = field_mover.playing;
+            moving.deck = field_mover.deck;
+            world.insert(entity, moving);
+            match prefetched.load_model(assets, &field_mover.model).await {
+                Ok(model) => {
+                    let id = renderer.create_model(&model);
+                    insert_model(world, entity, id, &model);
+                },
+                Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+            }
+        }
+    }
+
+    // Hand the renderer the field's occluders, replacing any from the last field. Ones whose
+    // image won't load are left out.
+    pub async fn load_occluders(&self, renderer: &mut Renderer, assets: &AssetServer, prefetched: &mut PrefetchedAssets) {
+        renderer.clear_field_occluders();
+        for occluder in &self.occluders {
+            match prefetched.load_image(assets, &occluder.image).await {
+                Ok(image) => renderer.add_field_occluder(occluder, &image),
+                Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+            }
+        }
+    }
+
+    // Hand the renderer the field's reflections, replacing any from the last field.
+    pub fn load_reflections(&self, renderer: &mut Renderer) {
+        renderer.set_field_reflections(&self.reflections);
+    }
+
+    // Switch the mixer to the field's effects.
+    pub fn load_audio(&self, world: &mut World) {
+        if let Some(mixer) = world.resource_mut::<Mixer>() {
+            mixer.set_field(self.audio);
+        }
+    }
+
+    // Start the field's music, unless it's already playing.
+    pub async fn load_music(&self, world: &mut World, assets: &AssetServer) {
+        if self.music.is_empty() || world.resource::<MusicPlayer>().is_some_and(|music| music.track().name == self.music) {
+            return;
+        }
+        match MusicTrack::load(assets, &self.music).await {
+            Ok(track) => {
+                let music = MusicPlayer::start(track, world.resource::<GameFlags>());
+                world.insert_resource(music);
+            },
+            Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+        }
+    }
+
+    // Show the field's background, if it has one.
+    pub async fn load_background(&self, renderer: &mut Renderer, assets: &AssetServer, prefetched: &mut PrefetchedAssets) {
+        if self.background.is_empty() {
+            return;
+        }
+        match prefetched.load_image(assets, &self.background).await {
+            Ok(image) => renderer.set_field_background(&image),
+            Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+        }
+    }
+
+    // Set up the field's cameras from its scene, pointing the world's camera through the first
+    // and loading every background ready for cutting between them. Without a scene, the last
+    // field's cameras are dropped.
+    pub async fn load_cameras(&self, world: &mut World, assets: &AssetServer, prefetched: &mut PrefetchedAssets) {
+        world.remove_resource::<FieldCameras>();
+        if self.scene.is_empty() {
+            return;
+        }
+        let scene_cameras = match assets.load_bytes(&self.scene).await {
+            Ok(bytes) => field_camera::cameras_from_gltf(&bytes).map_err(|e| AssetError::Decode(self.scene.clone(), e)),
+            Err(e) => Err(e)
+        };
+        let scene_cameras = match scene_cameras {
+            Ok(cameras) => cameras,
+            Err(e) => {
+                tracing::error!(target: targets::ASSETS, "{}", e);
+                return;
+            }
+        };
+
+        let cameras: Vec<FieldCamera> = scene_cameras.into_iter().map(|(name, camera)| {
+            let background = self.angles.iter().find(|angle| angle.camera == name)
+                .map(|angle| angle.background.clone())
+                .unwrap_or_else(|| self.background.clone());
+            FieldCamera { name, camera, background }
+        }).collect();
+        for angle in &self.angles {
+            if !cameras.iter().any(|camera| camera.name == angle.camera) {
+                tracing::warn!(target: targets::ASSETS, "{} doesn't have a camera called {}", self.scene, angle.camera);
+            }
+        }
+        let start = self.angles.first()
+            .and_then(|angle| cameras.iter().position(|camera| camera.name == angle.camera))
+            .unwrap_or(0);
+
+        let mut backgrounds: Vec<String> = Vec::new();
+        for camera in &cameras {
+            if !camera.background.is_empty() && !backgrounds.contains(&camera.background) {
+                backgrounds.push(camera.background.clone());
+            }
+        }
+        let mut field_cameras = FieldCameras::new(cameras, start);
+        for background in backgrounds {
+            match prefetched.load_image(assets, &background).await {
+                Ok(image) => field_cameras.insert_background(&background, image),
+                Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+            }
+        }
+        world.insert_resource(field_cameras.camera());
+        world.insert_resource(field_cameras);
+    }
+
+    // Draw the field's tilemap if it's a 2D field, or stop drawing the last field's if it
+    // isn't. The map's returned for its collision and objects.
+    pub async fn load_tilemap(&self, renderer: &mut Renderer, assets: &AssetServer, prefetched: &mut PrefetchedAssets) -> Option<Tilemap> {
+        renderer.clear_tilemap();
+        if self.tilemap.is_empty() {
+            return None;
+        }
+        match prefetched.load_tilemap(assets, &self.tilemap).await {
+            Ok(map) => {
+                renderer.set_tilemap(&map);
+                Some(map)
+            },
+            Err(e) => {
+                tracing::error!(target: targets::ASSETS, "{}", e);
+                None
+            }
+        }
+    }
+
+    // Where the player starts: the spawn point called `spawn`, or the first one if not given.
+    // Fields without any start at the origin.
+    pub fn spawn_position(&self, spawn: Option<&str>) -> Result<Vector3<f32>, String> {
+        match spawn {
+            Some(name) => self.spawns.iter().find(|spawn| spawn.name == name)
+                .map(|spawn| spawn.position)
+                .ok_or_else(|| format!("No spawn point \"{}\"", name)),
+            None => Ok(self.spawns.first().map(|spawn| spawn.position).unwrap_or(Vector3::new(0.0, 0.0, 0.0)))
+        }
+    }
+
+    // Every image, model and map the field needs, each only once.
+    pub fn asset_paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = Vec::new();
+        let all = [self.background.as_str(), self.tilemap.as_str()].into_iter()
+            .chain(self.angles.iter().map(|angle| angle.background.as_str()))
+            .chain(self.props.iter().map(|prop| prop.model.as_str()))
+            .chain(self.props.iter().flat_map(|prop| prop.states.iter().map(|state| state.model.as_str())))
+            .chain(self.occluders.iter().map(|occluder| occluder.image.as_str()));
+        for path in all {
+            if !path.is_empty() && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+
+// Every field in the game by name, and how they join up.
+#[derive(Clone, Debug, Default)]
+pub struct FieldMap {
+    fields: HashMap<String, FieldDescriptor>
+}
+
+impl FieldMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, field: FieldDescriptor) {
+        self.fields.insert(name.to_string(), field);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldDescriptor> {
+        self.fields.get(name)
+    }
+
+    // Every field's name, in order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.fields.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    // Record what every field needs with the asset server, including the ones that haven't
+    // been loaded yet, so the asset report covers all of them.
+    pub fn record_dependencies(&self, assets: &AssetServer) {
+        for (name, field) in &self.fields {
+            let owner = owner(name);
+            for path in field.asset_paths() {
+                assets.record_dependency(&owner, path);
+            }
+            // Music isn't prefetched, it's small and often carries on from the last field.
+            if !field.music.is_empty() {
+                assets.record_dependency(&owner, &field.music);
+            }
+            // Nor is the scene, only its cameras are read.
+            if !field.scene.is_empty() {
+                assets.record_dependency(&owner, &field.scene);
+            }
+            // Nor anything for its battles, which are loaded when one starts.
+            for path in [&field.battle_scene.backdrop, &field.battle_scene.music].into_iter().flatten() {
+                assets.record_dependency(&owner, path);
+            }
+        }
+    }
+
+    // The fields the exits in a field lead to, each only once. Exits to fields that aren't in
+    // the map are left out.
+    pub fn neighbours(&self, name: &str) -> Vec<&str> {
+        let mut neighbours: Vec<&str> = Vec::new();
+        for exit in self.fields.get(name).into_iter().flat_map(|field| &field.exits) {
+            if exit.target != name && self.fields.contains_key(&exit.target) && !neighbours.contains(&exit.target.as_str()) {
+                neighbours.push(&exit.target);
+            }
+        }
+        neighbours
+    }
+}
+
+// What the asset server records a field's assets under.
+pub fn owner(name: &str) -> String {
+    format!("field/{}", name)
+}
+
+// Images, models and maps loaded ahead of time, like the ones for the fields next to this
+// one, so going through a door doesn't have to wait for them. Each is handed out once and
+// then forgotten.
+#[derive(Default)]
+pub struct PrefetchedAssets {
+    images: HashMap<String, image::RgbaImage>,
+    models: HashMap<String, ModelData>,
+    tilemaps: HashMap<String, Tilemap>
+}
+
+impl PrefetchedAssets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_image(&mut self, path: &str, image: image::RgbaImage) {
+        self.images.insert(path.to_string(), image);
+    }
+
+    pub fn insert_model(&mut self, path: &str, model: ModelData) {
+        self.models.insert(path.to_string(), model);
+    }
+
+    pub fn insert_tilemap(&mut self, path: &str, map: Tilemap) {
+        self.tilemaps.insert(path.to_string(), map);
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.images.contains_key(path) || self.models.contains_key(path) || self.tilemaps.contains_key(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len() + self.models.len() + self.tilemaps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Forget everything `keep` says no to.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.images.retain(|path, _| keep(path));
+        self.models.retain(|path, _| keep(path));
+        self.tilemaps.retain(|path, _| keep(path));
+    }
+
+    // The prefetched image if there is one, otherwise load it now.
+    pub async fn load_image(&mut self, assets: &AssetServer, path: &str) -> Result<image::RgbaImage, AssetError> {
+        match self.images.remove(path) {
+            Some(image) => Ok(image),
+            None => assets.load_image(path).await
+        }
+    }
+
+    // The prefetched model if there is one, otherwise load it now.
+    pub async fn load_model(&mut self, assets: &AssetServer, path: &str) -> Result<ModelData, AssetError> {
+        match self.models.remove(path) {
+            Some(model) => Ok(model),
+            None => ModelData::load(assets, path).await
+        }
+    }
+
+    // The prefetched map if there is one, otherwise load it now.
+    pub async fn load_tilemap(&mut self, assets: &AssetServer, path: &str) -> Result<Tilemap, AssetError> {
+        match self.tilemaps.remove(path) {
+            Some(map) => Ok(map),
+            None => Tilemap::load(assets, path).await
+        }
+    }
+}
+
+// Everything in the way in the field right now: its blockers, and those of the states its props
+// are in.
+pub fn blockers(world: &World) -> Vec<VisionBlocker> {
+    let mut blockers = world.resource::<FieldDescriptor>().map(|field| field.blockers.clone()).unwrap_or_default();
+    for (_, props) in world.query::<PropStates>() {
+        blockers.extend(props.current().into_iter().flat_map(|state| state.blockers.iter().copied()));
+    }
+    blockers
+}
+
+// Leave the field: despawn everything that belongs to it and free their models. What the
+// renderer shows for the field is replaced when the next one loads.
+pub fn unload(world: &mut World, renderer: &mut Renderer) {
+    let entities: Vec<Entity> = world.query::<FieldEntity>().map(|(entity, _)| entity).collect();
+    let mut models = Vec::new();
+    for entity in entities {
+        // Props with states have a model for each, not just the one showing.
+        let state_models: Vec<ModelId> = world.get::<PropStates>(entity).map(|props| props.models().collect()).unwrap_or_default();
+        for id in world.get::<ModelInstance>(entity).map(|instance| instance.0).into_iter().chain(state_models) {
+            if !models.contains(&id) {
+                models.push(id);
+            }
+        }
+        world.despawn(entity);
+    }
+    for id in models {
+        renderer.remove_model(id);
+    }
+    world.remove_resource::<Tilemap>();
+    world.remove_resource::<FieldCameras>();
+    world.remove_resource::<FieldDescriptor>();
+    // The water's gone too, and where swimmers were doesn't mean anything in the next field.
+    world.remove_resource::<Ripples>();
+    for (_, swimmer) in world.query_mut::<Swimmer>() {
+        *swimmer = Swimmer::new();
+    }
+}
+
+// Despawn one of the field's entities, like an NPC that's walked out, freeing its model if
+// nothing else is using it.
+pub fn despawn(world: &mut World, renderer: &mut Renderer, entity: Entity) {
+    let model = world.get::<ModelInstance>(entity).map(|ModelInstance(id)| *id);
+    world.despawn(entity);
+    if let Some(id) = model {
+        if !world.query::<ModelInstance>().any(|(_, ModelInstance(other))| *other == id) {
+            renderer.remove_model(id);
+        }
+    }
+}
+
+// Give an entity a model, along with its sockets and whatever else the model needs to move.
+// Each entity gets its own, so they can all be posed separately.
+pub fn insert_model(world: &mut World, entity: Entity, id: ModelId, model: &ModelData) {
+    world.insert(entity, ModelInstance(id));
+    world.insert(entity, ModelSockets::from_model(model));
+    if !model.morph_targets.is_empty() {
+        world.insert(entity, MorphWeights::new(model));
+    }
+    if !model.spring_bones.is_empty() {
+        world.insert(entity, SpringBones::from_model(model));
+    }
+}
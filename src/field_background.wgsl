@@ -26,7 +26,45 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+struct Reflection {
+    // Left, top, right and bottom.
+    rect: vec4<f32>,
+    // x is how much of the reflection shows, yz how far ripples move it.
+    params: vec4<f32>,
+};
+
+struct BackgroundUniforms {
+    reflection_count: u32,
+    time: f32,
+    reflections: array<Reflection, 4>,
+};
+
+// The models as seen by the reflections' mirrored cameras, flipped left to right.
+@group(1) @binding(0)
+var t_reflection: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_reflection: sampler;
+
+@group(1) @binding(2)
+var<uniform> background: BackgroundUniforms;
+
+// Wobble the reflection sideways in bands that drift down, with a slower wave up and down.
+fn ripple(uv: vec2<f32>, amount: vec2<f32>) -> vec2<f32> {
+    let time = background.time;
+    return amount * vec2<f32>(sin(uv.y * 150.0 - time * 3.0), sin(uv.y * 90.0 + uv.x * 20.0 - time * 2.0) * 0.5);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.uv);
+    var color = textureSample(t_diffuse, s_diffuse, in.uv);
+    for (var i = 0u; i < background.reflection_count; i = i + 1u) {
+        let reflection = background.reflections[i];
+        if (all(in.uv >= reflection.rect.xy) && all(in.uv < reflection.rect.zw)) {
+            let uv = vec2<f32>(1.0 - in.uv.x, in.uv.y) + ripple(in.uv, reflection.params.yz);
+            let mirrored = textureSampleLevel(t_reflection, s_reflection, uv, 0.0);
+            color = vec4<f32>(mix(color.rgb, mirrored.rgb, mirrored.a * reflection.params.x), color.a);
+        }
+    }
+    return color;
 }
\ No newline at end of file
@@ -0,0 +1,258 @@
+// Fields can have more than one camera, each with the background that was rendered from it,
+// so a scene can cut to another angle half way through, like the door swinging shut seen from
+// the corridor. The cameras come from the field's glTF, named after their nodes. Scripts and
+// the console move between them with commands like "hall_door 1.5 easeinout swap=0.5".
+
+use std::{collections::HashMap, fmt, str::FromStr, time::Duration};
+
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector4};
+
+use crate::camera::Camera;
+use crate::renderer::Renderer;
+use crate::screen_effects::Easing;
+use crate::world::World;
+
+// One of a field's cameras, and the background that goes with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldCamera {
+    pub name: String,
+    pub camera: Camera,
+    pub background: String
+}
+
+// Every perspective camera in a glTF file's scene, by node name, in the order they're in the
+// file. Unnamed ones are called "node 3" and so on, like the validator does.
+pub fn cameras_from_gltf(bytes: &[u8]) -> Result<Vec<(String, Camera)>, String> {
+    let gltf = gltf::Gltf::from_slice(bytes).map_err(|e| e.to_string())?;
+    let scene = gltf.default_scene().or_else(|| gltf.scenes().next()).ok_or("There are no scenes")?;
+
+    let mut cameras = Vec::new();
+    let mut nodes: Vec<(gltf::Node, Matrix4<f32>)> = scene.nodes().map(|node| (node, Matrix4::identity())).collect();
+    while let Some((node, parent_transform)) = nodes.pop() {
+        let transform = parent_transform * Matrix4::from(node.transform().matrix());
+        nodes.extend(node.children().map(|child| (child, transform)));
+
+        let camera = match node.camera() {
+            Some(camera) => camera,
+            None => continue
+        };
+        let perspective = match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => perspective,
+            gltf::camera::Projection::Orthographic(_) => continue
+        };
+        let name = node.name().map(str::to_string).unwrap_or_else(|| format!("node {}", node.index()));
+        cameras.push((node.index(), name, camera_from_node(&transform, &perspective)));
+    }
+    cameras.sort_by_key(|(index, _, _)| *index);
+    Ok(cameras.into_iter().map(|(_, name, camera)| (name, camera)).collect())
+}
+
+// glTF cameras look down their node's -Z with +Y up.
+fn camera_from_node(transform: &Matrix4<f32>, perspective: &gltf::camera::Perspective) -> Camera {
+    let eye = transform * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    let forward = (transform * Vector4::new(0.0, 0.0, -1.0, 0.0)).truncate().normalize();
+    let up = (transform * Vector4::new(0.0, 1.0, 0.0, 0.0)).truncate().normalize();
+    let eye = Point3::new(eye.x, eye.y, eye.z);
+    Camera {
+        eye,
+        target: eye + forward,
+        up,
+        fovy: perspective.yfov().to_degrees(),
+        znear: perspective.znear(),
+        zfar: perspective.zfar().unwrap_or(Camera::default().zfar)
+    }
+}
+
+// A move to another camera. Without a duration it's a cut. Blends move the camera over the
+// duration, and since a pre-rendered background can't move with it, the background swaps once
+// at `swap`, how far through the blend it is, which defaults to the end so it lines up again
+// when the camera stops. After the name, the duration, easing and swap can come in any order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraCommand {
+    pub camera: String,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub swap: f32
+}
+
+impl FromStr for CameraCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let mut command = CameraCommand {
+            camera: words.next().ok_or("Expected a camera")?.to_string(),
+            duration: Duration::ZERO,
+            easing: Easing::default(),
+            swap: 1.0
+        };
+        for word in words {
+            if let Some(swap) = word.strip_prefix("swap=") {
+                command.swap = swap.parse().ok()
+                    .filter(|swap| (0.0..=1.0).contains(swap))
+                    .ok_or_else(|| format!("Bad swap \"{}\", expected 0 to 1", swap))?;
+            } else if let Ok(seconds) = word.parse::<f32>() {
+                command.duration = Duration::try_from_secs_f32(seconds).map_err(|_| format!("Bad duration \"{}\"", word))?;
+            } else {
+                command.easing = word.parse()?;
+            }
+        }
+        Ok(command)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct CameraBlend {
+    from: Camera,
+    to: usize,
+    duration: Duration,
+    elapsed: Duration,
+    easing: Easing,
+    swap: f32
+}
+
+// Resource for the field's cameras, which one's background is showing and where the view is.
+// The backgrounds are kept decoded so a cut doesn't have to wait for one to load.
+pub struct FieldCameras {
+    cameras: Vec<FieldCamera>,
+    showing: usize,
+    camera: Camera,
+    blend: Option<CameraBlend>,
+    backgrounds: HashMap<String, image::RgbaImage>,
+    // Whether the world's camera and the renderer's background need updating.
+    camera_changed: bool,
+    background_changed: bool
+}
+
+impl fmt::Debug for FieldCameras {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FieldCameras")
+            .field("cameras", &self.cameras)
+            .field("showing", &self.showing)
+            .field("camera", &self.camera)
+            .field("blend", &self.blend)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FieldCameras {
+    // Start on the camera at `start`, which the world's camera is pointed through and whose
+    // background goes up on the next update.
+    pub fn new(cameras: Vec<FieldCamera>, start: usize) -> Self {
+        let start = start.min(cameras.len().saturating_sub(1));
+        let camera = cameras.get(start).map(|camera| camera.camera).unwrap_or_default();
+        Self {
+            cameras,
+            showing: start,
+            camera,
+            blend: None,
+            backgrounds: HashMap::new(),
+            camera_changed: true,
+            background_changed: true
+        }
+    }
+
+    pub fn insert_background(&mut self, path: &str, image: image::RgbaImage) {
+        self.backgrounds.insert(path.to_string(), image);
+    }
+
+    pub fn cameras(&self) -> &[FieldCamera] {
+        &self.cameras
+    }
+
+    // The camera whose background is showing. Part way through a blend, that can be either
+    // end of it.
+    pub fn showing(&self) -> Option<&FieldCamera> {
+        self.cameras.get(self.showing)
+    }
+
+    // Where the view is now.
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.blend.is_some()
+    }
+
+    // Cut or start blending to a camera, replacing any blend already going. A blend starts
+    // from wherever the view is now.
+    pub fn run(&mut self, command: &CameraCommand) -> Result<(), String> {
+        let to = self.cameras.iter().position(|camera| camera.name == command.camera)
+            .ok_or_else(|| format!("No camera \"{}\"", command.camera))?;
+        self.blend = Some(CameraBlend {
+            from: self.camera,
+            to,
+            duration: command.duration,
+            elapsed: Duration::ZERO,
+            easing: command.easing,
+            swap: command.swap
+        });
+        // Cuts happen straight away, as do blends that swap at the start.
+        self.update(Duration::ZERO);
+        Ok(())
+    }
+
+    // Finish any blend straight away, e.g. when a cutscene's skipped.
+    pub fn finish(&mut self) {
+        if let Some(blend) = &mut self.blend {
+            blend.elapsed = blend.duration;
+        }
+        self.update(Duration::ZERO);
+    }
+
+    // Move any blend on. Returns true while the view's still moving, so there's something new
+    // to draw.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        let blend = match &mut self.blend {
+            Some(blend) => blend,
+            None => return false
+        };
+        blend.elapsed = (blend.elapsed + delta).min(blend.duration);
+        let progress = match blend.duration.is_zero() {
+            true => 1.0,
+            false => blend.elapsed.as_secs_f32() / blend.duration.as_secs_f32()
+        };
+        let blend = *blend;
+
+        self.camera = blend.from.lerp(&self.cameras[blend.to].camera, blend.easing.apply(progress));
+        self.camera_changed = true;
+        if progress >= blend.swap && self.showing != blend.to {
+            self.background_changed |= self.cameras[self.showing].background != self.cameras[blend.to].background;
+            self.showing = blend.to;
+        }
+        if progress >= 1.0 {
+            self.blend = None;
+        }
+        true
+    }
+
+    // The showing camera's background if it's changed since this was last asked, for handing
+    // to the renderer.
+    pub fn take_background(&mut self) -> Option<&image::RgbaImage> {
+        if !std::mem::take(&mut self.background_changed) {
+            return None;
+        }
+        let background = &self.cameras.get(self.showing)?.background;
+        self.backgrounds.get(background)
+    }
+}
+
+// Move the field's cameras on, point the world's camera where they're looking and swap the
+// background when it's time. The world's camera is only touched when they've moved, so
+// anything else that sets it in between is left alone. Returns true while the view's moving.
+pub fn update_field_cameras(world: &mut World, renderer: &mut Renderer, delta: Duration) -> bool {
+    let cameras = match world.resource_mut::<FieldCameras>() {
+        Some(cameras) => cameras,
+        None => return false
+    };
+    let moving = cameras.update(delta);
+    if let Some(background) = cameras.take_background() {
+        renderer.set_field_background(background);
+    }
+    if std::mem::take(&mut cameras.camera_changed) {
+        let camera = cameras.camera();
+        world.insert_resource(camera);
+    }
+    moving
+}
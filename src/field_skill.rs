@@ -0,0 +1,131 @@
+// Skills used outside battle: healing or curing someone, or something for the whole party,
+// like a sneak or lighting the way. What a skill does out here is an ItemEffect, the same as an
+// item's, so a cure spell and a potion work the same, and skills cost what they would in
+// battle. They're used from the status menu, or straight from the field with the hotbar's
+// number keys (see hotbar.rs), on whoever needs it most. Until the player sets it up, the
+// hotbar has the shortcuts: 1 for the first skill in the party that can be used out here, 2
+// for the next, and so on.
+//
+// Effects like "event reveal" are sent to FieldEvents, for the field to respond to. A field's
+// events list the flags they set, so with prop states (see prop_state.rs) a wall can crumble
+// to show a hidden passage. "light" also brightens the screen, easing off any vignette, and
+// widens the light in dark dungeons, see darkness.rs.
+
+use std::time::Duration;
+
+use crate::field_status::FieldStatus;
+use crate::inventory::{Inventory, ItemEffect};
+use crate::party::Party;
+use crate::pools;
+use crate::screen_effects::{Easing, EffectCommand, EffectParam};
+
+// The event that brightens the screen.
+pub const LIGHT_EVENT: &str = "light";
+const LIGHT_TIME: Duration = Duration::from_secs(1);
+
+// What a field does when an event's sent in it: sets a flag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldEventResponse {
+    pub event: String,
+    pub flag: String,
+    pub value: i32
+}
+
+// Resource collecting events sent by skills and items used on the field, until they're taken.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldEvents {
+    events: Vec<String>
+}
+
+impl FieldEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: &str) {
+        self.events.push(event.to_string());
+    }
+
+    // Events since the last call, in the order they were sent.
+    pub fn take_events(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// The flags a field's responses to an event set, as (flag, value).
+pub fn respond<'a>(responses: &'a [FieldEventResponse], event: &'a str) -> impl Iterator<Item = (&'a str, i32)> {
+    responses.iter()
+        .filter(move |response| response.event.eq_ignore_ascii_case(event))
+        .map(|response| (response.flag.as_str(), response.value))
+}
+
+// Easing the vignette off, for the light event.
+pub fn light_up() -> EffectCommand {
+    EffectCommand { param: EffectParam::Vignette, value: 0.0, duration: LIGHT_TIME, easing: Easing::EaseOut, color: None }
+}
+
+// Use `user`'s skill outside battle, on `target` if it's for someone rather than the whole
+// party. Says what happened for the menu to show, or why it can't be used, in which case
+// nothing's paid.
+pub fn use_skill(party: &mut Party, user: usize, skill: usize, target: Option<usize>, status: &mut FieldStatus, events: &mut FieldEvents, inventory: &mut Inventory) -> Result<String, String> {
+    let member = party.members.get(user).ok_or("There's no one there")?;
+    let skill = member.skills.get(skill).ok_or("There's no skill there")?.clone();
+    let effect = match &skill.field {
+        Some(effect) if *effect != ItemEffect::None => effect.clone(),
+        _ => return Err(format!("{} can't be used here", skill.name))
+    };
+    if member.stats.is_knocked_out() {
+        return Err(format!("{} is knocked out", member.name));
+    }
+    pools::can_afford(member, &skill, inventory)?;
+
+    let message = if effect.is_for_party() {
+        effect.apply_to_party(&skill.name, status, events)?
+    } else {
+        let target = party.members.get_mut(target.unwrap_or(user)).ok_or("There's no one there")?;
+        effect.apply_to(&skill.name, target)?
+    };
+    pools::pay(&mut party.members[user], &skill, inventory)?;
+    Ok(message)
+}
+
+// The skills the shortcuts use, in order, as (member, skill).
+pub fn shortcuts(party: &Party) -> Vec<(usize, usize)> {
+    party.members.iter().enumerate()
+        .flat_map(|(member, known)| known.skills.iter().enumerate()
+            .filter(|(_, skill)| skill.field.as_ref().is_some_and(|effect| *effect != ItemEffect::None))
+            .map(move |(skill, _)| (member, skill)))
+        .collect()
+}
+
+// Who an effect would do the most good for: whoever's lowest on HP for healing, or the first
+// that's knocked out or poisoned for reviving and curing. None if it's for the whole party, or
+// nobody needs it.
+pub fn best_target(party: &Party, effect: &ItemEffect) -> Option<usize> {
+    let mut members = party.members.iter().enumerate();
+    match effect {
+        ItemEffect::Restore { hp, .. } if *hp > 0 => members
+            .filter(|(_, member)| !member.stats.is_knocked_out() && member.stats.hp < member.stats.max_hp)
+            .min_by_key(|(_, member)| member.stats.hp as u64 * 100 / member.stats.max_hp.max(1) as u64)
+            .map(|(index, _)| index),
+        ItemEffect::Restore { .. } => members
+            .filter(|(_, member)| !member.stats.is_knocked_out() && member.stats.mp < member.stats.max_mp)
+            .min_by_key(|(_, member)| member.stats.mp)
+            .map(|(index, _)| index),
+        ItemEffect::Revive { .. } => members.find(|(_, member)| member.stats.is_knocked_out()).map(|(index, _)| index),
+        ItemEffect::Cure => members
+            .find(|(_, member)| !member.stats.is_knocked_out() && member.status_effects.iter().any(|effect| effect.is_harmful()))
+            .map(|(index, _)| index),
+        _ => None
+    }
+}
+
+// Who to use a skill or item called `name` on without asking, like best_target, or None if
+// it's for the whole party. An error if nobody needs it.
+pub fn pick_target(party: &Party, effect: &ItemEffect, name: &str) -> Result<Option<usize>, String> {
+    match best_target(party, effect) {
+        Some(target) => Ok(Some(target)),
+        None if effect.is_for_party() => Ok(None),
+        None => Err(format!("Nobody needs {}", name))
+    }
+}
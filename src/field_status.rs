@@ -0,0 +1,292 @@
+// Things that change what happens while walking around fields, from items and skills. Repel
+// makes random encounters rarer and lure makes them more common, for so many steps, and sneak
+// stops enemies getting the jump on the party with back attacks. They're written as "effect
+// steps", like "repel 100", in items.cfg and on skills.
+//
+// Repel and lure cancel each other out, so using one ends the other, and using one that's
+// already going tops it back up. They're counted down as the party walks and shown as icons in
+// the bottom left of the screen while they last.
+//
+// Walking also makes poison and regen on party members tick every few steps, hurting or healing
+// them a little. Poison flashes the screen and plays the "poison" stinger, if the music has one.
+// If it knocks out the leader, the next one standing takes over, and if there isn't anyone it's
+// game over.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::accessibility::Accessibility;
+use crate::formation::BattleSetup;
+use crate::party::{Party, StatusEffect};
+use crate::renderer::SCREEN_HEIGHT;
+use crate::rng::RngStream;
+use crate::screen_effects::{Easing, EffectCommand, EffectParam};
+use crate::ui::UiBatch;
+
+// How likely a random encounter is on each step, before repel or lure.
+pub const ENCOUNTER_CHANCE: f32 = 1.0 / 24.0;
+// What the chance is multiplied by while repel or lure are going.
+pub const REPEL_RATE: f32 = 0.25;
+pub const LURE_RATE: f32 = 2.0;
+// How far the party walks in a step, in metres.
+pub const STEP_LENGTH: f32 = 1.0;
+
+// How many steps between poison and regen ticking.
+pub const TICK_STEPS: u32 = 4;
+// How much of someone's max HP poison takes and regen gives each time, at least 1.
+pub const POISON_DAMAGE: f32 = 0.05;
+pub const REGEN_HEAL: f32 = 0.05;
+// The music stinger played when poison hurts.
+pub const POISON_STINGER: &str = "poison";
+// How far the screen flashes purple when poison hurts, and how long it takes to fade back.
+pub const POISON_FLASH: f32 = 0.4;
+const POISON_FLASH_TIME: Duration = Duration::from_millis(300);
+const POISON_COLOR: [f32; 3] = [0.5, 0.1, 0.6];
+
+const ICON_PADDING: f32 = 4.0;
+const ICON_MARGIN: f32 = 8.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FieldEffect {
+    Repel,
+    Lure,
+    Sneak
+}
+
+impl FieldEffect {
+    pub const ALL: [FieldEffect; 3] = [FieldEffect::Repel, FieldEffect::Lure, FieldEffect::Sneak];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldEffect::Repel => "repel",
+            FieldEffect::Lure => "lure",
+            FieldEffect::Sneak => "sneak"
+        }
+    }
+
+    // For showing in menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldEffect::Repel => "Repel",
+            FieldEffect::Lure => "Lure",
+            FieldEffect::Sneak => "Sneak"
+        }
+    }
+
+    // What's drawn in its icon. Letters rather than colours, so they can be told apart
+    // whatever the colour filter.
+    pub fn icon(&self) -> &'static str {
+        match self {
+            FieldEffect::Repel => "R",
+            FieldEffect::Lure => "L",
+            FieldEffect::Sneak => "S"
+        }
+    }
+
+    // The effect that using this one ends.
+    fn opposite(&self) -> Option<FieldEffect> {
+        match self {
+            FieldEffect::Repel => Some(FieldEffect::Lure),
+            FieldEffect::Lure => Some(FieldEffect::Repel),
+            FieldEffect::Sneak => None
+        }
+    }
+}
+
+impl fmt::Display for FieldEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FieldEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FieldEffect::ALL.into_iter()
+            .find(|effect| effect.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown field effect \"{}\", expected repel, lure or sneak", s.trim()))
+    }
+}
+
+// An effect and how many steps it lasts, as an item or skill gives it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldBoost {
+    pub effect: FieldEffect,
+    pub steps: u32
+}
+
+impl fmt::Display for FieldBoost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.effect, self.steps)
+    }
+}
+
+impl FromStr for FieldBoost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (effect, steps) = s.trim().split_once(' ').ok_or_else(|| format!("Expected \"effect steps\", not \"{}\"", s.trim()))?;
+        Ok(FieldBoost {
+            effect: effect.parse()?,
+            steps: steps.trim().parse().map_err(|_| format!("Bad number of steps \"{}\"", steps.trim()))?
+        })
+    }
+}
+
+// What happened to the party when poison and regen ticked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartyTick {
+    // Poison hurt someone, so flash the screen and play its sound.
+    pub hurt: bool,
+    pub knocked_out: Vec<String>,
+    // Who took over from a leader who was knocked out.
+    pub new_leader: Option<String>,
+    pub game_over: bool
+}
+
+// Resource with the effects that are going and how far the party's walked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldStatus {
+    // Each effect at most once, with the steps it has left.
+    effects: Vec<FieldBoost>,
+    // Distance walked towards the next step.
+    walked: f32,
+    // Steps since poison and regen last ticked.
+    tick_steps: u32,
+    // Left for the main loop to pick up with take_game_over.
+    game_over: bool
+}
+
+impl FieldStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn effects(&self) -> &[FieldBoost] {
+        &self.effects
+    }
+
+    pub fn has(&self, effect: FieldEffect) -> bool {
+        self.effects.iter().any(|active| active.effect == effect)
+    }
+
+    // Start an effect, saying what happened for the menu to show.
+    pub fn apply(&mut self, boost: FieldBoost) -> String {
+        let opposite = boost.effect.opposite();
+        self.effects.retain(|active| active.effect != boost.effect && Some(active.effect) != opposite);
+        if boost.steps > 0 {
+            self.effects.push(boost);
+        }
+        format!("{} for {} steps", boost.effect.label(), boost.steps)
+    }
+
+    // Add on however far the party's walked, giving back how many whole steps that makes.
+    // The movement system calls this, then `step` for each one.
+    pub fn walk(&mut self, distance: f32) -> u32 {
+        self.walked += distance.max(0.0);
+        let steps = (self.walked / STEP_LENGTH).floor();
+        self.walked -= steps * STEP_LENGTH;
+        steps as u32
+    }
+
+    // Count everything down a step, giving back the effects that wore off.
+    pub fn step(&mut self) -> Vec<FieldEffect> {
+        for active in &mut self.effects {
+            active.steps = active.steps.saturating_sub(1);
+        }
+        let worn_off = self.effects.iter().filter(|active| active.steps == 0).map(|active| active.effect).collect();
+        self.effects.retain(|active| active.steps > 0);
+        worn_off
+    }
+
+    // Count a step towards poison and regen ticking, hurting and healing the party every
+    // TICK_STEPS. Those who are knocked out are left alone.
+    pub fn tick_party(&mut self, party: &mut Party) -> PartyTick {
+        let mut tick = PartyTick::default();
+        self.tick_steps += 1;
+        if self.tick_steps < TICK_STEPS {
+            return tick;
+        }
+        self.tick_steps = 0;
+
+        let amount = |max_hp: u32, fraction: f32| ((max_hp as f32 * fraction) as u32).max(1);
+        for member in &mut party.members {
+            if member.stats.is_knocked_out() {
+                continue;
+            }
+            if member.has_status(StatusEffect::Poison) {
+                member.stats.hp = member.stats.hp.saturating_sub(amount(member.stats.max_hp, POISON_DAMAGE));
+                tick.hurt = true;
+                if member.stats.is_knocked_out() {
+                    tick.knocked_out.push(member.name.clone());
+                    continue;
+                }
+            }
+            if member.has_status(StatusEffect::Regen) {
+                member.stats.restore(amount(member.stats.max_hp, REGEN_HEAL), 0);
+            }
+        }
+
+        if !tick.knocked_out.is_empty() {
+            tick.new_leader = party.replace_leader().map(|leader| leader.name.clone());
+            tick.game_over = party.is_wiped_out();
+            self.game_over |= tick.game_over;
+        }
+        tick
+    }
+
+    // Whether the party was wiped out since this was last asked.
+    pub fn take_game_over(&mut self) -> bool {
+        std::mem::take(&mut self.game_over)
+    }
+
+    // How likely a random encounter is on a step right now.
+    pub fn encounter_chance(&self) -> f32 {
+        let mut chance = ENCOUNTER_CHANCE;
+        if self.has(FieldEffect::Repel) {
+            chance *= REPEL_RATE;
+        }
+        if self.has(FieldEffect::Lure) {
+            chance *= LURE_RATE;
+        }
+        chance
+    }
+
+    // Whether a random encounter starts on this step.
+    pub fn roll_encounter(&self, rng: &mut RngStream) -> bool {
+        rng.chance(self.encounter_chance())
+    }
+
+    // Change a battle that's about to start to suit the effects, so there's no back attack
+    // while sneaking.
+    pub fn adjust(&self, setup: &mut BattleSetup) {
+        if self.has(FieldEffect::Sneak) {
+            setup.back_attack = false;
+        }
+    }
+
+    // An icon for each effect with the steps it has left, in the bottom left of the screen.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        let size = UiBatch::line_height(scale) + ICON_PADDING * 2.0;
+        let y = SCREEN_HEIGHT as f32 - size - ICON_MARGIN;
+        let mut x = ICON_MARGIN;
+        for active in &self.effects {
+            let (icon_width, icon_height) = UiBatch::measure_text(scale, active.effect.icon());
+            batch.rect(x, y, size, size, skin.window);
+            batch.text(x + (size - icon_width) / 2.0, y + (size - icon_height) / 2.0, scale, active.effect.icon(), skin.highlight);
+            let steps = active.steps.to_string();
+            let text_y = y + (size - UiBatch::line_height(scale)) / 2.0;
+            x += size + ICON_MARGIN / 2.0;
+            x += batch.text(x, text_y, scale, &steps, skin.text) + ICON_MARGIN;
+        }
+    }
+}
+
+// The screen effects for poison hurting: straight to purple, then fading back. `strength` is
+// how far, which is nothing with screen effects turned off.
+pub fn poison_flash(strength: f32) -> [EffectCommand; 2] {
+    let flash = EffectCommand { param: EffectParam::Fade, value: strength, duration: Duration::ZERO, easing: Easing::default(), color: Some(POISON_COLOR) };
+    [flash, EffectCommand { value: 0.0, duration: POISON_FLASH_TIME, easing: Easing::EaseOut, color: None, ..flash }]
+}
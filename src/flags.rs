@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+// A flag changing value. Other systems, like achievements, watch for these.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlagEvent {
+    pub name: String,
+    pub old_value: i32,
+    pub value: i32
+}
+
+// Story and quest progress, as named numbers. A flag that has never been set is 0.
+// Quests use flags too, e.g. "quest.lost_cat" going from 0 to 1 to 2 as it progresses.
+#[derive(Clone, Debug, Default)]
+pub struct GameFlags {
+    flags: BTreeMap<String, i32>,
+    events: Vec<FlagEvent>
+}
+
+impl GameFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Flags loaded from a save. Doesn't send any events, nothing has changed as far as the
+    // game is concerned.
+    pub fn restore(flags: impl IntoIterator<Item = (String, i32)>) -> Self {
+        Self {
+            flags: flags.into_iter().filter(|(_, value)| *value != 0).collect(),
+            events: Vec::new()
+        }
+    }
+
+    pub fn get(&self, name: &str) -> i32 {
+        self.flags.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.get(name) != 0
+    }
+
+    pub fn set(&mut self, name: &str, value: i32) {
+        let old_value = self.get(name);
+        if old_value == value {
+            return;
+        }
+
+        if value == 0 {
+            self.flags.remove(name);
+        } else {
+            self.flags.insert(name.to_string(), value);
+        }
+        self.events.push(FlagEvent {
+            name: name.to_string(),
+            old_value,
+            value
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.flags.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+
+    // Changes since the last call.
+    pub fn take_events(&mut self) -> Vec<FlagEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
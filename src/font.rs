@@ -0,0 +1,434 @@
+// A tiny built in 5x8 bitmap font so we can draw text without loading anything from disk.
+// Each glyph is 5 columns, with bit 0 being the top row.
+//
+// It only has ASCII, so for other languages the game can list fonts to fall back on in
+// data/fonts.cfg. They're sheets of pixel art glyphs, tried in order for anything the built in
+// font doesn't have, with the ones for the player's language first so Japanese text gets
+// Japanese kanji rather than Chinese:
+//
+//   [misaki]
+//   image = fonts/misaki.png
+//   characters = fonts/misaki.txt
+//   cell = 8x8
+//   language = ja
+//
+// The characters file has the glyphs' characters in the order they are in the sheet, left to
+// right then top to bottom, with line breaks ignored. Wide characters, like kanji and
+// full-width punctuation, take up two cells, everything else one.
+
+use std::{collections::HashMap, sync::{Arc, OnceLock}};
+
+use crate::assets::{AssetError, AssetServer};
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 8;
+
+// Glyphs are laid out in the atlas in cells with a 1 pixel gap on the right.
+pub const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: u32 = GLYPH_HEIGHT;
+
+pub const ATLAS_COLUMNS: u32 = 16;
+pub const ATLAS_ROWS: u32 = 6;
+pub const ATLAS_WIDTH: u32 = ATLAS_COLUMNS * CELL_WIDTH;
+pub const ATLAS_HEIGHT: u32 = ATLAS_ROWS * CELL_HEIGHT;
+
+const FIRST_CHAR: u32 = 0x20;
+
+// The cell after the last printable character is completely filled, so plain rectangles can be
+// drawn with the same texture as text.
+pub const SOLID_CELL: u32 = 0x7F - FIRST_CHAR;
+
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x56, 0x20, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x2A, 0x1C, 0x7F, 0x1C, 0x2A], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x18, 0xA4, 0xA4, 0xA4, 0x7C], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x40, 0x80, 0x84, 0x7D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0xFC, 0x24, 0x24, 0x24, 0x18], // 'p'
+    [0x18, 0x24, 0x24, 0x24, 0xFC], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x24], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x1C, 0xA0, 0xA0, 0xA0, 0x7C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+// With fallback fonts the atlas is this many cells wide instead, as there can be thousands of
+// glyphs.
+pub const FALLBACK_ATLAS_COLUMNS: u32 = 256;
+
+// Top left pixel of a cell in the built in font's atlas.
+pub fn cell_origin(cell: u32) -> (u32, u32) {
+    ((cell % ATLAS_COLUMNS) * CELL_WIDTH, (cell / ATLAS_COLUMNS) * CELL_HEIGHT)
+}
+
+// Rasterize the built in font into an RGBA8 image with white glyphs on a transparent
+// background.
+pub fn build_atlas() -> Vec<u8> {
+    FontAtlas::builtin().pixels().to_vec()
+}
+
+// Whether a character is drawn two cells wide, like kanji, kana, hangul and full-width forms.
+pub fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3040..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD)
+}
+
+// How many cells a character takes up.
+pub fn char_columns(c: char) -> u32 {
+    if is_wide(c) { 2 } else { 1 }
+}
+
+// The ASCII character a full-width one is a wide version of, e.g. 'A' for 'Ａ', to draw when no
+// font has the full-width one.
+pub fn narrow_form(c: char) -> Option<char> {
+    match c as u32 {
+        0x3000 => Some(' '),
+        code @ 0xFF01..=0xFF5E => char::from_u32(code - 0xFEE0),
+        _ => None
+    }
+}
+
+// Where a fallback font comes from, one [section] of data/fonts.cfg.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FontSource {
+    pub name: String,
+    pub image: String,
+    pub characters: String,
+    // Size of each glyph in the sheet, in pixels.
+    pub cell: (u32, u32),
+    // The language the font's meant for, if it's meant for one.
+    pub language: Option<String>
+}
+
+impl FontSource {
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut sources: Vec<FontSource> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                sources.push(FontSource {
+                    name: name.trim().to_string(),
+                    image: String::new(),
+                    characters: String::new(),
+                    cell: (GLYPH_WIDTH, GLYPH_HEIGHT),
+                    language: None
+                });
+                continue;
+            }
+
+            let source = sources.last_mut().ok_or_else(|| format!("Line {}: expected a [font name] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "image" => source.image = value.to_string(),
+                "characters" => source.characters = value.to_string(),
+                "cell" => source.cell = value.split_once('x')
+                    .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+                    .filter(|&(width, height)| width > 0 && height > 0)
+                    .ok_or_else(|| format!("Line {}: expected a cell size like 8x8, not \"{}\"", number + 1, value))?,
+                "language" => source.language = Some(value.to_string()),
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+
+        if let Some(source) = sources.iter().find(|source| source.image.is_empty() || source.characters.is_empty()) {
+            return Err(format!("Font {} needs an image and characters", source.name));
+        }
+        Ok(sources)
+    }
+}
+
+// A font to draw characters the built in one doesn't have.
+#[derive(Clone, Debug)]
+pub struct FallbackFont {
+    pub name: String,
+    pub language: Option<String>,
+    glyphs: Vec<(char, image::RgbaImage)>
+}
+
+impl FallbackFont {
+    pub async fn load(assets: &AssetServer, source: &FontSource) -> Result<Self, AssetError> {
+        let sheet = assets.load_image(&source.image).await?;
+        let characters = assets.load_bytes(&source.characters).await?;
+        Ok(Self::from_sheet(source, &sheet, &String::from_utf8_lossy(&characters)))
+    }
+
+    // Cut the glyphs out of a sheet. Characters past the end of the sheet are left out.
+    pub fn from_sheet(source: &FontSource, sheet: &image::RgbaImage, characters: &str) -> Self {
+        let (cell_width, cell_height) = source.cell;
+        let columns = sheet.width() / cell_width;
+        let rows = sheet.height() / cell_height;
+        let glyphs = characters.chars()
+            .filter(|c| !c.is_control())
+            .enumerate()
+            .take_while(|(index, _)| (*index as u32) < columns * rows)
+            .map(|(index, c)| {
+                let (x, y) = ((index as u32 % columns) * cell_width, (index as u32 / columns) * cell_height);
+                (c, image::imageops::crop_imm(sheet, x, y, cell_width, cell_height).to_image())
+            })
+            .collect();
+        Self { name: source.name.clone(), language: source.language.clone(), glyphs }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.glyphs.iter().any(|(glyph, _)| *glyph == c)
+    }
+}
+
+// The fonts to try for `language`, in order: those for the language, then those for the same
+// language in any region, then those not meant for any, then the rest, for anything else.
+pub fn fallback_order<'a>(fonts: &'a [FallbackFont], language: &str) -> Vec<&'a FallbackFont> {
+    let base = crate::subtitles::base_language(language);
+    let rank = |font: &FallbackFont| match font.language.as_deref() {
+        Some(wanted) if wanted.eq_ignore_ascii_case(language) => 0,
+        Some(wanted) if crate::subtitles::base_language(wanted).eq_ignore_ascii_case(base) => 1,
+        None => 2,
+        Some(_) => 3
+    };
+    let mut order: Vec<&FallbackFont> = fonts.iter().collect();
+    order.sort_by_key(|font| rank(font));
+    order
+}
+
+// Where a glyph is in the atlas.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    // Top left pixel.
+    pub x: u32,
+    pub y: u32,
+    // How many cells wide it is. Can be fewer than the character takes up, e.g. an ASCII glyph
+    // standing in for a full-width character, in which case it's drawn in the middle.
+    pub columns: u32
+}
+
+// Every glyph the UI can draw, from the built in font then the fallbacks, packed into one
+// texture.
+#[derive(Clone, Debug)]
+pub struct FontAtlas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    glyphs: HashMap<char, Glyph>,
+    solid: Glyph
+}
+
+impl FontAtlas {
+    // Just the built in font. Made once and shared.
+    pub fn builtin() -> Arc<FontAtlas> {
+        static BUILTIN: OnceLock<Arc<FontAtlas>> = OnceLock::new();
+        BUILTIN.get_or_init(|| Arc::new(FontAtlas::new(&[]))).clone()
+    }
+
+    // The built in font, then each fallback in order, for whatever the ones before don't have.
+    pub fn new(fallbacks: &[&FallbackFont]) -> Self {
+        let mut extra: Vec<(char, &image::RgbaImage)> = Vec::new();
+        for font in fallbacks {
+            for (c, image) in &font.glyphs {
+                if !Self::is_builtin(*c) && !extra.iter().any(|(existing, _)| existing == c) {
+                    extra.push((*c, image));
+                }
+            }
+        }
+
+        // The built in font's cells first, laid out as they always are, then the fallbacks'
+        // glyphs, starting a new row when a wide one won't fit at the end of one.
+        let columns = if extra.is_empty() { ATLAS_COLUMNS } else { FALLBACK_ATLAS_COLUMNS };
+        let mut placed = Vec::new();
+        let (mut column, mut row) = ((SOLID_CELL + 1) % columns, (SOLID_CELL + 1) / columns);
+        for (c, image) in extra {
+            let width = char_columns(c);
+            if column + width > columns {
+                column = 0;
+                row += 1;
+            }
+            placed.push((c, image, Glyph { x: column * CELL_WIDTH, y: row * CELL_HEIGHT, columns: width }));
+            column += width;
+        }
+        let rows = if column == 0 { row } else { row + 1 };
+
+        let width = columns * CELL_WIDTH;
+        let height = rows * CELL_HEIGHT;
+        let mut atlas = Self { width, height, pixels: vec![0u8; (width * height * 4) as usize], glyphs: HashMap::new(), solid: Glyph { x: 0, y: 0, columns: 1 } };
+        let origin = |cell: u32| ((cell % columns) * CELL_WIDTH, (cell / columns) * CELL_HEIGHT);
+
+        for (cell, glyph) in GLYPHS.iter().enumerate() {
+            let (origin_x, origin_y) = origin(cell as u32);
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in 0..GLYPH_HEIGHT {
+                    if bits & (1 << row) != 0 {
+                        atlas.set_pixel(origin_x + column as u32, origin_y + row);
+                    }
+                }
+            }
+            if let Some(c) = char::from_u32(FIRST_CHAR + cell as u32) {
+                atlas.glyphs.insert(c, Glyph { x: origin_x, y: origin_y, columns: 1 });
+            }
+        }
+
+        // Fill in the solid cell.
+        let (origin_x, origin_y) = origin(SOLID_CELL);
+        for y in 0..CELL_HEIGHT {
+            for x in 0..CELL_WIDTH {
+                atlas.set_pixel(origin_x + x, origin_y + y);
+            }
+        }
+        atlas.solid = Glyph { x: origin_x, y: origin_y, columns: 1 };
+
+        // Fallback glyphs are cut down to fit their cells, leaving the usual gap on the right.
+        for (c, image, glyph) in placed {
+            let glyph_width = (glyph.columns * CELL_WIDTH - 1).min(image.width());
+            for y in 0..GLYPH_HEIGHT.min(image.height()) {
+                for x in 0..glyph_width {
+                    if image.get_pixel(x, y)[3] > 127 {
+                        atlas.set_pixel(glyph.x + x, glyph.y + y);
+                    }
+                }
+            }
+            atlas.glyphs.insert(c, glyph);
+        }
+
+        atlas
+    }
+
+    fn is_builtin(c: char) -> bool {
+        (FIRST_CHAR..FIRST_CHAR + GLYPHS.len() as u32).contains(&(c as u32))
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32) {
+        let index = ((y * self.width + x) * 4) as usize;
+        self.pixels[index..index + 4].copy_from_slice(&[255, 255, 255, 255]);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // RGBA8, white glyphs on a transparent background.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.glyphs.contains_key(&c)
+    }
+
+    // The glyph to draw for a character. Full-width characters no font has are drawn with
+    // their ASCII glyph, and anything else without a glyph as '?'.
+    pub fn glyph(&self, c: char) -> Glyph {
+        self.glyphs.get(&c)
+            .or_else(|| narrow_form(c).and_then(|narrow| self.glyphs.get(&narrow)))
+            .or_else(|| self.glyphs.get(&'?'))
+            .copied()
+            .unwrap_or(self.solid)
+    }
+
+    // A cell that's completely filled, so plain rectangles can be drawn with the same texture
+    // as text.
+    pub fn solid(&self) -> Glyph {
+        self.solid
+    }
+}
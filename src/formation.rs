@@ -0,0 +1,388 @@
+// Enemy formations: which enemies a battle starts with and where they stand. Battles are
+// set up from these rather than putting groups together in code, and encounter tables pick
+// between them. Formations are in data/formations.cfg:
+//
+// [cave_goblins]
+// name = Goblin Ambush
+// intro = cave.goblin_ambush
+// enemy = goblin 2-3 front
+// enemy = goblin_shaman 0-1 back
+//
+// [cave_ogre]
+// enemy = ogre 1 back at 0,-5 boss
+// music = music/ogre.cfg
+//
+// Each enemy line is "enemy = id count row", where the count can be a range, then optionally
+// "at x,z" to put them somewhere exact, "reach" if their attacks aren't weakened by rows and
+// "boss" if the battle's won by beating them. Enemies without a place are spread out along
+// their row. The intro's the event to run as the battle starts, for an ambush or a boss's
+// entrance. A backdrop or music given here is used instead of the one for where the battle
+// is, see battle_scene.rs.
+//
+// Encounter tables are in data/encounters.cfg, as "formation = weight" lines under a [table]
+// header:
+//
+// [cave]
+// cave_goblins = 3
+// cave_ogre = 1
+
+use std::{fmt, str::FromStr};
+
+use crate::battle_scene::BattleScene;
+use crate::rng::RngStream;
+
+// How far apart enemies in a row stand, in metres.
+const SPACING: f32 = 1.5;
+// What a physical attack's damage is multiplied by for each end of it that's in the back row.
+pub const BACK_ROW_DAMAGE: f32 = 0.5;
+// And how likely it is to hit someone in the back row.
+pub const BACK_ROW_ACCURACY: f32 = 0.9;
+// How likely a random encounter is to be a back attack, with the enemies getting the jump on
+// the party.
+pub const BACK_ATTACK_CHANCE: f32 = 0.125;
+
+// Which line an enemy or party member stands in, which decides who attacks can reach. Those in
+// the back row are safer, but hit more weakly too, unless their weapon has reach, like a bow or
+// a spear. Spells and items don't care about rows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Row {
+    Front,
+    Back
+}
+
+impl Row {
+    pub const ALL: [Row; 2] = [Row::Front, Row::Back];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Row::Front => "front",
+            Row::Back => "back"
+        }
+    }
+
+    // For showing in menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Row::Front => "Front",
+            Row::Back => "Back"
+        }
+    }
+
+    // The row to move to with the row change command.
+    pub fn other(&self) -> Row {
+        match self {
+            Row::Front => Row::Back,
+            Row::Back => Row::Front
+        }
+    }
+
+    // How far from the party the row stands, along -Z.
+    pub fn depth(&self) -> f32 {
+        match self {
+            Row::Front => -3.0,
+            Row::Back => -5.0
+        }
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Row {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Row::ALL.into_iter()
+            .find(|row| row.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown row \"{}\", expected front or back", s.trim()))
+    }
+}
+
+// What a physical attack's damage and chance to hit are multiplied by, for where the attacker
+// and target are standing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RowModifiers {
+    pub damage: f32,
+    pub accuracy: f32
+}
+
+impl RowModifiers {
+    // Attacks with reach aren't affected by rows at all.
+    pub fn new(attacker: Row, target: Row, reach: bool) -> Self {
+        if reach {
+            return Self { damage: 1.0, accuracy: 1.0 };
+        }
+        let damage = |row: Row| if row == Row::Back { BACK_ROW_DAMAGE } else { 1.0 };
+        Self {
+            damage: damage(attacker) * damage(target),
+            accuracy: if target == Row::Back { BACK_ROW_ACCURACY } else { 1.0 }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormationEnemy {
+    pub enemy: String,
+    // How many, from the first to the second, both included.
+    pub count: (u32, u32),
+    pub row: Row,
+    // Where they stand on the battlefield, x and z. None to be spread along the row.
+    pub position: Option<[f32; 2]>,
+    // Whether their attacks reach from and into the back row without being weakened.
+    pub reach: bool,
+    pub boss: bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Formation {
+    pub id: String,
+    pub name: String,
+    pub enemies: Vec<FormationEnemy>,
+    // The event to run as the battle starts.
+    pub intro: Option<String>,
+    // Overrides the backdrop or music of wherever the battle is.
+    pub scene: BattleScene
+}
+
+impl Formation {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut formations: Vec<Formation> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let id = id.trim().to_string();
+                formations.push(Formation { name: id.clone(), id, enemies: Vec::new(), intro: None, scene: BattleScene::default() });
+                continue;
+            }
+
+            let formation = formations.last_mut().ok_or_else(|| format!("Line {}: expected a [formation] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "name" => formation.name = value.to_string(),
+                "intro" => formation.intro = Some(value.to_string()),
+                "enemy" => formation.enemies.push(parse_enemy(value).map_err(|e| format!("Line {}: {}", number + 1, e))?),
+                _ if formation.scene.parse_key(key, value) => {},
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+
+        for formation in &formations {
+            if formation.enemies.is_empty() {
+                return Err(format!("{} doesn't have any enemies", formation.id));
+            }
+        }
+        Ok(formations)
+    }
+
+    // Whether there's a boss, which means there's no running away.
+    pub fn is_boss(&self) -> bool {
+        self.enemies.iter().any(|enemy| enemy.boss)
+    }
+
+    // Roll how many of each enemy there are and where they all stand.
+    pub fn assemble(&self, rng: &mut RngStream) -> Vec<BattleEnemy> {
+        let mut enemies = Vec::new();
+        let mut unplaced = Vec::new();
+        for group in &self.enemies {
+            let count = rng.range_inclusive(group.count.0, group.count.1);
+            for _ in 0..count {
+                if group.position.is_none() {
+                    unplaced.push(enemies.len());
+                }
+                enemies.push(BattleEnemy {
+                    enemy: group.enemy.clone(),
+                    row: group.row,
+                    position: group.position.unwrap_or_default(),
+                    reach: group.reach,
+                    boss: group.boss
+                });
+            }
+        }
+
+        // Spread the rest out along their row, centred.
+        for row in Row::ALL {
+            let in_row: Vec<usize> = unplaced.iter().copied().filter(|index| enemies[*index].row == row).collect();
+            let width = in_row.len().saturating_sub(1) as f32 * SPACING;
+            for (slot, index) in in_row.into_iter().enumerate() {
+                enemies[index].position = [slot as f32 * SPACING - width / 2.0, row.depth()];
+            }
+        }
+        enemies
+    }
+}
+
+impl FromStr for FormationEnemy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_enemy(s)
+    }
+}
+
+// "goblin 2-3 front at 1,-4 reach boss"
+fn parse_enemy(text: &str) -> Result<FormationEnemy, String> {
+    let mut words = text.split_whitespace();
+    let enemy = words.next().ok_or("expected \"enemy = id count row\"")?.to_string();
+    let count = words.next().ok_or_else(|| format!("expected how many {} there are", enemy))?;
+    let count = match count.split_once('-') {
+        Some((min, max)) => (min.parse().ok(), max.parse().ok()),
+        None => (count.parse().ok(), count.parse().ok())
+    };
+    let count = match count {
+        (Some(min), Some(max)) if min <= max => (min, max),
+        _ => return Err(format!("bad count for {}, expected a number or a range like 1-3", enemy))
+    };
+    let row = words.next().ok_or_else(|| format!("expected which row {} is in", enemy))?.parse()?;
+
+    let mut position = None;
+    let mut reach = false;
+    let mut boss = false;
+    while let Some(word) = words.next() {
+        match word {
+            "at" => {
+                let at = words.next().unwrap_or_default();
+                let (x, z) = at.split_once(',').ok_or_else(|| format!("bad position \"{}\", expected x,z", at))?;
+                position = Some([
+                    x.parse().map_err(|_| format!("bad position \"{}\", expected x,z", at))?,
+                    z.parse().map_err(|_| format!("bad position \"{}\", expected x,z", at))?
+                ]);
+            },
+            "reach" => reach = true,
+            "boss" => boss = true,
+            _ => return Err(format!("unexpected \"{}\"", word))
+        }
+    }
+    Ok(FormationEnemy { enemy, count, row, position, reach, boss })
+}
+
+// One enemy in a battle, after the formation's been rolled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BattleEnemy {
+    pub enemy: String,
+    pub row: Row,
+    pub position: [f32; 2],
+    pub reach: bool,
+    pub boss: bool
+}
+
+// Which formations can turn up somewhere, and how often each does compared to the others.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncounterTable {
+    pub id: String,
+    pub formations: Vec<(String, u32)>
+}
+
+impl EncounterTable {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut tables: Vec<EncounterTable> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                tables.push(EncounterTable { id: id.trim().to_string(), formations: Vec::new() });
+                continue;
+            }
+
+            let table = tables.last_mut().ok_or_else(|| format!("Line {}: expected a [table] first", number + 1))?;
+            let (formation, weight) = line.split_once('=')
+                .map(|(formation, weight)| (formation.trim(), weight.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"formation = weight\"", number + 1))?;
+            let weight = weight.parse().map_err(|_| format!("Line {}: bad weight \"{}\"", number + 1, weight))?;
+            table.formations.push((formation.to_string(), weight));
+        }
+        Ok(tables)
+    }
+
+    // Pick one of the formations.
+    pub fn pick(&self, rng: &mut RngStream) -> Option<&str> {
+        let weights: Vec<u32> = self.formations.iter().map(|(_, weight)| *weight).collect();
+        rng.weighted_index(&weights).map(|index| self.formations[index].0.as_str())
+    }
+}
+
+// Resource with every formation and encounter table.
+#[derive(Clone, Debug, Default)]
+pub struct Formations {
+    formations: Vec<Formation>,
+    tables: Vec<EncounterTable>
+}
+
+impl Formations {
+    // Fails if a table has a formation that doesn't exist, so typos are found on load.
+    pub fn new(formations: Vec<Formation>, tables: Vec<EncounterTable>) -> Result<Self, String> {
+        for table in &tables {
+            for (formation, _) in &table.formations {
+                if !formations.iter().any(|known| known.id == *formation) {
+                    return Err(format!("Encounter table {} has {}, which isn't a formation", table.id, formation));
+                }
+            }
+        }
+        Ok(Self { formations, tables })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Formation> {
+        self.formations.iter().find(|formation| formation.id == id)
+    }
+
+    pub fn table(&self, id: &str) -> Option<&EncounterTable> {
+        self.tables.iter().find(|table| table.id == id)
+    }
+
+    pub fn formations(&self) -> &[Formation] {
+        &self.formations
+    }
+
+    pub fn tables(&self) -> &[EncounterTable] {
+        &self.tables
+    }
+}
+
+// Everything a battle needs to start: what it's against, where they stand and how it opens.
+// Scripts ask for a formation by id, random encounters get one from their table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BattleSetup {
+    pub formation: String,
+    pub enemies: Vec<BattleEnemy>,
+    pub intro: Option<String>,
+    pub can_escape: bool,
+    // Whether the enemies caught the party from behind. Only random encounters can be.
+    pub back_attack: bool,
+    // Only the formation's own overrides to start with. Whatever starts the battle fills in
+    // the rest from where it is, with BattleScenes::resolve.
+    pub scene: BattleScene
+}
+
+impl BattleSetup {
+    pub fn new(formation: &Formation, rng: &mut RngStream) -> Self {
+        Self {
+            formation: formation.id.clone(),
+            enemies: formation.assemble(rng),
+            intro: formation.intro.clone(),
+            can_escape: !formation.is_boss(),
+            back_attack: false,
+            scene: formation.scene.clone()
+        }
+    }
+
+    // A random encounter from a table, or None if it's empty.
+    pub fn from_table(formations: &Formations, table: &EncounterTable, rng: &mut RngStream) -> Option<Self> {
+        let formation = formations.get(table.pick(rng)?)?;
+        let mut setup = Self::new(formation, rng);
+        setup.back_attack = rng.chance(BACK_ATTACK_CHANCE);
+        Some(setup)
+    }
+}
@@ -0,0 +1,107 @@
+use std::{thread, time::Duration};
+
+use instant::Instant;
+use winit::event::WindowEvent;
+
+// Sleeping isn't precise, so stop sleeping this long before the deadline and spin the rest.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(1500);
+
+// Keeps the game from drawing more often than it needs to. Caps the frame rate without relying
+// on vsync, and in low power mode only redraws when something has asked for it, e.g. input
+// in a menu, so a paused game doesn't keep the GPU busy.
+pub struct FrameLimiter {
+    fps_cap: Option<u32>,
+    low_power: bool,
+
+    // When the next frame is allowed to start.
+    next_frame: Option<Instant>,
+
+    // Whether anything has changed since the last frame. Only used in low power mode.
+    dirty: bool
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self {
+            fps_cap: None,
+            low_power: false,
+            next_frame: None,
+            dirty: true
+        }
+    }
+
+    pub fn fps_cap(&self) -> Option<u32> {
+        self.fps_cap
+    }
+
+    // None or 0 for no cap.
+    pub fn set_fps_cap(&mut self, fps_cap: Option<u32>) {
+        self.fps_cap = fps_cap.filter(|fps| *fps > 0);
+        self.next_frame = None;
+    }
+
+    pub fn low_power(&self) -> bool {
+        self.low_power
+    }
+
+    pub fn set_low_power(&mut self, low_power: bool) {
+        self.low_power = low_power;
+        self.dirty = true;
+    }
+
+    // Something changed that needs to be drawn.
+    pub fn request_redraw(&mut self) {
+        self.dirty = true;
+    }
+
+    // Whether to draw another frame. Always true unless in low power mode.
+    pub fn should_redraw(&self) -> bool {
+        !self.low_power || self.dirty
+    }
+
+    // Call once a frame has been drawn. Blocks until the next frame is due if there's a cap.
+    pub fn end_frame(&mut self) {
+        self.dirty = false;
+
+        let frame_time = match self.fps_cap {
+            Some(fps) => Duration::from_secs_f64(1.0 / fps as f64),
+            None => return
+        };
+
+        let now = Instant::now();
+        let deadline = match self.next_frame {
+            // If we've fallen behind, start again from now rather than rushing to catch up.
+            Some(deadline) if deadline > now => deadline,
+            _ => {
+                self.next_frame = Some(now + frame_time);
+                return;
+            }
+        };
+
+        let remaining = deadline - now;
+        if remaining > SPIN_THRESHOLD {
+            thread::sleep(remaining - SPIN_THRESHOLD);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+
+        self.next_frame = Some(deadline + frame_time);
+    }
+}
+
+// Whether a window event could change what's on screen by itself: input, and the window
+// changing size or focus. The mouse moving isn't, as it only matters when it moves the game's
+// own cursor or what a menu's pointing at, which whatever handles it has to ask for.
+pub fn changes_screen(event: &WindowEvent) -> bool {
+    matches!(event,
+        WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_)
+        | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } | WindowEvent::Touch(_)
+        | WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } | WindowEvent::Focused(_))
+}
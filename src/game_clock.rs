@@ -0,0 +1,142 @@
+// The time of day in the game world, which runs much faster than real time. NPC schedules,
+// and anything else that changes with the time, follow it.
+
+use std::time::Duration;
+
+use crate::world::World;
+
+pub const MINUTES_PER_DAY: u32 = 24 * 60;
+// Game minutes a real second, so a day goes by in 24 real minutes.
+pub const DEFAULT_RATE: f32 = 1.0;
+// New games start in the morning.
+const START_MINUTE: u32 = 8 * 60;
+
+// GameClock as it's kept in a save.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameClockState {
+    pub day: u32,
+    pub minute: u32
+}
+
+// Resource for the time of day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameClock {
+    // Counting from 1.
+    day: u32,
+    // Minutes since midnight, with how far through the current one.
+    minute: f32,
+    // Game minutes a real second.
+    pub rate: f32,
+    paused: bool,
+    // Whether it's gone on to another minute since anything last asked.
+    changed: bool
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameClock {
+    // A new game, on the first morning.
+    pub fn new() -> Self {
+        Self {
+            day: 1,
+            minute: START_MINUTE as f32,
+            rate: DEFAULT_RATE,
+            paused: false,
+            changed: true
+        }
+    }
+
+    pub fn restore(state: &GameClockState) -> Self {
+        Self {
+            day: state.day.max(1),
+            minute: (state.minute % MINUTES_PER_DAY) as f32,
+            ..Self::new()
+        }
+    }
+
+    pub fn save_state(&self) -> GameClockState {
+        GameClockState {
+            day: self.day,
+            minute: self.minute()
+        }
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    // Whole minutes since midnight.
+    pub fn minute(&self) -> u32 {
+        self.minute as u32
+    }
+
+    // Jump to a time of day, later today or, if it's already gone, tomorrow.
+    pub fn set_time(&mut self, minute: u32) {
+        let minute = minute % MINUTES_PER_DAY;
+        if minute < self.minute() {
+            self.day += 1;
+        }
+        self.minute = minute as f32;
+        self.changed = true;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Stop or start the clock, e.g. in menus and cutscenes.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // Move the clock on by real time.
+    pub fn advance(&mut self, delta: Duration) {
+        if self.paused {
+            return;
+        }
+        let before = self.minute();
+        self.minute += delta.as_secs_f32() * self.rate;
+        while self.minute >= MINUTES_PER_DAY as f32 {
+            self.minute -= MINUTES_PER_DAY as f32;
+            self.day += 1;
+        }
+        if self.minute() != before {
+            self.changed = true;
+        }
+    }
+
+    // Whether the minute's changed since this was last asked, for things that only need
+    // looking at when it does.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+// "07:30" -> minutes since midnight.
+pub fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    (hours < 24 && minutes < 60 && time.trim().len() == 5).then_some(hours * 60 + minutes)
+}
+
+// Minutes since midnight -> "07:30".
+pub fn format_time(minute: u32) -> String {
+    let minute = minute % MINUTES_PER_DAY;
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+// Move the world's clock on. Returns true if it's on another minute, or was set, since the
+// last update, so things that follow it know to look.
+pub fn update_game_clock(world: &mut World, delta: Duration) -> bool {
+    match world.resource_mut::<GameClock>() {
+        Some(clock) => {
+            clock.advance(delta);
+            clock.take_changed()
+        },
+        None => false
+    }
+}
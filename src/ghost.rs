@@ -0,0 +1,345 @@
+// Recording where someone goes on the field and playing it back as a see-through ghost, for
+// racing your best time in a mini-game or watching a movement bug happen again. A recording
+// samples where they are, which way they're facing and the clip they're playing a fixed number
+// of times a second, and stores it compactly:
+//
+//   "PSGHOST " u32 version
+//   u32 ticks a second
+//   u16 length, the field's name
+//   u8 clip count, then for each: u16 length, name, u32 duration in ms, u8 looping
+//   u32 frame count, then for each: i32 x, y, z in 1/256ths, u16 facing in 1/65536ths of a
+//   turn, u8 clip, 255 for none
+//
+// All numbers are little endian. Ghosts play the clips without their events, so they don't make
+// footstep sounds or the like.
+
+use std::time::Duration;
+
+use cgmath::{Quaternion, Rad, Rotation3, Vector3, VectorSpace};
+
+use crate::animation::{AnimationClip, AnimationPlayer};
+use crate::model::{ModelId, ModelInstance, ModelOpacity};
+use crate::transform::Transform;
+use crate::world::{Entity, World};
+
+const MAGIC: &[u8; 8] = b"PSGHOST ";
+const VERSION: u32 = 1;
+const NO_CLIP: u8 = u8::MAX;
+// Positions are stored in fixed point, in 1/256ths of a metre or pixel.
+const POSITION_SCALE: f32 = 256.0;
+const FRAME_SIZE: usize = 15;
+
+pub const TICK_RATE: u32 = 30;
+pub const GHOST_OPACITY: f32 = 0.4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GhostFrame {
+    pub position: Vector3<f32>,
+    // Radians about y, from facing down z.
+    pub facing: f32,
+    // Which of the track's clips was playing.
+    pub clip: Option<usize>
+}
+
+// A recording of one trip round one field.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GhostTrack {
+    pub field: String,
+    clips: Vec<AnimationClip>,
+    frames: Vec<GhostFrame>
+}
+
+impl GhostTrack {
+    pub fn new(field: &str) -> Self {
+        Self { field: field.to_string(), ..Default::default() }
+    }
+
+    // Add the next tick. Clips are only kept once each, and there's only room for 255 of them,
+    // more than that and the rest are left out.
+    pub fn push(&mut self, position: Vector3<f32>, facing: f32, clip: Option<&AnimationClip>) {
+        let clip = clip.and_then(|clip| match self.clips.iter().position(|known| known.name == clip.name) {
+            Some(index) => Some(index),
+            None if self.clips.len() < NO_CLIP as usize => {
+                self.clips.push(AnimationClip::new(&clip.name, clip.duration, clip.looping));
+                Some(self.clips.len() - 1)
+            },
+            None => None
+        });
+        self.frames.push(GhostFrame { position, facing, clip });
+    }
+
+    pub fn frames(&self) -> &[GhostFrame] {
+        &self.frames
+    }
+
+    pub fn clip(&self, index: usize) -> Option<&AnimationClip> {
+        self.clips.get(index)
+    }
+
+    // From the first tick to the last.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.frames.len().saturating_sub(1) as u64) / TICK_RATE
+    }
+
+    // Where they were at a time, going smoothly between ticks. Past the end, it's the last one.
+    pub fn sample(&self, time: Duration) -> Option<GhostFrame> {
+        let ticks = time.as_secs_f32() * TICK_RATE as f32;
+        let index = ticks as usize;
+        let (from, to) = match (self.frames.get(index), self.frames.get(index + 1)) {
+            (Some(from), Some(to)) => (from, to),
+            (Some(from), None) => return Some(*from),
+            _ => return self.frames.last().copied()
+        };
+        let t = ticks.fract();
+        // The short way round, so turning past behind doesn't spin them the long way.
+        let turn = (to.facing - from.facing + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        Some(GhostFrame {
+            position: from.position.lerp(to.position, t),
+            facing: from.facing + turn * t,
+            // Whichever tick's nearest.
+            clip: if t < 0.5 { from.clip } else { to.clip }
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&TICK_RATE.to_le_bytes());
+        write_string(&mut bytes, &self.field);
+        bytes.push(self.clips.len() as u8);
+        for clip in &self.clips {
+            write_string(&mut bytes, &clip.name);
+            bytes.extend_from_slice(&(clip.duration.as_millis() as u32).to_le_bytes());
+            bytes.push(clip.looping as u8);
+        }
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            for axis in [frame.position.x, frame.position.y, frame.position.z] {
+                bytes.extend_from_slice(&((axis * POSITION_SCALE).round() as i32).to_le_bytes());
+            }
+            let turns = (frame.facing / std::f32::consts::TAU).rem_euclid(1.0);
+            bytes.extend_from_slice(&((turns * 65536.0).round() as u32 as u16).to_le_bytes());
+            bytes.push(frame.clip.map_or(NO_CLIP, |clip| clip as u8));
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader(bytes);
+        if reader.take(8)? != MAGIC {
+            return Err("Not a ghost recording".to_string());
+        }
+        let version = reader.u32()?;
+        if version > VERSION {
+            return Err(format!("Ghost recording is from a newer version ({})", version));
+        }
+        let tick_rate = reader.u32()?;
+        if tick_rate != TICK_RATE {
+            return Err(format!("Ghost recording is at {} ticks a second, not {}", tick_rate, TICK_RATE));
+        }
+
+        let mut track = Self::new(&reader.string()?);
+        for _ in 0..reader.take(1)?[0] {
+            let name = reader.string()?;
+            let duration = Duration::from_millis(reader.u32()? as u64);
+            let looping = reader.take(1)?[0] != 0;
+            track.clips.push(AnimationClip::new(&name, duration, looping));
+        }
+        let count = reader.u32()? as usize;
+        if reader.0.len() < count * FRAME_SIZE {
+            return Err("Ghost recording is cut short".to_string());
+        }
+        for _ in 0..count {
+            let position = Vector3::new(reader.i32()? as f32, reader.i32()? as f32, reader.i32()? as f32) / POSITION_SCALE;
+            let facing = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as f32 / 65536.0 * std::f32::consts::TAU;
+            let clip = match reader.take(1)?[0] {
+                NO_CLIP => None,
+                clip if (clip as usize) < track.clips.len() => Some(clip as usize),
+                clip => return Err(format!("Ghost recording has no clip {}", clip))
+            };
+            track.frames.push(GhostFrame { position, facing, clip });
+        }
+        Ok(track)
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, text: &str) {
+    let text = &text.as_bytes()[..text.len().min(u16::MAX as usize)];
+    bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(text);
+}
+
+// Reads a recording from the front.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < count {
+            return Err("Ghost recording is cut short".to_string());
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = u16::from_le_bytes(self.take(2)?.try_into().unwrap()) as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).to_string())
+    }
+}
+
+// Resource for a recording that's going on.
+#[derive(Clone, Debug)]
+pub struct GhostRecorder {
+    pub entity: Entity,
+    pub track: GhostTrack,
+    // Since the last tick was taken.
+    since_tick: Duration
+}
+
+// Start recording an entity in a field, from where it is now. Replaces any recording that was
+// already going.
+pub fn start_recording(world: &mut World, entity: Entity, field: &str) {
+    let mut recorder = GhostRecorder { entity, track: GhostTrack::new(field), since_tick: Duration::ZERO };
+    take_tick(world, &mut recorder);
+    world.insert_resource(recorder);
+}
+
+// Stop recording, giving back what was recorded if anything was.
+pub fn stop_recording(world: &mut World) -> Option<GhostTrack> {
+    world.remove_resource::<GhostRecorder>().map(|recorder| recorder.track)
+}
+
+// Take a tick for every one that's passed. Call once a frame.
+pub fn record(world: &mut World, delta: Duration) {
+    let mut recorder = match world.remove_resource::<GhostRecorder>() {
+        Some(recorder) => recorder,
+        None => return
+    };
+    let tick = Duration::from_secs(1) / TICK_RATE;
+    recorder.since_tick += delta;
+    while recorder.since_tick >= tick {
+        recorder.since_tick -= tick;
+        take_tick(world, &mut recorder);
+    }
+    world.insert_resource(recorder);
+}
+
+fn take_tick(world: &World, recorder: &mut GhostRecorder) {
+    let transform = match world.get::<Transform>(recorder.entity) {
+        Some(transform) => transform,
+        None => return
+    };
+    let forward = transform.rotation * Vector3::unit_z();
+    let clip = world.get::<AnimationPlayer>(recorder.entity).and_then(AnimationPlayer::clip);
+    recorder.track.push(transform.position, forward.x.atan2(forward.z), clip);
+}
+
+// Component playing a recording back.
+#[derive(Clone, Debug)]
+pub struct Ghost {
+    track: GhostTrack,
+    time: Duration,
+    clip: Option<usize>,
+    // Go back to the start at the end, rather than disappearing.
+    pub looping: bool
+}
+
+impl Ghost {
+    pub fn track(&self) -> &GhostTrack {
+        &self.track
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+}
+
+// Spawn a ghost playing a recording back from the start, drawn with a model if it's given one.
+pub fn spawn_ghost(world: &mut World, track: GhostTrack, model: Option<ModelId>, looping: bool) -> Entity {
+    let entity = world.spawn();
+    let start = track.sample(Duration::ZERO);
+    world.insert(entity, Transform::default());
+    world.insert(entity, Ghost { track, time: Duration::ZERO, clip: None, looping });
+    if let Some(model) = model {
+        world.insert(entity, ModelInstance(model));
+        world.insert(entity, ModelOpacity(GHOST_OPACITY));
+    }
+    if let Some(frame) = start {
+        place(world, entity, &frame);
+    }
+    entity
+}
+
+// Move every ghost along its recording. Ghosts that have got to the end go, unless they loop.
+// Returns true while there are any.
+pub fn update_ghosts(world: &mut World, delta: Duration) -> bool {
+    let mut frames = Vec::new();
+    let mut finished = Vec::new();
+    for (entity, ghost) in world.query_mut::<Ghost>() {
+        ghost.time += delta;
+        let duration = ghost.track.duration();
+        if ghost.time > duration {
+            if !ghost.looping || duration.is_zero() {
+                finished.push(entity);
+                continue;
+            }
+            ghost.time = Duration::from_nanos((ghost.time.as_nanos() % duration.as_nanos()) as u64);
+        }
+        let frame = match ghost.track.sample(ghost.time) {
+            Some(frame) => frame,
+            None => continue
+        };
+        // Only start the clip again when it changes, so it plays through rather than sticking
+        // on its first frame.
+        let clip = match frame.clip {
+            Some(index) if ghost.clip != Some(index) => ghost.track.clip(index).cloned(),
+            _ => None
+        };
+        ghost.clip = frame.clip;
+        frames.push((entity, frame, clip));
+    }
+
+    let any = !frames.is_empty();
+    for (entity, frame, clip) in frames {
+        place(world, entity, &frame);
+        if let Some(clip) = clip {
+            match world.get_mut::<AnimationPlayer>(entity) {
+                Some(player) => player.play(clip),
+                None => {
+                    let mut player = AnimationPlayer::new();
+                    player.play(clip);
+                    world.insert(entity, player);
+                }
+            }
+        }
+    }
+    for entity in finished {
+        world.despawn(entity);
+    }
+    any
+}
+
+fn place(world: &mut World, entity: Entity, frame: &GhostFrame) {
+    if let Some(transform) = world.get_mut::<Transform>(entity) {
+        transform.position = frame.position;
+        transform.rotation = Quaternion::from_angle_y(Rad(frame.facing));
+    }
+}
+
+// Despawn every ghost, like when leaving the field they were recorded in.
+pub fn despawn_ghosts(world: &mut World) {
+    let ghosts: Vec<Entity> = world.query::<Ghost>().map(|(entity, _)| entity).collect();
+    for entity in ghosts {
+        world.despawn(entity);
+    }
+}
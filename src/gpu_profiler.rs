@@ -0,0 +1,220 @@
+use std::{fs::File, io::{BufWriter, Write}, path::Path, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::Duration};
+
+use wgpu::{Buffer, Device, QuerySet, Queue};
+
+use crate::logging::targets;
+
+// The most passes we can time in a single frame.
+const MAX_PASSES: u32 = 16;
+
+const QUERY_COUNT: u32 = MAX_PASSES * 2;
+const RESOLVE_BUFFER_SIZE: u64 = QUERY_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+// Times render passes on the GPU using timestamp queries.
+// Only available if the adapter supports wgpu::Features::TIMESTAMP_QUERY.
+//
+// Results are read back asynchronously, so the timings lag a frame or two behind. If the
+// previous frame's results are still being read back when a new frame starts, that frame
+// just isn't profiled.
+pub struct GpuProfiler {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+
+    // Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+
+    // Passes that have been timed in the frame currently being recorded.
+    frame_passes: Vec<&'static str>,
+    recording: bool,
+
+    // Passes in the frame that is being read back, and whether the read back has finished.
+    in_flight_passes: Vec<&'static str>,
+    in_flight_frame: u64,
+    readback_ready: Option<Arc<AtomicBool>>,
+
+    results: Vec<(&'static str, Duration)>,
+    frame_index: u64,
+
+    csv: Option<BufWriter<File>>
+}
+
+impl GpuProfiler {
+    pub fn required_features() -> wgpu::Features {
+        wgpu::Features::TIMESTAMP_QUERY
+    }
+
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Profiler Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT
+        });
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Profiler Resolve Buffer"),
+            size: RESOLVE_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Profiler Readback Buffer"),
+            size: RESOLVE_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            timestamp_period: queue.get_timestamp_period(),
+            frame_passes: Vec::new(),
+            recording: false,
+            in_flight_passes: Vec::new(),
+            in_flight_frame: 0,
+            readback_ready: None,
+            results: Vec::new(),
+            frame_index: 0,
+            csv: None
+        }
+    }
+
+    // Start a new frame. Picks up the results of an earlier frame if they're ready.
+    pub fn begin_frame(&mut self, device: &Device) {
+        self.frame_index += 1;
+        self.frame_passes.clear();
+
+        device.poll(wgpu::Maintain::Poll);
+        self.collect_results();
+
+        // Only record this frame if the readback buffer is free.
+        self.recording = self.readback_ready.is_none();
+    }
+
+    // Write a timestamp before a pass. Must be paired with end_pass().
+    pub fn begin_pass(&mut self, device: &Device, queue: &Queue, name: &'static str) {
+        if !self.recording || self.frame_passes.len() as u32 >= MAX_PASSES {
+            return;
+        }
+
+        let index = self.frame_passes.len() as u32 * 2;
+        self.frame_passes.push(name);
+        self.write_timestamp(device, queue, index);
+    }
+
+    // Write a timestamp after a pass.
+    pub fn end_pass(&mut self, device: &Device, queue: &Queue) {
+        if !self.recording || self.frame_passes.is_empty() {
+            return;
+        }
+
+        let index = (self.frame_passes.len() as u32 - 1) * 2 + 1;
+        self.write_timestamp(device, queue, index);
+    }
+
+    // Each of the renderers submits its own command buffer, so timestamps get their own tiny
+    // submissions in between. The queue keeps them in order.
+    fn write_timestamp(&self, device: &Device, queue: &Queue, index: u32) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Profiler Timestamp Encoder")
+        });
+        encoder.write_timestamp(&self.query_set, index);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Resolve this frame's queries and start reading them back.
+    pub fn end_frame(&mut self, device: &Device, queue: &Queue) {
+        if !self.recording || self.frame_passes.is_empty() {
+            return;
+        }
+        self.recording = false;
+
+        let query_count = self.frame_passes.len() as u32 * 2;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Profiler Resolve Encoder")
+        });
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, RESOLVE_BUFFER_SIZE);
+        queue.submit(Some(encoder.finish()));
+
+        let ready = Arc::new(AtomicBool::new(false));
+        let callback_ready = ready.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                callback_ready.store(true, Ordering::Release);
+            }
+        });
+
+        self.in_flight_passes = std::mem::take(&mut self.frame_passes);
+        self.in_flight_frame = self.frame_index;
+        self.readback_ready = Some(ready);
+    }
+
+    fn collect_results(&mut self) {
+        let ready = match &self.readback_ready {
+            Some(ready) => ready.load(Ordering::Acquire),
+            None => return
+        };
+        if !ready {
+            return;
+        }
+
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+
+            self.results.clear();
+            for (i, name) in self.in_flight_passes.iter().enumerate() {
+                let (start, end) = (timestamps[i * 2], timestamps[i * 2 + 1]);
+                let nanos = end.saturating_sub(start) as f64 * self.timestamp_period as f64;
+                self.results.push((name, Duration::from_nanos(nanos as u64)));
+            }
+        }
+        self.readback_buffer.unmap();
+        self.readback_ready = None;
+
+        self.write_csv();
+    }
+
+    // The most recent GPU timings for each pass.
+    pub fn get_results(&self) -> &[(&'static str, Duration)] {
+        &self.results
+    }
+
+    pub fn is_capturing_csv(&self) -> bool {
+        self.csv.is_some()
+    }
+
+    // Start appending every frame's pass timings to a CSV file.
+    pub fn start_csv_capture(&mut self, path: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,pass,gpu_ms")?;
+        self.csv = Some(writer);
+        Ok(())
+    }
+
+    pub fn stop_csv_capture(&mut self) {
+        if let Some(mut writer) = self.csv.take() {
+            if let Err(e) = writer.flush() {
+                tracing::error!(target: targets::RENDERER, "Couldn't write GPU profile: {}", e);
+            }
+        }
+    }
+
+    fn write_csv(&mut self) {
+        let writer = match &mut self.csv {
+            Some(writer) => writer,
+            None => return
+        };
+
+        for (name, time) in &self.results {
+            if let Err(e) = writeln!(writer, "{},{},{:.4}", self.in_flight_frame, name, time.as_secs_f64() * 1000.0) {
+                tracing::error!(target: targets::RENDERER, "Couldn't write GPU profile: {}", e);
+                self.csv = None;
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Entities that move with other entities, like passengers in a boat or a crate on a moving
+// platform. A child has a Parent and a LocalTransform saying where it is relative to that
+// parent, and the parent keeps a list of its Children. The child's Transform is still where it
+// is in the world, so everything else can keep reading that, and propagate_transforms works it
+// out from the parent every frame. Moving a child means changing its LocalTransform.
+//
+// Sticking things to a socket on a model, like a sword in a hand, is an Attachment instead, see
+// attachment.rs. Those are placed after this, so a sword held by someone on a boat goes along
+// with the boat.
+
+use cgmath::{Matrix4, SquareMatrix};
+
+use crate::transform::Transform;
+use crate::world::{Entity, World};
+
+// Children can have children of their own, but not this deep. Stops a loop from going round
+// forever if one's been made by hand.
+const MAX_DEPTH: usize = 32;
+
+// Component for an entity that moves with another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+// Component for an entity that has others moving with it, in the order they were added.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+// Component for where a child is relative to its parent.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LocalTransform(pub Transform);
+
+// Make `child` move with `parent`, keeping it where it is in the world for now. Taking it off
+// whatever it was on before. Fails if either's gone, or if `parent` is on `child` already,
+// however far down.
+pub fn set_parent(world: &mut World, child: Entity, parent: Entity) -> Result<(), String> {
+    if !world.contains(child) || !world.contains(parent) {
+        return Err("There's no such entity".to_string());
+    }
+    if ancestors(world, parent).any(|ancestor| ancestor == child) || child == parent {
+        return Err(format!("Entity {} can't go on {}, it's underneath it", child.id(), parent.id()));
+    }
+    remove_parent(world, child);
+
+    let parent_matrix = global_matrix(world, parent, 0).unwrap_or_else(Matrix4::identity);
+    let child_matrix = world.get::<Transform>(child).map_or_else(Matrix4::identity, Transform::matrix);
+    let local = parent_matrix.invert().map_or_else(Transform::default, |inverse| Transform::from_matrix(&(inverse * child_matrix)));
+    world.insert(child, Parent(parent));
+    world.insert(child, LocalTransform(local));
+    match world.get_mut::<Children>(parent) {
+        Some(children) => children.0.push(child),
+        None => world.insert(parent, Children(vec![child]))
+    }
+    Ok(())
+}
+
+// Stop `child` moving with its parent. It stays where it is in the world.
+pub fn remove_parent(world: &mut World, child: Entity) {
+    let parent = match world.remove::<Parent>(child) {
+        Some(Parent(parent)) => parent,
+        None => return
+    };
+    world.remove::<LocalTransform>(child);
+    let empty = match world.get_mut::<Children>(parent) {
+        Some(children) => {
+            children.0.retain(|entity| *entity != child);
+            children.0.is_empty()
+        },
+        None => false
+    };
+    if empty {
+        world.remove::<Children>(parent);
+    }
+}
+
+// The entity's parent, its parent's parent and so on up.
+pub fn ancestors(world: &World, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+    std::iter::successors(world.get::<Parent>(entity).map(|parent| parent.0), move |entity| world.get::<Parent>(*entity).map(|parent| parent.0))
+        .take(MAX_DEPTH)
+}
+
+// Despawn an entity and everything moving with it. Anything it was on forgets about it.
+pub fn despawn_recursive(world: &mut World, entity: Entity) {
+    remove_parent(world, entity);
+    despawn_children(world, entity, 0);
+}
+
+fn despawn_children(world: &mut World, entity: Entity, depth: usize) {
+    let children = world.remove::<Children>(entity).unwrap_or_default();
+    if depth < MAX_DEPTH {
+        for child in children.iter() {
+            despawn_children(world, child, depth + 1);
+        }
+    }
+    world.despawn(entity);
+}
+
+// Put every child where its parent says it should be in the world. Call once everything that
+// moves parents has had its turn, and before attachments. Children whose parent's gone are left
+// where they were, on their own.
+pub fn propagate_transforms(world: &mut World) {
+    let children: Vec<(Entity, Entity)> = world.query::<Parent>().map(|(entity, parent)| (entity, parent.0)).collect();
+    let mut orphans = Vec::new();
+    let mut placed = Vec::new();
+    for (child, parent) in children {
+        if !world.contains(parent) {
+            orphans.push(child);
+            continue;
+        }
+        if let Some(matrix) = global_matrix(world, child, 0) {
+            placed.push((child, Transform::from_matrix(&matrix)));
+        }
+    }
+    for child in orphans {
+        world.remove::<Parent>(child);
+        world.remove::<LocalTransform>(child);
+    }
+    for (child, transform) in placed {
+        world.insert(child, transform);
+    }
+
+    // Forget children that have been despawned.
+    let parents: Vec<Entity> = world.query::<Children>().map(|(entity, _)| entity).collect();
+    for parent in parents {
+        let alive: Vec<Entity> = match world.get::<Children>(parent) {
+            Some(children) => children.iter().filter(|child| world.contains(*child)).collect(),
+            None => continue
+        };
+        if alive.is_empty() {
+            world.remove::<Children>(parent);
+        } else {
+            world.insert(parent, Children(alive));
+        }
+    }
+}
+
+// Where an entity is in the world, going up through its parents.
+fn global_matrix(world: &World, entity: Entity, depth: usize) -> Option<Matrix4<f32>> {
+    let (parent, local) = match (world.get::<Parent>(entity), world.get::<LocalTransform>(entity)) {
+        (Some(parent), Some(local)) if depth < MAX_DEPTH && world.contains(parent.0) => (parent.0, local.0),
+        _ => return world.get::<Transform>(entity).map(Transform::matrix)
+    };
+    Some(global_matrix(world, parent, depth + 1)? * local.matrix())
+}
@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3, One};
+
+use crate::transform::Transform;
+use crate::world::{World, Entity};
+
+// Flinches can stack up, but never tip an entity over further than this.
+const MAX_FLINCH_DEGREES: f32 = 45.0;
+
+// Something got hit, e.g. by an attack's damage landing. The battle system sends these and
+// update_hit_reactions plays them on entities with a HitReaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitEvent {
+    pub target: Entity,
+    // Which way the blow pushes, in the world. Only the horizontal part is used.
+    pub direction: Vector3<f32>,
+    // 1 for a normal hit. Scales how far the target flinches and gets knocked back.
+    pub strength: f32
+}
+
+// Resource collecting hits until they're played.
+#[derive(Clone, Debug, Default)]
+pub struct HitEvents {
+    events: Vec<HitEvent>
+}
+
+impl HitEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: HitEvent) {
+        self.events.push(event);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ActiveHit {
+    direction: Vector3<f32>,
+    strength: f32,
+    elapsed: Duration
+}
+
+// Component for entities that react to being hit, by tilting away from the blow and sliding
+// back a little. Reactions are added on top of whatever else is moving the entity, and
+// several can play at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitReaction {
+    // How far a strength 1 hit tips the entity over at its worst.
+    pub flinch_degrees: f32,
+    // How far a strength 1 hit pushes the entity back.
+    pub knockback: f32,
+    // How long a reaction takes to play out.
+    pub duration: Duration,
+    hits: Vec<ActiveHit>
+}
+
+impl Default for HitReaction {
+    fn default() -> Self {
+        Self {
+            flinch_degrees: 15.0,
+            knockback: 0.5,
+            duration: Duration::from_millis(400),
+            hits: Vec::new()
+        }
+    }
+}
+
+impl HitReaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start reacting to a blow. Hits straight up or down don't push in any direction, so
+    // they're ignored.
+    pub fn hit(&mut self, direction: Vector3<f32>, strength: f32) {
+        let horizontal = Vector3::new(direction.x, 0.0, direction.z);
+        if horizontal.magnitude2() <= f32::EPSILON || strength <= 0.0 {
+            return;
+        }
+        self.hits.push(ActiveHit {
+            direction: horizontal.normalize(),
+            strength,
+            elapsed: Duration::ZERO
+        });
+    }
+
+    pub fn is_reacting(&self) -> bool {
+        !self.hits.is_empty()
+    }
+
+    // Move the reactions on, returning how far the entity is pushed this frame.
+    pub fn update(&mut self, delta: Duration) -> Vector3<f32> {
+        let mut pushed = Vector3::new(0.0, 0.0, 0.0);
+        let (duration, knockback) = (self.duration, self.knockback);
+        self.hits.retain_mut(|hit| {
+            let before = knockback_curve(progress(hit.elapsed, duration));
+            hit.elapsed = (hit.elapsed + delta).min(duration);
+            let after = knockback_curve(progress(hit.elapsed, duration));
+            pushed += hit.direction * hit.strength * knockback * (after - before);
+            hit.elapsed < duration
+        });
+        pushed
+    }
+
+    // How the entity is tipped over right now, about its origin.
+    pub fn flinch(&self) -> Quaternion<f32> {
+        let mut tilt = Vector3::new(0.0, 0.0, 0.0);
+        for hit in &self.hits {
+            // Tip over backwards, away from the blow.
+            let axis = Vector3::unit_y().cross(hit.direction);
+            tilt += axis * self.flinch_degrees * hit.strength * flinch_curve(progress(hit.elapsed, self.duration));
+        }
+        let degrees = tilt.magnitude().min(MAX_FLINCH_DEGREES);
+        if degrees <= f32::EPSILON {
+            return Quaternion::one();
+        }
+        Quaternion::from_axis_angle(tilt.normalize(), Deg(degrees))
+    }
+
+    // An entity's transform with the flinch on top.
+    pub fn apply(&self, transform: &Transform) -> Matrix4<f32> {
+        Matrix4::from_translation(transform.position)
+            * Matrix4::from(self.flinch())
+            * Matrix4::from_translation(-transform.position)
+            * transform.matrix()
+    }
+}
+
+fn progress(elapsed: Duration, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        1.0
+    } else {
+        elapsed.as_secs_f32() / duration.as_secs_f32()
+    }
+}
+
+// Fast at first, then slowing to a stop.
+fn knockback_curve(t: f32) -> f32 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+// Snaps back in the first fifth, then eases back upright.
+fn flinch_curve(t: f32) -> f32 {
+    const PEAK: f32 = 0.2;
+    if t < PEAK {
+        t / PEAK
+    } else {
+        let t = (t - PEAK) / (1.0 - PEAK);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    }
+}
+
+// Where an entity is drawn, with any flinch it's in the middle of. Attachments follow this too.
+pub fn posed_matrix(world: &World, entity: Entity, transform: &Transform) -> Matrix4<f32> {
+    match world.get::<HitReaction>(entity) {
+        Some(reaction) if reaction.is_reacting() => reaction.apply(transform),
+        _ => transform.matrix()
+    }
+}
+
+// Start reactions to the hits sent since last time, then move every reaction on, pushing
+// entities back. Returns true while any are still playing.
+pub fn update_hit_reactions(world: &mut World, delta: Duration) -> bool {
+    let events = world.resource_mut::<HitEvents>()
+        .map(|events| std::mem::take(&mut events.events))
+        .unwrap_or_default();
+    for event in events {
+        if let Some(reaction) = world.get_mut::<HitReaction>(event.target) {
+            reaction.hit(event.direction, event.strength);
+        }
+    }
+
+    let entities: Vec<Entity> = world.query::<HitReaction>()
+        .filter(|(_, reaction)| reaction.is_reacting())
+        .map(|(entity, _)| entity)
+        .collect();
+    let mut reacting = false;
+    for entity in entities {
+        let pushed = match world.get_mut::<HitReaction>(entity) {
+            Some(reaction) => {
+                let pushed = reaction.update(delta);
+                reacting |= reaction.is_reacting();
+                pushed
+            },
+            None => continue
+        };
+        if let Some(transform) = world.get_mut::<Transform>(entity) {
+            transform.position += pushed;
+        }
+    }
+    reacting
+}
@@ -0,0 +1,223 @@
+// Quick slots for the field items and skills that get used a lot, on the number keys. Each slot
+// holds a skill, by who knows it and what it's called, or an item by its id, written like
+//
+//   skill Tobin/Cure
+//   item potion
+//
+// and is used on whoever needs it most, see field_skill::pick_target. Skills and items are
+// put in slots by pressing a number on them in the status menu's skills or items, or with the
+// console's hotbar command, and a new game starts with the party's field skills in order.
+//
+// The slots are drawn in the bottom corner with their key, how many more uses there are (the
+// item's count, or what the skill's costs allow) and a bar running down while it cools down.
+// They're kept in saves.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::field_skill::{self, FieldEvents};
+use crate::field_status::FieldStatus;
+use crate::inventory::{Inventory, ItemEffect};
+use crate::party::Party;
+use crate::pools;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ui::UiBatch;
+
+pub const SLOT_COUNT: usize = 9;
+// How long a slot waits after it's used before it can be again, so holding a key down doesn't
+// drain the inventory.
+pub const COOLDOWN: Duration = Duration::from_millis(1500);
+
+const KEYS: [VirtualKeyCode; SLOT_COUNT] = [
+    VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9
+];
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 4.0;
+const COOLDOWN_HEIGHT: f32 = 2.0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuickSlot {
+    Skill { member: String, skill: String },
+    Item(String)
+}
+
+impl QuickSlot {
+    // What it's called, for the HUD and messages.
+    pub fn name(&self, inventory: &Inventory) -> String {
+        match self {
+            QuickSlot::Skill { skill, .. } => skill.clone(),
+            QuickSlot::Item(id) => inventory.slots().iter()
+                .find(|slot| slot.item.id == *id)
+                .map(|slot| slot.item.name.clone())
+                .unwrap_or_else(|| id.clone())
+        }
+    }
+}
+
+impl fmt::Display for QuickSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuickSlot::Skill { member, skill } => write!(f, "skill {}/{}", member, skill),
+            QuickSlot::Item(id) => write!(f, "item {}", id)
+        }
+    }
+}
+
+impl FromStr for QuickSlot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once(char::is_whitespace).map(|(kind, rest)| (kind, rest.trim())) {
+            Some(("skill", rest)) => match rest.split_once('/') {
+                Some((member, skill)) if !member.trim().is_empty() && !skill.trim().is_empty() => {
+                    Ok(QuickSlot::Skill { member: member.trim().to_string(), skill: skill.trim().to_string() })
+                },
+                _ => Err(format!("Expected \"skill member/name\", not \"{}\"", s))
+            },
+            Some(("item", id)) if !id.contains(char::is_whitespace) => Ok(QuickSlot::Item(id.to_string())),
+            _ => Err(format!("Expected \"skill member/name\" or \"item id\", not \"{}\"", s))
+        }
+    }
+}
+
+// Resource with what's in each slot, counting from 0 for the 1 key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hotbar {
+    slots: [Option<QuickSlot>; SLOT_COUNT],
+    cooldowns: [Duration; SLOT_COUNT]
+}
+
+impl Hotbar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The party's shortcuts, see field_skill::shortcuts, for a new game.
+    pub fn from_shortcuts(party: &Party) -> Self {
+        let mut hotbar = Self::new();
+        for (slot, (member, skill)) in field_skill::shortcuts(party).into_iter().take(SLOT_COUNT).enumerate() {
+            let member = &party.members[member];
+            hotbar.slots[slot] = Some(QuickSlot::Skill { member: member.name.clone(), skill: member.skills[skill].name.clone() });
+        }
+        hotbar
+    }
+
+    // Which slot a key uses, if any.
+    pub fn slot_for_key(key: VirtualKeyCode) -> Option<usize> {
+        KEYS.iter().position(|slot_key| *slot_key == key)
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&QuickSlot> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    // Put something in a slot, or empty it. Anything that was in another slot moves out of it,
+    // so nothing's in two. Returns false if there's no such slot.
+    pub fn set(&mut self, slot: usize, quick: Option<QuickSlot>) -> bool {
+        if slot >= SLOT_COUNT {
+            return false;
+        }
+        if let Some(quick) = &quick {
+            for other in self.slots.iter_mut().filter(|other| other.as_ref() == Some(quick)) {
+                *other = None;
+            }
+        }
+        self.slots[slot] = quick;
+        self.cooldowns[slot] = Duration::ZERO;
+        true
+    }
+
+    // The slots with something in, as (slot, what's in it).
+    pub fn slots(&self) -> impl Iterator<Item = (usize, &QuickSlot)> {
+        self.slots.iter().enumerate().filter_map(|(slot, quick)| quick.as_ref().map(|quick| (slot, quick)))
+    }
+
+    // Count down the cooldowns. Returns whether any are still going, to be drawn.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        let mut cooling = false;
+        for cooldown in &mut self.cooldowns {
+            cooling |= !cooldown.is_zero();
+            *cooldown = cooldown.saturating_sub(delta);
+        }
+        cooling
+    }
+
+    // How much of its cooldown a slot has left, from 1 just after it's used down to 0.
+    pub fn cooldown(&self, slot: usize) -> f32 {
+        self.cooldowns.get(slot).map_or(0.0, |cooldown| cooldown.as_secs_f32() / COOLDOWN.as_secs_f32())
+    }
+
+    // How many more times a slot could be used, or None if there's no limit, like for a skill
+    // that's free.
+    pub fn charges(&self, slot: usize, party: &Party, inventory: &Inventory) -> Option<u32> {
+        match self.get(slot)? {
+            QuickSlot::Skill { member, skill } => {
+                let member = party.members.iter().find(|known| known.name.eq_ignore_ascii_case(member))?;
+                let skill = member.skills.iter().find(|known| known.name.eq_ignore_ascii_case(skill))?;
+                pools::uses_left(member, skill, inventory)
+            },
+            QuickSlot::Item(id) => Some(inventory.count(id))
+        }
+    }
+
+    // Use what's in a slot on whoever needs it most. Says what happened, or why it can't be
+    // used, like the status menu.
+    pub fn use_slot(&mut self, slot: usize, party: &mut Party, status: &mut FieldStatus, events: &mut FieldEvents, inventory: &mut Inventory) -> Result<String, String> {
+        let quick = self.get(slot).cloned().ok_or("There's nothing in that slot")?;
+        if !self.cooldowns[slot].is_zero() {
+            return Err(format!("{} isn't ready yet", quick.name(inventory)));
+        }
+        let message = match &quick {
+            QuickSlot::Skill { member, skill } => {
+                let user = party.members.iter().position(|known| known.name.eq_ignore_ascii_case(member))
+                    .ok_or_else(|| format!("{} isn't in the party", member))?;
+                let known = party.members[user].skills.iter().position(|known| known.name.eq_ignore_ascii_case(skill))
+                    .ok_or_else(|| format!("{} doesn't know {}", member, skill))?;
+                let skill = &party.members[user].skills[known];
+                let target = field_skill::pick_target(party, &skill.field.clone().unwrap_or(ItemEffect::None), &skill.name)?;
+                field_skill::use_skill(party, user, known, target, status, events, inventory)?
+            },
+            QuickSlot::Item(id) => {
+                let index = inventory.slots().iter().position(|slot| slot.item.id == *id)
+                    .ok_or_else(|| format!("There aren't any {} left", quick.name(inventory)))?;
+                let item = inventory.slots()[index].item.clone();
+                match field_skill::pick_target(party, &item.effect, &item.name) {
+                    _ if item.effect == ItemEffect::None => return Err(format!("{} can't be used here", item.name)),
+                    Ok(Some(target)) => inventory.use_on(index, &mut party.members[target])?,
+                    Ok(None) => inventory.use_on_field(index, status, events)?,
+                    Err(e) => return Err(e)
+                }
+            }
+        };
+        self.cooldowns[slot] = COOLDOWN;
+        Ok(message)
+    }
+
+    // The slots with something in, along the bottom right, with their key and uses left. Ones
+    // that can't be used now are dimmed.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, party: &Party, inventory: &Inventory) {
+        let skin = accessibility.skin();
+        let scale = accessibility.text_scale();
+        let height = UiBatch::line_height(scale) * 2.0 + PADDING * 2.0;
+        let y = SCREEN_HEIGHT as f32 - height - MARGIN;
+        let mut x = SCREEN_WIDTH as f32 - MARGIN;
+        for (slot, quick) in self.slots().collect::<Vec<_>>().into_iter().rev() {
+            let label = format!("{} {}", slot + 1, quick.name(inventory));
+            let charges = self.charges(slot, party, inventory);
+            let uses = charges.map(|charges| format!("x{}", charges)).unwrap_or_default();
+            let width = UiBatch::measure_text(scale, &label).0.max(UiBatch::measure_text(scale, &uses).0) + PADDING * 2.0;
+            x -= width;
+            let color = if charges == Some(0) || self.cooldown(slot) > 0.0 { skin.dim_text } else { skin.text };
+            batch.rect(x, y, width, height, skin.window);
+            batch.text(x + PADDING, y + PADDING, scale, &label, color);
+            batch.text(x + PADDING, y + PADDING + UiBatch::line_height(scale), scale, &uses, color);
+            batch.rect(x, y + height - COOLDOWN_HEIGHT, width * self.cooldown(slot).min(1.0), COOLDOWN_HEIGHT, skin.highlight);
+            x -= MARGIN / 2.0;
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Vertex shader
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct ScreenUniforms {
+    // Size of the screen in egui points.
+    size: vec2<f32>,
+    // 1.0 if the output is sRGB and colours need converting to linear.
+    srgb_output: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> screen: ScreenUniforms;
+
+fn linear_from_gamma(gamma: vec3<f32>) -> vec3<f32> {
+    let cutoff = gamma < vec3<f32>(0.04045);
+    let lower = gamma / vec3<f32>(12.92);
+    let higher = pow((gamma + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = model.uv;
+
+    // egui gives us premultiplied sRGB colours.
+    if (screen.srgb_output > 0.5) {
+        out.color = vec4<f32>(linear_from_gamma(model.color.rgb), model.color.a);
+    } else {
+        out.color = model.color;
+    }
+
+    out.clip_position = vec4<f32>(
+        2.0 * model.position.x / screen.size.x - 1.0,
+        1.0 - 2.0 * model.position.y / screen.size.y,
+        0.0,
+        1.0
+    );
+    return out;
+}
+
+// Fragment shader
+@group(1) @binding(0)
+var t_egui: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_egui: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * textureSample(t_egui, s_egui, in.uv);
+}
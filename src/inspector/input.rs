@@ -0,0 +1,147 @@
+use instant::Instant;
+
+use egui::{Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2};
+use winit::{dpi::PhysicalSize, event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent}};
+
+// How far one line of mouse wheel scrolling moves, in points.
+const SCROLL_LINE_HEIGHT: f32 = 24.0;
+
+// Turns winit window events into egui input.
+pub struct EguiInput {
+    start_time: Instant,
+    pointer_pos: Pos2,
+    modifiers: Modifiers,
+    events: Vec<Event>
+}
+
+impl EguiInput {
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+            pointer_pos: Pos2::ZERO,
+            modifiers: Modifiers::default(),
+            events: Vec::new()
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent, pixels_per_point: f32) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_pos = Pos2::new(position.x as f32 / pixels_per_point, position.y as f32 / pixels_per_point);
+                self.events.push(Event::PointerMoved(self.pointer_pos));
+            },
+
+            WindowEvent::CursorLeft { .. } => self.events.push(Event::PointerGone),
+
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => PointerButton::Primary,
+                    MouseButton::Right => PointerButton::Secondary,
+                    MouseButton::Middle => PointerButton::Middle,
+                    MouseButton::Other(_) => return
+                };
+                self.events.push(Event::PointerButton {
+                    pos: self.pointer_pos,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: self.modifiers
+                });
+            },
+
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y) * SCROLL_LINE_HEIGHT,
+                    MouseScrollDelta::PixelDelta(delta) => Vec2::new(delta.x as f32, delta.y as f32) / pixels_per_point
+                };
+                self.events.push(Event::Scroll(delta));
+            },
+
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = Modifiers {
+                    alt: state.alt(),
+                    ctrl: state.ctrl(),
+                    shift: state.shift(),
+                    mac_cmd: cfg!(target_os = "macos") && state.logo(),
+                    command: if cfg!(target_os = "macos") { state.logo() } else { state.ctrl() }
+                };
+            },
+
+            // Control characters are sent as key events instead.
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => {
+                self.events.push(Event::Text(c.to_string()));
+            },
+
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode.and_then(translate_key) {
+                    self.events.push(Event::Key {
+                        key,
+                        pressed: input.state == ElementState::Pressed,
+                        repeat: false,
+                        modifiers: self.modifiers
+                    });
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    // Everything that has happened since the last call, ready to hand to egui.
+    pub fn take_raw_input(&mut self, size: PhysicalSize<u32>, pixels_per_point: f32) -> RawInput {
+        RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(size.width as f32, size.height as f32) / pixels_per_point
+            )),
+            pixels_per_point: Some(pixels_per_point),
+            time: Some(self.start_time.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            has_focus: true,
+            ..Default::default()
+        }
+    }
+}
+
+fn translate_key(key: VirtualKeyCode) -> Option<Key> {
+    Some(match key {
+        VirtualKeyCode::Down => Key::ArrowDown,
+        VirtualKeyCode::Left => Key::ArrowLeft,
+        VirtualKeyCode::Right => Key::ArrowRight,
+        VirtualKeyCode::Up => Key::ArrowUp,
+        VirtualKeyCode::Escape => Key::Escape,
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Back => Key::Backspace,
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::Insert => Key::Insert,
+        VirtualKeyCode::Delete => Key::Delete,
+        VirtualKeyCode::Home => Key::Home,
+        VirtualKeyCode::End => Key::End,
+        VirtualKeyCode::PageUp => Key::PageUp,
+        VirtualKeyCode::PageDown => Key::PageDown,
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => Key::Minus,
+        VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => Key::PlusEquals,
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Key::Num0,
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Key::Num1,
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Key::Num2,
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Key::Num3,
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Key::Num4,
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Key::Num5,
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Key::Num6,
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Key::Num7,
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Key::Num8,
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Key::Num9,
+        // Letters are only needed for shortcuts like copy and paste.
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::C => Key::C,
+        VirtualKeyCode::K => Key::K,
+        VirtualKeyCode::U => Key::U,
+        VirtualKeyCode::V => Key::V,
+        VirtualKeyCode::W => Key::W,
+        VirtualKeyCode::X => Key::X,
+        VirtualKeyCode::Y => Key::Y,
+        VirtualKeyCode::Z => Key::Z,
+        _ => return None
+    })
+}
@@ -0,0 +1,263 @@
+// An egui based debug window for poking at the world while the game is running.
+// Only built with the "inspector" feature.
+
+mod input;
+mod painter;
+
+use egui::{epaint::ClippedPrimitive, ComboBox, DragValue, RichText, ScrollArea, Slider, TexturesDelta, Ui};
+use wgpu::{Device, Queue, TextureFormat, TextureView};
+use winit::{dpi::PhysicalSize, event::WindowEvent, window::Window};
+
+use crate::camera::Camera;
+use crate::color_filter::ColorFilter;
+use crate::depth_of_field::{DepthOfField, DofQuality};
+use crate::renderer::{PostProcessSettings, WindowOverlay};
+use crate::transform::Transform;
+use crate::world::{Entity, Name, World};
+
+use input::EguiInput;
+use painter::EguiPainter;
+
+pub struct Inspector {
+    context: egui::Context,
+    input: EguiInput,
+
+    // Created on the first render, once we know what format the window is.
+    painter: Option<EguiPainter>,
+
+    visible: bool,
+    selected: Option<Entity>,
+    pixels_per_point: f32,
+
+    // Output from the last update, waiting to be drawn.
+    primitives: Vec<ClippedPrimitive>,
+    textures_delta: TexturesDelta
+}
+
+impl Inspector {
+    pub fn new(window: &Window) -> Self {
+        Self {
+            context: egui::Context::default(),
+            input: EguiInput::new(),
+            painter: None,
+            visible: false,
+            selected: None,
+            pixels_per_point: window.scale_factor() as f32,
+            primitives: Vec::new(),
+            textures_delta: TexturesDelta::default()
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    // Feed a window event to the inspector. Returns true if the inspector wants it to itself,
+    // e.g. the mouse is over one of its windows.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+            self.pixels_per_point = *scale_factor as f32;
+        }
+
+        if !self.visible {
+            return false;
+        }
+
+        self.input.handle_event(event, self.pixels_per_point);
+        match event {
+            WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } =>
+                self.context.wants_pointer_input(),
+            WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_) =>
+                self.context.wants_keyboard_input(),
+            _ => false
+        }
+    }
+
+    // Run the inspector UI for this frame.
+    pub fn update(&mut self, window: &Window, world: &mut World, post_process: &mut PostProcessSettings) {
+        if !self.visible {
+            self.primitives.clear();
+            return;
+        }
+
+        let raw_input = self.input.take_raw_input(window.inner_size(), self.pixels_per_point);
+        let context = self.context.clone();
+        let output = context.run(raw_input, |ctx| {
+            egui::Window::new("Inspector")
+                .default_width(320.0)
+                .show(ctx, |ui| {
+                    ScrollArea::vertical().show(ui, |ui| {
+                        ui.collapsing("Entities", |ui| self.entities_ui(ui, world));
+                        ui.collapsing("Camera", |ui| camera_ui(ui, world));
+                        ui.collapsing("Post Process", |ui| post_process_ui(ui, post_process));
+                        ui.collapsing("Resources", |ui| resources_ui(ui, world));
+                    });
+                });
+        });
+
+        self.primitives = self.context.tessellate(output.shapes);
+        self.textures_delta.append(output.textures_delta);
+    }
+
+    fn entities_ui(&mut self, ui: &mut Ui, world: &mut World) {
+        // Forget the selection if the entity has gone away.
+        if let Some(selected) = self.selected {
+            if !world.contains(selected) {
+                self.selected = None;
+            }
+        }
+
+        ui.label(format!("{} entities", world.entity_count()));
+        ScrollArea::vertical().id_source("entity_list").max_height(160.0).show(ui, |ui| {
+            for entity in world.entities().to_vec() {
+                let label = match world.get::<Name>(entity) {
+                    Some(name) => format!("{} ({})", name.0, entity.id()),
+                    None => format!("Entity {}", entity.id())
+                };
+                if ui.selectable_label(self.selected == Some(entity), label).clicked() {
+                    self.selected = Some(entity);
+                }
+            }
+        });
+
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => return
+        };
+
+        ui.separator();
+        if let Some(transform) = world.get_mut::<Transform>(selected) {
+            transform_ui(ui, transform);
+        }
+
+        for (type_name, description) in world.describe_components(selected) {
+            ui.collapsing(short_type_name(type_name), |ui| {
+                ui.label(RichText::new(description).monospace());
+            });
+        }
+    }
+}
+
+impl WindowOverlay for Inspector {
+    fn render(&mut self, device: &Device, queue: &Queue, dest_view: &TextureView, dest_format: TextureFormat, size: PhysicalSize<u32>) {
+        let painter = self.painter.get_or_insert_with(|| EguiPainter::new(device, dest_format));
+
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+        painter.update_textures(device, queue, &textures_delta);
+        painter.paint(device, queue, dest_view, size, self.pixels_per_point, &self.primitives);
+        painter.free_textures(&textures_delta);
+    }
+}
+
+fn vector_ui(ui: &mut Ui, label: &str, values: [&mut f32; 3], speed: f64) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label(label);
+        for value in values {
+            changed |= ui.add(DragValue::new(value).speed(speed)).changed();
+        }
+    });
+    changed
+}
+
+fn transform_ui(ui: &mut Ui, transform: &mut Transform) {
+    ui.label(RichText::new("Transform").strong());
+
+    let position = &mut transform.position;
+    vector_ui(ui, "Position", [&mut position.x, &mut position.y, &mut position.z], 0.05);
+
+    let mut rotation = transform.euler_degrees();
+    if vector_ui(ui, "Rotation", [&mut rotation.x, &mut rotation.y, &mut rotation.z], 1.0) {
+        transform.set_euler_degrees(rotation);
+    }
+
+    let scale = &mut transform.scale;
+    vector_ui(ui, "Scale", [&mut scale.x, &mut scale.y, &mut scale.z], 0.01);
+}
+
+fn camera_ui(ui: &mut Ui, world: &mut World) {
+    let camera = match world.resource_mut::<Camera>() {
+        Some(camera) => camera,
+        None => {
+            ui.label("No camera.");
+            return;
+        }
+    };
+
+    let eye = &mut camera.eye;
+    vector_ui(ui, "Eye", [&mut eye.x, &mut eye.y, &mut eye.z], 0.05);
+    let target = &mut camera.target;
+    vector_ui(ui, "Target", [&mut target.x, &mut target.y, &mut target.z], 0.05);
+    ui.add(Slider::new(&mut camera.fovy, 1.0..=120.0).text("FOV"));
+    ui.add(DragValue::new(&mut camera.znear).speed(0.01).prefix("Near "));
+    ui.add(DragValue::new(&mut camera.zfar).speed(1.0).prefix("Far "));
+}
+
+fn post_process_ui(ui: &mut Ui, settings: &mut PostProcessSettings) {
+    ui.add(Slider::new(&mut settings.brightness, -1.0..=1.0).text("Brightness"));
+    ui.add(Slider::new(&mut settings.contrast, 0.0..=3.0).text("Contrast"));
+    ui.add(Slider::new(&mut settings.saturation, 0.0..=3.0).text("Saturation"));
+    ui.add(Slider::new(&mut settings.vignette, 0.0..=2.0).text("Vignette"));
+    ui.horizontal(|ui| {
+        ui.color_edit_button_rgb(&mut settings.tint);
+        ui.add(Slider::new(&mut settings.tint_strength, 0.0..=1.0).text("Tint"));
+    });
+    ui.horizontal(|ui| {
+        let mut color = [settings.fade[0], settings.fade[1], settings.fade[2]];
+        if ui.color_edit_button_rgb(&mut color).changed() {
+            settings.fade[..3].copy_from_slice(&color);
+        }
+        ui.add(Slider::new(&mut settings.fade[3], 0.0..=1.0).text("Fade"));
+    });
+    ComboBox::from_label("Colour filter")
+        .selected_text(settings.color_filter.to_string())
+        .show_ui(ui, |ui| {
+            for filter in ColorFilter::ALL {
+                ui.selectable_value(&mut settings.color_filter, filter, filter.to_string());
+            }
+        });
+
+    let mut depth_of_field = settings.depth_of_field.is_some();
+    if ui.checkbox(&mut depth_of_field, "Depth of field").changed() {
+        settings.depth_of_field = depth_of_field.then(DepthOfField::default);
+    }
+    if let Some(dof) = &mut settings.depth_of_field {
+        ui.add(DragValue::new(&mut dof.focus_distance).speed(0.1).prefix("Focus "));
+        ui.add(DragValue::new(&mut dof.focus_range).speed(0.1).prefix("Range "));
+        ui.add(DragValue::new(&mut dof.falloff).speed(0.1).prefix("Falloff "));
+        ui.add(Slider::new(&mut dof.max_blur, 0.0..=16.0).text("Blur"));
+    }
+    ComboBox::from_label("Depth of field quality")
+        .selected_text(settings.dof_quality.to_string())
+        .show_ui(ui, |ui| {
+            for quality in DofQuality::ALL {
+                ui.selectable_value(&mut settings.dof_quality, quality, quality.to_string());
+            }
+        });
+
+    // The colour filter and depth of field quality are the player's settings, so they're kept.
+    if ui.button("Reset").clicked() {
+        *settings = PostProcessSettings {
+            color_filter: settings.color_filter,
+            dof_quality: settings.dof_quality,
+            ..PostProcessSettings::default()
+        };
+    }
+}
+
+fn resources_ui(ui: &mut Ui, world: &World) {
+    for (type_name, description) in world.describe_resources() {
+        ui.collapsing(short_type_name(type_name), |ui| {
+            ui.label(RichText::new(description).monospace());
+        });
+    }
+}
+
+// "ps_rpg_engine::transform::Transform" -> "Transform"
+fn short_type_name(type_name: &str) -> &str {
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
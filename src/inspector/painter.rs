@@ -0,0 +1,356 @@
+use std::{collections::HashMap, num::NonZeroU64, ops::Range};
+
+use egui::{epaint::{ClippedPrimitive, ImageDelta, Primitive}, ImageData, TextureFilter, TextureId, TexturesDelta};
+use wgpu::{BindGroup, Buffer, Device, Queue, Texture, TextureFormat, TextureView, util::{DeviceExt, StagingBelt}};
+
+use crate::logging::targets;
+use crate::pipeline_cache::{PipelineCache, PipelineId, BindGroupLayoutId, PipelineKey, texture_entry, sampler_entry, uniform_entry};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EguiVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [u8; 4],
+}
+
+impl EguiVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Unorm8x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<EguiVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScreenUniforms {
+    size: [f32; 2],
+    srgb_output: f32,
+    _padding: f32
+}
+
+// How big each of the staging belt's buffers is.
+const STAGING_CHUNK_SIZE: wgpu::BufferAddress = 256 * 1024;
+
+struct EguiTexture {
+    texture: Texture,
+    bind_group: BindGroup
+}
+
+// Draws egui's tessellated output with wgpu.
+pub struct EguiPainter {
+    // The painter lives outside the Renderer, so it keeps a cache of its own.
+    pipelines: PipelineCache,
+    render_pipeline: PipelineId,
+    texture_bind_group_layout: BindGroupLayoutId,
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    // What's in the uniform buffer, so it's only written when the window changes size.
+    uploaded_screen: Option<[f32; 2]>,
+
+    // Grown as needed and kept between frames, along with the staging belt that fills them.
+    vertex_buffer: Buffer,
+    vertex_capacity: usize,
+    index_buffer: Buffer,
+    index_capacity: usize,
+    staging_belt: StagingBelt,
+
+    // Kept between frames so painting doesn't allocate once they're big enough.
+    vertices: Vec<EguiVertex>,
+    indices: Vec<u32>,
+    draws: Vec<(egui::Rect, TextureId, Range<u32>, i32)>,
+
+    srgb_output: bool,
+    textures: HashMap<TextureId, EguiTexture>
+}
+
+impl EguiPainter {
+    pub fn new(device: &Device, output_format: TextureFormat) -> Self {
+        let mut pipelines = PipelineCache::new();
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("egui.wgsl"));
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Egui Uniform Buffer"),
+            size: std::mem::size_of::<ScreenUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let uniform_bind_group_layout = pipelines.create_bind_group_layout(device, "Egui Uniform Bind Group Layout", &[
+            uniform_entry(0, wgpu::ShaderStages::VERTEX)
+        ]);
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Egui Uniform Bind Group"),
+            layout: pipelines.get_bind_group_layout(uniform_bind_group_layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        let texture_bind_group_layout = pipelines.create_bind_group_layout(device, "Egui Texture Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        let mut pipeline_key = PipelineKey::new(
+            shader,
+            &[uniform_bind_group_layout, texture_bind_group_layout],
+            vec![EguiVertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                // egui uses premultiplied alpha.
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        );
+        // egui doesn't keep a consistent winding order.
+        pipeline_key.primitive.cull_mode = None;
+        let render_pipeline = pipelines.create_render_pipeline(device, "Egui Render Pipeline", pipeline_key);
+
+        Self {
+            pipelines,
+            render_pipeline,
+            texture_bind_group_layout,
+            uniform_buffer,
+            uniform_bind_group,
+            uploaded_screen: None,
+            vertex_buffer: Self::create_buffer(device, "Egui Vertex Buffer", wgpu::BufferUsages::VERTEX, 1024 * std::mem::size_of::<EguiVertex>()),
+            vertex_capacity: 1024,
+            index_buffer: Self::create_buffer(device, "Egui Index Buffer", wgpu::BufferUsages::INDEX, 1024 * std::mem::size_of::<u32>()),
+            index_capacity: 1024,
+            staging_belt: StagingBelt::new(STAGING_CHUNK_SIZE),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            srgb_output: output_format.describe().srgb,
+            textures: HashMap::new()
+        }
+    }
+
+    fn create_buffer(device: &Device, label: &str, usage: wgpu::BufferUsages, size: usize) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as wgpu::BufferAddress,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        })
+    }
+
+    // Apply any texture changes egui asked for. Must be called before paint().
+    pub fn update_textures(&mut self, device: &Device, queue: &Queue, delta: &TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            self.update_texture(device, queue, *id, image_delta);
+        }
+    }
+
+    // Free textures egui is finished with. Call after paint().
+    pub fn free_textures(&mut self, delta: &TexturesDelta) {
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn update_texture(&mut self, device: &Device, queue: &Queue, id: TextureId, delta: &ImageDelta) {
+        let pixels: Vec<u8> = match &delta.image {
+            ImageData::Color(image) => image.pixels.iter().flat_map(|color| color.to_array()).collect(),
+            ImageData::Font(image) => image.srgba_pixels(None).flat_map(|color| color.to_array()).collect()
+        };
+        let [width, height] = delta.image.size();
+        let size = wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1
+        };
+
+        // A patch of an existing texture.
+        if let Some([x, y]) = delta.pos {
+            let texture = match self.textures.get(&id) {
+                Some(texture) => &texture.texture,
+                None => {
+                    tracing::warn!(target: targets::RENDERER, "egui tried to update texture {:?} which doesn't exist.", id);
+                    return;
+                }
+            };
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: x as u32, y: y as u32, z: 0 },
+                    aspect: wgpu::TextureAspect::All
+                },
+                &pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * width as u32),
+                    rows_per_image: std::num::NonZeroU32::new(height as u32)
+                },
+                size
+            );
+            return;
+        }
+
+        // A whole new texture.
+        let texture = device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+            label: Some("Egui Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: if self.srgb_output { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm },
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        }, &pixels);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let filter = |filter: TextureFilter| match filter {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter(delta.options.magnification),
+            min_filter: filter(delta.options.minification),
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Egui Texture Bind Group"),
+            layout: self.pipelines.get_bind_group_layout(self.texture_bind_group_layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler)
+                }
+            ]
+        });
+
+        self.textures.insert(id, EguiTexture { texture, bind_group });
+    }
+
+    // Draw the primitives over whatever is already in dest_view.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint(&mut self, device: &Device, queue: &Queue, dest_view: &TextureView, size: winit::dpi::PhysicalSize<u32>, pixels_per_point: f32, primitives: &[ClippedPrimitive]) {
+        if primitives.is_empty() || size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        let screen = [size.width as f32 / pixels_per_point, size.height as f32 / pixels_per_point];
+        if self.uploaded_screen != Some(screen) {
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[ScreenUniforms {
+                size: screen,
+                srgb_output: if self.srgb_output { 1.0 } else { 0.0 },
+                _padding: 0.0
+            }]));
+            self.uploaded_screen = Some(screen);
+        }
+
+        // Put every mesh into one big vertex and index buffer.
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+        for primitive in primitives {
+            let mesh = match &primitive.primitive {
+                Primitive::Mesh(mesh) => mesh,
+                // We don't hand out any paint callbacks.
+                Primitive::Callback(_) => continue
+            };
+
+            let base_vertex = self.vertices.len() as i32;
+            let first_index = self.indices.len() as u32;
+            self.vertices.extend(mesh.vertices.iter().map(|vertex| EguiVertex {
+                position: [vertex.pos.x, vertex.pos.y],
+                uv: [vertex.uv.x, vertex.uv.y],
+                color: vertex.color.to_array()
+            }));
+            self.indices.extend_from_slice(&mesh.indices);
+
+            self.draws.push((primitive.clip_rect, mesh.texture_id, first_index..self.indices.len() as u32, base_vertex));
+        }
+
+        if self.indices.is_empty() {
+            return;
+        }
+
+        // Grow the buffers if this frame won't fit.
+        if self.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_buffer(device, "Egui Vertex Buffer", wgpu::BufferUsages::VERTEX, self.vertex_capacity * std::mem::size_of::<EguiVertex>());
+        }
+        if self.indices.len() > self.index_capacity {
+            self.index_capacity = self.indices.len().next_power_of_two();
+            self.index_buffer = Self::create_buffer(device, "Egui Index Buffer", wgpu::BufferUsages::INDEX, self.index_capacity * std::mem::size_of::<u32>());
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Egui Encoder.")
+        });
+
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(&self.vertices);
+        self.staging_belt.write_buffer(&mut encoder, &self.vertex_buffer, 0, NonZeroU64::new(vertex_bytes.len() as u64).unwrap(), device)
+            .copy_from_slice(vertex_bytes);
+        let index_bytes: &[u8] = bytemuck::cast_slice(&self.indices);
+        self.staging_belt.write_buffer(&mut encoder, &self.index_buffer, 0, NonZeroU64::new(index_bytes.len() as u64).unwrap(), device)
+            .copy_from_slice(index_bytes);
+        self.staging_belt.finish();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Egui Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: None
+            });
+
+            render_pass.set_pipeline(self.pipelines.get_render_pipeline(self.render_pipeline));
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+            for (clip_rect, texture_id, index_range, base_vertex) in &self.draws {
+                let texture = match self.textures.get(texture_id) {
+                    Some(texture) => texture,
+                    None => continue
+                };
+
+                // Convert the clip rect from points to pixels and keep it on screen.
+                let min_x = (clip_rect.min.x * pixels_per_point).round().clamp(0.0, size.width as f32) as u32;
+                let min_y = (clip_rect.min.y * pixels_per_point).round().clamp(0.0, size.height as f32) as u32;
+                let max_x = (clip_rect.max.x * pixels_per_point).round().clamp(min_x as f32, size.width as f32) as u32;
+                let max_y = (clip_rect.max.y * pixels_per_point).round().clamp(min_y as f32, size.height as f32) as u32;
+                if max_x == min_x || max_y == min_y {
+                    continue;
+                }
+
+                render_pass.set_scissor_rect(min_x, min_y, max_x - min_x, max_y - min_y);
+                render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                render_pass.draw_indexed(index_range.clone(), *base_vertex, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+        self.staging_belt.recall();
+    }
+}
@@ -0,0 +1,224 @@
+// Things the player can walk up to and use with the action button, like talking to an NPC,
+// picking up an item or going through a door. When more than one's in reach, the one that
+// matters most wins, so talking to someone beats the chest they're stood next to, and after
+// that whichever the player's most facing and closest to. Only things in front of the player
+// count at all. The winner's prompt goes at the bottom of the screen with an icon for what it
+// does, and changes as the player moves about.
+
+use std::{fmt, time::Duration};
+
+use cgmath::{InnerSpace, Rotation, Vector3};
+
+use crate::accessibility::Accessibility;
+use crate::field::{FieldEntity, FieldExit};
+use crate::renderer::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::transform::Transform;
+use crate::ui::{self, Color, UiBatch};
+use crate::world::{Entity, World};
+
+// How close the player has to be to use something, in metres, and in pixels for 2D fields.
+pub const INTERACT_RADIUS: f32 = 1.5;
+pub const INTERACT_RADIUS_2D: f32 = 24.0;
+// How far either side of straight ahead something can be and still count as in front.
+pub const FACING_ANGLE: cgmath::Deg<f32> = cgmath::Deg(60.0);
+
+const ICON_SCALE: f32 = 2.0;
+const PROMPT_MARGIN: f32 = 6.0;
+// How long the prompt takes to fade in when it changes.
+const FADE_TIME: Duration = Duration::from_millis(150);
+
+const TALK_ICON: [&str; 9] = [
+    ".#######.",
+    "#.......#",
+    "#.#.#.#.#",
+    "#.......#",
+    ".###.###.",
+    "...#.#...",
+    "...##....",
+    ".........",
+    "........."
+];
+const TAKE_ICON: [&str; 9] = [
+    "....#....",
+    "....#....",
+    "..#.#.#..",
+    "...###...",
+    "#########",
+    "...###...",
+    "..#.#.#..",
+    "....#....",
+    "....#...."
+];
+const DOOR_ICON: [&str; 9] = [
+    ".#######.",
+    ".#.....#.",
+    ".#.....#.",
+    ".#.....#.",
+    ".#...#.#.",
+    ".#.....#.",
+    ".#.....#.",
+    ".#.....#.",
+    "#########"
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InteractionKind {
+    Talk,
+    Take,
+    Door
+}
+
+impl InteractionKind {
+    pub const ALL: [InteractionKind; 3] = [InteractionKind::Talk, InteractionKind::Take, InteractionKind::Door];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InteractionKind::Talk => "talk",
+            InteractionKind::Take => "take",
+            InteractionKind::Door => "door"
+        }
+    }
+
+    // Higher goes first when more than one's in reach. People move about and can be missed,
+    // items can be come back for, doors stay put.
+    pub fn priority(&self) -> i32 {
+        match self {
+            InteractionKind::Talk => 2,
+            InteractionKind::Take => 1,
+            InteractionKind::Door => 0
+        }
+    }
+
+    fn icon(&self) -> (&'static [&'static str; 9], Color) {
+        match self {
+            InteractionKind::Talk => (&TALK_ICON, [1.0, 1.0, 1.0, 1.0]),
+            InteractionKind::Take => (&TAKE_ICON, [1.0, 0.9, 0.4, 1.0]),
+            InteractionKind::Door => (&DOOR_ICON, [0.8, 0.6, 0.4, 1.0])
+        }
+    }
+
+    pub fn sprite(&self) -> image::RgbaImage {
+        let (rows, color) = self.icon();
+        ui::pixel_sprite(rows, &[('#', color)])
+    }
+}
+
+impl fmt::Display for InteractionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Component for something the player can use, with the prompt that shows when it's picked,
+// like "Talk to Marle".
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interactable {
+    pub kind: InteractionKind,
+    pub prompt: String,
+    // Starts as the kind's, but can be raised for something that shouldn't be missed.
+    pub priority: i32
+}
+
+impl Interactable {
+    pub fn new(kind: InteractionKind, prompt: &str) -> Self {
+        Self { kind, prompt: prompt.to_string(), priority: kind.priority() }
+    }
+}
+
+// Resource with what the action button would use right now.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionTarget {
+    pub entity: Entity,
+    pub kind: InteractionKind,
+    pub prompt: String,
+    // How long it's been picked, for fading the prompt in.
+    pub shown: Duration
+}
+
+// What the player would use, if anything: the highest priority of everything in reach and in
+// front of them, then whatever's most straight ahead for how close it is.
+pub fn select(world: &World, player: Entity, radius: f32) -> Option<Entity> {
+    let transform = world.get::<Transform>(player)?;
+    let forward = transform.rotation.rotate_vector(Vector3::unit_z());
+    let forward = Vector3::new(forward.x, 0.0, forward.z).normalize();
+    let min_facing = cgmath::Rad::from(FACING_ANGLE).0.cos();
+
+    world.query::<Interactable>()
+        .filter(|(entity, _)| *entity != player)
+        .filter_map(|(entity, interactable)| {
+            let offset = world.get::<Transform>(entity)?.position - transform.position;
+            let offset = Vector3::new(offset.x, 0.0, offset.z);
+            let distance = offset.magnitude();
+            if distance > radius {
+                return None;
+            }
+            // Something right on top of the player is in front, whichever way they're facing.
+            let facing = if distance > f32::EPSILON { forward.dot(offset / distance) } else { 1.0 };
+            if facing < min_facing {
+                return None;
+            }
+            // Twice as far off to the side is as good as straight ahead.
+            Some((entity, interactable.priority, distance * (2.0 - facing)))
+        })
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.2.total_cmp(&a.2)))
+        .map(|(entity, _, _)| entity)
+}
+
+// Pick what the player would use from where they are now, for the prompt. Returns true if it
+// changed, or the prompt's still fading in.
+pub fn update_interaction(world: &mut World, player: Entity, radius: f32, delta: Duration) -> bool {
+    let selected = select(world, player, radius)
+        .and_then(|entity| world.get::<Interactable>(entity).map(|interactable| (entity, interactable.clone())));
+    let target = world.resource_mut::<InteractionTarget>();
+    match (selected, target) {
+        (Some((entity, interactable)), Some(target)) if target.entity == entity && target.prompt == interactable.prompt => {
+            let fading = target.shown < FADE_TIME;
+            target.shown += delta;
+            fading
+        },
+        (Some((entity, interactable)), _) => {
+            world.insert_resource(InteractionTarget { entity, kind: interactable.kind, prompt: interactable.prompt, shown: Duration::ZERO });
+            true
+        },
+        (None, Some(_)) => {
+            world.remove_resource::<InteractionTarget>();
+            true
+        },
+        (None, None) => false
+    }
+}
+
+// Spawn something to walk up to for each of a field's exits. They belong to the field, so they
+// go when the player leaves.
+pub fn spawn_exits(world: &mut World, exits: &[FieldExit]) {
+    for exit in exits {
+        let entity = world.spawn();
+        world.insert(entity, Transform::from_position(exit.position));
+        world.insert(entity, FieldEntity);
+        world.insert(entity, Interactable::new(InteractionKind::Door, &format!("Go to {}", exit.target)));
+        world.insert(entity, exit.clone());
+    }
+}
+
+// The picked target's icon and prompt, at the bottom of the screen in the middle.
+pub fn build_prompt(world: &World, batch: &mut UiBatch, accessibility: &Accessibility) {
+    let target = match world.resource::<InteractionTarget>() {
+        Some(target) => target,
+        None => return
+    };
+    let skin = accessibility.skin();
+    let alpha = (target.shown.as_secs_f32() / FADE_TIME.as_secs_f32()).min(1.0);
+    let scale = 2.0 * accessibility.text_scale();
+    let icon = target.kind.sprite();
+    let icon_width = icon.width() as f32 * ICON_SCALE;
+    let (text_width, text_height) = UiBatch::measure_text(scale, &target.prompt);
+    let height = text_height.max(icon.height() as f32 * ICON_SCALE);
+    let width = icon_width + PROMPT_MARGIN + text_width;
+
+    let x = (SCREEN_WIDTH as f32 - width) / 2.0;
+    let y = SCREEN_HEIGHT as f32 - height - PROMPT_MARGIN * 3.0;
+    let faded = |color: Color| [color[0], color[1], color[2], color[3] * alpha];
+    batch.rect(x - PROMPT_MARGIN, y - PROMPT_MARGIN, width + PROMPT_MARGIN * 2.0, height + PROMPT_MARGIN * 2.0, faded(skin.window));
+    batch.sprite(x, y + (height - icon.height() as f32 * ICON_SCALE) / 2.0, ICON_SCALE, &icon, alpha);
+    batch.text(x + icon_width + PROMPT_MARGIN, y + (height - text_height) / 2.0, scale, &target.prompt, faded(skin.text));
+}
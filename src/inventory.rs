@@ -0,0 +1,243 @@
+// What the party's carrying, and every item there is. Items are in data/items.cfg:
+//
+// [potion]
+// name = Potion
+// description = Restores 50 HP.
+// effect = restore 50 0
+//
+// The effect is "restore hp mp", "revive hp", "cure" for poison and anything else that hurts,
+// "field effect steps" for a repel, lure or sneak (see field_status.rs), "event name" for
+// something the field responds to, like lighting it up (see field_skill.rs), or "none" for
+// things that can't be used. Skills that can be used outside battle have one of these too.
+
+use std::{fmt, str::FromStr};
+
+use crate::field_skill::FieldEvents;
+use crate::field_status::{FieldBoost, FieldStatus};
+use crate::party::PartyMember;
+
+// What an item, or a skill used outside battle, does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemEffect {
+    // Heal HP and MP, like a potion or an ether.
+    Restore { hp: u32, mp: u32 },
+    // Bring someone who's been knocked out back with some HP.
+    Revive { hp: u32 },
+    // Take away status effects that hurt, like poison.
+    Cure,
+    // A repel, lure or sneak for the whole party, see field_status.rs.
+    Field(FieldBoost),
+    // Sent to the field for it to respond to, see field_skill.rs.
+    Event(String),
+    // Key items and the like, which can't be used from the menu.
+    None
+}
+
+impl fmt::Display for ItemEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemEffect::Restore { hp, mp } => write!(f, "restore {} {}", hp, mp),
+            ItemEffect::Revive { hp } => write!(f, "revive {}", hp),
+            ItemEffect::Cure => write!(f, "cure"),
+            ItemEffect::Field(boost) => write!(f, "field {}", boost),
+            ItemEffect::Event(event) => write!(f, "event {}", event),
+            ItemEffect::None => write!(f, "none")
+        }
+    }
+}
+
+impl FromStr for ItemEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let number = |word: &str| word.parse().map_err(|_| format!("bad amount \"{}\"", word));
+        match words.as_slice() {
+            ["restore", hp, mp] => Ok(ItemEffect::Restore { hp: number(hp)?, mp: number(mp)? }),
+            ["revive", hp] => Ok(ItemEffect::Revive { hp: number(hp)? }),
+            ["cure"] => Ok(ItemEffect::Cure),
+            ["field", effect, steps] => Ok(ItemEffect::Field(format!("{} {}", effect, steps).parse()?)),
+            ["event", event] => Ok(ItemEffect::Event(event.to_string())),
+            ["none"] => Ok(ItemEffect::None),
+            _ => Err(format!("Unknown effect \"{}\", expected \"restore hp mp\", \"revive hp\", \"cure\", \"field effect steps\", \"event name\" or \"none\"", s.trim()))
+        }
+    }
+}
+
+impl ItemEffect {
+    // Whether it's for the whole party, rather than someone in it.
+    pub fn is_for_party(&self) -> bool {
+        matches!(self, ItemEffect::Field(_) | ItemEffect::Event(_))
+    }
+
+    // Use it on someone, saying what happened for the menu to show, or why it wouldn't do
+    // anything. `name` is the item or skill, for the messages.
+    pub fn apply_to(&self, name: &str, member: &mut PartyMember) -> Result<String, String> {
+        let stats = &mut member.stats;
+        Ok(match self {
+            ItemEffect::None => return Err(format!("{} can't be used here", name)),
+            ItemEffect::Field(_) | ItemEffect::Event(_) => return Err(format!("{} is for the whole party", name)),
+            ItemEffect::Restore { .. } | ItemEffect::Cure if stats.is_knocked_out() => return Err(format!("{} is knocked out", member.name)),
+            ItemEffect::Restore { hp, mp } => match stats.restore(*hp, *mp) {
+                (0, 0) => return Err(format!("It won't do {} any good", member.name)),
+                (hp, 0) => format!("{} recovered {} HP", member.name, hp),
+                (0, mp) => format!("{} recovered {} MP", member.name, mp),
+                (hp, mp) => format!("{} recovered {} HP and {} MP", member.name, hp, mp)
+            },
+            ItemEffect::Revive { .. } if !stats.is_knocked_out() => return Err(format!("{} isn't knocked out", member.name)),
+            ItemEffect::Revive { hp } => {
+                stats.restore((*hp).max(1), 0);
+                format!("{} came round", member.name)
+            },
+            ItemEffect::Cure => {
+                let cured: Vec<&str> = member.status_effects.iter().filter(|effect| effect.is_harmful()).map(|effect| effect.name()).collect();
+                if cured.is_empty() {
+                    return Err(format!("It won't do {} any good", member.name));
+                }
+                let message = format!("{} was cured of {}", member.name, cured.join(" and "));
+                member.status_effects.retain(|effect| !effect.is_harmful());
+                message
+            }
+        })
+    }
+
+    // Use it on the whole party, saying what happened, or why it can't be.
+    pub fn apply_to_party(&self, name: &str, status: &mut FieldStatus, events: &mut FieldEvents) -> Result<String, String> {
+        match self {
+            ItemEffect::Field(boost) => Ok(status.apply(*boost)),
+            ItemEffect::Event(event) => {
+                events.send(event);
+                Ok(format!("Used {}", name))
+            },
+            _ => Err(format!("{} isn't used on the field", name))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub effect: ItemEffect
+}
+
+impl Item {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut items: Vec<Item> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let id = id.trim().to_string();
+                items.push(Item { name: id.clone(), id, description: String::new(), effect: ItemEffect::None });
+                continue;
+            }
+
+            let item = items.last_mut().ok_or_else(|| format!("Line {}: expected an [item] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "name" => item.name = value.to_string(),
+                "description" => item.description = value.to_string(),
+                "effect" => item.effect = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?,
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        Ok(items)
+    }
+}
+
+// Resource with every item there is, to look them up by id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ItemCatalog(pub Vec<Item>);
+
+impl ItemCatalog {
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.0.iter().find(|item| item.id == id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InventorySlot {
+    pub item: Item,
+    pub count: u32
+}
+
+// Resource for the party's items, in the order they were picked up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inventory {
+    slots: Vec<InventorySlot>
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn slots(&self) -> &[InventorySlot] {
+        &self.slots
+    }
+
+    pub fn count(&self, id: &str) -> u32 {
+        self.slots.iter().find(|slot| slot.item.id == id).map(|slot| slot.count).unwrap_or_default()
+    }
+
+    pub fn add(&mut self, item: &Item, count: u32) {
+        match self.slots.iter_mut().find(|slot| slot.item.id == item.id) {
+            Some(slot) => slot.count += count,
+            None => self.slots.push(InventorySlot { item: item.clone(), count })
+        }
+    }
+
+    // Pick up any changes to the items, after they've been reloaded. Items that aren't in the
+    // catalog any more are kept as they were.
+    pub fn refresh(&mut self, items: &ItemCatalog) {
+        for slot in &mut self.slots {
+            if let Some(item) = items.get(&slot.item.id) {
+                slot.item = item.clone();
+            }
+        }
+    }
+
+    // Take some away. Returns false, and takes nothing, if there aren't that many.
+    pub fn remove(&mut self, id: &str, count: u32) -> bool {
+        let index = match self.slots.iter().position(|slot| slot.item.id == id && slot.count >= count) {
+            Some(index) => index,
+            None => return false
+        };
+        self.slots[index].count -= count;
+        if self.slots[index].count == 0 {
+            self.slots.remove(index);
+        }
+        true
+    }
+
+    // Use the item in a slot on a party member, using one up. Items that wouldn't do
+    // anything aren't used, and the reason why comes back instead, for the menu to show.
+    pub fn use_on(&mut self, slot: usize, member: &mut PartyMember) -> Result<String, String> {
+        let item = match self.slots.get(slot) {
+            Some(slot) => slot.item.clone(),
+            None => return Err("There's nothing there".to_string())
+        };
+        let message = item.effect.apply_to(&item.name, member)?;
+        self.remove(&item.id, 1);
+        Ok(message)
+    }
+
+    // Use an item that's for the whole party, like a repel, using one up.
+    pub fn use_on_field(&mut self, slot: usize, status: &mut FieldStatus, events: &mut FieldEvents) -> Result<String, String> {
+        let item = match self.slots.get(slot) {
+            Some(slot) => slot.item.clone(),
+            None => return Err("There's nothing there".to_string())
+        };
+        let message = item.effect.apply_to_party(&item.name, status, events)?;
+        self.remove(&item.id, 1);
+        Ok(message)
+    }
+}
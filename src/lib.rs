@@ -0,0 +1,109 @@
+pub mod renderer;
+pub mod pipeline_cache;
+pub mod font;
+pub mod bidi;
+pub mod ui;
+pub mod menu;
+pub mod tween;
+pub mod debug_overlay;
+pub mod gpu_profiler;
+pub mod world;
+pub mod transform;
+pub mod hierarchy;
+pub mod camera;
+pub mod field;
+pub mod field_camera;
+pub mod tilemap;
+pub mod model;
+pub mod logging;
+pub mod rng;
+pub mod parse;
+pub mod assets;
+pub mod mods;
+pub mod display;
+pub mod cursor;
+pub mod pointer;
+pub mod flags;
+pub mod party;
+pub mod inventory;
+pub mod formation;
+pub mod combat;
+pub mod loot;
+pub mod battle_scene;
+pub mod battle_script;
+pub mod auto_battle;
+pub mod field_status;
+pub mod field_skill;
+pub mod hotbar;
+pub mod darkness;
+pub mod trap;
+pub mod puzzle;
+pub mod game_clock;
+pub mod platform;
+pub mod telemetry;
+pub mod achievements;
+pub mod save;
+pub mod save_menu;
+pub mod status_menu;
+pub mod title;
+pub mod minigame;
+pub mod battle_ui;
+pub mod battle_sequence;
+pub mod battle_transition;
+pub mod timed_hit;
+pub mod stagger;
+pub mod pools;
+pub mod combo;
+pub mod summon;
+pub mod battle_report;
+pub mod warp_menu;
+pub mod name_entry;
+pub mod movie;
+pub mod subtitles;
+pub mod message;
+pub mod cinematic;
+pub mod strings;
+pub mod animation;
+pub mod attachment;
+pub mod spring_bone;
+pub mod schedule;
+pub mod dialogue;
+pub mod hit_reaction;
+pub mod emote;
+pub mod vision;
+pub mod chase;
+pub mod pickup;
+pub mod water;
+pub mod player_controller;
+pub mod interaction;
+pub mod mover;
+pub mod prop_state;
+pub mod ghost;
+pub mod play_stats;
+pub mod accessibility;
+pub mod color_filter;
+pub mod depth_of_field;
+pub mod screen_effects;
+pub mod music;
+pub mod mixer;
+pub mod validate;
+
+// Desktop only, these need a filesystem or threads.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod paths;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod console;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod frame_limiter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod prefetch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod data_watch;
+
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(feature = "inspector")]
+pub mod inspector;
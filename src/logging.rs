@@ -0,0 +1,96 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use tracing_appender::{non_blocking::WorkerGuard, rolling::{RollingFileAppender, Rotation}};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::paths;
+
+// Log targets for each subsystem, so they can be filtered separately.
+// e.g. "warn,renderer=debug" to only see warnings apart from the renderer.
+pub mod targets {
+    pub const ENGINE: &str = "engine";
+    pub const RENDERER: &str = "renderer";
+    pub const ASSETS: &str = "assets";
+    pub const BATTLE: &str = "battle";
+    pub const SCRIPT: &str = "script";
+}
+
+// Used when RUST_LOG isn't set. wgpu is very chatty at info.
+pub const DEFAULT_FILTER: &str = "info,wgpu_core=warn,wgpu_hal=warn,naga=warn";
+
+#[cfg(not(target_arch = "wasm32"))]
+const LOG_FILE_PREFIX: &str = "ps_rpg_engine";
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LOG_FILES: usize = 7;
+
+// Owns the global logger. Keep it alive for as long as the game runs or buffered
+// log lines won't make it to the file. The web build logs to the browser console instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Logging {
+    filter_handle: reload::Handle<EnvFilter, Registry>,
+    log_dir: Option<PathBuf>,
+    _file_guard: Option<WorkerGuard>
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Logging {
+    // Set up logging to stdout and to a daily rotating file in the user data directory.
+    pub fn init() -> Self {
+        let filter = EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+        let (filter_layer, filter_handle) = reload::Layer::new(filter);
+
+        let log_dir = paths::log_dir();
+        let file_appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(&log_dir);
+
+        let (file_layer, file_guard, file_error) = match file_appender {
+            Ok(appender) => {
+                let (writer, guard) = tracing_appender::non_blocking(appender);
+                (Some(fmt::layer().with_writer(writer).with_ansi(false)), Some(guard), None)
+            },
+            Err(e) => (None, None, Some(e))
+        };
+
+        tracing_subscriber::registry()
+            .with(filter_layer)
+            .with(fmt::layer())
+            .with(file_layer)
+            .init();
+
+        // Can only complain once the logger exists.
+        if let Some(e) = &file_error {
+            tracing::warn!(target: targets::ENGINE, "Couldn't open a log file in {}: {}", log_dir.display(), e);
+        }
+
+        Self {
+            filter_handle,
+            log_dir: file_error.is_none().then_some(log_dir),
+            _file_guard: file_guard
+        }
+    }
+
+    // Change which log levels are shown, using the same syntax as RUST_LOG.
+    pub fn set_filter(&self, directives: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+        self.filter_handle.reload(filter).map_err(|e| e.to_string())
+    }
+
+    pub fn current_filter(&self) -> String {
+        self.filter_handle.with_current(|filter| filter.to_string())
+            .unwrap_or_default()
+    }
+
+    // Where log files are being written, if anywhere.
+    pub fn log_dir(&self) -> Option<&Path> {
+        self.log_dir.as_deref()
+    }
+}
@@ -0,0 +1,208 @@
+// What enemies drop when they're beaten and what can be stolen from them. Each enemy has a
+// table in data/loot.cfg:
+//
+// [goblin]
+// drop = potion 0.5
+// rare_drop = goblin_charm 0.05
+// steal = ether 0.4
+// rare_steal = silver_dagger 0.1
+//
+// Each line is "item chance", with the chance from 0 to 1. The rare items are tried first, so
+// an enemy gives up at most one item when it's beaten and one when it's stolen from, and a rare
+// one's announced when it turns up. Stealing only works once on each enemy.
+
+use crate::inventory::{Inventory, ItemCatalog};
+use crate::rng::RngStream;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootChance {
+    pub item: String,
+    pub chance: f32
+}
+
+// What one enemy can drop or have stolen. Anything left out can't happen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnemyLoot {
+    pub enemy: String,
+    pub drop: Option<LootChance>,
+    pub rare_drop: Option<LootChance>,
+    pub steal: Option<LootChance>,
+    pub rare_steal: Option<LootChance>
+}
+
+impl EnemyLoot {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut tables: Vec<EnemyLoot> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(enemy) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                tables.push(EnemyLoot { enemy: enemy.trim().to_string(), ..Default::default() });
+                continue;
+            }
+
+            let table = tables.last_mut().ok_or_else(|| format!("Line {}: expected an [enemy] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = item chance\"", number + 1))?;
+            let field = match key {
+                "drop" => &mut table.drop,
+                "rare_drop" => &mut table.rare_drop,
+                "steal" => &mut table.steal,
+                "rare_steal" => &mut table.rare_steal,
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            };
+            *field = Some(parse_chance(value).map_err(|e| format!("Line {}: {}", number + 1, e))?);
+        }
+        Ok(tables)
+    }
+
+    // What the enemy drops when it's beaten, if anything.
+    pub fn roll_drop(&self, rng: &mut RngStream) -> Option<Loot> {
+        roll(&self.rare_drop, &self.drop, rng)
+    }
+
+    pub fn can_steal(&self) -> bool {
+        self.steal.is_some() || self.rare_steal.is_some()
+    }
+
+    fn chances(&self) -> impl Iterator<Item = &LootChance> {
+        [&self.drop, &self.rare_drop, &self.steal, &self.rare_steal].into_iter().flatten()
+    }
+}
+
+fn parse_chance(value: &str) -> Result<LootChance, String> {
+    let (item, chance) = value.split_once(char::is_whitespace)
+        .ok_or_else(|| format!("expected \"item chance\", not \"{}\"", value))?;
+    let chance = chance.trim();
+    let chance = chance.parse().ok()
+        .filter(|chance| (0.0..=1.0).contains(chance))
+        .ok_or_else(|| format!("bad chance \"{}\"", chance))?;
+    Ok(LootChance { item: item.to_string(), chance })
+}
+
+// The rare item first, then the common one if that didn't come up.
+fn roll(rare: &Option<LootChance>, common: &Option<LootChance>, rng: &mut RngStream) -> Option<Loot> {
+    // Both are rolled every time, so what comes later doesn't depend on whether the rare one did.
+    let rare = rare.as_ref().filter(|rare| rng.chance(rare.chance));
+    let common = common.as_ref().filter(|common| rng.chance(common.chance));
+    match (rare, common) {
+        (Some(rare), _) => Some(Loot { item: rare.item.clone(), rare: true }),
+        (None, Some(common)) => Some(Loot { item: common.item.clone(), rare: false }),
+        (None, None) => None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loot {
+    pub item: String,
+    pub rare: bool
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StealResult {
+    Stole(Loot),
+    Missed,
+    // Each enemy can only be stolen from once.
+    AlreadyStolen,
+    NothingToSteal
+}
+
+// Try to steal from an enemy, marking them as stolen from if it works. The bonus is added to
+// each chance, for thieves and the gloves they wear.
+pub fn steal(loot: &EnemyLoot, stolen: &mut bool, bonus: f32, rng: &mut RngStream) -> StealResult {
+    if *stolen {
+        return StealResult::AlreadyStolen;
+    }
+    if !loot.can_steal() {
+        return StealResult::NothingToSteal;
+    }
+    let boost = |chance: &LootChance| LootChance { item: chance.item.clone(), chance: (chance.chance + bonus).clamp(0.0, 1.0) };
+    match roll(&loot.rare_steal.as_ref().map(boost), &loot.steal.as_ref().map(boost), rng) {
+        Some(loot) => {
+            *stolen = true;
+            StealResult::Stole(loot)
+        },
+        None => StealResult::Missed
+    }
+}
+
+// Resource with every enemy's loot.
+#[derive(Clone, Debug, Default)]
+pub struct LootTables {
+    tables: Vec<EnemyLoot>
+}
+
+impl LootTables {
+    // Fails if a table has an item that doesn't exist, so typos are found on load.
+    pub fn new(tables: Vec<EnemyLoot>, items: &ItemCatalog) -> Result<Self, String> {
+        for table in &tables {
+            for chance in table.chances() {
+                if items.get(&chance.item).is_none() {
+                    return Err(format!("{} has {}, which isn't an item", table.enemy, chance.item));
+                }
+            }
+        }
+        Ok(Self { tables })
+    }
+
+    pub fn get(&self, enemy: &str) -> Option<&EnemyLoot> {
+        self.tables.iter().find(|table| table.enemy == enemy)
+    }
+
+    pub fn tables(&self) -> &[EnemyLoot] {
+        &self.tables
+    }
+}
+
+// What the party gets for winning a battle, for the rewards screen to show.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VictoryRewards {
+    pub loot: Vec<Loot>
+}
+
+impl VictoryRewards {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Roll the drops for everyone beaten. Enemies without a table drop nothing.
+    pub fn roll<'a>(tables: &LootTables, enemies: impl IntoIterator<Item = &'a str>, rng: &mut RngStream) -> Self {
+        let loot = enemies.into_iter()
+            .filter_map(|enemy| tables.get(enemy)?.roll_drop(rng))
+            .collect();
+        Self { loot }
+    }
+
+    // Put everything in the inventory, returning a line for each item with how many there were,
+    // in the order they first dropped. Rare ones get a line of their own before the rest.
+    pub fn apply(&self, inventory: &mut Inventory, items: &ItemCatalog) -> Vec<String> {
+        let mut counts: Vec<(&Loot, u32)> = Vec::new();
+        for loot in &self.loot {
+            match counts.iter_mut().find(|(counted, _)| *counted == loot) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((loot, 1))
+            }
+        }
+
+        let mut rare = Vec::new();
+        let mut lines = Vec::new();
+        for (loot, count) in counts {
+            let item = match items.get(&loot.item) {
+                Some(item) => item,
+                None => continue
+            };
+            inventory.add(item, count);
+            if loot.rare {
+                rare.push(format!("Rare item! {}", item.name));
+            }
+            lines.push(if count > 1 { format!("{} x{}", item.name, count) } else { item.name.clone() });
+        }
+        rare.extend(lines);
+        rare
+    }
+}
@@ -1,30 +1,784 @@
-use winit::{event_loop::{EventLoop, ControlFlow}, window::{WindowBuilder}, event::{Event, WindowEvent}};
+// The web build starts from the library's wasm entry point instead, see web.rs.
+#![cfg_attr(target_arch = "wasm32", no_main)]
 
-mod renderer;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io, path::{Path, PathBuf}, sync::{mpsc, Arc}, time::{Duration, Instant}};
+
+#[cfg(not(target_arch = "wasm32"))]
+use winit::{event_loop::{EventLoop, ControlFlow}, window::{Window, WindowBuilder}, event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode}};
+
+#[cfg(not(target_arch = "wasm32"))]
+use cgmath::{EuclideanSpace, Point3, Quaternion, Rad, Rotation3, Vector3};
+
+#[cfg(not(target_arch = "wasm32"))]
+use ps_rpg_engine::{
+    renderer,
+    accessibility::{Accessibility, TextSpeed},
+    animation::{self, AnimationEvents},
+    attachment,
+    hierarchy,
+    assets::{AssetError, AssetManifest, AssetServer},
+    mods::{self, ModCommands},
+    camera::Camera,
+    color_filter::ColorFilter,
+    depth_of_field::{DepthOfField, DofQuality},
+    screen_effects::{self, EffectCommand, ScreenEffects},
+    music::{self, MusicPlayer},
+    mixer::{self, Bus, Mixer},
+    achievements::{Achievements, AchievementDefinition},
+    config::{self, Config},
+    console::{self, Console, ConsoleCommand},
+    cursor::{Cursor, CursorStyle},
+    debug_overlay::{DebugOverlay, DebugInfo, FrameStats},
+    display::{self, WindowMode, WindowPlacement},
+    flags::GameFlags,
+    font::{self, FallbackFont, FontAtlas, FontSource},
+    bidi::LanguageLayout,
+    hit_reaction::{self, HitEvent, HitEvents, HitReaction},
+    emote::{self, EmoteKind},
+    vision::{self, DetectionEvents, VisionBlocker, VisionCone},
+    chase::{self, CatchEvents, Chaser},
+    pickup::{self, FieldPickup, PickupToasts, Respawn},
+    water::{self, Swimmer, WaterRegion},
+    player_controller::{MovementMode, PlayerController},
+    interaction::{self, InteractionKind, InteractionTarget},
+    mover::{self, FieldMover, LoopMode, Mover, MoverTrack, Passenger},
+    prop_state::{self, PropState},
+    ghost::{self, GhostTrack},
+    telemetry::{self, BattleOutcome, NullTelemetry, Telemetry, TelemetryEvent},
+    frame_limiter::{self, FrameLimiter},
+    field::{self, FieldDescriptor, FieldExit, FieldMap, FieldProp, FieldSpawn},
+    field_camera::{self, CameraCommand, FieldCameras},
+    game_clock::{self, GameClock},
+    auto_battle::{self, AutoBattle},
+    field_skill::{self, FieldEventResponse, FieldEvents},
+    field_status::{self, FieldBoost, FieldEffect, FieldStatus, PartyTick},
+    hotbar::{self, Hotbar, QuickSlot},
+    darkness::{self, Darkness, FieldDarkness},
+    trap::{self, FieldTrap, TrapKind},
+    puzzle::{self, FieldPuzzle, FieldPuzzles},
+    battle_scene::BattleScenes,
+    battle_script::{BattleAction, BattleDirector, BattleScript, BattleScripts},
+    battle_report::{self, BattleReport, BattleReports},
+    combo::{ComboTech, ComboTechs},
+    summon::{SummonDefinition, Summons},
+    pools::{self, PoolDefinition, Pools, SkillCost},
+    formation::{BattleSetup, EncounterTable, Formation, Formations, Row},
+    combat::CombatConfig,
+    schedule::{self, NpcDefinition, NpcSchedules, ScheduleWalk, ScheduledNpc},
+    dialogue::{DialogueContext, NpcDialogue, NpcDialogues},
+    logging::{Logging, targets},
+    model::{self, ModelBatch, ModelData, ModelInstance, MorphWeights},
+    movie::{Movie, MoviePlayer},
+    paths,
+    platform::{self, Platform},
+    prefetch::FieldPrefetcher,
+    data_watch::{self, DataWatcher},
+    pointer::{Pointer, PointerEvent, PointerResponse},
+    play_stats::{self, PlayStats},
+    rng::{self, Rng},
+    save::{self, SaveError, SaveGame, SuspendState},
+    save_menu::{SaveMenu, SaveMenuAction, SaveMenuMode, SlotInfo},
+    status_menu::{StatusMenu, StatusMenuAction},
+    minigame::{MiniGameLaunch, MiniGames, TimingGame},
+    title::{TitleAction, TitleBackground, TitleConfig, TitleScreen},
+    party::{EquipSlot, Equipment, Gender, Party, PartyMember, Skill, Stats, StatusEffect, TargetType},
+    inventory::{Inventory, Item, ItemCatalog, ItemEffect},
+    loot::{self, LootTables, StealResult, VictoryRewards},
+    warp_menu::{WarpChoice, WarpMenu, WarpPreset},
+    name_entry::{self, KeyboardLayout, NameEntry},
+    message::{MessageWindow, Overflow},
+    cinematic::Cinematic,
+    battle_transition::{BattleAssets, BattleTransition, TransitionStyle},
+    menu::{KeyRepeat, MenuSounds},
+    strings::{self, StringTable},
+    spring_bone,
+    tilemap::Tilemap,
+    transform::Transform,
+    ui::{UiBatch, UiImageId},
+    validate::{self, GameData, Severity, ValidationIssue, ValidationReport},
+    world::{Entity, World, Name}
+};
+#[cfg(all(feature = "inspector", not(target_arch = "wasm32")))]
+use ps_rpg_engine::inspector::Inspector;
+
+// How often to check the console while waiting in low power mode.
+#[cfg(not(target_arch = "wasm32"))]
+const LOW_POWER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// There's only the one field for now.
+#[cfg(not(target_arch = "wasm32"))]
+const FIELD: &str = "test_field";
+#[cfg(not(target_arch = "wasm32"))]
+const LOCATION: &str = "Test Field";
 
 // Run the game window. This won't return until the window closes.
-pub async fn run_game_window() {
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_game_window(logging: Logging) {
     // Create the window.
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut config = Config::load();
+    let window = display::restore_placement(WindowBuilder::new(), &event_loop, config.window_placement.as_ref())
+        .build(&event_loop)
+        .unwrap();
+
+    // Create the renderer. Whatever's loaded for the whole game is recorded under "game".
+    let assets = AssetServer::default().with_mods(mods::enabled_dirs(&mods::find_mods(&paths::mods_dir()), &config.mods));
+    for dir in assets.mods() {
+        tracing::info!(target: targets::ASSETS, "Using the mod in {}", dir.display());
+    }
+    assets.set_language(&config.language);
+    let game_assets = assets.for_owner("game");
+    let manifest = load_manifest(&assets).await;
+    let mut renderer = renderer::Renderer::new(&window, &game_assets).await;
+
+    let mut platform = platform::init();
+    // This game doesn't send telemetry anywhere, a game that does would give its own backend
+    // here. Crashes are queued from the start, in case telemetry's on.
+    telemetry::install_crash_hook(paths::telemetry_queue_path());
+    let mut telemetry = Telemetry::new(Box::new(NullTelemetry), Some(paths::telemetry_queue_path()), config.telemetry);
+    let mut achievements = Achievements::new(load_achievements(&game_assets).await);
+    achievements.load_local(&paths::achievements_path(), platform.as_mut());
+
+    display::set_location_title(&window, Some(LOCATION));
+    platform.set_location(LOCATION);
+    match display::load_window_icon(&game_assets, display::WINDOW_ICON).await {
+        Ok(icon) => window.set_window_icon(Some(icon)),
+        Err(e) => tracing::warn!(target: targets::ASSETS, "No window icon: {}", e)
+    }
+
+    // Go back to whichever mode the window was in last time.
+    let mode = config.window_mode;
+    if mode != WindowMode::Windowed {
+        change_window_mode(&window, &mut renderer, &mut config, mode);
+    }
+
+    // Fonts for whatever the built in one can't draw, with the ones for the player's language
+    // first, and which way its text goes. Redone when the language changes.
+    let fonts = load_fonts(&game_assets).await;
+    let languages = load_languages(&game_assets).await;
+    let mut ui_language = config.language.clone();
+    let mut language_layout = LanguageLayout::pick(&languages, &ui_language);
+    let mut ui_batch = UiBatch::new();
+    ui_batch.set_direction(language_layout.direction);
+    if !fonts.is_empty() {
+        let atlas = Arc::new(FontAtlas::new(&font::fallback_order(&fonts, &ui_language)));
+        renderer.set_font(&atlas);
+        ui_batch.set_font(atlas);
+    }
+    let mut frame_stats = FrameStats::new();
+    let mut debug_overlay = DebugOverlay::new();
+    let mut cursor = Cursor::new();
+    let mut pointer = Pointer::new();
+    let mut save_menu = SaveMenu::new();
+    let mut status_menu = StatusMenu::new();
+    let menu_sounds = load_menu_sounds(&game_assets).await;
+    let mut menu_keys = KeyRepeat::new();
+    let mut controller = PlayerController::new();
+    let mut minigames = MiniGames::new();
+    minigames.register("timing", TimingGame::start);
+    // The screen as it was when the save menu was opened, for the save's thumbnail.
+    let mut save_thumbnail = None;
+    let mut movie: Option<MoviePlayer> = None;
+    let mut loading_movie = None;
+    // The battle that's swirling in or on screen, its backdrop and music while they load, and
+    // one to start at the end of the frame.
+    let mut battle: Option<BattleTransition> = None;
+    let mut loading_battle = None;
+    let mut starting_battle = None;
+    let mut focused = true;
 
-    // Create the renderer.
-    let mut renderer = renderer::Renderer::new(&window).await;
+    // Nothing loads entities from field data yet, so start with a player at the origin.
+    let mut world = World::new();
+    world.insert_resource(Camera::default());
+    world.insert_resource(GameFlags::new());
+    world.insert_resource(AnimationEvents::new());
+    world.insert_resource(HitEvents::new());
+    world.insert_resource(BattleReports::new());
+    world.insert_resource(PlayStats::new());
+    world.insert_resource(GameClock::new());
+    world.insert_resource(NpcSchedules(load_npcs(&game_assets).await));
+    world.insert_resource(or_default(read_dialogue(&game_assets).await, "NPC dialogue"));
+    world.insert_resource(load_strings(&game_assets, &config.language).await);
+    let formations = or_default(read_formations(&game_assets).await, "enemy formations");
+    world.insert_resource(or_default(read_battle_scripts(&game_assets, &formations).await, "battle scripts"));
+    world.insert_resource(formations);
+    world.insert_resource(or_default(read_combat_config(&game_assets).await, "the combat config"));
+    world.insert_resource(or_default(read_battle_scenes(&game_assets).await, "battle scenes"));
+    world.insert_resource(or_default(read_combos(&game_assets).await, "combos"));
+    world.insert_resource(or_default(read_summons(&game_assets).await, "summons"));
+    let pools = or_default(read_pools(&game_assets).await, "skill cost pools");
+    status_menu.set_pools(pools.clone());
+    world.insert_resource(pools);
+    let items = or_default(read_items(&game_assets).await, "items");
+    world.insert_resource(or_default(read_loot(&game_assets, &items).await, "loot tables"));
+    let party = test_party();
+    world.insert_resource(Hotbar::from_shortcuts(&party));
+    world.insert_resource(party);
+    world.insert_resource(test_inventory(&items));
+    world.insert_resource(items);
+    world.insert_resource(AutoBattle::new());
+    world.insert_resource(FieldStatus::new());
+    world.insert_resource(FieldEvents::new());
+    world.insert_resource(ScreenEffects::new());
+    world.insert_resource(Mixer::new(mixer::SAMPLE_RATE, config.volumes));
+    world.insert_resource(config.accessibility.clone());
+
+    let rng = Rng::from_time();
+    tracing::info!(target: targets::ENGINE, "Random seed is {}", rng.seed());
+    world.insert_resource(rng);
+    let player = world.spawn();
+    world.insert(player, Name("Player".to_string()));
+    world.insert(player, Transform::from_position(Vector3::new(0.0, 0.0, 0.0)));
+    world.insert(player, HitReaction::new());
+    world.insert(player, Swimmer::new());
+    world.insert(player, Passenger);
+    match ModelData::load(&game_assets, "models/test_prop.gltf").await {
+        Ok(model) => field::insert_model(&mut world, player, renderer.create_model(&model), &model),
+        Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+    }
+
+    // The game starts on the title screen, in front of its field or picture.
+    let mut title = TitleScreen::new(load_title(&game_assets).await);
+    let mut title_image = load_title_image(&game_assets, &mut renderer, title.config()).await;
+    title.open(save::any_saves(&platform.save_dir()));
+    // With a picture in front of it, the field doesn't matter, so use the one a new game starts in.
+    let first_field = match &title.config().background {
+        TitleBackground::Field(field) => field.clone(),
+        _ => title.config().start_field.clone()
+    };
+
+    let fields = test_fields();
+    fields.record_dependencies(&assets);
+    if let Some(party) = world.resource::<Party>() {
+        log_validation(&validate_game_data(&game_assets, &manifest, &fields, party).await);
+    }
+    let mut prefetcher = FieldPrefetcher::new(&assets);
+    if !enter_field(&mut world, &mut renderer, &assets, &fields, &mut prefetcher, &first_field).await {
+        tracing::error!(target: targets::ENGINE, "No field {} to start in", first_field);
+    }
+    let mut current_field = first_field;
+    let warp_presets = load_warp_presets(&game_assets).await;
+    let mut warp_menu = WarpMenu::new();
+    let mut name_entry = NameEntry::new();
+    name_entry.set_layout(load_keyboard(&game_assets).await);
+    // Which party member the name being typed is for.
+    let mut renaming = None;
+    let mut message_window = MessageWindow::new();
+    let mut cinematic = Cinematic::new();
+    let mut warp = None;
+    // Where to put the player once the warp's done, when carrying on from a suspend save.
+    let mut resume: Option<SuspendState> = None;
+    // Warps load the next field straight away, from inside the event loop.
+    let runtime = tokio::runtime::Handle::current();
+    let mut data_watcher = DataWatcher::new(&game_assets, &data_watch::DATA_FILES);
+
+    #[cfg(feature = "inspector")]
+    let mut inspector = Inspector::new(&window);
+
+    let console = Console::spawn_stdin();
+    let mut mod_commands = ModCommands::load(assets.mods());
+
+    renderer.set_vsync(config.vsync);
+    renderer.set_lod_bias(config.lod_bias);
+    renderer.get_post_process_settings_mut().color_filter = config.color_filter;
+    renderer.get_post_process_settings_mut().dof_quality = config.dof_quality;
+    let mut frame_limiter = FrameLimiter::new();
+    frame_limiter.set_fps_cap(config.fps_cap);
+    frame_limiter.set_low_power(config.low_power);
 
     // Run the event loop.
     event_loop.run(move |event, _, control_flow| {
+        // Input and the window changing might change what's on screen. Moving the mouse only
+        // does with the game's own cursor, or when it points a menu at something else.
+        let mut pointer_event = None;
+        let mut key_repeated = false;
+        if let Event::WindowEvent { ref event, .. } = event {
+            cursor.handle_event(&window, event);
+            pointer_event = pointer.handle_event(window.inner_size(), event);
+            let moves_cursor = matches!(event, WindowEvent::CursorMoved { .. } | WindowEvent::CursorLeft { .. }) && cursor.style() == CursorStyle::Sprite;
+            if frame_limiter::changes_screen(event) || moves_cursor {
+                frame_limiter.request_redraw();
+            }
+            if let WindowEvent::Focused(now_focused) = event {
+                focused = *now_focused;
+                controller.release_all();
+                menu_keys.release_all();
+            }
+            if let WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), .. }, .. } = event {
+                key_repeated = !menu_keys.handle_key(*key, *state == ElementState::Pressed);
+            }
+            if matches!(event, WindowEvent::KeyboardInput { .. }) || pointer_event.is_some() {
+                title.wake();
+            }
+        }
+
         match event {
             // Draw
-            Event::RedrawRequested(window_id) if window_id == window.id() => match renderer.render() {
-                Ok(_) => {}
-                Err(e) => eprintln!("{:?}", e),
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                let delta = frame_stats.begin_frame();
+                prefetcher.update();
+                // 2D fields scroll to keep the player on screen. Their x and z are map pixels.
+                if let (Some(map), Some(transform)) = (world.resource::<Tilemap>(), world.get::<Transform>(player)) {
+                    renderer.set_tilemap_scroll(map.scroll_to(transform.position.x, transform.position.z));
+                }
+                if renderer.advance_ripples(delta.as_secs_f32()) {
+                    frame_limiter.request_redraw();
+                }
+                if screen_effects::update_screen_effects(&mut world, delta, renderer.get_post_process_settings_mut()) {
+                    frame_limiter.request_redraw();
+                }
+                if field_camera::update_field_cameras(&mut world, &mut renderer, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if minigames.is_running() {
+                    if let Some(result) = minigames.update(delta) {
+                        tracing::info!(target: targets::SCRIPT, "{} finished with {}", result.game, result.value);
+                        if let Some(flags) = world.resource_mut::<GameFlags>() {
+                            result.apply(flags);
+                        }
+                    }
+                    frame_limiter.request_redraw();
+                }
+                let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                if message_window.update(delta, &accessibility) {
+                    frame_limiter.request_redraw();
+                }
+                if cinematic.update(delta) {
+                    frame_limiter.request_redraw();
+                }
+                if let Some(transition) = &mut battle {
+                    if transition.update(delta, renderer.get_post_process_settings_mut()) {
+                        frame_limiter.request_redraw();
+                    }
+                    if transition.is_ready() {
+                        transition.reveal(&mut world, &mut renderer);
+                        frame_limiter.request_redraw();
+                    }
+                }
+                if status_menu.update(delta) {
+                    frame_limiter.request_redraw();
+                }
+                // Held arrows move through whichever menu's open, see menu.rs.
+                if let Some(key) = menu_keys.update(delta) {
+                    if name_entry.is_open() {
+                        name_entry.handle_key(key);
+                    } else if save_menu.is_open() {
+                        save_menu.handle_key(key);
+                    } else if warp_menu.is_open() {
+                        warp_menu.handle_key(key);
+                    } else if status_menu.is_open() {
+                        if let (Some(party), Some(inventory)) = (world.resource::<Party>(), world.resource::<Inventory>()) {
+                            status_menu.handle_key(key, party, inventory);
+                        }
+                    } else if title.is_open() {
+                        title.handle_key(key);
+                    }
+                }
+                if menu_keys.is_holding() {
+                    frame_limiter.request_redraw();
+                }
+                // Nothing plays sound effects yet, besides the log.
+                for sound in [title.take_sound(), save_menu.take_sound(), warp_menu.take_sound(), status_menu.take_sound(), name_entry.take_sound()].into_iter().flatten() {
+                    if let Some(path) = menu_sounds.get(sound) {
+                        tracing::debug!(target: targets::ENGINE, "Menu sound {} plays {} on the {} bus", sound, path, Bus::Sfx);
+                    }
+                }
+                // The title screen counts down to its attract intro, unless the load menu's open
+                // in front of it.
+                if title.is_open() && !save_menu.is_open() {
+                    let action = title.update(delta);
+                    run_title_action(action, &mut title, &mut title_image, &mut world, &mut renderer, &mut save_menu, platform.as_ref(), &mut warp, &mut resume);
+                    frame_limiter.request_redraw();
+                }
+                if game_clock::update_game_clock(&mut world, delta) {
+                    let arrivals = schedule::update_schedules(&mut world, &current_field);
+                    if !arrivals.is_empty() {
+                        let field_assets = assets.for_owner(&field::owner(&current_field));
+                        tokio::task::block_in_place(|| runtime.block_on(schedule::spawn_npcs(&mut world, &mut renderer, &field_assets, &arrivals)));
+                    }
+                }
+                for entity in schedule::update_npc_walks(&mut world, delta) {
+                    field::despawn(&mut world, &mut renderer, entity);
+                }
+                if world.query::<ScheduleWalk>().next().is_some() {
+                    frame_limiter.request_redraw();
+                }
+                if chase::update_chasers(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if mover::update_movers(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                // Follow the setting, which the console can change.
+                if telemetry.is_enabled() != config.telemetry {
+                    telemetry.set_enabled(config.telemetry);
+                }
+                telemetry.update(&mut world, delta);
+                if ui_language != config.language {
+                    ui_language = config.language.clone();
+                    language_layout = LanguageLayout::pick(&languages, &ui_language);
+                    ui_batch.set_direction(language_layout.direction);
+                    if !fonts.is_empty() {
+                        let atlas = Arc::new(FontAtlas::new(&font::fallback_order(&fonts, &ui_language)));
+                        renderer.set_font(&atlas);
+                        ui_batch.set_font(atlas);
+                    }
+                    let strings = tokio::task::block_in_place(|| runtime.block_on(load_strings(&game_assets, &ui_language)));
+                    world.insert_resource(strings);
+                    name_entry.set_layout(tokio::task::block_in_place(|| runtime.block_on(load_keyboard(&game_assets))));
+                    frame_limiter.request_redraw();
+                }
+                ghost::record(&mut world, delta);
+                if ghost::update_ghosts(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if world.resource_mut::<Hotbar>().is_some_and(|hotbar| hotbar.update(delta)) {
+                    frame_limiter.request_redraw();
+                }
+                respond_to_field_events(&mut world, &mut renderer);
+                // Flags might have changed since last frame, from a script, a field skill or the
+                // console.
+                if prop_state::update_prop_states(&mut world) {
+                    if let Some(map) = world.resource::<Tilemap>() {
+                        renderer.set_tilemap(map);
+                    }
+                    frame_limiter.request_redraw();
+                }
+                // Nothing runs cutscenes yet, so getting caught is only logged unless it's a battle.
+                if let Some(events) = world.resource_mut::<CatchEvents>() {
+                    for catch in events.take_events() {
+                        tracing::info!(target: targets::ENGINE, "Entity {} caught entity {}", catch.chaser.id(), catch.target.id());
+                        if let Some(setup) = catch.battle.and_then(|battle| roll_battle(&mut world, fields.get(&current_field), &battle)) {
+                            tracing::info!(target: targets::BATTLE, "Caught into {}", setup.formation);
+                            let style = TransitionStyle::for_battle(&setup);
+                            starting_battle = Some((setup, style));
+                        }
+                    }
+                }
+                if model::update_morph_weights(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if animation::update_animations(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if hit_reaction::update_hit_reactions(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                // Walking only happens on the field, not with a menu or anything else up. Steps
+                // still come from the walk command, nothing counts them from this yet.
+                if title.is_open() || save_menu.is_open() || status_menu.is_open() || warp_menu.is_open() || name_entry.is_open() || message_window.is_open() || minigames.is_running() || movie.is_some() || cinematic.is_playing() || battle.is_some() {
+                    controller.release_all();
+                }
+                let camera = world.resource::<Camera>().copied().unwrap_or_default();
+                if controller.update(&mut world, player, &camera, config.movement, config.always_run, delta) > 0.0 {
+                    frame_limiter.request_redraw();
+                }
+                let reach = if world.resource::<Tilemap>().is_some() { interaction::INTERACT_RADIUS_2D } else { interaction::INTERACT_RADIUS };
+                if interaction::update_interaction(&mut world, player, reach, delta) {
+                    frame_limiter.request_redraw();
+                }
+                let spot_radius = if world.resource::<Tilemap>().is_some() { emote::SPOT_RADIUS_2D } else { emote::SPOT_RADIUS };
+                emote::spot_player(&mut world, player, spot_radius);
+                vision::update_vision(&mut world, player);
+                // Nothing runs chase scripts yet, besides the log.
+                if let Some(events) = world.resource_mut::<DetectionEvents>() {
+                    for detection in events.take_events() {
+                        tracing::info!(target: targets::ENGINE, "Entity {} saw the player at ({:.1}, {:.1})", detection.guard.id(), detection.position.x, detection.position.z);
+                    }
+                }
+                if emote::update_emotes(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if pickup::update_pickups(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                let trap_radius = if world.resource::<Tilemap>().is_some() { trap::TRAP_RADIUS_2D } else { trap::TRAP_RADIUS };
+                for event in trap::update_traps(&mut world, player, trap_radius) {
+                    tracing::info!(target: targets::ENGINE, "{}", event.message);
+                    if event.hurt {
+                        let strength = world.resource::<Accessibility>().map(|accessibility| accessibility.effect_strength(1.0)).unwrap_or(1.0);
+                        if let Some(effects) = world.resource_mut::<ScreenEffects>() {
+                            for command in trap::spike_shake(strength) {
+                                effects.run(&command, renderer.get_post_process_settings_mut());
+                            }
+                        }
+                    }
+                    if let Some((field, spawn)) = event.warp {
+                        warp = Some(WarpChoice::Field { field, spawn });
+                    }
+                    frame_limiter.request_redraw();
+                }
+                if darkness::update_darkness(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                if water::update_swimming(&mut world, delta) {
+                    frame_limiter.request_redraw();
+                }
+                music::update_music(&mut world, delta);
+                // Nothing plays these yet, besides the log.
+                if let Some(music) = world.resource_mut::<MusicPlayer>() {
+                    for stinger in music.take_stingers() {
+                        tracing::debug!(target: targets::ENGINE, "Stinger {} on beat {:.0}", stinger, music.beat());
+                    }
+                }
+                hierarchy::propagate_transforms(&mut world);
+                spring_bone::update_spring_bones(&mut world, delta);
+                attachment::update_attachments(&mut world);
+                // Nothing listens for these yet, besides the log. Footsteps are logged as the
+                // sound they'd make, which is different in water.
+                let events = world.resource_mut::<AnimationEvents>().map(AnimationEvents::take_events).unwrap_or_default();
+                for event in events {
+                    let name = match event.name.as_str() {
+                        "footstep" => water::footstep_sound(&world, event.entity, &event.name),
+                        _ => event.name
+                    };
+                    tracing::debug!(target: targets::ENGINE, "Entity {} passed \"{}\" in {}", event.entity.id(), name, event.clip);
+                }
+
+                #[cfg(feature = "inspector")]
+                inspector.update(&window, &mut world, renderer.get_post_process_settings_mut());
+
+                // Build up the UI for this frame.
+                ui_batch.clear();
+                let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                if let Some(player) = &movie {
+                    player.build(&mut ui_batch, &accessibility);
+                }
+                // Sparkles and emotes go over whatever they're on, through the camera, or the
+                // scroll in 2D fields.
+                let camera = world.resource::<Camera>().copied().unwrap_or_default();
+                let scroll = match (world.resource::<Tilemap>(), world.get::<Transform>(player)) {
+                    (Some(map), Some(transform)) => Some(map.scroll_to(transform.position.x, transform.position.z)),
+                    _ => None
+                };
+                let to_screen = |point: Vector3<f32>| match scroll {
+                    Some(scroll) => Some((point.x - scroll[0], point.z - scroll[1])),
+                    None => camera.to_screen(Point3::from_vec(point), renderer::SCREEN_WIDTH as f32, renderer::SCREEN_HEIGHT as f32)
+                };
+                if let Some(darkness) = world.resource::<Darkness>() {
+                    darkness.apply(renderer.get_post_process_settings_mut(), world.get::<Transform>(player).and_then(|transform| to_screen(transform.position)));
+                }
+                water::build_ripples(&world, &mut ui_batch, to_screen);
+                pickup::build_pickups(&world, &mut ui_batch, to_screen);
+                emote::build_emotes(&world, &mut ui_batch, to_screen);
+                // Cutscenes and battles hide the HUD, and talking hides the prompt.
+                let hide_hud = cinematic.hides_hud() || battle.is_some();
+                if let Some(transition) = &battle {
+                    transition.build(&mut ui_batch);
+                }
+                cinematic.build(&mut ui_batch);
+                if !message_window.is_open() && !hide_hud {
+                    interaction::build_prompt(&world, &mut ui_batch, &accessibility);
+                }
+                message_window.build(&mut ui_batch, &accessibility);
+                if !hide_hud {
+                    if let Some(status) = world.resource::<FieldStatus>() {
+                        status.build(&mut ui_batch, &accessibility);
+                    }
+                    if let (Some(hotbar), Some(party), Some(inventory)) = (world.resource::<Hotbar>(), world.resource::<Party>(), world.resource::<Inventory>()) {
+                        hotbar.build(&mut ui_batch, &accessibility, party, inventory);
+                    }
+                }
+                debug_overlay.build(&mut ui_batch, &frame_stats, renderer.get_stats(), &DebugInfo {
+                    field: &current_field,
+                    state: if minigames.is_running() { "Mini-game" } else if title.is_open() { "Title" } else if battle.is_some() { "Battle" } else if cinematic.is_playing() { "Cutscene" } else { "Field" },
+                    entities: world.entity_count()
+                });
+                // The player's menus read from the right in languages that want them to.
+                ui_batch.set_mirrored(language_layout.mirror_menus);
+                title.build(&mut ui_batch, &accessibility, title_image);
+                save_menu.build(&mut ui_batch, &accessibility);
+                if let (Some(party), Some(inventory)) = (world.resource::<Party>(), world.resource::<Inventory>()) {
+                    status_menu.build(&mut ui_batch, &accessibility, party, inventory);
+                }
+                ui_batch.set_mirrored(false);
+                warp_menu.build(&mut ui_batch, &accessibility);
+                name_entry.build(&mut ui_batch, &accessibility, world.resource::<StringTable>());
+                minigames.build(&mut ui_batch, &accessibility);
+                achievements.build_toasts(&mut ui_batch, &accessibility);
+                if let Some(toasts) = world.resource::<PickupToasts>() {
+                    toasts.build(&mut ui_batch, &accessibility);
+                }
+                cursor.build(&mut ui_batch);
+
+                #[cfg(feature = "inspector")]
+                let overlay: Option<&mut dyn renderer::WindowOverlay> = Some(&mut inspector);
+                #[cfg(not(feature = "inspector"))]
+                let overlay: Option<&mut dyn renderer::WindowOverlay> = None;
+
+                match renderer.render(&ModelBatch::from_world(&world), &ui_batch, overlay) {
+                    Ok(_) => {}
+                    Err(e) => tracing::error!(target: targets::RENDERER, "{:?}", e),
+                }
+
+                frame_stats.end_frame();
+                frame_limiter.end_frame();
             },
 
             Event::MainEventsCleared => {
-                // Request another draw.
-                window.request_redraw();
+                // Mods' commands wait for the title screen to go, then get a little of each tick.
+                if !title.is_open() && !mod_commands.is_empty() {
+                    mod_commands.start_tick();
+                }
+                let mut typed = console.poll().into_iter();
+                while let Some(command) = typed.next().or_else(|| mod_commands.next_command()) {
+                    let mut context = ConsoleContext {
+                        logging: &logging,
+                        world: &mut world,
+                        window: &window,
+                        renderer: &mut renderer,
+                        frame_limiter: &mut frame_limiter,
+                        cursor: &mut cursor,
+                        config: &mut config,
+                        achievements: &mut achievements,
+                        platform: platform.as_mut(),
+                        assets: &assets,
+                        manifest: &manifest,
+                        loading_movie: &mut loading_movie,
+                        fields: &fields,
+                        current_field: &current_field,
+                        warp_presets: &warp_presets,
+                        warp_menu: &mut warp_menu,
+                        warp: &mut warp,
+                        name_entry: &mut name_entry,
+                        renaming: &mut renaming,
+                        message_window: &mut message_window,
+                        cinematic: &mut cinematic,
+                        starting_battle: &mut starting_battle,
+                        minigames: &mut minigames
+                    };
+                    run_console_command(&mut context, &command);
+                    frame_limiter.request_redraw();
+                }
+
+                if let Some(choice) = warp.take() {
+                    match choice {
+                        WarpChoice::Field { field, spawn } => {
+                            let entered = tokio::task::block_in_place(|| runtime.block_on(
+                                warp_to_field(&mut world, &mut renderer, &assets, &fields, &mut prefetcher, player, &field, spawn.as_deref())
+                            ));
+                            if entered {
+                                current_field = field;
+                            }
+                            if let Some(suspend) = resume.take().filter(|_| entered) {
+                                if let Some(setup) = resume_at(&mut world, &fields, player, &suspend) {
+                                    let style = TransitionStyle::for_battle(&setup);
+                                    starting_battle = Some((setup, style));
+                                }
+                            }
+                        },
+                        WarpChoice::Preset(name) => match (warp_presets.iter().find(|preset| preset.name == name), world.resource_mut::<GameFlags>()) {
+                            (Some(preset), Some(flags)) => {
+                                preset.apply(flags);
+                                tracing::info!(target: targets::ENGINE, "Applied preset \"{}\"", name);
+                            },
+                            _ => tracing::error!(target: targets::ENGINE, "No preset \"{}\"", name)
+                        }
+                    }
+                    frame_limiter.request_redraw();
+                }
+
+                // Keep the field's last frame to swirl away while the battle loads.
+                if let Some((setup, style)) = starting_battle.take() {
+                    if battle.is_none() {
+                        renderer.capture_transition_frame();
+                        loading_battle = Some(load_battle(&assets, &setup));
+                        battle = Some(BattleTransition::start(setup, style, renderer.get_post_process_settings_mut()));
+                        frame_limiter.request_redraw();
+                    }
+                }
+
+                // Battles that ended are in flags by now, and are events for achievements too.
+                let reports = world.resource_mut::<BattleReports>().map(BattleReports::take_reports).unwrap_or_default();
+                for report in reports {
+                    for event in report.events() {
+                        achievements.notify_event(&event, platform.as_mut());
+                    }
+                }
+                if let Some(flags) = world.resource_mut::<GameFlags>() {
+                    achievements.update(&flags.take_events(), platform.as_mut());
+                }
+                platform.update();
+
+                // Pick up edits to the data files. Battles will hold them back until they're
+                // over, but nothing runs battles yet, so they're always taken straight away.
+                data_watcher.update(Instant::now(), &game_assets);
+                let changed = data_watcher.take_changed(true);
+                if !changed.is_empty() {
+                    tokio::task::block_in_place(|| runtime.block_on(reload_data(&mut world, &game_assets, &changed)));
+                    if let Some(pools) = world.resource::<Pools>() {
+                        status_menu.set_pools(pools.clone());
+                    }
+                }
+
+                // Poison on the field knocked everyone out, so it's back to the title screen.
+                if world.resource_mut::<FieldStatus>().is_some_and(FieldStatus::take_game_over) && !title.is_open() {
+                    tracing::info!(target: targets::ENGINE, "Game over");
+                    status_menu.close();
+                    title_image = tokio::task::block_in_place(|| runtime.block_on(load_title_image(&game_assets, &mut renderer, title.config())));
+                    title.open(save::any_saves(&platform.save_dir()));
+                }
+
+                // The clocks don't count time in the background, on the title screen or in the
+                // save menu.
+                if let Some(stats) = world.resource_mut::<PlayStats>() {
+                    stats.set_paused(!focused || title.is_open() || save_menu.is_open());
+                }
+                // The time of day stops in the pause menu, cutscenes and battles as well.
+                if let Some(clock) = world.resource_mut::<GameClock>() {
+                    clock.set_paused(!focused || title.is_open() || save_menu.is_open() || status_menu.is_open() || minigames.is_running() || cinematic.is_playing() || battle.is_some());
+                }
+
+                // Hand the battle what it needs once it's loaded.
+                if let Some(result) = loading_battle.as_ref().and_then(|receiver| receiver.try_recv().ok()) {
+                    loading_battle = None;
+                    if let Some(transition) = &mut battle {
+                        transition.loaded(result);
+                        frame_limiter.request_redraw();
+                    }
+                }
+
+                // Start a movie once it's loaded.
+                if let Some(result) = loading_movie.as_ref().and_then(|receiver| receiver.try_recv().ok()) {
+                    loading_movie = None;
+                    match result {
+                        Ok(loaded) => {
+                            if let Some(player) = movie.take() {
+                                player.finish(&mut renderer);
+                            }
+                            movie = Some(MoviePlayer::start(loaded, &mut renderer));
+                        },
+                        Err(e) => tracing::error!(target: targets::ASSETS, "{}", e)
+                    }
+                }
+                if let Some(player) = &mut movie {
+                    player.update(&mut renderer);
+                    frame_limiter.request_redraw();
+                    if player.is_finished() {
+                        if let Some(player) = movie.take() {
+                            player.finish(&mut renderer);
+                        }
+                    }
+                }
+
+                // Request another draw, or sleep until something happens. Keep waking up now
+                // and again to check the console.
+                if frame_limiter.should_redraw() {
+                    window.request_redraw();
+                    *control_flow = ControlFlow::Poll;
+                } else {
+                    *control_flow = ControlFlow::WaitUntil(Instant::now() + LOW_POWER_POLL_INTERVAL);
+                }
             },
 
+            // Let the inspector have first go at input.
+            #[cfg(feature = "inspector")]
+            Event::WindowEvent {
+                ref event,
+                window_id
+            } if window_id == window.id() && inspector.handle_event(event) => {},
+
             Event::WindowEvent {
                 ref event,
                 window_id
@@ -32,13 +786,344 @@ pub async fn run_game_window() {
                 // Resized window.
                 WindowEvent::Resized(physical_size) => {
                     renderer.resize(*physical_size);
+                    remember_placement(&window, &mut config);
                 },
 
+                WindowEvent::Moved(_) => remember_placement(&window, &mut config),
+
                 WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                     renderer.resize(**new_inner_size);
                 },
 
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                // Menus repeat held arrows at their own pace, so the system's repeats are left out.
+                WindowEvent::KeyboardInput { .. } if key_repeated && (title.is_open() || save_menu.is_open() || warp_menu.is_open() || status_menu.is_open() || name_entry.is_open()) => {},
+
+                // Movies take the keyboard while they play, and can be skipped.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if movie.is_some() => {
+                    if let (Some(player), VirtualKeyCode::Escape | VirtualKeyCode::Return | VirtualKeyCode::Space) = (&mut movie, key) {
+                        player.skip();
+                    }
+                },
+
+                // So do mini-games.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if minigames.is_running() => minigames.handle_key(*key),
+
+                // So do battles. Nothing runs them yet, so once one's shown Escape goes back to
+                // the field.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                    ..
+                } if battle.as_ref().is_some_and(BattleTransition::is_revealed) => {
+                    if let Some(transition) = battle.take() {
+                        tracing::info!(target: targets::BATTLE, "Back to the field from {}", transition.setup().formation);
+                        transition.finish(&mut world, &mut renderer);
+                    }
+                },
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                    ..
+                } if battle.is_some() => {},
+
+                // Typing a name takes the keyboard, both the keys that move round its grid and
+                // the characters typed straight in.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if name_entry.is_open() => {
+                    let name = name_entry.handle_key(*key);
+                    rename(&mut world, renaming, name);
+                },
+                WindowEvent::ReceivedCharacter(c) if name_entry.is_open() => {
+                    name_entry.handle_char(*c);
+                },
+
+                // The save menu takes the keyboard while it's open.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if save_menu.is_open() => {
+                    let action = save_menu.handle_key(*key);
+                    if run_save_menu_action(action, &mut save_menu, &mut world, &mut renderer, platform.as_ref(), &mut save_thumbnail) {
+                        close_title(&mut title, &mut title_image, &mut renderer);
+                    }
+                },
+
+                // So does the warp menu.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if warp_menu.is_open() => warp = warp_menu.handle_key(*key),
+
+                // The message window, while someone's talking.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if message_window.is_open() => {
+                    let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                    message_window.handle_key(*key, &accessibility);
+                },
+
+                // And the status menu.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } if status_menu.is_open() => {
+                    let action = match (world.resource::<Party>(), world.resource::<Inventory>()) {
+                        (Some(party), Some(inventory)) => status_menu.handle_key(*key, party, inventory),
+                        _ => None
+                    };
+                    run_status_menu_action(action, &mut status_menu, &mut world);
+                },
+
+                // The title screen's menu, while it's showing. Other keys, like fullscreen, still work.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key @ (VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::Return)),
+                        ..
+                    },
+                    ..
+                } if title.is_open() => {
+                    let action = title.handle_key(*key);
+                    run_title_action(action, &mut title, &mut title_image, &mut world, &mut renderer, &mut save_menu, platform.as_ref(), &mut warp, &mut resume);
+                },
+
+                // Menus can be used with the mouse or a finger too, and a click or tap skips a movie.
+                WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } | WindowEvent::Touch(_) => {
+                    // Mirrored menus are clicked where they're drawn.
+                    let menu_event = pointer_event.map(|event| if language_layout.mirror_menus { event.mirrored() } else { event });
+                    // Only redraw if a menu actually changed, as the mouse moving about mostly doesn't.
+                    let mut changed = false;
+                    match (pointer_event, menu_event, &mut movie) {
+                        (Some(PointerEvent::Click { .. }), _, Some(player)) => player.skip(),
+                        (Some(event), _, None) if minigames.is_running() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            minigames.handle_pointer(event, &accessibility);
+                        },
+                        (Some(event), _, None) if name_entry.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            let response = name_entry.handle_pointer(event, &accessibility);
+                            changed = response.changed;
+                            rename(&mut world, renaming, response.action);
+                        },
+                        (_, Some(event), None) if save_menu.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            let response = save_menu.handle_pointer(event, &accessibility);
+                            changed = response.changed;
+                            if run_save_menu_action(response.action, &mut save_menu, &mut world, &mut renderer, platform.as_ref(), &mut save_thumbnail) {
+                                close_title(&mut title, &mut title_image, &mut renderer);
+                            }
+                        },
+                        (Some(event), _, None) if message_window.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            message_window.handle_pointer(event, &accessibility);
+                        },
+                        (Some(event), _, None) if warp_menu.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            let response = warp_menu.handle_pointer(event, &accessibility);
+                            changed = response.changed;
+                            warp = response.action;
+                        },
+                        (_, Some(event), None) if status_menu.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            let response = match (world.resource::<Party>(), world.resource::<Inventory>()) {
+                                (Some(party), Some(inventory)) => status_menu.handle_pointer(event, &accessibility, party, inventory),
+                                _ => PointerResponse::new(None, false)
+                            };
+                            changed = response.changed;
+                            run_status_menu_action(response.action, &mut status_menu, &mut world);
+                        },
+                        (_, Some(event), None) if title.is_open() => {
+                            let accessibility = world.resource::<Accessibility>().cloned().unwrap_or_default();
+                            let response = title.handle_pointer(event, &accessibility);
+                            changed = response.changed;
+                            run_title_action(response.action, &mut title, &mut title_image, &mut world, &mut renderer, &mut save_menu, platform.as_ref(), &mut warp, &mut resume);
+                        },
+                        _ => {}
+                    }
+                    if changed {
+                        frame_limiter.request_redraw();
+                    }
+                },
+
+                // Open the save menu, keeping hold of what's on screen for the thumbnail.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F5),
+                        ..
+                    },
+                    ..
+                } if !title.is_open() && !cinematic.is_playing() => {
+                    save_thumbnail = renderer.capture_screen().map(|screen| save::make_thumbnail(&screen));
+                    open_save_menu(&mut save_menu, &mut renderer, platform.as_ref(), SaveMenuMode::Save);
+                },
+
+                // Walking, when nothing else has the keyboard. Letting go has to be heard even
+                // if a menu opened in the meantime.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key @ (VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::Left | VirtualKeyCode::Right
+                            | VirtualKeyCode::W | VirtualKeyCode::A | VirtualKeyCode::S | VirtualKeyCode::D
+                            | VirtualKeyCode::LShift | VirtualKeyCode::RShift)),
+                        ..
+                    },
+                    ..
+                } => {
+                    controller.handle_key(*key, *state == ElementState::Pressed);
+                },
+
+                // Use whatever the prompt's showing.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Return | VirtualKeyCode::Space),
+                        ..
+                    },
+                    ..
+                } if !title.is_open() && !cinematic.is_playing() => {
+                    if let Some(target) = world.resource::<InteractionTarget>().cloned() {
+                        interact(&mut world, &target, &mut warp);
+                    }
+                },
+
+                // Open the status menu.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                    ..
+                } if !title.is_open() && !cinematic.is_playing() => status_menu.open(),
+
+                // The hotbar's quick slots, on the number keys.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3
+                            | VirtualKeyCode::Key4 | VirtualKeyCode::Key5 | VirtualKeyCode::Key6
+                            | VirtualKeyCode::Key7 | VirtualKeyCode::Key8 | VirtualKeyCode::Key9)),
+                        ..
+                    },
+                    ..
+                } if !title.is_open() && !cinematic.is_playing() => {
+                    if let Some(slot) = Hotbar::slot_for_key(*key) {
+                        let mut hotbar = world.remove_resource::<Hotbar>().unwrap_or_default();
+                        match use_field_skill(&mut world, |party, status, events, inventory| hotbar.use_slot(slot, party, status, events, inventory)) {
+                            Ok(message) => tracing::info!(target: targets::ENGINE, "{}", message),
+                            Err(e) => tracing::info!(target: targets::ENGINE, "{}", e)
+                        }
+                        world.insert_resource(hotbar);
+                        frame_limiter.request_redraw();
+                    }
+                },
+
+                // Open the load menu.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F9),
+                        ..
+                    },
+                    ..
+                } => open_save_menu(&mut save_menu, &mut renderer, platform.as_ref(), SaveMenuMode::Load),
+
+                // Toggle the debug overlay.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                    ..
+                } => debug_overlay.toggle(),
+
+                // Toggle the inspector.
+                #[cfg(feature = "inspector")]
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F2),
+                        ..
+                    },
+                    ..
+                } => inspector.toggle(),
+
+                // Start or stop dumping GPU timings to a file.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F4),
+                        ..
+                    },
+                    ..
+                } => renderer.toggle_gpu_profile_capture(Path::new("gpu_profile.csv")),
+
+                // Switch between windowed and fullscreen.
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                    ..
+                } => {
+                    let mode = if config.window_mode == WindowMode::Windowed { WindowMode::Borderless } else { WindowMode::Windowed };
+                    change_window_mode(&window, &mut renderer, &mut config, mode);
+                },
+
+                WindowEvent::CloseRequested => {
+                    save_config(&config);
+                    telemetry.flush();
+                    // Quitting in the middle of a game can be carried on from next time.
+                    if !title.is_open() {
+                        write_suspend(&world, platform.as_ref(), &current_field, player, battle.as_ref());
+                    }
+                    *control_flow = ControlFlow::Exit;
+                },
                 _  => {}
             },
             _ => {}
@@ -46,7 +1131,2436 @@ pub async fn run_game_window() {
     });
 }
 
+// Nothing sets up a party yet, so start with a couple of people to try the menus out with.
+#[cfg(not(target_arch = "wasm32"))]
+fn test_party() -> Party {
+    let skill = |name: &str, description: &str, mp_cost, target| Skill { name: name.to_string(), description: description.to_string(), mp_cost, costs: Vec::new(), target, field: None };
+    let mut aria = PartyMember::new("Aria", Stats {
+        level: 5, experience: 1240, hp: 96, max_hp: 150, mp: 12, max_mp: 40, strength: 14, magic: 6, defence: 11, speed: 9
+    });
+    aria.gender = Gender::Female;
+    aria.equip(EquipSlot::Weapon, Some(Equipment::new("Bronze Sword")));
+    aria.equip(EquipSlot::Armour, Some(Equipment::new("Leather Vest")));
+    aria.skills.push(skill("Cleave", "Hits every enemy in the front row.", 6, TargetType::AllEnemies));
+    aria.skills.push(Skill {
+        costs: vec![SkillCost::Pool { pool: "tp".to_string(), amount: 50 }],
+        ..skill("Rising Blade", "Cuts one enemy with everything built up from being hit.", 0, TargetType::Enemy)
+    });
+    let mut tobin = PartyMember::new("Tobin", Stats {
+        level: 4, experience: 980, hp: 0, max_hp: 90, mp: 35, max_mp: 60, strength: 7, magic: 15, defence: 7, speed: 12
+    });
+    tobin.gender = Gender::Male;
+    tobin.equip(EquipSlot::Weapon, Some(Equipment { reach: true, ..Equipment::new("Sling") }));
+    tobin.equip(EquipSlot::Accessory, Some(Equipment::new("Lucky Charm")));
+    tobin.row = Row::Back;
+    tobin.skills.push(Skill {
+        field: Some(ItemEffect::Restore { hp: 40, mp: 0 }),
+        ..skill("Cure", "Restores a little HP to one ally.", 4, TargetType::Ally)
+    });
+    tobin.skills.push(skill("Fire", "Burns one enemy. Slimes hate it.", 5, TargetType::Enemy));
+    tobin.skills.push(skill("Call Sprite", "Calls a sprite to fight alongside the party for a while.", 8, TargetType::User));
+    tobin.skills.push(Skill {
+        field: Some(ItemEffect::Field(FieldBoost { effect: FieldEffect::Sneak, steps: 60 })),
+        ..skill("Sneak", "Creeps about so enemies can't catch the party from behind.", 3, TargetType::AllAllies)
+    });
+    tobin.skills.push(Skill {
+        field: Some(ItemEffect::Event("reveal".to_string())),
+        ..skill("Reveal", "Shows the way through walls that aren't what they seem.", 2, TargetType::AllAllies)
+    });
+    tobin.skills.push(Skill {
+        field: Some(ItemEffect::Event(field_skill::LIGHT_EVENT.to_string())),
+        ..skill("Glow", "Lights up dark places.", 2, TargetType::AllAllies)
+    });
+    tobin.gambits = ["ally hp < 30: skill Cure", "ally down: item phoenix_down", "always: skill Fire", "always: attack weakest"]
+        .iter()
+        .filter_map(|gambit| gambit.parse().ok())
+        .collect();
+    Party::new(vec![aria, tobin])
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn test_inventory(items: &ItemCatalog) -> Inventory {
+    let mut inventory = Inventory::new();
+    for (id, count) in [("potion", 3), ("ether", 1), ("phoenix_down", 1), ("repel", 2), ("lure", 1), ("antidote", 2), ("cellar_key", 1)] {
+        match items.get(id) {
+            Some(item) => inventory.add(item, count),
+            None => tracing::warn!(target: targets::ASSETS, "There's no item \"{}\" to start with", id)
+        }
+    }
+    inventory
+}
+
+// Nothing loads field data yet, so put a couple of rows of props behind the player to have
+// something to look at.
+#[cfg(not(target_arch = "wasm32"))]
+fn test_field() -> FieldDescriptor {
+    let mut props: Vec<FieldProp> = (0..10).map(|i| FieldProp {
+        model: "models/test_prop.gltf".to_string(),
+        transform: Transform::from_position(Vector3::new((i % 5) as f32 * 1.5 - 3.0, 0.0, -4.0 - (i / 5) as f32 * 4.0)),
+        ..Default::default()
+    }).collect();
+    // The first one's part of the field's puzzle, and stays wherever it's moved to, like from
+    // the inspector.
+    props[0].name = "test_block".to_string();
+    // A gate that's shut until "gate_open" is set, from the console for now.
+    props.push(FieldProp {
+        transform: Transform::from_position(Vector3::new(-4.0, 0.0, 2.0)),
+        flag: "gate_open".to_string(),
+        states: vec![
+            PropState {
+                name: "closed".to_string(),
+                value: 0,
+                model: "models/test_prop.gltf".to_string(),
+                blockers: vec![VisionBlocker { min: [-4.5, 1.5], max: [-3.5, 2.5] }],
+                ..Default::default()
+            },
+            PropState { name: "open".to_string(), value: 1, ..Default::default() }
+        ],
+        ..Default::default()
+    });
+    // A wall that crumbles when Reveal's used here, showing a way through.
+    props.push(FieldProp {
+        transform: Transform::from_position(Vector3::new(4.0, 0.0, 2.0)),
+        flag: "test_passage".to_string(),
+        states: vec![
+            PropState {
+                name: "wall".to_string(),
+                value: 0,
+                model: "models/test_prop.gltf".to_string(),
+                blockers: vec![VisionBlocker { min: [3.5, 1.5], max: [4.5, 2.5] }],
+                ..Default::default()
+            },
+            PropState { name: "passage".to_string(), value: 1, ..Default::default() }
+        ],
+        ..Default::default()
+    });
+    FieldDescriptor {
+        background: "fields/test_field.png".to_string(),
+        props,
+        movers: vec![FieldMover {
+            name: "lift".to_string(),
+            model: "models/test_prop.gltf".to_string(),
+            track: MoverTrack::Path { points: vec![Vector3::new(-5.0, 0.0, -1.0), Vector3::new(-5.0, 2.0, -1.0)], speed: 0.5 },
+            mode: LoopMode::PingPong,
+            playing: true,
+            deck: Some([0.75, 0.75])
+        }],
+        exits: vec![FieldExit { target: "test_tilemap".to_string(), position: Vector3::new(0.0, 0.0, 2.0) }],
+        events: vec![FieldEventResponse { event: "reveal".to_string(), flag: "test_passage".to_string(), value: 1 }],
+        // The passage stays open and the block stays put until "puzzle reset".
+        puzzle: FieldPuzzle { flags: vec!["test_passage".to_string()], pieces: vec!["test_block".to_string()] },
+        traps: vec![FieldTrap {
+            id: "teleporter".to_string(),
+            kind: TrapKind::Teleport { position: Vector3::new(1.5, 0.0, -2.5) },
+            position: Vector3::new(-1.5, 0.0, 1.0),
+            flag: String::new()
+        }],
+        pickups: vec![
+            FieldPickup { id: "chest".to_string(), item: "phoenix_down".to_string(), count: 1, position: Vector3::new(-2.0, 0.0, -1.0), respawn: Respawn::Never },
+            FieldPickup { id: "herbs".to_string(), item: "potion".to_string(), count: 2, position: Vector3::new(2.0, 0.0, -1.0), respawn: Respawn::Days(1) }
+        ],
+        water: vec![WaterRegion { min: [3.0, -3.0], max: [6.0, 0.0], footstep: Some("splash".to_string()), ..Default::default() }],
+        spawns: vec![
+            FieldSpawn { name: "start".to_string(), position: Vector3::new(0.0, 0.0, 0.0) },
+            FieldSpawn { name: "door".to_string(), position: Vector3::new(0.0, 0.0, 1.5) }
+        ],
+        ..Default::default()
+    }
+}
+
+// A 2D field to try the tilemap renderer out with.
+#[cfg(not(target_arch = "wasm32"))]
+fn test_fields() -> FieldMap {
+    let mut fields = FieldMap::new();
+    fields.insert(FIELD, test_field());
+    fields.insert("test_tilemap", test_tilemap());
+    fields
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn test_tilemap() -> FieldDescriptor {
+    FieldDescriptor {
+        tilemap: "fields/test_tilemap.tmj".to_string(),
+        terrain: "tiles".to_string(),
+        exits: vec![FieldExit { target: FIELD.to_string(), position: Vector3::new(320.0, 0.0, 792.0) }],
+        spawns: vec![FieldSpawn { name: "door".to_string(), position: Vector3::new(320.0, 0.0, 760.0) }],
+        // It's a dark dungeon, with spikes in the way and a hole that drops back to the start.
+        traps: vec![
+            FieldTrap { id: "spikes".to_string(), kind: TrapKind::Spikes { damage: 10 }, position: Vector3::new(320.0, 0.0, 700.0), flag: String::new() },
+            FieldTrap {
+                id: "pit".to_string(),
+                kind: TrapKind::Pitfall { field: FIELD.to_string(), spawn: Some("start".to_string()) },
+                position: Vector3::new(360.0, 0.0, 640.0),
+                flag: String::new()
+            }
+        ],
+        darkness: Some(FieldDarkness { strength: 0.9, radius: 80.0 }),
+        ..Default::default()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_achievements(assets: &AssetServer) -> Vec<AchievementDefinition> {
+    let definitions = assets.load_bytes("data/achievements.cfg").await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| AchievementDefinition::parse_list(&String::from_utf8_lossy(&bytes)));
+    match definitions {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load achievements: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Story progress QA can jump to from the warp menu.
+#[cfg(not(target_arch = "wasm32"))]
+// The game doesn't have to have any, so data/fonts.cfg not being there is fine.
+async fn load_fonts(assets: &AssetServer) -> Vec<FallbackFont> {
+    let sources = match assets.load_bytes("data/fonts.cfg").await {
+        Ok(bytes) => FontSource::parse_list(&String::from_utf8_lossy(&bytes)),
+        Err(AssetError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => Err(e.to_string())
+    };
+    let sources = match sources {
+        Ok(sources) => sources,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load the font list: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut fonts = Vec::new();
+    for source in &sources {
+        match FallbackFont::load(assets, source).await {
+            Ok(font) => fonts.push(font),
+            Err(e) => tracing::warn!(target: targets::ASSETS, "Couldn't load the {} font: {}", source.name, e)
+        }
+    }
+    fonts
+}
+
+// The game's own Latin keyboard is used for languages without text/keyboard.cfg.
+async fn load_keyboard(assets: &AssetServer) -> KeyboardLayout {
+    let layout = match assets.load_bytes(name_entry::KEYBOARD_PATH).await {
+        Ok(bytes) => KeyboardLayout::parse(&String::from_utf8_lossy(&bytes)),
+        Err(AssetError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => return KeyboardLayout::default(),
+        Err(e) => Err(e.to_string())
+    };
+    layout.unwrap_or_else(|e| {
+        tracing::warn!(target: targets::ASSETS, "Couldn't load the keyboard: {}", e);
+        KeyboardLayout::default()
+    })
+}
+
+// Give the party member that was being renamed the name that was typed, once it's done.
+fn rename(world: &mut World, member: Option<usize>, name: Option<String>) {
+    let (member, name) = match (member, name) {
+        (Some(member), Some(name)) => (member, name),
+        _ => return
+    };
+    if let Some(member) = world.resource_mut::<Party>().and_then(|party| party.members.get_mut(member)) {
+        tracing::info!(target: targets::ENGINE, "{} is now called {}", member.name, name);
+        member.name = name;
+    }
+}
+
+// Menus are silent without data/menu_sounds.cfg.
+async fn load_menu_sounds(assets: &AssetServer) -> MenuSounds {
+    let sounds = match assets.load_bytes("data/menu_sounds.cfg").await {
+        Ok(bytes) => MenuSounds::parse(&String::from_utf8_lossy(&bytes)),
+        Err(AssetError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => return MenuSounds::default(),
+        Err(e) => Err(e.to_string())
+    };
+    sounds.unwrap_or_else(|e| {
+        tracing::warn!(target: targets::ASSETS, "Couldn't load the menu sounds: {}", e);
+        MenuSounds::default()
+    })
+}
+
+// Languages that aren't in data/languages.cfg, or all of them if it isn't there, are left to
+// right.
+async fn load_languages(assets: &AssetServer) -> Vec<LanguageLayout> {
+    let layouts = match assets.load_bytes("data/languages.cfg").await {
+        Ok(bytes) => LanguageLayout::parse_list(&String::from_utf8_lossy(&bytes)),
+        Err(AssetError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => Err(e.to_string())
+    };
+    match layouts {
+        Ok(layouts) => layouts,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load the language list: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// The translated strings for `language`. Without any, everything's in the game's own words.
+async fn load_strings(assets: &AssetServer, language: &str) -> StringTable {
+    let table = match assets.load_bytes(strings::STRINGS_PATH).await {
+        Ok(bytes) => StringTable::parse(language, &String::from_utf8_lossy(&bytes)),
+        Err(AssetError::Io(_, e)) if e.kind() == std::io::ErrorKind::NotFound => return StringTable::new(language),
+        Err(e) => Err(e.to_string())
+    };
+    table.unwrap_or_else(|e| {
+        tracing::warn!(target: targets::ASSETS, "Couldn't load the strings for {}: {}", language, e);
+        StringTable::new(language)
+    })
+}
+
+async fn load_warp_presets(assets: &AssetServer) -> Vec<WarpPreset> {
+    let presets = assets.load_bytes("data/warp_presets.cfg").await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| WarpPreset::parse_list(&String::from_utf8_lossy(&bytes)));
+    match presets {
+        Ok(presets) => presets,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load warp presets: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_npcs(assets: &AssetServer) -> Vec<NpcDefinition> {
+    let npcs = assets.load_bytes("data/npcs.cfg").await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| NpcDefinition::parse_list(&String::from_utf8_lossy(&bytes)));
+    match npcs {
+        Ok(npcs) => npcs,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load NPC schedules: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_formations(assets: &AssetServer) -> Result<Formations, String> {
+    let formations = Formation::parse_list(&load_text(assets, "data/formations.cfg").await?)?;
+    let tables = EncounterTable::parse_list(&load_text(assets, "data/encounters.cfg").await?)?;
+    Formations::new(formations, tables)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_items(assets: &AssetServer) -> Result<ItemCatalog, String> {
+    Item::parse_list(&load_text(assets, "data/items.cfg").await?).map(ItemCatalog)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_loot(assets: &AssetServer, items: &ItemCatalog) -> Result<LootTables, String> {
+    LootTables::new(loot::EnemyLoot::parse_list(&load_text(assets, "data/loot.cfg").await?)?, items)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_battle_scripts(assets: &AssetServer, formations: &Formations) -> Result<BattleScripts, String> {
+    BattleScripts::new(BattleScript::parse_list(&load_text(assets, "data/battle_scripts.cfg").await?)?, formations)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_battle_scenes(assets: &AssetServer) -> Result<BattleScenes, String> {
+    BattleScenes::parse(&load_text(assets, "data/battle_scenes.cfg").await?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_combat_config(assets: &AssetServer) -> Result<CombatConfig, String> {
+    CombatConfig::parse(&load_text(assets, "data/combat.cfg").await?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_combos(assets: &AssetServer) -> Result<ComboTechs, String> {
+    Ok(ComboTechs(ComboTech::parse_list(&load_text(assets, "data/combos.cfg").await?)?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_pools(assets: &AssetServer) -> Result<Pools, String> {
+    Ok(Pools(PoolDefinition::parse_list(&load_text(assets, "data/pools.cfg").await?)?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_summons(assets: &AssetServer) -> Result<Summons, String> {
+    Ok(Summons(SummonDefinition::parse_list(&load_text(assets, "data/summons.cfg").await?)?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_dialogue(assets: &AssetServer) -> Result<NpcDialogues, String> {
+    let text = load_text(assets, "data/dialogue.cfg").await?;
+    Ok(NpcDialogues(NpcDialogue::parse_list(&text)?))
+}
+
+// Read every data file again and check they all agree, including whether they can be read at
+// all, for a report of everything wrong at once.
+#[cfg(not(target_arch = "wasm32"))]
+async fn validate_game_data(assets: &AssetServer, manifest: &AssetManifest, fields: &FieldMap, party: &Party) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut texts = Vec::new();
+    for path in data_watch::DATA_FILES {
+        texts.push(checked(&mut report, path, load_text(assets, path).await));
+    }
+    let text = |path: &str| data_watch::DATA_FILES.iter().position(|known| *known == path).map(|index| texts[index].as_str()).unwrap_or_default();
+
+    let items = checked(&mut report, "data/items.cfg", Item::parse_list(text("data/items.cfg")));
+    let loot = checked(&mut report, "data/loot.cfg", loot::EnemyLoot::parse_list(text("data/loot.cfg")));
+    let formations = checked(&mut report, "data/formations.cfg", Formation::parse_list(text("data/formations.cfg")));
+    let encounter_tables = checked(&mut report, "data/encounters.cfg", EncounterTable::parse_list(text("data/encounters.cfg")));
+    let battle_scripts = checked(&mut report, "data/battle_scripts.cfg", BattleScript::parse_list(text("data/battle_scripts.cfg")));
+    let battle_scenes = checked(&mut report, "data/battle_scenes.cfg", BattleScenes::parse(text("data/battle_scenes.cfg")));
+    checked(&mut report, "data/combat.cfg", CombatConfig::parse(text("data/combat.cfg")));
+    checked(&mut report, "data/dialogue.cfg", NpcDialogue::parse_list(text("data/dialogue.cfg")));
+    let combos = checked(&mut report, "data/combos.cfg", ComboTech::parse_list(text("data/combos.cfg")));
+    let summons = checked(&mut report, "data/summons.cfg", SummonDefinition::parse_list(text("data/summons.cfg")));
+    let pools = checked(&mut report, "data/pools.cfg", PoolDefinition::parse_list(text("data/pools.cfg")));
+
+    let mut cross = validate::validate_data(&GameData {
+        items: &items,
+        loot: &loot,
+        formations: &formations,
+        encounter_tables: &encounter_tables,
+        battle_scripts: &battle_scripts,
+        battle_scenes: &battle_scenes,
+        combos: &combos,
+        summons: &summons,
+        pools: &pools,
+        fields,
+        party,
+        manifest
+    });
+    report.issues.append(&mut cross.issues);
+    report.issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+// What was read, or the default if it couldn't be, adding why to the report.
+#[cfg(not(target_arch = "wasm32"))]
+fn checked<T: Default>(report: &mut ValidationReport, path: &str, read: Result<T, String>) -> T {
+    read.unwrap_or_else(|e| {
+        report.issues.push(ValidationIssue { severity: Severity::Error, message: format!("{}: {}", path, e) });
+        T::default()
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn log_validation(report: &ValidationReport) {
+    for issue in &report.issues {
+        match issue.severity {
+            Severity::Error => tracing::error!(target: targets::ASSETS, "{}", issue.message),
+            Severity::Warning => tracing::warn!(target: targets::ASSETS, "{}", issue.message)
+        }
+    }
+    if !report.issues.is_empty() {
+        tracing::warn!(target: targets::ASSETS, "The game data has {} problems, run with --validate-data to list them", report.issues.len());
+    }
+}
+
+// What was read, or the default if it couldn't be, saying why.
+#[cfg(not(target_arch = "wasm32"))]
+fn or_default<T: Default>(read: Result<T, String>, what: &str) -> T {
+    read.unwrap_or_else(|e| {
+        tracing::warn!(target: targets::ASSETS, "Couldn't load {}: {}", what, e);
+        T::default()
+    })
+}
+
+// Read the data files that changed again, replacing what's in the world. Anything that fails
+// to read keeps what it had, so a typo mid-edit doesn't wipe it out.
+#[cfg(not(target_arch = "wasm32"))]
+async fn reload_data(world: &mut World, assets: &AssetServer, changed: &[String]) {
+    let any = |paths: &[&str]| paths.iter().any(|path| changed.iter().any(|changed| changed == path));
+    let warn = |what: &str, e: String| tracing::warn!(target: targets::ASSETS, "Couldn't reload {}, keeping the old ones: {}", what, e);
+
+    if any(&["data/items.cfg", "data/loot.cfg"]) {
+        // Loot's checked against the items, so they go together.
+        match read_items(assets).await {
+            Ok(items) => match read_loot(assets, &items).await {
+                Ok(loot) => {
+                    if let Some(inventory) = world.resource_mut::<Inventory>() {
+                        inventory.refresh(&items);
+                    }
+                    world.insert_resource(items);
+                    world.insert_resource(loot);
+                    tracing::info!(target: targets::ASSETS, "Reloaded items and loot");
+                },
+                Err(e) => warn("items and loot", e)
+            },
+            Err(e) => warn("items and loot", e)
+        }
+    }
+    if any(&["data/formations.cfg", "data/encounters.cfg", "data/battle_scripts.cfg"]) {
+        // And battle scripts against the formations.
+        match read_formations(assets).await {
+            Ok(formations) => match read_battle_scripts(assets, &formations).await {
+                Ok(scripts) => {
+                    world.insert_resource(formations);
+                    world.insert_resource(scripts);
+                    tracing::info!(target: targets::ASSETS, "Reloaded enemy formations and battle scripts");
+                },
+                Err(e) => warn("enemy formations and battle scripts", e)
+            },
+            Err(e) => warn("enemy formations and battle scripts", e)
+        }
+    }
+    if any(&["data/battle_scenes.cfg"]) {
+        match read_battle_scenes(assets).await {
+            Ok(scenes) => {
+                world.insert_resource(scenes);
+                tracing::info!(target: targets::ASSETS, "Reloaded battle scenes");
+            },
+            Err(e) => warn("battle scenes", e)
+        }
+    }
+    if any(&["data/combat.cfg"]) {
+        match read_combat_config(assets).await {
+            Ok(config) => {
+                world.insert_resource(config);
+                tracing::info!(target: targets::ASSETS, "Reloaded the combat config");
+            },
+            Err(e) => warn("the combat config", e)
+        }
+    }
+    if any(&["data/combos.cfg"]) {
+        match read_combos(assets).await {
+            Ok(combos) => {
+                world.insert_resource(combos);
+                tracing::info!(target: targets::ASSETS, "Reloaded combos");
+            },
+            Err(e) => warn("combos", e)
+        }
+    }
+    if any(&["data/summons.cfg"]) {
+        match read_summons(assets).await {
+            Ok(summons) => {
+                world.insert_resource(summons);
+                tracing::info!(target: targets::ASSETS, "Reloaded summons");
+            },
+            Err(e) => warn("summons", e)
+        }
+    }
+    if any(&["data/pools.cfg"]) {
+        match read_pools(assets).await {
+            Ok(pools) => {
+                world.insert_resource(pools);
+                tracing::info!(target: targets::ASSETS, "Reloaded skill cost pools");
+            },
+            Err(e) => warn("skill cost pools", e)
+        }
+    }
+    if any(&["data/dialogue.cfg"]) {
+        match read_dialogue(assets).await {
+            Ok(dialogue) => {
+                world.insert_resource(dialogue);
+                tracing::info!(target: targets::ASSETS, "Reloaded NPC dialogue");
+            },
+            Err(e) => warn("NPC dialogue", e)
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_title(assets: &AssetServer) -> TitleConfig {
+    let config = assets.load_bytes("data/title.cfg").await
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| TitleConfig::parse(&String::from_utf8_lossy(&bytes)));
+    let mut config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load the title screen: {}", e);
+            TitleConfig::default()
+        }
+    };
+    if config.start_field.is_empty() {
+        config.start_field = FIELD.to_string();
+    }
+    config
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+// The title screen's picture, if it has one rather than a field behind it.
+async fn load_title_image(assets: &AssetServer, renderer: &mut renderer::Renderer, config: &TitleConfig) -> Option<UiImageId> {
+    match &config.background {
+        TitleBackground::Image(path) => match assets.load_image(path).await {
+            Ok(image) => Some(renderer.create_ui_image(&image)),
+            Err(e) => {
+                tracing::error!(target: targets::ASSETS, "{}", e);
+                None
+            }
+        },
+        _ => None
+    }
+}
+
+async fn load_text(assets: &AssetServer, path: &str) -> Result<String, String> {
+    assets.load_bytes(path).await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .map_err(|e| e.to_string())
+}
+
+// Set up a field, taking whatever's been prefetched for it, and start prefetching the fields
+// next to it. Returns false if there's no such field.
+#[cfg(not(target_arch = "wasm32"))]
+async fn enter_field(world: &mut World, renderer: &mut renderer::Renderer, assets: &AssetServer, fields: &FieldMap, prefetcher: &mut FieldPrefetcher, name: &str) -> bool {
+    let field = match fields.get(name) {
+        Some(field) => field.clone(),
+        None => return false
+    };
+    let field_assets = assets.for_owner(&field::owner(name));
+    field.spawn_props(world, renderer, &field_assets, prefetcher.assets()).await;
+    field.spawn_movers(world, renderer, &field_assets, prefetcher.assets()).await;
+    pickup::spawn_pickups(world, name, &field.pickups);
+    trap::spawn_traps(world, &field.traps);
+    puzzle::enter_field(world, name, &field.puzzle);
+    darkness::enter_field(world, field.darkness, renderer.get_post_process_settings_mut());
+    interaction::spawn_exits(world, &field.exits);
+    let npcs = schedule::arrivals_on_load(world, name);
+    schedule::spawn_npcs(world, renderer, &field_assets, &npcs).await;
+    field.load_background(renderer, &field_assets, prefetcher.assets()).await;
+    field.load_cameras(world, &field_assets, prefetcher.assets()).await;
+    field.load_occluders(renderer, &field_assets, prefetcher.assets()).await;
+    field.load_reflections(renderer);
+    field.load_audio(world);
+    field.load_music(world, &field_assets).await;
+    if let Some(map) = field.load_tilemap(renderer, &field_assets, prefetcher.assets()).await {
+        world.insert_resource(map);
+    }
+    // Props start in the state their flags say, which can show or hide some of the map's tiles.
+    if prop_state::update_prop_states(world) {
+        if let Some(map) = world.resource::<Tilemap>() {
+            renderer.set_tilemap(map);
+        }
+    }
+    world.insert_resource(field);
+    prefetcher.prefetch_neighbours(fields, name);
+    telemetry::record(world, TelemetryEvent::FieldEntered { field: name.to_string() });
+    true
+}
+
+// Leave the current field for another one and put the player at one of its spawn points.
+// Returns whether it went.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+async fn warp_to_field(world: &mut World, renderer: &mut renderer::Renderer, assets: &AssetServer, fields: &FieldMap, prefetcher: &mut FieldPrefetcher, player: Entity, name: &str, spawn: Option<&str>) -> bool {
+    let position = match fields.get(name).map(|field| field.spawn_position(spawn)) {
+        Some(Ok(position)) => position,
+        Some(Err(e)) => {
+            tracing::error!(target: targets::ENGINE, "{} in {}", e, name);
+            return false;
+        },
+        None => {
+            tracing::error!(target: targets::ENGINE, "No field \"{}\"", name);
+            return false;
+        }
+    };
+
+    tracing::info!(target: targets::ENGINE, "Warping to {}", name);
+    // Recordings and ghosts only make sense in the field they're in.
+    ghost::despawn_ghosts(world);
+    if ghost::stop_recording(world).is_some() {
+        tracing::warn!(target: targets::ENGINE, "Left the field, so the ghost recording was thrown away");
+    }
+    puzzle::leave_field(world);
+    field::unload(world, renderer);
+    enter_field(world, renderer, assets, fields, prefetcher, name).await;
+    if let Some(transform) = world.get_mut::<Transform>(player) {
+        transform.position = position;
+    }
+    true
+}
+
+// The list of assets the game ships with, for the asset report.
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_manifest(assets: &AssetServer) -> AssetManifest {
+    match assets.load_bytes("data/assets.manifest").await {
+        Ok(bytes) => AssetManifest::parse(&String::from_utf8_lossy(&bytes)),
+        Err(e) => {
+            tracing::warn!(target: targets::ASSETS, "Couldn't load the asset manifest: {}", e);
+            AssetManifest::default()
+        }
+    }
+}
+
+// Load a movie in the background, it could be big.
+#[cfg(not(target_arch = "wasm32"))]
+// Load a battle's backdrop and music in the background.
+fn load_battle(assets: &AssetServer, setup: &BattleSetup) -> mpsc::Receiver<Result<BattleAssets, AssetError>> {
+    let (sender, receiver) = mpsc::channel();
+    let assets = assets.clone();
+    let scene = setup.scene.clone();
+    tokio::spawn(async move {
+        let _ = sender.send(BattleAssets::load(&assets, &scene).await);
+    });
+    receiver
+}
+
+fn load_movie(assets: &AssetServer, name: &str, language: &str) -> mpsc::Receiver<Result<Movie, AssetError>> {
+    let (sender, receiver) = mpsc::channel();
+    let assets = assets.clone();
+    let name = name.to_string();
+    let language = language.to_string();
+    tokio::spawn(async move {
+        let _ = sender.send(Movie::load(&assets, &name, &language).await);
+    });
+    receiver
+}
+
+// Show what's in each save slot.
+#[cfg(not(target_arch = "wasm32"))]
+fn open_save_menu(menu: &mut SaveMenu, renderer: &mut renderer::Renderer, platform: &dyn Platform, mode: SaveMenuMode) {
+    let save_dir = platform.save_dir();
+    let slots = (0..save::SLOT_COUNT).map(|slot| {
+        let path = save::slot_path(&save_dir, slot);
+        match SaveGame::read_from(&path) {
+            Ok(save_game) => {
+                let thumbnail = save_game.thumbnail.as_ref().map(|thumbnail| renderer.create_ui_image(thumbnail));
+                Some(SlotInfo::new(&save_game, thumbnail))
+            },
+            Err(SaveError::Io(e)) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                tracing::warn!(target: targets::ENGINE, "Couldn't read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }).collect();
+    menu.open(mode, slots);
+}
+
+// Do what was picked on the title screen.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+fn run_title_action(action: Option<TitleAction>, title: &mut TitleScreen, title_image: &mut Option<UiImageId>, world: &mut World, renderer: &mut renderer::Renderer, save_menu: &mut SaveMenu, platform: &dyn Platform, warp: &mut Option<WarpChoice>, resume: &mut Option<SuspendState>) {
+    match action {
+        // Start over from scratch, in the field new games start in.
+        Some(TitleAction::NewGame) => {
+            world.insert_resource(GameFlags::new());
+            world.insert_resource(PlayStats::new());
+            world.insert_resource(GameClock::new());
+            let party = test_party();
+            world.insert_resource(Hotbar::from_shortcuts(&party));
+            world.insert_resource(party);
+            world.insert_resource(FieldPuzzles::new());
+            world.insert_resource(FieldStatus::new());
+            let inventory = world.resource::<ItemCatalog>().map(test_inventory).unwrap_or_default();
+            world.insert_resource(inventory);
+            let config = title.config();
+            *warp = Some(WarpChoice::Field { field: config.start_field.clone(), spawn: config.start_spawn.clone() });
+            close_title(title, title_image, renderer);
+        },
+        // Quitting last time leaves a suspend save, which goes straight back to where the
+        // player was. Otherwise it's the load menu, and the title screen goes once a save's
+        // been loaded.
+        Some(TitleAction::Continue) => match take_suspend(world, platform) {
+            Some(suspend) => {
+                *warp = Some(WarpChoice::Field { field: suspend.field.clone(), spawn: None });
+                *resume = Some(suspend);
+                close_title(title, title_image, renderer);
+            },
+            None => open_save_menu(save_menu, renderer, platform, SaveMenuMode::Load)
+        },
+        // There isn't a settings screen yet, everything's set from the console for now.
+        Some(TitleAction::Settings) => tracing::info!(target: targets::ENGINE, "There's no settings screen yet, try \"help\" in the console"),
+        // Nothing runs scripts yet, besides the log.
+        Some(TitleAction::Attract(script)) => tracing::info!(target: targets::SCRIPT, "Attract intro {}", script),
+        None => {}
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn close_title(title: &mut TitleScreen, title_image: &mut Option<UiImageId>, renderer: &mut renderer::Renderer) {
+    title.close();
+    if let Some(image) = title_image.take() {
+        renderer.remove_ui_image(image);
+    }
+}
+
+// Use a skill on the field with everything it needs taken out of the world for it, or say why
+// it can't be used.
+#[cfg(not(target_arch = "wasm32"))]
+fn use_field_skill(world: &mut World, use_skill: impl FnOnce(&mut Party, &mut FieldStatus, &mut FieldEvents, &mut Inventory) -> Result<String, String>) -> Result<String, String> {
+    let mut status = world.remove_resource::<FieldStatus>().ok_or("There's no field here")?;
+    let mut events = world.remove_resource::<FieldEvents>().unwrap_or_default();
+    let mut inventory = world.remove_resource::<Inventory>().unwrap_or_default();
+    let result = match world.resource_mut::<Party>() {
+        Some(party) => use_skill(party, &mut status, &mut events, &mut inventory),
+        None => Err("There's no one there".to_string())
+    };
+    world.insert_resource(inventory);
+    world.insert_resource(events);
+    world.insert_resource(status);
+    result
+}
+
+// Skills and items used on the field send events, which set the flags the field says they do
+// and can light the place up.
+#[cfg(not(target_arch = "wasm32"))]
+fn respond_to_field_events(world: &mut World, renderer: &mut renderer::Renderer) {
+    let events = world.resource_mut::<FieldEvents>().map(FieldEvents::take_events).unwrap_or_default();
+    let responses = world.resource::<FieldDescriptor>().map(|field| field.events.clone()).unwrap_or_default();
+    for event in events {
+        if let Some(flags) = world.resource_mut::<GameFlags>() {
+            for (flag, value) in field_skill::respond(&responses, &event) {
+                flags.set(flag, value);
+            }
+        }
+        if event.eq_ignore_ascii_case(field_skill::LIGHT_EVENT) {
+            if let Some(effects) = world.resource_mut::<ScreenEffects>() {
+                effects.run(&field_skill::light_up(), renderer.get_post_process_settings_mut());
+            }
+            if let Some(darkness) = world.resource_mut::<Darkness>() {
+                darkness.light(darkness::LIGHT_BOOST, darkness::LIGHT_BOOST_TIME);
+            }
+        }
+    }
+}
+
+// Do what was picked in the status menu.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_status_menu_action(action: Option<StatusMenuAction>, menu: &mut StatusMenu, world: &mut World) {
+    let (slot, member) = match action {
+        Some(StatusMenuAction::UseItem { slot, member }) => (slot, member),
+        Some(StatusMenuAction::UseFieldItem { slot }) => {
+            let mut status = match world.remove_resource::<FieldStatus>() {
+                Some(status) => status,
+                None => return
+            };
+            let mut events = world.remove_resource::<FieldEvents>().unwrap_or_default();
+            let result = match world.resource_mut::<Inventory>() {
+                Some(inventory) => inventory.use_on_field(slot, &mut status, &mut events),
+                None => Err("There's nothing there".to_string())
+            };
+            world.insert_resource(events);
+            world.insert_resource(status);
+            menu.finish_action(result);
+            return;
+        },
+        Some(StatusMenuAction::UseSkill { member, skill, target }) => {
+            let result = use_field_skill(world, |party, status, events, inventory| field_skill::use_skill(party, member, skill, target, status, events, inventory));
+            menu.finish_action(result);
+            return;
+        },
+        Some(StatusMenuAction::ChangeRow { member }) => {
+            if let Some(member) = world.resource_mut::<Party>().and_then(|party| party.members.get_mut(member)) {
+                member.row = member.row.other();
+                menu.finish_action(Ok(format!("{} moved to the {} row", member.name, member.row)));
+            }
+            return;
+        },
+        Some(StatusMenuAction::ToggleAuto { member }) => {
+            if let Some(member) = world.resource_mut::<Party>().and_then(|party| party.members.get_mut(member)) {
+                member.auto_battle = !member.auto_battle;
+                menu.finish_action(Ok(format!("{} {} fight by themselves", member.name, if member.auto_battle { "will" } else { "won't" })));
+            }
+            return;
+        },
+        Some(StatusMenuAction::BindSkill { quick_slot, member, skill }) => {
+            let quick = world.resource::<Party>()
+                .and_then(|party| party.members.get(member))
+                .and_then(|member| Some(QuickSlot::Skill { member: member.name.clone(), skill: member.skills.get(skill)?.name.clone() }));
+            bind_quick_slot(world, menu, quick_slot, quick);
+            return;
+        },
+        Some(StatusMenuAction::BindItem { quick_slot, slot }) => {
+            let quick = world.resource::<Inventory>()
+                .and_then(|inventory| inventory.slots().get(slot))
+                .map(|slot| QuickSlot::Item(slot.item.id.clone()));
+            bind_quick_slot(world, menu, quick_slot, quick);
+            return;
+        },
+        None => return
+    };
+    let mut inventory = match world.remove_resource::<Inventory>() {
+        Some(inventory) => inventory,
+        None => return
+    };
+    let result = match world.resource_mut::<Party>().and_then(|party| party.members.get_mut(member)) {
+        Some(member) => inventory.use_on(slot, member),
+        None => Err("There's no one there".to_string())
+    };
+    world.insert_resource(inventory);
+    menu.finish_action(result);
+}
+
+// Put a skill or item picked in the status menu in a hotbar slot.
+#[cfg(not(target_arch = "wasm32"))]
+fn bind_quick_slot(world: &mut World, menu: &mut StatusMenu, slot: usize, quick: Option<QuickSlot>) {
+    let name = match (&quick, world.resource::<Inventory>()) {
+        (Some(quick), Some(inventory)) => quick.name(inventory),
+        _ => return
+    };
+    if let Some(hotbar) = world.resource_mut::<Hotbar>() {
+        hotbar.set(slot, quick);
+        menu.finish_action(Ok(format!("{} is on {}", name, slot + 1)));
+    }
+}
+
+// Do what was picked in the save menu, closing it afterwards or if it was closed. Returns
+// true if a save was loaded.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_save_menu_action(action: Option<SaveMenuAction>, menu: &mut SaveMenu, world: &mut World, renderer: &mut renderer::Renderer, platform: &dyn Platform, thumbnail: &mut Option<image::RgbaImage>) -> bool {
+    match action {
+        Some(SaveMenuAction::Save(slot)) => {
+            let save_game = SaveGame::capture(world, LOCATION, thumbnail.take());
+            write_save(&save_game, platform, slot);
+            close_save_menu(menu, renderer);
+        },
+        Some(SaveMenuAction::Load(slot)) => {
+            let loaded = load_save(world, platform, slot);
+            close_save_menu(menu, renderer);
+            return loaded;
+        },
+        Some(SaveMenuAction::Export(slot)) => export_save(platform, slot, &save::export_path(&paths::export_dir(), slot)),
+        // Open the menu again afterwards, to show what's in the slot now.
+        Some(SaveMenuAction::Import(slot)) => {
+            let imported = import_save(platform, &save::export_path(&paths::export_dir(), slot), slot);
+            if let Some(mode) = menu.mode().filter(|_| imported) {
+                close_save_menu(menu, renderer);
+                open_save_menu(menu, renderer, platform, mode);
+            }
+        },
+        None if !menu.is_open() => close_save_menu(menu, renderer),
+        None => {}
+    }
+    false
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn export_save(platform: &dyn Platform, slot: usize, export_path: &Path) {
+    match save::export_save(&save::slot_path(&platform.save_dir(), slot), export_path) {
+        Ok(_) => tracing::info!(target: targets::ENGINE, "Exported slot {} to {}", slot + 1, export_path.display()),
+        Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't export slot {}: {}", slot + 1, e)
+    }
+}
+
+// Import an exported save into a slot. Saves exported by other versions of the game are let in
+// as long as they load, with a warning, as they're mostly from bug reports.
+#[cfg(not(target_arch = "wasm32"))]
+fn import_save(platform: &dyn Platform, export_path: &Path, slot: usize) -> bool {
+    match save::import_save(export_path, &save::slot_path(&platform.save_dir(), slot)) {
+        Ok(export) => {
+            if export.game_version != env!("CARGO_PKG_VERSION") {
+                tracing::warn!(target: targets::ENGINE, "{} is from version {} of the game, this is {}",
+                    export_path.display(), export.game_version, env!("CARGO_PKG_VERSION"));
+            }
+            tracing::info!(target: targets::ENGINE, "Imported {} into slot {}", export_path.display(), slot + 1);
+            true
+        },
+        Err(e) => {
+            tracing::error!(target: targets::ENGINE, "Couldn't import {}: {}", export_path.display(), e);
+            false
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn close_save_menu(menu: &mut SaveMenu, renderer: &mut renderer::Renderer) {
+    for thumbnail in menu.close() {
+        renderer.remove_ui_image(thumbnail);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_save(save_game: &SaveGame, platform: &dyn Platform, slot: usize) {
+    let path = save::slot_path(&platform.save_dir(), slot);
+    match save_game.write_to(&path) {
+        Ok(_) => tracing::info!(target: targets::ENGINE, "Saved to {}", path.display()),
+        Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't save to {}: {}", path.display(), e)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_save(world: &mut World, platform: &dyn Platform, slot: usize) -> bool {
+    let path = save::slot_path(&platform.save_dir(), slot);
+    match SaveGame::read_from(&path) {
+        Ok(save_game) => {
+            save_game.apply(world);
+            // Repels and the like aren't saved, so they end on loading.
+            world.insert_resource(FieldStatus::new());
+            tracing::info!(target: targets::ENGINE, "Loaded {}", path.display());
+            true
+        },
+        Err(e) => {
+            tracing::error!(target: targets::ENGINE, "Couldn't load {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+// Save everything to the suspend save, with where the player's stood and the battle they're in.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_suspend(world: &World, platform: &dyn Platform, field: &str, player: Entity, battle: Option<&BattleTransition>) {
+    let transform = world.get::<Transform>(player).copied().unwrap_or_default();
+    let forward = transform.rotation * Vector3::unit_z();
+    let mut save_game = SaveGame::capture(world, LOCATION, None);
+    save_game.suspend = Some(SuspendState {
+        field: field.to_string(),
+        position: transform.position.into(),
+        facing: forward.x.atan2(forward.z),
+        battle: battle.map(|battle| battle.setup().clone())
+    });
+    let path = save::suspend_path(&platform.save_dir());
+    match save_game.write_to(&path) {
+        Ok(_) => tracing::info!(target: targets::ENGINE, "Suspended to {}", path.display()),
+        Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't suspend to {}: {}", path.display(), e)
+    }
+}
+
+// Load the suspend save if there is one, which deletes it. Returns where the player was.
+#[cfg(not(target_arch = "wasm32"))]
+fn take_suspend(world: &mut World, platform: &dyn Platform) -> Option<SuspendState> {
+    match save::take_suspend(&platform.save_dir()) {
+        Ok(Some(save_game)) => {
+            save_game.apply(world);
+            // Repels and the like aren't saved here either.
+            world.insert_resource(FieldStatus::new());
+            tracing::info!(target: targets::ENGINE, "Carrying on from the suspend save");
+            save_game.suspend
+        },
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!(target: targets::ENGINE, "Couldn't load the suspend save: {}", e);
+            None
+        }
+    }
+}
+
+// Put the player back where they were when they quit, once they're in the field. Returns the
+// battle they were in the middle of, to go back into.
+#[cfg(not(target_arch = "wasm32"))]
+fn resume_at(world: &mut World, fields: &FieldMap, player: Entity, suspend: &SuspendState) -> Option<BattleSetup> {
+    if let Some(transform) = world.get_mut::<Transform>(player) {
+        transform.position = suspend.position.into();
+        transform.rotation = Quaternion::from_angle_y(Rad(suspend.facing));
+    }
+    let battle = suspend.battle.as_ref()?;
+    // Older suspend saves only kept the formation.
+    let setup = if battle.enemies.is_empty() {
+        roll_battle(world, fields.get(&suspend.field), &battle.formation)?
+    } else {
+        battle.clone()
+    };
+    tracing::info!(target: targets::BATTLE, "Back into {}", setup.formation);
+    Some(setup)
+}
+
+// Switch window mode and remember it for next time.
+#[cfg(not(target_arch = "wasm32"))]
+fn change_window_mode(window: &Window, renderer: &mut renderer::Renderer, config: &mut Config, mode: WindowMode) {
+    // Remember where the window was so it can go back there.
+    remember_placement(window, config);
+
+    let monitor = display::set_window_mode(window, mode, config.monitor.as_deref());
+    config.window_mode = mode;
+    if mode != WindowMode::Windowed {
+        if let Some(name) = monitor.and_then(|monitor| monitor.name()) {
+            config.monitor = Some(name);
+        }
+    }
+
+    // Not every platform sends a resize event when going fullscreen.
+    renderer.resize(window.inner_size());
+
+    save_config(config);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_config(config: &Config) {
+    if let Err(e) = config.save() {
+        tracing::warn!(target: targets::ENGINE, "Couldn't save config to {}: {}", Config::path().display(), e);
+    }
+}
+
+// Keep track of where the window is while it's windowed.
+#[cfg(not(target_arch = "wasm32"))]
+fn remember_placement(window: &Window, config: &mut Config) {
+    if let Some(placement) = WindowPlacement::of(window) {
+        config.window_placement = Some(placement);
+    }
+}
+
+// Everything console commands can change.
+#[cfg(not(target_arch = "wasm32"))]
+struct ConsoleContext<'a> {
+    logging: &'a Logging,
+    world: &'a mut World,
+    window: &'a Window,
+    renderer: &'a mut renderer::Renderer,
+    frame_limiter: &'a mut FrameLimiter,
+    cursor: &'a mut Cursor,
+    config: &'a mut Config,
+    achievements: &'a mut Achievements,
+    platform: &'a mut dyn Platform,
+    assets: &'a AssetServer,
+    manifest: &'a AssetManifest,
+    loading_movie: &'a mut Option<mpsc::Receiver<Result<Movie, AssetError>>>,
+    fields: &'a FieldMap,
+    current_field: &'a str,
+    warp_presets: &'a [WarpPreset],
+    warp_menu: &'a mut WarpMenu,
+    warp: &'a mut Option<WarpChoice>,
+    name_entry: &'a mut NameEntry,
+    renaming: &'a mut Option<usize>,
+    message_window: &'a mut MessageWindow,
+    cinematic: &'a mut Cinematic,
+    starting_battle: &'a mut Option<(BattleSetup, TransitionStyle)>,
+    minigames: &'a mut MiniGames
+}
+
+// Roll a battle from a formation or an encounter table, saying why if it can't. Its backdrop
+// and music are picked for `field` unless the formation has its own.
+#[cfg(not(target_arch = "wasm32"))]
+// Say which dialogue tree talking to an NPC would run now. Nothing runs dialogue yet, so this
+// is as far as it goes.
+fn talk_to(world: &World, npc: &str) {
+    let (flags, party, clock) = match (world.resource::<GameFlags>(), world.resource::<Party>(), world.resource::<GameClock>()) {
+        (Some(flags), Some(party), Some(clock)) => (flags, party, clock),
+        _ => return
+    };
+    let dialogue = match world.resource::<NpcDialogues>() {
+        Some(dialogue) => dialogue,
+        None => return
+    };
+    let talk = DialogueContext { flags, party, minute: clock.minute() };
+    match dialogue.choose(npc, &talk) {
+        Some(tree) => tracing::info!(target: targets::SCRIPT, "{} runs {}", npc, tree),
+        None => tracing::info!(target: targets::SCRIPT, "{} has nothing to say", npc)
+    }
+}
+
+// Do what the action button does for the picked target.
+fn interact(world: &mut World, target: &InteractionTarget, warp: &mut Option<WarpChoice>) {
+    match target.kind {
+        InteractionKind::Talk => if let Some(npc) = world.get::<ScheduledNpc>(target.entity) {
+            talk_to(world, &npc.name.clone());
+        },
+        InteractionKind::Take => match pickup::collect(world, target.entity) {
+            Ok(text) => tracing::info!(target: targets::ENGINE, "{}", text),
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        InteractionKind::Door => if let Some(exit) = world.get::<FieldExit>(target.entity) {
+            *warp = Some(WarpChoice::Field { field: exit.target.clone(), spawn: None });
+        }
+    }
+}
+
+fn roll_battle(world: &mut World, field: Option<&FieldDescriptor>, name: &str) -> Option<BattleSetup> {
+    let formations = world.resource::<Formations>()?.clone();
+    let rng = world.resource_mut::<Rng>()?;
+    let setup = match (formations.get(name), formations.table(name)) {
+        (Some(formation), _) => Some(BattleSetup::new(formation, rng.stream(rng::streams::BATTLE))),
+        (None, Some(table)) => BattleSetup::from_table(&formations, table, rng.stream(rng::streams::ENCOUNTERS)),
+        (None, None) => {
+            tracing::error!(target: targets::ENGINE, "No formation or encounter table \"{}\"", name);
+            return None;
+        }
+    };
+    let mut setup = match setup {
+        Some(setup) => setup,
+        None => {
+            tracing::error!(target: targets::ENGINE, "{} is empty", name);
+            return None;
+        }
+    };
+    if let Some(scenes) = world.resource::<BattleScenes>() {
+        setup.scene = scenes.resolve(&setup.scene, field);
+    }
+    if let Some(status) = world.resource::<FieldStatus>() {
+        status.adjust(&mut setup);
+    }
+    Some(setup)
+}
+
+// Show what poison and regen did on the field: a flash and a sound when poison hurts, and who
+// was knocked out.
+#[cfg(not(target_arch = "wasm32"))]
+fn show_party_tick(world: &mut World, renderer: &mut renderer::Renderer, tick: &PartyTick) {
+    if tick.hurt {
+        let strength = world.resource::<Accessibility>().map(|accessibility| accessibility.effect_strength(field_status::POISON_FLASH)).unwrap_or(field_status::POISON_FLASH);
+        if let Some(effects) = world.resource_mut::<ScreenEffects>() {
+            for command in field_status::poison_flash(strength) {
+                effects.run(&command, renderer.get_post_process_settings_mut());
+            }
+        }
+        // Not every track has the sound, which is fine.
+        if let Some(music) = world.resource_mut::<MusicPlayer>() {
+            let _ = music.play_stinger(field_status::POISON_STINGER);
+        }
+    }
+    for name in &tick.knocked_out {
+        tracing::info!(target: targets::ENGINE, "{} was knocked out by poison", name);
+    }
+    if let Some(leader) = &tick.new_leader {
+        tracing::info!(target: targets::ENGINE, "{} takes the lead", leader);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_console_command(context: &mut ConsoleContext, command: &ConsoleCommand) {
+    match command.name.as_str() {
+        // "log" on its own prints the current filter, otherwise it sets a new one.
+        "log" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Log filter is \"{}\"", context.logging.current_filter());
+        },
+        "log" => match context.logging.set_filter(&command.args) {
+            Ok(_) => tracing::info!(target: targets::ENGINE, "Log filter set to \"{}\"", command.args),
+            Err(e) => tracing::error!(target: targets::ENGINE, "Bad log filter \"{}\": {}", command.args, e)
+        },
+        // "seed" on its own prints the seed, otherwise it restarts every stream from a new seed.
+        "seed" if command.args.is_empty() => {
+            if let Some(rng) = context.world.resource::<Rng>() {
+                tracing::info!(target: targets::ENGINE, "Random seed is {}", rng.seed());
+            }
+        },
+        "seed" => match command.args.parse::<u64>() {
+            Ok(seed) => {
+                context.world.insert_resource(Rng::new(seed));
+                tracing::info!(target: targets::ENGINE, "Random seed set to {}", seed);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "Bad seed \"{}\": {}", command.args, e)
+        },
+        // "window" on its own prints the mode, otherwise it switches to windowed, borderless or exclusive.
+        "window" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Window mode is {}", context.config.window_mode);
+        },
+        "window" => match command.args.parse::<WindowMode>() {
+            Ok(mode) => change_window_mode(context.window, context.renderer, context.config, mode),
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "monitor" on its own lists the monitors, otherwise it picks the one to go fullscreen on.
+        "monitor" if command.args.is_empty() => {
+            for (i, monitor) in context.window.available_monitors().enumerate() {
+                tracing::info!(target: targets::ENGINE, "{}: {}", i, display::describe_monitor(&monitor));
+            }
+        },
+        "monitor" => match display::select_monitor(context.window, &command.args) {
+            Some(monitor) => {
+                context.config.monitor = monitor.name();
+                tracing::info!(target: targets::ENGINE, "Fullscreen monitor set to {}", display::describe_monitor(&monitor));
+                let mode = context.config.window_mode;
+                change_window_mode(context.window, context.renderer, context.config, mode);
+            },
+            None => tracing::error!(target: targets::ENGINE, "No monitor \"{}\"", command.args)
+        },
+        // "fps" on its own prints the cap, otherwise it sets it. "off" or 0 for no cap.
+        "fps" if command.args.is_empty() => {
+            match context.frame_limiter.fps_cap() {
+                Some(fps) => tracing::info!(target: targets::ENGINE, "Frame rate capped at {}", fps),
+                None => tracing::info!(target: targets::ENGINE, "Frame rate isn't capped")
+            }
+        },
+        "fps" => {
+            let fps_cap = if command.args == "off" { Ok(None) } else { command.args.parse::<u32>().map(Some) };
+            match fps_cap {
+                Ok(fps_cap) => {
+                    context.frame_limiter.set_fps_cap(fps_cap);
+                    context.config.fps_cap = context.frame_limiter.fps_cap();
+                    save_config(context.config);
+                },
+                Err(e) => tracing::error!(target: targets::ENGINE, "Bad frame rate cap \"{}\": {}", command.args, e)
+            }
+        },
+        // "lod" on its own prints the LOD bias, otherwise it sets it.
+        "lod" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "LOD bias is {}", context.renderer.lod_bias());
+        },
+        "lod" => match command.args.parse::<f32>() {
+            Ok(bias) if bias > 0.0 => {
+                context.renderer.set_lod_bias(bias);
+                context.config.lod_bias = context.renderer.lod_bias();
+                save_config(context.config);
+            },
+            _ => tracing::error!(target: targets::ENGINE, "Bad LOD bias \"{}\"", command.args)
+        },
+        // "drawdistance" on its own prints the field's draw distance, otherwise it overrides it.
+        "drawdistance" => {
+            let field = match context.world.resource_mut::<FieldDescriptor>() {
+                Some(field) => field,
+                None => return
+            };
+            match command.args.as_str() {
+                "" => match field.max_draw_distance {
+                    Some(distance) => tracing::info!(target: targets::ENGINE, "Draw distance is {}", distance),
+                    None => tracing::info!(target: targets::ENGINE, "There's no draw distance")
+                },
+                "off" => field.max_draw_distance = None,
+                distance => match distance.parse::<f32>() {
+                    Ok(distance) => field.max_draw_distance = Some(distance),
+                    Err(e) => tracing::error!(target: targets::ENGINE, "Bad draw distance \"{}\": {}", distance, e)
+                }
+            }
+        },
+        "vsync" | "lowpower" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "vsync is {}, low power mode is {}", context.renderer.vsync(), context.frame_limiter.low_power());
+        },
+        "vsync" | "lowpower" => match config::parse_bool(&command.args) {
+            Ok(on) => {
+                if command.name == "vsync" {
+                    context.renderer.set_vsync(on);
+                    context.config.vsync = on;
+                } else {
+                    context.frame_limiter.set_low_power(on);
+                    context.config.low_power = on;
+                }
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "textscale", "textspeed", "highcontrast", "screeneffects" and "subtitles" print the
+        // accessibility settings on their own, otherwise they change one.
+        "textscale" | "textspeed" | "highcontrast" | "screeneffects" | "subtitles" => {
+            let accessibility = match context.world.resource_mut::<Accessibility>() {
+                Some(accessibility) => accessibility,
+                None => return
+            };
+            if command.args.is_empty() {
+                tracing::info!(target: targets::ENGINE, "Text scale is {}, text speed is {}, high contrast is {}, screen effects are {}, subtitles are {}",
+                    accessibility.text_scale(), accessibility.text_speed, accessibility.high_contrast, accessibility.screen_effects, accessibility.subtitles);
+                return;
+            }
+            let result = match command.name.as_str() {
+                "textscale" => command.args.parse::<f32>()
+                    .map(|scale| accessibility.set_text_scale(scale))
+                    .map_err(|_| format!("Bad text scale \"{}\"", command.args)),
+                "textspeed" => command.args.parse::<TextSpeed>().map(|speed| accessibility.text_speed = speed),
+                "highcontrast" => config::parse_bool(&command.args).map(|on| accessibility.high_contrast = on),
+                "screeneffects" => config::parse_bool(&command.args).map(|on| accessibility.screen_effects = on),
+                _ => config::parse_bool(&command.args).map(|on| accessibility.subtitles = on)
+            };
+            match result {
+                Ok(_) => {
+                    context.config.accessibility = accessibility.clone();
+                    save_config(context.config);
+                },
+                Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+            }
+        },
+        // "language" on its own prints the language text is shown in, otherwise it changes it,
+        // e.g. "language ja". Takes effect from the next thing that's loaded, including the
+        // localized versions of assets.
+        "language" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Language is {}", context.config.language);
+        },
+        "language" => {
+            context.config.language = command.args.clone();
+            save_config(context.config);
+            context.assets.set_language(&context.config.language);
+        },
+        // "movement" on its own prints whether walking goes by the camera or the world,
+        // otherwise it switches, e.g. "movement world".
+        "movement" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Movement is {}-relative", context.config.movement);
+        },
+        "movement" => match command.args.parse::<MovementMode>() {
+            Ok(mode) => {
+                context.config.movement = mode;
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "alwaysrun" on its own prints whether the player runs without shift held, otherwise it
+        // switches, e.g. "alwaysrun on".
+        "alwaysrun" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Always run is {}", if context.config.always_run { "on" } else { "off" });
+        },
+        "alwaysrun" => match config::parse_bool(&command.args) {
+            Ok(on) => {
+                context.config.always_run = on;
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "mods" lists the mods in the mods folder and whether they're on, "mod <name> on/off"
+        // switches one. Mods that are switched on go on last, over the others. Either way it's
+        // only once the game's started again, as everything's been loaded already.
+        "mods" => {
+            let found = mods::find_mods(&paths::mods_dir());
+            if found.is_empty() {
+                tracing::info!(target: targets::ENGINE, "No mods in {}", paths::mods_dir().display());
+            }
+            for info in found {
+                let on = context.config.mods.contains(&info.name);
+                tracing::info!(target: targets::ENGINE, "{} ({}){}: {}", info.name, if on { "on" } else { "off" },
+                    if info.title != info.name { format!(", {}", info.title) } else { String::new() }, info.description);
+            }
+        },
+        "mod" => {
+            let (name, on) = match command.args.rsplit_once(' ').map(|(name, on)| (name.trim(), config::parse_bool(on))) {
+                Some((name, Ok(on))) if !name.is_empty() => (name, on),
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Usage: mod <name> on/off");
+                    return;
+                }
+            };
+            if on && !mods::find_mods(&paths::mods_dir()).iter().any(|info| info.name == name) {
+                tracing::error!(target: targets::ENGINE, "No mod \"{}\" in {}", name, paths::mods_dir().display());
+                return;
+            }
+            context.config.mods.retain(|existing| existing != name);
+            if on {
+                context.config.mods.push(name.to_string());
+            }
+            save_config(context.config);
+            tracing::info!(target: targets::ENGINE, "Mod {} will be {} next time the game starts", name, if on { "on" } else { "off" });
+        },
+        // "telemetry" on its own prints whether the player's agreed to send telemetry, otherwise
+        // it switches, e.g. "telemetry on".
+        "telemetry" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Telemetry is {}", if context.config.telemetry { "on" } else { "off" });
+        },
+        "telemetry" => match config::parse_bool(&command.args) {
+            Ok(on) => {
+                context.config.telemetry = on;
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "colorfilter" on its own prints the colour blindness filter, otherwise it switches to
+        // another, e.g. "colorfilter deuteranopia".
+        "colorfilter" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Colour filter is {}", context.renderer.get_post_process_settings().color_filter);
+        },
+        "colorfilter" => match command.args.parse::<ColorFilter>() {
+            Ok(filter) => {
+                context.renderer.get_post_process_settings_mut().color_filter = filter;
+                context.config.color_filter = filter;
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "dof" on its own prints the depth of field quality, otherwise it changes it, e.g.
+        // "dof high".
+        "dof" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Depth of field quality is {}", context.renderer.get_post_process_settings().dof_quality);
+        },
+        "dof" => match command.args.parse::<DofQuality>() {
+            Ok(quality) => {
+                context.renderer.get_post_process_settings_mut().dof_quality = quality;
+                context.config.dof_quality = quality;
+                save_config(context.config);
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "focus" on its own prints the depth of field, "focus <distance>" focuses that far from
+        // the camera and "focus none" turns it off. Battles and photo mode set it themselves.
+        "focus" if command.args.is_empty() => match context.renderer.get_post_process_settings().depth_of_field {
+            Some(dof) => tracing::info!(target: targets::ENGINE, "Focused {} away, sharp for {} either side, fully blurred {} further",
+                dof.focus_distance, dof.focus_range, dof.falloff),
+            None => tracing::info!(target: targets::ENGINE, "Depth of field is off")
+        },
+        "focus" => {
+            let settings = context.renderer.get_post_process_settings_mut();
+            match command.args.as_str() {
+                "none" | "off" => settings.depth_of_field = None,
+                distance => match distance.parse::<f32>() {
+                    Ok(distance) => settings.depth_of_field = Some(DepthOfField {
+                        focus_distance: distance,
+                        ..settings.depth_of_field.unwrap_or_default()
+                    }),
+                    Err(e) => tracing::error!(target: targets::ENGINE, "Bad focus distance \"{}\": {}", distance, e)
+                }
+            }
+        },
+        // "effect" on its own prints the post processing cutscenes animate, "effect stop" finishes
+        // them straight away and anything else starts one, e.g. "effect fade 1 2 easein #000000".
+        "effect" if command.args.is_empty() => {
+            let settings = context.renderer.get_post_process_settings();
+            let shake = context.world.resource::<ScreenEffects>().map(|effects| effects.shake()).unwrap_or_default();
+            tracing::info!(target: targets::ENGINE, "Brightness {}, contrast {}, saturation {}, vignette {}, tint {:?} at {}, fade {:?}, shake {}",
+                settings.brightness, settings.contrast, settings.saturation, settings.vignette, settings.tint, settings.tint_strength, settings.fade, shake);
+        },
+        "effect" => {
+            let effects = match context.world.resource_mut::<ScreenEffects>() {
+                Some(effects) => effects,
+                None => return
+            };
+            if command.args == "stop" {
+                effects.finish(context.renderer.get_post_process_settings_mut());
+                return;
+            }
+            match command.args.parse::<EffectCommand>() {
+                Ok(effect) => effects.run(&effect, context.renderer.get_post_process_settings_mut()),
+                Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+            }
+        },
+        // "camera" on its own lists the field's cameras, "camera stop" finishes moving straight
+        // away and anything else cuts or blends to one, e.g. "camera hall_door 1.5 easeinout".
+        "camera" if command.args.is_empty() => match context.world.resource::<FieldCameras>() {
+            Some(cameras) => {
+                let names: Vec<&str> = cameras.cameras().iter().map(|camera| camera.name.as_str()).collect();
+                let showing = cameras.showing().map(|camera| camera.name.as_str()).unwrap_or_default();
+                tracing::info!(target: targets::ENGINE, "Cameras {}, showing {}", names.join(", "), showing);
+            },
+            None => tracing::info!(target: targets::ENGINE, "This field only has the one camera")
+        },
+        "camera" => {
+            let cameras = match context.world.resource_mut::<FieldCameras>() {
+                Some(cameras) => cameras,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "This field doesn't have any cameras to move between");
+                    return;
+                }
+            };
+            if command.args == "stop" {
+                cameras.finish();
+                return;
+            }
+            if let Err(e) = command.args.parse::<CameraCommand>().and_then(|camera| cameras.run(&camera)) {
+                tracing::error!(target: targets::ENGINE, "{}", e);
+            }
+        },
+        // "volume" prints every bus's volume, "volume bus" prints one and "volume bus level" sets
+        // it, e.g. "volume music 0.5".
+        "volume" => {
+            let mixer = match context.world.resource_mut::<Mixer>() {
+                Some(mixer) => mixer,
+                None => return
+            };
+            let mut args = command.args.split_whitespace();
+            let bus = match args.next().map(|bus| bus.parse::<Bus>()) {
+                Some(Ok(bus)) => bus,
+                Some(Err(e)) => {
+                    tracing::error!(target: targets::ENGINE, "{}", e);
+                    return;
+                },
+                None => {
+                    let volumes: Vec<String> = Bus::ALL.iter().map(|bus| format!("{} {}", bus, mixer.volumes().get(*bus))).collect();
+                    tracing::info!(target: targets::ENGINE, "Volumes: {}", volumes.join(", "));
+                    return;
+                }
+            };
+            match args.next().map(|volume| volume.parse::<f32>()) {
+                Some(Ok(volume)) => {
+                    mixer.set_volume(bus, volume);
+                    context.config.volumes = mixer.volumes();
+                    save_config(context.config);
+                },
+                Some(Err(_)) => tracing::error!(target: targets::ENGINE, "Bad volume \"{}\"", command.args),
+                None => tracing::info!(target: targets::ENGINE, "{} volume is {}", bus, mixer.volumes().get(bus))
+            }
+        },
+        // "warp" on its own opens the warp menu, otherwise it goes straight to a field, e.g.
+        // "warp test_tilemap" or "warp test_field door".
+        "warp" if command.args.is_empty() => {
+            context.warp_menu.open(WarpChoice::list(context.fields, context.warp_presets));
+        },
+        "warp" => {
+            let mut args = command.args.split_whitespace();
+            *context.warp = args.next().map(|field| WarpChoice::Field {
+                field: field.to_string(),
+                spawn: args.next().map(str::to_string)
+            });
+        },
+        // "preset" on its own lists the warp presets, otherwise it applies one by name.
+        "preset" if command.args.is_empty() => {
+            let names: Vec<&str> = context.warp_presets.iter().map(|preset| preset.name.as_str()).collect();
+            tracing::info!(target: targets::ENGINE, "Presets: {}", names.join(", "));
+        },
+        "preset" => *context.warp = Some(WarpChoice::Preset(command.args.clone())),
+
+        // "auto" on its own prints who fights by themselves and how, "auto on" or "auto off" puts
+        // the whole party on auto battle or takes it off.
+        "auto" if command.args.is_empty() => {
+            let auto = context.world.resource::<AutoBattle>().copied().unwrap_or_default();
+            tracing::info!(target: targets::BATTLE, "Party auto battle is {}, at {}x speed", if auto.party { "on" } else { "off" }, auto.speed);
+            if let Some(party) = context.world.resource::<Party>() {
+                for member in &party.members {
+                    let gambits: Vec<String> = member.gambits.iter().map(ToString::to_string).collect();
+                    tracing::info!(target: targets::BATTLE, "  {}{}: {}", member.name, if auto.is_auto(member) { " (auto)" } else { "" }, gambits.join(", then "));
+                }
+            }
+        },
+        "auto" => match (config::parse_bool(&command.args), context.world.resource_mut::<AutoBattle>()) {
+            (Ok(on), Some(auto)) => auto.party = on,
+            (Err(e), _) => tracing::error!(target: targets::ENGINE, "{}", e),
+            _ => {}
+        },
+        // "battlespeed" goes to the next speed for fast forwarding through battles, or to the one
+        // given.
+        "battlespeed" => {
+            let auto = match context.world.resource_mut::<AutoBattle>() {
+                Some(auto) => auto,
+                None => return
+            };
+            if command.args.is_empty() {
+                auto.next_speed();
+            } else {
+                match command.args.parse() {
+                    Ok(speed) if auto_battle::SPEEDS.contains(&speed) => auto.speed = speed,
+                    _ => {
+                        tracing::error!(target: targets::ENGINE, "Bad speed \"{}\", expected one of {:?}", command.args, auto_battle::SPEEDS);
+                        return;
+                    }
+                }
+            }
+            tracing::info!(target: targets::BATTLE, "Battles run at {}x speed", auto.speed);
+        },
+        // "say" shows some text in the message window, like dialogue would, for checking how it
+        // fits, e.g. "say overflow=ellipsis hyphenate=off Hello there". "\n" in the text is a
+        // line break and "\n\n" starts a new page. Placeholders like {player} are filled in, and
+        // a string's id shows that string, e.g. "say pickup.take".
+        // "rename <member>" types a new name for them, with the on-screen keyboard.
+        "rename" => {
+            let party = context.world.resource::<Party>();
+            match party.and_then(|party| party.members.iter().position(|member| member.name.eq_ignore_ascii_case(command.args.trim()))) {
+                Some(index) => {
+                    let name = party.map(|party| party.members[index].name.clone()).unwrap_or_default();
+                    let title = strings::localize(context.world, "name_entry.title", "Name {name}", &[("name", name.as_str().into())]);
+                    context.name_entry.open(&title, &name);
+                    *context.renaming = Some(index);
+                },
+                None => tracing::error!(target: targets::ENGINE, "No one called \"{}\" in the party", command.args.trim())
+            }
+        },
+        "say" => {
+            let (mut overflow, mut hyphenate) = (Overflow::Paginate, true);
+            let mut text = command.args.as_str();
+            while let Some((option, rest)) = text.split_once(' ').filter(|(option, _)| option.contains('=')) {
+                let result = match option.split_once('=') {
+                    Some(("overflow", value)) => value.parse().map(|value| overflow = value),
+                    Some(("hyphenate", value)) => config::parse_bool(value).map(|value| hyphenate = value),
+                    _ => Err(format!("Unknown option \"{}\"", option))
+                };
+                if let Err(e) = result {
+                    tracing::error!(target: targets::ENGINE, "{}", e);
+                    return;
+                }
+                text = rest.trim_start();
+            }
+            let text = text.replace("\\n", "\n");
+            let text = strings::localize(context.world, &text, &text, &[]);
+            context.message_window.open_with(&text, overflow, hyphenate);
+        },
+        // "minigame" on its own lists them, otherwise it starts one, like a script would.
+        "minigame" => {
+            if command.args.is_empty() {
+                tracing::info!(target: targets::ENGINE, "Mini-games: {}", context.minigames.names().collect::<Vec<_>>().join(", "));
+                return;
+            }
+            match command.args.parse::<MiniGameLaunch>().and_then(|launch| context.minigames.launch(&launch)) {
+                Ok(_) => tracing::info!(target: targets::SCRIPT, "Started {}", command.args),
+                Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+            }
+        },
+        // "music" prints what's playing and how loud each layer is. "music layer name volume
+        // [beats]" fades a layer in time with the music and "music stinger name" plays one.
+        "music" => {
+            let music = match context.world.resource_mut::<MusicPlayer>() {
+                Some(music) => music,
+                None => {
+                    tracing::info!(target: targets::ENGINE, "No music playing");
+                    return;
+                }
+            };
+            let args: Vec<&str> = command.args.split_whitespace().collect();
+            let result = match args.as_slice() {
+                [] => {
+                    let layers: Vec<String> = music.layer_volumes().map(|(name, volume)| format!("{} {:.2}", name, volume)).collect();
+                    tracing::info!(target: targets::ENGINE, "Playing {} at beat {:.1}, layers: {}", music.track().name, music.beat(), layers.join(", "));
+                    Ok(())
+                },
+                ["layer", name, volume] | ["layer", name, volume, _] => match (volume.parse::<f32>(), args.get(3).map_or(Ok(0.0), |beats| beats.parse::<f32>())) {
+                    (Ok(volume), Ok(beats)) => music.fade_layer(name, volume, beats),
+                    _ => Err(format!("Bad volume or beats in \"{}\"", command.args))
+                },
+                ["stinger", name] => music.play_stinger(name),
+                _ => Err("Usage: music [layer <name> <volume> [beats]] [stinger <name>]".to_string())
+            };
+            if let Err(e) = result {
+                tracing::error!(target: targets::ENGINE, "{}", e);
+            }
+        },
+        // "cursor" on its own prints the style, otherwise it switches to system, hidden or sprite.
+        "cursor" if command.args.is_empty() => {
+            tracing::info!(target: targets::ENGINE, "Cursor style is {}", context.cursor.style());
+        },
+        "cursor" => match command.args.parse::<CursorStyle>() {
+            Ok(style) => context.cursor.set_style(context.window, style),
+            Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "flag name" prints a flag, "flag name value" sets it.
+        "flag" => {
+            let mut args = command.args.split_whitespace();
+            let (name, value) = (args.next(), args.next());
+            let flags = match context.world.resource_mut::<GameFlags>() {
+                Some(flags) => flags,
+                None => return
+            };
+            match (name, value.map(str::parse::<i32>)) {
+                (None, _) => {
+                    for (name, value) in flags.iter() {
+                        tracing::info!(target: targets::ENGINE, "{} = {}", name, value);
+                    }
+                },
+                (Some(name), None) => tracing::info!(target: targets::ENGINE, "{} = {}", name, flags.get(name)),
+                (Some(name), Some(Ok(value))) => flags.set(name, value),
+                (Some(_), Some(Err(e))) => tracing::error!(target: targets::ENGINE, "Bad flag value: {}", e)
+            }
+        },
+        // "time" prints the time of day, "time hh:mm" skips ahead to it, e.g. "time 20:00" to
+        // watch the shopkeeper go home.
+        "time" => {
+            let clock = match context.world.resource_mut::<GameClock>() {
+                Some(clock) => clock,
+                None => return
+            };
+            if command.args.is_empty() {
+                tracing::info!(target: targets::ENGINE, "Day {}, {}", clock.day(), game_clock::format_time(clock.minute()));
+                return;
+            }
+            match game_clock::parse_time(&command.args) {
+                Some(minute) => clock.set_time(minute),
+                None => tracing::error!(target: targets::ENGINE, "Bad time \"{}\", expected hh:mm", command.args)
+            }
+        },
+        // "talk <npc>" says which dialogue tree talking to them would run now. Nothing runs
+        // dialogue yet, so this is as far as it goes.
+        "talk" => talk_to(context.world, &command.args),
+        // "battle" lists the formations and encounter tables, "battle <formation>" or "battle
+        // <table>" rolls one, prints what it'd be up against and swirls into it. A transition
+        // after it, like "battle <formation> pixelate", picks how the field goes.
+        "battle" => {
+            if command.args.is_empty() {
+                if let Some(formations) = context.world.resource::<Formations>() {
+                    let ids: Vec<&str> = formations.formations().iter().map(|formation| formation.id.as_str()).collect();
+                    let tables: Vec<&str> = formations.tables().iter().map(|table| table.id.as_str()).collect();
+                    tracing::info!(target: targets::ENGINE, "Formations {}, encounter tables {}", ids.join(", "), tables.join(", "));
+                }
+                return;
+            }
+            let (name, style) = match command.args.rsplit_once(' ').map(|(name, style)| (name, style.parse::<TransitionStyle>())) {
+                Some((name, Ok(style))) => (name.trim(), Some(style)),
+                _ => (command.args.as_str(), None)
+            };
+            if let Some(setup) = roll_battle(context.world, context.fields.get(context.current_field), name) {
+                tracing::info!(target: targets::BATTLE, "{}{}{}{}", setup.formation,
+                    setup.intro.as_ref().map(|intro| format!(", intro {}", intro)).unwrap_or_default(),
+                    if setup.can_escape { "" } else { ", no escape" },
+                    if setup.back_attack { ", back attack" } else { "" });
+                tracing::info!(target: targets::BATTLE, "  Backdrop {}, music {}",
+                    setup.scene.backdrop.as_deref().unwrap_or("none"), setup.scene.music.as_deref().unwrap_or("none"));
+                for enemy in &setup.enemies {
+                    tracing::info!(target: targets::BATTLE, "  {} in the {} row at {:?}{}{}", enemy.enemy, enemy.row, enemy.position,
+                        if enemy.reach { ", reach" } else { "" }, if enemy.boss { ", boss" } else { "" });
+                }
+                let style = style.unwrap_or_else(|| TransitionStyle::for_battle(&setup));
+                *context.starting_battle = Some((setup, style));
+            }
+        },
+        // "battlescript <formation> <turn> [enemy=hp% ...]" prints what the formation's script
+        // would do by that turn with its enemies on that much HP, like a battle would run it.
+        "battlescript" => {
+            let mut words = command.args.split_whitespace();
+            let (formation, turn) = match (words.next(), words.next().map(str::parse::<u32>)) {
+                (Some(formation), Some(Ok(turn))) => (formation, turn),
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Expected \"battlescript <formation> <turn> [enemy=hp% ...]\"");
+                    return;
+                }
+            };
+            let mut enemies = Vec::new();
+            for word in words {
+                match word.split_once('=').and_then(|(enemy, hp)| Some((enemy, hp.trim_end_matches('%').parse().ok()?))) {
+                    Some((enemy, hp)) => enemies.push((enemy, Stats { hp, max_hp: 100, ..Default::default() })),
+                    None => {
+                        tracing::error!(target: targets::ENGINE, "Expected enemy=hp%, not \"{}\"", word);
+                        return;
+                    }
+                }
+            }
+            let mut director = match context.world.resource::<BattleScripts>() {
+                Some(scripts) => BattleDirector::new(scripts.get(formation)),
+                None => return
+            };
+            let enemies: Vec<(&str, &Stats)> = enemies.iter().map(|(enemy, stats)| (*enemy, stats)).collect();
+            // Every turn up to this one, so turn triggers that have passed go off too.
+            for turn in 1..=turn {
+                director.check(turn, &enemies);
+            }
+            if !director.is_paused() {
+                tracing::info!(target: targets::BATTLE, "Nothing happens");
+            }
+            while let Some(action) = director.next_action() {
+                match action {
+                    BattleAction::Say(line) => tracing::info!(target: targets::BATTLE, "  \"{}\"", line),
+                    BattleAction::Phase { enemy, phase } => tracing::info!(target: targets::BATTLE, "  {} goes into its {} phase", enemy, phase),
+                    BattleAction::Stat { enemy, stat, percent } => tracing::info!(target: targets::BATTLE, "  {}'s {} goes to {}%", enemy, stat, percent),
+                    BattleAction::Spawn(enemy) => tracing::info!(target: targets::BATTLE, "  {}-{} {} join in the {} row", enemy.count.0, enemy.count.1, enemy.enemy, enemy.row),
+                    BattleAction::Backdrop(backdrop) => tracing::info!(target: targets::BATTLE, "  The backdrop changes to {}", backdrop),
+                    BattleAction::Music(music) => tracing::info!(target: targets::BATTLE, "  The music changes to {}", music)
+                }
+            }
+        },
+        // "fieldstatus" lists the repels and the like that are going, "fieldstatus <effect>
+        // <steps>" starts one as if an item had been used.
+        "fieldstatus" => {
+            let status = match context.world.resource_mut::<FieldStatus>() {
+                Some(status) => status,
+                None => return
+            };
+            if command.args.is_empty() {
+                let effects: Vec<String> = status.effects().iter().map(|active| active.to_string()).collect();
+                tracing::info!(target: targets::ENGINE, "Field effects {}, encounter chance {:.1}% a step",
+                    if effects.is_empty() { "none".to_string() } else { effects.join(", ") }, status.encounter_chance() * 100.0);
+                return;
+            }
+            match command.args.parse::<FieldBoost>() {
+                Ok(boost) => tracing::info!(target: targets::ENGINE, "{}", status.apply(boost)),
+                Err(e) => tracing::error!(target: targets::ENGINE, "{}", e)
+            }
+        },
+        // "darkness" says how dark it is here, "darkness <strength> <radius>" makes it dark like a
+        // dungeon with a light that reaches that many pixels and "darkness off" lights it up.
+        "darkness" => {
+            let mut words = command.args.split_whitespace();
+            match (words.next(), words.next().map(str::parse::<f32>)) {
+                (None, _) => match context.world.resource::<Darkness>() {
+                    Some(darkness) => tracing::info!(target: targets::ENGINE, "The light reaches {:.0} pixels, boosted for {:.0}s more",
+                        darkness.radius(), darkness.boost_left().as_secs_f32()),
+                    None => tracing::info!(target: targets::ENGINE, "It isn't dark here")
+                },
+                (Some("off"), None) => darkness::enter_field(context.world, None, context.renderer.get_post_process_settings_mut()),
+                (Some(strength), Some(Ok(radius))) => match strength.parse::<f32>() {
+                    Ok(strength) => {
+                        let darkness = FieldDarkness { strength: strength.clamp(0.0, 1.0), radius: radius.max(0.0) };
+                        darkness::enter_field(context.world, Some(darkness), context.renderer.get_post_process_settings_mut());
+                    },
+                    Err(_) => tracing::error!(target: targets::ENGINE, "Bad strength \"{}\"", strength)
+                },
+                _ => tracing::error!(target: targets::ENGINE, "Expected \"darkness [strength radius]\" or \"darkness off\"")
+            }
+        },
+        // "cinematic" says whether a cutscene's playing, "cinematic on" starts one, sliding the
+        // letterbox in and hiding the HUD, and "cinematic off" ends it.
+        "cinematic" => match (command.args.is_empty(), config::parse_bool(&command.args)) {
+            (true, _) => tracing::info!(target: targets::ENGINE, "{}", if context.cinematic.is_playing() { "A cutscene's playing" } else { "No cutscene's playing" }),
+            (false, Ok(true)) => context.cinematic.start(),
+            (false, Ok(false)) => context.cinematic.end(),
+            (false, Err(e)) => tracing::error!(target: targets::ENGINE, "{}", e)
+        },
+        // "puzzle" lists the fields whose puzzles have been started on and "puzzle reset [field]"
+        // starts one again, this field's if not given. Ending it with "*" resets every field
+        // whose name starts with it, e.g. "puzzle reset cave*" when leaving the caves.
+        "puzzle" => {
+            let mut words = command.args.split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) => {
+                    let snapshot = puzzle::snapshot(context.world);
+                    if snapshot.is_empty() {
+                        tracing::info!(target: targets::ENGINE, "No puzzles have been started on");
+                    }
+                    for (field, state) in snapshot {
+                        let flags: Vec<String> = state.flags.iter().map(|(flag, value)| format!("{} = {}", flag, value)).collect();
+                        let pieces: Vec<String> = state.pieces.iter().map(|(piece, position)| format!("{} at {:.1}, {:.1}, {:.1}", piece, position.x, position.y, position.z)).collect();
+                        tracing::info!(target: targets::ENGINE, "{}: {}", field, flags.into_iter().chain(pieces).collect::<Vec<_>>().join(", "));
+                    }
+                },
+                (Some("reset"), field) => {
+                    let field = field.unwrap_or(context.current_field);
+                    let reset = puzzle::reset(context.world, field);
+                    if reset.is_empty() {
+                        tracing::info!(target: targets::ENGINE, "No puzzles to reset in {}", field);
+                    } else {
+                        tracing::info!(target: targets::ENGINE, "Reset the puzzles in {}", reset.join(", "));
+                    }
+                },
+                _ => tracing::error!(target: targets::ENGINE, "Usage: puzzle [reset [field]]")
+            }
+        },
+        // "hotbar" lists what's in the quick slots, "hotbar <slot> <skill member/name or item id>"
+        // puts something in one and "hotbar <slot> clear" empties it.
+        "hotbar" => {
+            let hotbar = match context.world.resource_mut::<Hotbar>() {
+                Some(hotbar) => hotbar,
+                None => return
+            };
+            if command.args.is_empty() {
+                let slots: Vec<String> = hotbar.slots().map(|(slot, quick)| format!("{}: {}", slot + 1, quick)).collect();
+                tracing::info!(target: targets::ENGINE, "Hotbar {}", if slots.is_empty() { "empty".to_string() } else { slots.join(", ") });
+                return;
+            }
+            let (slot, what) = command.args.split_once(char::is_whitespace).unwrap_or((&command.args, ""));
+            let slot = match slot.parse::<usize>() {
+                Ok(slot @ 1..=hotbar::SLOT_COUNT) => slot - 1,
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Expected a slot from 1 to {}", hotbar::SLOT_COUNT);
+                    return;
+                }
+            };
+            let quick = match what.trim() {
+                "clear" => None,
+                what => match what.parse::<QuickSlot>() {
+                    Ok(quick) => Some(quick),
+                    Err(e) => {
+                        tracing::error!(target: targets::ENGINE, "{}", e);
+                        return;
+                    }
+                }
+            };
+            hotbar.set(slot, quick);
+        },
+        // "walk <steps> [table]" takes steps like the movement system will, counting down field
+        // effects and rolling encounters from the table, which is the field's name if not given.
+        // It stops at the first encounter.
+        "walk" => {
+            let mut words = command.args.split_whitespace();
+            let steps = match words.next().map(str::parse::<u32>) {
+                Some(Ok(steps)) => steps,
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Expected \"walk <steps> [table]\"");
+                    return;
+                }
+            };
+            let table = words.next().unwrap_or(context.current_field).to_string();
+            let has_table = context.world.resource::<Formations>().is_some_and(|formations| formations.table(&table).is_some());
+            let mut status = match context.world.remove_resource::<FieldStatus>() {
+                Some(status) => status,
+                None => return
+            };
+            let mut walked = 0;
+            let mut encounter = false;
+            let mut game_over = false;
+            while walked < steps && !encounter && !game_over {
+                walked += 1;
+                for effect in status.step() {
+                    tracing::info!(target: targets::ENGINE, "{} wore off after {} steps", effect.label(), walked);
+                }
+                let tick = match context.world.resource_mut::<Party>() {
+                    Some(party) => status.tick_party(party),
+                    None => PartyTick::default()
+                };
+                show_party_tick(context.world, context.renderer, &tick);
+                game_over = tick.game_over;
+                encounter = !game_over && has_table && context.world.resource_mut::<Rng>().is_some_and(|rng| status.roll_encounter(rng.stream(rng::streams::ENCOUNTERS)));
+            }
+            context.world.insert_resource(status);
+            if let Some(stats) = context.world.resource_mut::<PlayStats>() {
+                stats.add_steps(walked as u64);
+            }
+            if game_over {
+                return;
+            }
+            if !encounter {
+                tracing::info!(target: targets::ENGINE, "Walked {} steps without a fight", walked);
+                return;
+            }
+            if let Some(setup) = roll_battle(context.world, context.fields.get(context.current_field), &table) {
+                tracing::info!(target: targets::BATTLE, "Ran into {} after {} steps{}", setup.formation, walked, if setup.back_attack { ", a back attack" } else { "" });
+                let style = TransitionStyle::for_battle(&setup);
+                *context.starting_battle = Some((setup, style));
+            }
+        },
+        // "statuseffect <member> <effect> [on/off]" poisons someone or the like, or cures them.
+        "statuseffect" => {
+            let words: Vec<&str> = command.args.split_whitespace().collect();
+            let (name, effect, on) = match words.as_slice() {
+                [name, effect] => (*name, effect.parse::<StatusEffect>(), true),
+                [name, effect, on] => (*name, effect.parse::<StatusEffect>(), *on != "off"),
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Expected \"statuseffect <member> <effect> [on/off]\"");
+                    return;
+                }
+            };
+            let effect = match effect {
+                Ok(effect) => effect,
+                Err(e) => {
+                    tracing::error!(target: targets::ENGINE, "{}", e);
+                    return;
+                }
+            };
+            let member = match context.world.resource_mut::<Party>().and_then(|party| party.members.iter_mut().find(|member| member.name.eq_ignore_ascii_case(name))) {
+                Some(member) => member,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "No one called \"{}\" in the party", name);
+                    return;
+                }
+            };
+            member.status_effects.retain(|known| *known != effect);
+            if on {
+                member.status_effects.push(effect);
+            }
+            tracing::info!(target: targets::ENGINE, "{} {} {}", member.name, if on { "has" } else { "doesn't have" }, effect.label());
+        },
+        // "win <formation or table> [turns=N] [item=id ...] [skill=name ...]" rolls a battle and
+        // wins it straight away, putting what the enemies dropped in the inventory and reporting
+        // it as if it took that many turns, using those items and skills. There's no rewards
+        // screen to show it on until battles run, so it's logged.
+        "win" => {
+            let mut args = command.args.split_whitespace();
+            let setup = match roll_battle(context.world, context.fields.get(context.current_field), args.next().unwrap_or_default()) {
+                Some(setup) => setup,
+                None => return
+            };
+            let mut report = BattleReport::new(&setup.formation);
+            report.end_turn();
+            for arg in args {
+                match arg.split_once('=') {
+                    Some(("turns", turns)) => match turns.parse::<u32>() {
+                        Ok(turns) => report.turns = turns.max(1),
+                        Err(_) => tracing::warn!(target: targets::ENGINE, "Bad turns \"{}\"", turns)
+                    },
+                    Some(("item", item)) => report.use_item(item),
+                    Some(("skill", name)) => {
+                        let party = context.world.resource::<Party>();
+                        match party.and_then(|party| party.members.iter().flat_map(|member| &member.skills).find(|skill| skill.name.eq_ignore_ascii_case(name))) {
+                            Some(skill) => report.use_skill(skill),
+                            None => tracing::warn!(target: targets::ENGINE, "No one in the party knows \"{}\"", name)
+                        }
+                    },
+                    _ => tracing::warn!(target: targets::ENGINE, "Expected turns=, item= or skill=, not \"{}\"", arg)
+                }
+            }
+            for enemy in &setup.enemies {
+                report.defeat(&enemy.enemy);
+            }
+            let tables = context.world.resource::<LootTables>().cloned().unwrap_or_default();
+            let rewards = match context.world.resource_mut::<Rng>() {
+                Some(rng) => VictoryRewards::roll(&tables, setup.enemies.iter().map(|enemy| enemy.enemy.as_str()), rng.stream(rng::streams::LOOT)),
+                None => return
+            };
+            let items = context.world.resource::<ItemCatalog>().cloned().unwrap_or_default();
+            let lines = match context.world.resource_mut::<Inventory>() {
+                Some(inventory) => rewards.apply(inventory, &items),
+                None => return
+            };
+            tracing::info!(target: targets::BATTLE, "Beat {} in {} turns", setup.formation, report.turns);
+            battle_report::record(context.world, report.finish(BattleOutcome::Won));
+            if lines.is_empty() {
+                tracing::info!(target: targets::BATTLE, "  Nothing dropped");
+            }
+            for line in lines {
+                tracing::info!(target: targets::BATTLE, "  {}", line);
+            }
+        },
+        // "combo" lists the combos and whether they can be used, and "combo <name>" uses one,
+        // taking everyone's MP. Nothing runs battles yet, so that's all it does.
+        "combo" => {
+            let combos = context.world.resource::<ComboTechs>().cloned().unwrap_or_default();
+            let party = match context.world.resource_mut::<Party>() {
+                Some(party) => party,
+                None => return
+            };
+            if command.args.is_empty() {
+                for combo in &combos.0 {
+                    let members: Vec<&str> = combo.members.iter().map(|member| member.name.as_str()).collect();
+                    match combo.check(party) {
+                        Ok(_) => tracing::info!(target: targets::BATTLE, "{} ({}): {}", combo.name, members.join(" + "), combo.description),
+                        Err(e) => tracing::info!(target: targets::BATTLE, "{} ({}): can't be used, {}", combo.name, members.join(" + "), e)
+                    }
+                }
+                return;
+            }
+            let combo = match combos.get(&command.args) {
+                Some(combo) => combo,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "No combo called \"{}\"", command.args);
+                    return;
+                }
+            };
+            match combo.pay(party) {
+                Ok(users) => {
+                    let names: Vec<&str> = users.iter().map(|index| party.members[*index].name.as_str()).collect();
+                    tracing::info!(target: targets::BATTLE, "{} use {}", names.join(" and "), combo.name);
+                },
+                Err(e) => tracing::info!(target: targets::BATTLE, "Can't use {}: {}", combo.name, e)
+            }
+        },
+        // "summon" lists the summons, and "summon <id>" has whoever knows the skill that calls
+        // one use it, taking their MP. Nothing runs battles yet, so that's all it does.
+        "summon" => {
+            let summons = context.world.resource::<Summons>().cloned().unwrap_or_default();
+            let lasts = |summon: &SummonDefinition| match summon.turns {
+                Some(turns) => format!("{} turns", turns),
+                None => "the whole battle".to_string()
+            };
+            if command.args.is_empty() {
+                for summon in &summons.0 {
+                    tracing::info!(target: targets::BATTLE, "{} ({}): called by {}, for {}, HP {} strength {} speed {}",
+                        summon.name, summon.id, summon.skill, lasts(summon), summon.stats.max_hp, summon.stats.strength, summon.stats.speed);
+                }
+                return;
+            }
+            let summon = match summons.get(&command.args) {
+                Some(summon) => summon,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "No summon called \"{}\"", command.args);
+                    return;
+                }
+            };
+            let mut inventory = context.world.remove_resource::<Inventory>().unwrap_or_default();
+            let caller = context.world.resource_mut::<Party>().and_then(|party| party.members.iter_mut()
+                .filter(|member| !member.stats.is_knocked_out())
+                .find_map(|member| {
+                    let skill = member.skills.iter().find(|skill| skill.name.eq_ignore_ascii_case(&summon.skill))?.clone();
+                    Some((member, skill))
+                }));
+            match caller {
+                Some((member, skill)) => match pools::pay(member, &skill, &mut inventory) {
+                    Ok(()) => tracing::info!(target: targets::BATTLE, "{} uses {}, and {} joins for {}", member.name, skill.name, summon.name, lasts(summon)),
+                    Err(e) => tracing::info!(target: targets::BATTLE, "Can't use {}: {}", skill.name, e)
+                },
+                None => tracing::info!(target: targets::BATTLE, "Nobody who's standing knows {}", summon.skill)
+            }
+            context.world.insert_resource(inventory);
+        },
+        // "steal <enemy>" tries to steal from an enemy, like the Steal command will. Each go is a
+        // fresh enemy, so it can always be stolen from.
+        "steal" => {
+            let table = match context.world.resource::<LootTables>().and_then(|tables| tables.get(&command.args)) {
+                Some(table) => table.clone(),
+                None => {
+                    tracing::error!(target: targets::ENGINE, "No loot table for \"{}\"", command.args);
+                    return;
+                }
+            };
+            let result = match context.world.resource_mut::<Rng>() {
+                Some(rng) => loot::steal(&table, &mut false, 0.0, rng.stream(rng::streams::LOOT)),
+                None => return
+            };
+            match result {
+                StealResult::Stole(stolen) => {
+                    let item = match context.world.resource::<ItemCatalog>().and_then(|items| items.get(&stolen.item)) {
+                        Some(item) => item.clone(),
+                        None => return
+                    };
+                    if let Some(inventory) = context.world.resource_mut::<Inventory>() {
+                        inventory.add(&item, 1);
+                    }
+                    tracing::info!(target: targets::BATTLE, "{}Stole {} from {}", if stolen.rare { "Rare item! " } else { "" }, item.name, command.args);
+                },
+                StealResult::Missed => tracing::info!(target: targets::BATTLE, "Couldn't steal anything"),
+                StealResult::AlreadyStolen => tracing::info!(target: targets::BATTLE, "Already stole from {}", command.args),
+                StealResult::NothingToSteal => tracing::info!(target: targets::BATTLE, "{} has nothing to steal", command.args)
+            }
+        },
+        // List every achievement and whether it's unlocked.
+        "achievements" => {
+            for definition in context.achievements.definitions() {
+                let unlocked = if context.achievements.is_unlocked(&definition.id) { "x" } else { " " };
+                tracing::info!(target: targets::ENGINE, "[{}] {} - {}", unlocked, definition.name, definition.description);
+            }
+        },
+        // Pretend something happened, e.g. "event battle.won".
+        "event" => context.achievements.notify_event(&command.args, context.platform),
+        "movie" if command.args.is_empty() => tracing::warn!(target: targets::ENGINE, "Usage: movie <name>"),
+        "movie" => *context.loading_movie = Some(load_movie(context.assets, &command.args, &context.config.language)),
+        // Recompile a shader from the source tree, e.g. "shader model.wgsl".
+        "shader" if command.args.is_empty() => tracing::warn!(target: targets::ENGINE, "Usage: shader <file>"),
+        "shader" => match std::fs::read_to_string(Path::new("src").join(&command.args)) {
+            Ok(source) => {
+                if !context.renderer.reload_shader(&command.args, &source) {
+                    tracing::error!(target: targets::ENGINE, "No shader \"{}\"", command.args);
+                }
+            },
+            Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't read shader \"{}\": {}", command.args, e)
+        },
+        // "morph entity" lists an entity's morph targets, "morph entity target weight [seconds]"
+        // sets one, blending over the time given.
+        "morph" => {
+            let mut args = command.args.split_whitespace();
+            let weights = match args.next().and_then(|name| context.world.find_by_name(name)) {
+                Some(entity) => context.world.get_mut::<MorphWeights>(entity),
+                None => {
+                    tracing::error!(target: targets::ENGINE, "Usage: morph <entity> [target] [weight] [seconds]");
+                    return;
+                }
+            };
+            let weights = match weights {
+                Some(weights) => weights,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "That entity doesn't have morph targets");
+                    return;
+                }
+            };
+            let (target, weight, seconds) = (args.next(), args.next().map(str::parse::<f32>), args.next().map(str::parse::<f32>));
+            match (target, weight, seconds.unwrap_or(Ok(0.0))) {
+                (None, _, _) => {
+                    for name in weights.names() {
+                        tracing::info!(target: targets::ENGINE, "{} = {}", name, weights.get(name).unwrap_or_default());
+                    }
+                },
+                (Some(target), Some(Ok(weight)), Ok(seconds)) if seconds >= 0.0 => {
+                    if !weights.animate(target, weight, Duration::from_secs_f32(seconds)) {
+                        tracing::error!(target: targets::ENGINE, "No morph target \"{}\"", target);
+                    }
+                },
+                _ => tracing::error!(target: targets::ENGINE, "Bad morph weight or time")
+            }
+        },
+        // Show an emote over an entity like a script would, e.g. "emote Shopkeeper question 2".
+        "emote" => {
+            let mut args = command.args.split_whitespace();
+            let (entity, kind) = match (args.next().and_then(|name| context.world.find_by_name(name)), args.next().map(str::parse::<EmoteKind>)) {
+                (Some(entity), Some(Ok(kind))) => (entity, kind),
+                (_, Some(Err(e))) => {
+                    tracing::error!(target: targets::ENGINE, "{}", e);
+                    return;
+                },
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Usage: emote <entity> <exclamation/question/heart/sweat> [seconds]");
+                    return;
+                }
+            };
+            let duration = match args.next() {
+                Some(seconds) => match seconds.parse().ok().and_then(|seconds| Duration::try_from_secs_f32(seconds).ok()) {
+                    Some(duration) if !duration.is_zero() => duration,
+                    _ => {
+                        tracing::error!(target: targets::ENGINE, "Bad duration \"{}\"", seconds);
+                        return;
+                    }
+                },
+                None => emote::DEFAULT_DURATION
+            };
+            emote::show_emote(context.world, entity, kind, duration);
+        },
+        // Give an entity a vision cone like a guard's, e.g. "guard Shopkeeper 60 8", or take it
+        // away with "guard Shopkeeper off".
+        "guard" => {
+            let mut args = command.args.split_whitespace();
+            let entity = match args.next().and_then(|name| context.world.find_by_name(name)) {
+                Some(entity) => entity,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "Usage: guard <entity> [angle range] or guard <entity> off");
+                    return;
+                }
+            };
+            let cone = match (args.next(), args.next()) {
+                (Some("off"), None) => {
+                    context.world.remove::<VisionCone>(entity);
+                    context.world.remove::<vision::SeeingPlayer>(entity);
+                    return;
+                },
+                (None, None) => VisionCone::default(),
+                (Some(angle), Some(range)) => match (angle.parse::<f32>(), range.parse::<f32>()) {
+                    (Ok(angle), Ok(range)) if (0.0..=360.0).contains(&angle) && range > 0.0 => VisionCone { angle, range },
+                    _ => {
+                        tracing::error!(target: targets::ENGINE, "Bad angle or range");
+                        return;
+                    }
+                },
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Usage: guard <entity> [angle range] or guard <entity> off");
+                    return;
+                }
+            };
+            context.world.insert(entity, cone);
+        },
+        // Send an entity after another one, the player if not given, e.g. "chase Guard Player 3
+        // battle=guards" to fight the guards formation when they catch up. "chase Guard off"
+        // calls them off.
+        "chase" => {
+            let mut args = command.args.split_whitespace();
+            let entity = match args.next().and_then(|name| context.world.find_by_name(name)) {
+                Some(entity) => entity,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "Usage: chase <entity> [target] [speed] [battle=formation] or chase <entity> off");
+                    return;
+                }
+            };
+            let player = match context.world.find_by_name("Player") {
+                Some(player) => player,
+                None => return
+            };
+            let is_2d = context.world.resource::<Tilemap>().is_some();
+            let mut chaser = Chaser::new(player, if is_2d { chase::DEFAULT_SPEED_2D } else { chase::DEFAULT_SPEED });
+            if is_2d {
+                chaser.catch_radius = chase::DEFAULT_CATCH_RADIUS_2D;
+            }
+            for arg in args {
+                if arg == "off" {
+                    context.world.remove::<Chaser>(entity);
+                    return;
+                } else if let Some(battle) = arg.strip_prefix("battle=") {
+                    chaser.battle = Some(battle.to_string());
+                } else if let Ok(speed) = arg.parse::<f32>() {
+                    if speed <= 0.0 {
+                        tracing::error!(target: targets::ENGINE, "Bad speed \"{}\"", arg);
+                        return;
+                    }
+                    chaser.speed = speed;
+                } else {
+                    match context.world.find_by_name(arg) {
+                        Some(target) if target != entity => chaser.target = target,
+                        _ => {
+                            tracing::error!(target: targets::ENGINE, "No entity \"{}\" to chase", arg);
+                            return;
+                        }
+                    }
+                }
+            }
+            context.world.insert(entity, chaser);
+        },
+        // "mover" on its own lists the field's movers and whether they're going, otherwise it
+        // starts or stops one, e.g. "mover lift stop". "restart" sends it back to the start.
+        "mover" if command.args.is_empty() => {
+            for (entity, moving) in context.world.query::<Mover>() {
+                let name = context.world.get::<Name>(entity).map_or("?", |name| name.0.as_str());
+                tracing::info!(target: targets::ENGINE, "{}: {}, {}, {:.1}s", name, moving.mode, if moving.playing { "going" } else { "stopped" }, moving.time().as_secs_f32());
+            }
+        },
+        "mover" => {
+            let mut args = command.args.split_whitespace();
+            let (name, action) = match (args.next(), args.next()) {
+                (Some(name), Some(action)) => (name, action),
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Usage: mover <name> start/stop/restart");
+                    return;
+                }
+            };
+            let found = match action {
+                "start" | "stop" => mover::set_playing(context.world, name, action == "start"),
+                "restart" => match context.world.find_by_name(name).and_then(|entity| context.world.get_mut::<Mover>(entity)) {
+                    Some(moving) => {
+                        moving.restart();
+                        true
+                    },
+                    None => false
+                },
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Usage: mover <name> start/stop/restart");
+                    return;
+                }
+            };
+            if !found {
+                tracing::error!(target: targets::ENGINE, "No mover \"{}\"", name);
+            }
+        },
+        // "ghost record" records the player until "ghost stop <name>" saves it, "ghost play
+        // <name> [loop]" plays one back and "ghost clear" gets rid of them.
+        "ghost" => {
+            let mut args = command.args.split_whitespace();
+            let path = |name: &str| paths::ghost_dir().join(format!("{}.ghost", name));
+            match (args.next(), args.next()) {
+                (Some("record"), None) => match context.world.find_by_name("Player") {
+                    Some(player) => {
+                        ghost::start_recording(context.world, player, context.current_field);
+                        tracing::info!(target: targets::ENGINE, "Recording a ghost");
+                    },
+                    None => tracing::error!(target: targets::ENGINE, "There's no player to record")
+                },
+                (Some("stop"), Some(name)) => match ghost::stop_recording(context.world) {
+                    Some(track) => {
+                        let path = path(name);
+                        let written = std::fs::create_dir_all(paths::ghost_dir()).and_then(|_| std::fs::write(&path, track.to_bytes()));
+                        match written {
+                            Ok(_) => tracing::info!(target: targets::ENGINE, "Saved {:.1}s of ghost to {}", track.duration().as_secs_f32(), path.display()),
+                            Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't save {}: {}", path.display(), e)
+                        }
+                    },
+                    None => tracing::error!(target: targets::ENGINE, "Nothing's being recorded")
+                },
+                (Some("play"), Some(name)) => {
+                    let path = path(name);
+                    let track = match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| GhostTrack::from_bytes(&bytes)) {
+                        Ok(track) => track,
+                        Err(e) => {
+                            tracing::error!(target: targets::ENGINE, "Couldn't load {}: {}", path.display(), e);
+                            return;
+                        }
+                    };
+                    if track.field != context.current_field {
+                        tracing::warn!(target: targets::ENGINE, "{} was recorded in {}, not here", name, track.field);
+                    }
+                    // Ghosts look like the player.
+                    let model = context.world.find_by_name("Player").and_then(|player| context.world.get::<ModelInstance>(player)).map(|instance| instance.0);
+                    ghost::spawn_ghost(context.world, track, model, args.next() == Some("loop"));
+                },
+                (Some("clear"), None) => ghost::despawn_ghosts(context.world),
+                _ => tracing::error!(target: targets::ENGINE, "Usage: ghost record, ghost stop <name>, ghost play <name> [loop] or ghost clear")
+            }
+        },
+        // Knock an entity back as if the camera hit it, e.g. "hit Player 2".
+        "hit" => {
+            let mut args = command.args.split_whitespace();
+            let target = match args.next().and_then(|name| context.world.find_by_name(name)) {
+                Some(target) => target,
+                None => {
+                    tracing::error!(target: targets::ENGINE, "Usage: hit <entity> [strength]");
+                    return;
+                }
+            };
+            let strength = match args.next().map(str::parse::<f32>).unwrap_or(Ok(1.0)) {
+                Ok(strength) => strength,
+                Err(e) => {
+                    tracing::error!(target: targets::ENGINE, "Bad strength: {}", e);
+                    return;
+                }
+            };
+            let eye = context.world.resource::<Camera>().copied().unwrap_or_default().eye;
+            let position = context.world.get::<Transform>(target).map_or(Vector3::new(0.0, 0.0, 0.0), |transform| transform.position);
+            if let Some(events) = context.world.resource_mut::<HitEvents>() {
+                events.send(HitEvent { target, direction: position - eye.to_vec(), strength });
+            }
+        },
+        "stats" => {
+            if let Some(stats) = context.world.resource::<PlayStats>() {
+                tracing::info!(target: targets::ENGINE, "Play time {}, {} steps, {} battles won, {} gil earned",
+                    play_stats::format_playtime(stats.playtime()), stats.steps, stats.battles_won, stats.gil_earned);
+            }
+        },
+        // "assets" on its own reports unused and missing assets, otherwise it lists what
+        // something needs, like "assets field/test_field".
+        "assets" if command.args.is_empty() => {
+            let report = context.assets.report(context.manifest);
+            for path in &report.unused {
+                tracing::info!(target: targets::ASSETS, "Unused: {}", path);
+            }
+            for missing in &report.missing {
+                tracing::warn!(target: targets::ASSETS, "Not in the manifest: {}, needed by {}", missing.path, missing.owner);
+            }
+            tracing::info!(target: targets::ASSETS, "{} unused, {} missing", report.unused.len(), report.missing.len());
+        },
+        "assets" => {
+            let dependencies = context.assets.dependencies_of(&command.args);
+            if dependencies.is_empty() {
+                tracing::info!(target: targets::ASSETS, "Nothing's recorded as needed by {}", command.args);
+            }
+            for path in dependencies {
+                tracing::info!(target: targets::ASSETS, "{} needs {}", command.args, path);
+            }
+        },
+        // "exportsave <slot> [file]" and "importsave <slot> [file]" copy a slot out to a file or
+        // back in, like E and I in the save menu but with any file.
+        "exportsave" | "importsave" => {
+            let mut words = command.args.split_whitespace();
+            let slot = match words.next().map(str::parse::<usize>) {
+                Some(Ok(slot)) if (1..=save::SLOT_COUNT).contains(&slot) => slot - 1,
+                _ => {
+                    tracing::error!(target: targets::ENGINE, "Expected \"{} <slot> [file]\" with a slot from 1 to {}", command.name, save::SLOT_COUNT);
+                    return;
+                }
+            };
+            let file = words.next().map_or_else(|| save::export_path(&paths::export_dir(), slot), PathBuf::from);
+            if command.name == "importsave" {
+                import_save(context.platform, &file, slot);
+            } else {
+                export_save(context.platform, slot, &file);
+            }
+        },
+        // "commands [file]" lists every command and what it takes as JSON, in the log or a file.
+        "commands" if command.args.is_empty() => tracing::info!(target: targets::ENGINE, "{}", console::commands_json()),
+        "commands" => match std::fs::write(&command.args, console::commands_json()) {
+            Ok(_) => tracing::info!(target: targets::ENGINE, "Wrote the commands to {}", command.args),
+            Err(e) => tracing::error!(target: targets::ENGINE, "Couldn't write {}: {}", command.args, e)
+        },
+        "help" => tracing::info!(target: targets::ENGINE, "{}", console::help()),
+        _ => tracing::warn!(target: targets::ENGINE, "Unknown command \"{}\"", command.name)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[tokio::main]
 async fn main() {
-    run_game_window().await;
+    let logging = Logging::init();
+    if let Some(log_dir) = logging.log_dir() {
+        tracing::info!(target: targets::ENGINE, "Logging to {}", log_dir.display());
+    }
+
+    // "--validate-data" checks the data files and exits, with 1 if anything's wrong, for build
+    // scripts.
+    if std::env::args().any(|arg| arg == "--validate-data") {
+        let assets = AssetServer::default();
+        let report = validate_game_data(&assets, &load_manifest(&assets).await, &test_fields(), &test_party()).await;
+        for issue in &report.issues {
+            println!("{}", issue);
+        }
+        println!("{} issues", report.issues.len());
+        std::process::exit(if report.has_errors() { 1 } else { 0 });
+    }
+
+    run_game_window(logging).await;
 }
@@ -0,0 +1,158 @@
+// What the menus share: the sounds they make and arrow keys repeating while they're held.
+//
+// Each menu says which sound it wants after a key or pointer event with take_sound: moving the
+// cursor, picking something, going back, or trying to pick something that can't be. Which file
+// each plays, on the sfx bus, is in data/menu_sounds.cfg:
+//
+//   move = sounds/menu_move.wav
+//   confirm = sounds/menu_confirm.wav
+//   cancel = sounds/menu_cancel.wav
+//   error = sounds/menu_error.wav
+//
+// Anything left out is silent.
+//
+// Held arrow keys move the cursor again after a moment, then steadily, at the same pace
+// everywhere rather than the system's key repeat, which varies.
+
+use std::{collections::HashSet, fmt, str::FromStr, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+// How long an arrow key's held before it starts repeating, then how often it repeats.
+pub const REPEAT_DELAY: Duration = Duration::from_millis(400);
+pub const REPEAT_INTERVAL: Duration = Duration::from_millis(80);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuSound {
+    Move,
+    Confirm,
+    Cancel,
+    Error
+}
+
+impl MenuSound {
+    pub const ALL: [MenuSound; 4] = [MenuSound::Move, MenuSound::Confirm, MenuSound::Cancel, MenuSound::Error];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MenuSound::Move => "move",
+            MenuSound::Confirm => "confirm",
+            MenuSound::Cancel => "cancel",
+            MenuSound::Error => "error"
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for MenuSound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MenuSound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MenuSound::ALL.into_iter()
+            .find(|sound| sound.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown menu sound \"{}\", expected move, confirm, cancel or error", s.trim()))
+    }
+}
+
+// Which file each menu sound plays.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MenuSounds([Option<String>; 4]);
+
+impl MenuSounds {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut sounds = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (sound, path) = line.split_once('=')
+                .map(|(sound, path)| (sound.trim(), path.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"sound = path\"", number + 1))?;
+            let sound: MenuSound = sound.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?;
+            sounds.0[sound.index()] = Some(path.to_string()).filter(|path| !path.is_empty());
+        }
+        Ok(sounds)
+    }
+
+    pub fn get(&self, sound: MenuSound) -> Option<&str> {
+        self.0[sound.index()].as_deref()
+    }
+
+    // Every file they play, for checking they're all there.
+    pub fn files(&self) -> Vec<&str> {
+        self.0.iter().flatten().map(String::as_str).collect()
+    }
+}
+
+fn is_arrow(key: VirtualKeyCode) -> bool {
+    matches!(key, VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::Left | VirtualKeyCode::Right)
+}
+
+// Which keys are down, to repeat the arrow keys for menus and tell the system's own repeats
+// apart from real presses.
+#[derive(Clone, Debug, Default)]
+pub struct KeyRepeat {
+    down: HashSet<VirtualKeyCode>,
+    // The last arrow pressed, while it's still down, and how long for.
+    held: Option<(VirtualKeyCode, Duration)>
+}
+
+impl KeyRepeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A key going down or up. Returns false for the system repeating a key that's already
+    // down, which should be ignored.
+    pub fn handle_key(&mut self, key: VirtualKeyCode, pressed: bool) -> bool {
+        if !pressed {
+            self.down.remove(&key);
+            if self.held.is_some_and(|(held, _)| held == key) {
+                self.held = None;
+            }
+            return true;
+        }
+        if !self.down.insert(key) {
+            return false;
+        }
+        if is_arrow(key) {
+            self.held = Some((key, Duration::ZERO));
+        }
+        true
+    }
+
+    // Everything's let go, like when the window loses focus.
+    pub fn release_all(&mut self) {
+        self.down.clear();
+        self.held = None;
+    }
+
+    // An arrow's held, so it's worth checking back each frame.
+    pub fn is_holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    // The arrow to move the cursor with again, if it's time. Only one a frame, so a long frame
+    // doesn't send the cursor flying.
+    pub fn update(&mut self, delta: Duration) -> Option<VirtualKeyCode> {
+        let (key, held) = self.held.as_mut()?;
+        let before = *held;
+        *held += delta;
+        let repeats = |time: Duration| match time.checked_sub(REPEAT_DELAY) {
+            Some(past) => past.as_nanos() / REPEAT_INTERVAL.as_nanos() + 1,
+            None => 0
+        };
+        (repeats(*held) > repeats(before)).then_some(*key)
+    }
+}
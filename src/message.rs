@@ -0,0 +1,229 @@
+// The message window along the bottom of the screen, where what characters say goes. Text's
+// wrapped to fit the window as it's drawn, at whatever size the player's picked, and split into
+// pages of a few lines when there's too much for one, so translators can write lines as long as
+// they like without breaking them up by hand. A blank line always starts a new page, and a soft
+// hyphen marks where a long word can be broken.
+//
+// Each page types out at the player's text speed. The action button finishes typing the page,
+// then goes to the next, and closes the window after the last.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::font;
+use crate::pointer::PointerEvent;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::subtitles;
+use crate::ui::UiBatch;
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+pub const LINES_PER_PAGE: usize = 3;
+
+// What happens to text that doesn't fit on one page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    // It goes on the next page.
+    #[default]
+    Paginate,
+    // It's cut off, with "..." on the end of the last line, for windows that only have room for
+    // the one page, like a sign.
+    Ellipsis
+}
+
+impl Overflow {
+    pub const ALL: [Overflow; 2] = [Overflow::Paginate, Overflow::Ellipsis];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overflow::Paginate => "paginate",
+            Overflow::Ellipsis => "ellipsis"
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Overflow::ALL.into_iter()
+            .find(|overflow| overflow.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown overflow \"{}\", expected paginate or ellipsis", s.trim()))
+    }
+}
+
+// How text's fitted into a window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MessageLayout {
+    // In cells of the font, with wide characters taking two.
+    pub columns: usize,
+    pub lines_per_page: usize,
+    pub overflow: Overflow,
+    // Whether words can be broken with a hyphen, see subtitles::wrap_hyphenated.
+    pub hyphenate: bool
+}
+
+impl MessageLayout {
+    // For the message window at the player's text size.
+    pub fn new(accessibility: &Accessibility) -> Self {
+        let width = SCREEN_WIDTH as f32 - (MARGIN + PADDING) * 2.0;
+        Self {
+            columns: (width / (font::CELL_WIDTH as f32 * text_scale(accessibility))) as usize,
+            lines_per_page: LINES_PER_PAGE,
+            overflow: Overflow::default(),
+            hyphenate: true
+        }
+    }
+
+    // Wrap the text and split it into pages, each a few lines joined by '\n'.
+    pub fn paginate(&self, text: &str) -> Vec<String> {
+        let lines_per_page = self.lines_per_page.max(1);
+        let mut pages = Vec::new();
+        for paragraph in text.split("\n\n").filter(|paragraph| !paragraph.trim().is_empty()) {
+            let wrapped = if self.hyphenate {
+                subtitles::wrap_hyphenated(paragraph.trim_matches('\n'), self.columns)
+            } else {
+                subtitles::wrap(paragraph.trim_matches('\n'), self.columns)
+            };
+            let lines: Vec<&str> = wrapped.lines().collect();
+            pages.extend(lines.chunks(lines_per_page).map(|page| page.join("\n")));
+        }
+
+        if self.overflow == Overflow::Ellipsis && pages.len() > 1 {
+            pages.truncate(1);
+            let mut lines: Vec<String> = pages[0].lines().map(str::to_string).collect();
+            if let Some(last) = lines.last_mut() {
+                while !last.is_empty() && last.chars().map(|c| font::char_columns(c) as usize).sum::<usize>() + 3 > self.columns {
+                    last.pop();
+                }
+                *last = format!("{}...", last.trim_end());
+            }
+            pages[0] = lines.join("\n");
+        }
+        pages
+    }
+}
+
+fn text_scale(accessibility: &Accessibility) -> f32 {
+    2.0 * accessibility.text_scale()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MessageWindow {
+    text: Option<String>,
+    overflow: Overflow,
+    hyphenate: bool,
+    page: usize,
+    // How long the page has been typing for, or None once it's all showing.
+    typing: Option<Duration>
+}
+
+impl MessageWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self, text: &str) {
+        self.open_with(text, Overflow::Paginate, true);
+    }
+
+    pub fn open_with(&mut self, text: &str, overflow: Overflow, hyphenate: bool) {
+        *self = Self { text: Some(text.to_string()), overflow, hyphenate, page: 0, typing: Some(Duration::ZERO) };
+    }
+
+    pub fn close(&mut self) {
+        self.text = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.text.is_some()
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    pub fn layout(&self, accessibility: &Accessibility) -> MessageLayout {
+        MessageLayout { overflow: self.overflow, hyphenate: self.hyphenate, ..MessageLayout::new(accessibility) }
+    }
+
+    // Laid out again each time, so changing the text size while it's open works.
+    pub fn pages(&self, accessibility: &Accessibility) -> Vec<String> {
+        self.text.as_deref().map(|text| self.layout(accessibility).paginate(text)).unwrap_or_default()
+    }
+
+    // The page as far as it's typed.
+    pub fn visible_text(&self, accessibility: &Accessibility) -> String {
+        let page = self.pages(accessibility).get(self.page).cloned().unwrap_or_default();
+        match self.typing {
+            Some(elapsed) => page.chars().take(accessibility.text_speed.visible_chars(elapsed, page.chars().count())).collect(),
+            None => page
+        }
+    }
+
+    // Type out more of the page. Returns whether anything changed.
+    pub fn update(&mut self, delta: Duration, accessibility: &Accessibility) -> bool {
+        let elapsed = match (&self.text, self.typing) {
+            (Some(_), Some(elapsed)) => elapsed + delta,
+            _ => return false
+        };
+        let length = self.pages(accessibility).get(self.page).map(|page| page.chars().count()).unwrap_or_default();
+        self.typing = (accessibility.text_speed.visible_chars(elapsed, length) < length).then_some(elapsed);
+        true
+    }
+
+    // Finish typing the page, or go to the next, or close after the last.
+    pub fn advance(&mut self, accessibility: &Accessibility) {
+        if !self.is_open() {
+            return;
+        }
+        if self.typing.is_some() {
+            self.typing = None;
+        } else if self.page + 1 < self.pages(accessibility).len() {
+            self.page += 1;
+            self.typing = Some(Duration::ZERO);
+        } else {
+            self.close();
+        }
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode, accessibility: &Accessibility) {
+        if let VirtualKeyCode::Return | VirtualKeyCode::Space | VirtualKeyCode::Z = key {
+            self.advance(accessibility);
+        }
+    }
+
+    // A click or tap anywhere does the same as the action button.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) {
+        if let PointerEvent::Click { .. } = event {
+            self.advance(accessibility);
+        }
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        if !self.is_open() {
+            return;
+        }
+        let skin = accessibility.skin();
+        let scale = text_scale(accessibility);
+        let lines = self.layout(accessibility).lines_per_page as f32;
+        let height = lines * UiBatch::line_height(scale) - scale + PADDING * 2.0;
+        let y = SCREEN_HEIGHT as f32 - MARGIN - height;
+        batch.rect(MARGIN, y, SCREEN_WIDTH as f32 - MARGIN * 2.0, height, skin.window);
+        batch.text(MARGIN + PADDING, y + PADDING, scale, &self.visible_text(accessibility), skin.text);
+
+        // Say there's more once the page has finished typing.
+        if self.typing.is_none() && self.page + 1 < self.pages(accessibility).len() {
+            let (width, more_height) = UiBatch::measure_text(scale, "v");
+            batch.text(SCREEN_WIDTH as f32 - MARGIN - PADDING - width, y + height - PADDING - more_height, scale, "v", skin.highlight);
+        }
+    }
+}
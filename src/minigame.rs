@@ -0,0 +1,257 @@
+// Mini-games, like cards, fishing or racing, that take over from the field for a while. Each is
+// self-contained: it gets its own updates, draws itself and takes all the input while it runs,
+// and doesn't touch the rest of the game. When it's done it hands back a number, like a score
+// or 1 for a win, which goes into a flag for the script that started it to read.
+//
+// Games are registered by name, then started with a line like "fishing pond=river result=fish"
+// where everything but "result" is passed to the game as it's started.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::flags::GameFlags;
+use crate::pointer::PointerEvent;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ui::UiBatch;
+
+pub trait MiniGame {
+    // Move the game on, returning its result once it's over.
+    fn update(&mut self, delta: Duration) -> Option<i32>;
+    fn handle_key(&mut self, key: VirtualKeyCode);
+    fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility);
+    fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility);
+}
+
+// Starts a game with the parameters it was launched with, or says what's wrong with them.
+pub type MiniGameFactory = fn(&MiniGameParams) -> Result<Box<dyn MiniGame>, String>;
+
+// The "key=value" parameters a game was launched with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MiniGameParams(pub Vec<(String, String)>);
+
+impl MiniGameParams {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    // A parameter read as a number or the like, or the default if it wasn't given.
+    pub fn parse_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.get(key) {
+            Some(value) => value.parse().map_err(|_| format!("Bad {} \"{}\"", key, value)),
+            None => Ok(default)
+        }
+    }
+}
+
+// Which game to start, what with, and which flag to put the result in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiniGameLaunch {
+    pub game: String,
+    pub params: MiniGameParams,
+    pub result_flag: Option<String>
+}
+
+impl FromStr for MiniGameLaunch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let mut launch = MiniGameLaunch {
+            game: words.next().ok_or("Expected a mini-game")?.to_string(),
+            params: MiniGameParams::default(),
+            result_flag: None
+        };
+        for word in words {
+            let (key, value) = word.split_once('=').ok_or_else(|| format!("Expected key=value, not \"{}\"", word))?;
+            match key {
+                "result" => launch.result_flag = Some(value.to_string()),
+                _ => launch.params.0.push((key.to_string(), value.to_string()))
+            }
+        }
+        Ok(launch)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MiniGameResult {
+    pub game: String,
+    pub value: i32,
+    pub result_flag: Option<String>
+}
+
+impl MiniGameResult {
+    // Hand the result back to the script, if it asked for it.
+    pub fn apply(&self, flags: &mut GameFlags) {
+        if let Some(flag) = &self.result_flag {
+            flags.set(flag, self.value);
+        }
+    }
+}
+
+struct RunningGame {
+    launch: MiniGameLaunch,
+    game: Box<dyn MiniGame>
+}
+
+// Every mini-game there is, and the one that's running. Only one runs at a time.
+#[derive(Default)]
+pub struct MiniGames {
+    factories: Vec<(String, MiniGameFactory)>,
+    running: Option<RunningGame>
+}
+
+impl MiniGames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, name: &str, factory: MiniGameFactory) {
+        self.factories.retain(|(existing, _)| existing != name);
+        self.factories.push((name.to_string(), factory));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    // The name of the game that's running.
+    pub fn running(&self) -> Option<&str> {
+        self.running.as_ref().map(|running| running.launch.game.as_str())
+    }
+
+    pub fn launch(&mut self, launch: &MiniGameLaunch) -> Result<(), String> {
+        if let Some(running) = self.running() {
+            return Err(format!("{} is still running", running));
+        }
+        let factory = self.factories.iter()
+            .find(|(name, _)| *name == launch.game)
+            .map(|(_, factory)| factory)
+            .ok_or_else(|| format!("There's no mini-game called \"{}\"", launch.game))?;
+        let game = factory(&launch.params).map_err(|e| format!("Couldn't start {}: {}", launch.game, e))?;
+        self.running = Some(RunningGame { launch: launch.clone(), game });
+        Ok(())
+    }
+
+    // Move the running game on, returning its result when it's over.
+    pub fn update(&mut self, delta: Duration) -> Option<MiniGameResult> {
+        let value = self.running.as_mut()?.game.update(delta)?;
+        let launch = self.running.take()?.launch;
+        Some(MiniGameResult { game: launch.game, value, result_flag: launch.result_flag })
+    }
+
+    pub fn handle_key(&mut self, key: VirtualKeyCode) {
+        if let Some(running) = &mut self.running {
+            running.game.handle_key(key);
+        }
+    }
+
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) {
+        if let Some(running) = &mut self.running {
+            running.game.handle_pointer(event, accessibility);
+        }
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        if let Some(running) = &self.running {
+            running.game.build(batch, accessibility);
+        }
+    }
+}
+
+impl fmt::Debug for MiniGames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiniGames")
+            .field("games", &self.names().collect::<Vec<_>>())
+            .field("running", &self.running())
+            .finish()
+    }
+}
+
+const MARGIN: f32 = 12.0;
+const BAR_WIDTH: f32 = 480.0;
+const BAR_HEIGHT: f32 = 24.0;
+const MARKER_WIDTH: f32 = 4.0;
+
+// A small game to try the hooks out with: a marker sweeps back and forth along a bar, and
+// stopping it nearer the middle scores more, up to 100 a go. The result is the total score.
+//
+//   speed = Sweeps across the bar a second, 1 if not given
+//   tries = How many goes there are, 3 if not given
+pub struct TimingGame {
+    speed: f32,
+    tries: usize,
+    // How far through a there-and-back sweep the marker is, from 0 to 2.
+    sweep: f32,
+    scores: Vec<i32>
+}
+
+impl TimingGame {
+    pub fn start(params: &MiniGameParams) -> Result<Box<dyn MiniGame>, String> {
+        let speed: f32 = params.parse_or("speed", 1.0)?;
+        let tries: usize = params.parse_or("tries", 3)?;
+        if speed <= 0.0 || tries == 0 {
+            return Err("speed and tries have to be more than 0".to_string());
+        }
+        Ok(Box::new(TimingGame { speed, tries, sweep: 0.0, scores: Vec::new() }))
+    }
+
+    // Where the marker is along the bar, from 0 to 1.
+    pub fn position(&self) -> f32 {
+        if self.sweep <= 1.0 { self.sweep } else { 2.0 - self.sweep }
+    }
+
+    fn stop(&mut self) {
+        if self.scores.len() < self.tries {
+            let score = 100.0 - (self.position() - 0.5).abs() * 200.0;
+            self.scores.push(score.round() as i32);
+        }
+    }
+}
+
+impl MiniGame for TimingGame {
+    fn update(&mut self, delta: Duration) -> Option<i32> {
+        if self.scores.len() >= self.tries {
+            return Some(self.scores.iter().sum());
+        }
+        self.sweep = (self.sweep + delta.as_secs_f32() * self.speed * 2.0) % 2.0;
+        None
+    }
+
+    // Enter or space stops the marker. Escape gives up, keeping the score so far.
+    fn handle_key(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::Space => self.stop(),
+            VirtualKeyCode::Escape => self.tries = self.scores.len(),
+            _ => {}
+        }
+    }
+
+    fn handle_pointer(&mut self, event: PointerEvent, _accessibility: &Accessibility) {
+        if let PointerEvent::Click { .. } = event {
+            self.stop();
+        }
+    }
+
+    fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, skin.backdrop);
+        batch.text(MARGIN, MARGIN, scale * 1.5, "Stop it in the middle", skin.highlight);
+
+        let x = (SCREEN_WIDTH as f32 - BAR_WIDTH) / 2.0;
+        let y = SCREEN_HEIGHT as f32 / 2.0;
+        batch.rect(x, y, BAR_WIDTH, BAR_HEIGHT, skin.window);
+        batch.rect(x + BAR_WIDTH * 0.45, y, BAR_WIDTH * 0.1, BAR_HEIGHT, skin.selected);
+        batch.rect(x + (BAR_WIDTH - MARKER_WIDTH) * self.position(), y - MARGIN / 2.0, MARKER_WIDTH, BAR_HEIGHT + MARGIN, skin.highlight);
+
+        let scores: Vec<String> = self.scores.iter().map(i32::to_string).collect();
+        let text = format!("Go {} of {}   {}", (self.scores.len() + 1).min(self.tries), self.tries, scores.join("  "));
+        batch.text(x, y + BAR_HEIGHT + MARGIN * 2.0, scale, &text, skin.text);
+    }
+}
@@ -0,0 +1,303 @@
+// Mixes sound into buses, one for each kind of sound, so each kind has its own volume and the
+// field's effects are applied to the mix rather than to every sound that plays. Sound is
+// interleaved stereo, left then right.
+
+use std::{f32::consts::TAU, fmt, str::FromStr};
+
+// What the game mixes at, in samples a second for each channel.
+pub const SAMPLE_RATE: u32 = 48000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Voice,
+    // Background loops, like wind or a crowd.
+    Ambient
+}
+
+impl Bus {
+    pub const ALL: [Bus; 4] = [Bus::Music, Bus::Sfx, Bus::Voice, Bus::Ambient];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bus::Music => "music",
+            Bus::Sfx => "sfx",
+            Bus::Voice => "voice",
+            Bus::Ambient => "ambient"
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Bus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Bus::ALL.into_iter()
+            .find(|bus| bus.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown bus \"{}\", expected music, sfx, voice or ambient", s.trim()))
+    }
+}
+
+// How loud each bus is, from 0 to 1. A player setting.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BusVolumes([f32; 4]);
+
+impl Default for BusVolumes {
+    fn default() -> Self {
+        Self([1.0; 4])
+    }
+}
+
+impl BusVolumes {
+    pub fn get(&self, bus: Bus) -> f32 {
+        self.0[bus.index()]
+    }
+
+    pub fn set(&mut self, bus: Bus, volume: f32) {
+        self.0[bus.index()] = volume.clamp(0.0, 1.0);
+    }
+}
+
+// How a field changes the way things sound, like muffled underwater or echoing in a cave.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FieldAudio {
+    // Cuts out everything above this many Hz, for being underwater or hearing things through
+    // a wall.
+    pub low_pass: Option<f32>,
+    // How much of each bus is sent to the reverb, from 0 to 1.
+    pub reverb_send: f32,
+    // How many seconds the reverb takes to die away. Bigger rooms take longer.
+    pub reverb_decay: f32,
+    // Whether the music goes through the field's effects too. It usually doesn't, since it
+    // isn't coming from anywhere in the field.
+    pub affects_music: bool
+}
+
+impl Default for FieldAudio {
+    fn default() -> Self {
+        Self {
+            low_pass: None,
+            reverb_send: 0.0,
+            reverb_decay: 1.5,
+            affects_music: false
+        }
+    }
+}
+
+impl FieldAudio {
+    pub fn affects(&self, bus: Bus) -> bool {
+        bus != Bus::Music || self.affects_music
+    }
+}
+
+// A one pole low pass filter for each channel.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct LowPass {
+    last: [f32; 2]
+}
+
+impl LowPass {
+    fn apply(&mut self, samples: &mut [f32], cutoff: f32, sample_rate: u32) {
+        let amount = 1.0 - (-TAU * cutoff / sample_rate as f32).exp();
+        for frame in samples.chunks_exact_mut(2) {
+            for (sample, last) in frame.iter_mut().zip(&mut self.last) {
+                *last += amount * (*sample - *last);
+                *sample = *last;
+            }
+        }
+    }
+}
+
+// A delay line that feeds back into itself.
+#[derive(Clone)]
+struct Delay {
+    buffer: Vec<f32>,
+    position: usize,
+    feedback: f32
+}
+
+impl Delay {
+    fn new(length: usize, feedback: f32) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+            feedback
+        }
+    }
+
+    fn comb(&mut self, input: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.buffer[self.position] = input + output * self.feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+
+    fn all_pass(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.position];
+        let output = delayed - input * self.feedback;
+        self.buffer[self.position] = input + delayed * self.feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+// Comb filters in parallel then all pass filters in a row, for each channel. The right
+// channel's delays are a little longer so the echoes spread out.
+const COMB_DELAYS: [f32; 4] = [0.0297, 0.0371, 0.0411, 0.0437];
+const ALL_PASS_DELAYS: [f32; 2] = [0.005, 0.0017];
+const STEREO_SPREAD: f32 = 0.0005;
+
+#[derive(Clone)]
+struct Reverb {
+    decay: f32,
+    combs: [Vec<Delay>; 2],
+    all_passes: [Vec<Delay>; 2]
+}
+
+impl Reverb {
+    fn new(decay: f32, sample_rate: u32) -> Self {
+        let samples = |seconds: f32| (seconds * sample_rate as f32) as usize;
+        let channel = |spread: f32| {
+            // Each echo's quieter by however much gets it to a thousandth after `decay`.
+            let combs = COMB_DELAYS.iter().map(|delay| Delay::new(samples(delay + spread), 0.001f32.powf((delay + spread) / decay.max(0.01)))).collect();
+            let all_passes = ALL_PASS_DELAYS.iter().map(|delay| Delay::new(samples(delay + spread), 0.7)).collect();
+            (combs, all_passes)
+        };
+        let (left_combs, left_all_passes) = channel(0.0);
+        let (right_combs, right_all_passes) = channel(STEREO_SPREAD);
+        Self {
+            decay,
+            combs: [left_combs, right_combs],
+            all_passes: [left_all_passes, right_all_passes]
+        }
+    }
+
+    fn apply(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(2) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let input = *sample;
+                let mut output = self.combs[channel].iter_mut().map(|comb| comb.comb(input)).sum::<f32>() / COMB_DELAYS.len() as f32;
+                for all_pass in &mut self.all_passes[channel] {
+                    output = all_pass.all_pass(output);
+                }
+                *sample = output;
+            }
+        }
+    }
+}
+
+// Resource that mixes the buses together. Whatever plays sound hands it each bus's sounds
+// added together and gets back what to play.
+#[derive(Clone)]
+pub struct Mixer {
+    sample_rate: u32,
+    volumes: BusVolumes,
+    field: FieldAudio,
+    low_passes: [LowPass; 4],
+    reverb: Reverb,
+    bus: Vec<f32>,
+    sends: Vec<f32>
+}
+
+// Leaves out the reverb's buffers, which are thousands of samples long.
+impl fmt::Debug for Mixer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mixer")
+            .field("sample_rate", &self.sample_rate)
+            .field("volumes", &self.volumes)
+            .field("field", &self.field)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32, volumes: BusVolumes) -> Self {
+        let field = FieldAudio::default();
+        Self {
+            sample_rate,
+            volumes,
+            field,
+            low_passes: [LowPass::default(); 4],
+            reverb: Reverb::new(field.reverb_decay, sample_rate),
+            bus: Vec::new(),
+            sends: Vec::new()
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn volumes(&self) -> BusVolumes {
+        self.volumes
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        self.volumes.set(bus, volume);
+    }
+
+    pub fn field(&self) -> FieldAudio {
+        self.field
+    }
+
+    // Switch to a field's effects. The reverb starts again if the room's a different size.
+    pub fn set_field(&mut self, field: FieldAudio) {
+        if field.reverb_decay != self.reverb.decay {
+            self.reverb = Reverb::new(field.reverb_decay, self.sample_rate);
+        }
+        self.field = field;
+    }
+
+    // Mix each bus's sound into `output`, which is overwritten. Buses that aren't given are
+    // silent, and each input should be as long as the output.
+    pub fn mix(&mut self, inputs: &[(Bus, &[f32])], output: &mut [f32]) {
+        output.fill(0.0);
+        self.sends.clear();
+        self.sends.resize(output.len(), 0.0);
+
+        for bus in Bus::ALL {
+            self.bus.clear();
+            self.bus.resize(output.len(), 0.0);
+            let mut silent = true;
+            for (_, input) in inputs.iter().filter(|(input_bus, _)| *input_bus == bus) {
+                for (mixed, sample) in self.bus.iter_mut().zip(input.iter()) {
+                    *mixed += sample;
+                }
+                silent = false;
+            }
+            // The filter still has to settle even if the bus goes quiet.
+            if silent && self.low_passes[bus.index()].last == [0.0, 0.0] {
+                continue;
+            }
+
+            let volume = self.volumes.get(bus);
+            let affected = self.field.affects(bus);
+            if let (Some(cutoff), true) = (self.field.low_pass, affected) {
+                self.low_passes[bus.index()].apply(&mut self.bus, cutoff, self.sample_rate);
+            } else {
+                self.low_passes[bus.index()] = LowPass::default();
+            }
+            let send = if affected { self.field.reverb_send } else { 0.0 };
+            for ((out, send_out), sample) in output.iter_mut().zip(self.sends.iter_mut()).zip(&self.bus) {
+                *out += sample * volume;
+                *send_out += sample * volume * send;
+            }
+        }
+
+        self.reverb.apply(&mut self.sends);
+        for (out, reverb) in output.iter_mut().zip(&self.sends) {
+            *out += reverb;
+        }
+    }
+}
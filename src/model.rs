@@ -0,0 +1,1450 @@
+use std::{collections::HashMap, num::NonZeroU64, ops::Range, time::Duration};
+
+use cgmath::{Matrix, Matrix3, Matrix4, Point3, SquareMatrix, InnerSpace, Vector3, Vector4, EuclideanSpace};
+use wgpu::{Device, Queue, Texture, TextureView, Sampler, BindGroup, Buffer, TextureFormat, util::{DeviceExt, StagingBelt}};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::camera::Camera;
+use crate::field::FieldDescriptor;
+use crate::hit_reaction;
+use crate::logging::targets;
+use crate::pipeline_cache::{PipelineCache, PipelineId, BindGroupLayoutId, PipelineKey, texture_entry, sampler_entry, uniform_entry};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::spring_bone::SpringBoneData;
+use crate::transform::Transform;
+use crate::world::World;
+
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+// How big each of the staging belt's buffers is. Enough for a thousand or so instances.
+const STAGING_CHUNK_SIZE: wgpu::BufferAddress = 64 * 1024;
+
+// Where the light comes from. Fields don't have their own lighting yet.
+const LIGHT_DIRECTION: [f32; 4] = [-0.4, -1.0, -0.6, 0.0];
+
+// When to switch to each lower detail level, by default. See LodPolicy::ScreenSize.
+const DEFAULT_LOD_SCREEN_SIZES: &[f32] = &[0.25, 0.1, 0.04, 0.015];
+
+// Most morph targets a model can have. Every instance carries a weight for each of them.
+pub const MAX_MORPH_TARGETS: usize = 8;
+
+// Width of the textures morph target displacements are kept in. They're as tall as they
+// need to be.
+const MORPH_TEXTURE_WIDTH: usize = 1024;
+
+// Longest step morph weights take in one frame, so a long pause, like the window sleeping in
+// low power mode, doesn't skip a whole blink.
+const MAX_MORPH_STEP: Duration = Duration::from_millis(100);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    // For normal maps. w is 1 or -1, for which way the bitangent points.
+    pub tangent: [f32; 4]
+}
+
+impl ModelVertex {
+    // Locations 3 to 7 are the instance data.
+    const ATTRIBS: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2, 8 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
+
+// Per model transform, opacity and morph weights, passed as instance data.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ModelInstanceData {
+    model: [[f32; 4]; 4],
+    opacity: f32,
+    morph_weights: [f32; MAX_MORPH_TARGETS]
+}
+
+impl ModelInstanceData {
+    const ATTRIBS: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        3 => Float32x4, 4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Float32, 9 => Float32x4, 10 => Float32x4
+    ];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelInstanceData>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniforms {
+    view_projection: [[f32; 4]; 4],
+    light_direction: [f32; 4]
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniforms {
+    base_color: [f32; 4],
+    // Only rgb is used.
+    emissive: [f32; 4],
+    // Fragments less opaque than this are discarded. 0 keeps everything.
+    alpha_cutoff: f32,
+    normal_scale: f32,
+    _padding: [f32; 2]
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphUniforms {
+    vertex_count: u32,
+    target_count: u32,
+    _padding: [u32; 2]
+}
+
+// How far each of a mesh's vertices moves when a morph target's weight is 1.
+// Either can be empty if the target doesn't move them.
+#[derive(Clone, Debug, Default)]
+pub struct MorphTargetData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>
+}
+
+// A mesh with a single material, ready to upload.
+#[derive(Clone, Debug)]
+pub struct MeshData {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u32>,
+    pub material: usize,
+    // In the same order as the model's morph target names. Meshes that a target doesn't
+    // touch have an empty one, or stop short of it.
+    pub morph_targets: Vec<MorphTargetData>
+}
+
+// How a material's alpha is used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    // Alpha is ignored.
+    Opaque,
+    // Cut out where alpha is below the cutoff and solid everywhere else, for leaves, fences
+    // and hair cards. Drawn with the opaque meshes, so they don't need sorting.
+    Mask(f32),
+    // Blended over whatever is behind it, for glass, ghosts and the like. Drawn after
+    // everything opaque, furthest first.
+    Blend
+}
+
+#[derive(Clone, Debug)]
+pub struct MaterialData {
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<image::RgbaImage>,
+    pub alpha_mode: AlphaMode,
+
+    // Tangent space normals. Needs the meshes to have tangents.
+    pub normal_texture: Option<image::RgbaImage>,
+    pub normal_scale: f32,
+
+    // Light given off regardless of the lighting, multiplied by the texture if there is one.
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<image::RgbaImage>
+}
+
+impl Default for MaterialData {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            normal_texture: None,
+            normal_scale: 1.0,
+            emissive: [0.0, 0.0, 0.0],
+            emissive_texture: None
+        }
+    }
+}
+
+// A model loaded from a glTF file, before it's uploaded to the GPU.
+//
+// Meshes named with a _LOD<n> suffix, like "barrel_LOD1", make up the model's levels of
+// detail, with 0 being the most detailed. Meshes without a suffix are drawn at every level.
+#[derive(Clone, Debug)]
+pub struct ModelData {
+    pub lods: Vec<Vec<MeshData>>,
+    pub materials: Vec<MaterialData>,
+    // Names of the morph targets, shared by every mesh. See MorphWeights.
+    pub morph_targets: Vec<String>,
+    // Every named node, like bones and empties placed to hold a sword or a torch, and where it
+    // is relative to the model's origin. See ModelSockets.
+    pub sockets: Vec<(String, Matrix4<f32>)>,
+    // Sockets that swing about on their own, parents before the bones hanging off them.
+    pub spring_bones: Vec<SpringBoneData>,
+    // Radius of a sphere around the model's origin that contains all of it, however it's morphed.
+    pub radius: f32
+}
+
+impl ModelData {
+    // Build a model from meshes, working out its bounds.
+    pub fn new(lods: Vec<Vec<MeshData>>, materials: Vec<MaterialData>, morph_targets: Vec<String>) -> Self {
+        let radius = lods.iter().flatten()
+            .flat_map(|mesh| mesh.vertices.iter().enumerate().map(move |(i, vertex)| {
+                // As far as the vertex could go with every target fully on.
+                let displacement: f32 = mesh.morph_targets.iter()
+                    .filter_map(|target| target.positions.get(i))
+                    .map(|position| Vector3::from(*position).magnitude())
+                    .sum();
+                Vector3::from(vertex.position).magnitude() + displacement
+            }))
+            .fold(0.0, f32::max);
+        Self {
+            lods,
+            materials,
+            morph_targets,
+            sockets: Vec::new(),
+            spring_bones: Vec::new(),
+            radius
+        }
+    }
+
+    // Load a .gltf or .glb file. Buffers and images can be embedded, in the binary chunk or
+    // in data URIs, or in separate files next to the model.
+    pub async fn load(assets: &AssetServer, path: &str) -> Result<Self, AssetError> {
+        let bytes = assets.load_bytes(path).await?;
+        let decode_error = |e: &dyn std::fmt::Display| AssetError::Decode(path.to_string(), e.to_string());
+        let gltf = gltf::Gltf::from_slice(&bytes).map_err(|e| decode_error(&e))?;
+
+        let mut buffers = Vec::new();
+        for buffer in gltf.buffers() {
+            buffers.push(match buffer.source() {
+                gltf::buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| decode_error(&"Missing binary chunk"))?,
+                gltf::buffer::Source::Uri(uri) => load_uri(assets, path, uri).await?
+            });
+        }
+
+        let mut materials = Vec::new();
+        for material in gltf.materials() {
+            let pbr = material.pbr_metallic_roughness();
+            let base_color_texture = match pbr.base_color_texture() {
+                Some(info) => Some(load_texture(assets, path, &buffers, info.texture()).await?),
+                None => None
+            };
+            let (normal_texture, normal_scale) = match material.normal_texture() {
+                Some(info) => (Some(load_texture(assets, path, &buffers, info.texture()).await?), info.scale()),
+                None => (None, 1.0)
+            };
+            let emissive_texture = match material.emissive_texture() {
+                Some(info) => Some(load_texture(assets, path, &buffers, info.texture()).await?),
+                None => None
+            };
+            let alpha_mode = match material.alpha_mode() {
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque
+            };
+            materials.push(MaterialData {
+                base_color: pbr.base_color_factor(),
+                base_color_texture,
+                alpha_mode,
+                normal_texture,
+                normal_scale,
+                emissive: material.emissive_factor(),
+                emissive_texture
+            });
+        }
+        // For primitives that don't have a material.
+        let default_material = materials.len();
+        materials.push(MaterialData::default());
+
+        let scene = gltf.default_scene()
+            .or_else(|| gltf.scenes().next())
+            .ok_or_else(|| decode_error(&"No scenes"))?;
+
+        // Meshes for each detail level, and ones shared by all of them.
+        let mut levels: Vec<Vec<MeshData>> = Vec::new();
+        let mut shared = Vec::new();
+        let mut morph_targets: Vec<String> = Vec::new();
+        let mut sockets = Vec::new();
+        let mut spring_bones = Vec::new();
+
+        // Nodes are visited parents first, along with the spring bone they hang from if there is one.
+        let mut nodes: Vec<(gltf::Node, Matrix4<f32>, Option<usize>)> = scene.nodes().map(|node| (node, Matrix4::identity(), None)).collect();
+        while let Some((node, parent_transform, parent_spring_bone)) = nodes.pop() {
+            let transform = parent_transform * Matrix4::from(node.transform().matrix());
+            let mut spring_bone = None;
+            if let Some(name) = node.name() {
+                sockets.push((name.to_string(), transform));
+                if let Some(mut bone) = spring_bone_data(node.extras()) {
+                    bone.socket = name.to_string();
+                    bone.parent = parent_spring_bone;
+                    bone.parent_position = parent_transform.w.truncate();
+                    spring_bones.push(bone);
+                    spring_bone = Some(spring_bones.len() - 1);
+                }
+            }
+            nodes.extend(node.children().map(|child| (child, transform, spring_bone)));
+
+            let mesh = match node.mesh() {
+                Some(mesh) => mesh,
+                None => continue
+            };
+            let normal_transform = normal_matrix(&transform);
+            let level = mesh.name().and_then(lod_level);
+            let target_names = morph_target_names(&mesh);
+
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    tracing::warn!(target: targets::ASSETS, "{}: skipping a primitive that isn't made of triangles", path);
+                    continue;
+                }
+
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+                let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                    Some(positions) => positions.collect(),
+                    None => continue
+                };
+                let mut normals = reader.read_normals();
+                let mut uvs = reader.read_tex_coords(0).map(|uvs| uvs.into_f32());
+                let mut tangents = reader.read_tangents();
+                let has_tangents = tangents.is_some();
+
+                let mut vertices: Vec<ModelVertex> = positions.iter().map(|position| {
+                    let position = transform * Vector4::new(position[0], position[1], position[2], 1.0);
+                    let normal = normals.as_mut().and_then(Iterator::next).unwrap_or([0.0, 1.0, 0.0]);
+                    let normal = (normal_transform * Vector3::from(normal)).normalize();
+                    let tangent = tangents.as_mut().and_then(Iterator::next).unwrap_or([1.0, 0.0, 0.0, 1.0]);
+                    let tangent_direction = (transform * Vector4::new(tangent[0], tangent[1], tangent[2], 0.0)).truncate().normalize();
+                    ModelVertex {
+                        position: position.truncate().into(),
+                        normal: normal.into(),
+                        uv: uvs.as_mut().and_then(Iterator::next).unwrap_or([0.0, 0.0]),
+                        tangent: tangent_direction.extend(tangent[3]).into()
+                    }
+                }).collect();
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..positions.len() as u32).collect()
+                };
+
+                // Normal maps need tangents. Work some out from the UVs if the file doesn't have them.
+                if !has_tangents {
+                    generate_tangents(&mut vertices, &indices);
+                }
+
+                // Targets with the same name in different meshes, like "blink" on the face and
+                // the eyelashes, are moved by the same weight.
+                let mut mesh_targets = Vec::new();
+                for (i, (target_positions, target_normals, _)) in reader.read_morph_targets().enumerate() {
+                    let name = target_names.get(i).cloned().unwrap_or_else(|| i.to_string());
+                    let index = match morph_targets.iter().position(|target| *target == name) {
+                        Some(index) => index,
+                        None if morph_targets.len() < MAX_MORPH_TARGETS => {
+                            morph_targets.push(name);
+                            morph_targets.len() - 1
+                        },
+                        None => {
+                            tracing::warn!(target: targets::ASSETS, "{}: skipping morph target {}, models can only have {}", path, name, MAX_MORPH_TARGETS);
+                            continue;
+                        }
+                    };
+                    if mesh_targets.len() <= index {
+                        mesh_targets.resize_with(index + 1, MorphTargetData::default);
+                    }
+                    mesh_targets[index] = MorphTargetData {
+                        positions: target_positions.into_iter().flatten()
+                            .map(|offset| (transform * Vector4::new(offset[0], offset[1], offset[2], 0.0)).truncate().into())
+                            .collect(),
+                        normals: target_normals.into_iter().flatten()
+                            .map(|offset| (normal_transform * Vector3::from(offset)).into())
+                            .collect()
+                    };
+                }
+
+                let mesh_data = MeshData {
+                    vertices,
+                    indices,
+                    material: primitive.material().index().unwrap_or(default_material),
+                    morph_targets: mesh_targets
+                };
+                match level {
+                    Some(level) => {
+                        if levels.len() <= level {
+                            levels.resize_with(level + 1, Vec::new);
+                        }
+                        levels[level].push(mesh_data);
+                    },
+                    None => shared.push(mesh_data)
+                }
+            }
+        }
+
+        if levels.is_empty() {
+            levels.push(Vec::new());
+        }
+        for level in &mut levels {
+            level.extend(shared.iter().cloned());
+        }
+
+        let mut model = Self::new(levels, materials, morph_targets);
+        model.sockets = sockets;
+        model.spring_bones = spring_bones;
+        Ok(model)
+    }
+}
+
+// One of the custom properties exporters put in a glTF object's extras.
+fn extra(extras: &gltf::json::Extras, key: &str) -> Option<gltf::json::Value> {
+    let extras = extras.as_ref()?;
+    match gltf::json::deserialize::from_str::<gltf::json::Value>(extras.get()) {
+        Ok(gltf::json::Value::Object(mut extras)) => extras.remove(key),
+        _ => None
+    }
+}
+
+// glTF doesn't name morph targets itself, but most exporters put the names in the mesh's
+// extras as "targetNames".
+fn morph_target_names(mesh: &gltf::Mesh) -> Vec<String> {
+    match extra(mesh.extras(), "targetNames") {
+        Some(gltf::json::Value::Array(names)) => names.iter()
+            .map(|name| name.as_str().unwrap_or_default().to_string())
+            .collect(),
+        _ => Vec::new()
+    }
+}
+
+// Nodes tagged with "spring_bone" in their extras, either true or an object with any of
+// "stiffness", "drag" and "gravity".
+fn spring_bone_data(extras: &gltf::json::Extras) -> Option<SpringBoneData> {
+    let mut bone = SpringBoneData::default();
+    match extra(extras, "spring_bone")? {
+        gltf::json::Value::Bool(true) => {},
+        gltf::json::Value::Object(settings) => {
+            let setting = |key: &str| settings.get(key).and_then(gltf::json::Value::as_f64).map(|value| value as f32);
+            bone.stiffness = setting("stiffness").unwrap_or(bone.stiffness);
+            bone.drag = setting("drag").unwrap_or(bone.drag);
+            bone.gravity = setting("gravity").unwrap_or(bone.gravity);
+        },
+        _ => return None
+    }
+    Some(bone)
+}
+
+// Where a glTF texture's image is, either in one of the buffers or in a file, decoded.
+async fn load_texture(assets: &AssetServer, model_path: &str, buffers: &[Vec<u8>], texture: gltf::Texture<'_>) -> Result<image::RgbaImage, AssetError> {
+    let decode_error = |e: &dyn std::fmt::Display| AssetError::Decode(model_path.to_string(), e.to_string());
+    let image_bytes = match texture.source().source() {
+        gltf::image::Source::View { view, .. } => {
+            let buffer = &buffers[view.buffer().index()];
+            buffer.get(view.offset()..view.offset() + view.length())
+                .ok_or_else(|| decode_error(&"Image view is outside its buffer"))?
+                .to_vec()
+        },
+        gltf::image::Source::Uri { uri, .. } => load_uri(assets, model_path, uri).await?
+    };
+    let image = image::load_from_memory(&image_bytes).map_err(|e| decode_error(&e))?;
+    Ok(image.to_rgba8())
+}
+
+// Per vertex tangents from the direction the UVs run across each triangle, averaged over the
+// triangles sharing the vertex.
+fn generate_tangents(vertices: &mut [ModelVertex], indices: &[u32]) {
+    let mut tangents = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+    let mut bitangents = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() {
+            continue;
+        }
+
+        let edge_1 = Vector3::from(vertices[b].position) - Vector3::from(vertices[a].position);
+        let edge_2 = Vector3::from(vertices[c].position) - Vector3::from(vertices[a].position);
+        let uv_1 = [vertices[b].uv[0] - vertices[a].uv[0], vertices[b].uv[1] - vertices[a].uv[1]];
+        let uv_2 = [vertices[c].uv[0] - vertices[a].uv[0], vertices[c].uv[1] - vertices[a].uv[1]];
+        let determinant = uv_1[0] * uv_2[1] - uv_2[0] * uv_1[1];
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (edge_1 * uv_2[1] - edge_2 * uv_1[1]) / determinant;
+        let bitangent = (edge_2 * uv_1[0] - edge_1 * uv_2[0]) / determinant;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+
+    for (vertex, (tangent, bitangent)) in vertices.iter_mut().zip(tangents.into_iter().zip(bitangents)) {
+        // Make the tangent perpendicular to the normal.
+        let normal = Vector3::from(vertex.normal);
+        let tangent = tangent - normal * normal.dot(tangent);
+        if tangent.magnitude2() < f32::EPSILON {
+            continue;
+        }
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = tangent.normalize().extend(handedness).into();
+    }
+}
+
+// "barrel_LOD2" is level 2.
+fn lod_level(name: &str) -> Option<usize> {
+    let (_, level) = name.rsplit_once("_LOD")?;
+    level.parse().ok()
+}
+
+// Transforms normals the same way a matrix transforms positions, even when it's scaled
+// unevenly.
+fn normal_matrix(transform: &Matrix4<f32>) -> Matrix3<f32> {
+    let linear = Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate());
+    linear.invert().map(|inverse| inverse.transpose()).unwrap_or(linear)
+}
+
+// Buffers and images referred to by a glTF file. Either embedded as base64, or a file path
+// relative to the model.
+async fn load_uri(assets: &AssetServer, model_path: &str, uri: &str) -> Result<Vec<u8>, AssetError> {
+    use base64::Engine;
+
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,")
+            .ok_or_else(|| AssetError::Decode(model_path.to_string(), "Only base64 data URIs are supported".to_string()))?;
+        return base64::engine::general_purpose::STANDARD.decode(encoded)
+            .map_err(|e| AssetError::Decode(model_path.to_string(), e.to_string()));
+    }
+
+    let path = match model_path.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, uri),
+        None => uri.to_string()
+    };
+    assets.load_bytes(&path).await
+}
+
+// How a model picks which level of detail to draw.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LodPolicy {
+    // Drop to the next level each time the model's height on screen, as a fraction of the
+    // screen's height, goes below the next size.
+    ScreenSize(Vec<f32>),
+    // Drop to the next level each time the model gets further from the camera than the next
+    // distance.
+    Distance(Vec<f32>)
+}
+
+impl Default for LodPolicy {
+    fn default() -> Self {
+        LodPolicy::ScreenSize(DEFAULT_LOD_SCREEN_SIZES.to_vec())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModelId(u32);
+
+// Component for entities that should be drawn with a model, at their Transform.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ModelInstance(pub ModelId);
+
+// Component for fading a model out, from 1 for solid to 0 for invisible. Models that aren't
+// solid are drawn with the transparent ones.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ModelOpacity(pub f32);
+
+// A weight blending towards another over time.
+#[derive(Clone, Debug, PartialEq)]
+struct MorphTransition {
+    target: usize,
+    from: f32,
+    to: f32,
+    duration: Duration,
+    elapsed: Duration
+}
+
+// Component for bending a model into its morph targets, like closing its eyes or opening its
+// mouth. Targets are picked by name, so scripts and dialogue don't need to know how the model
+// was put together. Weights are usually from 0 to 1, but can go past either end.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphWeights {
+    names: Vec<String>,
+    weights: [f32; MAX_MORPH_TARGETS],
+    transitions: Vec<MorphTransition>
+}
+
+impl MorphWeights {
+    // Every one of the model's targets, all at 0.
+    pub fn new(model: &ModelData) -> Self {
+        Self {
+            names: model.morph_targets.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.index(name).map(|index| self.weights[index])
+    }
+
+    // Jump straight to a weight, stopping any transition. Returns false if there's no such target.
+    pub fn set(&mut self, name: &str, weight: f32) -> bool {
+        self.animate(name, weight, Duration::ZERO)
+    }
+
+    // Blend from the current weight to a new one, e.g. over a tenth of a second for a blink.
+    // Returns false if there's no such target.
+    pub fn animate(&mut self, name: &str, weight: f32, duration: Duration) -> bool {
+        let index = match self.index(name) {
+            Some(index) => index,
+            None => return false
+        };
+        self.transitions.retain(|transition| transition.target != index);
+        if duration.is_zero() {
+            self.weights[index] = weight;
+        } else {
+            self.transitions.push(MorphTransition {
+                target: index,
+                from: self.weights[index],
+                to: weight,
+                duration,
+                elapsed: Duration::ZERO
+            });
+        }
+        true
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.transitions.is_empty()
+    }
+
+    // Move transitions on, dropping the ones that have finished.
+    pub fn update(&mut self, delta: Duration) {
+        let weights = &mut self.weights;
+        self.transitions.retain_mut(|transition| {
+            transition.elapsed = (transition.elapsed + delta).min(transition.duration);
+            let t = transition.elapsed.as_secs_f32() / transition.duration.as_secs_f32();
+            weights[transition.target] = transition.from + (transition.to - transition.from) * t;
+            transition.elapsed < transition.duration
+        });
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|target| target == name)
+    }
+}
+
+// Move every entity's morph weights on. Returns true while any are still changing, so there's
+// something new to draw.
+pub fn update_morph_weights(world: &mut World, delta: Duration) -> bool {
+    let delta = delta.min(MAX_MORPH_STEP);
+    let mut animating = false;
+    for (_, weights) in world.query_mut::<MorphWeights>() {
+        weights.update(delta);
+        animating |= weights.is_animating();
+    }
+    animating
+}
+
+// A model to draw, and how.
+struct ModelDraw {
+    model: ModelId,
+    transform: Matrix4<f32>,
+    opacity: f32,
+    morph_weights: [f32; MAX_MORPH_TARGETS]
+}
+
+// The models to draw this frame and the camera to draw them with.
+pub struct ModelBatch {
+    camera: Camera,
+    max_draw_distance: Option<f32>,
+    draws: Vec<ModelDraw>
+}
+
+impl ModelBatch {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            max_draw_distance: None,
+            draws: Vec::new()
+        }
+    }
+
+    // Everything in the world with a model, seen through the world's camera.
+    pub fn from_world(world: &World) -> Self {
+        let mut batch = Self::new(world.resource::<Camera>().copied().unwrap_or_default());
+        batch.max_draw_distance = world.resource::<FieldDescriptor>().and_then(|field| field.max_draw_distance);
+        for (entity, instance) in world.query::<ModelInstance>() {
+            if let Some(transform) = world.get::<Transform>(entity) {
+                let opacity = world.get::<ModelOpacity>(entity).map_or(1.0, |opacity| opacity.0);
+                let morph_weights = world.get::<MorphWeights>(entity).map_or([0.0; MAX_MORPH_TARGETS], |weights| weights.weights);
+                batch.push(instance.0, hit_reaction::posed_matrix(world, entity, transform), opacity, morph_weights);
+            }
+        }
+        batch
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_max_draw_distance(&mut self, distance: Option<f32>) {
+        self.max_draw_distance = distance;
+    }
+
+    pub fn add(&mut self, model: ModelId, transform: Matrix4<f32>) {
+        self.add_with_opacity(model, transform, 1.0);
+    }
+
+    // Models that are completely faded out aren't drawn at all.
+    pub fn add_with_opacity(&mut self, model: ModelId, transform: Matrix4<f32>, opacity: f32) {
+        self.push(model, transform, opacity, [0.0; MAX_MORPH_TARGETS]);
+    }
+
+    pub fn add_morphed(&mut self, model: ModelId, transform: Matrix4<f32>, weights: &MorphWeights) {
+        self.push(model, transform, 1.0, weights.weights);
+    }
+
+    fn push(&mut self, model: ModelId, transform: Matrix4<f32>, opacity: f32, morph_weights: [f32; MAX_MORPH_TARGETS]) {
+        if opacity > 0.0 {
+            self.draws.push(ModelDraw {
+                model,
+                transform,
+                opacity: opacity.min(1.0),
+                morph_weights
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+}
+
+impl Default for ModelBatch {
+    fn default() -> Self {
+        Self::new(Camera::default())
+    }
+}
+
+// What happened while drawing a batch.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ModelRenderStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+    pub triangles: u32,
+    // Models that were too far away to draw.
+    pub culled: u32
+}
+
+struct GpuMesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    material: usize,
+    // Drawn in the transparent pass.
+    blend: bool,
+    // None if the mesh doesn't morph.
+    morph_targets: Option<GpuMorphTargets>
+}
+
+struct GpuMorphTargets {
+    _texture: Texture,
+    texture_bytes: u64,
+    _uniform_buffer: Buffer,
+    bind_group: BindGroup
+}
+
+struct GpuMaterial {
+    _textures: Vec<Texture>,
+    _uniform_buffer: Buffer,
+    bind_group: BindGroup
+}
+
+struct GpuModel {
+    lods: Vec<Vec<GpuMesh>>,
+    materials: Vec<GpuMaterial>,
+    radius: f32,
+    lod_policy: LodPolicy,
+    texture_bytes: u64
+}
+
+// Draws models over the field background, with a depth buffer of its own.
+// Opaque meshes are drawn first, then transparent ones are blended over them from back to front.
+pub struct ModelRenderer {
+    render_pipeline: PipelineId,
+    transparent_pipeline: PipelineId,
+    material_bind_group_layout: BindGroupLayoutId,
+    morph_bind_group_layout: BindGroupLayoutId,
+
+    camera_buffer: Buffer,
+    camera_bind_group: BindGroup,
+
+    _depth_texture: Texture,
+    depth_view: TextureView,
+
+    sampler: Sampler,
+    white_texture: Texture,
+    // Stands in for materials without a normal map. Points straight out of the surface.
+    flat_normal_texture: Texture,
+    // Bound for meshes that don't morph.
+    no_morph_targets: GpuMorphTargets,
+
+    instance_buffer: Buffer,
+    instance_capacity: usize,
+    staging_belt: StagingBelt,
+
+    // Kept between frames so drawing doesn't allocate once they're big enough.
+    visible: Vec<(ModelId, usize, ModelInstanceData)>,
+    instances: Vec<ModelInstanceData>,
+    draws: Vec<(ModelId, usize, Range<u32>)>,
+    // Transparent meshes with their distance along the view, and the draws for them once sorted.
+    transparent: Vec<(f32, ModelId, usize, usize, ModelInstanceData)>,
+    transparent_draws: Vec<(ModelId, usize, usize, Range<u32>)>,
+
+    models: HashMap<ModelId, GpuModel>,
+    next_model: u32,
+
+    // Above 1 keeps detailed models for longer, below 1 switches to simpler ones sooner.
+    lod_bias: f32
+}
+
+impl ModelRenderer {
+    pub fn new(device: &Device, queue: &Queue, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("model.wgsl"));
+
+        let camera_bind_group_layout = pipelines.create_bind_group_layout(device, "Model Camera Bind Group Layout", &[
+            uniform_entry(0, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        let material_bind_group_layout = pipelines.create_bind_group_layout(device, "Model Material Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT),
+            uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+            texture_entry(3, wgpu::ShaderStages::FRAGMENT),
+            texture_entry(4, wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        // Morph target displacements are read in the vertex shader, a texel at a time, from a
+        // float texture. WebGL doesn't have storage buffers, so they can't go in one of those.
+        let morph_bind_group_layout = pipelines.create_bind_group_layout(device, "Model Morph Bind Group Layout", &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false }
+                },
+                count: None
+            },
+            uniform_entry(1, wgpu::ShaderStages::VERTEX)
+        ]);
+
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Model Camera Buffer"),
+            size: std::mem::size_of::<CameraUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Model Camera Bind Group"),
+            layout: pipelines.get_bind_group_layout(camera_bind_group_layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Model Depth Texture"),
+            size: wgpu::Extent3d {
+                width: SCREEN_WIDTH as u32,
+                height: SCREEN_HEIGHT as u32,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            // Post processing reads it for depth of field.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Model Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // Used by materials without a texture, so every material can be drawn the same way.
+        let white_texture = create_texture(device, queue, &image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])), wgpu::TextureFormat::Rgba8UnormSrgb);
+        let flat_normal_texture = create_texture(device, queue, &image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255])), wgpu::TextureFormat::Rgba8Unorm);
+        let no_morph_targets = create_morph_targets(device, queue, pipelines.get_bind_group_layout(morph_bind_group_layout), 0, &[]);
+
+        let mut pipeline_key = PipelineKey::new(
+            shader,
+            &[camera_bind_group_layout, material_bind_group_layout, morph_bind_group_layout],
+            vec![ModelVertex::desc().into(), ModelInstanceData::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        );
+        pipeline_key.depth_stencil = Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default()
+        });
+        // The transparent pass tests against the opaque meshes' depth but doesn't write any, so
+        // everything behind a transparent mesh still shows through it.
+        let mut transparent_key = pipeline_key.clone();
+        transparent_key.targets = vec![Some(wgpu::ColorTargetState {
+            format: output_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+        if let Some(depth_stencil) = &mut transparent_key.depth_stencil {
+            depth_stencil.depth_write_enabled = false;
+        }
+
+        let render_pipeline = pipelines.create_render_pipeline(device, "Model Render Pipeline", pipeline_key);
+        let transparent_pipeline = pipelines.create_render_pipeline(device, "Model Transparent Render Pipeline", transparent_key);
+
+        let instance_capacity = 64;
+        let instance_buffer = Self::create_instance_buffer(device, instance_capacity);
+
+        Self {
+            render_pipeline,
+            transparent_pipeline,
+            material_bind_group_layout,
+            morph_bind_group_layout,
+
+            camera_buffer,
+            camera_bind_group,
+
+            _depth_texture: depth_texture,
+            depth_view,
+
+            sampler,
+            white_texture,
+            flat_normal_texture,
+            no_morph_targets,
+
+            instance_buffer,
+            instance_capacity,
+            staging_belt: StagingBelt::new(STAGING_CHUNK_SIZE),
+
+            visible: Vec::new(),
+            instances: Vec::new(),
+            draws: Vec::new(),
+            transparent: Vec::new(),
+            transparent_draws: Vec::new(),
+
+            models: HashMap::new(),
+            next_model: 0,
+
+            lod_bias: 1.0
+        }
+    }
+
+    fn create_instance_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Model Instance Buffer"),
+            size: (capacity * std::mem::size_of::<ModelInstanceData>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        })
+    }
+
+    // Upload a model so it can be drawn with a ModelBatch.
+    pub fn create_model(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, data: &ModelData) -> ModelId {
+        let mut texture_bytes = 0;
+        let materials = data.materials.iter().map(|material| {
+            // Normal maps hold directions rather than colours, so they aren't sRGB.
+            let mut textures = Vec::new();
+            let mut upload = |image: &Option<image::RgbaImage>, format, fallback: &Texture| {
+                match image {
+                    Some(image) => {
+                        texture_bytes += image.width() as u64 * image.height() as u64 * 4;
+                        let texture = create_texture(device, queue, image, format);
+                        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        textures.push(texture);
+                        view
+                    },
+                    None => fallback.create_view(&wgpu::TextureViewDescriptor::default())
+                }
+            };
+            let base_color_view = upload(&material.base_color_texture, wgpu::TextureFormat::Rgba8UnormSrgb, &self.white_texture);
+            let normal_view = upload(&material.normal_texture, wgpu::TextureFormat::Rgba8Unorm, &self.flat_normal_texture);
+            let emissive_view = upload(&material.emissive_texture, wgpu::TextureFormat::Rgba8UnormSrgb, &self.white_texture);
+
+            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Model Material Buffer"),
+                contents: bytemuck::cast_slice(&[MaterialUniforms {
+                    base_color: material.base_color,
+                    emissive: [material.emissive[0], material.emissive[1], material.emissive[2], 0.0],
+                    alpha_cutoff: match material.alpha_mode {
+                        AlphaMode::Mask(cutoff) => cutoff,
+                        AlphaMode::Opaque | AlphaMode::Blend => 0.0
+                    },
+                    normal_scale: material.normal_scale,
+                    _padding: [0.0; 2]
+                }]),
+                usage: wgpu::BufferUsages::UNIFORM
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Model Material Bind Group"),
+                layout: pipelines.get_bind_group_layout(self.material_bind_group_layout),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&base_color_view)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&normal_view)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(&emissive_view)
+                    }
+                ]
+            });
+
+            GpuMaterial {
+                _textures: textures,
+                _uniform_buffer: uniform_buffer,
+                bind_group
+            }
+        }).collect();
+
+        let lods: Vec<Vec<GpuMesh>> = data.lods.iter().map(|meshes| meshes.iter()
+            .filter(|mesh| !mesh.indices.is_empty())
+            .map(|mesh| GpuMesh {
+                vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Model Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&mesh.vertices),
+                    usage: wgpu::BufferUsages::VERTEX
+                }),
+                index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Model Index Buffer"),
+                    contents: bytemuck::cast_slice(&mesh.indices),
+                    usage: wgpu::BufferUsages::INDEX
+                }),
+                index_count: mesh.indices.len() as u32,
+                material: mesh.material.min(data.materials.len().saturating_sub(1)),
+                blend: data.materials.get(mesh.material).is_some_and(|material| material.alpha_mode == AlphaMode::Blend),
+                morph_targets: if mesh.morph_targets.iter().any(|target| !target.positions.is_empty() || !target.normals.is_empty()) {
+                    Some(create_morph_targets(device, queue, pipelines.get_bind_group_layout(self.morph_bind_group_layout), mesh.vertices.len(), &mesh.morph_targets))
+                } else {
+                    None
+                }
+            })
+            .collect()
+        ).collect();
+        texture_bytes += lods.iter().flatten()
+            .filter_map(|mesh| mesh.morph_targets.as_ref())
+            .map(|morph_targets| morph_targets.texture_bytes)
+            .sum::<u64>();
+
+        let id = ModelId(self.next_model);
+        self.next_model += 1;
+        self.models.insert(id, GpuModel {
+            lods,
+            materials,
+            radius: data.radius,
+            lod_policy: LodPolicy::default(),
+            texture_bytes
+        });
+        id
+    }
+
+    pub fn remove_model(&mut self, id: ModelId) {
+        self.models.remove(&id);
+    }
+
+    pub fn set_lod_policy(&mut self, id: ModelId, policy: LodPolicy) {
+        if let Some(model) = self.models.get_mut(&id) {
+            model.lod_policy = policy;
+        }
+    }
+
+    pub fn lod_bias(&self) -> f32 {
+        self.lod_bias
+    }
+
+    pub fn set_lod_bias(&mut self, bias: f32) {
+        self.lod_bias = bias.max(0.01);
+    }
+
+    // Depth of the models drawn last frame, for drawing things in amongst them afterwards.
+    pub fn depth_view(&self) -> &TextureView {
+        &self.depth_view
+    }
+
+    // Bytes of texture memory owned by the model renderer.
+    pub fn texture_bytes(&self) -> u64 {
+        SCREEN_WIDTH as u64 * SCREEN_HEIGHT as u64 * 4
+            + self.models.values().map(|model| model.texture_bytes).sum::<u64>()
+    }
+
+    // Which level of detail to draw a model at, or None if it's too far away to draw at all.
+    fn select_lod(&self, model: &GpuModel, camera: &Camera, max_draw_distance: Option<f32>, transform: &Matrix4<f32>) -> Option<usize> {
+        let centre = Point3::from_vec(transform.w.truncate());
+        let scale = transform.x.truncate().magnitude()
+            .max(transform.y.truncate().magnitude())
+            .max(transform.z.truncate().magnitude());
+        let radius = model.radius * scale;
+        let distance = (centre - camera.eye).magnitude();
+
+        if let Some(max_distance) = max_draw_distance {
+            if distance - radius > max_distance {
+                return None;
+            }
+        }
+
+        let level = match &model.lod_policy {
+            LodPolicy::ScreenSize(sizes) => {
+                let screen_size = if distance <= radius {
+                    1.0
+                } else {
+                    radius / (distance * (camera.fovy.to_radians() / 2.0).tan())
+                };
+                sizes.iter().take_while(|size| screen_size * self.lod_bias < **size).count()
+            },
+            LodPolicy::Distance(distances) =>
+                distances.iter().take_while(|switch_distance| distance / self.lod_bias >= **switch_distance).count()
+        };
+        Some(level.min(model.lods.len().saturating_sub(1)))
+    }
+
+    // With nothing to draw the depth buffer still needs clearing, or the occluders and depth of
+    // field would see the last models that were drawn.
+    fn clear_depth(&self, device: &Device, queue: &Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Model Depth Clear Encoder")
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Model Depth Clear Render Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true
+                }),
+                stencil_ops: None
+            })
+        });
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Draw the batch over whatever is already in dest_view.
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, batch: &ModelBatch) -> ModelRenderStats {
+        self.render_from(device, queue, pipelines, dest_view, batch, &batch.camera, None)
+    }
+
+    // Draw the batch as another camera sees it, like a reflection's, only inside the scissor
+    // rect. The rect is x, y, width and height in screen pixels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_reflection(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, batch: &ModelBatch, camera: &Camera, scissor: [u32; 4]) -> ModelRenderStats {
+        self.render_from(device, queue, pipelines, dest_view, batch, camera, Some(scissor))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_from(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, batch: &ModelBatch, camera: &Camera, scissor: Option<[u32; 4]>) -> ModelRenderStats {
+        let mut stats = ModelRenderStats::default();
+        if batch.is_empty() {
+            self.clear_depth(device, queue);
+            return stats;
+        }
+
+        // Work out what to draw and at which level of detail, then sort so everything drawn
+        // with the same model at the same level is together. Each mesh in a group is then drawn
+        // once with an instance for each model, however many chairs and barrels there are.
+        self.visible.clear();
+        for draw in &batch.draws {
+            let model = match self.models.get(&draw.model) {
+                Some(model) => model,
+                None => continue
+            };
+            match self.select_lod(model, camera, batch.max_draw_distance, &draw.transform) {
+                Some(level) => self.visible.push((draw.model, level, ModelInstanceData {
+                    model: draw.transform.into(),
+                    opacity: draw.opacity,
+                    morph_weights: draw.morph_weights
+                })),
+                None => stats.culled += 1
+            }
+        }
+        self.visible.sort_by_key(|(id, level, _)| (*id, *level));
+
+        // Transparent meshes, and every mesh of a model that's fading out, are set aside to be
+        // drawn afterwards.
+        let forward = (camera.target - camera.eye).normalize();
+        self.instances.clear();
+        self.draws.clear();
+        self.transparent.clear();
+        for (id, level, instance) in &self.visible {
+            let faded = instance.opacity < 1.0;
+            let centre = Point3::from_vec(Vector4::from(instance.model[3]).truncate());
+            let depth = (centre - camera.eye).dot(forward);
+            for (mesh_index, mesh) in self.models[id].lods[*level].iter().enumerate() {
+                if faded || mesh.blend {
+                    self.transparent.push((depth, *id, *level, mesh_index, *instance));
+                }
+            }
+            if faded {
+                continue;
+            }
+
+            let index = self.instances.len() as u32;
+            self.instances.push(*instance);
+            match self.draws.last_mut() {
+                Some((last_id, last_level, range)) if last_id == id && last_level == level => range.end = index + 1,
+                _ => self.draws.push((*id, *level, index..index + 1))
+            }
+        }
+        // Furthest first, so nearer meshes blend over the ones behind them. Neighbours that are
+        // the same mesh still go in one draw, as instances are drawn in order.
+        self.transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.transparent_draws.clear();
+        for (_, id, level, mesh, instance) in &self.transparent {
+            let index = self.instances.len() as u32;
+            self.instances.push(*instance);
+            match self.transparent_draws.last_mut() {
+                Some((last_id, last_level, last_mesh, range)) if last_id == id && last_level == level && last_mesh == mesh => range.end = index + 1,
+                _ => self.transparent_draws.push((*id, *level, *mesh, index..index + 1))
+            }
+        }
+
+        stats.instances = self.visible.len() as u32;
+        if self.instances.is_empty() {
+            self.clear_depth(device, queue);
+            return stats;
+        }
+
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.instance_capacity);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Model Renderer Encoder")
+        });
+
+        // Upload the camera and instances through the staging belt, which reuses its buffers
+        // from frame to frame.
+        let aspect = SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32;
+        let camera = CameraUniforms {
+            view_projection: camera.view_projection_matrix(aspect).into(),
+            light_direction: LIGHT_DIRECTION
+        };
+        self.staging_belt.write_buffer(&mut encoder, &self.camera_buffer, 0, buffer_size(std::mem::size_of::<CameraUniforms>()), device)
+            .copy_from_slice(bytemuck::bytes_of(&camera));
+        let instance_bytes: &[u8] = bytemuck::cast_slice(&self.instances);
+        self.staging_belt.write_buffer(&mut encoder, &self.instance_buffer, 0, buffer_size(instance_bytes.len()), device)
+            .copy_from_slice(instance_bytes);
+        self.staging_belt.finish();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Model Renderer Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true
+                    }),
+                    stencil_ops: None
+                })
+            });
+
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
+            render_pass.set_viewport(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0, 1.0);
+            if let Some([x, y, width, height]) = scissor {
+                render_pass.set_scissor_rect(x, y, width, height);
+            }
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+            for (id, level, instances) in &self.draws {
+                let model = &self.models[id];
+                for mesh in model.lods[*level].iter().filter(|mesh| !mesh.blend) {
+                    render_pass.set_bind_group(1, &model.materials[mesh.material].bind_group, &[]);
+                    render_pass.set_bind_group(2, &mesh.morph_targets.as_ref().unwrap_or(&self.no_morph_targets).bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..mesh.index_count, 0, instances.clone());
+
+                    stats.draw_calls += 1;
+                    stats.triangles += mesh.index_count / 3 * instances.len() as u32;
+                }
+            }
+        }
+
+        if !self.transparent_draws.is_empty() {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Model Renderer Transparent Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }),
+                    stencil_ops: None
+                })
+            });
+
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.transparent_pipeline));
+            render_pass.set_viewport(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0, 1.0);
+            if let Some([x, y, width, height]) = scissor {
+                render_pass.set_scissor_rect(x, y, width, height);
+            }
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+            for (id, level, mesh, instances) in &self.transparent_draws {
+                let model = &self.models[id];
+                let mesh = &model.lods[*level][*mesh];
+                render_pass.set_bind_group(1, &model.materials[mesh.material].bind_group, &[]);
+                render_pass.set_bind_group(2, &mesh.morph_targets.as_ref().unwrap_or(&self.no_morph_targets).bind_group, &[]);
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.index_count, 0, instances.clone());
+
+                stats.draw_calls += 1;
+                stats.triangles += mesh.index_count / 3 * instances.len() as u32;
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+        self.staging_belt.recall();
+        stats
+    }
+}
+
+// Sizes of uploads through a staging belt, which can't be empty.
+fn buffer_size(bytes: usize) -> NonZeroU64 {
+    NonZeroU64::new(bytes as u64).expect("Staging belt writes can't be empty")
+}
+
+fn create_texture(device: &Device, queue: &Queue, image: &image::RgbaImage, format: TextureFormat) -> Texture {
+    let (width, height) = image.dimensions();
+    device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+        label: Some("Model Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+    }, image.as_raw())
+}
+
+// Each target's position displacements, then its normal displacements, one texel per vertex,
+// running on from one row of the texture to the next.
+fn create_morph_targets(device: &Device, queue: &Queue, layout: &wgpu::BindGroupLayout, vertex_count: usize, targets: &[MorphTargetData]) -> GpuMorphTargets {
+    let targets = &targets[..targets.len().min(MAX_MORPH_TARGETS)];
+    let texels = (targets.len() * 2 * vertex_count).max(1);
+    let width = texels.min(MORPH_TEXTURE_WIDTH);
+    let height = texels.div_ceil(width);
+
+    let mut data = vec![[0.0f32; 4]; width * height];
+    for (i, target) in targets.iter().enumerate() {
+        for (slot, offsets) in [&target.positions, &target.normals].into_iter().enumerate() {
+            let start = (i * 2 + slot) * vertex_count;
+            for (texel, offset) in data[start..start + vertex_count].iter_mut().zip(offsets) {
+                *texel = [offset[0], offset[1], offset[2], 0.0];
+            }
+        }
+    }
+
+    let texture = device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+        label: Some("Model Morph Texture"),
+        size: wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+    }, bytemuck::cast_slice(&data));
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Model Morph Buffer"),
+        contents: bytemuck::cast_slice(&[MorphUniforms {
+            vertex_count: vertex_count as u32,
+            target_count: targets.len() as u32,
+            _padding: [0; 2]
+        }]),
+        usage: wgpu::BufferUsages::UNIFORM
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Model Morph Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view)
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.as_entire_binding()
+            }
+        ]
+    });
+
+    GpuMorphTargets {
+        _texture: texture,
+        texture_bytes: (width * height * std::mem::size_of::<[f32; 4]>()) as u64,
+        _uniform_buffer: uniform_buffer,
+        bind_group
+    }
+}
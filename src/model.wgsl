@@ -0,0 +1,140 @@
+struct Camera {
+    view_projection: mat4x4<f32>,
+    light_direction: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Vertex shader
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(8) tangent: vec4<f32>,
+};
+
+struct InstanceInput {
+    @location(3) model_0: vec4<f32>,
+    @location(4) model_1: vec4<f32>,
+    @location(5) model_2: vec4<f32>,
+    @location(6) model_3: vec4<f32>,
+    @location(7) opacity: f32,
+    @location(9) morph_weights_0: vec4<f32>,
+    @location(10) morph_weights_1: vec4<f32>,
+};
+
+struct Morph {
+    vertex_count: u32,
+    target_count: u32,
+};
+
+@group(2) @binding(0)
+var t_morph: texture_2d<f32>;
+
+@group(2) @binding(1)
+var<uniform> morph: Morph;
+
+// How far a vertex moves for one of the morph targets. Each target has a slot for position
+// displacements followed by one for normal displacements.
+fn morph_offset(slot: u32, vertex_index: u32) -> vec3<f32> {
+    let texel = slot * morph.vertex_count + vertex_index;
+    let width = u32(textureDimensions(t_morph).x);
+    return textureLoad(t_morph, vec2<i32>(i32(texel % width), i32(texel / width)), 0).xyz;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) opacity: f32,
+    @location(3) tangent: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    vertex: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+    // Blend in the morph targets. Most weights are 0 most of the time, so those are skipped.
+    var weights = array<f32, 8>(
+        instance.morph_weights_0.x, instance.morph_weights_0.y, instance.morph_weights_0.z, instance.morph_weights_0.w,
+        instance.morph_weights_1.x, instance.morph_weights_1.y, instance.morph_weights_1.z, instance.morph_weights_1.w,
+    );
+    var position = vertex.position;
+    var normal = vertex.normal;
+    for (var i = 0u; i < min(morph.target_count, 8u); i = i + 1u) {
+        let weight = weights[i];
+        if (weight != 0.0) {
+            position = position + weight * morph_offset(i * 2u, vertex_index);
+            normal = normal + weight * morph_offset(i * 2u + 1u, vertex_index);
+        }
+    }
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * model * vec4<f32>(position, 1.0);
+    out.normal = (model * vec4<f32>(normal, 0.0)).xyz;
+    out.uv = vertex.uv;
+    out.opacity = instance.opacity;
+    out.tangent = vec4<f32>((model * vec4<f32>(vertex.tangent.xyz, 0.0)).xyz, vertex.tangent.w);
+    return out;
+}
+
+// Fragment shader
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    alpha_cutoff: f32,
+    normal_scale: f32,
+};
+
+@group(1) @binding(0)
+var t_base_color: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_base_color: sampler;
+
+@group(1) @binding(2)
+var<uniform> material: Material;
+
+@group(1) @binding(3)
+var t_normal: texture_2d<f32>;
+
+@group(1) @binding(4)
+var t_emissive: texture_2d<f32>;
+
+// Bend the surface normal by the normal map, which is in tangent space.
+fn mapped_normal(in: VertexOutput, sampled: vec3<f32>) -> vec3<f32> {
+    let normal = normalize(in.normal);
+    let along_surface = in.tangent.xyz - normal * dot(normal, in.tangent.xyz);
+    // Meshes without tangents can't be normal mapped.
+    if (dot(along_surface, along_surface) < 0.000001) {
+        return normal;
+    }
+    let tangent = normalize(along_surface);
+    let bitangent = cross(normal, tangent) * in.tangent.w;
+    let local = normalize(vec3<f32>((sampled.xy * 2.0 - 1.0) * material.normal_scale, sampled.z * 2.0 - 1.0));
+    return normalize(mat3x3<f32>(tangent, bitangent, normal) * local);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Every texture is sampled up front, as sampling has to happen before any discard.
+    let color = textureSample(t_base_color, s_base_color, in.uv) * material.base_color;
+    let sampled_normal = textureSample(t_normal, s_base_color, in.uv).xyz;
+    let emissive = textureSample(t_emissive, s_base_color, in.uv).rgb * material.emissive.rgb;
+
+    // Cut out materials, like leaves, are either solid or not there at all.
+    if (color.a < material.alpha_cutoff) {
+        discard;
+    }
+
+    // Simple diffuse lighting with some ambient so the dark side isn't black. Emissive light
+    // is added on top, however dark it is.
+    let normal = mapped_normal(in, sampled_normal);
+    let light = max(dot(normal, -normalize(camera.light_direction.xyz)), 0.0);
+    return vec4<f32>(color.rgb * (0.35 + 0.65 * light) + emissive, color.a * in.opacity);
+}
@@ -0,0 +1,221 @@
+// Mods: folders of extra or replacement assets and data, mounted over the game's own so user
+// content can swap textures or add quests without repacking anything. Each mod is a folder in
+// the mods directory laid out like the game's, with an optional mod.cfg describing it:
+//
+//   title = A name to show for it
+//   description = What it does
+//
+// Which mods are on, and in what order, is kept in the settings. Later mods win over earlier
+// ones, and all of them over the game. Most files are simply replaced by the mod's copy, but data
+// files are merged: a [section] with the same name as one the game has replaces it, new
+// sections are added after the game's, and lines before the first section, like settings or a
+// manifest's paths, come after the game's, so they win where later lines do.
+//
+// Mods can also run console commands, one a line in a commands.txt in their folder, once the
+// title screen's gone. Only ones that change the game, like flags, camera moves and dialogue,
+// are allowed, see MOD_COMMANDS, never ones for settings, saves, files or getting about. Each
+// tick they get MOD_COMMAND_TIME to run in, and no more than MOD_COMMANDS_PER_TICK, so a long
+// or slow list can't hold a frame up. The "commands" console command lists every command, what
+// it takes and whether mods can run it. There's no console on the web, so no commands there.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::VecDeque, time::{Duration, Instant}};
+use std::{fs, path::{Path, PathBuf}};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::console::ConsoleCommand;
+use crate::logging::targets;
+
+pub const COMMANDS_FILE: &str = "commands.txt";
+
+// The console commands mods can run.
+pub const MOD_COMMANDS: &[&str] = &[
+    "flag", "time", "talk", "say", "effect", "camera", "focus", "music", "battle", "fieldstatus", "darkness",
+    "puzzle", "minigame", "morph", "hit", "emote", "guard", "chase", "mover", "movie"
+];
+// How long mods' commands get each tick, and how many of them at most.
+#[cfg(not(target_arch = "wasm32"))]
+pub const MOD_COMMAND_TIME: Duration = Duration::from_millis(2);
+pub const MOD_COMMANDS_PER_TICK: usize = 8;
+
+// A mod that's been found, whether it's on or not.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModInfo {
+    // The folder's name, which is what the settings go by.
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub dir: PathBuf
+}
+
+impl ModInfo {
+    // Read a mod's folder. The title's the folder's name if mod.cfg doesn't say.
+    pub fn load(dir: &Path) -> Option<Self> {
+        let name = dir.file_name()?.to_str()?.to_string();
+        let mut info = Self { title: name.clone(), name, description: String::new(), dir: dir.to_path_buf() };
+        if let Ok(text) = fs::read_to_string(dir.join("mod.cfg")) {
+            for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                    Some(("title", title)) => info.title = title.to_string(),
+                    Some(("description", description)) => info.description = description.to_string(),
+                    _ => tracing::warn!(target: targets::ASSETS, "Ignoring \"{}\" in {}", line, dir.join("mod.cfg").display())
+                }
+            }
+        }
+        Some(info)
+    }
+}
+
+// Every mod in the mods directory, by name.
+pub fn find_mods(mods_dir: &Path) -> Vec<ModInfo> {
+    let entries = match fs::read_dir(mods_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new()
+    };
+    let mut mods: Vec<ModInfo> = entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| ModInfo::load(&path))
+        .collect();
+    mods.sort_by(|a, b| a.name.cmp(&b.name));
+    mods
+}
+
+// The folders of the mods that are on, in the order they go on, for AssetServer::with_mods.
+// Mods the settings mention that aren't there any more are left out with a warning.
+pub fn enabled_dirs(mods: &[ModInfo], enabled: &[String]) -> Vec<PathBuf> {
+    enabled.iter().filter_map(|name| match mods.iter().find(|info| info.name == *name) {
+        Some(info) => Some(info.dir.clone()),
+        None => {
+            tracing::warn!(target: targets::ASSETS, "Mod \"{}\" is on, but isn't in the mods folder", name);
+            None
+        }
+    }).collect()
+}
+
+// Whether a mod's copy of a file is merged with the game's rather than replacing it.
+pub fn is_merged(path: &str) -> bool {
+    path.starts_with("data/") && (path.ends_with(".cfg") || path.ends_with(".manifest"))
+}
+
+// Merge a mod's data file over the game's, see the top of the file.
+pub fn merge_data(base: &str, over: &str) -> String {
+    let (base_start, mut sections) = split_sections(base);
+    let (over_start, over_sections) = split_sections(over);
+    for (header, lines) in over_sections {
+        match sections.iter_mut().find(|(existing, _)| *existing == header) {
+            Some(section) => section.1 = lines,
+            None => sections.push((header, lines))
+        }
+    }
+
+    let mut text = String::new();
+    for line in base_start.iter().chain(&over_start) {
+        text.push_str(line);
+        text.push('\n');
+    }
+    for (header, lines) in sections {
+        text.push_str(header);
+        text.push('\n');
+        for line in lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// The lines before the first section, then each section's header and lines.
+type Sections<'a> = (Vec<&'a str>, Vec<(&'a str, Vec<&'a str>)>);
+
+fn split_sections(text: &str) -> Sections<'_> {
+    let mut start = Vec::new();
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            sections.push((trimmed, Vec::new()));
+            continue;
+        }
+        match sections.last_mut() {
+            Some((_, lines)) => lines.push(line),
+            None => start.push(line)
+        }
+    }
+    (start, sections)
+}
+
+pub fn allows_command(name: &str) -> bool {
+    MOD_COMMANDS.contains(&name)
+}
+
+// Commands mods have asked to run, waiting their turn, along with which mod asked.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug, Default)]
+pub struct ModCommands {
+    queue: VecDeque<(String, ConsoleCommand)>,
+    // When this tick's turn started, and how many have had a go in it.
+    tick_start: Option<Instant>,
+    tick_count: usize
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ModCommands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Read the commands.txt in each mod's folder, in the order the mods go on. Lines starting
+    // with # are comments.
+    pub fn load(dirs: &[PathBuf]) -> Self {
+        let mut commands = Self::new();
+        for dir in dirs {
+            let text = match fs::read_to_string(dir.join(COMMANDS_FILE)) {
+                Ok(text) => text,
+                Err(_) => continue
+            };
+            let name = dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            for command in text.lines().filter(|line| !line.trim().starts_with('#')).filter_map(ConsoleCommand::parse) {
+                commands.push(&name, command);
+            }
+        }
+        commands
+    }
+
+    pub fn push(&mut self, mod_name: &str, command: ConsoleCommand) {
+        self.queue.push_back((mod_name.to_string(), command));
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // Start this tick's turn. Until it's called next_command() gives nothing.
+    pub fn start_tick(&mut self) {
+        self.tick_start = Some(Instant::now());
+        self.tick_count = 0;
+    }
+
+    // The next command to run, if there's any of the tick's budget left. The time's counted
+    // from start_tick, so it includes running the ones before. Ones mods can't run are
+    // dropped with a warning, and still count towards the budget.
+    pub fn next_command(&mut self) -> Option<ConsoleCommand> {
+        loop {
+            let started = self.tick_start?;
+            if self.queue.is_empty() || self.tick_count >= MOD_COMMANDS_PER_TICK || started.elapsed() >= MOD_COMMAND_TIME {
+                self.tick_start = None;
+                return None;
+            }
+            let (mod_name, command) = self.queue.pop_front()?;
+            self.tick_count += 1;
+            if allows_command(&command.name) {
+                return Some(command);
+            }
+            tracing::warn!(target: targets::ASSETS, "Mod \"{}\" can't run \"{}\"", mod_name, command.name);
+        }
+    }
+}
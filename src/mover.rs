@@ -0,0 +1,304 @@
+// Things in fields that move about by themselves, like a lift going up and down or the
+// gondolas on a ferris wheel. Each follows a track of keyframes, either a path of points at a
+// steady speed or an animation on a node in the field's glTF, and goes round, back and forth or
+// just the once. Scripts and the console start and stop them by name with "mover lift start".
+//
+// Passengers standing on a mover's deck are carried along with it, turning as it turns. They
+// aren't parented to it, see hierarchy.rs, as the player walks about by setting their Transform
+// and the hierarchy would put them back. Props that should stay put on a mover can be parented.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use cgmath::{InnerSpace, Matrix4, Quaternion, Rotation, SquareMatrix, Vector3};
+
+use crate::transform::Transform;
+use crate::world::{Entity, World};
+
+// How far above or below a deck passengers can be and still be stood on it.
+pub const DECK_HEIGHT: f32 = 0.3;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    // Back to the start once it gets to the end, like a ferris wheel.
+    #[default]
+    Loop,
+    // To the end and back again, like a lift.
+    PingPong,
+    // To the end and stop, like a drawbridge coming down.
+    Once
+}
+
+impl LoopMode {
+    pub const ALL: [LoopMode; 3] = [LoopMode::Loop, LoopMode::PingPong, LoopMode::Once];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoopMode::Loop => "loop",
+            LoopMode::PingPong => "pingpong",
+            LoopMode::Once => "once"
+        }
+    }
+}
+
+impl fmt::Display for LoopMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LoopMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LoopMode::ALL.into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown loop mode \"{}\", expected loop, pingpong or once", s.trim()))
+    }
+}
+
+// Where a mover is at a moment on its track.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: Duration,
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>
+}
+
+// Where a mover's keyframes come from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MoverTrack {
+    // Through each point in turn at a steady speed, in metres a second, without turning.
+    Path { points: Vec<Vector3<f32>>, speed: f32 },
+    // The animation on a node in the field's glTF, by the node's name.
+    Animation(String)
+}
+
+// A mover in a field's data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldMover {
+    // What scripts call it.
+    pub name: String,
+    pub model: String,
+    pub track: MoverTrack,
+    pub mode: LoopMode,
+    // Whether it's going when the field loads, or waits to be started.
+    pub playing: bool,
+    // Half the width and depth of the part that can be stood on, around its origin, if any.
+    pub deck: Option<[f32; 2]>
+}
+
+// Keyframes for going through `points` at `speed`, with the time each is reached.
+pub fn path_keyframes(points: &[Vector3<f32>], speed: f32) -> Vec<Keyframe> {
+    let mut time = 0.0;
+    let mut keyframes = Vec::new();
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 && speed > 0.0 {
+            time += (point - points[i - 1]).magnitude() / speed;
+        }
+        keyframes.push(Keyframe { time: Duration::from_secs_f32(time), position: *point, rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0) });
+    }
+    keyframes
+}
+
+// Keyframes from the animation on the node called `node` in a glTF file. Its translation and
+// rotation channels are merged, with whichever isn't animated staying where the node is. The
+// animation's data has to be in a .glb's binary chunk or a base64 data URI.
+pub fn keyframes_from_gltf(bytes: &[u8], node: &str) -> Result<Vec<Keyframe>, String> {
+    use base64::Engine;
+
+    let gltf = gltf::Gltf::from_slice(bytes).map_err(|e| e.to_string())?;
+    let target = gltf.nodes().find(|n| n.name() == Some(node)).ok_or_else(|| format!("There's no node called {}", node))?;
+    let buffers: Vec<Option<Vec<u8>>> = gltf.buffers().map(|buffer| match buffer.source() {
+        gltf::buffer::Source::Bin => gltf.blob.clone(),
+        gltf::buffer::Source::Uri(uri) => uri.strip_prefix("data:")
+            .and_then(|data| data.split_once(";base64,"))
+            .and_then(|(_, encoded)| base64::engine::general_purpose::STANDARD.decode(encoded).ok())
+    }).collect();
+
+    let (rest_position, rest_rotation, _) = target.transform().decomposed();
+    let mut translations: Vec<(f32, Vector3<f32>)> = Vec::new();
+    let mut rotations: Vec<(f32, Quaternion<f32>)> = Vec::new();
+    for channel in gltf.animations().flat_map(|animation| animation.channels().collect::<Vec<_>>()) {
+        if channel.target().node().index() != target.index() {
+            continue;
+        }
+        let reader = channel.reader(|buffer| buffers.get(buffer.index()).and_then(Option::as_deref));
+        let times: Vec<f32> = match reader.read_inputs() {
+            Some(times) => times.collect(),
+            None => return Err(format!("The animation on {} has no data", node))
+        };
+        match reader.read_outputs() {
+            Some(gltf::animation::util::ReadOutputs::Translations(values)) => {
+                translations = times.iter().copied().zip(values.map(Vector3::from)).collect();
+            },
+            Some(gltf::animation::util::ReadOutputs::Rotations(values)) => {
+                rotations = times.iter().copied().zip(values.into_f32().map(|[x, y, z, w]| Quaternion::new(w, x, y, z))).collect();
+            },
+            Some(_) => {},
+            None => return Err(format!("The animation on {} has no data", node))
+        }
+    }
+    if translations.is_empty() && rotations.is_empty() {
+        return Err(format!("{} isn't animated", node));
+    }
+
+    let [x, y, z, w] = rest_rotation;
+    let (rest_position, rest_rotation) = (Vector3::from(rest_position), Quaternion::new(w, x, y, z));
+    let mut times: Vec<f32> = translations.iter().map(|(time, _)| *time).chain(rotations.iter().map(|(time, _)| *time)).collect();
+    times.sort_by(f32::total_cmp);
+    times.dedup();
+    Ok(times.into_iter().map(|time| Keyframe {
+        time: Duration::from_secs_f32(time.max(0.0)),
+        position: sample(&translations, time, |a, b, t| a + (b - a) * t).unwrap_or(rest_position),
+        rotation: sample(&rotations, time, |a, b, t| a.nlerp(b, t)).unwrap_or(rest_rotation)
+    }).collect())
+}
+
+// A channel's value at `time`, between the keys either side.
+fn sample<T: Copy>(keys: &[(f32, T)], time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let after = keys.partition_point(|(key_time, _)| *key_time <= time);
+    match (after.checked_sub(1).and_then(|i| keys.get(i)), keys.get(after)) {
+        (Some((a_time, a)), Some((b_time, b))) => Some(lerp(*a, *b, (time - a_time) / (b_time - a_time))),
+        (Some((_, value)), None) | (None, Some((_, value))) => Some(*value),
+        (None, None) => None
+    }
+}
+
+// Component moving an entity along a track.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mover {
+    keyframes: Vec<Keyframe>,
+    pub mode: LoopMode,
+    pub playing: bool,
+    pub deck: Option<[f32; 2]>,
+    time: Duration
+}
+
+impl Mover {
+    pub fn new(keyframes: Vec<Keyframe>, mode: LoopMode) -> Self {
+        Self { keyframes, mode, playing: true, deck: None, time: Duration::ZERO }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    // How long it takes to get from one end of the track to the other.
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |keyframe| keyframe.time)
+    }
+
+    // How far it's been going, counting every lap.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    // Start again from the beginning.
+    pub fn restart(&mut self) {
+        self.time = Duration::ZERO;
+        self.playing = true;
+    }
+
+    // How far along the track it is, once laps and going back are taken into account.
+    fn track_time(&self) -> Duration {
+        let duration = self.duration();
+        if duration.is_zero() {
+            return Duration::ZERO;
+        }
+        match self.mode {
+            LoopMode::Loop => Duration::from_secs_f64(self.time.as_secs_f64() % duration.as_secs_f64()),
+            LoopMode::PingPong => {
+                let lap = self.time.as_secs_f64() % (duration.as_secs_f64() * 2.0);
+                Duration::from_secs_f64(if lap > duration.as_secs_f64() { duration.as_secs_f64() * 2.0 - lap } else { lap })
+            },
+            LoopMode::Once => self.time.min(duration)
+        }
+    }
+
+    // Where it is on the track now.
+    pub fn sample(&self) -> Option<(Vector3<f32>, Quaternion<f32>)> {
+        let time = self.track_time().as_secs_f32();
+        let positions: Vec<(f32, Vector3<f32>)> = self.keyframes.iter().map(|keyframe| (keyframe.time.as_secs_f32(), keyframe.position)).collect();
+        let rotations: Vec<(f32, Quaternion<f32>)> = self.keyframes.iter().map(|keyframe| (keyframe.time.as_secs_f32(), keyframe.rotation)).collect();
+        Some((sample(&positions, time, |a, b, t| a + (b - a) * t)?, sample(&rotations, time, |a, b, t| a.nlerp(b, t))?))
+    }
+}
+
+// Component for anyone who gets carried along by movers they're stood on, like the player.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Passenger;
+
+// Move every mover that's playing along its track, carrying whoever's on its deck. Ones that
+// only go once stop at the end. Returns true while any are moving.
+pub fn update_movers(world: &mut World, delta: Duration) -> bool {
+    let movers: Vec<Entity> = world.query::<Mover>().map(|(entity, _)| entity).collect();
+    let passengers: Vec<Entity> = world.query::<Passenger>().map(|(entity, _)| entity).collect();
+    let mut moving = false;
+    for entity in movers {
+        let (before, deck) = match (world.get::<Transform>(entity), world.get::<Mover>(entity)) {
+            (Some(transform), Some(mover)) if mover.playing => (*transform, mover.deck),
+            _ => continue
+        };
+        let mover = match world.get_mut::<Mover>(entity) {
+            Some(mover) => mover,
+            None => continue
+        };
+        mover.time += delta;
+        if mover.mode == LoopMode::Once && mover.time >= mover.duration() {
+            mover.playing = false;
+        }
+        moving |= mover.playing;
+        let (position, rotation) = match mover.sample() {
+            Some(sample) => sample,
+            None => continue
+        };
+        let after = Transform { position, rotation, ..before };
+        world.insert(entity, after);
+
+        // Whoever was on the deck before it moved goes where that bit of deck went.
+        let deck = match deck {
+            Some(deck) => deck,
+            None => continue
+        };
+        let to_deck = match before.matrix().invert() {
+            Some(inverse) => inverse,
+            None => continue
+        };
+        let carry: Matrix4<f32> = after.matrix() * to_deck;
+        let turn = after.rotation * before.rotation.invert();
+        for passenger in &passengers {
+            let transform = match world.get_mut::<Transform>(*passenger) {
+                Some(transform) => transform,
+                None => continue
+            };
+            let on_deck = to_deck * transform.position.extend(1.0);
+            let scale = before.scale;
+            let standing = on_deck.x.abs() * scale.x.abs() <= deck[0]
+                && on_deck.z.abs() * scale.z.abs() <= deck[1]
+                && (on_deck.y * scale.y).abs() <= DECK_HEIGHT;
+            if standing {
+                transform.position = (carry * transform.position.extend(1.0)).truncate();
+                transform.rotation = (turn * transform.rotation).normalize();
+            }
+        }
+    }
+    moving
+}
+
+// Start or stop the mover called `name`. Returns false if there's no such mover.
+pub fn set_playing(world: &mut World, name: &str, playing: bool) -> bool {
+    let entity = match world.find_by_name(name) {
+        Some(entity) => entity,
+        None => return false
+    };
+    match world.get_mut::<Mover>(entity) {
+        Some(mover) => {
+            if playing && mover.mode == LoopMode::Once && mover.time >= mover.duration() {
+                mover.restart();
+            }
+            mover.playing = playing;
+            true
+        },
+        None => false
+    }
+}
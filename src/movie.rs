@@ -0,0 +1,242 @@
+// Pre-rendered cutscenes, played as a sequence of images. A movie is a directory under movies/
+// with a movie.cfg describing it:
+//
+// fps = 24
+// frames = 240
+// # Optional, how the frame files are named. The #s are replaced with the frame number,
+// # starting from 1 and padded with zeros.
+// pattern = ####.png
+// # Optional soundtrack.
+// audio = opening.ogg
+// # Optional, the languages there are subtitles in. Each is a subtitles.<language>.srt in the
+// # movie's directory, see subtitles.rs.
+// subtitles = en, ja
+//
+// Frames are kept compressed in memory and only decoded when they're shown.
+
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::accessibility::Accessibility;
+use crate::assets::{AssetError, AssetServer};
+use crate::logging::targets;
+use crate::renderer::{PostProcessSettings, Renderer, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::subtitles::{self, Subtitles};
+use crate::ui::{UiBatch, UiImageId, WHITE};
+
+pub struct Movie {
+    pub name: String,
+    pub fps: f32,
+    pub audio: Option<String>,
+    pub subtitles: Option<Subtitles>,
+    frames: Vec<Vec<u8>>
+}
+
+impl Movie {
+    // Subtitles are loaded in the language closest to `language` that the movie has.
+    pub async fn load(assets: &AssetServer, name: &str, language: &str) -> Result<Self, AssetError> {
+        let config_path = format!("movies/{}/movie.cfg", name);
+        let bad_config = |e: String| AssetError::Decode(config_path.clone(), e);
+        let config = assets.load_bytes(&config_path).await?;
+
+        let mut fps = None;
+        let mut frame_count = None;
+        let mut pattern = "####.png".to_string();
+        let mut audio = None;
+        let mut languages = Vec::new();
+        for line in String::from_utf8_lossy(&config).lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| bad_config(format!("Expected \"key = value\", got \"{}\"", line)))?;
+            match key {
+                "fps" => fps = Some(value.parse::<f32>().ok().filter(|fps| *fps > 0.0).ok_or_else(|| bad_config(format!("Bad fps \"{}\"", value)))?),
+                "frames" => frame_count = Some(value.parse::<usize>().map_err(|_| bad_config(format!("Bad frame count \"{}\"", value)))?),
+                "pattern" => pattern = value.to_string(),
+                "audio" => audio = Some(value.to_string()),
+                "subtitles" => languages = value.split(',').map(|language| language.trim().to_string()).filter(|language| !language.is_empty()).collect(),
+                _ => return Err(bad_config(format!("Unknown key \"{}\"", key)))
+            }
+        }
+        let fps = fps.ok_or_else(|| bad_config("Missing fps".to_string()))?;
+        let frame_count = frame_count.ok_or_else(|| bad_config("Missing frames".to_string()))?;
+
+        let mut frames = Vec::with_capacity(frame_count);
+        for frame in 1..=frame_count {
+            let path = format!("movies/{}/{}", name, frame_file_name(&pattern, frame));
+            frames.push(assets.load_bytes(&path).await?);
+        }
+
+        let subtitles = match subtitles::pick_language(&languages, language) {
+            Some(language) => Some(Subtitles::load(assets, &format!("movies/{}/subtitles.{}.srt", name, language), language).await?),
+            None => None
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            fps,
+            audio,
+            subtitles,
+            frames
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f32(self.frames.len() as f32 / self.fps)
+    }
+
+    pub fn decode_frame(&self, frame: usize) -> Result<image::RgbaImage, image::ImageError> {
+        image::load_from_memory(&self.frames[frame]).map(|image| image.to_rgba8())
+    }
+}
+
+// "####.png" and 7 gives "0007.png".
+fn frame_file_name(pattern: &str, frame: usize) -> String {
+    let width = pattern.chars().filter(|c| *c == '#').count();
+    match pattern.find('#') {
+        Some(start) => format!("{}{:0width$}{}", &pattern[..start], frame, &pattern[start + width..], width = width),
+        None => pattern.to_string()
+    }
+}
+
+// Plays a movie over the whole screen. Post processing is turned off while it plays, since the
+// movie was made with its own look.
+pub struct MoviePlayer {
+    movie: Movie,
+    image: Option<UiImageId>,
+    size: (u32, u32),
+    frame: usize,
+    started: Instant,
+    skipped: bool,
+
+    post_process: PostProcessSettings
+}
+
+impl MoviePlayer {
+    pub fn start(movie: Movie, renderer: &mut Renderer) -> Self {
+        tracing::info!(target: targets::ENGINE, "Playing movie {}", movie.name);
+        if let Some(audio) = &movie.audio {
+            tracing::warn!(target: targets::ENGINE, "Movie {} has a soundtrack ({}), but there's no audio output to play it on.", movie.name, audio);
+        }
+
+        // Movies play without the field's effects, but keep the player's colour filter and
+        // depth of field quality.
+        let settings = renderer.get_post_process_settings_mut();
+        let post_process = std::mem::replace(settings, PostProcessSettings {
+            color_filter: settings.color_filter,
+            dof_quality: settings.dof_quality,
+            ..PostProcessSettings::default()
+        });
+        let mut player = Self {
+            movie,
+            image: None,
+            size: (0, 0),
+            frame: 0,
+            started: Instant::now(),
+            skipped: false,
+            post_process
+        };
+        player.show_frame(renderer, 0);
+        player
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    // How far into the movie we are. Frames are picked by time rather than counted, so the
+    // movie keeps to its soundtrack even when frames are dropped.
+    pub fn position(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn skip(&mut self) {
+        tracing::info!(target: targets::ENGINE, "Skipped movie {}", self.movie.name);
+        self.skipped = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.skipped || self.position() >= self.movie.duration()
+    }
+
+    // Show whichever frame should be on screen now.
+    pub fn update(&mut self, renderer: &mut Renderer) {
+        if self.is_finished() {
+            return;
+        }
+
+        let frame = ((self.position().as_secs_f32() * self.movie.fps) as usize).min(self.movie.frame_count() - 1);
+        if frame != self.frame {
+            self.show_frame(renderer, frame);
+        }
+    }
+
+    fn show_frame(&mut self, renderer: &mut Renderer, frame: usize) {
+        self.frame = frame;
+        if self.movie.frame_count() == 0 {
+            return;
+        }
+
+        let image = match self.movie.decode_frame(frame) {
+            Ok(image) => image,
+            Err(e) => {
+                tracing::warn!(target: targets::ASSETS, "Couldn't decode frame {} of movie {}: {}", frame + 1, self.movie.name, e);
+                return;
+            }
+        };
+
+        match self.image {
+            Some(id) if image.dimensions() == self.size => renderer.update_ui_image(id, &image),
+            _ => {
+                if let Some(id) = self.image.take() {
+                    renderer.remove_ui_image(id);
+                }
+                self.image = Some(renderer.create_ui_image(&image));
+                self.size = image.dimensions();
+            }
+        }
+    }
+
+    // Black out the screen and draw the movie as big as it'll fit, with its subtitles if the
+    // player wants them.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, [0.0, 0.0, 0.0, 1.0]);
+
+        let id = match self.image {
+            Some(id) => id,
+            None => return
+        };
+        let (width, height) = (self.size.0 as f32, self.size.1 as f32);
+        let scale = (SCREEN_WIDTH as f32 / width).min(SCREEN_HEIGHT as f32 / height);
+        let (width, height) = (width * scale, height * scale);
+        batch.image((SCREEN_WIDTH as f32 - width) / 2.0, (SCREEN_HEIGHT as f32 - height) / 2.0, width, height, id, WHITE);
+
+        let subtitle = self.movie.subtitles.as_ref().and_then(|subtitles| subtitles.at(self.position()));
+        if let (Some(text), true) = (subtitle, accessibility.subtitles) {
+            subtitles::build_subtitle(batch, text, accessibility);
+        }
+    }
+
+    // Clean up once the movie is over and put post processing back how it was. The player's
+    // settings are left alone, in case they were changed while the movie played.
+    pub fn finish(mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.image.take() {
+            renderer.remove_ui_image(id);
+        }
+        let settings = renderer.get_post_process_settings_mut();
+        *settings = PostProcessSettings {
+            color_filter: settings.color_filter,
+            dof_quality: settings.dof_quality,
+            ..self.post_process
+        };
+    }
+}
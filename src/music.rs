@@ -0,0 +1,300 @@
+// Layered music. A track is a base loop that always plays, plus stems layered on top of it,
+// like drums for danger or strings at night, and short stingers played over it when
+// something happens. Tracks are described by a .cfg under music/:
+//
+// file = field.ogg
+// bpm = 120
+//
+// [layer danger]
+// file = field_danger.ogg
+// # Optional, plays while this flag is set, fading in and out over `fade` beats.
+// flag = field.danger
+// fade = 4
+// # Optional, how loud it starts, from 0 to 1.
+// volume = 0
+//
+// [stinger found_item]
+// file = found_item.ogg
+//
+// Layer fades and stingers wait for the next beat, so they always land in time with the music.
+
+use std::time::Duration;
+
+use crate::assets::{AssetError, AssetServer};
+use crate::flags::GameFlags;
+use crate::logging::targets;
+use crate::world::World;
+
+const DEFAULT_FADE_BEATS: f32 = 4.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MusicLayer {
+    pub name: String,
+    pub file: String,
+    // Follows a flag, playing while it's set.
+    pub flag: Option<String>,
+    // How many beats following the flag takes to fade.
+    pub fade: f32,
+    pub volume: f32
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MusicStinger {
+    pub name: String,
+    pub file: String
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MusicTrack {
+    pub name: String,
+    pub file: String,
+    pub bpm: f32,
+    pub layers: Vec<MusicLayer>,
+    pub stingers: Vec<MusicStinger>
+}
+
+impl MusicTrack {
+    pub async fn load(assets: &AssetServer, path: &str) -> Result<Self, AssetError> {
+        let text = assets.load_bytes(path).await?;
+        Self::parse(path, &String::from_utf8_lossy(&text)).map_err(|e| AssetError::Decode(path.to_string(), e))
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Self, String> {
+        enum Section {
+            Track,
+            Layer(usize),
+            Stinger(usize)
+        }
+
+        let mut track = MusicTrack {
+            name: name.to_string(),
+            file: String::new(),
+            bpm: 0.0,
+            layers: Vec::new(),
+            stingers: Vec::new()
+        };
+        let mut section = Section::Track;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let (kind, name) = header.trim().split_once(' ')
+                    .map(|(kind, name)| (kind, name.trim().to_string()))
+                    .ok_or_else(|| format!("Line {}: expected [layer name] or [stinger name]", number + 1))?;
+                section = match kind {
+                    "layer" => {
+                        track.layers.push(MusicLayer { name, file: String::new(), flag: None, fade: DEFAULT_FADE_BEATS, volume: 0.0 });
+                        Section::Layer(track.layers.len() - 1)
+                    },
+                    "stinger" => {
+                        track.stingers.push(MusicStinger { name, file: String::new() });
+                        Section::Stinger(track.stingers.len() - 1)
+                    },
+                    _ => return Err(format!("Line {}: unknown section \"{}\"", number + 1, kind))
+                };
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            let amount = |value: &str| value.parse::<f32>().ok().filter(|value| *value >= 0.0);
+            match (&section, key) {
+                (Section::Track, "file") => track.file = value.to_string(),
+                (Section::Track, "bpm") => track.bpm = amount(value).filter(|bpm| *bpm > 0.0).ok_or_else(|| format!("Line {}: bad bpm \"{}\"", number + 1, value))?,
+                (Section::Layer(index), "file") => track.layers[*index].file = value.to_string(),
+                (Section::Layer(index), "flag") => track.layers[*index].flag = Some(value.to_string()),
+                (Section::Layer(index), "fade") => track.layers[*index].fade = amount(value).ok_or_else(|| format!("Line {}: bad fade \"{}\"", number + 1, value))?,
+                (Section::Layer(index), "volume") => track.layers[*index].volume = amount(value).filter(|volume| *volume <= 1.0).ok_or_else(|| format!("Line {}: bad volume \"{}\"", number + 1, value))?,
+                (Section::Stinger(index), "file") => track.stingers[*index].file = value.to_string(),
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+
+        if track.file.is_empty() {
+            return Err("Missing file".to_string());
+        }
+        if track.bpm == 0.0 {
+            return Err("Missing bpm".to_string());
+        }
+        let names = track.layers.iter().map(|layer| (&layer.name, &layer.file)).chain(track.stingers.iter().map(|stinger| (&stinger.name, &stinger.file)));
+        for (name, file) in names {
+            if file.is_empty() {
+                return Err(format!("{} is missing a file", name));
+            }
+        }
+        Ok(track)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&MusicLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn stinger(&self, name: &str) -> Option<&MusicStinger> {
+        self.stingers.iter().find(|stinger| stinger.name == name)
+    }
+
+    // The audio files the track needs, for the asset report.
+    pub fn files(&self) -> Vec<&str> {
+        std::iter::once(self.file.as_str())
+            .chain(self.layers.iter().map(|layer| layer.file.as_str()))
+            .chain(self.stingers.iter().map(|stinger| stinger.file.as_str()))
+            .collect()
+    }
+}
+
+// A layer's volume going from one level to another, starting on a beat.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct LayerFade {
+    from: f32,
+    to: f32,
+    start: f32,
+    beats: f32
+}
+
+impl LayerFade {
+    fn volume(&self, beat: f32) -> f32 {
+        if beat >= self.start + self.beats {
+            return self.to;
+        }
+        let t = ((beat - self.start) / self.beats).clamp(0.0, 1.0);
+        self.from + (self.to - self.from) * t
+    }
+}
+
+// A stinger that's been asked for and is waiting for its beat.
+#[derive(Clone, Debug, PartialEq)]
+struct QueuedStinger {
+    file: String,
+    beat: f32
+}
+
+// Resource for the music that's playing. Keeps time in beats and works out each layer's
+// volume; whatever plays the audio follows along with `layer_volume` and `take_stingers`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MusicPlayer {
+    track: MusicTrack,
+    position: Duration,
+    fades: Vec<LayerFade>,
+    queued: Vec<QueuedStinger>,
+    stingers: Vec<String>
+}
+
+impl MusicPlayer {
+    // Start a track from the beginning. Layers that follow flags start where the flags say,
+    // without fading.
+    pub fn start(track: MusicTrack, flags: Option<&GameFlags>) -> Self {
+        tracing::info!(target: targets::ENGINE, "Playing music {}", track.name);
+        tracing::warn!(target: targets::ENGINE, "There's no audio output to play {} on.", track.file);
+        let fades = track.layers.iter().map(|layer| {
+            let volume = match (&layer.flag, flags) {
+                (Some(flag), Some(flags)) => if flags.is_set(flag) { 1.0 } else { 0.0 },
+                _ => layer.volume
+            };
+            LayerFade { from: volume, to: volume, start: 0.0, beats: 0.0 }
+        }).collect();
+        Self {
+            track,
+            position: Duration::ZERO,
+            fades,
+            queued: Vec::new(),
+            stingers: Vec::new()
+        }
+    }
+
+    pub fn track(&self) -> &MusicTrack {
+        &self.track
+    }
+
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    // How many beats have gone by since the track started.
+    pub fn beat(&self) -> f32 {
+        self.position.as_secs_f32() * self.track.bpm / 60.0
+    }
+
+    // The next beat, or this one if we're right on it.
+    pub fn next_beat(&self) -> f32 {
+        self.beat().ceil()
+    }
+
+    pub fn layer_volume(&self, name: &str) -> Option<f32> {
+        let index = self.track.layers.iter().position(|layer| layer.name == name)?;
+        Some(self.fades[index].volume(self.beat()))
+    }
+
+    // Every layer's name and how loud it is now.
+    pub fn layer_volumes(&self) -> impl Iterator<Item = (&str, f32)> {
+        let beat = self.beat();
+        self.track.layers.iter().zip(&self.fades).map(move |(layer, fade)| (layer.name.as_str(), fade.volume(beat)))
+    }
+
+    // Fade a layer to a volume over some beats, starting on the next beat. Zero beats
+    // switches it on the next beat.
+    pub fn fade_layer(&mut self, name: &str, volume: f32, beats: f32) -> Result<(), String> {
+        let index = self.track.layers.iter().position(|layer| layer.name == name)
+            .ok_or_else(|| format!("{} doesn't have a layer \"{}\"", self.track.name, name))?;
+        let beat = self.beat();
+        let start = self.next_beat();
+        self.fades[index] = LayerFade {
+            from: self.fades[index].volume(beat),
+            to: volume.clamp(0.0, 1.0),
+            start,
+            beats: beats.max(0.0)
+        };
+        Ok(())
+    }
+
+    // Play a stinger over the music on the next beat.
+    pub fn play_stinger(&mut self, name: &str) -> Result<(), String> {
+        let stinger = self.track.stinger(name).ok_or_else(|| format!("{} doesn't have a stinger \"{}\"", self.track.name, name))?;
+        self.queued.push(QueuedStinger { file: stinger.file.clone(), beat: self.next_beat() });
+        Ok(())
+    }
+
+    // Move the music on, following any flags its layers watch. Layers only start fading when
+    // their flag changes, so a script can still fade them by hand in the meantime.
+    pub fn update(&mut self, delta: Duration, flags: Option<&GameFlags>) {
+        if let Some(flags) = flags {
+            for index in 0..self.fades.len() {
+                let layer = &self.track.layers[index];
+                let flag = match &layer.flag {
+                    Some(flag) => flag,
+                    None => continue
+                };
+                let target = if flags.is_set(flag) { 1.0 } else { 0.0 };
+                let following = if self.fades[index].to > 0.0 { 1.0 } else { 0.0 };
+                if target != following {
+                    let (name, fade) = (layer.name.clone(), layer.fade);
+                    let _ = self.fade_layer(&name, target, fade);
+                }
+            }
+        }
+
+        self.position += delta;
+        let beat = self.beat();
+        let (due, waiting): (Vec<QueuedStinger>, Vec<QueuedStinger>) = std::mem::take(&mut self.queued).into_iter().partition(|stinger| stinger.beat <= beat);
+        self.queued = waiting;
+        self.stingers.extend(due.into_iter().map(|stinger| stinger.file));
+    }
+
+    // The stinger files that have reached their beat since this was last called.
+    pub fn take_stingers(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.stingers)
+    }
+}
+
+// Move the world's music on.
+pub fn update_music(world: &mut World, delta: Duration) {
+    let mut music = match world.remove_resource::<MusicPlayer>() {
+        Some(music) => music,
+        None => return
+    };
+    music.update(delta, world.resource::<GameFlags>());
+    world.insert_resource(music);
+}
@@ -0,0 +1,408 @@
+// Typing a name in without a keyboard: a grid of characters to move round with the arrows or
+// the d-pad, picking each with enter, with space, delete, the next page of characters and done
+// along the bottom. Typing on a keyboard works too, and moves the cursor to done so enter
+// finishes. Escape deletes the last character, or closes it if there's nothing to delete.
+//
+// Which characters there are goes by language, from text/keyboard.cfg, localized like any other
+// asset. Each page has a [Name] header, shown on the key that switches to it, then a line per
+// row, with spaces between characters ignored:
+//
+//   [ABC]
+//   A B C D E F G H I J
+//   K L M N O P Q R S T
+//
+// The game's own Latin pages are used if there isn't one.
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::menu::MenuSound;
+use crate::pointer::{PointerEvent, PointerResponse};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::strings::{StringContext, StringTable};
+use crate::ui::UiBatch;
+
+pub const KEYBOARD_PATH: &str = "text/keyboard.cfg";
+// The longest a party member's name can be, in characters.
+pub const NAME_LENGTH: usize = 8;
+
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 4.0;
+
+// One set of characters to pick from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyboardPage {
+    pub name: String,
+    pub rows: Vec<Vec<char>>
+}
+
+impl KeyboardPage {
+    fn new(name: &str, rows: &[&str]) -> Self {
+        Self { name: name.to_string(), rows: rows.iter().map(|row| row.chars().filter(|c| *c != ' ').collect()).collect() }
+    }
+}
+
+// Every page for a language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    pub pages: Vec<KeyboardPage>
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self {
+            pages: vec![
+                KeyboardPage::new("ABC", &["ABCDEFGHIJ", "KLMNOPQRST", "UVWXYZ"]),
+                KeyboardPage::new("abc", &["abcdefghij", "klmnopqrst", "uvwxyz"]),
+                KeyboardPage::new("123", &["0123456789", "!?.,'-&"])
+            ]
+        }
+    }
+}
+
+impl KeyboardLayout {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut pages: Vec<KeyboardPage> = Vec::new();
+        for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                pages.push(KeyboardPage { name: name.trim().to_string(), rows: Vec::new() });
+                continue;
+            }
+
+            let page = pages.last_mut().ok_or_else(|| format!("Line {}: expected a [Page name] first", number + 1))?;
+            page.rows.push(line.chars().filter(|c| !c.is_whitespace()).collect());
+        }
+
+        if let Some(empty) = pages.iter().find(|page| page.rows.is_empty()) {
+            return Err(format!("Page \"{}\" has no characters", empty.name));
+        }
+        if pages.is_empty() {
+            return Err("There are no pages".to_string());
+        }
+        Ok(Self { pages })
+    }
+}
+
+// What's under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameKey {
+    Char(char),
+    Space,
+    Delete,
+    NextPage,
+    Done
+}
+
+// The keys along the bottom, under every page.
+const BOTTOM_ROW: [NameKey; 4] = [NameKey::Space, NameKey::Delete, NameKey::NextPage, NameKey::Done];
+
+#[derive(Default)]
+pub struct NameEntry {
+    layout: KeyboardLayout,
+    open: bool,
+    title: String,
+    text: String,
+    page: usize,
+    // Row and column, where the row after the page's last is the bottom row.
+    cursor: (usize, usize),
+    sound: Option<MenuSound>
+}
+
+impl NameEntry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // For the player's language. Whatever's open starts again from the first page.
+    pub fn set_layout(&mut self, layout: KeyboardLayout) {
+        self.layout = layout;
+        self.page = 0;
+        self.cursor = (0, 0);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Start with `text` in it, like the name they have now, so it's easy to keep.
+    pub fn open(&mut self, title: &str, text: &str) {
+        self.open = true;
+        self.title = title.to_string();
+        self.text = text.chars().take(NAME_LENGTH).collect();
+        self.page = 0;
+        self.cursor = (0, 0);
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn selected(&self) -> Option<NameKey> {
+        self.key_at(self.cursor.0, self.cursor.1)
+    }
+
+    // The sound the last key or pointer event made, see menu.rs.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    // Move round the grid with the arrows, which wrap round, pick with enter and delete with
+    // escape or backspace. Tab goes to the next page. Returns the name once it's done.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        let (row, column) = self.cursor;
+        let rows = self.row_count();
+        match key {
+            VirtualKeyCode::Up => self.move_to((row + rows - 1) % rows, row),
+            VirtualKeyCode::Down => self.move_to((row + 1) % rows, row),
+            VirtualKeyCode::Left => {
+                let length = self.row_length(row);
+                self.select(row, (column + length - 1) % length);
+            },
+            VirtualKeyCode::Right => self.select(row, (column + 1) % self.row_length(row)),
+            VirtualKeyCode::Tab => self.next_page(),
+            VirtualKeyCode::Return => return self.pick(),
+            VirtualKeyCode::Escape | VirtualKeyCode::Back => self.back(),
+            _ => {}
+        }
+        None
+    }
+
+    // A character typed on a keyboard. Returns whether it went in.
+    pub fn handle_char(&mut self, c: char) -> bool {
+        if !self.open || c.is_control() || !self.push(c) {
+            return false;
+        }
+        self.cursor = (self.row_count() - 1, BOTTOM_ROW.len() - 1);
+        true
+    }
+
+    // The same for the mouse and touch, like the other menus. Clicking outside the keys does
+    // what escape does.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> PointerResponse<String> {
+        let before = (self.open, self.page, self.cursor, self.text.clone());
+        let action = self.pointer(event, accessibility);
+        PointerResponse::new(action, before != (self.open, self.page, self.cursor, self.text.clone()))
+    }
+
+    fn pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        let layout = Layout::new(accessibility, self.columns());
+        match event {
+            PointerEvent::Hover { x, y } => {
+                if let Some((row, column)) = self.key_under(&layout, x, y) {
+                    self.select(row, column);
+                }
+            },
+            PointerEvent::Click { x, y } => match self.key_under(&layout, x, y) {
+                Some((row, column)) => {
+                    self.select(row, column);
+                    return self.pick();
+                },
+                None => self.back()
+            },
+            PointerEvent::Scroll { .. } => {},
+            PointerEvent::Cancel => self.back()
+        }
+        None
+    }
+
+    fn page(&self) -> &KeyboardPage {
+        &self.layout.pages[self.page.min(self.layout.pages.len() - 1)]
+    }
+
+    // The page's rows and the bottom one.
+    fn row_count(&self) -> usize {
+        self.page().rows.len() + 1
+    }
+
+    fn row_length(&self, row: usize) -> usize {
+        self.page().rows.get(row).map_or(BOTTOM_ROW.len(), |keys| keys.len().max(1))
+    }
+
+    // The widest row, to size the keys by.
+    fn columns(&self) -> usize {
+        self.page().rows.iter().map(Vec::len).max().unwrap_or_default().max(BOTTOM_ROW.len())
+    }
+
+    fn key_at(&self, row: usize, column: usize) -> Option<NameKey> {
+        match self.page().rows.get(row) {
+            Some(keys) => keys.get(column).copied().map(NameKey::Char),
+            None => BOTTOM_ROW.get(column).copied()
+        }
+    }
+
+    fn key_under(&self, layout: &Layout, x: f32, y: f32) -> Option<(usize, usize)> {
+        let row = layout.row_at(y, self.row_count())?;
+        let column = layout.column_at(x, self.row_length(row), row + 1 == self.row_count())?;
+        self.key_at(row, column).map(|_| (row, column))
+    }
+
+    // Up or down to `row` from `from`, keeping to about the same place across, as rows can be
+    // different lengths and the bottom one's keys are wider.
+    fn move_to(&mut self, row: usize, from: usize) {
+        let across = (self.cursor.1 as f32 + 0.5) / self.row_length(from) as f32;
+        let length = self.row_length(row);
+        self.select(row, ((across * length as f32) as usize).min(length - 1));
+    }
+
+    fn select(&mut self, row: usize, column: usize) {
+        if (row, column) != self.cursor {
+            self.cursor = (row, column);
+            self.sound = Some(MenuSound::Move);
+        }
+    }
+
+    fn next_page(&mut self) {
+        if self.layout.pages.len() > 1 {
+            self.page = (self.page + 1) % self.layout.pages.len();
+            // Stay on the bottom row, otherwise keep within the new page.
+            let row = match self.cursor.0 + 1 >= self.row_count() {
+                true => self.row_count() - 1,
+                false => self.cursor.0.min(self.row_count() - 2)
+            };
+            self.cursor = (row, self.cursor.1.min(self.row_length(row) - 1));
+            self.sound = Some(MenuSound::Confirm);
+        }
+    }
+
+    fn push(&mut self, c: char) -> bool {
+        if self.text.chars().count() >= NAME_LENGTH {
+            self.sound = Some(MenuSound::Error);
+            return false;
+        }
+        self.text.push(c);
+        self.sound = Some(MenuSound::Confirm);
+        true
+    }
+
+    fn back(&mut self) {
+        if self.text.pop().is_none() {
+            self.open = false;
+        }
+        self.sound = Some(MenuSound::Cancel);
+    }
+
+    fn pick(&mut self) -> Option<String> {
+        match self.selected()? {
+            NameKey::Char(c) => { self.push(c); },
+            NameKey::Space if !self.text.is_empty() => { self.push(' '); },
+            NameKey::Delete if !self.text.is_empty() => {
+                self.text.pop();
+                self.sound = Some(MenuSound::Cancel);
+            },
+            NameKey::NextPage => self.next_page(),
+            NameKey::Done if !self.text.trim().is_empty() => {
+                self.open = false;
+                self.sound = Some(MenuSound::Confirm);
+                return Some(self.text.trim().to_string());
+            },
+            // No leading spaces, nothing to delete and no empty names.
+            _ => self.sound = Some(MenuSound::Error)
+        }
+        None
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, strings: Option<&StringTable>) {
+        if !self.open {
+            return;
+        }
+
+        let skin = accessibility.skin();
+        let layout = Layout::new(accessibility, self.columns());
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, skin.backdrop);
+        batch.text(MARGIN, MARGIN, layout.scale * 1.5, &self.title, skin.text);
+
+        // What's typed so far, with a line for each character left.
+        let blanks = NAME_LENGTH.saturating_sub(self.text.chars().count());
+        let typed = format!("{}{}", self.text, "_".repeat(blanks));
+        batch.text(MARGIN + PADDING, layout.text_y, layout.scale * 1.5, &typed, skin.highlight);
+
+        let label = |id: &str, fallback: &str| match strings {
+            Some(strings) => strings.text(id, fallback, &StringContext::default(), &[]),
+            None => fallback.to_string()
+        };
+        let next_page = &self.layout.pages[(self.page + 1) % self.layout.pages.len()].name;
+        for row in 0..self.row_count() {
+            let bottom = row + 1 == self.row_count();
+            for column in 0..self.row_length(row) {
+                let text = match self.key_at(row, column) {
+                    Some(NameKey::Char(c)) => c.to_string(),
+                    Some(NameKey::Space) => label("name_entry.space", "Space"),
+                    Some(NameKey::Delete) => label("name_entry.delete", "Delete"),
+                    Some(NameKey::NextPage) => next_page.clone(),
+                    Some(NameKey::Done) => label("name_entry.done", "Done"),
+                    None => continue
+                };
+                let (x, y, width) = layout.key_rect(row, column, bottom);
+                if (row, column) == self.cursor {
+                    batch.rect(x, y, width, layout.row_height, skin.selected);
+                }
+                let color = if bottom { skin.dim_text } else { skin.text };
+                batch.text(x + PADDING, y + PADDING, layout.scale, &text, color);
+            }
+        }
+    }
+}
+
+// Where the keys go, shared by drawing and hit testing. Character keys are all the same width,
+// and the bottom row's share the whole width between them.
+struct Layout {
+    scale: f32,
+    text_y: f32,
+    first_row_y: f32,
+    row_height: f32,
+    key_width: f32,
+    width: f32
+}
+
+impl Layout {
+    fn new(accessibility: &Accessibility, columns: usize) -> Self {
+        let scale = 2.0 * accessibility.text_scale();
+        let text_y = MARGIN * 2.0 + UiBatch::line_height(scale * 1.5);
+        let width = SCREEN_WIDTH as f32 - MARGIN * 2.0;
+        Self {
+            scale,
+            text_y,
+            first_row_y: text_y + UiBatch::line_height(scale * 1.5) + MARGIN,
+            row_height: UiBatch::line_height(scale) + PADDING * 2.0,
+            key_width: width / columns.max(1) as f32,
+            width
+        }
+    }
+
+    fn key_rect(&self, row: usize, column: usize, bottom: bool) -> (f32, f32, f32) {
+        let width = if bottom { self.width / BOTTOM_ROW.len() as f32 } else { self.key_width };
+        (MARGIN + width * column as f32, self.first_row_y + self.row_height * row as f32, width)
+    }
+
+    fn row_at(&self, y: f32, rows: usize) -> Option<usize> {
+        if y < self.first_row_y {
+            return None;
+        }
+        let row = ((y - self.first_row_y) / self.row_height) as usize;
+        (row < rows).then_some(row)
+    }
+
+    fn column_at(&self, x: f32, length: usize, bottom: bool) -> Option<usize> {
+        if x < MARGIN {
+            return None;
+        }
+        let width = if bottom { self.width / BOTTOM_ROW.len() as f32 } else { self.key_width };
+        let column = ((x - MARGIN) / width) as usize;
+        (column < length).then_some(column)
+    }
+}
@@ -0,0 +1,11 @@
+// Reading values out of the game's text files, shared by the settings and the data files. This
+// isn't desktop only like config is, as battle data needs it on the web too.
+
+// Accepts true/false, on/off, yes/no and 1/0.
+pub fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "on" | "yes" | "1" => Ok(true),
+        "false" | "off" | "no" | "0" => Ok(false),
+        _ => Err(format!("Expected on or off, got \"{}\"", value.trim()))
+    }
+}
@@ -0,0 +1,341 @@
+// The player's party: who's in it, how strong they are, what they're wearing and what they
+// can do. Battles, levelling up and the shops read and change these, the status menu shows
+// them.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::auto_battle::Gambit;
+use crate::inventory::ItemEffect;
+use crate::formation::Row;
+use crate::pools::SkillCost;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub level: u32,
+    pub experience: u32,
+    pub hp: u32,
+    pub max_hp: u32,
+    pub mp: u32,
+    pub max_mp: u32,
+    pub strength: u32,
+    pub magic: u32,
+    pub defence: u32,
+    pub speed: u32
+}
+
+impl Stats {
+    pub fn is_knocked_out(&self) -> bool {
+        self.hp == 0
+    }
+
+    // Heal by up to the amounts given, without going over the maximums. Returns how much of
+    // each was actually restored.
+    pub fn restore(&mut self, hp: u32, mp: u32) -> (u32, u32) {
+        let (old_hp, old_mp) = (self.hp, self.mp);
+        self.hp = self.hp.saturating_add(hp).min(self.max_hp);
+        self.mp = self.mp.saturating_add(mp).min(self.max_mp);
+        (self.hp - old_hp, self.mp - old_mp)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquipSlot {
+    Weapon,
+    Armour,
+    Accessory
+}
+
+impl EquipSlot {
+    pub const ALL: [EquipSlot; 3] = [EquipSlot::Weapon, EquipSlot::Armour, EquipSlot::Accessory];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipSlot::Weapon => "weapon",
+            EquipSlot::Armour => "armour",
+            EquipSlot::Accessory => "accessory"
+        }
+    }
+
+    // For showing in menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            EquipSlot::Weapon => "Weapon",
+            EquipSlot::Armour => "Armour",
+            EquipSlot::Accessory => "Accessory"
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for EquipSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EquipSlot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EquipSlot::ALL.into_iter()
+            .find(|slot| slot.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown equipment slot \"{}\", expected weapon, armour or accessory", s.trim()))
+    }
+}
+
+// Something worn in one of the slots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Equipment {
+    pub name: String,
+    // For weapons, whether attacks with it aren't weakened by rows. See formation::RowModifiers.
+    pub reach: bool
+}
+
+impl Equipment {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), reach: false }
+    }
+}
+
+// Who a skill can be used on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetType {
+    Enemy,
+    AllEnemies,
+    Ally,
+    AllAllies,
+    // Only whoever's using it.
+    User
+}
+
+impl TargetType {
+    pub const ALL: [TargetType; 5] = [TargetType::Enemy, TargetType::AllEnemies, TargetType::Ally, TargetType::AllAllies, TargetType::User];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetType::Enemy => "enemy",
+            TargetType::AllEnemies => "all_enemies",
+            TargetType::Ally => "ally",
+            TargetType::AllAllies => "all_allies",
+            TargetType::User => "self"
+        }
+    }
+
+    // Whether it's used on everyone it can be at once, rather than picking one.
+    pub fn is_all(&self) -> bool {
+        matches!(self, TargetType::AllEnemies | TargetType::AllAllies)
+    }
+}
+
+impl fmt::Display for TargetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TargetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TargetType::ALL.into_iter()
+            .find(|target| target.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown target \"{}\", expected enemy, all_enemies, ally, all_allies or self", s.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skill {
+    pub name: String,
+    pub description: String,
+    pub mp_cost: u32,
+    // What else it costs, from other pools or the inventory, see pools.rs.
+    pub costs: Vec<SkillCost>,
+    pub target: TargetType,
+    // What it does when it's used outside battle, like an item would, see field_skill.rs.
+    // None for skills that are only for battles.
+    pub field: Option<ItemEffect>
+}
+
+// Lasting effects on someone that carry on after battle. Poison and regen hurt and heal as the
+// party walks around fields too, see field_status.rs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusEffect {
+    Poison,
+    Regen
+}
+
+impl StatusEffect {
+    pub const ALL: [StatusEffect; 2] = [StatusEffect::Poison, StatusEffect::Regen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusEffect::Poison => "poison",
+            StatusEffect::Regen => "regen"
+        }
+    }
+
+    // For showing in menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatusEffect::Poison => "Poison",
+            StatusEffect::Regen => "Regen"
+        }
+    }
+
+    // Whether it's something to be cured of.
+    pub fn is_harmful(&self) -> bool {
+        match self {
+            StatusEffect::Poison => true,
+            StatusEffect::Regen => false
+        }
+    }
+}
+
+impl fmt::Display for StatusEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for StatusEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StatusEffect::ALL.into_iter()
+            .find(|effect| effect.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown status effect \"{}\", expected poison or regen", s.trim()))
+    }
+}
+
+// For text that says "she" or "he", see strings.rs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Gender {
+    Female,
+    Male,
+    #[default]
+    Neutral
+}
+
+impl Gender {
+    pub const ALL: [Gender; 3] = [Gender::Female, Gender::Male, Gender::Neutral];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gender::Female => "female",
+            Gender::Male => "male",
+            Gender::Neutral => "neutral"
+        }
+    }
+}
+
+impl fmt::Display for Gender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Gender {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Gender::ALL.into_iter()
+            .find(|gender| gender.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown gender \"{}\", expected female, male or neutral", s.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartyMember {
+    pub name: String,
+    pub gender: Gender,
+    pub stats: Stats,
+    // What's in each slot, in EquipSlot order.
+    equipment: [Option<Equipment>; 3],
+    pub skills: Vec<Skill>,
+    pub row: Row,
+    // Whether they fight by themselves, following their gambits.
+    pub auto_battle: bool,
+    pub gambits: Vec<Gambit>,
+    pub status_effects: Vec<StatusEffect>,
+    // How much they have in each pool besides MP, by its id, see pools.rs.
+    pub pools: BTreeMap<String, u32>
+}
+
+impl PartyMember {
+    pub fn new(name: &str, stats: Stats) -> Self {
+        Self {
+            name: name.to_string(),
+            gender: Gender::default(),
+            stats,
+            equipment: Default::default(),
+            skills: Vec::new(),
+            row: Row::Front,
+            auto_battle: false,
+            gambits: Gambit::defaults(),
+            status_effects: Vec::new(),
+            pools: BTreeMap::new()
+        }
+    }
+
+    pub fn equipped(&self, slot: EquipSlot) -> Option<&Equipment> {
+        self.equipment[slot.index()].as_ref()
+    }
+
+    // Put something in a slot, or None to empty it. Returns what was there.
+    pub fn equip(&mut self, slot: EquipSlot, item: Option<Equipment>) -> Option<Equipment> {
+        std::mem::replace(&mut self.equipment[slot.index()], item)
+    }
+
+    // How much is in a pool, MP included.
+    pub fn pool(&self, id: &str) -> u32 {
+        match id {
+            "mp" => self.stats.mp,
+            _ => self.pools.get(id).copied().unwrap_or_default()
+        }
+    }
+
+    pub fn has_status(&self, effect: StatusEffect) -> bool {
+        self.status_effects.contains(&effect)
+    }
+
+    // Whether their weapon reaches from the back row, or into it.
+    pub fn has_reach(&self) -> bool {
+        self.equipped(EquipSlot::Weapon).map(|weapon| weapon.reach).unwrap_or_default()
+    }
+}
+
+// Resource for the party, in battle order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Party {
+    pub members: Vec<PartyMember>
+}
+
+impl Party {
+    pub fn new(members: Vec<PartyMember>) -> Self {
+        Self { members }
+    }
+
+    // Whoever's first is the one walking around fields.
+    pub fn leader(&self) -> Option<&PartyMember> {
+        self.members.first()
+    }
+
+    // Swap the first member who's still standing to the front, if the leader's been knocked
+    // out. Returns who took over.
+    pub fn replace_leader(&mut self) -> Option<&PartyMember> {
+        if !self.leader()?.stats.is_knocked_out() {
+            return None;
+        }
+        let standing = self.members.iter().position(|member| !member.stats.is_knocked_out())?;
+        self.members.swap(0, standing);
+        self.leader()
+    }
+
+    // Everyone's been knocked out, which is game over.
+    pub fn is_wiped_out(&self) -> bool {
+        self.members.iter().all(|member| member.stats.is_knocked_out())
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+const APP_DIRECTORY: &str = "ps_rpg_engine";
+
+// Where per user files like logs, saves and settings go.
+// Falls back to the working directory if the platform doesn't have a data directory.
+pub fn user_data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .map(|dir| dir.join(APP_DIRECTORY))
+        .unwrap_or_else(|| PathBuf::from("user_data"))
+}
+
+pub fn log_dir() -> PathBuf {
+    user_data_dir().join("logs")
+}
+
+pub fn config_path() -> PathBuf {
+    user_data_dir().join("config.cfg")
+}
+
+pub fn save_dir() -> PathBuf {
+    user_data_dir().join("saves")
+}
+
+// Saves exported for sharing, and where ones to import are looked for. Not in the save directory,
+// so storefront cloud saves leave them alone.
+pub fn export_dir() -> PathBuf {
+    user_data_dir().join("exports")
+}
+
+// Where mods are installed, a folder each.
+pub fn mods_dir() -> PathBuf {
+    user_data_dir().join("mods")
+}
+
+// Recordings of the player's movement, for playing back as ghosts.
+pub fn ghost_dir() -> PathBuf {
+    user_data_dir().join("ghosts")
+}
+
+// Telemetry that hasn't been sent yet.
+pub fn telemetry_queue_path() -> PathBuf {
+    user_data_dir().join("telemetry_queue.txt")
+}
+
+// Achievements unlocked on this machine, whatever the platform.
+pub fn achievements_path() -> PathBuf {
+    user_data_dir().join("achievements.txt")
+}
@@ -0,0 +1,243 @@
+// Items lying about in fields, shown as a sparkle, that the player picks up by walking up to
+// them and pressing the action button. Each says what it gives and when it comes back:
+// never, like a chest, every time the field's entered, like berries on a bush, or after so
+// many days on the game clock.
+//
+// Ones that don't come back every visit remember being picked up in a flag, "pickup.field.id",
+// so it goes in saves like everything else, along with the inventory the item went into. Never
+// coming back sets it to 1, and coming back after some days sets it to the day it was picked up.
+
+use std::{collections::VecDeque, time::Duration};
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::accessibility::Accessibility;
+use crate::field::FieldEntity;
+use crate::flags::GameFlags;
+use crate::game_clock::GameClock;
+use crate::interaction::{Interactable, InteractionKind};
+use crate::inventory::{Inventory, ItemCatalog};
+use crate::renderer::SCREEN_WIDTH;
+use crate::strings;
+use crate::transform::Transform;
+use crate::ui::{self, UiBatch};
+use crate::world::{Entity, Name, World};
+
+// How close the player has to be to pick something up, in metres, and in pixels for 2D fields.
+pub const PICKUP_RADIUS: f32 = 1.0;
+pub const PICKUP_RADIUS_2D: f32 = 16.0;
+// How long a toast saying what was picked up shows for.
+pub const TOAST_TIME: Duration = Duration::from_millis(2500);
+
+// How long the sparkle spends on each frame.
+const TWINKLE_TIME: Duration = Duration::from_millis(400);
+const SPARKLE_SCALE: f32 = 2.0;
+const SPARKLE_COLOR: ui::Color = [1.0, 0.95, 0.6, 1.0];
+const SPARKLE_FRAMES: [[&str; 7]; 2] = [
+    ["...#...", "...#...", "..###..", "#######", "..###..", "...#...", "...#..."],
+    [".......", ".#...#.", "..#.#..", "...#...", "..#.#..", ".#...#.", "......."]
+];
+const TOAST_MARGIN: f32 = 8.0;
+
+// When something that's been picked up comes back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Respawn {
+    Never,
+    EveryVisit,
+    // After this many days on the game clock.
+    Days(u32)
+}
+
+// Something to pick up in a field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldPickup {
+    // Has to be different from the field's other pickups, as its flag is named after it.
+    pub id: String,
+    pub item: String,
+    pub count: u32,
+    pub position: Vector3<f32>,
+    pub respawn: Respawn
+}
+
+impl FieldPickup {
+    // Whether it's there to be picked up, going by its flag and the day.
+    pub fn is_available(&self, field: &str, flags: &GameFlags, day: u32) -> bool {
+        let collected = flags.get(&pickup_flag(field, &self.id));
+        match self.respawn {
+            Respawn::Never => collected == 0,
+            Respawn::EveryVisit => true,
+            Respawn::Days(days) => collected == 0 || day >= collected as u32 + days
+        }
+    }
+}
+
+// The flag that remembers a pickup being picked up.
+pub fn pickup_flag(field: &str, id: &str) -> String {
+    format!("pickup.{}.{}", field, id)
+}
+
+// Component for a pickup that's in the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pickup {
+    pub item: String,
+    pub count: u32,
+    pub respawn: Respawn,
+    pub flag: String,
+    // How long it's been sparkling, for the animation.
+    sparkle: Duration
+}
+
+// Resource with what's been picked up, to tell the player one at a time.
+#[derive(Clone, Debug, Default)]
+pub struct PickupToasts {
+    toasts: VecDeque<String>,
+    shown: Duration
+}
+
+impl PickupToasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn show(&mut self, text: String) {
+        self.toasts.push_back(text);
+    }
+
+    // What's showing now, if anything.
+    pub fn current(&self) -> Option<&str> {
+        self.toasts.front().map(String::as_str)
+    }
+
+    // Move on, taking each toast away once it's shown for long enough. Returns true while any
+    // are left.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        if self.toasts.is_empty() {
+            return false;
+        }
+        self.shown += delta;
+        if self.shown >= TOAST_TIME {
+            self.toasts.pop_front();
+            self.shown = Duration::ZERO;
+        }
+        !self.toasts.is_empty()
+    }
+
+    // The one showing now, at the top of the screen in the middle.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        let text = match self.current() {
+            Some(text) => text,
+            None => return
+        };
+        let skin = accessibility.skin();
+        let scale = 2.0 * accessibility.text_scale();
+        let (width, height) = UiBatch::measure_text(scale, text);
+        let x = (SCREEN_WIDTH as f32 - width) / 2.0;
+        batch.rect(x - TOAST_MARGIN, TOAST_MARGIN, width + TOAST_MARGIN * 2.0, height + TOAST_MARGIN * 2.0, skin.window);
+        batch.text(x, TOAST_MARGIN * 2.0, scale, text, skin.text);
+    }
+}
+
+// Spawn the pickups in a field that are there to be picked up. They belong to the field, so
+// they go when the player leaves.
+pub fn spawn_pickups(world: &mut World, field: &str, pickups: &[FieldPickup]) {
+    let day = world.resource::<GameClock>().map_or(1, GameClock::day);
+    let available: Vec<&FieldPickup> = match world.resource::<GameFlags>() {
+        Some(flags) => pickups.iter().filter(|pickup| pickup.is_available(field, flags, day)).collect(),
+        None => pickups.iter().collect()
+    };
+    for pickup in available {
+        let name = world.resource::<ItemCatalog>()
+            .and_then(|items| items.get(&pickup.item))
+            .map_or_else(|| pickup.item.clone(), |item| item.name.clone());
+        let entity = world.spawn();
+        world.insert(entity, Name(pickup.id.clone()));
+        world.insert(entity, Transform::from_position(pickup.position));
+        world.insert(entity, FieldEntity);
+        world.insert(entity, Pickup {
+            item: pickup.item.clone(),
+            count: pickup.count,
+            respawn: pickup.respawn,
+            flag: pickup_flag(field, &pickup.id),
+            sparkle: Duration::ZERO
+        });
+        let prompt = strings::localize(world, "pickup.take", "Take {item}", &[("item", name.into())]);
+        world.insert(entity, Interactable::new(InteractionKind::Take, &prompt));
+    }
+}
+
+// The closest pickup to the player within `radius`, if there is one.
+pub fn nearest_pickup(world: &World, player: Entity, radius: f32) -> Option<Entity> {
+    let position = world.get::<Transform>(player)?.position;
+    world.query::<Pickup>()
+        .filter_map(|(entity, _)| Some((entity, (world.get::<Transform>(entity)?.position - position).magnitude())))
+        .filter(|(_, distance)| *distance <= radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity)
+}
+
+// Pick something up, putting it in the inventory, remembering it in its flag and showing a
+// toast. Gives back what the toast says.
+pub fn collect(world: &mut World, entity: Entity) -> Result<String, String> {
+    let pickup = match world.get::<Pickup>(entity) {
+        Some(pickup) => pickup.clone(),
+        None => return Err("There's nothing to pick up".to_string())
+    };
+    let item = match world.resource::<ItemCatalog>().and_then(|items| items.get(&pickup.item)) {
+        Some(item) => item.clone(),
+        None => return Err(format!("There's no item \"{}\"", pickup.item))
+    };
+    match world.resource_mut::<Inventory>() {
+        Some(inventory) => inventory.add(&item, pickup.count),
+        None => return Err("There's no inventory to put it in".to_string())
+    }
+
+    let day = world.resource::<GameClock>().map_or(1, GameClock::day);
+    if let Some(flags) = world.resource_mut::<GameFlags>() {
+        match pickup.respawn {
+            Respawn::Never => flags.set(&pickup.flag, 1),
+            Respawn::EveryVisit => {},
+            Respawn::Days(_) => flags.set(&pickup.flag, day as i32)
+        }
+    }
+    world.despawn(entity);
+
+    let text = strings::localize(world, "pickup.found", "Found {item}{count, plural, one {} other { x#}}", &[
+        ("item", item.name.as_str().into()),
+        ("count", pickup.count.into())
+    ]);
+    if world.resource::<PickupToasts>().is_none() {
+        world.insert_resource(PickupToasts::new());
+    }
+    if let Some(toasts) = world.resource_mut::<PickupToasts>() {
+        toasts.show(text.clone());
+    }
+    Ok(text)
+}
+
+// Move the sparkles and toasts on. Returns true while there's anything to animate.
+pub fn update_pickups(world: &mut World, delta: Duration) -> bool {
+    let mut animating = false;
+    for (_, pickup) in world.query_mut::<Pickup>() {
+        pickup.sparkle += delta;
+        animating = true;
+    }
+    if let Some(toasts) = world.resource_mut::<PickupToasts>() {
+        animating |= toasts.update(delta);
+    }
+    animating
+}
+
+// Draw a sparkle on each pickup. `to_screen` is where a point in the world is on the screen,
+// like for emotes.
+pub fn build_pickups(world: &World, batch: &mut UiBatch, to_screen: impl Fn(Vector3<f32>) -> Option<(f32, f32)>) {
+    for (entity, pickup) in world.query::<Pickup>() {
+        let (x, y) = match world.get::<Transform>(entity).and_then(|transform| to_screen(transform.position)) {
+            Some(point) => point,
+            None => continue
+        };
+        let frame = (pickup.sparkle.as_millis() / TWINKLE_TIME.as_millis()) as usize % SPARKLE_FRAMES.len();
+        let sprite = ui::pixel_sprite(&SPARKLE_FRAMES[frame], &[('#', SPARKLE_COLOR)]);
+        let (width, height) = (sprite.width() as f32 * SPARKLE_SCALE, sprite.height() as f32 * SPARKLE_SCALE);
+        batch.sprite(x - width / 2.0, y - height / 2.0, SPARKLE_SCALE, &sprite, 1.0);
+    }
+}
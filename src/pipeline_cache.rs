@@ -0,0 +1,271 @@
+use wgpu::{BindGroupLayout, Device, RenderPipeline, ShaderModule};
+
+use crate::logging::targets;
+
+// Handles into a PipelineCache. They stay valid when a shader is reloaded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BindGroupLayoutId(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineId(usize);
+
+// wgpu::VertexBufferLayout borrows its attributes, so keys keep their own copy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    pub array_stride: wgpu::BufferAddress,
+    pub step_mode: wgpu::VertexStepMode,
+    pub attributes: Vec<wgpu::VertexAttribute>
+}
+
+impl From<wgpu::VertexBufferLayout<'_>> for VertexLayout {
+    fn from(layout: wgpu::VertexBufferLayout<'_>) -> Self {
+        Self {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes: layout.attributes.to_vec()
+        }
+    }
+}
+
+impl VertexLayout {
+    fn desc(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.array_stride,
+            step_mode: self.step_mode,
+            attributes: &self.attributes
+        }
+    }
+}
+
+// Everything that makes one render pipeline different from another.
+// Asking for a pipeline with the same key twice gives back the same pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineKey {
+    pub shader: ShaderId,
+    pub bind_group_layouts: Vec<BindGroupLayoutId>,
+    pub vertex_layouts: Vec<VertexLayout>,
+    pub targets: Vec<Option<wgpu::ColorTargetState>>,
+    pub primitive: wgpu::PrimitiveState,
+    pub depth_stencil: Option<wgpu::DepthStencilState>,
+    pub multisample: wgpu::MultisampleState
+}
+
+impl PipelineKey {
+    // The state nearly everything uses: one target, triangle lists with back faces culled,
+    // no depth buffer and no multisampling. Change the fields for anything else.
+    pub fn new(shader: ShaderId, bind_group_layouts: &[BindGroupLayoutId], vertex_layouts: Vec<VertexLayout>, target: wgpu::ColorTargetState) -> Self {
+        Self {
+            shader,
+            bind_group_layouts: bind_group_layouts.to_vec(),
+            vertex_layouts,
+            targets: vec![Some(target)],
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            }
+        }
+    }
+}
+
+// Bind group layout entries that keep coming up.
+pub fn texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true }
+        },
+        count: None
+    }
+}
+
+// A depth buffer for reading with textureLoad as a texture_2d<f32>. It can't be filtered.
+pub fn depth_texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false }
+        },
+        count: None
+    }
+}
+
+pub fn sampler_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None
+    }
+}
+
+pub fn uniform_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+        },
+        count: None
+    }
+}
+
+struct CachedShader {
+    label: String,
+    module: ShaderModule
+}
+
+struct CachedLayout {
+    entries: Vec<wgpu::BindGroupLayoutEntry>,
+    layout: BindGroupLayout
+}
+
+struct CachedPipeline {
+    label: String,
+    key: PipelineKey,
+    pipeline: RenderPipeline
+}
+
+// Shaders, bind group layouts and render pipelines, shared between the renderers.
+// Renderers hold on to ids and look the pipeline up when they draw, so a reloaded shader
+// is picked up without them knowing.
+// There are only ever a handful of each, so they're found by comparing keys one by one.
+#[derive(Default)]
+pub struct PipelineCache {
+    shaders: Vec<CachedShader>,
+    layouts: Vec<CachedLayout>,
+    pipelines: Vec<CachedPipeline>
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Shaders are told apart by their label, which include_wgsl! sets to the file name.
+    pub fn load_shader(&mut self, device: &Device, desc: wgpu::ShaderModuleDescriptor) -> ShaderId {
+        let label = desc.label.unwrap_or_default().to_string();
+        if let Some(index) = self.shaders.iter().position(|shader| shader.label == label) {
+            return ShaderId(index);
+        }
+
+        let module = device.create_shader_module(desc);
+        self.shaders.push(CachedShader { label, module });
+        ShaderId(self.shaders.len() - 1)
+    }
+
+    // Layouts with the same entries are shared, whatever they're called.
+    pub fn create_bind_group_layout(&mut self, device: &Device, label: &str, entries: &[wgpu::BindGroupLayoutEntry]) -> BindGroupLayoutId {
+        if let Some(index) = self.layouts.iter().position(|layout| layout.entries == entries) {
+            return BindGroupLayoutId(index);
+        }
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries
+        });
+        self.layouts.push(CachedLayout { entries: entries.to_vec(), layout });
+        BindGroupLayoutId(self.layouts.len() - 1)
+    }
+
+    // The shader's entry points have to be called vs_main and fs_main.
+    pub fn create_render_pipeline(&mut self, device: &Device, label: &str, key: PipelineKey) -> PipelineId {
+        if let Some(index) = self.pipelines.iter().position(|pipeline| pipeline.key == key) {
+            return PipelineId(index);
+        }
+
+        let pipeline = self.build_pipeline(device, label, &key);
+        self.pipelines.push(CachedPipeline { label: label.to_string(), key, pipeline });
+        PipelineId(self.pipelines.len() - 1)
+    }
+
+    pub fn get_bind_group_layout(&self, id: BindGroupLayoutId) -> &BindGroupLayout {
+        &self.layouts[id.0].layout
+    }
+
+    pub fn get_render_pipeline(&self, id: PipelineId) -> &RenderPipeline {
+        &self.pipelines[id.0].pipeline
+    }
+
+    // Recompile a shader from new source and rebuild every pipeline that uses it.
+    // Returns false if no shader has that label. Compile errors go to the device's error
+    // handler like any other validation error.
+    pub fn reload_shader(&mut self, device: &Device, label: &str, source: &str) -> bool {
+        let index = match self.shaders.iter().position(|shader| shader.label == label) {
+            Some(index) => index,
+            None => return false
+        };
+
+        self.shaders[index].module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into())
+        });
+
+        let mut rebuilt = 0;
+        for i in 0..self.pipelines.len() {
+            if self.pipelines[i].key.shader == ShaderId(index) {
+                let pipeline = self.build_pipeline(device, &self.pipelines[i].label, &self.pipelines[i].key);
+                self.pipelines[i].pipeline = pipeline;
+                rebuilt += 1;
+            }
+        }
+
+        tracing::info!(target: targets::RENDERER, "Reloaded shader {}, rebuilt {} pipelines.", label, rebuilt);
+        true
+    }
+
+    fn build_pipeline(&self, device: &Device, label: &str, key: &PipelineKey) -> RenderPipeline {
+        let shader = &self.shaders[key.shader.0].module;
+        let bind_group_layouts: Vec<&BindGroupLayout> = key.bind_group_layouts.iter()
+            .map(|id| self.get_bind_group_layout(*id))
+            .collect();
+        let vertex_layouts: Vec<wgpu::VertexBufferLayout> = key.vertex_layouts.iter()
+            .map(VertexLayout::desc)
+            .collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[]
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &vertex_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &key.targets,
+            }),
+            primitive: key.primitive,
+            depth_stencil: key.depth_stencil.clone(),
+            multisample: key.multisample,
+            multiview: None
+        })
+    }
+}
@@ -0,0 +1,67 @@
+// Storefront specific services like achievements and rich presence. The game only talks to the
+// Platform trait, so builds without a storefront compile and run exactly the same.
+
+#[cfg(feature = "steam")]
+mod steam;
+
+use std::path::PathBuf;
+
+use crate::logging::targets;
+
+pub trait Platform {
+    fn name(&self) -> &'static str;
+
+    // Unlock an achievement by its id. Unlocking one twice does nothing.
+    fn unlock_achievement(&mut self, id: &str);
+
+    // Show where the player is to their friends.
+    fn set_location(&mut self, location: &str);
+
+    // Where saves should go. Storefronts with cloud saves may want a particular directory.
+    fn save_dir(&self) -> PathBuf;
+
+    // Call once a frame.
+    fn update(&mut self) {}
+}
+
+// Used when there's no storefront.
+#[derive(Default)]
+pub struct NullPlatform;
+
+impl Platform for NullPlatform {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn unlock_achievement(&mut self, id: &str) {
+        tracing::debug!(target: targets::ENGINE, "Achievement {} unlocked, but there's no platform to tell.", id);
+    }
+
+    fn set_location(&mut self, _location: &str) {}
+
+    fn save_dir(&self) -> PathBuf {
+        default_save_dir()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_save_dir() -> PathBuf {
+    crate::paths::save_dir()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_save_dir() -> PathBuf {
+    PathBuf::from("saves")
+}
+
+// Connect to whichever storefront the game was built for, falling back to no platform if it
+// isn't running.
+pub fn init() -> Box<dyn Platform> {
+    #[cfg(feature = "steam")]
+    match steam::SteamPlatform::init() {
+        Some(steam) => return Box::new(steam),
+        None => tracing::warn!(target: targets::ENGINE, "Couldn't connect to Steam, achievements won't be unlocked.")
+    }
+
+    Box::new(NullPlatform)
+}
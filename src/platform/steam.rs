@@ -0,0 +1,118 @@
+// Steam backend, built with the "steam" feature. Talks to the Steamworks flat C API directly,
+// so the only requirement is the steam_api library from the Steamworks SDK (1.57) being
+// somewhere the linker can find it, and next to the executable at runtime.
+
+use std::{collections::BTreeSet, ffi::{c_char, c_void, CString}, path::PathBuf};
+
+use crate::logging::targets;
+
+use super::{default_save_dir, Platform};
+
+#[cfg_attr(all(windows, target_pointer_width = "64"), link(name = "steam_api64"))]
+#[cfg_attr(not(all(windows, target_pointer_width = "64")), link(name = "steam_api"))]
+extern "C" {
+    fn SteamAPI_Init() -> bool;
+    fn SteamAPI_Shutdown();
+    fn SteamAPI_RunCallbacks();
+
+    fn SteamAPI_SteamUserStats_v012() -> *mut c_void;
+    fn SteamAPI_ISteamUserStats_SetAchievement(user_stats: *mut c_void, name: *const c_char) -> bool;
+    fn SteamAPI_ISteamUserStats_StoreStats(user_stats: *mut c_void) -> bool;
+
+    fn SteamAPI_SteamFriends_v017() -> *mut c_void;
+    fn SteamAPI_ISteamFriends_SetRichPresence(friends: *mut c_void, key: *const c_char, value: *const c_char) -> bool;
+
+    fn SteamAPI_SteamUser_v023() -> *mut c_void;
+    fn SteamAPI_ISteamUser_GetSteamID(user: *mut c_void) -> u64;
+}
+
+pub struct SteamPlatform {
+    user_stats: *mut c_void,
+    friends: *mut c_void,
+    steam_id: u64,
+
+    // Achievements unlocked since stats were last stored.
+    pending: BTreeSet<String>
+}
+
+impl SteamPlatform {
+    // None if Steam isn't running or the game wasn't launched through it.
+    pub fn init() -> Option<Self> {
+        unsafe {
+            if !SteamAPI_Init() {
+                return None;
+            }
+
+            let user_stats = SteamAPI_SteamUserStats_v012();
+            let friends = SteamAPI_SteamFriends_v017();
+            let user = SteamAPI_SteamUser_v023();
+            if user_stats.is_null() || friends.is_null() || user.is_null() {
+                SteamAPI_Shutdown();
+                return None;
+            }
+
+            let steam_id = SteamAPI_ISteamUser_GetSteamID(user);
+            tracing::info!(target: targets::ENGINE, "Connected to Steam.");
+            Some(Self {
+                user_stats,
+                friends,
+                steam_id,
+                pending: BTreeSet::new()
+            })
+        }
+    }
+}
+
+impl Platform for SteamPlatform {
+    fn name(&self) -> &'static str {
+        "steam"
+    }
+
+    fn unlock_achievement(&mut self, id: &str) {
+        let name = match CString::new(id) {
+            Ok(name) => name,
+            Err(_) => return
+        };
+        if unsafe { SteamAPI_ISteamUserStats_SetAchievement(self.user_stats, name.as_ptr()) } {
+            self.pending.insert(id.to_string());
+        } else {
+            tracing::warn!(target: targets::ENGINE, "Steam didn't accept achievement {}", id);
+        }
+    }
+
+    fn set_location(&mut self, location: &str) {
+        // Shown through the "#Status_Field" localization token set up in Steamworks.
+        let (Ok(key), Ok(value)) = (CString::new("field"), CString::new(location)) else {
+            return;
+        };
+        let display = CString::new("steam_display").unwrap();
+        let token = CString::new("#Status_Field").unwrap();
+        unsafe {
+            SteamAPI_ISteamFriends_SetRichPresence(self.friends, key.as_ptr(), value.as_ptr());
+            SteamAPI_ISteamFriends_SetRichPresence(self.friends, display.as_ptr(), token.as_ptr());
+        }
+    }
+
+    // Steam Cloud syncs the save directory, so keep each account's saves apart.
+    fn save_dir(&self) -> PathBuf {
+        default_save_dir().join(format!("steam_{}", self.steam_id))
+    }
+
+    fn update(&mut self) {
+        unsafe {
+            SteamAPI_RunCallbacks();
+        }
+
+        if !self.pending.is_empty() && unsafe { SteamAPI_ISteamUserStats_StoreStats(self.user_stats) } {
+            self.pending.clear();
+        }
+    }
+}
+
+impl Drop for SteamPlatform {
+    fn drop(&mut self) {
+        unsafe {
+            SteamAPI_Shutdown();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+// PlayStats as it's kept in a save.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayStatsState {
+    pub playtime_seconds: u64,
+    pub steps: u64,
+    pub battles_won: u32,
+    pub gil_earned: u64
+}
+
+// Resource keeping count of how the player has got on, for the status menu and save slots.
+// The systems that walk, fight and hand out gil add to the counts as they go.
+#[derive(Clone, Debug, Default)]
+pub struct PlayStats {
+    // Time played before the clock last started.
+    playtime: Duration,
+    // When the clock started, or None while the game is paused.
+    running_since: Option<Instant>,
+    pub steps: u64,
+    pub battles_won: u32,
+    pub gil_earned: u64
+}
+
+impl PlayStats {
+    // A new game, with the clock running.
+    pub fn new() -> Self {
+        Self {
+            running_since: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    // Stats loaded from a save, with the clock running.
+    pub fn restore(state: &PlayStatsState) -> Self {
+        Self {
+            playtime: Duration::from_secs(state.playtime_seconds),
+            running_since: Some(Instant::now()),
+            steps: state.steps,
+            battles_won: state.battles_won,
+            gil_earned: state.gil_earned
+        }
+    }
+
+    pub fn save_state(&self) -> PlayStatsState {
+        PlayStatsState {
+            playtime_seconds: self.playtime().as_secs(),
+            steps: self.steps,
+            battles_won: self.battles_won,
+            gil_earned: self.gil_earned
+        }
+    }
+
+    // Time played, not counting time paused.
+    pub fn playtime(&self) -> Duration {
+        self.playtime + self.running_since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.running_since.is_none()
+    }
+
+    // Stop or start the clock, e.g. while the window is in the background. Does nothing if
+    // it's already that way.
+    pub fn set_paused(&mut self, paused: bool) {
+        match (paused, self.running_since) {
+            (true, Some(since)) => {
+                self.playtime += since.elapsed();
+                self.running_since = None;
+            },
+            (false, None) => self.running_since = Some(Instant::now()),
+            _ => {}
+        }
+    }
+
+    pub fn add_steps(&mut self, steps: u64) {
+        self.steps += steps;
+    }
+
+    pub fn record_battle_won(&mut self) {
+        self.battles_won += 1;
+    }
+
+    pub fn add_gil(&mut self, gil: u64) {
+        self.gil_earned += gil;
+    }
+}
+
+// "12:05:09", hours then minutes then seconds.
+pub fn format_playtime(playtime: Duration) -> String {
+    let seconds = playtime.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
@@ -0,0 +1,304 @@
+// Walking the player about with the arrow keys or WASD. Fields' cameras point all sorts of
+// ways, so by default up goes away from the camera, whichever way that is in the world, and
+// the config can switch it to going the same way in the world whatever the camera's doing.
+// 2D fields are always up the screen.
+//
+// Sticks walk when they're pushed a little and run when they're pushed most of the way, and
+// walking gets slower the less they're pushed. With the keys, shift runs, or walks instead if
+// the config has the player always running. Whatever clip the player's playing is sped up or
+// slowed down to keep their feet in time with how fast they're going. Nothing reads gamepads
+// yet, so the stick's only set by whatever calls set_stick.
+//
+// When the camera cuts to another angle mid-walk, the way the keys go is kept as it was until
+// they're let go or changed, so the player doesn't suddenly turn round. Slower camera moves are
+// followed as they happen.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use cgmath::{Deg, InnerSpace, Quaternion, Rad, Rotation3, Vector2, Vector3};
+use winit::event::VirtualKeyCode;
+
+use crate::animation::AnimationPlayer;
+use crate::camera::Camera;
+use crate::field;
+use crate::tilemap::Tilemap;
+use crate::transform::Transform;
+use crate::water;
+use crate::world::{Entity, World};
+
+// How fast the player walks, in metres a second, and in pixels for 2D fields.
+pub const WALK_SPEED: f32 = 2.5;
+pub const WALK_SPEED_2D: f32 = 64.0;
+pub const RUN_SPEED: f32 = 5.0;
+pub const RUN_SPEED_2D: f32 = 128.0;
+// How far the stick has to be pushed to run, after the deadzone.
+pub const RUN_THRESHOLD: f32 = 0.7;
+// Sticks never sit quite in the middle, so pushes smaller than this don't count.
+pub const STICK_DEADZONE: f32 = 0.2;
+// How far the camera has to turn at once to count as a cut rather than a pan.
+pub const CUT_ANGLE: Deg<f32> = Deg(30.0);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MovementMode {
+    // Up goes away from the camera.
+    #[default]
+    Camera,
+    // Up goes along -z in the world.
+    World
+}
+
+impl MovementMode {
+    pub const ALL: [MovementMode; 2] = [MovementMode::Camera, MovementMode::World];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MovementMode::Camera => "camera",
+            MovementMode::World => "world"
+        }
+    }
+}
+
+impl fmt::Display for MovementMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MovementMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MovementMode::ALL.into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown movement mode \"{}\", expected camera or world", s.trim()))
+    }
+}
+
+// How the player's getting about.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Gait {
+    #[default]
+    Standing,
+    Walking,
+    Running
+}
+
+impl Gait {
+    // Also the clip that goes with it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gait::Standing => "idle",
+            Gait::Walking => "walk",
+            Gait::Running => "run"
+        }
+    }
+
+    // How fast the clip's made for, so it can be sped up or slowed down to match.
+    pub fn speed(&self, is_2d: bool) -> f32 {
+        match (self, is_2d) {
+            (Gait::Standing, _) => 0.0,
+            (Gait::Walking, false) => WALK_SPEED,
+            (Gait::Walking, true) => WALK_SPEED_2D,
+            (Gait::Running, false) => RUN_SPEED,
+            (Gait::Running, true) => RUN_SPEED_2D
+        }
+    }
+}
+
+impl fmt::Display for Gait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Which keys are held, where the stick is and which way they go in the world.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerController {
+    // Up, down, left and right.
+    held: [bool; 4],
+    run_held: bool,
+    // x right and y up, as it comes from the gamepad.
+    stick: [f32; 2],
+    gait: Gait,
+    // The way up went in the world before a camera cut, as an angle round y, kept until the
+    // keys are let go or changed.
+    kept_heading: Option<Rad<f32>>,
+    // What heading the camera had last time, to spot cuts.
+    camera_heading: Option<Rad<f32>>
+}
+
+impl PlayerController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Press or let go of a key. Returns whether it's one for walking or running.
+    pub fn handle_key(&mut self, key: VirtualKeyCode, pressed: bool) -> bool {
+        let index = match key {
+            VirtualKeyCode::Up | VirtualKeyCode::W => 0,
+            VirtualKeyCode::Down | VirtualKeyCode::S => 1,
+            VirtualKeyCode::Left | VirtualKeyCode::A => 2,
+            VirtualKeyCode::Right | VirtualKeyCode::D => 3,
+            VirtualKeyCode::LShift | VirtualKeyCode::RShift => {
+                self.run_held = pressed;
+                return true;
+            },
+            _ => return false
+        };
+        let before = self.input();
+        self.held[index] = pressed;
+        // Changing which way's held picks up the camera's way again.
+        if self.input() != before {
+            self.kept_heading = None;
+        }
+        true
+    }
+
+    // Let go of everything, like when the window loses focus and won't hear the keys come up.
+    pub fn release_all(&mut self) {
+        self.held = [false; 4];
+        self.run_held = false;
+        self.stick = [0.0, 0.0];
+        self.kept_heading = None;
+    }
+
+    // Move the stick, x right and y up. Swinging it round far enough counts as changing which
+    // way's held, like with the keys.
+    pub fn set_stick(&mut self, stick: Vector2<f32>) {
+        let before = self.input();
+        self.stick = if stick.magnitude2() > 1.0 { stick.normalize() } else { stick }.into();
+        let after = self.input();
+        let swung = before.magnitude2() > 0.0 && after.magnitude2() > 0.0
+            && angle_between(Rad(before.x.atan2(before.y)), Rad(after.x.atan2(after.y))) > CUT_ANGLE.into();
+        if swung {
+            self.kept_heading = None;
+        }
+    }
+
+    // Whether the stick's pushed past the deadzone, which counts over the keys.
+    fn using_stick(&self) -> bool {
+        Vector2::from(self.stick).magnitude() > STICK_DEADZONE
+    }
+
+    // The stick past its deadzone, or the held keys, as x right and y up, at most 1 long.
+    pub fn input(&self) -> Vector2<f32> {
+        if self.using_stick() {
+            let stick = Vector2::from(self.stick);
+            let push = ((stick.magnitude() - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
+            return stick.normalize() * push;
+        }
+        let axis = |negative: bool, positive: bool| positive as i32 as f32 - negative as i32 as f32;
+        let input = Vector2::new(axis(self.held[2], self.held[3]), axis(self.held[1], self.held[0]));
+        if input.magnitude2() > 1.0 { input.normalize() } else { input }
+    }
+
+    // Whether they'd walk or run and how fast, before anything like water slows them down. The
+    // stick walks slower the less it's pushed, up to the run threshold. `always_run` swaps what
+    // shift does.
+    pub fn pace(&self, always_run: bool, is_2d: bool) -> (Gait, f32) {
+        let push = self.input().magnitude();
+        let gait = if push == 0.0 {
+            Gait::Standing
+        } else if self.using_stick() {
+            if push >= RUN_THRESHOLD { Gait::Running } else { Gait::Walking }
+        } else if self.run_held != always_run {
+            Gait::Running
+        } else {
+            Gait::Walking
+        };
+        let speed = match gait {
+            Gait::Walking if self.using_stick() => gait.speed(is_2d) * push / RUN_THRESHOLD,
+            _ => gait.speed(is_2d)
+        };
+        (gait, speed)
+    }
+
+    // How they got about last update.
+    pub fn gait(&self) -> Gait {
+        self.gait
+    }
+
+    // Which way to walk in the world, at most 1 long, going by the held keys and the camera.
+    pub fn direction(&mut self, camera: &Camera, mode: MovementMode, is_2d: bool) -> Vector3<f32> {
+        let camera_heading = match (mode, is_2d) {
+            (MovementMode::Camera, false) => heading_of(camera.target - camera.eye),
+            _ => None
+        }.unwrap_or(Rad(std::f32::consts::PI));
+
+        // Pans move the way the keys go with them, but cuts don't until the keys are let go.
+        let last_heading = self.camera_heading.replace(camera_heading);
+        let input = self.input();
+        if input.magnitude2() == 0.0 {
+            self.kept_heading = None;
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let before_cut = last_heading.filter(|last| angle_between(*last, camera_heading) > CUT_ANGLE.into());
+        if self.kept_heading.is_none() {
+            self.kept_heading = before_cut;
+        }
+        let heading = self.kept_heading.unwrap_or(camera_heading);
+
+        let forward = Vector3::new(heading.0.sin(), 0.0, heading.0.cos());
+        let right = Vector3::new(-forward.z, 0.0, forward.x);
+        forward * input.y + right * input.x
+    }
+
+    // Walk or run the player for a frame, turning them to face the way they're going and
+    // matching their clip's speed to how fast that is. They don't go into the field's blockers
+    // or a 2D field's solid tiles. Returns how far they went.
+    pub fn update(&mut self, world: &mut World, player: Entity, camera: &Camera, mode: MovementMode, always_run: bool, delta: Duration) -> f32 {
+        let is_2d = world.resource::<Tilemap>().is_some();
+        let direction = self.direction(camera, mode, is_2d);
+        let (gait, speed) = self.pace(always_run, is_2d);
+        let speed = speed * water::speed_scale(world, player);
+        let moved = step(world, player, direction, speed * delta.as_secs_f32());
+        self.gait = if moved > 0.0 { gait } else { Gait::Standing };
+
+        // A clip made for walking at 2.5 m/s plays at half speed when they're going 1.25 m/s.
+        // Standing plays at its own speed.
+        if let Some(animation) = world.get_mut::<AnimationPlayer>(player) {
+            animation.speed = match self.gait {
+                Gait::Standing => 1.0,
+                gait => speed / gait.speed(is_2d)
+            };
+        }
+        moved
+    }
+}
+
+// Move the player `distance` along `direction`, unless something's in the way. Returns how far
+// they went.
+fn step(world: &mut World, player: Entity, direction: Vector3<f32>, distance: f32) -> f32 {
+    if direction.magnitude2() == 0.0 || distance <= 0.0 {
+        return 0.0;
+    }
+    let map = world.resource::<Tilemap>();
+    let position = match world.get::<Transform>(player) {
+        Some(transform) => transform.position,
+        None => return 0.0
+    };
+    let to = position + direction.normalize() * distance;
+    let blocked = map.is_some_and(|map| map.is_solid_at(to.x, to.z))
+        || field::blockers(world).iter().any(|blocker| blocker.contains(to.x, to.z));
+
+    let transform = match world.get_mut::<Transform>(player) {
+        Some(transform) => transform,
+        None => return 0.0
+    };
+    transform.rotation = Quaternion::from_angle_y(Rad(direction.x.atan2(direction.z)));
+    if blocked {
+        return 0.0;
+    }
+    transform.position = to;
+    (to - position).magnitude()
+}
+
+// The angle round y a direction's pointing, None if it's straight up or down.
+fn heading_of(direction: Vector3<f32>) -> Option<Rad<f32>> {
+    (direction.x.abs() > f32::EPSILON || direction.z.abs() > f32::EPSILON).then(|| Rad(direction.x.atan2(direction.z)))
+}
+
+fn angle_between(a: Rad<f32>, b: Rad<f32>) -> Rad<f32> {
+    let difference = (b.0 - a.0).rem_euclid(std::f32::consts::TAU);
+    Rad(difference.min(std::f32::consts::TAU - difference))
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use instant::Instant;
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::{ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent}};
+
+use crate::renderer::{letterbox_viewport, window_to_screen, SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// A touch that lets go within this long without moving far is a tap.
+const TAP_TIME: Duration = Duration::from_millis(500);
+// In virtual screen pixels.
+const TAP_DISTANCE: f32 = 12.0;
+// How far a swipe or a touchpad scroll goes for one step, in virtual screen pixels.
+const SCROLL_STEP: f32 = 48.0;
+
+// Something the mouse or a finger did, for menus. Positions are in virtual screen pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerEvent {
+    // The mouse moved over the screen.
+    Hover { x: f32, y: f32 },
+    // A left click or a tap.
+    Click { x: f32, y: f32 },
+    // Scrolled with the wheel or swiped. Positive steps go down a list.
+    Scroll { steps: i32 },
+    // A right click, like pressing escape.
+    Cancel
+}
+
+impl PointerEvent {
+    // The same event for a menu that's been flipped left to right, see UiBatch::set_mirrored.
+    pub fn mirrored(self) -> Self {
+        match self {
+            PointerEvent::Hover { x, y } => PointerEvent::Hover { x: SCREEN_WIDTH as f32 - x, y },
+            PointerEvent::Click { x, y } => PointerEvent::Click { x: SCREEN_WIDTH as f32 - x, y },
+            other => other
+        }
+    }
+}
+
+// What a menu did with a PointerEvent: what was picked, if anything, and whether the menu
+// changed, so it needs drawing again. Most of the mouse moving about changes nothing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerResponse<A> {
+    pub action: Option<A>,
+    pub changed: bool
+}
+
+impl<A> PointerResponse<A> {
+    // Picking something always counts as a change.
+    pub fn new(action: Option<A>, changed: bool) -> Self {
+        let changed = changed || action.is_some();
+        Self { action, changed }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ActiveTouch {
+    id: u64,
+    started: Instant,
+    start: (f32, f32),
+    last: (f32, f32),
+    // Moved too far to be a tap, so it's scrolling.
+    swiping: bool
+}
+
+// Turns mouse and touch window events into PointerEvents, hit tested against the letterboxed
+// virtual screen. Clicks and taps in the black bars are ignored. Only one finger is followed
+// at a time.
+#[derive(Clone, Debug, Default)]
+pub struct Pointer {
+    mouse: Option<(f32, f32)>,
+    touch: Option<ActiveTouch>,
+    // Scrolling that hasn't added up to a whole step yet.
+    scroll: f32
+}
+
+impl Pointer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, window_size: PhysicalSize<u32>, event: &WindowEvent) -> Option<PointerEvent> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse = window_to_screen(window_size, *position);
+                self.mouse.map(|(x, y)| PointerEvent::Hover { x, y })
+            },
+            WindowEvent::CursorLeft { .. } => {
+                self.mouse = None;
+                None
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } =>
+                self.mouse.map(|(x, y)| PointerEvent::Click { x, y }),
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Right, .. } => Some(PointerEvent::Cancel),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    // Wheel up scrolls up the list.
+                    MouseScrollDelta::LineDelta(_, lines) => -lines,
+                    MouseScrollDelta::PixelDelta(pixels) => -(pixels.y as f32) * screen_scale(window_size) / SCROLL_STEP
+                };
+                self.scroll(steps)
+            },
+            WindowEvent::Touch(touch) => self.handle_touch(window_size, touch),
+            _ => None
+        }
+    }
+
+    fn handle_touch(&mut self, window_size: PhysicalSize<u32>, touch: &Touch) -> Option<PointerEvent> {
+        // Fingers in the black bars still swipe, so follow them off the screen.
+        let position = unclamped_window_to_screen(window_size, touch.location);
+        match touch.phase {
+            TouchPhase::Started => {
+                if self.touch.is_none() && window_to_screen(window_size, touch.location).is_some() {
+                    self.touch = Some(ActiveTouch {
+                        id: touch.id,
+                        started: Instant::now(),
+                        start: position,
+                        last: position,
+                        swiping: false
+                    });
+                    self.scroll = 0.0;
+                }
+                None
+            },
+            TouchPhase::Moved => {
+                let active = self.touch.as_mut().filter(|active| active.id == touch.id)?;
+                let moved = (position.0 - active.start.0).hypot(position.1 - active.start.1);
+                active.swiping |= moved > TAP_DISTANCE;
+                // Dragging up moves down the list, like scrolling a page.
+                let dragged = position.1 - active.last.1;
+                active.last = position;
+                if active.swiping {
+                    self.scroll(-dragged / SCROLL_STEP)
+                } else {
+                    None
+                }
+            },
+            TouchPhase::Ended => {
+                let active = self.touch.filter(|active| active.id == touch.id)?;
+                self.touch = None;
+                let tapped = !active.swiping && active.started.elapsed() <= TAP_TIME;
+                tapped.then_some(PointerEvent::Click { x: active.start.0, y: active.start.1 })
+            },
+            TouchPhase::Cancelled => {
+                if self.touch.is_some_and(|active| active.id == touch.id) {
+                    self.touch = None;
+                }
+                None
+            }
+        }
+    }
+
+    // Add up scrolling, giving back however many whole steps that makes.
+    fn scroll(&mut self, steps: f32) -> Option<PointerEvent> {
+        self.scroll += steps;
+        let whole = self.scroll.trunc();
+        self.scroll -= whole;
+        (whole != 0.0).then_some(PointerEvent::Scroll { steps: whole as i32 })
+    }
+}
+
+// Virtual screen pixels per window pixel.
+fn screen_scale(window_size: PhysicalSize<u32>) -> f32 {
+    let (_, _, _, height) = letterbox_viewport(window_size);
+    SCREEN_HEIGHT as f32 / height
+}
+
+fn unclamped_window_to_screen(window_size: PhysicalSize<u32>, position: PhysicalPosition<f64>) -> (f32, f32) {
+    let (x, y, _, _) = letterbox_viewport(window_size);
+    let scale = screen_scale(window_size);
+    ((position.x as f32 - x) * scale, (position.y as f32 - y) * scale)
+}
@@ -0,0 +1,303 @@
+// What skills cost besides MP: other pools, like TP that builds up as someone takes damage,
+// and item components, used up from the inventory. Which pools a game has, and how they fill,
+// are in data/pools.cfg, each under an [id] header:
+//
+//   [tp]
+//   name = TP
+//   max = 100
+//   start = 0
+//   per_turn = 5
+//   on_damage = 1
+//   after_battle = empty
+//
+// max is the most it holds, and start what everyone has when a battle starts. per_turn is added
+// at the end of each of their turns, and on_damage for each percent of their max HP they lose.
+// after_battle is keep, empty or full. MP isn't in there: everyone always has it, in their
+// stats, and it only comes back from items and resting.
+//
+// Skills cost their MP plus anything in their costs, written "tp 50" for 50 from the TP pool or
+// "item magic_gem 2" for two of an item, and the same one twice costs both. Whatever uses a
+// skill checks can_afford, then pays.
+
+use std::{fmt, str::FromStr};
+
+use crate::inventory::Inventory;
+use crate::party::{PartyMember, Skill};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SkillCost {
+    Pool { pool: String, amount: u32 },
+    Item { item: String, count: u32 }
+}
+
+impl fmt::Display for SkillCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkillCost::Pool { pool, amount } => write!(f, "{} {}", pool, amount),
+            SkillCost::Item { item, count } => write!(f, "item {} {}", item, count)
+        }
+    }
+}
+
+impl FromStr for SkillCost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let number = |value: &str| value.parse().map_err(|_| format!("Bad amount \"{}\"", value));
+        match words.as_slice() {
+            ["item", item] => Ok(SkillCost::Item { item: item.to_string(), count: 1 }),
+            ["item", item, count] => Ok(SkillCost::Item { item: item.to_string(), count: number(count)? }),
+            [pool, amount] => Ok(SkillCost::Pool { pool: pool.to_lowercase(), amount: number(amount)? }),
+            _ => Err(format!("Expected \"pool amount\" or \"item id [count]\", not \"{}\"", s.trim()))
+        }
+    }
+}
+
+// Whether someone could use a skill now, or why not.
+pub fn can_afford(member: &PartyMember, skill: &Skill, inventory: &Inventory) -> Result<(), String> {
+    if member.stats.mp < skill.mp_cost {
+        return Err(format!("{} doesn't have enough MP", member.name));
+    }
+    for cost in &combined(skill) {
+        match cost {
+            SkillCost::Pool { pool, amount } if member.pool(pool) < *amount => {
+                return Err(format!("{} doesn't have enough {}", member.name, pool.to_uppercase()));
+            },
+            SkillCost::Item { item, count } if inventory.count(item) < *count => {
+                return Err(format!("There aren't enough {} for {}", item, skill.name));
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Take what a skill costs, if it can be used. Nothing's taken if it can't.
+pub fn pay(member: &mut PartyMember, skill: &Skill, inventory: &mut Inventory) -> Result<(), String> {
+    can_afford(member, skill, inventory)?;
+    member.stats.mp -= skill.mp_cost;
+    for cost in &combined(skill) {
+        match cost {
+            SkillCost::Pool { pool, amount } => {
+                let left = member.pool(pool).saturating_sub(*amount);
+                member.pools.insert(pool.clone(), left);
+            },
+            SkillCost::Item { item, count } => {
+                inventory.remove(item, *count);
+            }
+        }
+    }
+    Ok(())
+}
+
+// How many more times someone could use a skill with what they've got, or None if it doesn't
+// cost anything.
+pub fn uses_left(member: &PartyMember, skill: &Skill, inventory: &Inventory) -> Option<u32> {
+    let mut have = vec![(member.stats.mp, skill.mp_cost)];
+    for cost in &combined(skill) {
+        have.push(match cost {
+            SkillCost::Pool { pool, amount } => (member.pool(pool), *amount),
+            SkillCost::Item { item, count } => (inventory.count(item), *count)
+        });
+    }
+    have.into_iter().filter(|(_, cost)| *cost > 0).map(|(have, cost)| have / cost).min()
+}
+
+// A skill's costs with the same pool or item listed more than once added together, so "tp 30"
+// twice is checked and paid as "tp 60".
+fn combined(skill: &Skill) -> Vec<SkillCost> {
+    let mut costs: Vec<SkillCost> = Vec::new();
+    for cost in &skill.costs {
+        let same = costs.iter_mut().find_map(|other| match (other, cost) {
+            (SkillCost::Pool { pool, amount }, SkillCost::Pool { pool: other_pool, amount: more }) if pool == other_pool => Some((amount, *more)),
+            (SkillCost::Item { item, count }, SkillCost::Item { item: other_item, count: more }) if item == other_item => Some((count, *more)),
+            _ => None
+        });
+        match same {
+            Some((total, more)) => *total = total.saturating_add(more),
+            None => costs.push(cost.clone())
+        }
+    }
+    costs
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AfterBattle {
+    Keep,
+    Empty,
+    Full
+}
+
+impl AfterBattle {
+    pub const ALL: [AfterBattle; 3] = [AfterBattle::Keep, AfterBattle::Empty, AfterBattle::Full];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AfterBattle::Keep => "keep",
+            AfterBattle::Empty => "empty",
+            AfterBattle::Full => "full"
+        }
+    }
+}
+
+impl fmt::Display for AfterBattle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AfterBattle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AfterBattle::ALL.into_iter()
+            .find(|after| after.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown after_battle \"{}\", expected keep, empty or full", s.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolDefinition {
+    pub id: String,
+    // What it's shown as, like "TP".
+    pub name: String,
+    pub max: u32,
+    // What everyone has when a battle starts, or None to carry on from the last.
+    pub start: Option<u32>,
+    pub per_turn: u32,
+    // Added for each percent of max HP lost.
+    pub on_damage: f32,
+    pub after_battle: AfterBattle
+}
+
+impl PoolDefinition {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            max: 100,
+            start: None,
+            per_turn: 0,
+            on_damage: 0.0,
+            after_battle: AfterBattle::Keep
+        }
+    }
+
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut pools: Vec<PoolDefinition> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let id = id.trim().to_lowercase();
+                if id == "mp" {
+                    return Err(format!("Line {}: MP is in everyone's stats, it isn't a pool", number + 1));
+                }
+                if pools.iter().any(|pool| pool.id == id) {
+                    return Err(format!("Line {}: there's already a pool called {}", number + 1, id));
+                }
+                pools.push(PoolDefinition::new(&id));
+                continue;
+            }
+
+            let pool = pools.last_mut().ok_or_else(|| format!("Line {}: expected a [pool] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            let bad = || format!("Line {}: bad {} \"{}\"", number + 1, key, value);
+            match key {
+                "name" => pool.name = value.to_string(),
+                "max" => pool.max = value.parse().map_err(|_| bad())?,
+                "start" => pool.start = Some(value.parse().map_err(|_| bad())?),
+                "per_turn" => pool.per_turn = value.parse().map_err(|_| bad())?,
+                "on_damage" => pool.on_damage = value.parse().ok().filter(|value: &f32| *value >= 0.0).ok_or_else(bad)?,
+                "after_battle" => pool.after_battle = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?,
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        Ok(pools)
+    }
+
+    fn add(&self, member: &mut PartyMember, amount: u32) {
+        let value = member.pool(&self.id).saturating_add(amount).min(self.max);
+        member.pools.insert(self.id.clone(), value);
+    }
+}
+
+// Resource with every pool the game has, besides MP.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pools(pub Vec<PoolDefinition>);
+
+impl Pools {
+    pub fn get(&self, id: &str) -> Option<&PoolDefinition> {
+        self.0.iter().find(|pool| pool.id.eq_ignore_ascii_case(id))
+    }
+
+    pub fn start_battle(&self, member: &mut PartyMember) {
+        for pool in &self.0 {
+            if let Some(start) = pool.start {
+                member.pools.insert(pool.id.clone(), start.min(pool.max));
+            }
+        }
+    }
+
+    // At the end of each of their turns.
+    pub fn end_turn(&self, member: &mut PartyMember) {
+        for pool in &self.0 {
+            pool.add(member, pool.per_turn);
+        }
+    }
+
+    // They've lost this much HP.
+    pub fn take_damage(&self, member: &mut PartyMember, damage: u32) {
+        let percent = damage as f32 * 100.0 / member.stats.max_hp.max(1) as f32;
+        for pool in &self.0 {
+            pool.add(member, (percent * pool.on_damage).round() as u32);
+        }
+    }
+
+    pub fn end_battle(&self, member: &mut PartyMember) {
+        for pool in &self.0 {
+            match pool.after_battle {
+                AfterBattle::Keep => {},
+                AfterBattle::Empty => {
+                    member.pools.insert(pool.id.clone(), 0);
+                },
+                AfterBattle::Full => {
+                    member.pools.insert(pool.id.clone(), pool.max);
+                }
+            }
+        }
+    }
+
+    // Each pool someone has, like "TP 35/100", for HUDs.
+    pub fn labels(&self, member: &PartyMember) -> Vec<String> {
+        self.0.iter().map(|pool| format!("{} {}/{}", pool.name, member.pool(&pool.id), pool.max)).collect()
+    }
+
+    // What a skill costs, like "8 MP" or "50 TP, 1 Magic Gem". Items are named as they are in
+    // the inventory, if there are any.
+    pub fn cost_label(&self, skill: &Skill, inventory: &Inventory) -> String {
+        let mut costs = Vec::new();
+        if skill.mp_cost > 0 || skill.costs.is_empty() {
+            costs.push(format!("{} MP", skill.mp_cost));
+        }
+        for cost in &skill.costs {
+            costs.push(match cost {
+                SkillCost::Pool { pool, amount } => {
+                    let name = self.get(pool).map(|pool| pool.name.clone()).unwrap_or_else(|| pool.to_uppercase());
+                    format!("{} {}", amount, name)
+                },
+                SkillCost::Item { item, count } => {
+                    let name = inventory.slots().iter().find(|slot| slot.item.id == *item).map(|slot| slot.item.name.as_str()).unwrap_or(item);
+                    format!("{} {}", count, name)
+                }
+            });
+        }
+        costs.join(", ")
+    }
+}
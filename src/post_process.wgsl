@@ -20,13 +20,186 @@ fn vs_main(
 }
 
 // Fragment shader
+struct PostProcessUniforms {
+    // rgb is the tint colour, a is how strongly to apply it.
+    tint: vec4<f32>,
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    vignette: f32,
+    // rgb is the colour to fade to, a is how far.
+    fade: vec4<f32>,
+    // xy moves the picture, for screen shake.
+    offset: vec4<f32>,
+    // The colour blindness filter, identity when it's off.
+    color_matrix: mat3x3<f32>,
+    // Focus distance, focus range, falloff and the biggest blur in pixels.
+    dof: vec4<f32>,
+    // x is how many samples to blur with, none when depth of field is off. y and z are the
+    // camera's near and far planes.
+    dof_params: vec4<f32>,
+    // xy is the centre of the light in dark dungeons and z its radius, in virtual screen pixels.
+    // w is how dark it is outside, none when it's not dark.
+    light: vec4<f32>,
+    // x is which transition, none for the screen as it is, 1 swirl, 2 pixelate and 3 shatter.
+    // y is how far it's gone.
+    transition: vec4<f32>,
+};
+
+// How much of the light's radius is fully lit, before it starts fading into the dark.
+let LIGHT_SOFTNESS: f32 = 0.6;
+
+// How many turns the middle of a swirl has made by the end.
+let SWIRL_TURNS: f32 = 2.0;
+// The biggest pixels pixelating gets to, in virtual screen pixels.
+let PIXELATE_SIZE: f32 = 64.0;
+// How big shattered pieces are, in virtual screen pixels.
+let SHARD_SIZE: f32 = 80.0;
+
 @group(0) @binding(0)
 var t_diffuse: texture_2d<f32>;
 
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+@group(0) @binding(2)
+var<uniform> settings: PostProcessUniforms;
+
+// The models' depth buffer, with the background cleared to the far plane. Bound as a plain
+// float texture, as GLSL treats depth textures as shadow samplers that can only compare.
+@group(1) @binding(0)
+var t_depth: texture_2d<f32>;
+
+// The frame kept for transitions.
+@group(0) @binding(3)
+var t_transition: texture_2d<f32>;
+
+// A number from 0 to 1 that's the same for the same cell, for shards breaking off at random.
+fn hash(cell: vec2<f32>) -> f32 {
+    return fract(sin(dot(cell, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// The kept frame at uv, twisted, pixelated or shattered as far as the transition's gone, and
+// going dark towards the end.
+fn transition(uv: vec2<f32>) -> vec3<f32> {
+    let style = i32(settings.transition.x);
+    let progress = settings.transition.y;
+    let size = vec2<f32>(textureDimensions(t_transition));
+    var sample_uv = uv;
+    var visible = 1.0;
+
+    if (style == 1) {
+        // Turn round the middle, more so nearer it. Screen pixels rather than uvs, so it's round.
+        let from_center = (uv - vec2<f32>(0.5)) * size;
+        let falloff = 1.0 - clamp(length(from_center) / length(size * 0.5), 0.0, 1.0);
+        let angle = progress * progress * SWIRL_TURNS * 6.28318 * falloff * falloff;
+        let turned = vec2<f32>(
+            from_center.x * cos(angle) - from_center.y * sin(angle),
+            from_center.x * sin(angle) + from_center.y * cos(angle)
+        );
+        sample_uv = turned / size + vec2<f32>(0.5);
+    } else if (style == 2) {
+        let block = mix(1.0, PIXELATE_SIZE, progress * progress);
+        sample_uv = (floor(uv * size / block) + 0.5) * block / size;
+    } else if (style == 3) {
+        // Each piece shrinks into its middle, starting at a different time.
+        let pixel = uv * size;
+        let cell = floor(pixel / SHARD_SIZE);
+        let delay = hash(cell) * 0.5;
+        let scale = 1.0 - clamp((progress - delay) / 0.5, 0.0, 1.0);
+        let local = (pixel / SHARD_SIZE - cell - vec2<f32>(0.5)) / max(scale, 0.0001);
+        visible = select(0.0, 1.0, scale > 0.0 && abs(local.x) <= 0.5 && abs(local.y) <= 0.5);
+        sample_uv = (cell + vec2<f32>(0.5) + local) * SHARD_SIZE / size;
+    }
+
+    let color = textureSampleLevel(t_transition, s_diffuse, sample_uv, 0.0).rgb;
+    return color * visible * (1.0 - smoothstep(0.7, 1.0, progress));
+}
+
+// How far from the camera what's at uv is, along the way it's looking.
+fn view_distance(uv: vec2<f32>) -> f32 {
+    let near = settings.dof_params.y;
+    let far = settings.dof_params.z;
+    let size = textureDimensions(t_depth);
+    let pixel = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - vec2<i32>(1));
+    let depth = textureLoad(t_depth, pixel, 0).r;
+    return near * far / (far - depth * (far - near));
+}
+
+// Same as DepthOfField::blur_radius.
+fn blur_radius(uv: vec2<f32>) -> f32 {
+    let out_of_focus = abs(view_distance(uv) - settings.dof.x) - settings.dof.y;
+    return clamp(out_of_focus / max(settings.dof.z, 0.0001), 0.0, 1.0) * settings.dof.w;
+}
+
+// Average a disc of pixels around uv as big as its blur. Samples spiral out at the golden
+// angle so they spread evenly whatever the count. Each only counts if its own blur reaches
+// this far, so sharp things in front don't smear over the blurry backdrop around them.
+fn depth_of_field(uv: vec2<f32>, sharp: vec3<f32>) -> vec3<f32> {
+    let size = vec2<f32>(textureDimensions(t_diffuse));
+    let radius = blur_radius(uv);
+    if (radius < 0.5) {
+        return sharp;
+    }
+
+    let samples = i32(settings.dof_params.x);
+    var total = sharp;
+    var weight = 1.0;
+    for (var i = 0; i < samples; i = i + 1) {
+        let angle = f32(i) * 2.39996;
+        let offset_radius = sqrt((f32(i) + 0.5) / f32(samples)) * radius;
+        let offset = vec2<f32>(cos(angle), sin(angle)) * offset_radius;
+        let sample_uv = uv + offset / size;
+        let sample_weight = clamp(blur_radius(sample_uv) - offset_radius + 1.0, 0.0, 1.0);
+        total = total + textureSampleLevel(t_diffuse, s_diffuse, sample_uv, 0.0).rgb * sample_weight;
+        weight = weight + sample_weight;
+    }
+    return total / weight;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.uv);
-}
\ No newline at end of file
+    let uv = in.uv - settings.offset.xy;
+    let sample = textureSample(t_diffuse, s_diffuse, uv);
+    var color = sample.rgb;
+
+    // Blur what's out of focus before any colour grading.
+    if (settings.dof_params.x > 0.0) {
+        color = depth_of_field(uv, color);
+    }
+
+    // A transition replaces the frame with the kept one, still graded like the rest.
+    if (settings.transition.x > 0.0) {
+        color = transition(uv);
+    }
+
+    // Brightness and contrast around mid grey.
+    color = (color - 0.5) * settings.contrast + 0.5 + settings.brightness;
+
+    // Saturation.
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    color = mix(vec3<f32>(luminance), color, settings.saturation);
+
+    // Tint.
+    color = mix(color, color * settings.tint.rgb, settings.tint.a);
+
+    // Darken towards the edges of the screen.
+    let from_center = in.uv - vec2<f32>(0.5);
+    let vignette = 1.0 - settings.vignette * dot(from_center, from_center) * 2.0;
+    color = color * clamp(vignette, 0.0, 1.0);
+
+    // Darkness outside the light round the player. It follows the screen, not the shake.
+    if (settings.light.w > 0.0) {
+        let from_light = length(in.uv * vec2<f32>(textureDimensions(t_diffuse)) - settings.light.xy);
+        let dark = smoothstep(settings.light.z * LIGHT_SOFTNESS, max(settings.light.z, 0.0001), from_light);
+        color = color * (1.0 - settings.light.w * dark);
+    }
+
+    // Fade out, or flash.
+    color = mix(color, settings.fade.rgb, settings.fade.a);
+
+    // Colour blindness filter.
+    color = settings.color_matrix * clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), sample.a);
+}
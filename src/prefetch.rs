@@ -0,0 +1,131 @@
+use std::{collections::HashSet, sync::mpsc, thread};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::field::{FieldMap, PrefetchedAssets};
+use crate::logging::targets;
+use crate::model::ModelData;
+use crate::tilemap::Tilemap;
+
+enum Prefetched {
+    Image(image::RgbaImage),
+    Model(ModelData),
+    Tilemap(Tilemap)
+}
+
+// Loads the images, models and maps of the fields next to the current one in the background,
+// so they're ready by the time the player goes through a door. Everything is loaded one at a
+// time on a thread of its own, to stay out of the way of anything the game needs right now.
+pub struct FieldPrefetcher {
+    requests: mpsc::Sender<String>,
+    results: mpsc::Receiver<(String, Result<Prefetched, AssetError>)>,
+    // Asked for and still wanted, whether it's arrived or not.
+    wanted: HashSet<String>,
+    // Asked for and not back yet.
+    pending: HashSet<String>,
+    ready: PrefetchedAssets
+}
+
+impl FieldPrefetcher {
+    pub fn new(assets: &AssetServer) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<String>();
+        let (result_sender, results) = mpsc::channel();
+        let assets = assets.clone();
+        let spawned = thread::Builder::new().name("Field prefetch".to_string()).spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    tracing::error!(target: targets::ASSETS, "Couldn't start prefetching: {}", e);
+                    return;
+                }
+            };
+            // Stops when the prefetcher is dropped.
+            for path in request_receiver {
+                let result = runtime.block_on(load(&assets, &path));
+                if result_sender.send((path, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        if let Err(e) = spawned {
+            tracing::error!(target: targets::ASSETS, "Couldn't start prefetching: {}", e);
+        }
+
+        Self {
+            requests,
+            results,
+            wanted: HashSet::new(),
+            pending: HashSet::new(),
+            ready: PrefetchedAssets::new()
+        }
+    }
+
+    // Start loading everything the fields next to `current` need, and forget anything that was
+    // loaded for fields that aren't next to it any more. Call after `current` has been set up,
+    // so none of what it took from assets() is thrown away first.
+    pub fn prefetch_neighbours(&mut self, fields: &FieldMap, current: &str) {
+        // The current field's already loaded.
+        let loaded: HashSet<&str> = fields.get(current).map(|field| field.asset_paths().into_iter().collect()).unwrap_or_default();
+        let mut wanted = HashSet::new();
+        for neighbour in fields.neighbours(current) {
+            let field = match fields.get(neighbour) {
+                Some(field) => field,
+                None => continue
+            };
+            for path in field.asset_paths() {
+                if !loaded.contains(path) {
+                    wanted.insert(path.to_string());
+                }
+            }
+        }
+
+        for path in wanted.difference(&self.wanted) {
+            if self.pending.contains(path) {
+                continue;
+            }
+            tracing::debug!(target: targets::ASSETS, "Prefetching {}", path);
+            // If the thread's gone the field just loads them itself later.
+            if self.requests.send(path.clone()).is_ok() {
+                self.pending.insert(path.clone());
+            }
+        }
+        self.ready.retain(|path| wanted.contains(path));
+        self.wanted = wanted;
+    }
+
+    // Pick up whatever's finished loading. Call once a frame.
+    pub fn update(&mut self) {
+        while let Ok((path, result)) = self.results.try_recv() {
+            self.pending.remove(&path);
+            if !self.wanted.contains(&path) {
+                continue;
+            }
+            match result {
+                Ok(Prefetched::Image(image)) => self.ready.insert_image(&path, image),
+                Ok(Prefetched::Model(model)) => self.ready.insert_model(&path, model),
+                Ok(Prefetched::Tilemap(map)) => self.ready.insert_tilemap(&path, map),
+                // It'll be tried again, and the error shown, when the field loads it.
+                Err(e) => tracing::debug!(target: targets::ASSETS, "Couldn't prefetch {}: {}", path, e)
+            }
+        }
+    }
+
+    // What's been loaded so far, for the next field to take from.
+    pub fn assets(&mut self) -> &mut PrefetchedAssets {
+        &mut self.ready
+    }
+
+    // Whether everything asked for has been loaded, or failed to.
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+async fn load(assets: &AssetServer, path: &str) -> Result<Prefetched, AssetError> {
+    if path.ends_with(".gltf") || path.ends_with(".glb") {
+        ModelData::load(assets, path).await.map(Prefetched::Model)
+    } else if path.ends_with(".tmx") || path.ends_with(".tmj") {
+        Tilemap::load(assets, path).await.map(Prefetched::Tilemap)
+    } else {
+        assets.load_image(path).await.map(Prefetched::Image)
+    }
+}
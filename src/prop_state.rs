@@ -0,0 +1,105 @@
+// Props that look different and get in the way differently as the story goes on, like a bridge
+// that's whole until it's been blown up or a gate that's shut until the guard's been talked
+// to. Each state says what value of the prop's flag it's for, the model it shows, or none, the
+// tile layers it shows in a 2D field and the blockers it puts in the way. Props go to the state
+// for their flag's value whenever it changes, so a script setting the flag changes the prop
+// there and then. Values without a state of their own get the first one.
+
+use crate::flags::GameFlags;
+use crate::model::{ModelId, ModelInstance};
+use crate::tilemap::Tilemap;
+use crate::vision::VisionBlocker;
+use crate::world::{Entity, World};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PropState {
+    // Like "intact" or "broken", for the console and inspector.
+    pub name: String,
+    // The flag's value for this state.
+    pub value: i32,
+    // Empty for nothing to draw, like a bridge that's fallen away.
+    pub model: String,
+    // Tile layers that are only shown in this state, in a 2D field.
+    pub layers: Vec<String>,
+    // What's in the way in this state, like the gap where the bridge was.
+    pub blockers: Vec<VisionBlocker>
+}
+
+// Component for a prop with states, and which it's in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropStates {
+    pub flag: String,
+    states: Vec<PropState>,
+    // Each state's model, once loaded. None for states without one, or whose didn't load.
+    models: Vec<Option<ModelId>>,
+    current: Option<usize>
+}
+
+impl PropStates {
+    pub fn new(flag: &str, states: Vec<PropState>, models: Vec<Option<ModelId>>) -> Self {
+        Self { flag: flag.to_string(), states, models, current: None }
+    }
+
+    pub fn states(&self) -> &[PropState] {
+        &self.states
+    }
+
+    // Every state's model, for freeing them when the field goes.
+    pub fn models(&self) -> impl Iterator<Item = ModelId> + '_ {
+        self.models.iter().flatten().copied()
+    }
+
+    // The state it's in, None until it's first updated.
+    pub fn current(&self) -> Option<&PropState> {
+        self.states.get(self.current?)
+    }
+
+    // The state for a value of the flag.
+    pub fn state_for(&self, value: i32) -> usize {
+        self.states.iter().position(|state| state.value == value).unwrap_or(0)
+    }
+}
+
+// Put every prop in the state for its flag, changing its model and the map's tile layers if it
+// isn't already. Returns true if anything changed, in which case a 2D field's tiles need
+// handing to the renderer again.
+pub fn update_prop_states(world: &mut World) -> bool {
+    let props: Vec<Entity> = world.query::<PropStates>().map(|(entity, _)| entity).collect();
+    let mut layers = Vec::new();
+    let mut changed = false;
+    for entity in props {
+        let value = match (world.get::<PropStates>(entity), world.resource::<GameFlags>()) {
+            (Some(props), Some(flags)) => flags.get(&props.flag),
+            (Some(_), None) => 0,
+            (None, _) => continue
+        };
+        let props = match world.get_mut::<PropStates>(entity) {
+            Some(props) if !props.states.is_empty() => props,
+            _ => continue
+        };
+        let index = props.state_for(value);
+        if props.current == Some(index) {
+            continue;
+        }
+        props.current = Some(index);
+        changed = true;
+        for (i, state) in props.states.iter().enumerate() {
+            layers.extend(state.layers.iter().map(|layer| (layer.clone(), i == index)));
+        }
+        match props.models.get(index).copied().flatten() {
+            Some(model) => world.insert(entity, ModelInstance(model)),
+            None => { world.remove::<ModelInstance>(entity); }
+        }
+    }
+
+    // Shown layers win, for layers that more than one state uses.
+    if let Some(map) = world.resource_mut::<Tilemap>() {
+        for layer in map.layers.iter_mut() {
+            let shown: Vec<bool> = layers.iter().filter(|(name, _)| *name == layer.name).map(|(_, shown)| *shown).collect();
+            if !shown.is_empty() {
+                layer.visible = shown.contains(&true);
+            }
+        }
+    }
+    changed
+}
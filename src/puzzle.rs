@@ -0,0 +1,222 @@
+// Puzzles that stay how the player left them, like blocks that have been pushed and switches
+// that have been thrown. A field's descriptor lists its puzzle flags and pieces:
+//
+//   puzzle: FieldPuzzle { flags: vec!["cave.switch_a"], pieces: vec!["cave.block"] }
+//
+// While the player's in the field these are ordinary flags, so prop states, traps and the like
+// follow them, and ordinary props or movers, found by name. When they leave, the flags' values
+// and where the pieces were are put away in FieldPuzzles under the field's name and the flags
+// are cleared, so they only mean something in their own field. Coming back puts it all back.
+//
+// FieldPuzzles is kept in saves. Scripts and the console reset a field's puzzle, or every
+// field's whose name starts with something, with "puzzle reset cave*", like when the player
+// leaves a dungeon so its puzzles are there to solve again next time.
+
+use std::collections::BTreeMap;
+
+use cgmath::Vector3;
+
+use crate::flags::GameFlags;
+use crate::transform::Transform;
+use crate::world::{Entity, Name, World};
+
+// What in a field makes up its puzzle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldPuzzle {
+    pub flags: Vec<String>,
+    // Props and movers, by name.
+    pub pieces: Vec<String>
+}
+
+impl FieldPuzzle {
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty() && self.pieces.is_empty()
+    }
+}
+
+// How a field's puzzle was left. Flags that are 0 and pieces that hadn't moved aren't in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PuzzleState {
+    pub flags: BTreeMap<String, i32>,
+    pub pieces: BTreeMap<String, Vector3<f32>>
+}
+
+impl PuzzleState {
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty() && self.pieces.is_empty()
+    }
+}
+
+// The puzzle in the field the player's in, and where its pieces started, for resetting it
+// there and then.
+#[derive(Clone, Debug, PartialEq)]
+struct LivePuzzle {
+    field: String,
+    puzzle: FieldPuzzle,
+    starts: Vec<(Entity, Vector3<f32>)>
+}
+
+// Resource with every field's puzzle that's been left part way through, by field name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldPuzzles {
+    fields: BTreeMap<String, PuzzleState>,
+    live: Option<LivePuzzle>
+}
+
+impl FieldPuzzles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, field: &str) -> Option<&PuzzleState> {
+        self.fields.get(field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PuzzleState)> {
+        self.fields.iter().map(|(field, state)| (field.as_str(), state))
+    }
+
+    // The field whose puzzle is being played, if it has one.
+    pub fn live_field(&self) -> Option<&str> {
+        self.live.as_ref().map(|live| live.field.as_str())
+    }
+}
+
+// Whether a field's name matches a pattern, which is a name or the start of one then "*".
+pub fn matches(pattern: &str, field: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => field.starts_with(prefix),
+        None => field == pattern
+    }
+}
+
+// Put a field's puzzle back how it was left, once its props and movers are in. Call before
+// prop states are updated, so they start in the right state.
+pub fn enter_field(world: &mut World, field: &str, puzzle: &FieldPuzzle) {
+    let mut puzzles = world.remove_resource::<FieldPuzzles>().unwrap_or_default();
+    puzzles.live = None;
+    if !puzzle.is_empty() {
+        let state = puzzles.fields.get(field).cloned().unwrap_or_default();
+        let starts = puzzle.pieces.iter()
+            .filter_map(|name| world.find_by_name(name))
+            .filter_map(|entity| world.get::<Transform>(entity).map(|transform| (entity, transform.position)))
+            .collect();
+        puzzles.live = Some(LivePuzzle { field: field.to_string(), puzzle: puzzle.clone(), starts });
+        put_back(world, puzzle, &state);
+    }
+    world.insert_resource(puzzles);
+}
+
+// Put away the puzzle in the field the player's leaving, before its props and movers go.
+pub fn leave_field(world: &mut World) {
+    let mut puzzles = match world.remove_resource::<FieldPuzzles>() {
+        Some(puzzles) => puzzles,
+        None => return
+    };
+    if let Some(live) = puzzles.live.take() {
+        let state = live_state(world, &live);
+        if let Some(flags) = world.resource_mut::<GameFlags>() {
+            for flag in &live.puzzle.flags {
+                flags.set(flag, 0);
+            }
+        }
+        keep(&mut puzzles.fields, live.field, state);
+    }
+    world.insert_resource(puzzles);
+}
+
+// Puzzles loaded from a save. The one in the field the player's in goes back to how it was
+// saved there and then.
+pub fn load(world: &mut World, fields: BTreeMap<String, PuzzleState>) {
+    let mut puzzles = world.remove_resource::<FieldPuzzles>().unwrap_or_default();
+    puzzles.fields = fields;
+    if let Some(live) = &puzzles.live {
+        for (entity, start) in &live.starts {
+            if let Some(transform) = world.get_mut::<Transform>(*entity) {
+                transform.position = *start;
+            }
+        }
+        put_back(world, &live.puzzle, &puzzles.fields.get(&live.field).cloned().unwrap_or_default());
+    }
+    world.insert_resource(puzzles);
+}
+
+// Every field's puzzle, including how the one the player's in is right now, for saving.
+pub fn snapshot(world: &World) -> BTreeMap<String, PuzzleState> {
+    let puzzles = match world.resource::<FieldPuzzles>() {
+        Some(puzzles) => puzzles,
+        None => return BTreeMap::new()
+    };
+    let mut fields = puzzles.fields.clone();
+    if let Some(live) = &puzzles.live {
+        keep(&mut fields, live.field.clone(), live_state(world, live));
+    }
+    fields
+}
+
+// Start the puzzles in matching fields again. The one the player's in goes back to how it
+// started there and then. Returns the fields that were reset.
+pub fn reset(world: &mut World, pattern: &str) -> Vec<String> {
+    let mut puzzles = world.remove_resource::<FieldPuzzles>().unwrap_or_default();
+    let mut reset: Vec<String> = puzzles.fields.keys().filter(|field| matches(pattern, field)).cloned().collect();
+    puzzles.fields.retain(|field, _| !matches(pattern, field));
+    if let Some(live) = puzzles.live.as_ref().filter(|live| matches(pattern, &live.field)) {
+        if let Some(flags) = world.resource_mut::<GameFlags>() {
+            for flag in &live.puzzle.flags {
+                flags.set(flag, 0);
+            }
+        }
+        for (entity, start) in &live.starts {
+            if let Some(transform) = world.get_mut::<Transform>(*entity) {
+                transform.position = *start;
+            }
+        }
+        if !reset.contains(&live.field) {
+            reset.push(live.field.clone());
+        }
+    }
+    world.insert_resource(puzzles);
+    reset
+}
+
+// Only puzzles that have been started on are kept.
+fn keep(fields: &mut BTreeMap<String, PuzzleState>, field: String, state: PuzzleState) {
+    if state.is_empty() {
+        fields.remove(&field);
+    } else {
+        fields.insert(field, state);
+    }
+}
+
+fn live_state(world: &World, live: &LivePuzzle) -> PuzzleState {
+    let mut state = PuzzleState::default();
+    if let Some(flags) = world.resource::<GameFlags>() {
+        state.flags = live.puzzle.flags.iter()
+            .map(|flag| (flag.clone(), flags.get(flag)))
+            .filter(|(_, value)| *value != 0)
+            .collect();
+    }
+    for (entity, start) in &live.starts {
+        let name = world.get::<Name>(*entity).map(|name| name.0.clone());
+        let position = world.get::<Transform>(*entity).map(|transform| transform.position);
+        if let (Some(name), Some(position)) = (name, position) {
+            if position != *start {
+                state.pieces.insert(name, position);
+            }
+        }
+    }
+    state
+}
+
+fn put_back(world: &mut World, puzzle: &FieldPuzzle, state: &PuzzleState) {
+    if let Some(flags) = world.resource_mut::<GameFlags>() {
+        for flag in &puzzle.flags {
+            flags.set(flag, state.flags.get(flag).copied().unwrap_or(0));
+        }
+    }
+    for (name, position) in puzzle.pieces.iter().filter_map(|name| state.pieces.get(name).map(|position| (name, position))) {
+        if let Some(transform) = world.find_by_name(name).and_then(|entity| world.get_mut::<Transform>(entity)) {
+            transform.position = *position;
+        }
+    }
+}
@@ -1,10 +1,27 @@
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
-use wgpu::{Device, Queue, RenderPipeline, Surface, SurfaceConfiguration, Texture, TextureDescriptor, TextureView, Sampler, BindGroupLayout, TextureViewDescriptor, util::DeviceExt, Buffer, TextureFormat};
+use instant::Instant;
+
+use cgmath::Vector4;
+use wgpu::{Device, Queue, Surface, SurfaceConfiguration, Texture, TextureView, Sampler, BindGroup, TextureViewDescriptor, util::DeviceExt, Buffer, TextureFormat};
 use winit::window::Window;
 
-const SCREEN_WIDTH: usize = 640;
-const SCREEN_HEIGHT: usize = 800;
+use crate::assets::AssetServer;
+use crate::camera::Camera;
+use crate::field::{FieldOccluder, FieldReflection};
+use crate::color_filter::ColorFilter;
+use crate::depth_of_field::{DepthOfField, DofQuality};
+use crate::battle_transition::ScreenTransition;
+use crate::tilemap::{Tilemap, TilemapRenderer};
+use crate::model::{ModelBatch, ModelData, ModelId, ModelRenderer, LodPolicy, DEPTH_FORMAT};
+use crate::ui::{UiBatch, UiImageId, UiRenderer};
+use crate::font::FontAtlas;
+use crate::gpu_profiler::GpuProfiler;
+use crate::pipeline_cache::{PipelineCache, PipelineId, BindGroupLayoutId, PipelineKey, texture_entry, depth_texture_entry, sampler_entry, uniform_entry};
+use crate::logging::targets;
+
+pub const SCREEN_WIDTH: usize = 640;
+pub const SCREEN_HEIGHT: usize = 800;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -41,16 +58,25 @@ const TEXTURED_FULL_SCREEN_QUAD_VERTICES: &[Vertex] = &[
 pub struct FieldBackground {
     background_texture: Texture,
     background_sampler: Sampler,
+    texture_bytes: u64
 }
 
 impl FieldBackground {
     pub fn new(device: &Device, queue: &Queue, image_path: &Path) -> Self {
-        // Load the image.
+        let image = Self::load_image(image_path);
+        Self::from_image(device, queue, &image)
+    }
+
+    // Load and decode the background image.
+    pub fn load_image(image_path: &Path) -> image::RgbaImage {
         // TODO error handling.
         let image = image::io::Reader::open(image_path)
             .unwrap().decode().unwrap();
-        let image = image.to_rgba8();
+        image.to_rgba8()
+    }
 
+    // Upload an already decoded image.
+    pub fn from_image(device: &Device, queue: &Queue, image: &image::RgbaImage) -> Self {
         // Create the texture in wgpu.
         let texture_desc = wgpu::TextureDescriptor {
             label: Some("Field Background Texture"),
@@ -101,7 +127,8 @@ impl FieldBackground {
 
         Self {
             background_texture: texture,
-            background_sampler: sampler
+            background_sampler: sampler,
+            texture_bytes: image.as_raw().len() as u64
         }
     }
 
@@ -112,18 +139,61 @@ impl FieldBackground {
     pub fn get_texture(&self) -> &Texture {
         &self.background_texture
     }
+
+    pub fn get_texture_bytes(&self) -> u64 {
+        self.texture_bytes
+    }
+}
+
+// How many reflections a field can have.
+pub const MAX_FIELD_REFLECTIONS: usize = 4;
+
+// One of the field's reflections as the background shader sees it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct ReflectionUniforms {
+    // Left, top, right and bottom, in texture coordinates.
+    rect: [f32; 4],
+    // Strength, then how far ripples move it across and down in texture coordinates.
+    params: [f32; 4]
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct BackgroundUniforms {
+    reflection_count: u32,
+    // Seconds the ripples have been moving for.
+    time: f32,
+    _padding: [f32; 2],
+    reflections: [ReflectionUniforms; MAX_FIELD_REFLECTIONS]
 }
 
 // Draw a field background to a surface. 
 pub struct FieldBackgroundRenderer {
-    render_pipeline: RenderPipeline,
-    bind_group_layout: BindGroupLayout,
-    vertex_buffer: Buffer
+    render_pipeline: PipelineId,
+    bind_group_layout: BindGroupLayoutId,
+    vertex_buffer: Buffer,
+
+    // For the current background. Only changes when the background does.
+    bind_group: Option<BindGroup>,
+
+    // Models drawn mirrored in the field's reflections, and how to show them.
+    reflections: Vec<FieldReflection>,
+    reflection_time: f32,
+    reflection_bind_group_layout: BindGroupLayoutId,
+    reflection_bind_group: BindGroup,
+    // Only made while the field has reflections. The placeholder is bound the rest of the time.
+    reflection_texture: Option<(Texture, TextureView)>,
+    placeholder_view: TextureView,
+    reflection_sampler: Sampler,
+    reflection_format: TextureFormat,
+    uniform_buffer: Buffer,
+    uploaded_uniforms: BackgroundUniforms
 }
 
 impl FieldBackgroundRenderer {
-    pub fn new(device: &Device, output_format: TextureFormat) -> Self {
-        let shader = device.create_shader_module(wgpu::include_wgsl!("field_background.wgsl"));
+    pub fn new(device: &Device, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("field_background.wgsl"));
 
         // Create a vertex buffer containing a quad.
         let vertex_buffer = device.create_buffer_init(
@@ -136,86 +206,114 @@ impl FieldBackgroundRenderer {
 
         // Bind group layout.
         // We need to sample the background texture in our shader.
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true }
-                    },
-                    count: None
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None
-                }
-            ],
-            label: Some("Field Background Renderer Bind Group Layout")
-        });
+        let bind_group_layout = pipelines.create_bind_group_layout(device, "Field Background Renderer Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT)
+        ]);
+        // And the reflections to show over it.
+        let reflection_bind_group_layout = pipelines.create_bind_group_layout(device, "Field Reflection Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT),
+            uniform_entry(2, wgpu::ShaderStages::FRAGMENT)
+        ]);
 
         // Create a render pipeline.
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Field Background Render Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[]
-        });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Field Background Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::desc()
-                ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: output_format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        let render_pipeline = pipelines.create_render_pipeline(device, "Field Background Render Pipeline", PipelineKey::new(
+            shader,
+            &[bind_group_layout, reflection_bind_group_layout],
+            vec![Vertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        ));
+
+        // A see through pixel to show when there are no reflections.
+        let placeholder = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Field Reflection Placeholder Texture"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1
             },
-            multiview: None
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: output_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
         });
+        let placeholder_view = placeholder.create_view(&TextureViewDescriptor::default());
+
+        let reflection_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniforms: BackgroundUniforms = bytemuck::Zeroable::zeroed();
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Field Reflection Uniform Buffer"),
+                contents: bytemuck::bytes_of(&uniforms),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let reflection_bind_group = Self::create_reflection_bind_group(device, pipelines, reflection_bind_group_layout, &placeholder_view, &reflection_sampler, &uniform_buffer);
 
         Self {
             render_pipeline,
             bind_group_layout,
-            vertex_buffer
+            vertex_buffer,
+            bind_group: None,
+            reflections: Vec::new(),
+            reflection_time: 0.0,
+            reflection_bind_group_layout,
+            reflection_bind_group,
+            reflection_texture: None,
+            placeholder_view,
+            reflection_sampler,
+            reflection_format: output_format,
+            uniform_buffer,
+            uploaded_uniforms: uniforms
         }
     }
 
-    pub fn render(&mut self, device: &Device, queue: &Queue, dest_view: &TextureView, field_background: &FieldBackground) {
+    fn create_reflection_bind_group(device: &Device, pipelines: &PipelineCache, layout: BindGroupLayoutId, view: &TextureView, sampler: &Sampler, uniform_buffer: &Buffer) -> BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Field Reflection Bind Group"),
+            layout: pipelines.get_bind_group_layout(layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding()
+                }
+            ]
+        })
+    }
+
+    // Switch to drawing a different background.
+    pub fn set_background(&mut self, device: &Device, pipelines: &PipelineCache, field_background: &FieldBackground) {
         let texture = field_background.get_texture();
         let texture_view = texture.create_view(&TextureViewDescriptor::default());
 
-        let bind_group = device.create_bind_group(
+        self.bind_group = Some(device.create_bind_group(
             &wgpu::BindGroupDescriptor {
                 label: Some("Field Background Renderer Bind Group"),
-                layout: &self.bind_group_layout,
+                layout: pipelines.get_bind_group_layout(self.bind_group_layout),
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
@@ -227,7 +325,109 @@ impl FieldBackgroundRenderer {
                     }
                 ]
             }
-        );
+        ));
+    }
+
+    // Replace the reflections. Any past MAX_FIELD_REFLECTIONS are left out.
+    pub fn set_reflections(&mut self, device: &Device, pipelines: &PipelineCache, reflections: &[FieldReflection]) {
+        if reflections.len() > MAX_FIELD_REFLECTIONS {
+            tracing::warn!(target: targets::RENDERER, "Fields can only have {} reflections, but this one has {}.", MAX_FIELD_REFLECTIONS, reflections.len());
+        }
+        self.reflections = reflections.iter().take(MAX_FIELD_REFLECTIONS).cloned().collect();
+
+        // The texture's only kept while there's something to reflect.
+        if self.reflections.is_empty() {
+            self.reflection_texture = None;
+        } else if self.reflection_texture.is_none() {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Field Reflection Texture"),
+                size: wgpu::Extent3d {
+                    width: SCREEN_WIDTH as u32,
+                    height: SCREEN_HEIGHT as u32,
+                    depth_or_array_layers: 1
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.reflection_format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            self.reflection_texture = Some((texture, view));
+        }
+
+        let view = self.reflection_texture.as_ref().map(|(_, view)| view).unwrap_or(&self.placeholder_view);
+        self.reflection_bind_group = Self::create_reflection_bind_group(device, pipelines, self.reflection_bind_group_layout, view, &self.reflection_sampler, &self.uniform_buffer);
+    }
+
+    pub fn reflections(&self) -> &[FieldReflection] {
+        &self.reflections
+    }
+
+    // Where models are drawn mirrored, cleared ready for this frame. None without reflections.
+    pub fn begin_reflections(&self, device: &Device, queue: &Queue) -> Option<&TextureView> {
+        let (_, view) = self.reflection_texture.as_ref()?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Field Reflection Clear Encoder")
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Field Reflection Clear Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true
+                }
+            })],
+            depth_stencil_attachment: None
+        });
+        queue.submit(Some(encoder.finish()));
+        Some(view)
+    }
+
+    // Move the ripples along. Returns whether there are any, so the screen needs redrawing.
+    pub fn advance_ripples(&mut self, seconds: f32) -> bool {
+        if !self.reflections.iter().any(|reflection| reflection.ripple != 0.0) {
+            return false;
+        }
+        self.reflection_time += seconds;
+        true
+    }
+
+    pub fn texture_bytes(&self) -> u64 {
+        if self.reflection_texture.is_some() {
+            (SCREEN_WIDTH * SCREEN_HEIGHT * 4) as u64
+        } else {
+            0
+        }
+    }
+
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView) {
+        let bind_group = match &self.bind_group {
+            Some(bind_group) => bind_group,
+            None => return
+        };
+
+        // Upload the reflections if they've changed or are rippling.
+        let mut uniforms: BackgroundUniforms = bytemuck::Zeroable::zeroed();
+        uniforms.reflection_count = self.reflections.len() as u32;
+        uniforms.time = self.reflection_time;
+        for (uniform, reflection) in uniforms.reflections.iter_mut().zip(&self.reflections) {
+            *uniform = ReflectionUniforms {
+                rect: [
+                    reflection.x / SCREEN_WIDTH as f32,
+                    reflection.y / SCREEN_HEIGHT as f32,
+                    (reflection.x + reflection.width) / SCREEN_WIDTH as f32,
+                    (reflection.y + reflection.height) / SCREEN_HEIGHT as f32
+                ],
+                params: [reflection.strength, reflection.ripple / SCREEN_WIDTH as f32, reflection.ripple / SCREEN_HEIGHT as f32, 0.0]
+            };
+        }
+        if uniforms != self.uploaded_uniforms {
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+            self.uploaded_uniforms = uniforms;
+        }
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Field Background Renderer Encoder.")
@@ -237,7 +437,7 @@ impl FieldBackgroundRenderer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Field Background Renderer Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &dest_view,
+                    view: dest_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
@@ -247,13 +447,14 @@ impl FieldBackgroundRenderer {
                 depth_stencil_attachment: None
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
 
-            // Set the viewport.
-            render_pass.set_viewport(0.1, 0.1, 0.5, 0.5, 0.0, 1.0);
+            // The background covers the whole screen.
+            render_pass.set_viewport(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0, 1.0);
 
             // Bind the texture.
-            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_bind_group(1, &self.reflection_bind_group, &[]);
             
             // Set the vertex buffer and draw.
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
@@ -264,22 +465,345 @@ impl FieldBackgroundRenderer {
     }
 }
 
+// The part of the screen a reflection's models need drawing in, as a scissor rect. What the
+// mirrored camera sees is flipped left to right, so it's the other side of the screen to the
+// reflection itself, with room for the ripples. None if it's off the screen.
+pub fn reflection_scissor(reflection: &FieldReflection) -> Option<[u32; 4]> {
+    let margin = reflection.ripple.abs().ceil();
+    let left = (SCREEN_WIDTH as f32 - reflection.x - reflection.width - margin).max(0.0);
+    let right = (SCREEN_WIDTH as f32 - reflection.x + margin).min(SCREEN_WIDTH as f32);
+    let top = (reflection.y - margin).max(0.0);
+    let bottom = (reflection.y + reflection.height + margin).min(SCREEN_HEIGHT as f32);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    let (left, top) = (left.floor() as u32, top.floor() as u32);
+    Some([left, top, right.ceil() as u32 - left, bottom.ceil() as u32 - top])
+}
+
+// A cut out piece of the background, drawn again over the models.
+struct GpuFieldOccluder {
+    image: FieldBackground,
+    bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    // Where it goes, in virtual screen pixels.
+    rect: [f32; 4],
+    depth: f32,
+    // The depth buffer value the vertex buffer was last written for.
+    uploaded_depth: Option<f32>
+}
+
+// Draws the parts of a field's background that are in front of the walk area, like pillars,
+// over the models wherever they're nearer the camera. Characters go behind them without
+// needing a depth image of the whole background. Each has a single depth, so they suit
+// things that are upright and face the camera.
+pub struct FieldOccluderRenderer {
+    render_pipeline: PipelineId,
+    bind_group_layout: BindGroupLayoutId,
+    occluders: Vec<GpuFieldOccluder>
+}
+
+impl FieldOccluderRenderer {
+    pub fn new(device: &Device, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("field_occluder.wgsl"));
+
+        let bind_group_layout = pipelines.create_bind_group_layout(device, "Field Occluder Renderer Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        // Tested against the models' depth, but doesn't write any of its own.
+        let mut key = PipelineKey::new(
+            shader,
+            &[bind_group_layout],
+            vec![Vertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        );
+        key.depth_stencil = Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default()
+        });
+        let render_pipeline = pipelines.create_render_pipeline(device, "Field Occluder Render Pipeline", key);
+
+        Self {
+            render_pipeline,
+            bind_group_layout,
+            occluders: Vec::new()
+        }
+    }
+
+    pub fn add(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, occluder: &FieldOccluder, image: &image::RgbaImage) {
+        let image = FieldBackground::from_image(device, queue, image);
+        let texture_view = image.get_texture().create_view(&TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Field Occluder Bind Group"),
+                layout: pipelines.get_bind_group_layout(self.bind_group_layout),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture_view)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(image.get_sampler())
+                    }
+                ]
+            }
+        );
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Field Occluder Vertex Buffer"),
+            size: std::mem::size_of_val(TEXTURED_FULL_SCREEN_QUAD_VERTICES) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        self.occluders.push(GpuFieldOccluder {
+            image,
+            bind_group,
+            vertex_buffer,
+            rect: [occluder.x, occluder.y, occluder.width, occluder.height],
+            depth: occluder.depth,
+            uploaded_depth: None
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.occluders.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.occluders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.occluders.is_empty()
+    }
+
+    pub fn texture_bytes(&self) -> u64 {
+        self.occluders.iter().map(|occluder| occluder.image.get_texture_bytes()).sum()
+    }
+
+    // Draw over the models, using the depth buffer they were drawn with.
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, depth_view: &TextureView, camera: &Camera) {
+        if self.occluders.is_empty() {
+            return;
+        }
+
+        // Each occluder's depth moves with the camera's near and far planes, so work them out
+        // again and update the quads that have changed.
+        let projection = camera.projection_matrix(SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32);
+        for occluder in &mut self.occluders {
+            let clip = projection * Vector4::new(0.0, 0.0, -occluder.depth, 1.0);
+            let depth = if clip.w > 0.0 { (clip.z / clip.w).clamp(0.0, 1.0) } else { 0.0 };
+            if occluder.uploaded_depth != Some(depth) {
+                queue.write_buffer(&occluder.vertex_buffer, 0, bytemuck::cast_slice(&screen_quad(occluder.rect, depth)));
+                occluder.uploaded_depth = Some(depth);
+            }
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Field Occluder Renderer Encoder.")
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Field Occluder Renderer Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }),
+                    stencil_ops: None
+                })
+            });
+
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
+            render_pass.set_viewport(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0, 1.0);
+            for occluder in &self.occluders {
+                render_pass.set_bind_group(0, &occluder.bind_group, &[]);
+                render_pass.set_vertex_buffer(0, occluder.vertex_buffer.slice(..));
+                render_pass.draw(0..TEXTURED_FULL_SCREEN_QUAD_VERTICES.len() as u32, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+// A quad covering part of the screen at a depth, wound the same way as the full screen one.
+fn screen_quad(rect: [f32; 4], depth: f32) -> [Vertex; 6] {
+    let [x, y, width, height] = rect;
+    let left = x / SCREEN_WIDTH as f32 * 2.0 - 1.0;
+    let right = (x + width) / SCREEN_WIDTH as f32 * 2.0 - 1.0;
+    let top = 1.0 - y / SCREEN_HEIGHT as f32 * 2.0;
+    let bottom = 1.0 - (y + height) / SCREEN_HEIGHT as f32 * 2.0;
+    [
+        Vertex { position: [left, bottom, depth], uv: [0.0, 1.0] },
+        Vertex { position: [right, bottom, depth], uv: [1.0, 1.0] },
+        Vertex { position: [right, top, depth], uv: [1.0, 0.0] },
+
+        Vertex { position: [right, top, depth], uv: [1.0, 0.0] },
+        Vertex { position: [left, top, depth], uv: [0.0, 0.0] },
+        Vertex { position: [left, bottom, depth], uv: [0.0, 1.0] }
+    ]
+}
+
+// Tweakable values for the post processing shader.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostProcessSettings {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub vignette: f32,
+
+    // Colour to blend towards, and how far to blend.
+    pub tint: [f32; 3],
+    pub tint_strength: f32,
+
+    // Colour to fade to, and how far it's faded, e.g. to black at the end of a scene.
+    pub fade: [f32; 4],
+
+    // Moves the whole picture, in virtual screen pixels. Screen shake jiggles it about.
+    pub offset: [f32; 2],
+
+    // For colour blind players. Applied last, so it also covers the tint.
+    pub color_filter: ColorFilter,
+
+    // Blur by distance from the camera, for battles and photo mode. None for everything sharp.
+    pub depth_of_field: Option<DepthOfField>,
+    // The player's setting for how good the blur looks, and whether there's any at all.
+    pub dof_quality: DofQuality,
+
+    // How dark it is outside a circle of light, for dark dungeons, see darkness.rs. 0 for no
+    // darkness at all. The circle's centre and radius are in virtual screen pixels.
+    pub darkness: f32,
+    pub light_position: [f32; 2],
+    pub light_radius: f32,
+
+    // The captured frame being swirled away going into a battle, see battle_transition.rs. None
+    // for the screen as it is.
+    pub transition: Option<ScreenTransition>
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            vignette: 0.0,
+            tint: [1.0, 1.0, 1.0],
+            tint_strength: 0.0,
+            fade: [0.0, 0.0, 0.0, 0.0],
+            offset: [0.0, 0.0],
+            color_filter: ColorFilter::None,
+            depth_of_field: None,
+            dof_quality: DofQuality::default(),
+            darkness: 0.0,
+            light_position: [SCREEN_WIDTH as f32 / 2.0, SCREEN_HEIGHT as f32 / 2.0],
+            light_radius: 0.0,
+            transition: None
+        }
+    }
+}
+
+// Layout of PostProcessSettings as the shader sees it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostProcessUniforms {
+    tint: [f32; 4],
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    vignette: f32,
+    fade: [f32; 4],
+    // The offset in texture coordinates.
+    offset: [f32; 4],
+    // The colour filter's matrix, a column at a time padded out to vec4s like WGSL's mat3x3.
+    color_matrix: [[f32; 4]; 3],
+    // Focus distance, focus range, falloff and biggest blur.
+    dof: [f32; 4],
+    // How many samples the blur takes, none to turn it off, then the camera's near and far
+    // planes for turning depths back into distances.
+    dof_params: [f32; 4],
+    // The light's centre and radius in virtual screen pixels, then how dark it is outside.
+    light: [f32; 4],
+    // Which transition, none for the screen as it is, then how far it's gone.
+    transition: [f32; 4]
+}
+
+impl PostProcessUniforms {
+    fn new(settings: &PostProcessSettings, camera: &Camera) -> Self {
+        let color_matrix = settings.color_filter.matrix();
+        let (dof, samples) = match settings.depth_of_field {
+            Some(dof) => ([dof.focus_distance, dof.focus_range, dof.falloff, dof.max_blur], settings.dof_quality.samples()),
+            None => ([0.0; 4], 0)
+        };
+        Self {
+            tint: [settings.tint[0], settings.tint[1], settings.tint[2], settings.tint_strength],
+            brightness: settings.brightness,
+            contrast: settings.contrast,
+            saturation: settings.saturation,
+            vignette: settings.vignette,
+            fade: settings.fade,
+            offset: [settings.offset[0] / SCREEN_WIDTH as f32, settings.offset[1] / SCREEN_HEIGHT as f32, 0.0, 0.0],
+            color_matrix: [color_matrix.x.extend(0.0).into(), color_matrix.y.extend(0.0).into(), color_matrix.z.extend(0.0).into()],
+            dof,
+            dof_params: [samples as f32, camera.znear, camera.zfar, 0.0],
+            light: [settings.light_position[0], settings.light_position[1], settings.light_radius, settings.darkness],
+            transition: match settings.transition {
+                Some(transition) => [transition.style.shader_index() as f32, transition.progress, 0.0, 0.0],
+                None => [0.0; 4]
+            }
+        }
+    }
+}
+
 // Draw to the texture here and then use the render() function to draw to your output surface.
 // The post_process.wgsl shader can have post processing stuff in it.
 struct PostProcessRenderer {
-    render_pipeline: RenderPipeline,
-    bind_group_layout: BindGroupLayout,
+    render_pipeline: PipelineId,
+    bind_group: BindGroup,
+    // The models' depth buffer, for depth of field.
+    depth_bind_group_layout: BindGroupLayoutId,
+    depth_bind_group: Option<BindGroup>,
     vertex_buffer: Buffer,
 
+    settings: PostProcessSettings,
+    // What's in the uniform buffer, so it's only written when something changes.
+    uploaded_uniforms: PostProcessUniforms,
+    uniform_buffer: Buffer,
+
     texture: Texture,
-    sampler: Sampler,
+    view: TextureView,
+    // A frame kept for transitions to work on.
+    transition_texture: Texture,
+    _transition_view: TextureView,
+    _sampler: Sampler,
     texture_format: TextureFormat
 }
 
 impl PostProcessRenderer {
-    pub fn new(device: &Device, output_format: TextureFormat) -> Self {
+    pub fn new(device: &Device, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
         // Load shader
-        let shader = device.create_shader_module(wgpu::include_wgsl!("post_process.wgsl"));
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("post_process.wgsl"));
 
         // Create a texture.
         let texture_desc = wgpu::TextureDescriptor {
@@ -292,10 +816,20 @@ impl PostProcessRenderer {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // Copied from for screenshots and save thumbnails.
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
             label: Some("Post Process Texture")
         };
         let texture = device.create_texture(&texture_desc);
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        // And one to copy a frame into.
+        let transition_texture = device.create_texture(&wgpu::TextureDescriptor {
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("Post Process Transition Texture"),
+            ..texture_desc
+        });
+        let transition_view = transition_texture.create_view(&TextureViewDescriptor::default());
 
         // Vertex buffer for a screen quad.
         let vertex_buffer = device.create_buffer_init( 
@@ -308,70 +842,38 @@ impl PostProcessRenderer {
 
         // Bind group layout.
         // We need to sample the background texture in our shader.
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true }
-                    },
-                    count: None
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None
-                }
-            ],
-            label: Some("Post Process Bind Group Layout")
-        });
+        let bind_group_layout = pipelines.create_bind_group_layout(device, "Post Process Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT),
+            uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+            texture_entry(3, wgpu::ShaderStages::FRAGMENT)
+        ]);
+        let depth_bind_group_layout = pipelines.create_bind_group_layout(device, "Post Process Depth Bind Group Layout", &[
+            depth_texture_entry(0, wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        // Uniforms for the post process settings.
+        let settings = PostProcessSettings::default();
+        let uniforms = PostProcessUniforms::new(&settings, &Camera::default());
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Post Process Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[uniforms]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
 
         // Create a render pipeline.
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Post Process Render Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[]
-        });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Post Process Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::desc()
-                ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: output_format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None
-        });
+        let render_pipeline = pipelines.create_render_pipeline(device, "Post Process Render Pipeline", PipelineKey::new(
+            shader,
+            &[bind_group_layout, depth_bind_group_layout],
+            vec![Vertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        ));
 
         // Create a sampler.
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -384,12 +886,45 @@ impl PostProcessRenderer {
             ..Default::default()
         });
 
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Post Process Renderer Bind Group"),
+                layout: pipelines.get_bind_group_layout(bind_group_layout),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding()
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&transition_view)
+                    }
+                ]
+            }
+        );
+
         Self {
             render_pipeline,
-            bind_group_layout,
+            bind_group,
+            depth_bind_group_layout,
+            depth_bind_group: None,
             vertex_buffer,
+            settings,
+            uploaded_uniforms: uniforms,
+            uniform_buffer,
             texture,
-            sampler,
+            view,
+            transition_texture,
+            _transition_view: transition_view,
+            _sampler: sampler,
             texture_format: texture_desc.format
         }
     }
@@ -398,29 +933,63 @@ impl PostProcessRenderer {
         &self.texture
     }
 
+    // Where everything is drawn before post processing.
+    pub fn get_view(&self) -> &TextureView {
+        &self.view
+    }
+
     pub fn get_texture_format(&self) -> TextureFormat {
         self.texture_format
     }
 
-    pub fn render(&mut self, device: &Device, queue: &Queue, dest_view: &TextureView) {
-        let texture_view = self.texture.create_view(&TextureViewDescriptor::default());
+    pub fn get_texture_bytes(&self) -> u64 {
+        // The texture and the transition's.
+        (SCREEN_WIDTH * SCREEN_HEIGHT * 4 * 2) as u64
+    }
 
-        let bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                label: Some("Post Process Renderer Bind Group"),
-                layout: &self.bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&texture_view)
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler)
-                    }
-                ]
-            }
+    // Keep what was last drawn for a transition to work on.
+    pub fn capture_transition_frame(&self, device: &Device, queue: &Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Post Process Transition Capture Encoder.")
+        });
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            self.transition_texture.as_image_copy(),
+            wgpu::Extent3d { width: SCREEN_WIDTH as u32, height: SCREEN_HEIGHT as u32, depth_or_array_layers: 1 }
         );
+        queue.submit(Some(encoder.finish()));
+    }
+
+    pub fn get_settings(&self) -> &PostProcessSettings {
+        &self.settings
+    }
+
+    pub fn get_settings_mut(&mut self) -> &mut PostProcessSettings {
+        &mut self.settings
+    }
+
+    // The depth buffer depth of field reads, which has to be set before rendering.
+    pub fn set_depth_view(&mut self, device: &Device, pipelines: &PipelineCache, depth_view: &TextureView) {
+        self.depth_bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Process Depth Bind Group"),
+            layout: pipelines.get_bind_group_layout(self.depth_bind_group_layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view)
+                }
+            ]
+        }));
+    }
+
+    // The camera's the one the depth buffer was drawn with.
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, dest_size: winit::dpi::PhysicalSize<u32>, camera: &Camera) {
+        // Upload the latest settings.
+        let uniforms = PostProcessUniforms::new(&self.settings, camera);
+        if uniforms != self.uploaded_uniforms {
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+            self.uploaded_uniforms = uniforms;
+        }
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Post Process Renderer Encoder.")
@@ -430,20 +999,27 @@ impl PostProcessRenderer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Post Process Renderer Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &dest_view,
+                    view: dest_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: true
                     }
                 })],
                 depth_stencil_attachment: None
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
+
+            // Keep the screen's shape, with black bars filling the rest of the window.
+            let (x, y, width, height) = letterbox_viewport(dest_size);
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
 
             // Bind the texture.
-            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            if let Some(depth_bind_group) = &self.depth_bind_group {
+                render_pass.set_bind_group(1, depth_bind_group, &[]);
+            }
             
             // Set the vertex buffer and draw.
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
@@ -454,22 +1030,105 @@ impl PostProcessRenderer {
     }
 }
 
+// The biggest rectangle with the screen's aspect ratio that fits in the window, centred.
+// Returned as x, y, width and height in window pixels.
+pub fn letterbox_viewport(window_size: winit::dpi::PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+    let window_width = window_size.width.max(1) as f32;
+    let window_height = window_size.height.max(1) as f32;
+    let scale = (window_width / SCREEN_WIDTH as f32).min(window_height / SCREEN_HEIGHT as f32);
+
+    // Whole pixels so the edges stay sharp.
+    let width = (SCREEN_WIDTH as f32 * scale).round().max(1.0);
+    let height = (SCREEN_HEIGHT as f32 * scale).round().max(1.0);
+    let x = ((window_width - width) / 2.0).floor();
+    let y = ((window_height - height) / 2.0).floor();
+    (x, y, width, height)
+}
+
+// Convert a position in the window to virtual screen pixels. None if it's in the black bars.
+pub fn window_to_screen(window_size: winit::dpi::PhysicalSize<u32>, position: winit::dpi::PhysicalPosition<f64>) -> Option<(f32, f32)> {
+    let (x, y, width, height) = letterbox_viewport(window_size);
+    let screen_x = (position.x as f32 - x) / width * SCREEN_WIDTH as f32;
+    let screen_y = (position.y as f32 - y) / height * SCREEN_HEIGHT as f32;
+
+    let on_screen = (0.0..SCREEN_WIDTH as f32).contains(&screen_x) && (0.0..SCREEN_HEIGHT as f32).contains(&screen_y);
+    on_screen.then_some((screen_x, screen_y))
+}
+
+// Counters gathered while rendering the last frame. Handy for the debug overlay.
+#[derive(Default, Clone)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    // Models drawn, and the triangles drawn for them.
+    pub model_instances: u32,
+    pub triangles: u32,
+    // Models too far away to draw.
+    pub models_culled: u32,
+    pub texture_bytes: u64,
+
+    // CPU time spent recording and submitting each pass.
+    pub pass_timings: Vec<(&'static str, Duration)>,
+
+    // GPU time for each pass, if timestamp queries are supported. These lag a frame or two behind.
+    pub gpu_pass_timings: Vec<(&'static str, Duration)>
+}
+
+impl RenderStats {
+    fn time_pass(&mut self, name: &'static str, start: Instant) {
+        self.pass_timings.push((name, start.elapsed()));
+    }
+}
+
+// Something drawn straight onto the window after post processing, at the window's resolution.
+// Debug tools use this so they stay readable whatever the game's resolution is.
+pub trait WindowOverlay {
+    fn render(&mut self, device: &Device, queue: &Queue, dest_view: &TextureView, dest_format: TextureFormat, size: winit::dpi::PhysicalSize<u32>);
+}
+
+// Where the final image ends up.
+enum RenderOutput {
+    Window(Surface),
+    Headless(Texture, TextureView)
+}
+
+impl RenderOutput {
+    // Where the final image goes. Windows need this frame's view of the surface passing in.
+    fn view<'a>(&'a self, surface_view: Option<&'a TextureView>) -> &'a TextureView {
+        match (self, surface_view) {
+            (RenderOutput::Headless(_, view), _) => view,
+            (RenderOutput::Window(_), Some(view)) => view,
+            (RenderOutput::Window(_), None) => panic!("Rendering to a window needs the surface's view")
+        }
+    }
+}
+
 pub struct Renderer {
     device: Device,
     queue: Queue,
-    render_pipeline: RenderPipeline,
+    pipelines: PipelineCache,
+    render_pipeline: PipelineId,
 
-    surface: Surface,
+    output: RenderOutput,
     surface_config: SurfaceConfiguration,
 
     post_process_renderer: PostProcessRenderer,
 
     field_background: FieldBackground,
-    field_background_renderer: FieldBackgroundRenderer
+    field_background_renderer: FieldBackgroundRenderer,
+    tilemap_renderer: TilemapRenderer,
+
+    model_renderer: ModelRenderer,
+    field_occluder_renderer: FieldOccluderRenderer,
+
+    ui_renderer: UiRenderer,
+
+    gpu_profiler: Option<GpuProfiler>,
+
+    stats: RenderStats
 }
 
 impl Renderer {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, assets: &AssetServer) -> Self {
         // The instance is a handle to our GPU
         // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
         let instance = wgpu::Instance::new(wgpu::Backends::all());
@@ -482,9 +1141,65 @@ impl Renderer {
             },
         ).await.unwrap();
 
+        let (device, queue, profiling_supported) = Self::request_device(&adapter).await;
+
+        // Configure the surface.
+        let size = window.inner_size();
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface.get_supported_formats(&adapter)[0],
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto
+        };
+        surface.configure(&device, &surface_config);
+
+        let field_image = Self::load_field_image(assets).await;
+        Self::from_device(device, queue, profiling_supported, RenderOutput::Window(surface), surface_config, &field_image)
+    }
+
+    // A renderer that draws into an offscreen texture instead of a window, for tests and tools.
+    // Returns None if there's no adapter to render with.
+    pub async fn new_headless(assets: &AssetServer, width: u32, height: u32) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            },
+        ).await?;
+
+        let (device, queue, profiling_supported) = Self::request_device(&adapter).await;
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto
+        };
+        let texture = Self::create_headless_texture(&device, &surface_config);
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        let field_image = Self::load_field_image(assets).await;
+        Some(Self::from_device(device, queue, profiling_supported, RenderOutput::Headless(texture, view), surface_config, &field_image))
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> (Device, Queue, bool) {
+        // Turn on GPU profiling if we can.
+        let profiling_supported = adapter.features().contains(GpuProfiler::required_features());
+        let features = if profiling_supported {
+            GpuProfiler::required_features()
+        } else {
+            wgpu::Features::empty()
+        };
+
         let (device, queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
+                features,
                 // WebGL doesn't support all of wgpu's features, so if
                 // we're building for the web we'll have to disable some.
                 limits: if cfg!(target_arch = "wasm32") {
@@ -497,82 +1212,156 @@ impl Renderer {
             None, // Trace path
         ).await.unwrap();
 
-        // Configure the surface.
-        let size = window.inner_size();
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto
-        };
-        surface.configure(&device, &surface_config);
+        (device, queue, profiling_supported)
+    }
 
-        let post_process_renderer = PostProcessRenderer::new(&device, surface_config.format);
+    fn create_headless_texture(device: &Device, config: &SurfaceConfiguration) -> Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Output Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage
+        })
+    }
+
+    // Nothing picks which field to show yet, so always start on the test field.
+    async fn load_field_image(assets: &AssetServer) -> image::RgbaImage {
+        match assets.load_image("fields/test_field.png").await {
+            Ok(image) => image,
+            Err(e) => {
+                tracing::error!(target: targets::RENDERER, "{}", e);
+                image::RgbaImage::new(1, 1)
+            }
+        }
+    }
 
-        let field_background = FieldBackground::new(&device, &queue, Path::new("fields/test_field.png"));
-        let field_background_renderer = FieldBackgroundRenderer::new(&device, post_process_renderer.get_texture_format());
+    fn from_device(device: Device, queue: Queue, profiling_supported: bool, output: RenderOutput, surface_config: SurfaceConfiguration, field_image: &image::RgbaImage) -> Self {
+        let mut pipelines = PipelineCache::new();
+        let mut post_process_renderer = PostProcessRenderer::new(&device, &mut pipelines, surface_config.format);
+
+        let field_background = FieldBackground::from_image(&device, &queue, field_image);
+        let mut field_background_renderer = FieldBackgroundRenderer::new(&device, &mut pipelines, post_process_renderer.get_texture_format());
+        field_background_renderer.set_background(&device, &pipelines, &field_background);
+
+        let tilemap_renderer = TilemapRenderer::new(&device, &mut pipelines, post_process_renderer.get_texture_format());
+
+        let model_renderer = ModelRenderer::new(&device, &queue, &mut pipelines, post_process_renderer.get_texture_format());
+        post_process_renderer.set_depth_view(&device, &pipelines, model_renderer.depth_view());
+        let field_occluder_renderer = FieldOccluderRenderer::new(&device, &mut pipelines, post_process_renderer.get_texture_format());
+        let ui_renderer = UiRenderer::new(&device, &queue, &mut pipelines, post_process_renderer.get_texture_format());
+
+        let gpu_profiler = if profiling_supported {
+            Some(GpuProfiler::new(&device, &queue))
+        } else {
+            tracing::info!(target: targets::RENDERER, "Timestamp queries aren't supported, GPU profiling is disabled.");
+            None
+        };
 
         // Load shader.
-        let shader = device.create_shader_module(wgpu::include_wgsl!("main.wgsl"));
+        let shader = pipelines.load_shader(&device, wgpu::include_wgsl!("main.wgsl"));
 
         // Create a render pipeline.
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Main Window Render Pipeline Layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[]
-        });
-        
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Main Window Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::desc()
-                ],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: post_process_renderer.get_texture_format(),
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None
-        });
-        
+        let render_pipeline = pipelines.create_render_pipeline(&device, "Main Window Render Pipeline", PipelineKey::new(
+            shader,
+            &[],
+            vec![Vertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: post_process_renderer.get_texture_format(),
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        ));
+
         Self {
             device,
             queue,
+            pipelines,
             render_pipeline,
 
-            surface,
+            output,
             surface_config,
 
             post_process_renderer,
 
             field_background,
-            field_background_renderer
+            field_background_renderer,
+            tilemap_renderer,
+
+            model_renderer,
+            field_occluder_renderer,
+
+            ui_renderer,
+
+            gpu_profiler,
+
+            stats: RenderStats::default()
+        }
+    }
+
+    pub fn get_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    pub fn get_post_process_settings(&self) -> &PostProcessSettings {
+        self.post_process_renderer.get_settings()
+    }
+
+    pub fn get_post_process_settings_mut(&mut self) -> &mut PostProcessSettings {
+        self.post_process_renderer.get_settings_mut()
+    }
+
+    // Start or stop dumping GPU pass timings to a CSV file.
+    pub fn toggle_gpu_profile_capture(&mut self, path: &Path) {
+        let profiler = match &mut self.gpu_profiler {
+            Some(profiler) => profiler,
+            None => {
+                tracing::warn!(target: targets::RENDERER, "GPU profiling isn't supported on this adapter.");
+                return;
+            }
+        };
+
+        if profiler.is_capturing_csv() {
+            profiler.stop_csv_capture();
+            tracing::info!(target: targets::RENDERER, "Stopped GPU profile capture.");
+        } else if let Err(e) = profiler.start_csv_capture(path) {
+            tracing::error!(target: targets::RENDERER, "Couldn't start GPU profile capture to {}: {}", path.display(), e);
+        } else {
+            tracing::info!(target: targets::RENDERER, "Capturing GPU profile to {}.", path.display());
+        }
+    }
+
+    fn begin_gpu_pass(&mut self, name: &'static str) {
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.begin_pass(&self.device, &self.queue, name);
+        }
+    }
+
+    fn end_gpu_pass(&mut self) {
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.end_pass(&self.device, &self.queue);
+        }
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.surface_config.present_mode != wgpu::PresentMode::AutoNoVsync
+    }
+
+    // Without vsync the frame rate is only limited by the frame limiter, if at all.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.surface_config.present_mode = if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        };
+        if let RenderOutput::Window(surface) = &self.output {
+            surface.configure(&self.device, &self.surface_config);
         }
     }
 
@@ -580,13 +1369,35 @@ impl Renderer {
         if new_size.width > 0 && new_size.height > 0 {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            match &mut self.output {
+                RenderOutput::Window(surface) => surface.configure(&self.device, &self.surface_config),
+                RenderOutput::Headless(texture, view) => {
+                    *texture = Self::create_headless_texture(&self.device, &self.surface_config);
+                    *view = texture.create_view(&TextureViewDescriptor::default());
+                }
+            }
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    pub fn render(&mut self, model_batch: &ModelBatch, ui_batch: &UiBatch, overlay: Option<&mut dyn WindowOverlay>) -> Result<(), wgpu::SurfaceError> {
+        self.stats.draw_calls = 0;
+        self.stats.pass_timings.clear();
+        self.stats.texture_bytes = self.field_background.get_texture_bytes()
+            + self.post_process_renderer.get_texture_bytes()
+            + self.model_renderer.texture_bytes()
+            + self.field_background_renderer.texture_bytes()
+            + self.field_occluder_renderer.texture_bytes()
+            + self.tilemap_renderer.texture_bytes()
+            + self.ui_renderer.texture_bytes();
+
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.begin_frame(&self.device);
+            self.stats.gpu_pass_timings = profiler.get_results().to_vec();
+        }
+
         // Draw a background.
-        let view = self.post_process_renderer.get_texture().create_view(&wgpu::TextureViewDescriptor::default());
+        let start = Instant::now();
+        self.begin_gpu_pass("Clear");
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Main Encoder")
         });
@@ -595,7 +1406,7 @@ impl Renderer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("WaveSim_RenderPass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: self.post_process_renderer.get_view(),
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -611,7 +1422,7 @@ impl Renderer {
             });
 
             // Set render pipeline
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(self.pipelines.get_render_pipeline(self.render_pipeline));
 
             // Set the bind group
             //render_pass.set_bind_group(0, &bind_group, &[]);
@@ -624,18 +1435,267 @@ impl Renderer {
         }
 
         self.queue.submit(Some(encoder.finish()));
+        self.end_gpu_pass();
+        self.stats.time_pass("Clear", start);
+
+        // Draw the models mirrored in the field's reflections, for the background to show.
+        if !self.field_background_renderer.reflections().is_empty() {
+            let start = Instant::now();
+            self.begin_gpu_pass("Reflections");
+            if let Some(reflection_view) = self.field_background_renderer.begin_reflections(&self.device, &self.queue) {
+                for reflection in self.field_background_renderer.reflections() {
+                    let scissor = match reflection_scissor(reflection) {
+                        Some(scissor) => scissor,
+                        None => continue
+                    };
+                    let camera = model_batch.camera().reflected(reflection.point, reflection.normal);
+                    self.stats.draw_calls += self.model_renderer.render_reflection(&self.device, &self.queue, &self.pipelines, reflection_view, model_batch, &camera, scissor).draw_calls;
+                }
+            }
+            self.end_gpu_pass();
+            self.stats.time_pass("Reflections", start);
+        }
 
         // Draw the background.
-        self.field_background_renderer.render(&self.device, &self.queue, &view, &self.field_background);
+        let start = Instant::now();
+        self.begin_gpu_pass("Background");
+        self.field_background_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view());
+        self.end_gpu_pass();
+        self.stats.draw_calls += 1;
+        self.stats.time_pass("Background", start);
+
+        // 2D fields draw their tiles over it.
+        if !self.tilemap_renderer.is_empty() {
+            let start = Instant::now();
+            self.begin_gpu_pass("Tilemap");
+            self.stats.draw_calls += self.tilemap_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view(), false);
+            self.end_gpu_pass();
+            self.stats.time_pass("Tilemap", start);
+        }
 
-        // Do post processing and draw to the window.
-        let surface_texture = self.surface.get_current_texture()?;
-        let surface_texture_view = surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Draw models over the background.
+        let start = Instant::now();
+        self.begin_gpu_pass("Models");
+        let model_stats = self.model_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view(), model_batch);
+        self.end_gpu_pass();
+        self.stats.draw_calls += model_stats.draw_calls;
+        self.stats.model_instances = model_stats.instances;
+        self.stats.triangles = model_stats.triangles;
+        self.stats.models_culled = model_stats.culled;
+        self.stats.time_pass("Models", start);
+
+        // Draw the bits of background that are in front of the models.
+        if !self.field_occluder_renderer.is_empty() {
+            let start = Instant::now();
+            self.begin_gpu_pass("Occluders");
+            self.field_occluder_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view(), self.model_renderer.depth_view(), model_batch.camera());
+            self.end_gpu_pass();
+            self.stats.draw_calls += self.field_occluder_renderer.len() as u32;
+            self.stats.time_pass("Occluders", start);
+        }
 
-        self.post_process_renderer.render(&self.device, &self.queue, &surface_texture_view);
+        // And the tile layers that go over the models, like treetops and roofs.
+        if !self.tilemap_renderer.is_empty() {
+            let start = Instant::now();
+            self.begin_gpu_pass("Tilemap Above");
+            self.stats.draw_calls += self.tilemap_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view(), true);
+            self.end_gpu_pass();
+            self.stats.time_pass("Tilemap Above", start);
+        }
 
-        surface_texture.present();
+        // Draw the UI on top.
+        let start = Instant::now();
+        self.begin_gpu_pass("UI");
+        self.stats.draw_calls += self.ui_renderer.render(&self.device, &self.queue, &self.pipelines, self.post_process_renderer.get_view(), ui_batch);
+        self.end_gpu_pass();
+        self.stats.time_pass("UI", start);
+
+        // Do post processing and draw to the window. The window hands out a new texture each
+        // frame, so that's the one view that can't be kept around.
+        let surface_texture = match &self.output {
+            RenderOutput::Window(surface) => Some(surface.get_current_texture()?),
+            RenderOutput::Headless(..) => None
+        };
+        let surface_texture_view = surface_texture.as_ref()
+            .map(|surface_texture| surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        let start = Instant::now();
+        self.begin_gpu_pass("Post Process");
+        let size = winit::dpi::PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+        self.post_process_renderer.render(&self.device, &self.queue, &self.pipelines, self.output.view(surface_texture_view.as_ref()), size, model_batch.camera());
+        self.end_gpu_pass();
+        self.stats.draw_calls += 1;
+        self.stats.time_pass("Post Process", start);
+
+        if let Some(overlay) = overlay {
+            let start = Instant::now();
+            overlay.render(&self.device, &self.queue, self.output.view(surface_texture_view.as_ref()), self.surface_config.format, size);
+            self.stats.time_pass("Window Overlay", start);
+        }
+
+        if let Some(profiler) = &mut self.gpu_profiler {
+            profiler.end_frame(&self.device, &self.queue);
+        }
+
+        if let Some(surface_texture) = surface_texture {
+            surface_texture.present();
+        }
 
         Ok(())
     }
+
+    // Replace the field background, e.g. with a known image for a test.
+    pub fn set_field_background(&mut self, image: &image::RgbaImage) {
+        self.field_background = FieldBackground::from_image(&self.device, &self.queue, image);
+        self.field_background_renderer.set_background(&self.device, &self.pipelines, &self.field_background);
+    }
+
+    // Replace the field's reflections, e.g. when entering a field. Empty to have none.
+    pub fn set_field_reflections(&mut self, reflections: &[FieldReflection]) {
+        self.field_background_renderer.set_reflections(&self.device, &self.pipelines, reflections);
+    }
+
+    // Move the ripples in the field's reflections along. Returns whether there are any.
+    pub fn advance_ripples(&mut self, seconds: f32) -> bool {
+        self.field_background_renderer.advance_ripples(seconds)
+    }
+
+    // Add a piece of the background to draw over the models, from its cut out image.
+    pub fn add_field_occluder(&mut self, occluder: &FieldOccluder, image: &image::RgbaImage) {
+        self.field_occluder_renderer.add(&self.device, &self.queue, &self.pipelines, occluder, image);
+    }
+
+    // Remove all the occluders, e.g. when leaving a field.
+    pub fn clear_field_occluders(&mut self) {
+        self.field_occluder_renderer.clear();
+    }
+
+    // Draw a 2D field's tiles, replacing any from the last one.
+    pub fn set_tilemap(&mut self, map: &Tilemap) {
+        self.tilemap_renderer.set_map(&self.device, &self.queue, &self.pipelines, map);
+    }
+
+    // Stop drawing tiles, e.g. when going back to a pre-rendered field.
+    pub fn clear_tilemap(&mut self) {
+        self.tilemap_renderer.clear();
+    }
+
+    // Which part of the tilemap is on screen, as the top left in map pixels. See
+    // Tilemap::scroll_to.
+    pub fn set_tilemap_scroll(&mut self, scroll: [f32; 2]) {
+        self.tilemap_renderer.set_scroll(scroll);
+    }
+
+    // Recompile one of the renderer's shaders, e.g. "model.wgsl", from new source.
+    pub fn reload_shader(&mut self, name: &str, source: &str) -> bool {
+        self.pipelines.reload_shader(&self.device, name, source)
+    }
+
+    // Copy the last rendered frame back from the GPU. Only works for headless renderers.
+    pub fn read_pixels(&self) -> Option<image::RgbaImage> {
+        match &self.output {
+            RenderOutput::Headless(texture, _) => read_texture(&self.device, &self.queue, texture, self.surface_config.width, self.surface_config.height),
+            RenderOutput::Window(_) => None
+        }
+    }
+
+    // The game screen from the last frame, before post processing, at the virtual resolution.
+    // Used for save thumbnails.
+    pub fn capture_screen(&self) -> Option<image::RgbaImage> {
+        read_texture(&self.device, &self.queue, self.post_process_renderer.get_texture(), SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+    }
+
+    // Keep the last frame, for PostProcessSettings::transition to swirl away.
+    pub fn capture_transition_frame(&self) {
+        self.post_process_renderer.capture_transition_frame(&self.device, &self.queue);
+    }
+
+    pub fn create_model(&mut self, data: &ModelData) -> ModelId {
+        self.model_renderer.create_model(&self.device, &self.queue, &self.pipelines, data)
+    }
+
+    pub fn remove_model(&mut self, id: ModelId) {
+        self.model_renderer.remove_model(id);
+    }
+
+    pub fn set_model_lod_policy(&mut self, id: ModelId, policy: LodPolicy) {
+        self.model_renderer.set_lod_policy(id, policy);
+    }
+
+    pub fn lod_bias(&self) -> f32 {
+        self.model_renderer.lod_bias()
+    }
+
+    // Above 1 keeps detailed models for longer, below 1 switches to simpler ones sooner.
+    pub fn set_lod_bias(&mut self, bias: f32) {
+        self.model_renderer.set_lod_bias(bias);
+    }
+
+    // Draw UI text with this atlas, e.g. one with fonts for the player's language.
+    pub fn set_font(&mut self, atlas: &FontAtlas) {
+        self.ui_renderer.set_font(&self.device, &self.queue, &self.pipelines, atlas);
+    }
+
+    pub fn create_ui_image(&mut self, image: &image::RgbaImage) -> UiImageId {
+        self.ui_renderer.create_image(&self.device, &self.queue, &self.pipelines, image)
+    }
+
+    pub fn update_ui_image(&mut self, id: UiImageId, image: &image::RgbaImage) {
+        self.ui_renderer.update_image(&self.queue, id, image);
+    }
+
+    pub fn remove_ui_image(&mut self, id: UiImageId) {
+        self.ui_renderer.remove_image(id);
+    }
+}
+
+// Copy an RGBA8 texture back from the GPU. Blocks until the copy is done.
+fn read_texture(device: &Device, queue: &Queue, texture: &Texture, width: u32, height: u32) -> Option<image::RgbaImage> {
+    // Rows have to be padded out to a multiple of 256 bytes for the copy.
+    let unpadded_bytes_per_row = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture Readback Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Texture Readback Encoder")
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: std::num::NonZeroU32::new(height)
+            }
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        }
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    image::RgbaImage::from_raw(width, height, pixels)
 }
\ No newline at end of file
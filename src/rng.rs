@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use instant::SystemTime;
+
+// Names of the standard streams. Keeping systems on their own streams means e.g. opening a
+// chest doesn't change what the next battle does.
+pub mod streams {
+    pub const BATTLE: &str = "battle";
+    pub const ENCOUNTERS: &str = "encounters";
+    pub const LOOT: &str = "loot";
+}
+
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+// A single PCG32 random number generator. Small, fast and its whole state is two numbers,
+// so it's easy to save.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RngStream {
+    state: u64,
+    increment: u64
+}
+
+impl RngStream {
+    pub fn new(seed: u64, sequence: u64) -> Self {
+        let mut stream = Self {
+            state: 0,
+            // The increment must be odd.
+            increment: (sequence << 1) | 1
+        };
+        stream.next_u32();
+        stream.state = stream.state.wrapping_add(seed);
+        stream.next_u32();
+        stream
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.increment);
+
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    // A float in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    // A number in the range, without modulo bias.
+    pub fn range(&mut self, range: Range<u32>) -> u32 {
+        assert!(range.start < range.end, "Empty range");
+
+        let span = range.end - range.start;
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return range.start + value % span;
+            }
+        }
+    }
+
+    // The same as range, but with the end included, so it can go all the way up to u32::MAX.
+    // Backwards ranges always give min.
+    pub fn range_inclusive(&mut self, min: u32, max_inclusive: u32) -> u32 {
+        if max_inclusive < min {
+            return min;
+        }
+        match max_inclusive.checked_add(1) {
+            Some(end) => self.range(min..end),
+            None if min == 0 => self.next_u32(),
+            // One less from the bottom of the range so that the top fits, shifted back after.
+            None => self.range(min - 1..max_inclusive) + 1
+        }
+    }
+
+    // A number from min to max_inclusive. Backwards ranges, with max below min, always give min.
+    pub fn range_i32(&mut self, min: i32, max_inclusive: i32) -> i32 {
+        if max_inclusive < min {
+            return min;
+        }
+        match u32::try_from(max_inclusive as i64 - min as i64 + 1) {
+            Ok(span) => min.wrapping_add(self.range(0..span) as i32),
+            // All of i32, which is every u32 there is.
+            Err(_) => self.next_u32() as i32
+        }
+    }
+
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    // True with the given probability, from 0 to 1.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(&items[self.range(0..items.len() as u32) as usize])
+        }
+    }
+
+    // Pick an index with probability proportional to its weight. The weights can add up to
+    // more than a u32 holds.
+    pub fn weighted_index(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|weight| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = match u32::try_from(total) {
+            Ok(total) => self.range(0..total) as u64,
+            Err(_) => self.range_u64(total)
+        };
+        for (i, weight) in weights.iter().enumerate() {
+            if roll < *weight as u64 {
+                return Some(i);
+            }
+            roll -= *weight as u64;
+        }
+        None
+    }
+
+    // A number below `end`, like range but from two u32s.
+    fn range_u64(&mut self, end: u64) -> u64 {
+        let threshold = end.wrapping_neg() % end;
+        loop {
+            let value = ((self.next_u32() as u64) << 32) | self.next_u32() as u64;
+            if value >= threshold {
+                return value % end;
+            }
+        }
+    }
+}
+
+// Saved state of all of the streams, e.g. for a save file or a replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RngState {
+    pub seed: u64,
+    pub streams: Vec<(String, u64, u64)>
+}
+
+// The game's source of randomness. Each named stream is seeded from the master seed and
+// its name, so the same seed always gives the same results.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    seed: u64,
+    streams: BTreeMap<String, RngStream>
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: BTreeMap::new()
+        }
+    }
+
+    // Seeded from the clock, for normal play.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Get a stream, creating it if this is the first time it's been asked for.
+    pub fn stream(&mut self, name: &str) -> &mut RngStream {
+        let seed = self.seed;
+        self.streams.entry(name.to_string())
+            .or_insert_with(|| RngStream::new(seed, stable_hash(name)))
+    }
+
+    // Start a stream over from the beginning.
+    pub fn reset_stream(&mut self, name: &str) {
+        self.streams.remove(name);
+    }
+
+    pub fn save_state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            streams: self.streams.iter()
+                .map(|(name, stream)| (name.clone(), stream.state, stream.increment))
+                .collect()
+        }
+    }
+
+    pub fn restore_state(&mut self, state: &RngState) {
+        self.seed = state.seed;
+        self.streams = state.streams.iter()
+            .map(|(name, state, increment)| (name.clone(), RngStream { state: *state, increment: *increment }))
+            .collect();
+    }
+}
+
+// FNV-1a. std's hasher isn't guaranteed to give the same result between releases, which
+// would break old saves and replays.
+fn stable_hash(name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -0,0 +1,779 @@
+// Save files. A save is a header followed by tagged chunks, so new kinds of data can be added
+// without breaking old saves: readers skip chunks they don't know.
+//
+//   "PSRPGSAV" u32 version
+//   then repeated: [u8; 4] tag, u32 length, length bytes of data
+//
+// All numbers are little endian.
+//
+// Besides the slots there's a suspend save, written when the game's quit and deleted again as
+// soon as it's loaded. It lets the player stop anywhere without it being any use for going
+// back and trying something again, so save points still mean something. It's an ordinary save
+// with a SUSP chunk saying where the player was stood, and the battle they were in if they were
+// in one, enemies and all.
+//
+// Saves can also be exported to a file for sharing, like for a bug report or moving to another
+// machine. An export is a save wrapped up with which version of the game made it and a checksum,
+// so one that's been mangled on the way, or is from a newer game, is refused before it's
+// imported into a slot:
+//
+//   "PSRPGEXP" u32 export version
+//   u32 length, that many bytes of the game's version
+//   u32 checksum of the save, FNV-1a
+//   the save, to the end
+
+use std::{collections::BTreeMap, fmt, fs, io, path::{Path, PathBuf}};
+
+use cgmath::Vector3;
+use instant::SystemTime;
+
+use crate::battle_scene::BattleScene;
+use crate::flags::GameFlags;
+use crate::formation::{BattleEnemy, BattleSetup};
+use crate::game_clock::{GameClock, GameClockState};
+use crate::hotbar::Hotbar;
+use crate::inventory::{Inventory, ItemCatalog};
+use crate::logging::targets;
+use crate::party::{EquipSlot, Equipment, Party, PartyMember, Skill, Stats, TargetType};
+use crate::play_stats::{PlayStats, PlayStatsState};
+use crate::puzzle::{self, PuzzleState};
+use crate::rng::{Rng, RngState};
+use crate::world::World;
+
+const MAGIC: &[u8; 8] = b"PSRPGSAV";
+const VERSION: u32 = 1;
+
+const META_CHUNK: &[u8; 4] = b"META";
+const FLAGS_CHUNK: &[u8; 4] = b"FLAG";
+const RNG_CHUNK: &[u8; 4] = b"RNG ";
+const THUMBNAIL_CHUNK: &[u8; 4] = b"THMB";
+const STATS_CHUNK: &[u8; 4] = b"STAT";
+const CLOCK_CHUNK: &[u8; 4] = b"CLCK";
+const SUSPEND_CHUNK: &[u8; 4] = b"SUSP";
+const HOTBAR_CHUNK: &[u8; 4] = b"HOTB";
+const PUZZLE_CHUNK: &[u8; 4] = b"PUZL";
+const INVENTORY_CHUNK: &[u8; 4] = b"ITEM";
+const PARTY_CHUNK: &[u8; 4] = b"PRTY";
+
+const EXPORT_MAGIC: &[u8; 8] = b"PSRPGEXP";
+const EXPORT_VERSION: u32 = 1;
+
+pub const SLOT_COUNT: usize = 3;
+pub const EXPORT_EXTENSION: &str = "psrpgsave";
+pub const THUMBNAIL_WIDTH: u32 = 80;
+pub const THUMBNAIL_HEIGHT: u32 = 100;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(String)
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Format(e) => write!(f, "Bad save file: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+// Where the player was when they quit, for picking up from exactly there.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SuspendState {
+    pub field: String,
+    pub position: [f32; 3],
+    // Radians about y, from facing down z.
+    pub facing: f32,
+    // The battle, if they quit in the middle of one. Saves from before the enemies were kept
+    // only have its formation, and no enemies, so it's rolled again.
+    pub battle: Option<BattleSetup>
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveGame {
+    // Where the player saved, for the slot list.
+    pub location: String,
+    // Seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub flags: Vec<(String, i32)>,
+    pub rng: Option<RngState>,
+    // None for saves from before stats were kept.
+    pub stats: Option<PlayStatsState>,
+    // None for saves from before there was a time of day.
+    pub clock: Option<GameClockState>,
+    // None for saves from before there was a hotbar.
+    pub hotbar: Option<Hotbar>,
+    // Each item's id and how many, in the inventory's order. None for saves from before the
+    // inventory was saved.
+    pub inventory: Option<Vec<(String, u32)>>,
+    // Everyone in the party, as they are. None for saves from before the party was saved.
+    pub party: Option<Party>,
+    // How each field's puzzle was left, by field, see puzzle.rs.
+    pub puzzles: BTreeMap<String, PuzzleState>,
+    // A small picture of the screen when the game was saved.
+    pub thumbnail: Option<image::RgbaImage>,
+    // Only for the suspend save.
+    pub suspend: Option<SuspendState>
+}
+
+// Where a save slot lives in a save directory.
+pub fn slot_path(save_dir: &Path, slot: usize) -> PathBuf {
+    save_dir.join(format!("slot{}.sav", slot + 1))
+}
+
+// Where the suspend save lives in a save directory.
+pub fn suspend_path(save_dir: &Path) -> PathBuf {
+    save_dir.join("suspend.sav")
+}
+
+// Whether there's anything in any of the slots or a suspend save, for the title screen's
+// continue option.
+pub fn any_saves(save_dir: &Path) -> bool {
+    (0..SLOT_COUNT).any(|slot| slot_path(save_dir, slot).is_file()) || suspend_path(save_dir).is_file()
+}
+
+// Load the suspend save, deleting it. None if there isn't one. It's deleted before it's read,
+// so one that won't load doesn't get in the way forever, and if it can't be deleted it isn't
+// loaded either.
+pub fn take_suspend(save_dir: &Path) -> Result<Option<SaveGame>, SaveError> {
+    let path = suspend_path(save_dir);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into())
+    };
+    fs::remove_file(&path)?;
+    SaveGame::from_bytes(&bytes).map(Some)
+}
+
+// A save that's been exported, once it's been checked.
+#[derive(Clone, Debug)]
+pub struct ExportedSave {
+    // The version of the game that exported it, for warning about saves from other versions.
+    pub game_version: String,
+    // The save as it'd be in a slot.
+    pub bytes: Vec<u8>
+}
+
+impl ExportedSave {
+    // Wrap a slot's save up for exporting. Fails if the save won't load, so broken saves don't
+    // get passed around.
+    pub fn new(bytes: Vec<u8>) -> Result<Self, SaveError> {
+        SaveGame::from_bytes(&bytes)?;
+        Ok(Self { game_version: env!("CARGO_PKG_VERSION").to_string(), bytes })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(EXPORT_MAGIC);
+        bytes.extend_from_slice(&EXPORT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.game_version.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.game_version.as_bytes());
+        bytes.extend_from_slice(&checksum(&self.bytes).to_le_bytes());
+        bytes.extend_from_slice(&self.bytes);
+        bytes
+    }
+
+    // Unwrap an export, checking it's whole and that this version of the game can load it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        if bytes.len() < 16 || &bytes[..8] != EXPORT_MAGIC {
+            return Err(SaveError::Format("Not an exported save".to_string()));
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version > EXPORT_VERSION {
+            return Err(SaveError::Format(format!("Export is from a newer version ({})", version)));
+        }
+        let length = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        let truncated = || SaveError::Format("Truncated export".to_string());
+        // The length's straight from the file, so it could be anything.
+        let version_end = length.checked_add(16).ok_or_else(truncated)?;
+        let checksum_end = version_end.checked_add(4).ok_or_else(truncated)?;
+        let game_version = bytes.get(16..version_end).ok_or_else(truncated)?;
+        let game_version = String::from_utf8_lossy(game_version).to_string();
+        let expected = bytes.get(version_end..checksum_end).ok_or_else(truncated)?;
+        let save = &bytes[checksum_end..];
+        if checksum(save) != u32::from_le_bytes(expected.try_into().unwrap()) {
+            return Err(SaveError::Format("Export is damaged, its checksum doesn't match".to_string()));
+        }
+        // Saves from newer versions of the game are refused here.
+        SaveGame::from_bytes(save)?;
+        Ok(Self { game_version, bytes: save.to_vec() })
+    }
+}
+
+// Where a slot's export goes, and where an export for it is imported from.
+pub fn export_path(export_dir: &Path, slot: usize) -> PathBuf {
+    export_dir.join(format!("slot{}.{}", slot + 1, EXPORT_EXTENSION))
+}
+
+// Export a save file to a file of its own.
+pub fn export_save(save_path: &Path, export_path: &Path) -> Result<(), SaveError> {
+    let export = ExportedSave::new(fs::read(save_path)?)?;
+    write_file(export_path, &export.to_bytes())
+}
+
+// Import an exported save into a save file, replacing whatever was there. Returns what was
+// imported, to check which version it's from.
+pub fn import_save(export_path: &Path, save_path: &Path) -> Result<ExportedSave, SaveError> {
+    let export = ExportedSave::from_bytes(&fs::read(export_path)?)?;
+    write_file(save_path, &export.bytes)?;
+    Ok(export)
+}
+
+// Shrink a screen capture down to thumbnail size.
+pub fn make_thumbnail(screen: &image::RgbaImage) -> image::RgbaImage {
+    image::imageops::resize(screen, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, image::imageops::FilterType::Triangle)
+}
+
+impl SaveGame {
+    // Take everything that needs saving out of the world.
+    pub fn capture(world: &World, location: &str, thumbnail: Option<image::RgbaImage>) -> Self {
+        let saved_at = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            location: location.to_string(),
+            saved_at,
+            flags: world.resource::<GameFlags>()
+                .map(|flags| flags.iter().map(|(name, value)| (name.to_string(), value)).collect())
+                .unwrap_or_default(),
+            rng: world.resource::<Rng>().map(Rng::save_state),
+            stats: world.resource::<PlayStats>().map(PlayStats::save_state),
+            clock: world.resource::<GameClock>().map(GameClock::save_state),
+            hotbar: world.resource::<Hotbar>().cloned(),
+            inventory: world.resource::<Inventory>()
+                .map(|inventory| inventory.slots().iter().map(|slot| (slot.item.id.clone(), slot.count)).collect()),
+            party: world.resource::<Party>().cloned(),
+            puzzles: puzzle::snapshot(world),
+            thumbnail,
+            suspend: None
+        }
+    }
+
+    // Put the saved state back into the world.
+    pub fn apply(&self, world: &mut World) {
+        world.insert_resource(GameFlags::restore(self.flags.iter().cloned()));
+        if let Some(state) = &self.rng {
+            let mut rng = Rng::new(state.seed);
+            rng.restore_state(state);
+            world.insert_resource(rng);
+        }
+        // Older saves start counting from here.
+        world.insert_resource(self.stats.as_ref().map_or_else(PlayStats::new, PlayStats::restore));
+        world.insert_resource(self.clock.as_ref().map_or_else(GameClock::new, GameClock::restore));
+        // Older saves keep whatever's there.
+        if let Some(hotbar) = &self.hotbar {
+            world.insert_resource(hotbar.clone());
+        }
+        // Items are looked up again, so changes to them show. Ones that have gone from the game
+        // since are left out.
+        if let Some(saved) = &self.inventory {
+            let items = world.resource::<ItemCatalog>().cloned().unwrap_or_default();
+            let mut inventory = Inventory::new();
+            for (id, count) in saved {
+                match items.get(id) {
+                    Some(item) => inventory.add(item, *count),
+                    None => tracing::warn!(target: targets::ENGINE, "The save has {} {}, which isn't an item any more", count, id)
+                }
+            }
+            world.insert_resource(inventory);
+        }
+        if let Some(party) = &self.party {
+            world.insert_resource(party.clone());
+        }
+        puzzle::load(world, self.puzzles.clone());
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SaveError> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        let meta = format!("location={}\nsaved_at={}\n", self.location, self.saved_at);
+        write_chunk(&mut bytes, META_CHUNK, meta.as_bytes());
+
+        let flags: String = self.flags.iter().map(|(name, value)| format!("{}={}\n", name, value)).collect();
+        write_chunk(&mut bytes, FLAGS_CHUNK, flags.as_bytes());
+
+        if let Some(rng) = &self.rng {
+            let mut text = format!("seed={}\n", rng.seed);
+            for (name, state, increment) in &rng.streams {
+                text.push_str(&format!("{}={},{}\n", name, state, increment));
+            }
+            write_chunk(&mut bytes, RNG_CHUNK, text.as_bytes());
+        }
+
+        if let Some(stats) = &self.stats {
+            let text = format!("playtime={}\nsteps={}\nbattles_won={}\ngil_earned={}\n",
+                stats.playtime_seconds, stats.steps, stats.battles_won, stats.gil_earned);
+            write_chunk(&mut bytes, STATS_CHUNK, text.as_bytes());
+        }
+
+        if let Some(clock) = &self.clock {
+            let text = format!("day={}\nminute={}\n", clock.day, clock.minute);
+            write_chunk(&mut bytes, CLOCK_CHUNK, text.as_bytes());
+        }
+
+        if let Some(hotbar) = &self.hotbar {
+            let text: String = hotbar.slots().map(|(slot, quick)| format!("{}={}\n", slot + 1, quick)).collect();
+            write_chunk(&mut bytes, HOTBAR_CHUNK, text.as_bytes());
+        }
+
+        if let Some(inventory) = &self.inventory {
+            let text: String = inventory.iter().map(|(id, count)| format!("{}={}\n", id, count)).collect();
+            write_chunk(&mut bytes, INVENTORY_CHUNK, text.as_bytes());
+        }
+
+        if let Some(party) = &self.party {
+            write_chunk(&mut bytes, PARTY_CHUNK, write_party(party).as_bytes());
+        }
+
+        if !self.puzzles.is_empty() {
+            let mut text = String::new();
+            for (field, state) in &self.puzzles {
+                for (flag, value) in &state.flags {
+                    text.push_str(&format!("flag {} {}={}\n", field, flag, value));
+                }
+                for (piece, position) in &state.pieces {
+                    text.push_str(&format!("piece {} {}={},{},{}\n", field, piece, position.x, position.y, position.z));
+                }
+            }
+            write_chunk(&mut bytes, PUZZLE_CHUNK, text.as_bytes());
+        }
+
+        if let Some(suspend) = &self.suspend {
+            let [x, y, z] = suspend.position;
+            let mut text = format!("field={}\nposition={},{},{}\nfacing={}\n", suspend.field, x, y, z, suspend.facing);
+            if let Some(battle) = &suspend.battle {
+                text.push_str(&write_battle(battle));
+            }
+            write_chunk(&mut bytes, SUSPEND_CHUNK, text.as_bytes());
+        }
+
+        if let Some(thumbnail) = &self.thumbnail {
+            let mut png = Vec::new();
+            thumbnail.write_to(&mut io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+                .map_err(|e| SaveError::Format(e.to_string()))?;
+            write_chunk(&mut bytes, THUMBNAIL_CHUNK, &png);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        if bytes.len() < 12 || &bytes[..8] != MAGIC {
+            return Err(SaveError::Format("Not a save file".to_string()));
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version > VERSION {
+            return Err(SaveError::Format(format!("Save is from a newer version ({})", version)));
+        }
+
+        let mut save = Self {
+            location: String::new(),
+            saved_at: 0,
+            flags: Vec::new(),
+            rng: None,
+            stats: None,
+            clock: None,
+            hotbar: None,
+            inventory: None,
+            party: None,
+            puzzles: BTreeMap::new(),
+            thumbnail: None,
+            suspend: None
+        };
+
+        let mut rest = &bytes[12..];
+        while !rest.is_empty() {
+            if rest.len() < 8 {
+                return Err(SaveError::Format("Truncated chunk header".to_string()));
+            }
+            let tag: [u8; 4] = rest[..4].try_into().unwrap();
+            let length = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+            let data = rest.get(8..8 + length).ok_or_else(|| SaveError::Format("Truncated chunk".to_string()))?;
+            rest = &rest[8 + length..];
+
+            match &tag {
+                META_CHUNK => for (key, value) in key_values(data) {
+                    match key {
+                        "location" => save.location = value.to_string(),
+                        "saved_at" => save.saved_at = value.parse().unwrap_or_default(),
+                        _ => {}
+                    }
+                },
+                FLAGS_CHUNK => for (name, value) in key_values(data) {
+                    let value = value.parse().map_err(|_| SaveError::Format(format!("Bad flag value for {}", name)))?;
+                    save.flags.push((name.to_string(), value));
+                },
+                RNG_CHUNK => save.rng = Some(parse_rng(data)?),
+                STATS_CHUNK => save.stats = Some(parse_stats(data)?),
+                CLOCK_CHUNK => save.clock = Some(parse_clock(data)?),
+                HOTBAR_CHUNK => save.hotbar = Some(parse_hotbar(data)?),
+                INVENTORY_CHUNK => save.inventory = Some(parse_inventory(data)?),
+                PARTY_CHUNK => save.party = Some(parse_party(data)?),
+                PUZZLE_CHUNK => save.puzzles = parse_puzzles(data)?,
+                SUSPEND_CHUNK => save.suspend = Some(parse_suspend(data)?),
+                THUMBNAIL_CHUNK => save.thumbnail = image::load_from_memory(data).ok().map(|image| image.to_rgba8()),
+                // From a newer version, or something we don't need.
+                _ => {}
+            }
+        }
+
+        Ok(save)
+    }
+
+    pub fn write_to(&self, path: &Path) -> Result<(), SaveError> {
+        write_file(path, &self.to_bytes()?)
+    }
+
+    pub fn read_from(path: &Path) -> Result<Self, SaveError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), SaveError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Write to a temporary file first so a crash can't leave a half written save.
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// FNV-1a, enough to spot an export that's been damaged on the way.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(tag);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+fn key_values(data: &[u8]) -> impl Iterator<Item = (&str, &str)> {
+    std::str::from_utf8(data).unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+}
+
+fn parse_rng(data: &[u8]) -> Result<RngState, SaveError> {
+    let bad = || SaveError::Format("Bad random number state".to_string());
+    let mut state = RngState {
+        seed: 0,
+        streams: Vec::new()
+    };
+    for (key, value) in key_values(data) {
+        if key == "seed" {
+            state.seed = value.parse().map_err(|_| bad())?;
+        } else {
+            let (stream_state, increment) = value.split_once(',').ok_or_else(bad)?;
+            state.streams.push((key.to_string(), stream_state.parse().map_err(|_| bad())?, increment.parse().map_err(|_| bad())?));
+        }
+    }
+    Ok(state)
+}
+
+fn parse_stats(data: &[u8]) -> Result<PlayStatsState, SaveError> {
+    let bad = |key: &str| SaveError::Format(format!("Bad value for stat {}", key));
+    let mut stats = PlayStatsState::default();
+    for (key, value) in key_values(data) {
+        match key {
+            "playtime" => stats.playtime_seconds = value.parse().map_err(|_| bad(key))?,
+            "steps" => stats.steps = value.parse().map_err(|_| bad(key))?,
+            "battles_won" => stats.battles_won = value.parse().map_err(|_| bad(key))?,
+            "gil_earned" => stats.gil_earned = value.parse().map_err(|_| bad(key))?,
+            // Stats added in a newer version.
+            _ => {}
+        }
+    }
+    Ok(stats)
+}
+
+fn parse_clock(data: &[u8]) -> Result<GameClockState, SaveError> {
+    let bad = |key: &str| SaveError::Format(format!("Bad value for clock {}", key));
+    let mut clock = GameClockState::default();
+    for (key, value) in key_values(data) {
+        match key {
+            "day" => clock.day = value.parse().map_err(|_| bad(key))?,
+            "minute" => clock.minute = value.parse().map_err(|_| bad(key))?,
+            _ => {}
+        }
+    }
+    Ok(clock)
+}
+
+fn parse_hotbar(data: &[u8]) -> Result<Hotbar, SaveError> {
+    let mut hotbar = Hotbar::new();
+    for (key, value) in key_values(data) {
+        let bad = || SaveError::Format(format!("Bad hotbar slot {}", key));
+        let slot = key.parse::<usize>().ok().and_then(|slot| slot.checked_sub(1)).ok_or_else(bad)?;
+        // Slots past the last are from a newer version with more, and are left out.
+        hotbar.set(slot, Some(value.parse().map_err(|_| bad())?));
+    }
+    Ok(hotbar)
+}
+
+fn parse_inventory(data: &[u8]) -> Result<Vec<(String, u32)>, SaveError> {
+    key_values(data)
+        .map(|(id, count)| match count.parse() {
+            Ok(count) => Ok((id.to_string(), count)),
+            Err(_) => Err(SaveError::Format(format!("Bad item count for {}", id)))
+        })
+        .collect()
+}
+
+// Lines like "flag cave switch_a=1" and "piece cave block=1.5,0,-3".
+fn parse_puzzles(data: &[u8]) -> Result<BTreeMap<String, PuzzleState>, SaveError> {
+    let mut puzzles: BTreeMap<String, PuzzleState> = BTreeMap::new();
+    for (key, value) in key_values(data) {
+        let bad = || SaveError::Format(format!("Bad puzzle state {}", key));
+        let mut words = key.split_whitespace();
+        let (kind, field, name) = match (words.next(), words.next(), words.next()) {
+            (Some(kind), Some(field), Some(name)) => (kind, field, name),
+            _ => return Err(bad())
+        };
+        let state = puzzles.entry(field.to_string()).or_default();
+        match kind {
+            "flag" => {
+                state.flags.insert(name.to_string(), value.parse().map_err(|_| bad())?);
+            },
+            "piece" => {
+                let position: Vec<f32> = value.split(',').map(str::parse).collect::<Result<_, _>>().map_err(|_| bad())?;
+                let [x, y, z]: [f32; 3] = position.try_into().map_err(|_| bad())?;
+                state.pieces.insert(name.to_string(), Vector3::new(x, y, z));
+            },
+            // From a newer version.
+            _ => {}
+        }
+    }
+    Ok(puzzles)
+}
+
+fn parse_suspend(data: &[u8]) -> Result<SuspendState, SaveError> {
+    let bad = |key: &str| SaveError::Format(format!("Bad value for suspend {}", key));
+    let mut suspend = SuspendState::default();
+    for (key, value) in key_values(data) {
+        match key {
+            "field" => suspend.field = value.to_string(),
+            "position" => {
+                let position: Vec<f32> = value.split(',').map(str::parse).collect::<Result<_, _>>().map_err(|_| bad(key))?;
+                suspend.position = position.try_into().map_err(|_| bad(key))?;
+            },
+            "facing" => suspend.facing = value.parse().map_err(|_| bad(key))?,
+            _ => {}
+        }
+    }
+    suspend.battle = parse_battle(data)?;
+    Ok(suspend)
+}
+
+// The battle's formation, how it started and each enemy as
+// "enemy=row,x,y,reach,boss,enemy id", with reach and boss as 1 or 0.
+fn write_battle(battle: &BattleSetup) -> String {
+    let mut text = format!("battle={}\nescape={}\nback_attack={}\n", battle.formation, battle.can_escape as u8, battle.back_attack as u8);
+    for (key, value) in [("intro", &battle.intro), ("backdrop", &battle.scene.backdrop), ("music", &battle.scene.music)] {
+        if let Some(value) = value {
+            text.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    for enemy in &battle.enemies {
+        let [x, y] = enemy.position;
+        text.push_str(&format!("enemy={},{},{},{},{},{}\n", enemy.row, x, y, enemy.reach as u8, enemy.boss as u8, enemy.enemy));
+    }
+    text
+}
+
+fn parse_battle(data: &[u8]) -> Result<Option<BattleSetup>, SaveError> {
+    let bad = |key: &str| SaveError::Format(format!("Bad value for suspended battle {}", key));
+    let flag = |key: &str, value: &str| match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(bad(key))
+    };
+    let mut battle: Option<BattleSetup> = None;
+    for (key, value) in key_values(data) {
+        if key == "battle" {
+            battle = Some(BattleSetup {
+                formation: value.to_string(),
+                enemies: Vec::new(),
+                intro: None,
+                can_escape: true,
+                back_attack: false,
+                scene: BattleScene::default()
+            });
+            continue;
+        }
+        let battle = match battle.as_mut() {
+            Some(battle) => battle,
+            None => continue
+        };
+        match key {
+            "escape" => battle.can_escape = flag(key, value)?,
+            "back_attack" => battle.back_attack = flag(key, value)?,
+            "intro" => battle.intro = Some(value.to_string()),
+            "backdrop" => battle.scene.backdrop = Some(value.to_string()),
+            "music" => battle.scene.music = Some(value.to_string()),
+            "enemy" => {
+                let parts: Vec<&str> = value.splitn(6, ',').collect();
+                let [row, x, y, reach, boss, enemy]: [&str; 6] = parts.try_into().map_err(|_| bad(key))?;
+                battle.enemies.push(BattleEnemy {
+                    enemy: enemy.to_string(),
+                    row: row.parse().map_err(|_| bad(key))?,
+                    position: [x.parse().map_err(|_| bad(key))?, y.parse().map_err(|_| bad(key))?],
+                    reach: flag(key, reach)?,
+                    boss: flag(key, boss)?
+                });
+            },
+            _ => {}
+        }
+    }
+    Ok(battle)
+}
+
+// Each member starts with "member=name", followed by everything about them. A skill starts
+// with "skill=name", and the skill_ lines after it are about that skill. Equipment is
+// "equip=slot,reach,name" with reach as 1 or 0.
+fn write_party(party: &Party) -> String {
+    let mut text = String::new();
+    let mut line = |key: &str, value: &dyn fmt::Display| text.push_str(&format!("{}={}\n", key, value));
+    for member in &party.members {
+        let stats = &member.stats;
+        line("member", &member.name);
+        line("gender", &member.gender);
+        for (key, value) in [
+            ("level", stats.level), ("experience", stats.experience), ("hp", stats.hp), ("max_hp", stats.max_hp), ("mp", stats.mp),
+            ("max_mp", stats.max_mp), ("strength", stats.strength), ("magic", stats.magic), ("defence", stats.defence), ("speed", stats.speed)
+        ] {
+            line(key, &value);
+        }
+        for slot in EquipSlot::ALL {
+            if let Some(equipment) = member.equipped(slot) {
+                line("equip", &format!("{},{},{}", slot, equipment.reach as u8, equipment.name));
+            }
+        }
+        line("row", &member.row);
+        line("auto", &(member.auto_battle as u8));
+        for gambit in &member.gambits {
+            line("gambit", gambit);
+        }
+        for effect in &member.status_effects {
+            line("status", effect);
+        }
+        for (pool, amount) in &member.pools {
+            line("pool", &format!("{} {}", pool, amount));
+        }
+        for skill in &member.skills {
+            line("skill", &skill.name);
+            line("skill_description", &skill.description);
+            line("skill_target", &skill.target);
+            line("skill_mp", &skill.mp_cost);
+            for cost in &skill.costs {
+                line("skill_cost", cost);
+            }
+            if let Some(effect) = &skill.field {
+                line("skill_field", effect);
+            }
+        }
+    }
+    text
+}
+
+fn parse_party(data: &[u8]) -> Result<Party, SaveError> {
+    let mut party = Party::default();
+    for (key, value) in key_values(data) {
+        let bad = || SaveError::Format(format!("Bad value for party {}", key));
+        if key == "member" {
+            let mut member = PartyMember::new(value, Stats::default());
+            // Their own are in the save.
+            member.gambits.clear();
+            party.members.push(member);
+            continue;
+        }
+        let member = party.members.last_mut().ok_or_else(bad)?;
+        let number = |value: &str| value.parse::<u32>().map_err(|_| bad());
+        let stats = &mut member.stats;
+        match key {
+            "gender" => member.gender = value.parse().map_err(|_| bad())?,
+            "level" => stats.level = number(value)?,
+            "experience" => stats.experience = number(value)?,
+            "hp" => stats.hp = number(value)?,
+            "max_hp" => stats.max_hp = number(value)?,
+            "mp" => stats.mp = number(value)?,
+            "max_mp" => stats.max_mp = number(value)?,
+            "strength" => stats.strength = number(value)?,
+            "magic" => stats.magic = number(value)?,
+            "defence" => stats.defence = number(value)?,
+            "speed" => stats.speed = number(value)?,
+            "equip" => {
+                let parts: Vec<&str> = value.splitn(3, ',').collect();
+                let [slot, reach, name]: [&str; 3] = parts.try_into().map_err(|_| bad())?;
+                let slot: EquipSlot = slot.parse().map_err(|_| bad())?;
+                member.equip(slot, Some(Equipment { name: name.to_string(), reach: reach == "1" }));
+            },
+            "row" => member.row = value.parse().map_err(|_| bad())?,
+            "auto" => member.auto_battle = value == "1",
+            "gambit" => member.gambits.push(value.parse().map_err(|_| bad())?),
+            "status" => member.status_effects.push(value.parse().map_err(|_| bad())?),
+            "pool" => {
+                let (pool, amount) = value.split_once(' ').ok_or_else(bad)?;
+                member.pools.insert(pool.to_string(), number(amount)?);
+            },
+            "skill" => member.skills.push(Skill {
+                name: value.to_string(),
+                description: String::new(),
+                mp_cost: 0,
+                costs: Vec::new(),
+                target: TargetType::Enemy,
+                field: None
+            }),
+            _ if key.starts_with("skill_") => {
+                let skill = member.skills.last_mut().ok_or_else(bad)?;
+                match key {
+                    "skill_description" => skill.description = value.to_string(),
+                    "skill_target" => skill.target = value.parse().map_err(|_| bad())?,
+                    "skill_mp" => skill.mp_cost = number(value)?,
+                    "skill_cost" => skill.costs.push(value.parse().map_err(|_| bad())?),
+                    "skill_field" => skill.field = Some(value.parse().map_err(|_| bad())?),
+                    _ => {}
+                }
+            },
+            // From a newer version.
+            _ => {}
+        }
+    }
+    Ok(party)
+}
+
+// "2026-10-17 14:05" in UTC, for showing when a save was made.
+pub fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // Days since 1970 to a civil date, from Howard Hinnant's date algorithms.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, time / 3600, (time % 3600) / 60)
+}
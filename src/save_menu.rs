@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::menu::MenuSound;
+use crate::play_stats;
+use crate::pointer::{PointerEvent, PointerResponse};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::save::{self, SaveGame, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT};
+use crate::ui::{Color, UiBatch, UiImageId, WHITE};
+
+const MARGIN: f32 = 12.0;
+const EMPTY_THUMBNAIL: Color = [0.0, 0.0, 0.0, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveMenuMode {
+    Save,
+    Load
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveMenuAction {
+    Save(usize),
+    Load(usize),
+    // Copy the slot's save out to a file for sharing.
+    Export(usize),
+    // Replace what's in the slot with an exported save.
+    Import(usize)
+}
+
+// What the menu shows for a slot that has a save in it.
+pub struct SlotInfo {
+    pub location: String,
+    pub saved_at: u64,
+    // None for saves from before play time was kept.
+    pub playtime: Option<Duration>,
+    // The save's thumbnail, already uploaded to the renderer.
+    pub thumbnail: Option<UiImageId>
+}
+
+impl SlotInfo {
+    pub fn new(save: &SaveGame, thumbnail: Option<UiImageId>) -> Self {
+        Self {
+            location: save.location.clone(),
+            saved_at: save.saved_at,
+            playtime: save.stats.map(|stats| Duration::from_secs(stats.playtime_seconds)),
+            thumbnail
+        }
+    }
+}
+
+// The list of save slots, for picking one to save into or load from.
+pub struct SaveMenu {
+    mode: Option<SaveMenuMode>,
+    slots: Vec<Option<SlotInfo>>,
+    // Kept when it closes, so it opens where it was.
+    selected: usize,
+    sound: Option<MenuSound>
+}
+
+impl Default for SaveMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SaveMenu {
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            slots: Vec::new(),
+            selected: 0,
+            sound: None
+        }
+    }
+
+    pub fn mode(&self) -> Option<SaveMenuMode> {
+        self.mode
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    // Open the menu with what's in each slot, None for empty ones.
+    pub fn open(&mut self, mode: SaveMenuMode, slots: Vec<Option<SlotInfo>>) {
+        self.mode = Some(mode);
+        self.slots = slots;
+        self.selected = self.selected.min(self.slots.len().saturating_sub(1));
+    }
+
+    // The sound the last key or pointer event made, see menu.rs.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    // Close the menu, giving back the thumbnails so they can be removed from the renderer.
+    pub fn close(&mut self) -> Vec<UiImageId> {
+        self.mode = None;
+        self.slots.drain(..).flatten().filter_map(|slot| slot.thumbnail).collect()
+    }
+
+    // Move the selection with up and down, pick a slot with enter and close with escape. E
+    // exports the slot and I imports into it. Returns what to do when a slot is picked. Escape closes the menu, so check is_open
+    // afterwards to know whether to call close.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<SaveMenuAction> {
+        let mode = self.mode?;
+        match key {
+            VirtualKeyCode::Up if self.selected > 0 => self.select(self.selected - 1),
+            VirtualKeyCode::Down if self.selected + 1 < self.slots.len() => self.select(self.selected + 1),
+            VirtualKeyCode::Return => return self.pick(mode),
+            VirtualKeyCode::E if self.has_save(self.selected) => return self.confirm(SaveMenuAction::Export(self.selected)),
+            VirtualKeyCode::E => self.sound = Some(MenuSound::Error),
+            VirtualKeyCode::I => return self.confirm(SaveMenuAction::Import(self.selected)),
+            VirtualKeyCode::Escape => self.cancel(),
+            _ => {}
+        }
+        None
+    }
+
+    // The same for the mouse and touch. Hovering over a slot selects it, clicking or tapping
+    // picks it and scrolling moves the selection. Clicking outside the slots or right clicking
+    // closes the menu, like escape.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> PointerResponse<SaveMenuAction> {
+        let before = (self.mode, self.selected);
+        let action = self.pointer(event, accessibility);
+        PointerResponse::new(action, before != (self.mode, self.selected))
+    }
+
+    fn pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> Option<SaveMenuAction> {
+        let mode = self.mode?;
+        let layout = Layout::new(accessibility);
+        match event {
+            PointerEvent::Hover { x, y } => {
+                if let Some(index) = layout.slot_at(x, y, self.slots.len()) {
+                    self.select(index);
+                }
+            },
+            PointerEvent::Click { x, y } => match layout.slot_at(x, y, self.slots.len()) {
+                Some(index) => {
+                    self.select(index);
+                    return self.pick(mode);
+                },
+                None => self.cancel()
+            },
+            PointerEvent::Scroll { steps } => {
+                let last = self.slots.len().saturating_sub(1) as i64;
+                self.select((self.selected as i64 + steps as i64).clamp(0, last) as usize);
+            },
+            PointerEvent::Cancel => self.cancel()
+        }
+        None
+    }
+
+    fn select(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.sound = Some(MenuSound::Move);
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.mode = None;
+        self.sound = Some(MenuSound::Cancel);
+    }
+
+    fn confirm(&mut self, action: SaveMenuAction) -> Option<SaveMenuAction> {
+        self.sound = Some(MenuSound::Confirm);
+        Some(action)
+    }
+
+    fn pick(&mut self, mode: SaveMenuMode) -> Option<SaveMenuAction> {
+        match mode {
+            SaveMenuMode::Save => self.confirm(SaveMenuAction::Save(self.selected)),
+            // Nothing to load from an empty slot.
+            SaveMenuMode::Load if self.has_save(self.selected) => self.confirm(SaveMenuAction::Load(self.selected)),
+            SaveMenuMode::Load => {
+                self.sound = Some(MenuSound::Error);
+                None
+            }
+        }
+    }
+
+    fn has_save(&self, slot: usize) -> bool {
+        matches!(self.slots.get(slot), Some(Some(_)))
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        let mode = match self.mode {
+            Some(mode) => mode,
+            None => return
+        };
+
+        let skin = accessibility.skin();
+        let layout = Layout::new(accessibility);
+        let (scale, line_height) = (layout.scale, layout.line_height);
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, skin.backdrop);
+
+        let title = match mode {
+            SaveMenuMode::Save => "Save",
+            SaveMenuMode::Load => "Load"
+        };
+        batch.text(MARGIN, MARGIN, scale * 1.5, title, skin.text);
+        let hint = "E Export  I Import";
+        batch.text(SCREEN_WIDTH as f32 - MARGIN - UiBatch::measure_text(scale, hint).0, MARGIN, scale, hint, skin.dim_text);
+
+        for (index, slot) in self.slots.iter().enumerate() {
+            let y = layout.slot_y(index);
+            let background = if index == self.selected { skin.selected } else { skin.window };
+            batch.rect(MARGIN, y, layout.width, layout.slot_height, background);
+
+            let thumbnail_x = MARGIN * 2.0;
+            let text_x = thumbnail_x + THUMBNAIL_WIDTH as f32 + MARGIN;
+            let label = format!("Slot {}", index + 1);
+            match slot.as_ref().and_then(|slot| slot.thumbnail) {
+                Some(thumbnail) => batch.image(thumbnail_x, y + MARGIN, THUMBNAIL_WIDTH as f32, THUMBNAIL_HEIGHT as f32, thumbnail, WHITE),
+                None => batch.rect(thumbnail_x, y + MARGIN, THUMBNAIL_WIDTH as f32, THUMBNAIL_HEIGHT as f32, EMPTY_THUMBNAIL)
+            }
+
+            batch.text(text_x, y + MARGIN, scale, &label, skin.text);
+            match slot {
+                Some(slot) => {
+                    batch.text(text_x, y + MARGIN + line_height, scale, &slot.location, skin.text);
+                    batch.text(text_x, y + MARGIN + line_height * 2.0, scale, &save::format_timestamp(slot.saved_at), skin.dim_text);
+                    if let Some(playtime) = slot.playtime {
+                        batch.text(text_x, y + MARGIN + line_height * 3.0, scale, &format!("Time {}", play_stats::format_playtime(playtime)), skin.dim_text);
+                    }
+                },
+                None => {
+                    batch.text(text_x, y + MARGIN + line_height, scale, "Empty", skin.dim_text);
+                }
+            }
+        }
+    }
+}
+
+// Where the menu's slots go, shared by drawing and hit testing.
+struct Layout {
+    scale: f32,
+    line_height: f32,
+    first_slot_y: f32,
+    slot_height: f32,
+    width: f32
+}
+
+impl Layout {
+    fn new(accessibility: &Accessibility) -> Self {
+        let scale = 2.0 * accessibility.text_scale();
+        let line_height = UiBatch::line_height(scale);
+        Self {
+            scale,
+            line_height,
+            // Below the title.
+            first_slot_y: MARGIN * 2.0 + UiBatch::line_height(scale * 1.5),
+            // Tall enough for four lines of text, when it's been made big enough to need it.
+            slot_height: (THUMBNAIL_HEIGHT as f32).max(line_height * 4.0) + MARGIN * 2.0,
+            width: SCREEN_WIDTH as f32 - MARGIN * 2.0
+        }
+    }
+
+    fn slot_y(&self, index: usize) -> f32 {
+        self.first_slot_y + (self.slot_height + MARGIN) * index as f32
+    }
+
+    fn slot_at(&self, x: f32, y: f32, slot_count: usize) -> Option<usize> {
+        if !(MARGIN..MARGIN + self.width).contains(&x) {
+            return None;
+        }
+        (0..slot_count).find(|index| {
+            let top = self.slot_y(*index);
+            (top..top + self.slot_height).contains(&y)
+        })
+    }
+}
@@ -0,0 +1,307 @@
+// Where NPCs are through the day, like the shopkeeper who's at their stall by day and at home
+// by night. Schedules are in data/npcs.cfg:
+//
+// [Shopkeeper]
+// model = models/shopkeeper.gltf
+// 06:00 = town 1.5 0 -3 shopkeeping
+// 20:00 = town_house 0 0 1 sleeping
+//
+// Each "time = field x y z behaviour" line is where the NPC goes from then until the next one,
+// going round to the first again after midnight. The behaviour's what they're doing there,
+// for whatever animates them or talks to them. Nothing's simulated in fields the player isn't
+// in: NPCs are just put where they should be when the player arrives, and walk between their
+// spots while the player's there to see.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+
+use crate::assets::AssetServer;
+use crate::field::{self, FieldDescriptor, FieldEntity};
+use crate::game_clock::{self, GameClock};
+use crate::interaction::{Interactable, InteractionKind};
+use crate::logging::targets;
+use crate::model::ModelData;
+use crate::renderer::Renderer;
+use crate::transform::Transform;
+use crate::world::{Entity, Name, World};
+
+// Metres a second, or pixels in 2D fields.
+pub const DEFAULT_WALK_SPEED: f32 = 1.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleEntry {
+    // Minutes since midnight.
+    pub start: u32,
+    pub field: String,
+    pub position: Vector3<f32>,
+    pub behaviour: String
+}
+
+// An NPC's day, in order of time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+    entries: Vec<ScheduleEntry>
+}
+
+impl Schedule {
+    pub fn new(mut entries: Vec<ScheduleEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.start);
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[ScheduleEntry] {
+        &self.entries
+    }
+
+    // Which entry's in charge at a time of day. Before the first one it's still the last
+    // one from the night before.
+    pub fn index_at(&self, minute: u32) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        Some(self.entries.iter().rposition(|entry| entry.start <= minute).unwrap_or(self.entries.len() - 1))
+    }
+
+    pub fn at(&self, minute: u32) -> Option<&ScheduleEntry> {
+        self.entries.get(self.index_at(minute)?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpcDefinition {
+    pub name: String,
+    pub model: String,
+    pub speed: f32,
+    pub schedule: Schedule
+}
+
+impl NpcDefinition {
+    // See the top of the file for what these look like.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut npcs: Vec<NpcDefinition> = Vec::new();
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                if let Some(npc) = npcs.last_mut() {
+                    npc.schedule = Schedule::new(std::mem::take(&mut entries));
+                }
+                npcs.push(NpcDefinition { name: name.trim().to_string(), model: String::new(), speed: DEFAULT_WALK_SPEED, schedule: Schedule::default() });
+                continue;
+            }
+
+            let npc = npcs.last_mut().ok_or_else(|| format!("Line {}: expected an [NPC name] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            match key {
+                "model" => npc.model = value.to_string(),
+                "speed" => npc.speed = value.parse().map_err(|_| format!("Line {}: bad speed \"{}\"", number + 1, value))?,
+                time => {
+                    let start = game_clock::parse_time(time)
+                        .ok_or_else(|| format!("Line {}: unknown key \"{}\", expected model, speed or a time like 07:30", number + 1, time))?;
+                    entries.push(parse_entry(start, value).ok_or_else(|| format!("Line {}: expected \"field x y z behaviour\"", number + 1))?);
+                }
+            }
+        }
+        if let Some(npc) = npcs.last_mut() {
+            npc.schedule = Schedule::new(entries);
+        }
+        Ok(npcs)
+    }
+}
+
+fn parse_entry(start: u32, text: &str) -> Option<ScheduleEntry> {
+    let mut words = text.split_whitespace();
+    let field = words.next()?.to_string();
+    let mut coordinate = || words.next()?.parse::<f32>().ok();
+    let position = Vector3::new(coordinate()?, coordinate()?, coordinate()?);
+    Some(ScheduleEntry {
+        start,
+        field,
+        position,
+        behaviour: words.collect::<Vec<_>>().join(" ")
+    })
+}
+
+// Resource with every NPC that has a schedule.
+#[derive(Clone, Debug, Default)]
+pub struct NpcSchedules(pub Vec<NpcDefinition>);
+
+impl NpcSchedules {
+    pub fn get(&self, name: &str) -> Option<&NpcDefinition> {
+        self.0.iter().find(|npc| npc.name == name)
+    }
+}
+
+// Component for an NPC following its schedule, and which entry it's on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledNpc {
+    pub name: String,
+    pub entry: usize,
+    pub behaviour: String
+}
+
+// Component for an NPC on its way to a scheduled spot. NPCs walk straight there, there's no
+// pathfinding yet. Ones that are leaving go once they get to the exit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduleWalk {
+    pub target: Vector3<f32>,
+    pub speed: f32,
+    pub leaving: bool
+}
+
+// An NPC to spawn in the field, and where to walk to if they aren't there yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpcArrival {
+    pub npc: String,
+    pub entry: usize,
+    pub position: Vector3<f32>,
+    pub walk_to: Option<Vector3<f32>>
+}
+
+// The NPCs that should be in a field when the player gets there, already at their spots.
+pub fn arrivals_on_load(world: &World, field: &str) -> Vec<NpcArrival> {
+    let (npcs, minute) = match (world.resource::<NpcSchedules>(), world.resource::<GameClock>()) {
+        (Some(npcs), Some(clock)) => (npcs, clock.minute()),
+        _ => return Vec::new()
+    };
+    npcs.0.iter().filter_map(|npc| {
+        let entry = npc.schedule.index_at(minute)?;
+        let spot = &npc.schedule.entries()[entry];
+        (spot.field == field).then(|| NpcArrival { npc: npc.name.clone(), entry, position: spot.position, walk_to: None })
+    }).collect()
+}
+
+// Send the NPCs where their schedules say now the clock's moved on. Ones in the field moving
+// somewhere else in it walk there. Ones off to another field walk out through the exit that
+// goes there, or just go if there isn't one. Returns the NPCs coming into the field, for
+// spawning: they come in through the exit from where they were, or appear at their spot if
+// there isn't one.
+pub fn update_schedules(world: &mut World, field: &str) -> Vec<NpcArrival> {
+    let minute = match world.resource::<GameClock>() {
+        Some(clock) => clock.minute(),
+        None => return Vec::new()
+    };
+    let npcs = match world.resource::<NpcSchedules>() {
+        Some(npcs) => npcs.clone(),
+        None => return Vec::new()
+    };
+    let exits = world.resource::<FieldDescriptor>().map(|descriptor| descriptor.exits.clone()).unwrap_or_default();
+    let exit_to = |target: &str| exits.iter().find(|exit| exit.target == target).map(|exit| exit.position);
+
+    let present: Vec<(Entity, String)> = world.query::<ScheduledNpc>().map(|(entity, npc)| (entity, npc.name.clone())).collect();
+    for (entity, name) in &present {
+        let npc = match npcs.get(name) {
+            Some(npc) => npc,
+            None => continue
+        };
+        let entry = match npc.schedule.index_at(minute) {
+            Some(entry) => entry,
+            None => continue
+        };
+        let scheduled = match world.get_mut::<ScheduledNpc>(*entity) {
+            Some(scheduled) if scheduled.entry != entry => scheduled,
+            _ => continue
+        };
+        let spot = &npc.schedule.entries()[entry];
+        scheduled.entry = entry;
+        scheduled.behaviour = spot.behaviour.clone();
+
+        let walk = if spot.field == field {
+            ScheduleWalk { target: spot.position, speed: npc.speed, leaving: false }
+        } else {
+            let here = world.get::<Transform>(*entity).map(|transform| transform.position).unwrap_or(spot.position);
+            ScheduleWalk { target: exit_to(&spot.field).unwrap_or(here), speed: npc.speed, leaving: true }
+        };
+        world.insert(*entity, walk);
+    }
+
+    npcs.0.iter().filter(|npc| !present.iter().any(|(_, name)| *name == npc.name)).filter_map(|npc| {
+        let entry = npc.schedule.index_at(minute)?;
+        let spot = &npc.schedule.entries()[entry];
+        if spot.field != field {
+            return None;
+        }
+        let entries = npc.schedule.entries();
+        let previous = &entries[(entry + entries.len() - 1) % entries.len()];
+        Some(match exit_to(&previous.field).filter(|_| previous.field != field) {
+            Some(exit) => NpcArrival { npc: npc.name.clone(), entry, position: exit, walk_to: Some(spot.position) },
+            None => NpcArrival { npc: npc.name.clone(), entry, position: spot.position, walk_to: None }
+        })
+    }).collect()
+}
+
+// Spawn NPCs into the field. Each model's only loaded once however many NPCs use it. They
+// belong to the field, so they go when the player leaves.
+pub async fn spawn_npcs(world: &mut World, renderer: &mut Renderer, assets: &AssetServer, arrivals: &[NpcArrival]) {
+    let npcs = world.resource::<NpcSchedules>().cloned().unwrap_or_default();
+    let mut models = HashMap::new();
+    for arrival in arrivals {
+        let npc = match npcs.get(&arrival.npc) {
+            Some(npc) => npc,
+            None => continue
+        };
+        if !models.contains_key(&npc.model) {
+            let model = match ModelData::load(assets, &npc.model).await {
+                Ok(model) => Some((renderer.create_model(&model), model)),
+                Err(e) => {
+                    tracing::error!(target: targets::ASSETS, "{}", e);
+                    None
+                }
+            };
+            models.insert(npc.model.clone(), model);
+        }
+
+        let entity = world.spawn();
+        world.insert(entity, Name(npc.name.clone()));
+        world.insert(entity, Transform::from_position(arrival.position));
+        world.insert(entity, FieldEntity);
+        world.insert(entity, ScheduledNpc {
+            name: npc.name.clone(),
+            entry: arrival.entry,
+            behaviour: npc.schedule.entries()[arrival.entry].behaviour.clone()
+        });
+        world.insert(entity, Interactable::new(InteractionKind::Talk, &format!("Talk to {}", npc.name)));
+        if let Some(target) = arrival.walk_to {
+            world.insert(entity, ScheduleWalk { target, speed: npc.speed, leaving: false });
+        }
+        if let Some((id, model)) = &models[&npc.model] {
+            field::insert_model(world, entity, *id, model);
+        }
+    }
+}
+
+// Walk NPCs towards their spots, facing the way they're going. Returns the ones that have
+// walked out of the field, for despawning.
+pub fn update_npc_walks(world: &mut World, delta: Duration) -> Vec<Entity> {
+    let walking: Vec<(Entity, ScheduleWalk)> = world.query::<ScheduleWalk>().map(|(entity, walk)| (entity, *walk)).collect();
+    let mut left = Vec::new();
+    for (entity, walk) in walking {
+        let transform = match world.get_mut::<Transform>(entity) {
+            Some(transform) => transform,
+            None => continue
+        };
+        let to_go = walk.target - transform.position;
+        let step = walk.speed * delta.as_secs_f32();
+        if to_go.magnitude() > step {
+            let direction = to_go.normalize();
+            transform.position += direction * step;
+            transform.rotation = Quaternion::from_angle_y(Rad(direction.x.atan2(direction.z)));
+            continue;
+        }
+
+        transform.position = walk.target;
+        world.remove::<ScheduleWalk>(entity);
+        if walk.leaving {
+            left.push(entity);
+        }
+    }
+    left
+}
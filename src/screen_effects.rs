@@ -0,0 +1,291 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::accessibility::Accessibility;
+use crate::renderer::PostProcessSettings;
+use crate::world::World;
+
+// How a tween gets from its start to its end.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    // Starts slow and speeds up.
+    EaseIn,
+    // Starts fast and slows down.
+    EaseOut,
+    EaseInOut
+}
+
+impl Easing {
+    pub const ALL: [Easing; 4] = [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseIn => "easein",
+            Easing::EaseOut => "easeout",
+            Easing::EaseInOut => "easeinout"
+        }
+    }
+
+    // How far along the tween is when t of its time has passed, both from 0 to 1.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Easing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Easing::ALL.into_iter()
+            .find(|easing| easing.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown easing \"{}\", expected linear, easein, easeout or easeinout", s.trim()))
+    }
+}
+
+// A post processing value cutscenes can animate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EffectParam {
+    Brightness,
+    Contrast,
+    Saturation,
+    Vignette,
+    // How strongly the tint colour's applied.
+    Tint,
+    // How far the screen's faded to the fade colour, like black for a fade out or white for
+    // a flash.
+    Fade,
+    // How far the screen shakes each way, in virtual screen pixels.
+    Shake
+}
+
+impl EffectParam {
+    pub const ALL: [EffectParam; 7] = [
+        EffectParam::Brightness, EffectParam::Contrast, EffectParam::Saturation, EffectParam::Vignette,
+        EffectParam::Tint, EffectParam::Fade, EffectParam::Shake
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectParam::Brightness => "brightness",
+            EffectParam::Contrast => "contrast",
+            EffectParam::Saturation => "saturation",
+            EffectParam::Vignette => "vignette",
+            EffectParam::Tint => "tint",
+            EffectParam::Fade => "fade",
+            EffectParam::Shake => "shake"
+        }
+    }
+}
+
+impl fmt::Display for EffectParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for EffectParam {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EffectParam::ALL.into_iter()
+            .find(|param| param.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown effect \"{}\", expected brightness, contrast, saturation, vignette, tint, fade or shake", s.trim()))
+    }
+}
+
+// One step of a cutscene's visuals, e.g. "saturation 0 2 easeout" to drain the colour over
+// two seconds or "fade 1 0.5 #ffffff" to flash white. After the value, the duration, easing
+// and colour can come in any order. The colour goes with tint and fade.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EffectCommand {
+    pub param: EffectParam,
+    pub value: f32,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub color: Option<[f32; 3]>
+}
+
+impl FromStr for EffectCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let param: EffectParam = words.next().ok_or("Expected an effect and a value")?.parse()?;
+        let value = words.next().ok_or_else(|| format!("Expected a value for {}", param))?;
+        let value = value.parse().map_err(|_| format!("Bad {} \"{}\"", param, value))?;
+
+        let mut command = EffectCommand {
+            param,
+            value,
+            duration: Duration::ZERO,
+            easing: Easing::default(),
+            color: None
+        };
+        for word in words {
+            if let Some(hex) = word.strip_prefix('#') {
+                command.color = Some(parse_color(hex).ok_or_else(|| format!("Bad colour \"{}\", expected #rrggbb", word))?);
+            } else if let Ok(seconds) = word.parse::<f32>() {
+                command.duration = Duration::try_from_secs_f32(seconds).map_err(|_| format!("Bad duration \"{}\"", word))?;
+            } else {
+                command.easing = word.parse()?;
+            }
+        }
+        if command.color.is_some() && !matches!(param, EffectParam::Tint | EffectParam::Fade) {
+            return Err(format!("{} doesn't have a colour", param));
+        }
+        Ok(command)
+    }
+}
+
+fn parse_color(hex: &str) -> Option<[f32; 3]> {
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok().map(|c| c as f32 / 255.0);
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct EffectTween {
+    param: EffectParam,
+    from: f32,
+    to: f32,
+    duration: Duration,
+    elapsed: Duration,
+    easing: Easing
+}
+
+// Resource that animates post processing for cutscenes. Tweens start from wherever the value
+// is now, and only touch the settings while they're running, so whatever a field or movie set
+// up is left alone otherwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScreenEffects {
+    tweens: Vec<EffectTween>,
+    shake: f32,
+    // Seconds spent shaking, which picks where the shake's got to.
+    shake_time: f32
+}
+
+impl ScreenEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start a command, replacing any tween already running on the same value. Colours change
+    // straight away.
+    pub fn run(&mut self, command: &EffectCommand, settings: &mut PostProcessSettings) {
+        if let Some(color) = command.color {
+            match command.param {
+                EffectParam::Tint => settings.tint = color,
+                _ => settings.fade[..3].copy_from_slice(&color)
+            }
+        }
+
+        self.tweens.retain(|tween| tween.param != command.param);
+        let from = self.value(command.param, settings);
+        if command.duration.is_zero() {
+            self.set_value(command.param, command.value, settings);
+        } else {
+            self.tweens.push(EffectTween {
+                param: command.param,
+                from,
+                to: command.value,
+                duration: command.duration,
+                elapsed: Duration::ZERO,
+                easing: command.easing
+            });
+        }
+    }
+
+    // Finish every tween straight away, e.g. when a cutscene's skipped.
+    pub fn finish(&mut self, settings: &mut PostProcessSettings) {
+        for tween in std::mem::take(&mut self.tweens) {
+            self.set_value(tween.param, tween.to, settings);
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.tweens.is_empty() || self.shake != 0.0
+    }
+
+    pub fn shake(&self) -> f32 {
+        self.shake
+    }
+
+    // Move the tweens on and write them to the settings. Shakes are scaled by
+    // `shake_strength`, which is nothing for players who turned screen effects off. Returns
+    // true while anything's still moving, so there's something new to draw.
+    pub fn update(&mut self, delta: Duration, settings: &mut PostProcessSettings, shake_strength: f32) -> bool {
+        for index in 0..self.tweens.len() {
+            let tween = &mut self.tweens[index];
+            tween.elapsed = (tween.elapsed + delta).min(tween.duration);
+            let t = tween.easing.apply(tween.elapsed.as_secs_f32() / tween.duration.as_secs_f32());
+            let (param, value) = (tween.param, tween.from + (tween.to - tween.from) * t);
+            self.set_value(param, value, settings);
+        }
+        self.tweens.retain(|tween| tween.elapsed < tween.duration);
+
+        // Two waves that don't line up, so the shake doesn't look like it's going round in
+        // circles.
+        if self.shake != 0.0 {
+            self.shake_time += delta.as_secs_f32();
+            let amount = self.shake * shake_strength;
+            settings.offset = [
+                amount * (self.shake_time * 53.0).sin(),
+                amount * (self.shake_time * 41.0 + 1.3).sin()
+            ];
+        } else if self.shake_time != 0.0 {
+            self.shake_time = 0.0;
+            settings.offset = [0.0, 0.0];
+        }
+        self.is_animating()
+    }
+
+    fn value(&self, param: EffectParam, settings: &PostProcessSettings) -> f32 {
+        match param {
+            EffectParam::Brightness => settings.brightness,
+            EffectParam::Contrast => settings.contrast,
+            EffectParam::Saturation => settings.saturation,
+            EffectParam::Vignette => settings.vignette,
+            EffectParam::Tint => settings.tint_strength,
+            EffectParam::Fade => settings.fade[3],
+            EffectParam::Shake => self.shake
+        }
+    }
+
+    fn set_value(&mut self, param: EffectParam, value: f32, settings: &mut PostProcessSettings) {
+        match param {
+            EffectParam::Brightness => settings.brightness = value,
+            EffectParam::Contrast => settings.contrast = value,
+            EffectParam::Saturation => settings.saturation = value,
+            EffectParam::Vignette => settings.vignette = value,
+            EffectParam::Tint => settings.tint_strength = value,
+            EffectParam::Fade => settings.fade[3] = value,
+            EffectParam::Shake => self.shake = value.max(0.0)
+        }
+    }
+}
+
+// Move the world's screen effects on. Returns true while any are still changing, so there's
+// something new to draw.
+pub fn update_screen_effects(world: &mut World, delta: Duration, settings: &mut PostProcessSettings) -> bool {
+    let shake_strength = world.resource::<Accessibility>().map(|accessibility| accessibility.effect_strength(1.0)).unwrap_or(1.0);
+    match world.resource_mut::<ScreenEffects>() {
+        Some(effects) => effects.update(delta, settings, shake_strength),
+        None => false
+    }
+}
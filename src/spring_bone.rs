@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use cgmath::{Matrix3, Matrix4, Quaternion, Rotation, SquareMatrix, InnerSpace, Vector3, One};
+
+use crate::attachment::ModelSockets;
+use crate::field::FieldDescriptor;
+use crate::model::ModelData;
+use crate::transform::Transform;
+use crate::world::{World, Entity};
+
+const GRAVITY: f32 = 9.8;
+
+// Longest step the simulation takes in one go. Longer frames are split up so springs don't
+// fly apart after a hitch.
+const MAX_STEP: Duration = Duration::from_millis(33);
+// And the most steps in a frame, so a long pause doesn't take a long time to catch up.
+const MAX_STEPS: u32 = 4;
+
+// A bone that swings about on its own, like a bit of a cape, a lock of hair or a tail.
+// Tagged in the glTF with "spring_bone" in the node's extras.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpringBoneData {
+    // The socket it moves.
+    pub socket: String,
+    // Which of the model's spring bones it hangs from, if it hangs from one.
+    pub parent: Option<usize>,
+    // Where the bone it hangs from is, relative to the model's origin.
+    pub parent_position: Vector3<f32>,
+    // How hard it pulls back to where it is at rest. 0 just hangs.
+    pub stiffness: f32,
+    // How much of its swing it loses each step, from 0 to 1.
+    pub drag: f32,
+    // 1 for normal gravity, 0 for none.
+    pub gravity: f32
+}
+
+impl Default for SpringBoneData {
+    fn default() -> Self {
+        Self {
+            socket: String::new(),
+            parent: None,
+            parent_position: Vector3::new(0.0, 0.0, 0.0),
+            stiffness: 1.0,
+            drag: 0.4,
+            gravity: 1.0
+        }
+    }
+}
+
+// Where a bone has got to in the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SpringBoneState {
+    position: Vector3<f32>,
+    previous: Vector3<f32>
+}
+
+// Component for an entity whose model has spring bones. Poses the entity's ModelSockets, so
+// whatever's attached to the bones swings with them.
+// Skinned models don't bend yet, so the bones only move attachments for now.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpringBones {
+    bones: Vec<SpringBoneData>,
+    // Each bone's socket at rest, relative to the model's origin.
+    rest: Vec<Matrix4<f32>>,
+    // Empty until the first update, when the bones start off at rest.
+    state: Vec<SpringBoneState>
+}
+
+impl SpringBones {
+    pub fn new(bones: Vec<SpringBoneData>, sockets: &ModelSockets) -> Self {
+        let rest = bones.iter()
+            .map(|bone| sockets.get(&bone.socket).copied().unwrap_or_else(Matrix4::identity))
+            .collect();
+        Self {
+            bones,
+            rest,
+            state: Vec::new()
+        }
+    }
+
+    pub fn from_model(model: &ModelData) -> Self {
+        Self::new(model.spring_bones.clone(), &ModelSockets::from_model(model))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bones.is_empty()
+    }
+
+    // Put every bone back at rest, e.g. after teleporting the entity.
+    pub fn reset(&mut self) {
+        self.state.clear();
+    }
+
+    // Move the bones on for an entity at `transform`, returning each bone's socket posed
+    // relative to the model's origin. Wind is an acceleration, like gravity.
+    fn step(&mut self, transform: &Matrix4<f32>, wind: Vector3<f32>, delta: f32) -> Vec<(String, Matrix4<f32>)> {
+        let linear = Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate());
+        let world_point = |point: Vector3<f32>| (transform * point.extend(1.0)).truncate();
+
+        let starting = self.state.len() != self.bones.len();
+        if starting {
+            self.state = self.rest.iter()
+                .map(|rest| {
+                    let position = world_point(rest.w.truncate());
+                    SpringBoneState { position, previous: position }
+                })
+                .collect();
+        }
+
+        // How far each bone has turned from rest, which the bones hanging off it turn by too.
+        let mut rotations: Vec<Quaternion<f32>> = Vec::with_capacity(self.bones.len());
+        let mut posed = Vec::with_capacity(self.bones.len());
+        for (i, bone) in self.bones.iter().enumerate() {
+            let (parent_position, parent_rotation) = match bone.parent.filter(|parent| *parent < i) {
+                Some(parent) => (self.state[parent].position, rotations[parent]),
+                None => (world_point(bone.parent_position), Quaternion::one())
+            };
+
+            let rest_position = self.rest[i].w.truncate();
+            let rest_offset = parent_rotation.rotate_vector(linear * (rest_position - bone.parent_position));
+            let length = rest_offset.magnitude();
+
+            let state = &mut self.state[i];
+            if !starting {
+                let velocity = (state.position - state.previous) * (1.0 - bone.drag.clamp(0.0, 1.0));
+                let spring = (parent_position + rest_offset - state.position) * (bone.stiffness * delta).min(1.0);
+                let acceleration = Vector3::new(0.0, -GRAVITY * bone.gravity, 0.0) + wind;
+                let mut next = state.position + velocity + spring + acceleration * delta * delta;
+
+                // Bones don't stretch.
+                let offset = next - parent_position;
+                if offset.magnitude2() > f32::EPSILON {
+                    next = parent_position + offset.normalize() * length;
+                }
+                state.previous = state.position;
+                state.position = next;
+            }
+
+            let offset = state.position - parent_position;
+            let rotation = if length > f32::EPSILON && offset.magnitude2() > f32::EPSILON {
+                Quaternion::between_vectors(rest_offset / length, offset.normalize()) * parent_rotation
+            } else {
+                parent_rotation
+            };
+            rotations.push(rotation);
+
+            // Turn the socket about its own origin, then move it to where the bone is. The
+            // entity's transform is taken back off, as sockets are relative to the model.
+            let world_rest = transform * self.rest[i];
+            let world_posed = Matrix4::from_translation(state.position)
+                * Matrix4::from(rotation)
+                * Matrix4::from_translation(-world_rest.w.truncate())
+                * world_rest;
+            let local = transform.invert().map_or(self.rest[i], |inverse| inverse * world_posed);
+            posed.push((bone.socket.clone(), local));
+        }
+        posed
+    }
+}
+
+// Swing every entity's spring bones, blown about by the field's wind. Call after animation
+// and before update_attachments.
+pub fn update_spring_bones(world: &mut World, delta: Duration) {
+    let wind = world.resource::<FieldDescriptor>().map_or(Vector3::new(0.0, 0.0, 0.0), |field| Vector3::from(field.wind));
+    let entities: Vec<Entity> = world.query::<SpringBones>().map(|(entity, _)| entity).collect();
+
+    // Split the frame into even steps, dropping whatever doesn't fit after the last one.
+    let steps = (delta.as_secs_f32() / MAX_STEP.as_secs_f32()).ceil().clamp(1.0, MAX_STEPS as f32);
+    let step = delta.min(MAX_STEP * MAX_STEPS).as_secs_f32() / steps;
+    if step <= 0.0 {
+        return;
+    }
+
+    for entity in entities {
+        let transform = match world.get::<Transform>(entity) {
+            Some(transform) => transform.matrix(),
+            None => continue
+        };
+        let posed = match world.get_mut::<SpringBones>(entity) {
+            Some(bones) => (0..steps as u32).map(|_| bones.step(&transform, wind, step)).last().unwrap_or_default(),
+            None => continue
+        };
+        if let Some(sockets) = world.get_mut::<ModelSockets>(entity) {
+            for (name, matrix) in posed {
+                sockets.set(&name, matrix);
+            }
+        }
+    }
+}
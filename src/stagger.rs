@@ -0,0 +1,105 @@
+// Breaking enemies, like Octopath Traveler's breaks or Final Fantasy XIII's stagger. Every hit
+// fills the target's break gauge a little, and hits on a weakness fill it much faster. Once it's
+// full they're broken: they lose their next turns and take more damage until they recover, and
+// the gauge starts again empty.
+//
+// It's optional, and only on if data/combat.cfg says "stagger = on". break_gauge, break_normal,
+// break_weakness, break_bonus and break_turns there say how much it takes and what it does, see
+// CombatConfig. Whatever runs the battle keeps a gauge for each combatant that can be broken,
+// passes each hit on to it, sets Defence::broken from it, and asks it whether a turn's lost.
+
+use crate::accessibility::Accessibility;
+use crate::battle_ui::{self, Combatant};
+use crate::camera::Camera;
+use crate::combat::{Attack, CombatRules, HitResult};
+use crate::ui::UiBatch;
+
+const GAUGE_WIDTH: f32 = 48.0;
+const GAUGE_HEIGHT: f32 = 4.0;
+// Between the gauge and the head it's over.
+const GAUGE_GAP: f32 = 6.0;
+const BREAK_TEXT: &str = "BREAK";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BreakGauge {
+    size: f32,
+    filled: f32,
+    turns: u32,
+    // Turns left to lose, while broken.
+    lost: u32
+}
+
+impl BreakGauge {
+    // For someone with this much max HP. None if stagger's off in the config, so games without
+    // it never have a gauge.
+    pub fn new(max_hp: u32, rules: &dyn CombatRules) -> Option<Self> {
+        let config = rules.config();
+        if !config.stagger {
+            return None;
+        }
+        Some(Self {
+            size: (max_hp as f32 * config.break_gauge).max(1.0),
+            filled: 0.0,
+            turns: config.break_turns.max(1),
+            lost: 0
+        })
+    }
+
+    // They've been hit. Returns whether that broke them. Nothing fills it while they're broken.
+    pub fn hit(&mut self, rules: &dyn CombatRules, attack: &Attack, result: &HitResult) -> bool {
+        if self.is_broken() {
+            return false;
+        }
+        self.filled += rules.break_damage(attack, result);
+        if self.filled < self.size {
+            return false;
+        }
+        self.filled = self.size;
+        self.lost = self.turns;
+        true
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.lost > 0
+    }
+
+    // How full it is, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        self.filled / self.size
+    }
+
+    // Their turn's come round. Returns whether they lose it to being broken. They recover once
+    // they've lost as many as break_turns says, with an empty gauge, and act as normal on the
+    // turn after.
+    pub fn take_turn(&mut self) -> bool {
+        if !self.is_broken() {
+            return false;
+        }
+        self.lost -= 1;
+        if self.lost == 0 {
+            self.filled = 0.0;
+        }
+        true
+    }
+
+    // A bar under their head filling up, or "BREAK" while they're broken.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, camera: &Camera, combatant: &Combatant) {
+        if combatant.knocked_out {
+            return;
+        }
+        let (x, y) = match battle_ui::head_on_screen(camera, combatant) {
+            Some(head) => head,
+            None => return
+        };
+        let skin = accessibility.skin();
+        let (left, top) = (x - GAUGE_WIDTH / 2.0, y + GAUGE_GAP);
+        if self.is_broken() {
+            let scale = accessibility.text_scale();
+            let (width, _) = UiBatch::measure_text(scale, BREAK_TEXT);
+            batch.text(x - width / 2.0, top, scale, BREAK_TEXT, skin.highlight);
+            return;
+        }
+        batch.rect(left, top, GAUGE_WIDTH, GAUGE_HEIGHT, skin.window);
+        batch.rect(left, top, GAUGE_WIDTH * self.fraction().min(1.0), GAUGE_HEIGHT, skin.highlight);
+    }
+}
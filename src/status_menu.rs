@@ -0,0 +1,496 @@
+// The pause menu's status screens: the party at a glance, each member's stats, equipment and
+// skills, and the items, which can be used from here. Opened with escape in a field.
+//
+// Each screen is a list to pick from, and picking opens the next screen on top of it. Escape
+// goes back a screen, or closes the menu from the first one. It slides in as it opens and back
+// out as it closes. Each screen remembers what was picked on it last, so opening it again, or
+// going back to it, starts there.
+//
+// Pressing a number on a skill or item that can be used in a field puts it in that hotbar
+// slot, see hotbar.rs.
+
+use std::{collections::HashMap, time::Duration};
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::font;
+use crate::hotbar::Hotbar;
+use crate::inventory::{Inventory, ItemEffect};
+use crate::menu::MenuSound;
+use crate::party::{EquipSlot, Party, PartyMember};
+use crate::pointer::{PointerEvent, PointerResponse};
+use crate::pools::Pools;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::subtitles;
+use crate::tween::{MenuSlide, UiPose};
+use crate::ui::UiBatch;
+
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 4.0;
+// Lines kept at the bottom for descriptions and what happened.
+const FOOTER_LINES: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusScreen {
+    // Everyone's level, HP and MP, then the items.
+    Party,
+    // One member's stats and equipment.
+    Character(usize),
+    Skills(usize),
+    Items,
+    // Who to use the item in this inventory slot on.
+    ItemTarget(usize),
+    // Who to use a member's skill on, as (member, skill).
+    SkillTarget(usize, usize)
+}
+
+// Something the menu wants done to the party or inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusMenuAction {
+    UseItem { slot: usize, member: usize },
+    // Use an item that's for the whole party, like a repel.
+    UseFieldItem { slot: usize },
+    // Use a skill that works in a field, like sneak or cure, see field_skill.rs. The target's
+    // None for skills that are for the whole party.
+    UseSkill { member: usize, skill: usize, target: Option<usize> },
+    // Move someone to the other row.
+    ChangeRow { member: usize },
+    // Turn someone's auto battle on or off.
+    ToggleAuto { member: usize },
+    // Put a member's skill, or the item in an inventory slot, in a hotbar slot.
+    BindSkill { quick_slot: usize, member: usize, skill: usize },
+    BindItem { quick_slot: usize, slot: usize }
+}
+
+// A row in one of the lists. Dim rows are there to read but can't be picked.
+struct MenuRow {
+    text: String,
+    dim: bool
+}
+
+#[derive(Default)]
+pub struct StatusMenu {
+    // The screens opened so far and what's selected on each, the one showing last. Empty
+    // when the menu's closed.
+    screens: Vec<(StatusScreen, usize)>,
+    // What happened last, like using an item, until the next key.
+    message: Option<String>,
+    slide: MenuSlide,
+    // The screen that was showing when it closed, drawn as it slides out.
+    closing: Option<(StatusScreen, usize)>,
+    // What was selected last on each screen.
+    remembered: HashMap<StatusScreen, usize>,
+    sound: Option<MenuSound>,
+    // The pools besides MP, to show and cost skills in.
+    pools: Pools
+}
+
+impl StatusMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.screens.is_empty()
+    }
+
+    pub fn set_pools(&mut self, pools: Pools) {
+        self.pools = pools;
+    }
+
+    pub fn open(&mut self) {
+        self.screens = vec![(StatusScreen::Party, self.remembered(StatusScreen::Party))];
+        self.message = None;
+        self.slide.open();
+    }
+
+    pub fn close(&mut self) {
+        self.closing = self.screens.last().copied();
+        self.screens.clear();
+        self.slide.close();
+    }
+
+    // Slide it in or out. Returns whether it moved.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        self.slide.update(delta)
+    }
+
+    pub fn screen(&self) -> Option<StatusScreen> {
+        self.screens.last().map(|(screen, _)| *screen)
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    // The sound the last key or pointer event made, see menu.rs.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    fn remembered(&self, screen: StatusScreen) -> usize {
+        self.remembered.get(&screen).copied().unwrap_or_default()
+    }
+
+    // Move the selection with up and down, pick with enter and go back with escape.
+    pub fn handle_key(&mut self, key: VirtualKeyCode, party: &Party, inventory: &Inventory) -> Option<StatusMenuAction> {
+        let (screen, selected) = *self.screens.last()?;
+        let count = rows(screen, party, inventory, &self.pools).len();
+        let selected = selected.min(count.saturating_sub(1));
+        self.message = None;
+        match key {
+            VirtualKeyCode::Up if selected > 0 => self.select(selected - 1),
+            VirtualKeyCode::Down if selected + 1 < count => self.select(selected + 1),
+            VirtualKeyCode::Return => return self.pick(party, inventory),
+            VirtualKeyCode::Escape | VirtualKeyCode::Back => self.back(),
+            _ => if let Some(quick_slot) = Hotbar::slot_for_key(key) {
+                return self.bind(quick_slot, screen, selected, party, inventory);
+            }
+        }
+        None
+    }
+
+    // The same for the mouse and touch, like the other menus. Clicking outside the list goes
+    // back, like escape.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility, party: &Party, inventory: &Inventory) -> PointerResponse<StatusMenuAction> {
+        let before = (self.screens.clone(), self.message.clone());
+        let action = self.pointer(event, accessibility, party, inventory);
+        PointerResponse::new(action, before != (self.screens.clone(), self.message.clone()))
+    }
+
+    fn pointer(&mut self, event: PointerEvent, accessibility: &Accessibility, party: &Party, inventory: &Inventory) -> Option<StatusMenuAction> {
+        let (screen, selected) = *self.screens.last()?;
+        let layout = Layout::new(accessibility, header(screen, party, &self.pools).len());
+        let count = rows(screen, party, inventory, &self.pools).len();
+        match event {
+            PointerEvent::Hover { x, y } => {
+                if let Some(index) = layout.row_at(x, y, selected, count) {
+                    self.select(index);
+                }
+            },
+            PointerEvent::Click { x, y } => {
+                self.message = None;
+                match layout.row_at(x, y, selected, count) {
+                    Some(index) => {
+                        self.select(index);
+                        return self.pick(party, inventory);
+                    },
+                    None => self.back()
+                }
+            },
+            PointerEvent::Scroll { steps } => {
+                let last = count.saturating_sub(1) as i64;
+                self.select((selected as i64 + steps as i64).clamp(0, last) as usize);
+            },
+            PointerEvent::Cancel => self.back()
+        }
+        None
+    }
+
+    // Show how an action went. Using an item or skill on someone goes back to the list either
+    // way.
+    pub fn finish_action(&mut self, result: Result<String, String>) {
+        if let Some((StatusScreen::ItemTarget(_) | StatusScreen::SkillTarget(..), _)) = self.screens.last() {
+            self.screens.pop();
+        }
+        self.message = Some(result.unwrap_or_else(|e| e));
+    }
+
+    // Go back a screen, closing the menu from the first one.
+    fn back(&mut self) {
+        self.sound = Some(MenuSound::Cancel);
+        if self.screens.len() == 1 {
+            self.close();
+        } else {
+            self.screens.pop();
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        if let Some((screen, selected)) = self.screens.last_mut() {
+            if *selected != index {
+                *selected = index;
+                self.sound = Some(MenuSound::Move);
+            }
+            self.remembered.insert(*screen, index);
+        }
+    }
+
+    // Put what's selected in a hotbar slot, if it's a skill or item that can be used here.
+    fn bind(&mut self, quick_slot: usize, screen: StatusScreen, selected: usize, party: &Party, inventory: &Inventory) -> Option<StatusMenuAction> {
+        let (name, effect, action) = match screen {
+            StatusScreen::Skills(member) => {
+                let skill = party.members.get(member)?.skills.get(selected)?;
+                (&skill.name, skill.field.as_ref(), StatusMenuAction::BindSkill { quick_slot, member, skill: selected })
+            },
+            StatusScreen::Items => {
+                let slot = inventory.slots().get(selected)?;
+                (&slot.item.name, Some(&slot.item.effect), StatusMenuAction::BindItem { quick_slot, slot: selected })
+            },
+            _ => return None
+        };
+        if effect.is_none_or(|effect| *effect == ItemEffect::None) {
+            self.message = Some(format!("{} can't be used here", name));
+            self.sound = Some(MenuSound::Error);
+            return None;
+        }
+        self.sound = Some(MenuSound::Confirm);
+        Some(action)
+    }
+
+    fn pick(&mut self, party: &Party, inventory: &Inventory) -> Option<StatusMenuAction> {
+        let (screen, selected) = *self.screens.last()?;
+        let picked = self.pick_next(screen, selected, party, inventory);
+        self.sound = Some(match (&picked, &self.message) {
+            (Some(_), _) => MenuSound::Confirm,
+            (None, Some(_)) => MenuSound::Error,
+            (None, None) if self.screen() != Some(screen) => MenuSound::Confirm,
+            (None, None) => MenuSound::Error
+        });
+        picked
+    }
+
+    // Open the screen for what's picked, or say what to do, or why it can't be picked.
+    fn pick_next(&mut self, screen: StatusScreen, selected: usize, party: &Party, inventory: &Inventory) -> Option<StatusMenuAction> {
+        let next = match screen {
+            StatusScreen::Party if selected < party.members.len() => StatusScreen::Character(selected),
+            StatusScreen::Party => StatusScreen::Items,
+            // The equipment slots come first, then the skills, the row and auto battle.
+            StatusScreen::Character(member) if selected == EquipSlot::ALL.len() => StatusScreen::Skills(member),
+            StatusScreen::Character(member) if selected == EquipSlot::ALL.len() + 1 => return Some(StatusMenuAction::ChangeRow { member }),
+            StatusScreen::Character(member) if selected == EquipSlot::ALL.len() + 2 => return Some(StatusMenuAction::ToggleAuto { member }),
+            StatusScreen::Skills(member) => match party.members.get(member).and_then(|member| member.skills.get(selected)) {
+                Some(skill) if skill.field.as_ref().is_some_and(ItemEffect::is_for_party) => {
+                    return Some(StatusMenuAction::UseSkill { member, skill: selected, target: None });
+                },
+                Some(skill) if skill.field.as_ref().is_some_and(|effect| *effect != ItemEffect::None) => StatusScreen::SkillTarget(member, selected),
+                Some(skill) => {
+                    self.message = Some(format!("{} can't be used here", skill.name));
+                    return None;
+                },
+                None => return None
+            },
+            StatusScreen::Character(_) => return None,
+            StatusScreen::Items => match inventory.slots().get(selected) {
+                Some(slot) if slot.item.effect == ItemEffect::None => {
+                    self.message = Some(format!("{} can't be used here", slot.item.name));
+                    return None;
+                },
+                Some(slot) if slot.item.effect.is_for_party() => return Some(StatusMenuAction::UseFieldItem { slot: selected }),
+                Some(_) => StatusScreen::ItemTarget(selected),
+                None => return None
+            },
+            StatusScreen::ItemTarget(slot) if selected < party.members.len() => return Some(StatusMenuAction::UseItem { slot, member: selected }),
+            StatusScreen::ItemTarget(_) => return None,
+            StatusScreen::SkillTarget(member, skill) if selected < party.members.len() => {
+                return Some(StatusMenuAction::UseSkill { member, skill, target: Some(selected) });
+            },
+            StatusScreen::SkillTarget(..) => return None
+        };
+        self.screens.push((next, self.remembered(next)));
+        None
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, party: &Party, inventory: &Inventory) {
+        let closing = self.closing.filter(|_| self.slide.is_showing());
+        let (screen, selected) = match self.screens.last().copied().or(closing) {
+            Some(screen) => screen,
+            None => return
+        };
+
+        let skin = accessibility.skin();
+        let header = header(screen, party, &self.pools);
+        let rows = rows(screen, party, inventory, &self.pools);
+        let selected = selected.min(rows.len().saturating_sub(1));
+        let layout = Layout::new(accessibility, header.len());
+
+        // The backdrop fades, and everything on it slides too.
+        let pose = self.slide.pose();
+        batch.set_pose(UiPose { alpha: pose.alpha, ..UiPose::REST }, [0.0, 0.0]);
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, skin.backdrop);
+        batch.set_pose(pose, [0.0, 0.0]);
+        batch.text(MARGIN, MARGIN, layout.scale * 1.5, &title(screen, party, inventory), skin.highlight);
+        for (line, text) in header.iter().enumerate() {
+            batch.text(MARGIN + PADDING, layout.header_y + UiBatch::line_height(layout.scale) * line as f32, layout.scale, text, skin.text);
+        }
+
+        let first = layout.first_visible(selected);
+        for (row, index) in (first..rows.len()).take(layout.visible_rows).enumerate() {
+            let y = layout.row_y(row);
+            if index == selected {
+                batch.rect(MARGIN, y, layout.width, layout.row_height, skin.selected);
+            }
+            let color = if rows[index].dim { skin.dim_text } else { skin.text };
+            batch.text(MARGIN + PADDING, y + PADDING, layout.scale, &rows[index].text, color);
+        }
+
+        let footer = self.message.clone().or_else(|| description(screen, selected, party, inventory));
+        if let Some(footer) = footer {
+            let max_columns = (layout.width / (font::CELL_WIDTH as f32 * layout.scale)) as usize;
+            let footer: String = subtitles::wrap(&footer, max_columns).lines().take(FOOTER_LINES).collect::<Vec<_>>().join("\n");
+            batch.text(MARGIN + PADDING, layout.footer_y, layout.scale, &footer, skin.dim_text);
+        }
+        batch.clear_pose();
+    }
+}
+
+fn title(screen: StatusScreen, party: &Party, inventory: &Inventory) -> String {
+    let name = |member: usize| party.members.get(member).map(|member| member.name.as_str()).unwrap_or_default();
+    match screen {
+        StatusScreen::Party => "Party".to_string(),
+        StatusScreen::Character(member) => name(member).to_string(),
+        StatusScreen::Skills(member) => format!("{}'s skills", name(member)),
+        StatusScreen::Items => "Items".to_string(),
+        StatusScreen::ItemTarget(slot) => match inventory.slots().get(slot) {
+            Some(slot) => format!("Use {} on", slot.item.name),
+            None => "Use on".to_string()
+        },
+        StatusScreen::SkillTarget(member, skill) => match party.members.get(member).and_then(|member| member.skills.get(skill)) {
+            Some(skill) => format!("Use {} on", skill.name),
+            None => "Use on".to_string()
+        }
+    }
+}
+
+// Lines shown above the list.
+fn header(screen: StatusScreen, party: &Party, pools: &Pools) -> Vec<String> {
+    let member = match screen {
+        StatusScreen::Character(member) => party.members.get(member),
+        _ => None
+    };
+    match member {
+        Some(member) => {
+            let stats = &member.stats;
+            let mut lines = vec![
+                format!("Level {}   EXP {}", stats.level, stats.experience),
+                format!("HP {}/{}   MP {}/{}", stats.hp, stats.max_hp, stats.mp, stats.max_mp)
+            ];
+            // Any other pools the game has, like TP.
+            let pools = pools.labels(member);
+            if !pools.is_empty() {
+                lines.push(pools.join("   "));
+            }
+            lines.extend([
+                format!("Strength {:<4} Magic {}", stats.strength, stats.magic),
+                format!("Defence {:<5} Speed {}", stats.defence, stats.speed),
+                String::new()
+            ]);
+            lines
+        },
+        None => Vec::new()
+    }
+}
+
+fn rows(screen: StatusScreen, party: &Party, inventory: &Inventory, pools: &Pools) -> Vec<MenuRow> {
+    let row = |text: String, dim: bool| MenuRow { text, dim };
+    match screen {
+        StatusScreen::Party => party.members.iter()
+            .map(|member| row(member_summary(member), member.stats.is_knocked_out()))
+            .chain(std::iter::once(row("Items".to_string(), false)))
+            .collect(),
+        StatusScreen::Character(member) => match party.members.get(member) {
+            Some(member) => EquipSlot::ALL.iter()
+                .map(|slot| {
+                    let equipped = match member.equipped(*slot) {
+                        Some(item) if item.reach => format!("{} (reach)", item.name),
+                        Some(item) => item.name.clone(),
+                        None => "-".to_string()
+                    };
+                    row(format!("{:<10} {}", slot.label(), equipped), true)
+                })
+                .chain([
+                    row("Skills".to_string(), false),
+                    row(format!("{:<10} {}", "Row", member.row.label()), false),
+                    row(format!("{:<10} {}", "Auto", if member.auto_battle { "On" } else { "Off" }), false)
+                ])
+                .collect(),
+            None => Vec::new()
+        },
+        StatusScreen::Skills(member) => party.members.get(member).into_iter()
+            .flat_map(|member| &member.skills)
+            .map(|skill| row(format!("{:<16} {:>6}", skill.name, pools.cost_label(skill, inventory)), skill.field.as_ref().is_none_or(|effect| *effect == ItemEffect::None)))
+            .collect(),
+        StatusScreen::Items => inventory.slots().iter()
+            .map(|slot| row(format!("{:<16} x{}", slot.item.name, slot.count), slot.item.effect == ItemEffect::None))
+            .collect(),
+        StatusScreen::ItemTarget(_) | StatusScreen::SkillTarget(..) => party.members.iter().map(|member| row(member_summary(member), false)).collect()
+    }
+}
+
+fn member_summary(member: &PartyMember) -> String {
+    let stats = &member.stats;
+    format!("{:<10} Lv {:<3} HP {:>4}/{:<4} MP {:>3}/{}", member.name, stats.level, stats.hp, stats.max_hp, stats.mp, stats.max_mp)
+}
+
+// What the selected row is, for the bottom of the screen.
+fn description(screen: StatusScreen, selected: usize, party: &Party, inventory: &Inventory) -> Option<String> {
+    match screen {
+        StatusScreen::Skills(member) => party.members.get(member)?.skills.get(selected).map(|skill| skill.description.clone()),
+        StatusScreen::SkillTarget(member, skill) => party.members.get(member)?.skills.get(skill).map(|skill| skill.description.clone()),
+        StatusScreen::Items | StatusScreen::ItemTarget(_) => {
+            let slot = match screen {
+                StatusScreen::ItemTarget(slot) => slot,
+                _ => selected
+            };
+            inventory.slots().get(slot).map(|slot| slot.item.description.clone())
+        },
+        StatusScreen::Character(_) if selected == EquipSlot::ALL.len() + 1 => {
+            Some("The back row is hit less often and for less, but hits back for less too, unless their weapon has reach.".to_string())
+        },
+        StatusScreen::Character(member) if selected == EquipSlot::ALL.len() + 2 => {
+            let gambits: Vec<String> = party.members.get(member)?.gambits.iter().map(ToString::to_string).collect();
+            Some(format!("Fights by themselves in battle: {}", gambits.join(", then ")))
+        },
+        _ => None
+    }
+}
+
+// Where everything goes, shared by drawing and hit testing. Lists longer than the screen
+// scroll to keep the selected row showing.
+struct Layout {
+    scale: f32,
+    header_y: f32,
+    first_row_y: f32,
+    row_height: f32,
+    visible_rows: usize,
+    footer_y: f32,
+    width: f32
+}
+
+impl Layout {
+    fn new(accessibility: &Accessibility, header_lines: usize) -> Self {
+        let scale = 2.0 * accessibility.text_scale();
+        let line_height = UiBatch::line_height(scale);
+        let header_y = MARGIN * 2.0 + UiBatch::line_height(scale * 1.5);
+        let first_row_y = header_y + line_height * header_lines as f32;
+        let row_height = line_height + PADDING * 2.0;
+        let footer_y = SCREEN_HEIGHT as f32 - MARGIN - line_height * FOOTER_LINES as f32;
+        Self {
+            scale,
+            header_y,
+            first_row_y,
+            row_height,
+            visible_rows: (((footer_y - MARGIN - first_row_y) / row_height) as usize).max(1),
+            footer_y,
+            width: SCREEN_WIDTH as f32 - MARGIN * 2.0
+        }
+    }
+
+    fn first_visible(&self, selected: usize) -> usize {
+        (selected + 1).saturating_sub(self.visible_rows)
+    }
+
+    fn row_y(&self, row: usize) -> f32 {
+        self.first_row_y + self.row_height * row as f32
+    }
+
+    fn row_at(&self, x: f32, y: f32, selected: usize, count: usize) -> Option<usize> {
+        if !(MARGIN..MARGIN + self.width).contains(&x) || y < self.first_row_y {
+            return None;
+        }
+        let row = ((y - self.first_row_y) / self.row_height) as usize;
+        let index = self.first_visible(selected) + row;
+        (row < self.visible_rows && index < count).then_some(index)
+    }
+}
@@ -0,0 +1,347 @@
+// Text the game shows that isn't in an asset of its own, like toasts and prompts. The game's
+// own words are in the code, and translations go in text/strings.cfg, which is localized like
+// any other asset, so each language has its own in localized/<language>/text/strings.cfg. One
+// string per line, by id:
+//
+//   pickup.found = {item} trouvé{count, plural, one {} other { x#}}
+//
+// Text in {} is filled in as it's shown, rather than by sticking bits of sentence together, as
+// word order and endings differ between languages:
+//
+//   {player}      the party leader's name
+//   {member.2}    the second person in the party
+//   {flag.name}   a flag's value
+//
+// and anything else, like {item} and {count}, is given by whatever's showing the text. Plurals
+// and genders pick between versions the way ICU's MessageFormat does, which translation tools
+// know:
+//
+//   {count, plural, =0 {Nothing} one {# potion} other {# potions}}
+//   {player, select, female {She} male {He} other {They}} left.
+//
+// Plurals go by the language's rules for which numbers count as "one", "few" and so on, and #
+// is the number. Genders go by the party member's. Anything that matches nothing is "other".
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::flags::GameFlags;
+use crate::party::{Gender, Party, PartyMember};
+use crate::world::World;
+
+pub const STRINGS_PATH: &str = "text/strings.cfg";
+// What the game's own words are written in, for their plurals.
+pub const SOURCE_LANGUAGE: &str = "en";
+
+// Something to fill in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Number(i64),
+    // Their name, and what select picks by.
+    Person { name: String, gender: Gender }
+}
+
+impl Value {
+    fn number(&self) -> Option<i64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Text(text) => text.trim().parse().ok(),
+            Value::Person { .. } => None
+        }
+    }
+
+    // What select picks by.
+    fn key(&self) -> String {
+        match self {
+            Value::Person { gender, .. } => gender.name().to_string(),
+            _ => self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Text(text) => f.write_str(text),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Person { name, .. } => f.write_str(name)
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(number: i32) -> Self {
+        Value::Number(number as i64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(number: u32) -> Self {
+        Value::Number(number as i64)
+    }
+}
+
+impl From<&PartyMember> for Value {
+    fn from(member: &PartyMember) -> Self {
+        Value::Person { name: member.name.clone(), gender: member.gender }
+    }
+}
+
+// Which version of a plural a number takes, see CLDR's plural rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other
+}
+
+impl PluralCategory {
+    pub const ALL: [PluralCategory; 6] = [
+        PluralCategory::Zero, PluralCategory::One, PluralCategory::Two, PluralCategory::Few, PluralCategory::Many, PluralCategory::Other
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other"
+        }
+    }
+}
+
+impl fmt::Display for PluralCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// The plural a whole number takes in a language. Languages that aren't here go like English.
+pub fn plural_category(language: &str, number: i64) -> PluralCategory {
+    let n = number.unsigned_abs();
+    let (last, last_two) = (n % 10, n % 100);
+    let few = (2..=4).contains(&last) && !(12..=14).contains(&last_two);
+    match crate::subtitles::base_language(language).to_ascii_lowercase().as_str() {
+        "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" => PluralCategory::Other,
+        "fr" | "pt" | "hi" if n <= 1 => PluralCategory::One,
+        "fr" | "pt" | "hi" => PluralCategory::Other,
+        "ru" | "uk" | "be" if last == 1 && last_two != 11 => PluralCategory::One,
+        "ru" | "uk" | "be" if few => PluralCategory::Few,
+        "ru" | "uk" | "be" => PluralCategory::Many,
+        "pl" if n == 1 => PluralCategory::One,
+        "pl" if few => PluralCategory::Few,
+        "pl" => PluralCategory::Many,
+        "cs" | "sk" => match n {
+            1 => PluralCategory::One,
+            2..=4 => PluralCategory::Few,
+            _ => PluralCategory::Other
+        },
+        "ar" => match (n, last_two) {
+            (0, _) => PluralCategory::Zero,
+            (1, _) => PluralCategory::One,
+            (2, _) => PluralCategory::Two,
+            (_, 3..=10) => PluralCategory::Few,
+            (_, 11..=99) => PluralCategory::Many,
+            _ => PluralCategory::Other
+        },
+        "he" => match n {
+            1 => PluralCategory::One,
+            2 => PluralCategory::Two,
+            _ => PluralCategory::Other
+        },
+        _ if n == 1 => PluralCategory::One,
+        _ => PluralCategory::Other
+    }
+}
+
+// Where {player}, {member.N} and {flag.name} come from. Either can be missing, and whatever
+// needs them is left as it's written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StringContext<'a> {
+    pub flags: Option<&'a GameFlags>,
+    pub party: Option<&'a Party>
+}
+
+impl<'a> StringContext<'a> {
+    pub fn from_world(world: &'a World) -> Self {
+        Self { flags: world.resource::<GameFlags>(), party: world.resource::<Party>() }
+    }
+
+    // What `name` is, from `args` first, then the game.
+    fn value(&self, name: &str, args: &[(&str, Value)]) -> Option<Value> {
+        if let Some((_, value)) = args.iter().find(|(arg, _)| *arg == name) {
+            return Some(value.clone());
+        }
+        if name == "player" {
+            return self.party?.leader().map(Value::from);
+        }
+        if let Some(index) = name.strip_prefix("member.") {
+            let index: usize = index.parse().ok()?;
+            return self.party?.members.get(index.checked_sub(1)?).map(Value::from);
+        }
+        let flag = name.strip_prefix("flag.")?;
+        Some(Value::Number(self.flags?.get(flag) as i64))
+    }
+}
+
+// Fill in a string's placeholders, see the top of the file. Ones that can't be filled in are
+// left as they're written, so they're easy to spot.
+pub fn format(template: &str, language: &str, context: &StringContext, args: &[(&str, Value)]) -> String {
+    format_with(template, language, context, args, None)
+}
+
+// `hash` is what # stands for, inside a plural.
+fn format_with(template: &str, language: &str, context: &StringContext, args: &[(&str, Value)], hash: Option<i64>) -> String {
+    let mut formatted = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '{' => match closing_brace(rest) {
+                Some(end) => {
+                    let placeholder = &rest[1..end];
+                    match placeholder_text(placeholder, language, context, args, hash) {
+                        Some(text) => formatted.push_str(&text),
+                        None => formatted.push_str(&rest[..=end])
+                    }
+                    rest = &rest[end + 1..];
+                    continue;
+                },
+                None => formatted.push(c)
+            },
+            '#' if hash.is_some() => formatted.push_str(&hash.unwrap_or_default().to_string()),
+            _ => formatted.push(c)
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    formatted
+}
+
+// Where the '}' that closes the '{' at the start of `text` is.
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+// What "name", "name, plural, ..." or "name, select, ..." comes out as, if it can be worked out.
+fn placeholder_text(placeholder: &str, language: &str, context: &StringContext, args: &[(&str, Value)], hash: Option<i64>) -> Option<String> {
+    let mut parts = placeholder.splitn(3, ',');
+    let name = parts.next()?.trim();
+    let value = context.value(name, args)?;
+    let (kind, branches) = match (parts.next(), parts.next()) {
+        (Some(kind), Some(branches)) => (kind.trim(), parse_branches(branches)?),
+        (None, _) => return Some(value.to_string()),
+        _ => return None
+    };
+
+    let find = |key: &str| branches.iter().find(|(branch, _)| *branch == key).map(|(_, body)| *body);
+    match kind {
+        "plural" => {
+            let number = value.number()?;
+            let body = find(&format!("={}", number))
+                .or_else(|| find(plural_category(language, number).name()))
+                .or_else(|| find("other"))?;
+            Some(format_with(body, language, context, args, Some(number)))
+        },
+        "select" => {
+            let body = find(&value.key()).or_else(|| find("other"))?;
+            Some(format_with(body, language, context, args, hash))
+        },
+        _ => None
+    }
+}
+
+// "one {...} other {...}" as each key and what's in its braces.
+fn parse_branches(text: &str) -> Option<Vec<(&str, &str)>> {
+    let mut branches = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let open = rest.find('{')?;
+        let end = closing_brace(&rest[open..])? + open;
+        branches.push((rest[..open].trim(), &rest[open + 1..end]));
+        rest = rest[end + 1..].trim_start();
+    }
+    Some(branches)
+}
+
+// Resource with the strings for the player's language.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StringTable {
+    pub language: String,
+    strings: BTreeMap<String, String>
+}
+
+impl StringTable {
+    // Nothing translated, so everything's in the game's own words.
+    pub fn new(language: &str) -> Self {
+        Self { language: language.to_string(), strings: BTreeMap::new() }
+    }
+
+    pub fn parse(language: &str, text: &str) -> Result<Self, String> {
+        let mut table = Self::new(language);
+        for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (id, string) = line.split_once('=')
+                .map(|(id, string)| (id.trim(), string.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"id = text\"", number + 1))?;
+            if table.strings.insert(id.to_string(), string.to_string()).is_some() {
+                return Err(format!("Line {}: \"{}\" is already there", number + 1, id));
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.strings.get(id).map(String::as_str)
+    }
+
+    // The string `id`, or `fallback` if it isn't translated, filled in.
+    pub fn text(&self, id: &str, fallback: &str, context: &StringContext, args: &[(&str, Value)]) -> String {
+        match self.get(id) {
+            Some(string) => format(string, &self.language, context, args),
+            None => format(fallback, SOURCE_LANGUAGE, context, args)
+        }
+    }
+}
+
+// The same as StringTable::text, with what's in the world. Falls back to the game's own words
+// if there's no table.
+pub fn localize(world: &World, id: &str, fallback: &str, args: &[(&str, Value)]) -> String {
+    let context = StringContext::from_world(world);
+    match world.resource::<StringTable>() {
+        Some(table) => table.text(id, fallback, &context, args),
+        None => format(fallback, SOURCE_LANGUAGE, &context, args)
+    }
+}
@@ -0,0 +1,264 @@
+// Subtitles for movies and voiced scenes. Tracks are SubRip (.srt) files, which most
+// subtitling tools export:
+//
+// 1
+// 00:00:01,500 --> 00:00:04,000
+// Where am I?
+//
+// 2
+// 00:00:04,500 --> 00:00:07,250
+// You're safe now.
+// Rest a while.
+
+use std::time::Duration;
+
+use crate::accessibility::Accessibility;
+use crate::assets::{AssetError, AssetServer};
+use crate::font;
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ui::UiBatch;
+
+const MARGIN: f32 = 16.0;
+const PADDING: f32 = 6.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubtitleSegment {
+    pub start: Duration,
+    pub end: Duration,
+    // '\n' between lines.
+    pub text: String
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtitles {
+    pub language: String,
+    segments: Vec<SubtitleSegment>
+}
+
+impl Subtitles {
+    pub async fn load(assets: &AssetServer, path: &str, language: &str) -> Result<Self, AssetError> {
+        let text = assets.load_bytes(path).await?;
+        Self::parse(language, &String::from_utf8_lossy(&text)).map_err(|e| AssetError::Decode(path.to_string(), e))
+    }
+
+    pub fn parse(language: &str, text: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut lines = text.trim_start_matches('\u{feff}').lines().map(str::trim_end).enumerate().peekable();
+        while let Some((number, line)) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // The counter's optional, plenty of files get it wrong anyway.
+            let (number, timing) = if line.contains("-->") {
+                (number, line)
+            } else {
+                match lines.next() {
+                    Some(next) => next,
+                    None => return Err(format!("Line {}: expected the times after \"{}\"", number + 1, line))
+                }
+            };
+            let (start, end) = timing.split_once("-->")
+                .ok_or_else(|| format!("Line {}: expected \"start --> end\", got \"{}\"", number + 1, timing))?;
+            let start = parse_time(start).ok_or_else(|| format!("Line {}: bad start time \"{}\"", number + 1, start.trim()))?;
+            let end = parse_time(end).ok_or_else(|| format!("Line {}: bad end time \"{}\"", number + 1, end.trim()))?;
+            if end < start {
+                return Err(format!("Line {}: ends before it starts", number + 1));
+            }
+
+            let mut text = Vec::new();
+            while let Some((_, line)) = lines.next_if(|(_, line)| !line.trim().is_empty()) {
+                text.push(line.trim());
+            }
+            segments.push(SubtitleSegment { start, end, text: text.join("\n") });
+        }
+        segments.sort_by_key(|segment| segment.start);
+
+        Ok(Self {
+            language: language.to_string(),
+            segments
+        })
+    }
+
+    pub fn segments(&self) -> &[SubtitleSegment] {
+        &self.segments
+    }
+
+    // What to show this far in. If two overlap, the one that started last.
+    pub fn at(&self, position: Duration) -> Option<&str> {
+        self.segments.iter().rev()
+            .find(|segment| segment.start <= position && position < segment.end)
+            .map(|segment| segment.text.as_str())
+    }
+}
+
+// "00:01:02,500" or "00:01:02.500".
+fn parse_time(time: &str) -> Option<Duration> {
+    let (clock, millis) = time.trim().split_once([',', '.'])?;
+    let mut parts = clock.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 || millis.len() != 3 {
+        return None;
+    }
+    let millis = millis.parse::<u64>().ok()?;
+    Some(Duration::from_millis(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis))
+}
+
+// Which of the languages a track comes in to show for the player's language: the same one,
+// then the same language for another region, e.g. "en-GB" for "en-US", then the first.
+pub fn pick_language<'a>(available: &'a [String], wanted: &str) -> Option<&'a str> {
+    available.iter().find(|language| language.eq_ignore_ascii_case(wanted))
+        .or_else(|| available.iter().find(|language| base_language(language).eq_ignore_ascii_case(base_language(wanted))))
+        .or_else(|| available.first())
+        .map(String::as_str)
+}
+
+// "pt-BR" -> "pt"
+pub fn base_language(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or(language)
+}
+
+// Characters that can't start a line, like closing brackets, full-width punctuation and small
+// kana, and ones that can't end one, like opening brackets, going by the Japanese and Chinese
+// rules for where lines can break.
+const NO_LINE_START: &str = ")]}>,.!?:;%\u{2019}\u{201D}\u{2026}\u{2025}、。，．・：；？！ー～）］｝〉》」』】〕〗〙〟ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ々〻ゝゞヽヾ";
+const NO_LINE_END: &str = "([{<\u{2018}\u{201C}（［｛〈《「『【〔〖〘〝";
+
+// Whether a line can break either side of a character without there being a space, as Japanese
+// and Chinese don't put spaces between words. Korean does, so hangul goes by words.
+fn breaks_anywhere(c: char) -> bool {
+    font::is_wide(c) && !matches!(c as u32, 0x1100..=0x115F | 0xAC00..=0xD7A3)
+}
+
+fn columns(text: &str) -> usize {
+    text.chars().map(|c| font::char_columns(c) as usize).sum()
+}
+
+// Where a word can be broken with a hyphen, if it has to be.
+const SOFT_HYPHEN: char = '\u{AD}';
+
+// What comes between a piece of a line and the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Gap {
+    Nothing,
+    Space,
+    SoftHyphen
+}
+
+// Break text into lines no more than `max_columns` cells wide, with wide characters taking two.
+// Lines break between words where there are any, and between characters of Japanese and
+// Chinese, but never before closing punctuation or after opening brackets.
+pub fn wrap(text: &str, max_columns: usize) -> String {
+    wrap_with(text, max_columns, false)
+}
+
+// Like wrap, but words can be broken with a hyphen too: at soft hyphens, where a translator's
+// said a long word can go, and anywhere in a word too long for a line of its own.
+pub fn wrap_hyphenated(text: &str, max_columns: usize) -> String {
+    wrap_with(text, max_columns, true)
+}
+
+fn wrap_with(text: &str, max_columns: usize, hyphenate: bool) -> String {
+    let max_columns = max_columns.max(1);
+    let mut wrapped: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let mut current = String::new();
+        for (word, gap) in break_units(line, hyphenate) {
+            let mut word = word;
+            loop {
+                let gap_columns = (!current.is_empty() && gap == Gap::Space) as usize;
+                if columns(&current) + gap_columns + columns(&word) <= max_columns {
+                    if gap_columns > 0 {
+                        current.push(' ');
+                    }
+                    current.push_str(&word);
+                    break;
+                }
+                if !current.is_empty() {
+                    if gap == Gap::SoftHyphen && columns(&current) < max_columns {
+                        current.push('-');
+                    }
+                    wrapped.push(std::mem::take(&mut current));
+                    continue;
+                }
+                // Too long for a line on its own. Always take at least one character, even if
+                // it's a wide one on a line one cell wide.
+                let hyphen = hyphenate && max_columns > 1;
+                let room = if hyphen { max_columns - 1 } else { max_columns };
+                let mut width = 0;
+                let split = word.char_indices()
+                    .find(|&(index, c)| {
+                        width += font::char_columns(c) as usize;
+                        index > 0 && width > room
+                    })
+                    .map(|(index, _)| index)
+                    .unwrap_or(word.len());
+                let mut piece = word[..split].to_string();
+                if hyphen && split < word.len() {
+                    piece.push('-');
+                }
+                wrapped.push(piece);
+                word = word[split..].to_string();
+                if word.is_empty() {
+                    break;
+                }
+            }
+        }
+        wrapped.push(current);
+    }
+    wrapped.join("\n")
+}
+
+// Split a line into the pieces it can be broken between, and what came before each: words, or
+// single characters of Japanese and Chinese, kept together with any punctuation that can't be
+// split from them. Words are split at soft hyphens too when hyphenating, and otherwise they're
+// left out.
+fn break_units(line: &str, hyphenate: bool) -> Vec<(String, Gap)> {
+    let mut units: Vec<(String, Gap)> = Vec::new();
+    let mut gap = Gap::Nothing;
+    // Whether the last unit's a word that's still going.
+    let mut in_word = false;
+    for c in line.chars() {
+        if c == SOFT_HYPHEN {
+            if hyphenate && in_word {
+                gap = Gap::SoftHyphen;
+                in_word = false;
+            }
+            continue;
+        }
+        if c.is_whitespace() && c != '\u{3000}' {
+            gap = Gap::Space;
+            in_word = false;
+            continue;
+        }
+        let joins = gap != Gap::Space && units.last().is_some_and(|(last, _)| {
+            NO_LINE_START.contains(c) || last.chars().last().is_some_and(|end| NO_LINE_END.contains(end))
+        });
+        match units.last_mut() {
+            Some((last, _)) if joins || (in_word && !breaks_anywhere(c)) => last.push(c),
+            _ => units.push((c.to_string(), gap))
+        }
+        in_word = !breaks_anywhere(c);
+        gap = Gap::Nothing;
+    }
+    units
+}
+
+// Draw a subtitle along the bottom of the screen, on a dark strip so it's readable over
+// anything. Drawn as big as the player's text scale asks for.
+pub fn build_subtitle(batch: &mut UiBatch, text: &str, accessibility: &Accessibility) {
+    if text.is_empty() {
+        return;
+    }
+
+    let scale = 2.0 * accessibility.text_scale();
+    let max_columns = ((SCREEN_WIDTH as f32 - (MARGIN + PADDING) * 2.0) / (font::CELL_WIDTH as f32 * scale)) as usize;
+    let text = wrap(text, max_columns);
+    let (width, height) = UiBatch::measure_text(scale, &text);
+    let x = (SCREEN_WIDTH as f32 - width) / 2.0;
+    let y = SCREEN_HEIGHT as f32 - MARGIN - height;
+
+    let skin = accessibility.skin();
+    batch.rect(x - PADDING, y - PADDING, width + PADDING * 2.0, height + PADDING * 2.0, skin.backdrop);
+    batch.text(x, y, scale, &text, skin.text);
+}
@@ -0,0 +1,258 @@
+// Summons and pets: someone a skill brings into a battle for a while, fighting alongside
+// whoever called them. They're in data/summons.cfg, each under an [id] header:
+//
+//   [sprite]
+//   name = Sprite
+//   skill = Call Sprite
+//   hp = 40
+//   strength = 6
+//   speed = 14
+//   turns = 3
+//   gambit = always: attack weakest
+//
+// skill is the one that calls them, and the stats are theirs alone. They take turns of their
+// own, doing what their gambits say like an auto battling member, though they can only attack
+// for now. They leave after as many of their own turns as turns says, or stay for the whole
+// battle without it, and leave early if they're knocked out or, unless "with_summoner = no",
+// whoever called them is. Calling another sends the last one away, so there's only ever one
+// each.
+//
+// They aren't party members, so they're not in the party's menus, don't get experience or
+// rewards and are gone when the battle ends.
+
+use cgmath::Point3;
+
+use crate::auto_battle::{self, AutoCommand, Gambit, GambitAction};
+use crate::battle_ui::{Combatant, Side, TurnOrder};
+use crate::inventory::Inventory;
+use crate::parse::parse_bool;
+use crate::party::{PartyMember, Stats};
+
+// How far in front of whoever called them they stand, in metres.
+const STEP_FORWARD: f32 = 1.0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SummonDefinition {
+    pub id: String,
+    pub name: String,
+    // The skill that calls them.
+    pub skill: String,
+    pub stats: Stats,
+    pub gambits: Vec<Gambit>,
+    // How many of their own turns they stay for, or None for the whole battle.
+    pub turns: Option<u32>,
+    // Whether they leave when whoever called them is knocked out.
+    pub with_summoner: bool
+}
+
+impl SummonDefinition {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: id.to_string(),
+            skill: String::new(),
+            stats: Stats { level: 1, experience: 0, hp: 1, max_hp: 1, mp: 0, max_mp: 0, strength: 1, magic: 1, defence: 1, speed: 1 },
+            gambits: Vec::new(),
+            turns: None,
+            with_summoner: true
+        }
+    }
+
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut summons: Vec<SummonDefinition> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let id = id.trim();
+                if summons.iter().any(|summon| summon.id == id) {
+                    return Err(format!("Line {}: there's already a summon called {}", number + 1, id));
+                }
+                summons.push(SummonDefinition::new(id));
+                continue;
+            }
+
+            let summon = summons.last_mut().ok_or_else(|| format!("Line {}: expected a [summon] first", number + 1))?;
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"key = value\"", number + 1))?;
+            let number_value = || value.parse::<u32>().map_err(|_| format!("Line {}: bad {} \"{}\"", number + 1, key, value));
+            match key {
+                "name" => summon.name = value.to_string(),
+                "skill" => summon.skill = value.to_string(),
+                "hp" => {
+                    summon.stats.max_hp = number_value()?.max(1);
+                    summon.stats.hp = summon.stats.max_hp;
+                },
+                "mp" => {
+                    summon.stats.max_mp = number_value()?;
+                    summon.stats.mp = summon.stats.max_mp;
+                },
+                "strength" => summon.stats.strength = number_value()?,
+                "magic" => summon.stats.magic = number_value()?,
+                "defence" => summon.stats.defence = number_value()?,
+                "speed" => summon.stats.speed = number_value()?,
+                "turns" => summon.turns = Some(number_value()?.max(1)),
+                "with_summoner" => summon.with_summoner = parse_bool(value).map_err(|e| format!("Line {}: {}", number + 1, e))?,
+                "gambit" => {
+                    let gambit: Gambit = value.parse().map_err(|e| format!("Line {}: {}", number + 1, e))?;
+                    if !matches!(gambit.action, GambitAction::Attack(_)) {
+                        return Err(format!("Line {}: summons can only attack, not \"{}\"", number + 1, gambit));
+                    }
+                    summon.gambits.push(gambit);
+                },
+                _ => return Err(format!("Line {}: unknown key \"{}\"", number + 1, key))
+            }
+        }
+        for summon in &mut summons {
+            if summon.gambits.is_empty() {
+                summon.gambits = Gambit::defaults();
+            }
+        }
+        Ok(summons)
+    }
+}
+
+// Resource with every summon.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summons(pub Vec<SummonDefinition>);
+
+impl Summons {
+    pub fn get(&self, id: &str) -> Option<&SummonDefinition> {
+        self.0.iter().find(|summon| summon.id == id)
+    }
+
+    // Who a skill calls, if anyone.
+    pub fn for_skill(&self, skill: &str) -> Option<&SummonDefinition> {
+        self.0.iter().find(|summon| summon.skill.eq_ignore_ascii_case(skill))
+    }
+}
+
+// One that's in a battle now.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveSummon {
+    pub id: String,
+    // Their stats and gambits, for auto_battle::decide.
+    pub member: PartyMember,
+    // Who called them and who they are, as indices into the combatants.
+    pub summoner: usize,
+    pub combatant: usize,
+    pub turns_left: Option<u32>,
+    pub with_summoner: bool
+}
+
+// The summons in a battle. They're added to the end of the combatants so nobody else's index
+// changes, and stay there knocked out once they've left, until the battle's over.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BattleSummons {
+    active: Vec<ActiveSummon>
+}
+
+impl BattleSummons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Bring one in for `summoner`, sending away any they'd already called. They get a turn slot
+    // of their own, waiting as if they'd just acted. Returns who they are in the combatants.
+    pub fn summon(&mut self, definition: &SummonDefinition, summoner: usize, combatants: &mut Vec<Combatant>, turns: &mut TurnOrder) -> Option<usize> {
+        let caller = combatants.get(summoner)?.clone();
+        if let Some(index) = self.active.iter().position(|active| active.summoner == summoner) {
+            let previous = self.active.remove(index);
+            combatants[previous.combatant].knocked_out = true;
+        }
+
+        let stats = definition.stats;
+        let mut member = PartyMember::new(&definition.name, stats);
+        member.gambits = definition.gambits.clone();
+        member.auto_battle = true;
+
+        let forward = if caller.position.z > 0.0 { -STEP_FORWARD } else { STEP_FORWARD };
+        combatants.push(Combatant {
+            name: definition.name.clone(),
+            side: caller.side,
+            speed: stats.speed,
+            position: Point3::new(caller.position.x, caller.position.y, caller.position.z + forward),
+            knocked_out: false,
+            summoned: true
+        });
+        let combatant = combatants.len() - 1;
+        turns.use_turn(combatant, combatants);
+        self.active.push(ActiveSummon {
+            id: definition.id.clone(),
+            member,
+            summoner,
+            combatant,
+            turns_left: definition.turns,
+            with_summoner: definition.with_summoner
+        });
+        Some(combatant)
+    }
+
+    pub fn get(&self, combatant: usize) -> Option<&ActiveSummon> {
+        self.active.iter().find(|active| active.combatant == combatant)
+    }
+
+    pub fn get_mut(&mut self, combatant: usize) -> Option<&mut ActiveSummon> {
+        self.active.iter_mut().find(|active| active.combatant == combatant)
+    }
+
+    pub fn active(&self) -> &[ActiveSummon] {
+        &self.active
+    }
+
+    // What one does on their turn, following their gambits. `stats` has everyone's stats in the
+    // same order as the combatants, like auto_battle::decide.
+    pub fn decide(&self, combatant: usize, combatants: &[Combatant], stats: &[Stats]) -> Option<AutoCommand> {
+        let active = self.get(combatant)?;
+        auto_battle::decide(combatant, &active.member, combatants, stats, &Inventory::new())
+    }
+
+    // One's had their turn. Returns their name if that was their last and they've left.
+    pub fn end_turn(&mut self, combatant: usize, combatants: &mut [Combatant]) -> Option<String> {
+        let active = self.get_mut(combatant)?;
+        let turns_left = active.turns_left.as_mut()?;
+        *turns_left = turns_left.saturating_sub(1);
+        if *turns_left > 0 {
+            return None;
+        }
+        self.dismiss(combatant, combatants)
+    }
+
+    // Send away anyone who's been knocked out, or whose summoner has. Returns their names.
+    pub fn update(&mut self, combatants: &mut [Combatant]) -> Vec<String> {
+        let leaving: Vec<usize> = self.active.iter()
+            .filter(|active| {
+                let knocked_out = |index: usize| combatants.get(index).is_none_or(|combatant| combatant.knocked_out);
+                knocked_out(active.combatant) || active.member.stats.is_knocked_out() || (active.with_summoner && knocked_out(active.summoner))
+            })
+            .map(|active| active.combatant)
+            .collect();
+        leaving.into_iter().filter_map(|combatant| self.dismiss(combatant, combatants)).collect()
+    }
+
+    // Send one away now. Returns their name, if they were here.
+    pub fn dismiss(&mut self, combatant: usize, combatants: &mut [Combatant]) -> Option<String> {
+        let index = self.active.iter().position(|active| active.combatant == combatant)?;
+        let active = self.active.remove(index);
+        if let Some(combatant) = combatants.get_mut(active.combatant) {
+            combatant.knocked_out = true;
+        }
+        Some(active.member.name)
+    }
+
+    // The battle's over: everyone summoned goes, and is taken out of the combatants.
+    pub fn end_battle(&mut self, combatants: &mut Vec<Combatant>) {
+        self.active.clear();
+        combatants.retain(|combatant| !combatant.summoned);
+    }
+}
+
+// The party's own people in a battle, as indices into the combatants: who gets experience and
+// rewards, and shows in the party's menus. Summons are left out.
+pub fn party_members(combatants: &[Combatant]) -> Vec<usize> {
+    (0..combatants.len()).filter(|index| combatants[*index].side == Side::Party && !combatants[*index].summoned).collect()
+}
@@ -0,0 +1,294 @@
+// Opt-in telemetry, like which fields players get to and which battles beat them. Games send it
+// wherever they like by implementing TelemetryBackend, and the engine takes care of the rest:
+// nothing's recorded unless the player's turned it on, events are sent in batches rather than
+// one at a time, and they're queued on disk until the backend takes them, so quitting, going
+// offline or crashing doesn't lose them. Without a backend they go nowhere.
+//
+// Gameplay code only calls record, which puts the event in a resource for the main loop to
+// pick up, so it doesn't need to know whether telemetry's on or where it's going.
+//
+// The queue on disk is one event a line, "seconds kind details":
+//
+//   1792252800 field_entered test_field
+//   1792252860 battle_result goblins won
+//   1792252900 crash Index out of bounds at src/party.rs:120:9
+
+use std::{fmt, fs, io::Write, path::PathBuf, str::FromStr, sync::atomic::{AtomicBool, Ordering}, time::Duration};
+
+use instant::SystemTime;
+
+use crate::logging::targets;
+use crate::world::World;
+
+// Send once this many events are waiting...
+pub const BATCH_SIZE: usize = 20;
+// ...or once the oldest has waited this long.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+// Whether the crash hook should queue crashes. The hook's installed once and can't be taken out
+// again, so turning telemetry off goes through this instead.
+static CRASH_REPORTS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BattleOutcome {
+    Won,
+    Lost,
+    Fled
+}
+
+impl BattleOutcome {
+    pub const ALL: [BattleOutcome; 3] = [BattleOutcome::Won, BattleOutcome::Lost, BattleOutcome::Fled];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BattleOutcome::Won => "won",
+            BattleOutcome::Lost => "lost",
+            BattleOutcome::Fled => "fled"
+        }
+    }
+}
+
+impl fmt::Display for BattleOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BattleOutcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BattleOutcome::ALL.into_iter()
+            .find(|outcome| outcome.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown battle outcome \"{}\", expected won, lost or fled", s.trim()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TelemetryEvent {
+    FieldEntered { field: String },
+    BattleResult { formation: String, outcome: BattleOutcome },
+    // What the panic said, and where.
+    Crash { message: String }
+}
+
+// An event and when it happened, in seconds since the Unix epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TelemetryRecord {
+    pub at: u64,
+    pub event: TelemetryEvent
+}
+
+impl TelemetryRecord {
+    pub fn now(event: TelemetryEvent) -> Self {
+        let at = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Self { at, event }
+    }
+
+    // As a line of the queue, without the line break.
+    pub fn to_line(&self) -> String {
+        // Names are single words, but messages can be anything.
+        match &self.event {
+            TelemetryEvent::FieldEntered { field } => format!("{} field_entered {}", self.at, field),
+            TelemetryEvent::BattleResult { formation, outcome } => format!("{} battle_result {} {}", self.at, formation, outcome),
+            TelemetryEvent::Crash { message } => format!("{} crash {}", self.at, message.replace(['\r', '\n'], " "))
+        }
+    }
+
+    pub fn parse_line(line: &str) -> Result<Self, String> {
+        let mut parts = line.trim().splitn(3, ' ');
+        let at = parts.next().and_then(|at| at.parse().ok()).ok_or_else(|| format!("Bad telemetry time in \"{}\"", line))?;
+        let event = match (parts.next(), parts.next()) {
+            (Some("field_entered"), Some(field)) => TelemetryEvent::FieldEntered { field: field.to_string() },
+            (Some("battle_result"), Some(details)) => match details.split_once(' ') {
+                Some((formation, outcome)) => TelemetryEvent::BattleResult { formation: formation.to_string(), outcome: outcome.parse()? },
+                None => return Err(format!("Expected a formation and outcome in \"{}\"", line))
+            },
+            (Some("crash"), message) => TelemetryEvent::Crash { message: message.unwrap_or_default().to_string() },
+            _ => return Err(format!("Unknown telemetry event \"{}\"", line))
+        };
+        Ok(Self { at, event })
+    }
+}
+
+// What a game implements to send telemetry to its own analytics.
+pub trait TelemetryBackend {
+    fn name(&self) -> &'static str;
+
+    // Send a batch of events, oldest first. Returning an error keeps them queued to try again
+    // at the next flush.
+    fn send(&mut self, batch: &[TelemetryRecord]) -> Result<(), String>;
+}
+
+// Used when the game doesn't have anywhere to send telemetry. Everything sent is dropped.
+#[derive(Default)]
+pub struct NullTelemetry;
+
+impl TelemetryBackend for NullTelemetry {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn send(&mut self, _batch: &[TelemetryRecord]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Resource collecting events since the main loop last took them.
+#[derive(Clone, Debug, Default)]
+pub struct TelemetryEvents {
+    events: Vec<TelemetryRecord>
+}
+
+impl TelemetryEvents {
+    pub fn take_events(&mut self) -> Vec<TelemetryRecord> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// Note something happened, for sending if telemetry's on.
+pub fn record(world: &mut World, event: TelemetryEvent) {
+    let record = TelemetryRecord::now(event);
+    match world.resource_mut::<TelemetryEvents>() {
+        Some(events) => events.events.push(record),
+        None => world.insert_resource(TelemetryEvents { events: vec![record] })
+    }
+}
+
+// Batches events up and hands them to the backend, keeping them on disk until it's taken them.
+pub struct Telemetry {
+    backend: Box<dyn TelemetryBackend>,
+    enabled: bool,
+    queue: Vec<TelemetryRecord>,
+    // None to only keep the queue in memory.
+    queue_path: Option<PathBuf>,
+    // Since the last flush.
+    waited: Duration
+}
+
+impl Telemetry {
+    // Start off with whatever was left queued on disk last time, like a crash.
+    pub fn new(backend: Box<dyn TelemetryBackend>, queue_path: Option<PathBuf>, enabled: bool) -> Self {
+        let mut queue = Vec::new();
+        if let Some(text) = queue_path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                match TelemetryRecord::parse_line(line) {
+                    Ok(record) => queue.push(record),
+                    Err(e) => tracing::warn!(target: targets::ENGINE, "Dropping queued telemetry: {}", e)
+                }
+            }
+        }
+        let mut telemetry = Self { backend, enabled: true, queue, queue_path, waited: Duration::ZERO };
+        telemetry.set_enabled(enabled);
+        telemetry
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Turning it off throws away anything that hasn't been sent yet, as the player's said no.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        CRASH_REPORTS.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.queue.clear();
+            if let Some(path) = &self.queue_path {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    // Events waiting to be sent, oldest first.
+    pub fn pending(&self) -> &[TelemetryRecord] {
+        &self.queue
+    }
+
+    // Queue an event. Does nothing if telemetry's off.
+    pub fn push(&mut self, record: TelemetryRecord) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(path) = &self.queue_path {
+            if let Err(e) = append_line(path, &record.to_line()) {
+                tracing::warn!(target: targets::ENGINE, "Couldn't queue telemetry in {}: {}", path.display(), e);
+            }
+        }
+        self.queue.push(record);
+    }
+
+    // Take the events gameplay's recorded since last time, and send a batch if one's due. Call
+    // once a frame.
+    pub fn update(&mut self, world: &mut World, delta: Duration) {
+        let events = world.resource_mut::<TelemetryEvents>().map(TelemetryEvents::take_events).unwrap_or_default();
+        for record in events {
+            self.push(record);
+        }
+        self.waited += delta;
+        if self.queue.len() >= BATCH_SIZE || (!self.queue.is_empty() && self.waited >= FLUSH_INTERVAL) {
+            self.flush();
+        }
+    }
+
+    // Send everything queued, a batch at a time. Stops at the first batch the backend won't
+    // take, keeping it and the rest for next time.
+    pub fn flush(&mut self) {
+        self.waited = Duration::ZERO;
+        if !self.enabled || self.queue.is_empty() {
+            return;
+        }
+        let mut sent = 0;
+        for batch in self.queue.chunks(BATCH_SIZE) {
+            if let Err(e) = self.backend.send(batch) {
+                tracing::warn!(target: targets::ENGINE, "Couldn't send telemetry to {}: {}", self.backend.name(), e);
+                break;
+            }
+            sent += batch.len();
+        }
+        if sent == 0 {
+            return;
+        }
+        self.queue.drain(..sent);
+        if let Some(path) = &self.queue_path {
+            let text: String = self.queue.iter().map(|record| record.to_line() + "\n").collect();
+            let written = if text.is_empty() { fs::remove_file(path).or(Ok(())) } else { fs::write(path, text) };
+            if let Err(e) = written {
+                tracing::warn!(target: targets::ENGINE, "Couldn't update the telemetry queue in {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn append_line(path: &PathBuf, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+// Queue crashes on disk as they happen, to be sent next time the game's started, if telemetry's
+// on by then. Goes before whatever panic hook was there already, which still runs.
+pub fn install_crash_hook(queue_path: PathBuf) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if CRASH_REPORTS.load(Ordering::Relaxed) {
+            let payload = info.payload();
+            let what = payload.downcast_ref::<&str>().copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Panic");
+            let message = match info.location() {
+                Some(location) => format!("{} at {}", what, location),
+                None => what.to_string()
+            };
+            let _ = append_line(&queue_path, &TelemetryRecord::now(TelemetryEvent::Crash { message }).to_line());
+        }
+        previous(info);
+    }));
+}
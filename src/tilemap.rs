@@ -0,0 +1,870 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use cgmath::Vector3;
+use wgpu::{BindGroup, Buffer, Device, Queue, Sampler, Texture, TextureFormat, TextureView, util::DeviceExt};
+
+use crate::assets::{AssetError, AssetServer};
+use crate::field::FieldExit;
+use crate::pipeline_cache::{PipelineCache, PipelineId, BindGroupLayoutId, PipelineKey, texture_entry, sampler_entry, uniform_entry};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+
+// Tiled keeps whether a tile's flipped in the top bits of its id.
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+// Only used by hexagonal maps, which aren't supported, but it still has to be masked off.
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+const TILE_ID_MASK: u32 = !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+
+// An atlas of tiles, all the same size.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tileset {
+    // The id its first tile has in the map's layers. The rest follow on from it.
+    pub first_gid: u32,
+    // The atlas image, relative to the map.
+    pub image: String,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    // Pixels around the edge of the atlas, and between tiles.
+    pub margin: u32,
+    pub spacing: u32,
+    // Tiles nothing can walk through, by their id in the tileset. Set with a "solid" property
+    // on the tile in Tiled.
+    pub solid: HashSet<u32>
+}
+
+impl Tileset {
+    fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    // Where a tile is in the atlas in texture coordinates, as left, top, right and bottom.
+    pub fn uv_rect(&self, id: u32) -> [f32; 4] {
+        let columns = self.columns.max(1);
+        let x = self.margin + (id % columns) * (self.tile_width + self.spacing);
+        let y = self.margin + (id / columns) * (self.tile_height + self.spacing);
+        let width = self.image_width.max(1) as f32;
+        let height = self.image_height.max(1) as f32;
+        [
+            x as f32 / width,
+            y as f32 / height,
+            (x + self.tile_width) as f32 / width,
+            (y + self.tile_height) as f32 / height
+        ]
+    }
+}
+
+// A grid of tiles. Drawn under the models, or over them if it has an "above" property.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    // A row at a time from the top left. 0 is empty, and the top bits say how it's flipped.
+    pub tiles: Vec<u32>,
+    pub visible: bool,
+    pub above: bool
+}
+
+impl TileLayer {
+    // The tile at a place in the layer, or 0 if there isn't one.
+    pub fn tile(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles.get((y * self.width + x) as usize).copied().unwrap_or(0)
+    }
+}
+
+// Something placed on an object layer, like a door, a sign or where an NPC stands. What it
+// is is up to its type, which Tiled also calls its class.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileObject {
+    pub name: String,
+    pub kind: String,
+    // In map pixels, from the top left.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub properties: HashMap<String, String>
+}
+
+impl TileObject {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+// One tile to draw. The corners' texture coordinates go top left, top right, bottom right,
+// bottom left, so flipped tiles are just a different order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileQuad {
+    // Which of the map's tilesets it's from.
+    pub tileset: usize,
+    // Where it goes in map pixels, as x, y, width and height.
+    pub rect: [f32; 4],
+    pub uvs: [[f32; 2]; 4]
+}
+
+// A 2D field made in Tiled, from a .tmx or .tmj file. Only orthogonal, fixed size maps with
+// the tilesets embedded in them are supported.
+#[derive(Clone, Debug, Default)]
+pub struct Tilemap {
+    // In tiles.
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    // In order of first_gid.
+    pub tilesets: Vec<Tileset>,
+    // From the bottom up.
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<TileObject>,
+    // Each tileset's image, once loaded.
+    pub atlases: Vec<image::RgbaImage>
+}
+
+impl Tilemap {
+    // Load a map and its tilesets' images.
+    pub async fn load(assets: &AssetServer, path: &str) -> Result<Self, AssetError> {
+        let bytes = assets.load_bytes(path).await?;
+        let text = String::from_utf8_lossy(&bytes);
+        let mut map = if path.ends_with(".tmx") {
+            Self::parse_tmx(&text)
+        } else {
+            Self::parse_tmj(&text)
+        }.map_err(|e| AssetError::Decode(path.to_string(), e))?;
+
+        for tileset in &mut map.tilesets {
+            let image = assets.load_image(&relative_path(path, &tileset.image)).await?;
+            tileset.image_width = image.width();
+            tileset.image_height = image.height();
+            map.atlases.push(image);
+        }
+        Ok(map)
+    }
+
+    // Read a map saved as JSON.
+    pub fn parse_tmj(text: &str) -> Result<Self, String> {
+        let json: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        check_map_kind(json_str(&json, "orientation"), json.get("infinite").and_then(|infinite| infinite.as_bool()).unwrap_or(false))?;
+
+        let mut map = Self {
+            width: json_number(&json, "width")?,
+            height: json_number(&json, "height")?,
+            tile_width: json_number(&json, "tilewidth")?,
+            tile_height: json_number(&json, "tileheight")?,
+            ..Default::default()
+        };
+        for tileset in json.get("tilesets").and_then(|tilesets| tilesets.as_array()).into_iter().flatten() {
+            if let Some(source) = tileset.get("source").and_then(|source| source.as_str()) {
+                return Err(external_tileset_error(source));
+            }
+            let mut solid = HashSet::new();
+            for tile in tileset.get("tiles").and_then(|tiles| tiles.as_array()).into_iter().flatten() {
+                if is_true(json_properties(tile).get("solid")) {
+                    solid.insert(json_number(tile, "id")?);
+                }
+            }
+            map.tilesets.push(Tileset {
+                first_gid: json_number(tileset, "firstgid")?,
+                image: json_str(tileset, "image").to_string(),
+                image_width: json_number(tileset, "imagewidth").unwrap_or(0),
+                image_height: json_number(tileset, "imageheight").unwrap_or(0),
+                tile_width: json_number(tileset, "tilewidth")?,
+                tile_height: json_number(tileset, "tileheight")?,
+                columns: json_number(tileset, "columns")?,
+                tile_count: json_number(tileset, "tilecount")?,
+                margin: json_number(tileset, "margin").unwrap_or(0),
+                spacing: json_number(tileset, "spacing").unwrap_or(0),
+                solid
+            });
+        }
+        map.add_json_layers(&json, true)?;
+        map.finish()
+    }
+
+    // Layers can be in groups, which are flattened out. Hiding a group hides everything in it.
+    fn add_json_layers(&mut self, parent: &serde_json::Value, parent_visible: bool) -> Result<(), String> {
+        for layer in parent.get("layers").and_then(|layers| layers.as_array()).into_iter().flatten() {
+            let visible = parent_visible && layer.get("visible").and_then(|visible| visible.as_bool()).unwrap_or(true);
+            match json_str(layer, "type") {
+                "tilelayer" => {
+                    let tiles = match layer.get("data") {
+                        Some(serde_json::Value::Array(data)) => data.iter().map(|tile| tile.as_u64().unwrap_or(0) as u32).collect(),
+                        Some(serde_json::Value::String(data)) => decode_tiles(data, json_str(layer, "encoding"), json_str(layer, "compression"))?,
+                        _ => return Err(format!("Layer \"{}\" has no tiles", json_str(layer, "name")))
+                    };
+                    self.layers.push(TileLayer {
+                        name: json_str(layer, "name").to_string(),
+                        width: json_number(layer, "width")?,
+                        height: json_number(layer, "height")?,
+                        tiles,
+                        visible,
+                        above: is_true(json_properties(layer).get("above"))
+                    });
+                },
+                "objectgroup" => {
+                    for object in layer.get("objects").and_then(|objects| objects.as_array()).into_iter().flatten() {
+                        let kind = match json_str(object, "type") {
+                            "" => json_str(object, "class"),
+                            kind => kind
+                        };
+                        self.objects.push(TileObject {
+                            name: json_str(object, "name").to_string(),
+                            kind: kind.to_string(),
+                            x: json_float(object, "x"),
+                            y: json_float(object, "y"),
+                            width: json_float(object, "width"),
+                            height: json_float(object, "height"),
+                            properties: json_properties(object)
+                        });
+                    }
+                },
+                "group" => self.add_json_layers(layer, visible)?,
+                // Image layers aren't supported.
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Read a map saved as XML.
+    pub fn parse_tmx(text: &str) -> Result<Self, String> {
+        let root = XmlElement::parse(text)?;
+        if root.name != "map" {
+            return Err(format!("Expected a map, found {}", root.name));
+        }
+        check_map_kind(root.attribute("orientation").unwrap_or_default(), root.attribute("infinite") == Some("1"))?;
+
+        let mut map = Self {
+            width: root.number("width")?,
+            height: root.number("height")?,
+            tile_width: root.number("tilewidth")?,
+            tile_height: root.number("tileheight")?,
+            ..Default::default()
+        };
+        for tileset in root.children("tileset") {
+            if let Some(source) = tileset.attribute("source") {
+                return Err(external_tileset_error(source));
+            }
+            let image = tileset.child("image").ok_or_else(|| format!("Tileset \"{}\" has no image, image collections aren't supported", tileset.attribute("name").unwrap_or_default()))?;
+            let mut solid = HashSet::new();
+            for tile in tileset.children("tile") {
+                if is_true(tile.properties().get("solid")) {
+                    solid.insert(tile.number("id")?);
+                }
+            }
+            map.tilesets.push(Tileset {
+                first_gid: tileset.number("firstgid")?,
+                image: image.attribute("source").unwrap_or_default().to_string(),
+                image_width: image.number("width").unwrap_or(0),
+                image_height: image.number("height").unwrap_or(0),
+                tile_width: tileset.number("tilewidth")?,
+                tile_height: tileset.number("tileheight")?,
+                columns: tileset.number("columns")?,
+                tile_count: tileset.number("tilecount")?,
+                margin: tileset.number("margin").unwrap_or(0),
+                spacing: tileset.number("spacing").unwrap_or(0),
+                solid
+            });
+        }
+        map.add_xml_layers(&root, true)?;
+        map.finish()
+    }
+
+    fn add_xml_layers(&mut self, parent: &XmlElement, parent_visible: bool) -> Result<(), String> {
+        for layer in &parent.children {
+            let visible = parent_visible && layer.attribute("visible") != Some("0");
+            match layer.name.as_str() {
+                "layer" => {
+                    let name = layer.attribute("name").unwrap_or_default();
+                    let data = layer.child("data").ok_or_else(|| format!("Layer \"{}\" has no tiles", name))?;
+                    let tiles = match data.attribute("encoding") {
+                        // Without an encoding each tile is an element of its own.
+                        None => data.children("tile").map(|tile| tile.number("gid").unwrap_or(0)).collect(),
+                        Some(encoding) => decode_tiles(&data.text, encoding, data.attribute("compression").unwrap_or_default())?
+                    };
+                    self.layers.push(TileLayer {
+                        name: name.to_string(),
+                        width: layer.number("width")?,
+                        height: layer.number("height")?,
+                        tiles,
+                        visible,
+                        above: is_true(layer.properties().get("above"))
+                    });
+                },
+                "objectgroup" => {
+                    for object in layer.children("object") {
+                        self.objects.push(TileObject {
+                            name: object.attribute("name").unwrap_or_default().to_string(),
+                            kind: object.attribute("type").or_else(|| object.attribute("class")).unwrap_or_default().to_string(),
+                            x: object.float("x"),
+                            y: object.float("y"),
+                            width: object.float("width"),
+                            height: object.float("height"),
+                            properties: object.properties()
+                        });
+                    }
+                },
+                "group" => self.add_xml_layers(layer, visible)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Check what was read makes sense, so nothing later has to.
+    fn finish(mut self) -> Result<Self, String> {
+        if self.tile_width == 0 || self.tile_height == 0 {
+            return Err("Tiles can't be 0 pixels big".to_string());
+        }
+        for layer in &self.layers {
+            if layer.tiles.len() != (layer.width * layer.height) as usize {
+                return Err(format!("Layer \"{}\" should have {} tiles but has {}", layer.name, layer.width * layer.height, layer.tiles.len()));
+            }
+        }
+        for tileset in &self.tilesets {
+            if tileset.image.is_empty() {
+                return Err("A tileset has no image, image collections aren't supported".to_string());
+            }
+        }
+        self.tilesets.sort_by_key(|tileset| tileset.first_gid);
+        Ok(self)
+    }
+
+    pub fn pixel_width(&self) -> f32 {
+        (self.width * self.tile_width) as f32
+    }
+
+    pub fn pixel_height(&self) -> f32 {
+        (self.height * self.tile_height) as f32
+    }
+
+    // Which tileset a tile's from, and its id in that tileset.
+    pub fn tileset(&self, gid: u32) -> Option<(usize, u32)> {
+        let gid = gid & TILE_ID_MASK;
+        self.tilesets.iter()
+            .position(|tileset| tileset.contains(gid))
+            .map(|index| (index, gid - self.tilesets[index].first_gid))
+    }
+
+    // Whether anything's in the way at a tile. Everything off the edge of the map is.
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return true;
+        }
+        self.layers.iter().any(|layer| {
+            self.tileset(layer.tile(x as u32, y as u32))
+                .is_some_and(|(tileset, id)| self.tilesets[tileset].solid.contains(&id))
+        })
+    }
+
+    // The same, for a point in map pixels.
+    pub fn is_solid_at(&self, x: f32, y: f32) -> bool {
+        self.is_solid((x / self.tile_width as f32).floor() as i32, (y / self.tile_height as f32).floor() as i32)
+    }
+
+    // The objects a point in map pixels is inside, e.g. to see what the player's stepped on.
+    pub fn objects_at(&self, x: f32, y: f32) -> impl Iterator<Item = &TileObject> {
+        self.objects.iter().filter(move |object| object.contains(x, y))
+    }
+
+    // Objects with the "exit" type and a "target" property, as exits into other fields. In 2D
+    // fields x and z are map pixels.
+    pub fn exits(&self) -> Vec<FieldExit> {
+        self.objects.iter()
+            .filter(|object| object.kind == "exit")
+            .filter_map(|object| Some(FieldExit {
+                target: object.properties.get("target")?.clone(),
+                position: Vector3::new(object.x + object.width / 2.0, 0.0, object.y + object.height / 2.0)
+            }))
+            .collect()
+    }
+
+    // Every tile in a layer that has something to draw.
+    pub fn quads(&self, layer: &TileLayer) -> Vec<TileQuad> {
+        let mut quads = Vec::new();
+        for y in 0..layer.height {
+            for x in 0..layer.width {
+                let gid = layer.tile(x, y);
+                let (index, id) = match self.tileset(gid) {
+                    Some(found) => found,
+                    None => continue
+                };
+                let tileset = &self.tilesets[index];
+                let [left, top, right, bottom] = tileset.uv_rect(id);
+
+                // Undo Tiled's flips in the opposite order to how it applies them.
+                let uvs = [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(mut u, mut v)| {
+                    if gid & FLIPPED_VERTICALLY != 0 {
+                        v = 1 - v;
+                    }
+                    if gid & FLIPPED_HORIZONTALLY != 0 {
+                        u = 1 - u;
+                    }
+                    if gid & FLIPPED_DIAGONALLY != 0 {
+                        std::mem::swap(&mut u, &mut v);
+                    }
+                    [if u == 0 { left } else { right }, if v == 0 { top } else { bottom }]
+                });
+
+                // Tiles bigger than the map's grid stick up out of the bottom left of their cell.
+                let cell_bottom = ((y + 1) * self.tile_height) as f32;
+                quads.push(TileQuad {
+                    tileset: index,
+                    rect: [(x * self.tile_width) as f32, cell_bottom - tileset.tile_height as f32, tileset.tile_width as f32, tileset.tile_height as f32],
+                    uvs
+                });
+            }
+        }
+        quads
+    }
+
+    // Where to scroll to so a point in map pixels is in the middle of the screen, without
+    // showing past the edges of the map. Maps smaller than the screen are put in the middle.
+    pub fn scroll_to(&self, x: f32, y: f32) -> [f32; 2] {
+        let axis = |centre: f32, map_size: f32, screen_size: f32| {
+            if map_size <= screen_size {
+                (map_size - screen_size) / 2.0
+            } else {
+                (centre - screen_size / 2.0).clamp(0.0, map_size - screen_size)
+            }
+        };
+        [
+            axis(x, self.pixel_width(), SCREEN_WIDTH as f32),
+            axis(y, self.pixel_height(), SCREEN_HEIGHT as f32)
+        ]
+    }
+}
+
+fn check_map_kind(orientation: &str, infinite: bool) -> Result<(), String> {
+    if orientation != "orthogonal" {
+        return Err(format!("Only orthogonal maps are supported, not {}", orientation));
+    }
+    if infinite {
+        return Err("Infinite maps aren't supported, turn it off in the map's properties".to_string());
+    }
+    Ok(())
+}
+
+fn external_tileset_error(source: &str) -> String {
+    format!("The tileset {} is in a file of its own, embed it in the map", source)
+}
+
+fn is_true(property: Option<&String>) -> bool {
+    property.is_some_and(|value| value == "true")
+}
+
+// A path relative to a map, as a path relative to the asset root.
+fn relative_path(map_path: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = match map_path.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => Vec::new()
+    };
+    for part in path.split('/') {
+        match part {
+            "." | "" => {},
+            ".." => {
+                parts.pop();
+            },
+            part => parts.push(part)
+        }
+    }
+    parts.join("/")
+}
+
+// Tile data saved as CSV or base64, the latter maybe compressed.
+fn decode_tiles(data: &str, encoding: &str, compression: &str) -> Result<Vec<u32>, String> {
+    use base64::Engine;
+
+    match encoding {
+        "csv" => data.split(',')
+            .map(str::trim)
+            .filter(|tile| !tile.is_empty())
+            .map(|tile| tile.parse::<u32>().map_err(|e| format!("Bad tile \"{}\": {}", tile, e)))
+            .collect(),
+        "base64" => {
+            let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).map_err(|e| e.to_string())?;
+            let bytes = match compression {
+                "" => bytes,
+                "zlib" => decompress(flate2::read::ZlibDecoder::new(bytes.as_slice()))?,
+                "gzip" => decompress(flate2::read::GzDecoder::new(bytes.as_slice()))?,
+                compression => return Err(format!("{} compression isn't supported, use zlib, gzip or none", compression))
+            };
+            Ok(bytes.chunks_exact(4).map(|tile| u32::from_le_bytes([tile[0], tile[1], tile[2], tile[3]])).collect())
+        },
+        encoding => Err(format!("Unknown tile encoding {}", encoding))
+    }
+}
+
+fn decompress(mut decoder: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    decoder.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+fn json_number(value: &serde_json::Value, key: &str) -> Result<u32, String> {
+    value.get(key).and_then(|number| number.as_u64()).map(|number| number as u32).ok_or_else(|| format!("Missing {}", key))
+}
+
+fn json_float(value: &serde_json::Value, key: &str) -> f32 {
+    value.get(key).and_then(|number| number.as_f64()).unwrap_or(0.0) as f32
+}
+
+fn json_str<'a>(value: &'a serde_json::Value, key: &str) -> &'a str {
+    value.get(key).and_then(|string| string.as_str()).unwrap_or_default()
+}
+
+// Custom properties, whatever their type, as text.
+fn json_properties(value: &serde_json::Value) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    for property in value.get("properties").and_then(|properties| properties.as_array()).into_iter().flatten() {
+        let value = match property.get("value") {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => continue
+        };
+        properties.insert(json_str(property, "name").to_string(), value);
+    }
+    properties
+}
+
+// Just enough of an XML document to read a map from.
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<XmlElement>,
+    text: String
+}
+
+impl XmlElement {
+    fn parse(text: &str) -> Result<Self, String> {
+        use xml::reader::XmlEvent;
+
+        let mut open = vec![XmlElement::default()];
+        for event in xml::reader::EventReader::from_str(text) {
+            match event.map_err(|e| e.to_string())? {
+                XmlEvent::StartElement { name, attributes, .. } => open.push(XmlElement {
+                    name: name.local_name,
+                    attributes: attributes.into_iter().map(|attribute| (attribute.name.local_name, attribute.value)).collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = open.pop().ok_or("Unbalanced elements")?;
+                    open.last_mut().ok_or("Unbalanced elements")?.children.push(element);
+                },
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = open.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                },
+                _ => {}
+            }
+        }
+        open.pop()
+            .and_then(|document| document.children.into_iter().next())
+            .ok_or_else(|| "The file is empty".to_string())
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn number(&self, name: &str) -> Result<u32, String> {
+        let value = self.attribute(name).ok_or_else(|| format!("Missing {}", name))?;
+        value.parse().map_err(|e| format!("Bad {} \"{}\": {}", name, value, e))
+    }
+
+    fn float(&self, name: &str) -> f32 {
+        self.attribute(name).and_then(|value| value.parse().ok()).unwrap_or(0.0)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn child<'a>(&'a self, name: &'a str) -> Option<&'a XmlElement> {
+        self.children(name).next()
+    }
+
+    fn properties(&self) -> HashMap<String, String> {
+        self.child("properties")
+            .into_iter()
+            .flat_map(|properties| properties.children("property"))
+            .filter_map(|property| {
+                // Multi-line strings are kept in the element instead of the attribute.
+                let value = property.attribute("value").map(str::to_string).unwrap_or_else(|| property.text.clone());
+                Some((property.attribute("name")?.to_string(), value))
+            })
+            .collect()
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileVertex {
+    // In map pixels.
+    position: [f32; 2],
+    uv: [f32; 2]
+}
+
+impl TileVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TileVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TilemapUniforms {
+    scroll: [f32; 2],
+    screen_size: [f32; 2]
+}
+
+struct GpuAtlas {
+    _texture: Texture,
+    bind_group: BindGroup,
+    texture_bytes: u64
+}
+
+// All of one layer's tiles from one tileset, drawn together.
+struct GpuTileBatch {
+    atlas: usize,
+    vertex_buffer: Buffer,
+    vertex_count: u32,
+    above: bool
+}
+
+// Draws a tilemap's layers, scrolled so the part being looked at is on screen. The layers
+// below the models and the ones above them are drawn separately.
+pub struct TilemapRenderer {
+    render_pipeline: PipelineId,
+    bind_group_layout: BindGroupLayoutId,
+    uniform_buffer: Buffer,
+    sampler: Sampler,
+
+    atlases: Vec<GpuAtlas>,
+    batches: Vec<GpuTileBatch>,
+
+    scroll: [f32; 2],
+    // What's in the uniform buffer, so it's only written when it changes.
+    uploaded_scroll: Option<[f32; 2]>
+}
+
+impl TilemapRenderer {
+    pub fn new(device: &Device, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("tilemap.wgsl"));
+
+        let bind_group_layout = pipelines.create_bind_group_layout(device, "Tilemap Renderer Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT),
+            uniform_entry(2, wgpu::ShaderStages::VERTEX)
+        ]);
+
+        let render_pipeline = pipelines.create_render_pipeline(device, "Tilemap Render Pipeline", PipelineKey::new(
+            shader,
+            &[bind_group_layout],
+            vec![TileVertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        ));
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tilemap Uniform Buffer"),
+            size: std::mem::size_of::<TilemapUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        // Tiles are pixel art, and filtering them would bleed their neighbours in the atlas in.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            render_pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            sampler,
+            atlases: Vec::new(),
+            batches: Vec::new(),
+            scroll: [0.0, 0.0],
+            uploaded_scroll: None
+        }
+    }
+
+    // Upload a map's atlases and tiles, replacing whichever map was there before. The map's
+    // layers don't change once it's loaded, so this is the only time they're uploaded.
+    pub fn set_map(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, map: &Tilemap) {
+        self.clear();
+
+        for atlas in &map.atlases {
+            let texture = device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+                label: Some("Tilemap Atlas Texture"),
+                size: wgpu::Extent3d {
+                    width: atlas.width(),
+                    height: atlas.height(),
+                    depth_or_array_layers: 1
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+            }, atlas.as_raw());
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Tilemap Atlas Bind Group"),
+                layout: pipelines.get_bind_group_layout(self.bind_group_layout),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler)
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.uniform_buffer.as_entire_binding()
+                    }
+                ]
+            });
+            self.atlases.push(GpuAtlas {
+                _texture: texture,
+                bind_group,
+                texture_bytes: atlas.as_raw().len() as u64
+            });
+        }
+
+        for layer in map.layers.iter().filter(|layer| layer.visible) {
+            let mut vertices: Vec<Vec<TileVertex>> = vec![Vec::new(); self.atlases.len()];
+            for quad in map.quads(layer) {
+                let atlas_vertices = match vertices.get_mut(quad.tileset) {
+                    Some(atlas_vertices) => atlas_vertices,
+                    None => continue
+                };
+                let [x, y, width, height] = quad.rect;
+                let [top_left, top_right, bottom_right, bottom_left] = quad.uvs;
+                atlas_vertices.extend_from_slice(&[
+                    TileVertex { position: [x, y + height], uv: bottom_left },
+                    TileVertex { position: [x + width, y + height], uv: bottom_right },
+                    TileVertex { position: [x + width, y], uv: top_right },
+
+                    TileVertex { position: [x + width, y], uv: top_right },
+                    TileVertex { position: [x, y], uv: top_left },
+                    TileVertex { position: [x, y + height], uv: bottom_left }
+                ]);
+            }
+
+            for (atlas, vertices) in vertices.into_iter().enumerate() {
+                if vertices.is_empty() {
+                    continue;
+                }
+                let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Tilemap Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX
+                });
+                self.batches.push(GpuTileBatch {
+                    atlas,
+                    vertex_buffer,
+                    vertex_count: vertices.len() as u32,
+                    above: layer.above
+                });
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.atlases.clear();
+        self.batches.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    // The top left of the screen, in map pixels.
+    pub fn set_scroll(&mut self, scroll: [f32; 2]) {
+        self.scroll = scroll;
+    }
+
+    pub fn texture_bytes(&self) -> u64 {
+        self.atlases.iter().map(|atlas| atlas.texture_bytes).sum()
+    }
+
+    // Draw the layers below the models, or the ones above them. Returns the number of draw
+    // calls made.
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, above: bool) -> u32 {
+        if !self.batches.iter().any(|batch| batch.above == above) {
+            return 0;
+        }
+
+        if self.uploaded_scroll != Some(self.scroll) {
+            let uniforms = TilemapUniforms {
+                scroll: self.scroll,
+                screen_size: [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32]
+            };
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+            self.uploaded_scroll = Some(self.scroll);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Tilemap Renderer Encoder.")
+        });
+
+        let mut draw_calls = 0;
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Tilemap Renderer Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: None
+            });
+
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
+            render_pass.set_viewport(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0, 1.0);
+            for batch in self.batches.iter().filter(|batch| batch.above == above) {
+                render_pass.set_bind_group(0, &self.atlases[batch.atlas].bind_group, &[]);
+                render_pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
+                render_pass.draw(0..batch.vertex_count, 0..1);
+                draw_calls += 1;
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+        draw_calls
+    }
+}
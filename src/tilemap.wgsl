@@ -0,0 +1,43 @@
+// Vertex shader
+struct TilemapUniforms {
+    // The top left of the screen, in map pixels.
+    scroll: vec2<f32>,
+    screen_size: vec2<f32>,
+};
+
+@group(0) @binding(2)
+var<uniform> tilemap: TilemapUniforms;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Positions are in map pixels with y going down, so scroll them and turn them into clip space.
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.uv = model.uv;
+    let screen = (model.position - tilemap.scroll) / tilemap.screen_size;
+    out.clip_position = vec4<f32>(screen.x * 2.0 - 1.0, 1.0 - screen.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// Fragment shader
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.uv);
+}
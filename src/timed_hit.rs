@@ -0,0 +1,231 @@
+// Timed hits, like Super Mario RPG's: a skill can ask for the button to be pressed just as it
+// lands, for more damage. Its animation has a "timed_hit" event a little before its "hit" one,
+// and from there a box shrinks onto the target, closing on it as the hit lands. Pressing enter
+// or space then grades it, better the closer it was to the hit, and the hits wait to be dealt
+// until it's graded. Only the first press counts, so mashing doesn't help.
+//
+// What each grade multiplies damage by is in data/combat.cfg, as timed_good, timed_great and
+// timed_perfect.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::accessibility::Accessibility;
+use crate::combat::{CombatRules, HitResult, Outcome};
+use crate::screen_effects::Easing;
+use crate::tween::{Tween, UiPose};
+use crate::ui::UiBatch;
+
+// The animation event a skill asks for a timed hit on.
+pub const TIMED_HIT_EVENT: &str = "timed_hit";
+// How big the box is once it's closed on the target, in pixels.
+const CUE_SIZE: f32 = 24.0;
+// How many times bigger it starts.
+const CUE_GROWTH: f32 = 3.0;
+const CUE_THICKNESS: f32 = 2.0;
+// How long the grade stays up.
+const GRADE_DURATION: Duration = Duration::from_millis(800);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimedGrade {
+    Miss,
+    Good,
+    Great,
+    Perfect
+}
+
+impl TimedGrade {
+    pub const ALL: [TimedGrade; 4] = [TimedGrade::Miss, TimedGrade::Good, TimedGrade::Great, TimedGrade::Perfect];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimedGrade::Miss => "miss",
+            TimedGrade::Good => "good",
+            TimedGrade::Great => "great",
+            TimedGrade::Perfect => "perfect"
+        }
+    }
+
+    // What's shown over the target.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimedGrade::Miss => "Too bad",
+            TimedGrade::Good => "Good",
+            TimedGrade::Great => "Great!",
+            TimedGrade::Perfect => "Perfect!!"
+        }
+    }
+}
+
+impl fmt::Display for TimedGrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TimedGrade {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimedGrade::ALL.into_iter()
+            .find(|grade| grade.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown grade \"{}\", expected miss, good, great or perfect", s.trim()))
+    }
+}
+
+// How long before the hit the cue starts, and how far either side of it a press gets each grade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedWindow {
+    pub lead: Duration,
+    pub perfect: Duration,
+    pub great: Duration,
+    pub good: Duration
+}
+
+impl Default for TimedWindow {
+    fn default() -> Self {
+        Self {
+            lead: Duration::from_millis(500),
+            perfect: Duration::from_millis(40),
+            great: Duration::from_millis(90),
+            good: Duration::from_millis(160)
+        }
+    }
+}
+
+impl TimedWindow {
+    // The grade for a press this far from the hit, either way.
+    pub fn grade(&self, off_by: Duration) -> TimedGrade {
+        if off_by <= self.perfect {
+            TimedGrade::Perfect
+        } else if off_by <= self.great {
+            TimedGrade::Great
+        } else if off_by <= self.good {
+            TimedGrade::Good
+        } else {
+            TimedGrade::Miss
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedHit {
+    window: TimedWindow,
+    // What each grade multiplies damage by, in the order of TimedGrade::ALL.
+    multipliers: [f32; 4],
+    // Everything's timed from the cue.
+    since_cue: Duration,
+    // When the hit event came.
+    moment: Option<Duration>,
+    pressed: Option<Duration>,
+    grade: Option<TimedGrade>,
+    // The grade popping up, once there is one.
+    banner: Tween
+}
+
+impl TimedHit {
+    // Started at the skill's timed hit event.
+    pub fn new(window: TimedWindow, rules: &dyn CombatRules) -> Self {
+        Self {
+            window,
+            multipliers: TimedGrade::ALL.map(|grade| rules.timed_hit_multiplier(grade)),
+            since_cue: Duration::ZERO,
+            moment: None,
+            pressed: None,
+            grade: None,
+            banner: Tween::new(UiPose::REST)
+        }
+    }
+
+    // The skill's animation reached its hit.
+    pub fn hit(&mut self) {
+        if self.moment.is_none() {
+            self.moment = Some(self.since_cue);
+            self.decide();
+        }
+    }
+
+    // The button was pressed. Returns whether it counted, which only the first press does.
+    pub fn press(&mut self) -> bool {
+        if self.pressed.is_some() || self.grade.is_some() {
+            return false;
+        }
+        self.pressed = Some(self.since_cue);
+        self.decide();
+        true
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.since_cue += delta;
+        self.banner.update(delta);
+        self.decide();
+    }
+
+    // None until it's been pressed, or it's too late to.
+    pub fn grade(&self) -> Option<TimedGrade> {
+        self.grade
+    }
+
+    // Graded, and the grade's been shown.
+    pub fn is_finished(&self) -> bool {
+        self.grade.is_some() && self.banner.is_finished()
+    }
+
+    // What damage is multiplied by. Nothing changes until it's graded.
+    pub fn multiplier(&self) -> f32 {
+        match self.grade {
+            Some(grade) => self.multipliers[grade as usize],
+            None => 1.0
+        }
+    }
+
+    // A hit with its damage multiplied by the grade. Misses stay misses.
+    pub fn apply(&self, result: HitResult) -> HitResult {
+        if result.outcome == Outcome::Miss {
+            return result;
+        }
+        HitResult { damage: (result.damage as f32 * self.multiplier()).round() as u32, ..result }
+    }
+
+    fn decide(&mut self) {
+        let moment = match (self.grade, self.moment) {
+            (None, Some(moment)) => moment,
+            _ => return
+        };
+        let grade = match self.pressed {
+            Some(pressed) => self.window.grade(pressed.abs_diff(moment)),
+            None if self.since_cue > moment + self.window.good => TimedGrade::Miss,
+            None => return
+        };
+        self.grade = Some(grade);
+        self.banner = Tween::new(UiPose { scale: 1.5, ..UiPose::REST })
+            .then(UiPose::REST, GRADE_DURATION / 4, Easing::EaseOut)
+            .hold(GRADE_DURATION / 2)
+            .then(UiPose { alpha: 0.0, ..UiPose::at(0.0, -CUE_SIZE) }, GRADE_DURATION / 4, Easing::Linear);
+    }
+
+    // The box closing in on (x, y) until it's graded, then the grade.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, x: f32, y: f32) {
+        let skin = accessibility.skin();
+        match self.grade {
+            None => {
+                let t = (self.since_cue.as_secs_f32() / self.window.lead.as_secs_f32().max(f32::EPSILON)).min(1.0);
+                let size = CUE_SIZE * (CUE_GROWTH + (1.0 - CUE_GROWTH) * t);
+                let (left, top) = (x - size / 2.0, y - size / 2.0);
+                let color = if self.pressed.is_some() { skin.dim_text } else { skin.highlight };
+                batch.rect(left, top, size, CUE_THICKNESS, color);
+                batch.rect(left, top + size - CUE_THICKNESS, size, CUE_THICKNESS, color);
+                batch.rect(left, top, CUE_THICKNESS, size, color);
+                batch.rect(left + size - CUE_THICKNESS, top, CUE_THICKNESS, size, color);
+            },
+            Some(grade) if !self.banner.is_finished() => {
+                let scale = 2.0 * accessibility.text_scale();
+                let (width, height) = UiBatch::measure_text(scale, grade.label());
+                let color = if grade == TimedGrade::Miss { skin.dim_text } else { skin.highlight };
+                batch.set_pose(self.banner.pose(), [x, y]);
+                batch.text(x - width / 2.0, y - height / 2.0, scale, grade.label(), color);
+                batch.clear_pose();
+            },
+            Some(_) => {}
+        }
+    }
+}
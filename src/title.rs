@@ -0,0 +1,383 @@
+// The title screen the game starts on, with the menu for starting a new game or carrying on
+// from a save. What it shows comes from data/title.cfg, one "key = value" per line:
+//
+//   title = The game's name, drawn above the menu
+//   image = A picture to show behind it, or...
+//   field = ...a field to show behind it instead
+//   menu = Which options to show and in what order, from new_game, continue and settings
+//   start = The field a new game starts in, optionally followed by a spawn point
+//   idle = Seconds without any input before the attract intro plays
+//   attract = The script to run for the attract intro
+
+use std::{fmt, str::FromStr};
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::menu::MenuSound;
+use crate::pointer::{PointerEvent, PointerResponse};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ui::{UiBatch, UiImageId, WHITE};
+
+const MENU_WIDTH: f32 = 240.0;
+const PADDING: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TitleOption {
+    NewGame,
+    Continue,
+    Settings
+}
+
+impl TitleOption {
+    pub const ALL: [TitleOption; 3] = [TitleOption::NewGame, TitleOption::Continue, TitleOption::Settings];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TitleOption::NewGame => "new_game",
+            TitleOption::Continue => "continue",
+            TitleOption::Settings => "settings"
+        }
+    }
+
+    // For showing in the menu.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TitleOption::NewGame => "New Game",
+            TitleOption::Continue => "Continue",
+            TitleOption::Settings => "Settings"
+        }
+    }
+}
+
+impl fmt::Display for TitleOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TitleOption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TitleOption::ALL.into_iter()
+            .find(|option| option.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown title option \"{}\", expected new_game, continue or settings", s.trim()))
+    }
+}
+
+// What's shown behind the menu.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TitleBackground {
+    #[default]
+    None,
+    Image(String),
+    Field(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TitleConfig {
+    pub title: String,
+    pub background: TitleBackground,
+    pub menu: Vec<TitleOption>,
+    pub start_field: String,
+    pub start_spawn: Option<String>,
+    pub idle_seconds: f32,
+    // None to never play an attract intro.
+    pub attract: Option<String>
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            background: TitleBackground::None,
+            menu: TitleOption::ALL.to_vec(),
+            start_field: String::new(),
+            start_spawn: None,
+            idle_seconds: 30.0,
+            attract: None
+        }
+    }
+}
+
+impl TitleConfig {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = TitleConfig::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: String| format!("Line {}: {}", number + 1, message);
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| error("expected \"key = value\"".to_string()))?;
+            match key {
+                "title" => config.title = value.to_string(),
+                "image" | "field" if config.background != TitleBackground::None => {
+                    return Err(error("there's already a background, only one of image or field can be given".to_string()));
+                },
+                "image" => config.background = TitleBackground::Image(value.to_string()),
+                "field" => config.background = TitleBackground::Field(value.to_string()),
+                "menu" => {
+                    config.menu = value.split(',').map(str::parse).collect::<Result<_, _>>().map_err(error)?;
+                    if config.menu.is_empty() {
+                        return Err(error("the menu needs at least one option".to_string()));
+                    }
+                },
+                "start" => {
+                    let mut words = value.split_whitespace();
+                    config.start_field = words.next().ok_or_else(|| error("expected a field to start in".to_string()))?.to_string();
+                    config.start_spawn = words.next().map(str::to_string);
+                },
+                "idle" => config.idle_seconds = value.parse().ok()
+                    .filter(|seconds: &f32| *seconds > 0.0)
+                    .ok_or_else(|| error(format!("bad idle time \"{}\"", value)))?,
+                "attract" => config.attract = Some(value.to_string()).filter(|script| !script.is_empty()),
+                _ => return Err(error(format!("unknown key \"{}\"", key)))
+            }
+        }
+        Ok(config)
+    }
+}
+
+// What the title screen wants done.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TitleAction {
+    NewGame,
+    Continue,
+    Settings,
+    // Nothing's been pressed for a while, so run the attract intro script.
+    Attract(String)
+}
+
+pub struct TitleScreen {
+    config: TitleConfig,
+    open: bool,
+    // Continue is only there to pick when there's a save to carry on from.
+    can_continue: bool,
+    selected: usize,
+    // Seconds since anything was pressed.
+    idle: f32,
+    sound: Option<MenuSound>
+}
+
+impl TitleScreen {
+    pub fn new(config: TitleConfig) -> Self {
+        Self {
+            config,
+            open: false,
+            can_continue: false,
+            selected: 0,
+            idle: 0.0,
+            sound: None
+        }
+    }
+
+    pub fn config(&self) -> &TitleConfig {
+        &self.config
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Show the title screen, starting on Continue if there's a save, otherwise on the first
+    // option that can be picked.
+    pub fn open(&mut self, can_continue: bool) {
+        self.open = true;
+        self.can_continue = can_continue;
+        self.idle = 0.0;
+        let menu = &self.config.menu;
+        self.selected = menu.iter().position(|option| can_continue && *option == TitleOption::Continue)
+            .or_else(|| (0..menu.len()).find(|index| self.is_enabled(*index)))
+            .unwrap_or_default();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn selected(&self) -> Option<TitleOption> {
+        self.config.menu.get(self.selected).copied().filter(|_| self.open)
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        match self.config.menu.get(index) {
+            Some(TitleOption::Continue) => self.can_continue,
+            Some(_) => true,
+            None => false
+        }
+    }
+
+    // The sound the last key or pointer event made, see menu.rs.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    // Start counting down to the attract intro again, after any input.
+    pub fn wake(&mut self) {
+        self.idle = 0.0;
+    }
+
+    // Count down to the attract intro, returning it when it's time.
+    pub fn update(&mut self, delta: std::time::Duration) -> Option<TitleAction> {
+        let script = self.config.attract.as_ref().filter(|_| self.open)?;
+        self.idle += delta.as_secs_f32();
+        if self.idle < self.config.idle_seconds {
+            return None;
+        }
+        self.idle = 0.0;
+        Some(TitleAction::Attract(script.clone()))
+    }
+
+    // Move the selection with up and down, skipping anything that can't be picked, and pick
+    // with enter.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<TitleAction> {
+        if !self.open {
+            return None;
+        }
+        self.wake();
+        match key {
+            VirtualKeyCode::Up => self.step(-1),
+            VirtualKeyCode::Down => self.step(1),
+            VirtualKeyCode::Return => return self.pick(),
+            _ => {}
+        }
+        None
+    }
+
+    // The same for the mouse and touch. There's nothing to go back to, so clicking outside
+    // the menu does nothing.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> PointerResponse<TitleAction> {
+        let before = (self.open, self.selected);
+        let action = self.pointer(event, accessibility);
+        PointerResponse::new(action, before != (self.open, self.selected))
+    }
+
+    fn pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> Option<TitleAction> {
+        if !self.open {
+            return None;
+        }
+        self.wake();
+        let layout = Layout::new(accessibility);
+        match event {
+            PointerEvent::Hover { x, y } => match layout.row_at(x, y, self.config.menu.len()) {
+                Some(index) if self.is_enabled(index) => self.select(index),
+                _ => {}
+            },
+            PointerEvent::Click { x, y } => match layout.row_at(x, y, self.config.menu.len()) {
+                Some(index) if self.is_enabled(index) => {
+                    self.selected = index;
+                    return self.pick();
+                },
+                _ => {}
+            },
+            PointerEvent::Scroll { steps } => self.step(steps.signum()),
+            PointerEvent::Cancel => {}
+        }
+        None
+    }
+
+    fn step(&mut self, direction: i32) {
+        let mut index = self.selected as i64;
+        loop {
+            index += direction as i64;
+            if index < 0 || index >= self.config.menu.len() as i64 {
+                return;
+            }
+            if self.is_enabled(index as usize) {
+                self.select(index as usize);
+                return;
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.sound = Some(MenuSound::Move);
+        }
+    }
+
+    fn pick(&mut self) -> Option<TitleAction> {
+        if !self.is_enabled(self.selected) {
+            self.sound = Some(MenuSound::Error);
+            return None;
+        }
+        let action = match self.config.menu.get(self.selected)? {
+            TitleOption::NewGame => TitleAction::NewGame,
+            TitleOption::Continue => TitleAction::Continue,
+            TitleOption::Settings => TitleAction::Settings
+        };
+        self.sound = Some(MenuSound::Confirm);
+        Some(action)
+    }
+
+    // Draw the menu, over the background image if there is one. A field in the background is
+    // already drawn by the renderer.
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility, background: Option<UiImageId>) {
+        if !self.open {
+            return;
+        }
+
+        let skin = accessibility.skin();
+        let layout = Layout::new(accessibility);
+        if let Some(image) = background {
+            batch.image(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, image, WHITE);
+        }
+
+        let title_scale = layout.scale * 2.0;
+        let (title_width, _) = UiBatch::measure_text(title_scale, &self.config.title);
+        batch.text((SCREEN_WIDTH as f32 - title_width) / 2.0, SCREEN_HEIGHT as f32 * 0.25, title_scale, &self.config.title, skin.highlight);
+
+        let menu_height = layout.row_height * self.config.menu.len() as f32;
+        batch.rect(layout.x - PADDING, layout.first_row_y - PADDING, MENU_WIDTH + PADDING * 2.0, menu_height + PADDING * 2.0, skin.window);
+        for (index, option) in self.config.menu.iter().enumerate() {
+            let y = layout.row_y(index);
+            if index == self.selected {
+                batch.rect(layout.x, y, MENU_WIDTH, layout.row_height, skin.selected);
+            }
+            let color = if self.is_enabled(index) { skin.text } else { skin.dim_text };
+            let (width, _) = UiBatch::measure_text(layout.scale, option.label());
+            batch.text(layout.x + (MENU_WIDTH - width) / 2.0, y + PADDING, layout.scale, option.label(), color);
+        }
+    }
+}
+
+// Where the menu goes, shared by drawing and hit testing. It sits in the middle of the screen,
+// just below halfway.
+struct Layout {
+    scale: f32,
+    x: f32,
+    first_row_y: f32,
+    row_height: f32
+}
+
+impl Layout {
+    fn new(accessibility: &Accessibility) -> Self {
+        let scale = 2.0 * accessibility.text_scale();
+        Self {
+            scale,
+            x: (SCREEN_WIDTH as f32 - MENU_WIDTH) / 2.0,
+            first_row_y: SCREEN_HEIGHT as f32 * 0.55,
+            row_height: UiBatch::line_height(scale) + PADDING * 2.0
+        }
+    }
+
+    fn row_y(&self, index: usize) -> f32 {
+        self.first_row_y + self.row_height * index as f32
+    }
+
+    fn row_at(&self, x: f32, y: f32, count: usize) -> Option<usize> {
+        if !(self.x..self.x + MENU_WIDTH).contains(&x) {
+            return None;
+        }
+        (0..count).find(|index| {
+            let top = self.row_y(*index);
+            (top..top + self.row_height).contains(&y)
+        })
+    }
+}
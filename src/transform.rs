@@ -0,0 +1,66 @@
+use cgmath::{Vector3, Quaternion, Matrix3, Matrix4, Euler, Deg, One, InnerSpace, SquareMatrix};
+
+// Where an entity is, which way it's facing and how big it is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+impl Transform {
+    pub fn from_position(position: Vector3<f32>) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    // Split a matrix back up into position, rotation and scale. Matrices with shear in them,
+    // which only come from scaling unevenly and then rotating, lose it.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let mut scale = Vector3::new(matrix.x.truncate().magnitude(), matrix.y.truncate().magnitude(), matrix.z.truncate().magnitude());
+        // Mirrored matrices flip one axis, which can't be a rotation.
+        let linear = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+        let unscale = |axis: Vector3<f32>, scale: f32| if scale == 0.0 { axis } else { axis / scale };
+        let rotation = Matrix3::from_cols(
+            unscale(linear.x, scale.x),
+            unscale(linear.y, scale.y),
+            unscale(linear.z, scale.z)
+        );
+        Self {
+            position: matrix.w.truncate(),
+            rotation: Quaternion::from(rotation).normalize(),
+            scale
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    // Rotation as euler angles in degrees. Easier to edit by hand than a quaternion.
+    pub fn euler_degrees(&self) -> Vector3<f32> {
+        let euler = Euler::from(self.rotation);
+        Vector3::new(Deg::from(euler.x).0, Deg::from(euler.y).0, Deg::from(euler.z).0)
+    }
+
+    pub fn set_euler_degrees(&mut self, degrees: Vector3<f32>) {
+        self.rotation = Quaternion::from(Euler::new(Deg(degrees.x), Deg(degrees.y), Deg(degrees.z)));
+    }
+}
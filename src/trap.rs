@@ -0,0 +1,163 @@
+// Traps in dungeon fields, set off by walking onto them: spikes that hurt the party,
+// teleporters that move the player somewhere else in the field, and pitfalls that drop them
+// into another field, like the floor below. Each is a FieldTrap in the field's descriptor.
+//
+// A trap goes off when the player steps onto it, and again only once they've stepped off and
+// back on. One with a flag is disarmed while the flag's set, like by a lever, or a skill's event
+// (see field_skill.rs). Spikes never knock anyone out, leaving them on 1 HP at worst, so a trap
+// alone can't end the game.
+//
+// There's nothing to see of a trap besides whatever prop the field puts there.
+
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::field::FieldEntity;
+use crate::flags::GameFlags;
+use crate::party::Party;
+use crate::screen_effects::{Easing, EffectCommand, EffectParam};
+use crate::transform::Transform;
+use crate::world::{Entity, Name, World};
+
+// How close the player has to be to set one off, in metres, and in pixels for 2D fields.
+pub const TRAP_RADIUS: f32 = 0.5;
+pub const TRAP_RADIUS_2D: f32 = 8.0;
+
+// How far spikes shake the screen, in virtual screen pixels, and for how long.
+const SPIKE_SHAKE: f32 = 4.0;
+const SPIKE_SHAKE_TIME: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrapKind {
+    // Takes this much HP from everyone who's standing.
+    Spikes { damage: u32 },
+    // Moves the player to here in the same field.
+    Teleport { position: Vector3<f32> },
+    // Drops the player into another field, at one of its spawns or its default.
+    Pitfall { field: String, spawn: Option<String> }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldTrap {
+    pub id: String,
+    pub kind: TrapKind,
+    pub position: Vector3<f32>,
+    // Disarms it while it's set. Empty for one that's always armed.
+    pub flag: String
+}
+
+// Component for a trap that's in the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub flag: String,
+    // Whether the player's stood on it, having set it off already.
+    pub sprung: bool
+}
+
+impl Trap {
+    pub fn is_armed(&self, flags: Option<&GameFlags>) -> bool {
+        self.flag.is_empty() || flags.is_none_or(|flags| flags.get(&self.flag) == 0)
+    }
+}
+
+// What a trap did, for whatever's running the field to show, and for pitfalls to warp to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrapEvent {
+    pub trap: Entity,
+    pub message: String,
+    // Only for spikes, so it can be felt.
+    pub hurt: bool,
+    // The field and spawn a pitfall drops the player into.
+    pub warp: Option<(String, Option<String>)>
+}
+
+// Spawn the field's traps. They belong to the field, so they go when the player leaves.
+pub fn spawn_traps(world: &mut World, traps: &[FieldTrap]) {
+    for trap in traps {
+        let entity = world.spawn();
+        world.insert(entity, Name(trap.id.clone()));
+        world.insert(entity, Transform::from_position(trap.position));
+        world.insert(entity, FieldEntity);
+        world.insert(entity, Trap { kind: trap.kind.clone(), flag: trap.flag.clone(), sprung: false });
+    }
+}
+
+// Set off any armed trap the player's just stepped onto within `radius`, and re-arm the ones
+// they've stepped off. Spikes and teleports happen here, pitfalls are left to the warp.
+pub fn update_traps(world: &mut World, player: Entity, radius: f32) -> Vec<TrapEvent> {
+    let position = match world.get::<Transform>(player) {
+        Some(transform) => transform.position,
+        None => return Vec::new()
+    };
+    let traps: Vec<Entity> = world.query::<Trap>().map(|(entity, _)| entity).collect();
+    let mut events = Vec::new();
+    for entity in traps {
+        let on = world.get::<Transform>(entity).is_some_and(|transform| (transform.position - position).magnitude() <= radius);
+        let armed = world.get::<Trap>(entity).is_some_and(|trap| trap.is_armed(world.resource::<GameFlags>()));
+        let trap = match world.get_mut::<Trap>(entity) {
+            Some(trap) => trap,
+            None => continue
+        };
+        let sprung = trap.sprung;
+        trap.sprung = on;
+        if !on || sprung || !armed {
+            continue;
+        }
+        let kind = trap.kind.clone();
+        events.push(spring(world, entity, player, &kind, radius));
+        // A teleport can only go off once a frame, or two could send the player back and forth.
+        if matches!(kind, TrapKind::Teleport { .. }) {
+            break;
+        }
+    }
+    events
+}
+
+fn spring(world: &mut World, entity: Entity, player: Entity, kind: &TrapKind, radius: f32) -> TrapEvent {
+    let mut event = TrapEvent { trap: entity, message: String::new(), hurt: false, warp: None };
+    match kind {
+        TrapKind::Spikes { damage } => {
+            let mut hurt = Vec::new();
+            for member in world.resource_mut::<Party>().map(|party| party.members.iter_mut()).into_iter().flatten() {
+                if !member.stats.is_knocked_out() {
+                    member.stats.hp = member.stats.hp.saturating_sub(*damage).max(1);
+                    hurt.push(member.name.clone());
+                }
+            }
+            event.message = format!("Spikes! {} took {} damage", hurt.join(" and "), damage);
+            event.hurt = true;
+        },
+        TrapKind::Teleport { position } => {
+            if let Some(transform) = world.get_mut::<Transform>(player) {
+                transform.position = *position;
+            }
+            // Landing on a trap doesn't set it off, so teleporters can be two way.
+            for (_, trap) in world.query_mut::<Trap>() {
+                trap.sprung = false;
+            }
+            let landed_on: Vec<Entity> = world.query::<Trap>()
+                .filter(|(other, _)| world.get::<Transform>(*other).is_some_and(|transform| (transform.position - position).magnitude() <= radius))
+                .map(|(other, _)| other)
+                .collect();
+            for other in landed_on {
+                if let Some(trap) = world.get_mut::<Trap>(other) {
+                    trap.sprung = true;
+                }
+            }
+            event.message = "The floor glows and the room shifts".to_string();
+        },
+        TrapKind::Pitfall { field, spawn } => {
+            event.message = "The floor gives way!".to_string();
+            event.warp = Some((field.clone(), spawn.clone()));
+        }
+    }
+    event
+}
+
+// A jolt for spikes. `strength` is how far, which is nothing with screen effects turned off.
+pub fn spike_shake(strength: f32) -> [EffectCommand; 2] {
+    let shake = EffectCommand { param: EffectParam::Shake, value: SPIKE_SHAKE * strength, duration: Duration::ZERO, easing: Easing::default(), color: None };
+    [shake, EffectCommand { value: 0.0, duration: SPIKE_SHAKE_TIME, easing: Easing::EaseOut, ..shake }]
+}
@@ -0,0 +1,208 @@
+// Animating bits of UI: sliding, growing, fading and tinting them, one step after another.
+// Whatever's animated keeps a Tween, moves it on each frame and draws through its pose with
+// UiBatch::set_pose, rather than working out its own offsets and fades. A tween can play once,
+// loop, or go back and forth, like a cursor pulsing.
+
+use std::time::Duration;
+
+use crate::screen_effects::Easing;
+use crate::ui::{Color, WHITE};
+
+// How long menus take to slide in and out.
+pub const SLIDE_TIME: Duration = Duration::from_millis(150);
+// How far they slide from, in pixels.
+pub const SLIDE_DISTANCE: f32 = 24.0;
+
+// Where something is, relative to where it's laid out, and how it's drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiPose {
+    // In pixels.
+    pub offset: [f32; 2],
+    // Around the origin given to UiBatch::set_pose.
+    pub scale: f32,
+    pub alpha: f32,
+    // Multiplies its colours.
+    pub tint: Color
+}
+
+impl Default for UiPose {
+    fn default() -> Self {
+        Self::REST
+    }
+}
+
+impl UiPose {
+    // Just as it's laid out.
+    pub const REST: UiPose = UiPose { offset: [0.0, 0.0], scale: 1.0, alpha: 1.0, tint: WHITE };
+
+    pub fn at(x: f32, y: f32) -> Self {
+        Self { offset: [x, y], ..Self::REST }
+    }
+
+    pub fn lerp(&self, to: &UiPose, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            offset: [lerp(self.offset[0], to.offset[0]), lerp(self.offset[1], to.offset[1])],
+            scale: lerp(self.scale, to.scale),
+            alpha: lerp(self.alpha, to.alpha),
+            tint: [0, 1, 2, 3].map(|channel| lerp(self.tint[channel], to.tint[channel]))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TweenStep {
+    pub to: UiPose,
+    pub duration: Duration,
+    pub easing: Easing
+}
+
+// What a tween does once it gets to the end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    // Stays there.
+    #[default]
+    Once,
+    // Starts again from the beginning.
+    Loop,
+    // Plays backwards to the beginning, then forwards again.
+    PingPong
+}
+
+// Steps from a starting pose, built up like Tween::new(start).then(...).then(...).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tween {
+    from: UiPose,
+    steps: Vec<TweenStep>,
+    repeat: Repeat,
+    elapsed: Duration
+}
+
+impl Tween {
+    // Staying at `from` until steps are added.
+    pub fn new(from: UiPose) -> Self {
+        Self { from, steps: Vec::new(), repeat: Repeat::Once, elapsed: Duration::ZERO }
+    }
+
+    // Go on to `to` once the steps before have finished.
+    pub fn then(mut self, to: UiPose, duration: Duration, easing: Easing) -> Self {
+        self.steps.push(TweenStep { to, duration, easing });
+        self
+    }
+
+    // Stay where the last step left it for a while.
+    pub fn hold(self, duration: Duration) -> Self {
+        let to = self.end();
+        self.then(to, duration, Easing::Linear)
+    }
+
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    // Once through all the steps.
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    // Where the last step ends up.
+    pub fn end(&self) -> UiPose {
+        self.steps.last().map_or(self.from, |step| step.to)
+    }
+
+    // Played through, for tweens that only play once. Ones that repeat never finish.
+    pub fn is_finished(&self) -> bool {
+        self.repeat == Repeat::Once && self.elapsed >= self.duration()
+    }
+
+    // Move it on. Returns whether it's still moving.
+    pub fn update(&mut self, delta: Duration) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.elapsed += delta;
+        // Repeating ones only need to know where they are in the cycle.
+        let cycle = match self.repeat {
+            Repeat::Once => return true,
+            Repeat::Loop => self.duration(),
+            Repeat::PingPong => self.duration() * 2
+        };
+        if cycle.is_zero() {
+            return false;
+        }
+        self.elapsed = Duration::from_nanos((self.elapsed.as_nanos() % cycle.as_nanos()) as u64);
+        true
+    }
+
+    pub fn pose(&self) -> UiPose {
+        let duration = self.duration();
+        let time = match self.repeat {
+            Repeat::PingPong if self.elapsed > duration => duration.saturating_sub(self.elapsed - duration),
+            _ => self.elapsed.min(duration)
+        };
+
+        let mut from = self.from;
+        let mut start = Duration::ZERO;
+        for step in &self.steps {
+            if time < start + step.duration {
+                let t = (time - start).as_secs_f32() / step.duration.as_secs_f32();
+                return from.lerp(&step.to, step.easing.apply(t));
+            }
+            from = step.to;
+            start += step.duration;
+        }
+        from
+    }
+}
+
+// A menu sliding and fading in as it opens, and back out as it closes, so it can still be
+// drawn for a moment after it's closed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuSlide {
+    tween: Tween,
+    open: bool
+}
+
+impl Default for MenuSlide {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuSlide {
+    // Where menus slide in from and out to.
+    pub fn hidden() -> UiPose {
+        UiPose { alpha: 0.0, ..UiPose::at(-SLIDE_DISTANCE, 0.0) }
+    }
+
+    pub fn new() -> Self {
+        Self { tween: Tween::new(Self::hidden()), open: false }
+    }
+
+    // Slide in from wherever it's got to, so opening it again as it closes doesn't jump.
+    pub fn open(&mut self) {
+        self.tween = Tween::new(self.pose()).then(UiPose::REST, SLIDE_TIME, Easing::EaseOut);
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        if self.open {
+            self.tween = Tween::new(self.pose()).then(Self::hidden(), SLIDE_TIME, Easing::EaseIn);
+            self.open = false;
+        }
+    }
+
+    // Open, or still sliding out.
+    pub fn is_showing(&self) -> bool {
+        self.open || !self.tween.is_finished()
+    }
+
+    pub fn update(&mut self, delta: Duration) -> bool {
+        self.tween.update(delta)
+    }
+
+    pub fn pose(&self) -> UiPose {
+        self.tween.pose()
+    }
+}
@@ -0,0 +1,607 @@
+use std::{collections::HashMap, num::NonZeroU64, sync::Arc};
+
+use wgpu::{Device, Queue, Texture, TextureView, Sampler, BindGroup, Buffer, TextureFormat, util::{DeviceExt, StagingBelt}};
+
+use crate::bidi::{self, TextDirection};
+use crate::font::{self, FontAtlas, Glyph};
+use crate::logging::targets;
+use crate::pipeline_cache::{PipelineCache, PipelineId, BindGroupLayoutId, PipelineKey, texture_entry, sampler_entry};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::tween::UiPose;
+
+pub type Color = [f32; 4];
+
+pub const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+
+// Make a little pixel art image, like the cursor or an emote, from rows of characters. Each
+// character is looked up in `palette` for its colour, and anything not in it is see through.
+pub fn pixel_sprite(rows: &[&str], palette: &[(char, Color)]) -> image::RgbaImage {
+    let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or_default();
+    let mut image = image::RgbaImage::new(width as u32, rows.len() as u32);
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if let Some((_, color)) = palette.iter().find(|(known, _)| *known == c) {
+                image.put_pixel(x as u32, y as u32, image::Rgba(color.map(|channel| (channel * 255.0).round() as u8)));
+            }
+        }
+    }
+    image
+}
+
+// The colours menus and message windows are drawn with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindowSkin {
+    // Darkens the game behind a full screen menu.
+    pub backdrop: Color,
+    pub window: Color,
+    // The window, or part of it, that's picked.
+    pub selected: Color,
+    pub text: Color,
+    // Less important text, like timestamps.
+    pub dim_text: Color,
+    // Titles and things that should catch the eye.
+    pub highlight: Color
+}
+
+impl WindowSkin {
+    pub const DEFAULT: WindowSkin = WindowSkin {
+        backdrop: [0.0, 0.0, 0.0, 0.6],
+        window: [0.05, 0.05, 0.15, 0.9],
+        selected: [0.2, 0.2, 0.45, 0.9],
+        text: WHITE,
+        dim_text: [0.6, 0.6, 0.6, 1.0],
+        highlight: [1.0, 0.85, 0.3, 1.0]
+    };
+
+    // Nothing shows through the windows, and all the text is bright.
+    pub const HIGH_CONTRAST: WindowSkin = WindowSkin {
+        backdrop: [0.0, 0.0, 0.0, 0.9],
+        window: [0.0, 0.0, 0.0, 1.0],
+        selected: [0.0, 0.15, 0.6, 1.0],
+        text: WHITE,
+        dim_text: [0.9, 0.9, 0.9, 1.0],
+        highlight: [1.0, 1.0, 0.0, 1.0]
+    };
+}
+
+// How big each of the staging belt's buffers is. A full screen of text fits in one.
+const STAGING_CHUNK_SIZE: wgpu::BufferAddress = 256 * 1024;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct UiVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+impl UiVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<UiVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
+
+// An image that's been uploaded to the UI renderer, e.g. a save thumbnail.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UiImageId(u32);
+
+// A list of quads to draw on top of everything else this frame.
+// Positions are in pixels of the virtual screen, with the origin at the top left.
+pub struct UiBatch {
+    vertices: Vec<UiVertex>,
+
+    // Which texture to use from each vertex onwards. None is the font.
+    draws: Vec<(Option<UiImageId>, usize)>,
+
+    // Where to find each glyph. Has to match the atlas the UiRenderer was given.
+    font: Arc<FontAtlas>,
+    // Which way lines of text go, for the player's language.
+    direction: TextDirection,
+    // Whether what's drawn now is flipped left to right, see set_mirrored.
+    mirrored: bool,
+    // How what's drawn now is moved, scaled and faded, and the point it's scaled around, see
+    // set_pose.
+    pose: Option<(UiPose, [f32; 2])>
+}
+
+impl Default for UiBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UiBatch {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            draws: Vec::new(),
+            font: FontAtlas::builtin(),
+            direction: TextDirection::LeftToRight,
+            mirrored: false,
+            pose: None
+        }
+    }
+
+    pub fn set_direction(&mut self, direction: TextDirection) {
+        self.direction = direction;
+    }
+
+    // Flip where everything drawn from now on goes, so a menu laid out left to right reads from
+    // the right. Text is lined up on the right instead of the left, but isn't flipped itself,
+    // and nor are images.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+    }
+
+    pub fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    // Where something `width` wide at `x` goes, flipped if need be.
+    fn mirror_x(&self, x: f32, width: f32) -> f32 {
+        if self.mirrored { SCREEN_WIDTH as f32 - x - width } else { x }
+    }
+
+    // Draw everything from now on in a tween's pose, scaled around `origin`, until clear_pose.
+    // Mirrored menus slide the other way.
+    pub fn set_pose(&mut self, pose: UiPose, origin: [f32; 2]) {
+        self.pose = Some((pose, origin));
+    }
+
+    pub fn clear_pose(&mut self) {
+        self.pose = None;
+    }
+
+    // Draw text from a different atlas, e.g. one with fallback fonts, after giving it to the
+    // renderer with Renderer::set_font.
+    pub fn set_font(&mut self, font: Arc<FontAtlas>) {
+        self.font = font;
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.draws.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    // Draw a solid rectangle.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.use_texture(None);
+        let (u, v) = self.glyph_uv(self.font.solid());
+        let half_cell = (0.5 / self.font.width() as f32, 0.5 / self.font.height() as f32);
+        let uv = [u + half_cell.0, v + half_cell.1];
+        self.quad(self.mirror_x(x, width), y, width, height, uv, uv, color);
+    }
+
+    // Draw a line of text. Each font pixel is drawn as a scale x scale block, and wide
+    // characters take up two cells. Right to left text is put the right way round, see bidi.rs.
+    // '\n' starts a new line. Returns the width of the widest line in pixels.
+    pub fn text(&mut self, x: f32, y: f32, scale: f32, text: &str, color: Color) -> f32 {
+        let cell_width = font::CELL_WIDTH as f32 * scale;
+        let cell_height = font::CELL_HEIGHT as f32 * scale;
+
+        let (uv_width, uv_height) = (
+            font::CELL_WIDTH as f32 / self.font.width() as f32,
+            font::CELL_HEIGHT as f32 / self.font.height() as f32
+        );
+
+        self.use_texture(None);
+        let mut widest = 0.0f32;
+        for (row, line) in bidi::shape(text).split('\n').enumerate() {
+            let line = bidi::visual_order(line, self.direction);
+            let line_width = Self::columns(&line) as f32 * cell_width;
+            let mut cursor_x = self.mirror_x(x, line_width);
+            let cursor_y = y + row as f32 * Self::line_height(scale);
+            for c in line.chars() {
+                let columns = font::char_columns(c);
+                if c != ' ' {
+                    let glyph = self.font.glyph(c);
+                    let (u, v) = self.glyph_uv(glyph);
+                    let offset = columns.saturating_sub(glyph.columns) as f32 * cell_width / 2.0;
+                    let (width, uv_glyph_width) = (glyph.columns as f32 * cell_width, glyph.columns as f32 * uv_width);
+                    self.quad(cursor_x + offset, cursor_y, width, cell_height, [u, v], [u + uv_glyph_width, v + uv_height], color);
+                }
+                cursor_x += columns as f32 * cell_width;
+            }
+            widest = widest.max(line_width);
+        }
+
+        widest
+    }
+
+    fn columns(line: &str) -> u32 {
+        line.chars().map(font::char_columns).sum()
+    }
+
+    // Draw a small image with each pixel as a solid `scale` x `scale` block, so it doesn't
+    // need uploading first. Fine for sprites a few pixels across, but not for anything big.
+    pub fn sprite(&mut self, x: f32, y: f32, scale: f32, image: &image::RgbaImage, alpha: f32) {
+        // Moved when mirrored, but not flipped.
+        let x = self.mirror_x(x, image.width() as f32 * scale);
+        let mirrored = std::mem::replace(&mut self.mirrored, false);
+        for (px, py, pixel) in image.enumerate_pixels() {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let mut color = pixel.0.map(|channel| channel as f32 / 255.0);
+            color[3] *= alpha;
+            self.rect(x + px as f32 * scale, y + py as f32 * scale, scale, scale, color);
+        }
+        self.mirrored = mirrored;
+    }
+
+    // Draw an image stretched over a rectangle, multiplied by a colour.
+    pub fn image(&mut self, x: f32, y: f32, width: f32, height: f32, image: UiImageId, color: Color) {
+        self.use_texture(Some(image));
+        self.quad(self.mirror_x(x, width), y, width, height, [0.0, 0.0], [1.0, 1.0], color);
+    }
+
+    // Start a new draw if the texture is changing.
+    fn use_texture(&mut self, texture: Option<UiImageId>) {
+        if self.draws.last().map(|(current, _)| *current) != Some(texture) {
+            self.draws.push((texture, self.vertices.len()));
+        }
+    }
+
+    // Distance between the tops of two lines of text.
+    pub fn line_height(scale: f32) -> f32 {
+        (font::CELL_HEIGHT as f32 + 1.0) * scale
+    }
+
+    // Size in pixels that some text would take up if drawn with text().
+    pub fn measure_text(scale: f32, text: &str) -> (f32, f32) {
+        let text = bidi::shape(text);
+        let lines = text.split('\n');
+        let line_count = lines.clone().count() as f32;
+        let longest = lines.map(Self::columns).max().unwrap_or(0) as f32;
+        (
+            longest * font::CELL_WIDTH as f32 * scale,
+            line_count * Self::line_height(scale) - scale
+        )
+    }
+
+    fn glyph_uv(&self, glyph: Glyph) -> (f32, f32) {
+        (glyph.x as f32 / self.font.width() as f32, glyph.y as f32 / self.font.height() as f32)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn quad(&mut self, x: f32, y: f32, width: f32, height: f32, uv_min: [f32; 2], uv_max: [f32; 2], color: Color) {
+        let (x, y, width, height, color) = match self.pose {
+            Some((pose, origin)) => {
+                let (origin_x, offset_x) = if self.mirrored {
+                    (SCREEN_WIDTH as f32 - origin[0], -pose.offset[0])
+                } else {
+                    (origin[0], pose.offset[0])
+                };
+                let mut color = [0, 1, 2, 3].map(|channel| color[channel] * pose.tint[channel]);
+                color[3] *= pose.alpha;
+                (
+                    origin_x + (x - origin_x) * pose.scale + offset_x,
+                    origin[1] + (y - origin[1]) * pose.scale + pose.offset[1],
+                    width * pose.scale,
+                    height * pose.scale,
+                    color
+                )
+            },
+            None => (x, y, width, height, color)
+        };
+
+        // Convert from screen pixels to clip space.
+        let to_clip = |px: f32, py: f32| [
+            px / SCREEN_WIDTH as f32 * 2.0 - 1.0,
+            1.0 - py / SCREEN_HEIGHT as f32 * 2.0
+        ];
+
+        let top_left = UiVertex { position: to_clip(x, y), uv: uv_min, color };
+        let top_right = UiVertex { position: to_clip(x + width, y), uv: [uv_max[0], uv_min[1]], color };
+        let bottom_left = UiVertex { position: to_clip(x, y + height), uv: [uv_min[0], uv_max[1]], color };
+        let bottom_right = UiVertex { position: to_clip(x + width, y + height), uv: uv_max, color };
+
+        self.vertices.extend_from_slice(&[
+            bottom_left, bottom_right, top_right,
+            top_right, top_left, bottom_left
+        ]);
+    }
+}
+
+struct UiImage {
+    texture: Texture,
+    bind_group: BindGroup,
+    width: u32,
+    height: u32
+}
+
+// Draws a UiBatch over the top of a texture.
+pub struct UiRenderer {
+    render_pipeline: PipelineId,
+    bind_group_layout: BindGroupLayoutId,
+    bind_group: BindGroup,
+
+    images: HashMap<UiImageId, UiImage>,
+    next_image: u32,
+    image_sampler: Sampler,
+
+    vertex_buffer: Buffer,
+    vertex_capacity: usize,
+    staging_belt: StagingBelt,
+
+    _font_texture: Texture,
+    font_sampler: Sampler,
+    font_size: (u32, u32)
+}
+
+impl UiRenderer {
+    pub fn new(device: &Device, queue: &Queue, pipelines: &mut PipelineCache, output_format: TextureFormat) -> Self {
+        let shader = pipelines.load_shader(device, wgpu::include_wgsl!("ui.wgsl"));
+
+        // The font is pixel art, so don't filter it.
+        let font_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = pipelines.create_bind_group_layout(device, "UI Renderer Bind Group Layout", &[
+            texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+            sampler_entry(1, wgpu::ShaderStages::FRAGMENT)
+        ]);
+
+        // Start off with the built in font, until there's an atlas with fallbacks.
+        let atlas = FontAtlas::builtin();
+        let (font_texture, bind_group) = Self::create_font(device, queue, pipelines, bind_group_layout, &font_sampler, &atlas);
+
+        let render_pipeline = pipelines.create_render_pipeline(device, "UI Render Pipeline", PipelineKey::new(
+            shader,
+            &[bind_group_layout],
+            vec![UiVertex::desc().into()],
+            wgpu::ColorTargetState {
+                format: output_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            }
+        ));
+
+        let vertex_capacity = 1024;
+        let vertex_buffer = Self::create_vertex_buffer(device, vertex_capacity);
+
+        // Images are usually drawn smaller than they are, so filter them.
+        let image_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            render_pipeline,
+            bind_group_layout,
+            bind_group,
+            images: HashMap::new(),
+            next_image: 0,
+            image_sampler,
+            vertex_buffer,
+            vertex_capacity,
+            staging_belt: StagingBelt::new(STAGING_CHUNK_SIZE),
+            _font_texture: font_texture,
+            font_sampler,
+            font_size: (atlas.width(), atlas.height())
+        }
+    }
+
+    // Draw text with a different atlas. Batches have to be given the same one with
+    // UiBatch::set_font.
+    pub fn set_font(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, atlas: &FontAtlas) {
+        let (font_texture, bind_group) = Self::create_font(device, queue, pipelines, self.bind_group_layout, &self.font_sampler, atlas);
+        self._font_texture = font_texture;
+        self.bind_group = bind_group;
+        self.font_size = (atlas.width(), atlas.height());
+    }
+
+    fn create_font(device: &Device, queue: &Queue, pipelines: &PipelineCache, layout: BindGroupLayoutId, sampler: &Sampler, atlas: &FontAtlas) -> (Texture, BindGroup) {
+        let font_texture = device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+            label: Some("UI Font Texture"),
+            size: wgpu::Extent3d {
+                width: atlas.width(),
+                height: atlas.height(),
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        }, atlas.pixels());
+        let font_view = font_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // The font only changes with the language, so the bind group's made along with it.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Renderer Bind Group"),
+            layout: pipelines.get_bind_group_layout(layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&font_view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler)
+                }
+            ]
+        });
+        (font_texture, bind_group)
+    }
+
+    // Bytes of texture memory owned by the UI renderer.
+    pub fn texture_bytes(&self) -> u64 {
+        self.font_size.0 as u64 * self.font_size.1 as u64 * 4
+            + self.images.values().map(|image| image.width as u64 * image.height as u64 * 4).sum::<u64>()
+    }
+
+    // Upload an image so it can be drawn with UiBatch::image().
+    pub fn create_image(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, image: &image::RgbaImage) -> UiImageId {
+        let (width, height) = image.dimensions();
+        let texture = device.create_texture_with_data(queue, &wgpu::TextureDescriptor {
+            label: Some("UI Image Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST
+        }, image.as_raw());
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Image Bind Group"),
+            layout: pipelines.get_bind_group_layout(self.bind_group_layout),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.image_sampler)
+                }
+            ]
+        });
+
+        let id = UiImageId(self.next_image);
+        self.next_image += 1;
+        self.images.insert(id, UiImage {
+            texture,
+            bind_group,
+            width,
+            height
+        });
+        id
+    }
+
+    // Replace an image's pixels, e.g. for the next frame of a movie. The new image has to be
+    // the same size as the old one.
+    pub fn update_image(&mut self, queue: &Queue, id: UiImageId, image: &image::RgbaImage) {
+        let ui_image = match self.images.get(&id) {
+            Some(ui_image) => ui_image,
+            None => return
+        };
+        if image.dimensions() != (ui_image.width, ui_image.height) {
+            tracing::warn!(target: targets::RENDERER, "UI image is {}x{}, can't update it with a {}x{} image", ui_image.width, ui_image.height, image.width(), image.height());
+            return;
+        }
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &ui_image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * ui_image.width),
+                rows_per_image: std::num::NonZeroU32::new(ui_image.height)
+            },
+            wgpu::Extent3d {
+                width: ui_image.width,
+                height: ui_image.height,
+                depth_or_array_layers: 1
+            }
+        );
+    }
+
+    pub fn remove_image(&mut self, id: UiImageId) {
+        self.images.remove(&id);
+    }
+
+    fn create_vertex_buffer(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("UI Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<UiVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        })
+    }
+
+    // Draw the batch over whatever is already in dest_view. Returns the number of draw calls made.
+    pub fn render(&mut self, device: &Device, queue: &Queue, pipelines: &PipelineCache, dest_view: &TextureView, batch: &UiBatch) -> u32 {
+        if batch.is_empty() {
+            return 0;
+        }
+
+        // Grow the vertex buffer if this batch won't fit.
+        if batch.vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = batch.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.vertex_capacity);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("UI Renderer Encoder.")
+        });
+
+        // Upload through the staging belt so the same staging buffers get used every frame.
+        let vertex_bytes: &[u8] = bytemuck::cast_slice(&batch.vertices);
+        let size = NonZeroU64::new(vertex_bytes.len() as u64).unwrap();
+        self.staging_belt.write_buffer(&mut encoder, &self.vertex_buffer, 0, size, device)
+            .copy_from_slice(vertex_bytes);
+        self.staging_belt.finish();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("UI Renderer Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: dest_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: None
+            });
+
+            render_pass.set_pipeline(pipelines.get_render_pipeline(self.render_pipeline));
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+            for (i, (texture, start)) in batch.draws.iter().enumerate() {
+                let end = batch.draws.get(i + 1).map_or(batch.vertices.len(), |(_, next)| *next);
+                let bind_group = match texture {
+                    None => &self.bind_group,
+                    Some(id) => match self.images.get(id) {
+                        Some(image) => &image.bind_group,
+                        // Removed since the batch was built.
+                        None => continue
+                    }
+                };
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(*start as u32..end as u32, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+        self.staging_belt.recall();
+
+        batch.draws.len() as u32
+    }
+}
@@ -0,0 +1,437 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+
+use crate::assets::AssetManifest;
+use crate::auto_battle::GambitAction;
+use crate::battle_scene::{BattleScenes, DEFAULT_TERRAIN};
+use crate::battle_script::{BattleAction, BattleScript, BattleTrigger};
+use crate::combo::ComboTech;
+use crate::pools::{PoolDefinition, SkillCost};
+use crate::summon::SummonDefinition;
+use crate::trap::TrapKind;
+use crate::field::{FieldMap, WALKMESH_NAME};
+use crate::formation::{EncounterTable, Formation};
+use crate::inventory::Item;
+use crate::loot::EnemyLoot;
+use crate::model::MAX_MORPH_TARGETS;
+use crate::party::Party;
+
+// Models bigger or smaller than this, in metres, were probably exported at the wrong scale.
+const MODEL_SIZE_RANGE: (f32, f32) = (0.05, 50.0);
+// The same for fields, which are whole rooms.
+const FIELD_SIZE_RANGE: (f32, f32) = (0.5, 500.0);
+
+// What a glTF file is meant to be, which decides what it needs to have in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GltfKind {
+    // A character or prop.
+    Model,
+    // A field, with the cameras its backgrounds were rendered from and a walk mesh.
+    Field
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // Loads, but probably not how it was meant to.
+    Warning,
+    // Won't load or won't work.
+    Error
+}
+
+// Something wrong with a glTF file or the game's data, worded for whoever made it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message)
+        }
+    }
+}
+
+// Everything found wrong with a file, worst first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    fn error(&mut self, message: String) {
+        self.issues.push(ValidationIssue { severity: Severity::Error, message });
+    }
+
+    fn warning(&mut self, message: String) {
+        self.issues.push(ValidationIssue { severity: Severity::Warning, message });
+    }
+}
+
+// Check a .gltf or .glb file follows the engine's conventions, so problems are found when
+// it's exported instead of when the game loads it. Only the file itself is looked at, not
+// any buffers or images it points to.
+pub fn validate_gltf(bytes: &[u8], kind: GltfKind) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let gltf = match gltf::Gltf::from_slice(bytes) {
+        Ok(gltf) => gltf,
+        Err(e) => {
+            report.error(format!("not a valid glTF file: {}", e));
+            return report;
+        }
+    };
+
+    for buffer in gltf.buffers() {
+        if matches!(buffer.source(), gltf::buffer::Source::Bin) && gltf.blob.is_none() {
+            report.error(format!("buffer {} is meant to be in the binary chunk, but there isn't one. Export as .glb, or as .gltf with separate or embedded buffers", buffer.index()));
+        }
+    }
+
+    let scene = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(scene) => scene,
+        None => {
+            report.error("there are no scenes, so there's nothing to load".to_string());
+            return report;
+        }
+    };
+
+    let mut cameras = Vec::new();
+    let mut walkmeshes = 0;
+    let mut misnamed_walkmeshes = Vec::new();
+    let mut bounds: Option<([f32; 3], [f32; 3])> = None;
+    // Meshes used by more than one node are only complained about once.
+    let mut checked_meshes = HashSet::new();
+
+    let mut nodes: Vec<(gltf::Node, Matrix4<f32>)> = scene.nodes().map(|node| (node, Matrix4::identity())).collect();
+    while let Some((node, parent_transform)) = nodes.pop() {
+        let transform = parent_transform * Matrix4::from(node.transform().matrix());
+        nodes.extend(node.children().map(|child| (child, transform)));
+        let node_name = node.name().map(str::to_string).unwrap_or_else(|| format!("node {}", node.index()));
+
+        if transform.determinant().abs() <= f32::EPSILON {
+            report.warning(format!("{} is scaled to nothing, so it won't be seen. Apply its scale before exporting", node_name));
+        }
+
+        if let Some(camera) = node.camera() {
+            if let gltf::camera::Projection::Orthographic(_) = camera.projection() {
+                report.error(format!("camera {} is orthographic, field cameras need to be perspective", node_name));
+            }
+            cameras.push((node_name.clone(), node.name().is_some()));
+        }
+
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => continue
+        };
+        let mesh_name = mesh.name().map(str::to_string).unwrap_or_else(|| format!("mesh {}", mesh.index()));
+
+        for name in [node.name(), mesh.name()].into_iter().flatten() {
+            if name == WALKMESH_NAME {
+                walkmeshes += 1;
+                break;
+            }
+            let simplified: String = name.chars().filter(char::is_ascii_alphanumeric).collect();
+            if simplified.eq_ignore_ascii_case(WALKMESH_NAME) && !misnamed_walkmeshes.iter().any(|misnamed| misnamed == name) {
+                misnamed_walkmeshes.push(name.to_string());
+            }
+        }
+
+        let first_use = checked_meshes.insert(mesh.index());
+
+        if let Some((_, level)) = mesh_name.to_ascii_uppercase().rsplit_once("_LOD") {
+            if first_use && mesh_name.rsplit_once("_LOD").and_then(|(_, level)| level.parse::<usize>().ok()).is_none() {
+                let base = &mesh_name[..mesh_name.len() - level.len() - 4];
+                report.warning(format!("{} looks like a detail level, but detail levels are named with _LOD and a number on the end, like {}_LOD1. It'll be drawn at every level", mesh_name, base));
+            }
+        }
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                if first_use {
+                    report.error(format!("{} has a part drawn as {:?} instead of triangles, it won't be drawn. Triangulate it before exporting", mesh_name, primitive.mode()));
+                }
+                continue;
+            }
+            if primitive.get(&gltf::Semantic::Positions).is_none() {
+                if first_use {
+                    report.error(format!("{} has a part without any vertex positions", mesh_name));
+                }
+                continue;
+            }
+            let targets = primitive.morph_targets().count();
+            if first_use && targets > MAX_MORPH_TARGETS {
+                report.warning(format!("{} has {} morph targets, only the first {} will work", mesh_name, targets, MAX_MORPH_TARGETS));
+            }
+
+            let bounding_box = primitive.bounding_box();
+            for corner in 0..8 {
+                let local = [
+                    if corner & 1 == 0 { bounding_box.min[0] } else { bounding_box.max[0] },
+                    if corner & 2 == 0 { bounding_box.min[1] } else { bounding_box.max[1] },
+                    if corner & 4 == 0 { bounding_box.min[2] } else { bounding_box.max[2] }
+                ];
+                let point = transform * Vector4::new(local[0], local[1], local[2], 1.0);
+                let point = [point.x, point.y, point.z];
+                bounds = Some(match bounds {
+                    Some((min, max)) => ([0, 1, 2].map(|i| min[i].min(point[i])), [0, 1, 2].map(|i| max[i].max(point[i]))),
+                    None => (point, point)
+                });
+            }
+        }
+    }
+
+    if let Some((min, max)) = bounds {
+        let size = (0..3).map(|i| max[i] - min[i]).fold(0.0, f32::max);
+        let (smallest, largest) = match kind {
+            GltfKind::Model => MODEL_SIZE_RANGE,
+            GltfKind::Field => FIELD_SIZE_RANGE
+        };
+        if size > largest {
+            report.warning(format!("it's {:.1} units across, which is very big. The engine works in metres, check it wasn't exported in centimetres", size));
+        } else if size < smallest {
+            report.warning(format!("it's {:.3} units across, which is very small. The engine works in metres, check the export scale", size));
+        }
+    } else if kind == GltfKind::Model {
+        report.warning("there aren't any meshes in the scene, so nothing will be drawn".to_string());
+    }
+
+    if kind == GltfKind::Field {
+        // Fields can have more than one camera to cut between, which scripts pick by name.
+        if cameras.is_empty() {
+            report.error("there's no camera. Fields need the camera the background was rendered from".to_string());
+        } else if cameras.len() > 1 {
+            let mut seen = HashSet::new();
+            for (name, named) in &cameras {
+                if !named {
+                    report.error(format!("camera {} doesn't have a name. Fields with more than one camera need them named so they can be cut between", name));
+                } else if !seen.insert(name) {
+                    report.error(format!("there's more than one camera called {}, each needs its own name", name));
+                }
+            }
+        }
+        match (walkmeshes, misnamed_walkmeshes.first()) {
+            (0, Some(misnamed)) => report.error(format!("there's no walk mesh. If {} is meant to be it, rename it to {}", misnamed, WALKMESH_NAME)),
+            (0, None) => report.error(format!("there's no walk mesh. Fields need a mesh called {} for where characters can go", WALKMESH_NAME)),
+            (1, _) => {},
+            (count, _) => report.error(format!("there are {} meshes called {}, fields need exactly one", count, WALKMESH_NAME))
+        }
+    }
+
+    report.issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+// The game's data, as read from its files, for checking they all agree with each other.
+pub struct GameData<'a> {
+    pub items: &'a [Item],
+    pub loot: &'a [EnemyLoot],
+    pub formations: &'a [Formation],
+    pub encounter_tables: &'a [EncounterTable],
+    pub battle_scripts: &'a [BattleScript],
+    pub battle_scenes: &'a BattleScenes,
+    pub combos: &'a [ComboTech],
+    pub summons: &'a [SummonDefinition],
+    pub pools: &'a [PoolDefinition],
+    pub fields: &'a FieldMap,
+    pub party: &'a Party,
+    pub manifest: &'a AssetManifest
+}
+
+// Check everything the data files point at exists, so a typo is found when the game starts
+// rather than when a battle gets to it. Every problem is reported, not just the first.
+// Skills and enemies don't have files of their own yet, so there's nothing to check their
+// animations and models against.
+pub fn validate_data(data: &GameData) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let is_item = |id: &str| data.items.iter().any(|item| item.id == id);
+    let is_enemy = |id: &str| data.formations.iter().flat_map(|formation| &formation.enemies).any(|enemy| enemy.enemy == id);
+
+    let mut seen = HashSet::new();
+    for item in data.items {
+        if !seen.insert(&item.id) {
+            report.error(format!("data/items.cfg: there's more than one {}", item.id));
+        }
+    }
+
+    for table in data.loot {
+        if !is_enemy(&table.enemy) {
+            report.warning(format!("data/loot.cfg: {} isn't in any formation, so its loot can't be won", table.enemy));
+        }
+        for chance in [&table.drop, &table.rare_drop, &table.steal, &table.rare_steal].into_iter().flatten() {
+            if !is_item(&chance.item) {
+                report.error(format!("data/loot.cfg: {} has {}, which isn't an item", table.enemy, chance.item));
+            }
+        }
+    }
+
+    for table in data.encounter_tables {
+        for (formation, _) in &table.formations {
+            if !data.formations.iter().any(|known| known.id == *formation) {
+                report.error(format!("data/encounters.cfg: {} has {}, which isn't a formation", table.id, formation));
+            }
+        }
+    }
+
+    for script in data.battle_scripts {
+        let formation = match data.formations.iter().find(|formation| formation.id == script.formation) {
+            Some(formation) => formation,
+            None => {
+                report.error(format!("data/battle_scripts.cfg: {} isn't a formation", script.formation));
+                continue;
+            }
+        };
+        // Enemies the script brings in count as being in the battle too.
+        let spawned: Vec<&str> = script.rules.iter()
+            .flat_map(|rule| &rule.actions)
+            .filter_map(|action| match action {
+                BattleAction::Spawn(enemy) => Some(enemy.enemy.as_str()),
+                _ => None
+            })
+            .collect();
+        let in_battle = |enemy: &str| formation.enemies.iter().any(|known| known.enemy == enemy) || spawned.contains(&enemy);
+        for rule in &script.rules {
+            let trigger_enemy = match &rule.trigger {
+                BattleTrigger::Hp { enemy, .. } => Some(enemy),
+                BattleTrigger::Turn(_) => None
+            };
+            let action_enemies = rule.actions.iter().filter_map(|action| match action {
+                BattleAction::Phase { enemy, .. } | BattleAction::Stat { enemy, .. } => Some(enemy),
+                _ => None
+            });
+            for enemy in trigger_enemy.into_iter().chain(action_enemies) {
+                if !in_battle(enemy) {
+                    report.error(format!("data/battle_scripts.cfg: {} has {}, who isn't in the battle", script.formation, enemy));
+                }
+            }
+            for action in &rule.actions {
+                match action {
+                    BattleAction::Backdrop(path) | BattleAction::Music(path) => check_asset(&mut report, data.manifest, "data/battle_scripts.cfg", path),
+                    _ => {}
+                }
+            }
+        }
+    }
+    for formation in data.formations {
+        for path in [&formation.scene.backdrop, &formation.scene.music].into_iter().flatten() {
+            check_asset(&mut report, data.manifest, "data/formations.cfg", path);
+        }
+    }
+
+    if data.battle_scenes.get(DEFAULT_TERRAIN).is_none() {
+        report.warning(format!("data/battle_scenes.cfg: there's no [{}], so terrains without a scene have no backdrop", DEFAULT_TERRAIN));
+    }
+    for terrain in data.battle_scenes.terrains() {
+        if let Some(scene) = data.battle_scenes.get(terrain) {
+            for path in [&scene.backdrop, &scene.music].into_iter().flatten() {
+                check_asset(&mut report, data.manifest, "data/battle_scenes.cfg", path);
+            }
+        }
+    }
+    let mut puzzle_flags = HashMap::new();
+    for name in data.fields.names() {
+        let field = match data.fields.get(name) {
+            Some(field) => field,
+            None => continue
+        };
+        if !field.terrain.is_empty() && data.battle_scenes.get(&field.terrain).is_none() {
+            report.warning(format!("field {}: there's no battle scene for its terrain {}, so it gets the default", name, field.terrain));
+        }
+        for path in [&field.battle_scene.backdrop, &field.battle_scene.music].into_iter().flatten() {
+            check_asset(&mut report, data.manifest, &format!("field {}", name), path);
+        }
+        let mut seen = HashSet::new();
+        for pickup in &field.pickups {
+            if !is_item(&pickup.item) {
+                report.error(format!("field {}: the pickup {} gives {}, which isn't an item", name, pickup.id, pickup.item));
+            }
+            if !seen.insert(&pickup.id) {
+                report.error(format!("field {}: there's more than one pickup called {}, so they'd share a flag", name, pickup.id));
+            }
+        }
+        for trap in &field.traps {
+            if let TrapKind::Pitfall { field: below, spawn } = &trap.kind {
+                match data.fields.get(below).map(|below| below.spawn_position(spawn.as_deref())) {
+                    None => report.error(format!("field {}: the pitfall {} drops into {}, which isn't a field", name, trap.id, below)),
+                    Some(Err(e)) => report.error(format!("field {}: the pitfall {} drops into {}: {}", name, trap.id, below, e)),
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+        for piece in &field.puzzle.pieces {
+            let prop_names = field.props.iter().map(|prop| prop.name_or(if prop.states.is_empty() { &prop.model } else { &prop.flag }));
+            if !prop_names.chain(field.movers.iter().map(|mover| mover.name.clone())).any(|known| known == *piece) {
+                report.error(format!("field {}: the puzzle piece {} isn't one of its props or movers", name, piece));
+            }
+        }
+        for flag in &field.puzzle.flags {
+            if let Some(other) = puzzle_flags.insert(flag, name) {
+                report.error(format!("field {}: the puzzle flag {} is in {}'s puzzle too, so leaving either would clear it", name, flag, other));
+            }
+        }
+    }
+
+    for member in &data.party.members {
+        for gambit in &member.gambits {
+            match &gambit.action {
+                GambitAction::Skill(skill) if !member.skills.iter().any(|known| known.name.eq_ignore_ascii_case(skill)) => {
+                    report.warning(format!("{}: the gambit \"{}\" is for a skill they don't have", member.name, gambit));
+                },
+                GambitAction::Item(item) if !is_item(item) => {
+                    report.error(format!("{}: the gambit \"{}\" is for an item that doesn't exist", member.name, gambit));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    for member in &data.party.members {
+        for skill in &member.skills {
+            for cost in &skill.costs {
+                match cost {
+                    SkillCost::Pool { pool, .. } if !data.pools.iter().any(|known| known.id == *pool) => {
+                        report.error(format!("{}: {} costs {}, which isn't in data/pools.cfg", member.name, skill.name, pool));
+                    },
+                    SkillCost::Item { item, .. } if !is_item(item) => {
+                        report.error(format!("{}: {} costs {}, which isn't an item", member.name, skill.name, item));
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for combo in data.combos {
+        for member in &combo.members {
+            if !data.party.members.iter().any(|known| known.name.eq_ignore_ascii_case(&member.name)) {
+                report.warning(format!("data/combos.cfg: {} needs {}, who isn't in the party", combo.name, member.name));
+            }
+        }
+    }
+
+    let mut skills = HashSet::new();
+    for summon in data.summons {
+        if summon.skill.is_empty() {
+            report.error(format!("data/summons.cfg: {} has no skill to call them", summon.id));
+        } else if !skills.insert(summon.skill.to_lowercase()) {
+            report.error(format!("data/summons.cfg: {} is called by {}, which already calls someone else", summon.id, summon.skill));
+        } else if !data.party.members.iter().flat_map(|member| &member.skills).any(|skill| skill.name.eq_ignore_ascii_case(&summon.skill)) {
+            report.warning(format!("data/summons.cfg: {} is called by {}, which nobody in the party knows", summon.id, summon.skill));
+        }
+    }
+
+    report.issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+fn check_asset(report: &mut ValidationReport, manifest: &AssetManifest, file: &str, path: &str) {
+    if !manifest.contains(path) {
+        report.error(format!("{}: {} isn't in the asset manifest", file, path));
+    }
+}
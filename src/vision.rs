@@ -0,0 +1,211 @@
+// What guards can see, for sneaking past them. A guard has a cone out in front of them, so wide
+// and so far, and the player's seen when they're inside it with nothing in the way. Walls and
+// crates that block sight are boxes on the walk mesh, listed in the field's blockers, and in 2D
+// fields the solid tiles block it too.
+//
+// Being seen puts an exclamation over the guard and sends a detection, which is what starts a
+// chase. The guard won't see them again until they've been out of sight. Sneaking makes guards
+// see less far.
+
+use std::time::Duration;
+
+use cgmath::{Deg, InnerSpace, Rotation, Vector2, Vector3};
+
+use crate::emote::{self, EmoteKind};
+use crate::field;
+use crate::field_status::{FieldEffect, FieldStatus};
+use crate::tilemap::Tilemap;
+use crate::transform::Transform;
+use crate::world::{Entity, World};
+
+// How far guards can see while the party's sneaking, as a fraction of their range.
+pub const SNEAK_RANGE: f32 = 0.5;
+// What guards get if they aren't told, in degrees across and metres.
+pub const DEFAULT_ANGLE: f32 = 90.0;
+pub const DEFAULT_RANGE: f32 = 6.0;
+// How long the exclamation shows for when they see the player.
+pub const DETECTION_EMOTE: Duration = Duration::from_millis(1000);
+
+// Component for anyone who can spot the player, facing the way their transform does.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VisionCone {
+    // How wide it is from one edge to the other, in degrees.
+    pub angle: f32,
+    // How far they can see, in metres, or pixels in 2D fields.
+    pub range: f32
+}
+
+impl Default for VisionCone {
+    fn default() -> Self {
+        Self { angle: DEFAULT_ANGLE, range: DEFAULT_RANGE }
+    }
+}
+
+impl VisionCone {
+    // Whether a point's inside the cone of someone standing at `eye`. Heights don't matter,
+    // only where things are on the walk mesh.
+    pub fn contains(&self, eye: &Transform, point: Vector3<f32>) -> bool {
+        let to_point = flatten(point - eye.position);
+        let distance = to_point.magnitude();
+        if distance > self.range {
+            return false;
+        }
+        if distance <= f32::EPSILON {
+            return true;
+        }
+        let forward = flatten(eye.rotation.rotate_vector(Vector3::unit_z()));
+        if forward.magnitude2() <= f32::EPSILON {
+            return false;
+        }
+        forward.angle(to_point) <= Deg(self.angle / 2.0).into()
+    }
+}
+
+// Something that blocks sight, as a box on the walk mesh from one corner to the other, in x
+// and z. They'd be the parts of a field's walk mesh tagged as walls, but nothing reads the walk
+// mesh out of the glTF yet, so they're listed with the field.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VisionBlocker {
+    pub min: [f32; 2],
+    pub max: [f32; 2]
+}
+
+impl VisionBlocker {
+    // Whether a point on the walk mesh is inside it.
+    pub fn contains(&self, x: f32, z: f32) -> bool {
+        (self.min[0]..=self.max[0]).contains(&x) && (self.min[1]..=self.max[1]).contains(&z)
+    }
+
+    // Whether the line from one point to another goes through it.
+    pub fn blocks(&self, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+        let (from, to) = (flatten(from), flatten(to));
+        let direction = to - from;
+        let (mut enter, mut leave) = (0.0f32, 1.0f32);
+        for axis in 0..2 {
+            let (start, step) = (from[axis], direction[axis]);
+            if step.abs() <= f32::EPSILON {
+                if start < self.min[axis] || start > self.max[axis] {
+                    return false;
+                }
+                continue;
+            }
+            let (a, b) = ((self.min[axis] - start) / step, (self.max[axis] - start) / step);
+            enter = enter.max(a.min(b));
+            leave = leave.min(a.max(b));
+            if enter > leave {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Who saw the player, and where the player was.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Detection {
+    pub guard: Entity,
+    pub position: Vector3<f32>
+}
+
+// Resource collecting detections for chase scripts to pick up.
+#[derive(Clone, Debug, Default)]
+pub struct DetectionEvents {
+    events: Vec<Detection>
+}
+
+impl DetectionEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Detections since the last call, in the order they happened.
+    pub fn take_events(&mut self) -> Vec<Detection> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+// Marks a guard that's seeing the player, so they're only detected once each time they come
+// into view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeeingPlayer;
+
+// Whether there's nothing in the way between two points, going by the blockers and, in 2D
+// fields, the map's solid tiles.
+pub fn line_of_sight(from: Vector3<f32>, to: Vector3<f32>, blockers: &[VisionBlocker], map: Option<&Tilemap>) -> bool {
+    if blockers.iter().any(|blocker| blocker.blocks(from, to)) {
+        return false;
+    }
+    let map = match map {
+        Some(map) => map,
+        None => return true
+    };
+    // Checked every half a tile, which doesn't miss any a line goes through more than a
+    // corner of. In 2D fields x and z are map pixels.
+    let spacing = map.tile_width.min(map.tile_height).max(1) as f32 / 2.0;
+    let distance = flatten(to - from).magnitude();
+    let samples = (distance / spacing).ceil() as u32;
+    (1..samples).all(|sample| {
+        let point = from + (to - from) * (sample as f32 / samples as f32);
+        !map.is_solid_at(point.x, point.z)
+    })
+}
+
+// Check every guard for whether they can see the player. The ones who've just started to show
+// an exclamation and send a detection. Returns who they were.
+pub fn update_vision(world: &mut World, player: Entity) -> Vec<Entity> {
+    let position = match world.get::<Transform>(player) {
+        Some(transform) => transform.position,
+        None => return Vec::new()
+    };
+    let range_scale = match world.resource::<FieldStatus>() {
+        Some(status) if status.has(FieldEffect::Sneak) => SNEAK_RANGE,
+        _ => 1.0
+    };
+    let blockers = field::blockers(world);
+    let map = world.resource::<Tilemap>();
+
+    let mut seeing = Vec::new();
+    for (guard, cone) in world.query::<VisionCone>() {
+        if guard == player {
+            continue;
+        }
+        let eye = match world.get::<Transform>(guard) {
+            Some(eye) => eye,
+            None => continue
+        };
+        let cone = VisionCone { range: cone.range * range_scale, ..*cone };
+        let sees = cone.contains(eye, position) && line_of_sight(eye.position, position, &blockers, map);
+        seeing.push((guard, sees));
+    }
+
+    let mut detected = Vec::new();
+    for (guard, sees) in seeing {
+        match (sees, world.has::<SeeingPlayer>(guard)) {
+            (true, false) => {
+                world.insert(guard, SeeingPlayer);
+                emote::show_emote(world, guard, EmoteKind::Exclamation, DETECTION_EMOTE);
+                detected.push(Detection { guard, position });
+            },
+            (false, true) => {
+                world.remove::<SeeingPlayer>(guard);
+            },
+            _ => {}
+        }
+    }
+
+    if detected.is_empty() {
+        return Vec::new();
+    }
+    if world.resource::<DetectionEvents>().is_none() {
+        world.insert_resource(DetectionEvents::new());
+    }
+    let guards = detected.iter().map(|detection| detection.guard).collect();
+    if let Some(events) = world.resource_mut::<DetectionEvents>() {
+        events.events.extend(detected);
+    }
+    guards
+}
+
+fn flatten(vector: Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(vector.x, vector.z)
+}
@@ -0,0 +1,272 @@
+// A developer menu for jumping straight to any field, and setting up the game for testing
+// later parts of it, so QA don't have to play through to get there. Opened from the console
+// with "warp".
+
+use winit::event::VirtualKeyCode;
+
+use crate::accessibility::Accessibility;
+use crate::menu::MenuSound;
+use crate::field::FieldMap;
+use crate::flags::GameFlags;
+use crate::pointer::{PointerEvent, PointerResponse};
+use crate::renderer::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::ui::UiBatch;
+
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 4.0;
+
+// Story progress to jump to, as the flags it would have set by then.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WarpPreset {
+    pub name: String,
+    pub flags: Vec<(String, i32)>
+}
+
+impl WarpPreset {
+    // Presets are "flag = value" lines under a [Preset name] header, e.g.
+    //
+    // [After the prologue]
+    // intro.complete = 1
+    // quest.lost_cat = 2
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        let mut presets: Vec<WarpPreset> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                presets.push(WarpPreset { name: name.trim().to_string(), flags: Vec::new() });
+                continue;
+            }
+
+            let preset = presets.last_mut().ok_or_else(|| format!("Line {}: expected a [Preset name] first", number + 1))?;
+            let (flag, value) = line.split_once('=')
+                .map(|(flag, value)| (flag.trim(), value.trim()))
+                .ok_or_else(|| format!("Line {}: expected \"flag = value\"", number + 1))?;
+            let value = value.parse().map_err(|_| format!("Line {}: bad value \"{}\"", number + 1, value))?;
+            preset.flags.push((flag.to_string(), value));
+        }
+        Ok(presets)
+    }
+
+    pub fn apply(&self, flags: &mut GameFlags) {
+        for (flag, value) in &self.flags {
+            flags.set(flag, *value);
+        }
+    }
+}
+
+// Something to pick from the menu.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WarpChoice {
+    // Go to a field, at one of its spawn points or its first.
+    Field { field: String, spawn: Option<String> },
+    // Apply the preset with this name.
+    Preset(String)
+}
+
+impl WarpChoice {
+    // Every field, each followed by its spawn points, then the presets.
+    pub fn list(fields: &FieldMap, presets: &[WarpPreset]) -> Vec<WarpChoice> {
+        let mut choices = Vec::new();
+        for name in fields.names() {
+            choices.push(WarpChoice::Field { field: name.to_string(), spawn: None });
+            for spawn in fields.get(name).into_iter().flat_map(|field| &field.spawns) {
+                choices.push(WarpChoice::Field { field: name.to_string(), spawn: Some(spawn.name.clone()) });
+            }
+        }
+        choices.extend(presets.iter().map(|preset| WarpChoice::Preset(preset.name.clone())));
+        choices
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            WarpChoice::Field { field, spawn: None } => field.clone(),
+            WarpChoice::Field { spawn: Some(spawn), .. } => format!("  {}", spawn),
+            WarpChoice::Preset(name) => format!("Preset: {}", name)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WarpMenu {
+    choices: Vec<WarpChoice>,
+    open: bool,
+    // Kept when it closes, so it opens where it was.
+    selected: usize,
+    sound: Option<MenuSound>
+}
+
+impl WarpMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, choices: Vec<WarpChoice>) {
+        self.open = true;
+        self.choices = choices;
+        self.selected = self.selected.min(self.choices.len().saturating_sub(1));
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn selected(&self) -> Option<&WarpChoice> {
+        self.choices.get(self.selected)
+    }
+
+    // The sound the last key or pointer event made, see menu.rs.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    // Move the selection with up and down, pick with enter and close with escape. Picking
+    // something closes the menu.
+    pub fn handle_key(&mut self, key: VirtualKeyCode) -> Option<WarpChoice> {
+        if !self.open {
+            return None;
+        }
+        match key {
+            VirtualKeyCode::Up if self.selected > 0 => self.select(self.selected - 1),
+            VirtualKeyCode::Down if self.selected + 1 < self.choices.len() => self.select(self.selected + 1),
+            VirtualKeyCode::Return => return self.pick(),
+            VirtualKeyCode::Escape => self.cancel(),
+            _ => {}
+        }
+        None
+    }
+
+    // The same for the mouse and touch, like the save menu.
+    pub fn handle_pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> PointerResponse<WarpChoice> {
+        let before = (self.open, self.selected);
+        let action = self.pointer(event, accessibility);
+        PointerResponse::new(action, before != (self.open, self.selected))
+    }
+
+    fn pointer(&mut self, event: PointerEvent, accessibility: &Accessibility) -> Option<WarpChoice> {
+        if !self.open {
+            return None;
+        }
+        let layout = Layout::new(accessibility);
+        match event {
+            PointerEvent::Hover { x, y } => {
+                if let Some(index) = layout.row_at(x, y, self.selected, self.choices.len()) {
+                    self.select(index);
+                }
+            },
+            PointerEvent::Click { x, y } => match layout.row_at(x, y, self.selected, self.choices.len()) {
+                Some(index) => {
+                    self.select(index);
+                    return self.pick();
+                },
+                None => self.cancel()
+            },
+            PointerEvent::Scroll { steps } => {
+                let last = self.choices.len().saturating_sub(1) as i64;
+                self.select((self.selected as i64 + steps as i64).clamp(0, last) as usize);
+            },
+            PointerEvent::Cancel => self.cancel()
+        }
+        None
+    }
+
+    fn select(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.sound = Some(MenuSound::Move);
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.open = false;
+        self.sound = Some(MenuSound::Cancel);
+    }
+
+    fn pick(&mut self) -> Option<WarpChoice> {
+        let choice = match self.choices.get(self.selected) {
+            Some(choice) => choice.clone(),
+            None => {
+                self.sound = Some(MenuSound::Error);
+                return None;
+            }
+        };
+        self.open = false;
+        self.sound = Some(MenuSound::Confirm);
+        Some(choice)
+    }
+
+    pub fn build(&self, batch: &mut UiBatch, accessibility: &Accessibility) {
+        if !self.open {
+            return;
+        }
+
+        let skin = accessibility.skin();
+        let layout = Layout::new(accessibility);
+        batch.rect(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, skin.backdrop);
+        batch.text(MARGIN, MARGIN, layout.scale * 1.5, "Warp", skin.text);
+
+        let first = layout.first_visible(self.selected);
+        for (row, index) in (first..self.choices.len()).take(layout.visible_rows).enumerate() {
+            let y = layout.row_y(row);
+            let choice = &self.choices[index];
+            if index == self.selected {
+                batch.rect(MARGIN, y, layout.width, layout.row_height, skin.selected);
+            }
+            let color = match choice {
+                WarpChoice::Field { spawn: None, .. } => skin.text,
+                WarpChoice::Field { .. } => skin.dim_text,
+                WarpChoice::Preset(_) => skin.highlight
+            };
+            batch.text(MARGIN + PADDING, y + PADDING, layout.scale, &choice.label(), color);
+        }
+    }
+}
+
+// Where the menu's rows go, shared by drawing and hit testing. There can be more rows than
+// fit, so the list scrolls to keep the selected one on screen.
+struct Layout {
+    scale: f32,
+    first_row_y: f32,
+    row_height: f32,
+    visible_rows: usize,
+    width: f32
+}
+
+impl Layout {
+    fn new(accessibility: &Accessibility) -> Self {
+        let scale = 2.0 * accessibility.text_scale();
+        let first_row_y = MARGIN * 2.0 + UiBatch::line_height(scale * 1.5);
+        let row_height = UiBatch::line_height(scale) + PADDING * 2.0;
+        Self {
+            scale,
+            first_row_y,
+            row_height,
+            visible_rows: (((SCREEN_HEIGHT as f32 - first_row_y - MARGIN) / row_height) as usize).max(1),
+            width: SCREEN_WIDTH as f32 - MARGIN * 2.0
+        }
+    }
+
+    fn first_visible(&self, selected: usize) -> usize {
+        (selected + 1).saturating_sub(self.visible_rows)
+    }
+
+    fn row_y(&self, row: usize) -> f32 {
+        self.first_row_y + self.row_height * row as f32
+    }
+
+    fn row_at(&self, x: f32, y: f32, selected: usize, count: usize) -> Option<usize> {
+        if !(MARGIN..MARGIN + self.width).contains(&x) || y < self.first_row_y {
+            return None;
+        }
+        let row = ((y - self.first_row_y) / self.row_height) as usize;
+        let index = self.first_visible(selected) + row;
+        (row < self.visible_rows && index < count).then_some(index)
+    }
+}
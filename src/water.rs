@@ -0,0 +1,215 @@
+// Water in fields that can be swum in, like a river or a pond. Each region's a box on the walk
+// mesh, in x and z, with how fast swimming goes, the clips that are played for it, the
+// footstep sound it uses and maybe a flag that has to be set before anyone can go in, like
+// having learnt to swim. They'd be the parts of the walk mesh tagged as water, but nothing
+// reads the walk mesh out of the glTF yet, so they're listed with the field.
+//
+// Swimmers leave ripples behind them as they go. Ones that can't go in the water yet are put
+// back where they were before they stepped in.
+
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::field::FieldDescriptor;
+use crate::flags::GameFlags;
+use crate::transform::Transform;
+use crate::ui::{self, UiBatch};
+use crate::world::{Entity, World};
+
+// What water regions get if they aren't told.
+pub const SWIM_SPEED: f32 = 0.5;
+pub const SWIM_ANIMATIONS: &str = "swim";
+// How often swimmers leave a ripple while they're moving, and how long each lasts.
+pub const RIPPLE_INTERVAL: Duration = Duration::from_millis(350);
+pub const RIPPLE_TIME: Duration = Duration::from_millis(800);
+
+// How big ripples get, as a scale on the sprite.
+const RIPPLE_SCALE: (f32, f32) = (1.0, 4.0);
+// A ring squashed flat, as it's lying on the water.
+const RIPPLE_SPRITE: [&str; 5] = [
+    "..#####..",
+    ".#.....#.",
+    "#.......#",
+    ".#.....#.",
+    "..#####.."
+];
+const RIPPLE_COLOR: ui::Color = [0.85, 0.95, 1.0, 0.8];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterRegion {
+    // Corners of the box on the walk mesh, in x and z.
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    // What walking speed's multiplied by while swimming.
+    pub speed: f32,
+    // Put in front of clip names while swimming, so "walk" is "swim_walk".
+    pub animations: String,
+    // A flag that has to be set to go in, if any.
+    pub requires: Option<String>,
+    // The sound footsteps make instead, if it's different.
+    pub footstep: Option<String>
+}
+
+impl Default for WaterRegion {
+    fn default() -> Self {
+        Self {
+            min: [0.0, 0.0],
+            max: [0.0, 0.0],
+            speed: SWIM_SPEED,
+            animations: SWIM_ANIMATIONS.to_string(),
+            requires: None,
+            footstep: None
+        }
+    }
+}
+
+impl WaterRegion {
+    pub fn contains(&self, position: Vector3<f32>) -> bool {
+        (self.min[0]..=self.max[0]).contains(&position.x) && (self.min[1]..=self.max[1]).contains(&position.z)
+    }
+
+    // Whether the story's got far enough to go in.
+    pub fn can_enter(&self, flags: Option<&GameFlags>) -> bool {
+        match &self.requires {
+            Some(flag) => flags.is_some_and(|flags| flags.is_set(flag)),
+            None => true
+        }
+    }
+
+    // The clip to play while swimming instead of `clip`.
+    pub fn clip(&self, clip: &str) -> String {
+        format!("{}_{}", self.animations, clip)
+    }
+}
+
+// The water region a point's in, if any.
+pub fn water_at(regions: &[WaterRegion], position: Vector3<f32>) -> Option<&WaterRegion> {
+    regions.iter().find(|region| region.contains(position))
+}
+
+// Component for anyone who can go in water, with what they're swimming in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Swimmer {
+    water: Option<WaterRegion>,
+    // Where they were last update, to put them back if they can't go in and to tell if
+    // they're moving.
+    last_position: Option<Vector3<f32>>,
+    since_ripple: Duration
+}
+
+impl Swimmer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The water they're in, or None on dry land.
+    pub fn water(&self) -> Option<&WaterRegion> {
+        self.water.as_ref()
+    }
+}
+
+// What an entity's walking speed is multiplied by, for the movement system.
+pub fn speed_scale(world: &World, entity: Entity) -> f32 {
+    world.get::<Swimmer>(entity).and_then(Swimmer::water).map_or(1.0, |water| water.speed)
+}
+
+// The clip an entity plays instead of `clip`, which is changed while it's swimming.
+pub fn clip_name(world: &World, entity: Entity, clip: &str) -> String {
+    match world.get::<Swimmer>(entity).and_then(Swimmer::water) {
+        Some(water) => water.clip(clip),
+        None => clip.to_string()
+    }
+}
+
+// The sound an entity's footsteps make, `sound` unless the water it's in has its own.
+pub fn footstep_sound(world: &World, entity: Entity, sound: &str) -> String {
+    world.get::<Swimmer>(entity)
+        .and_then(Swimmer::water)
+        .and_then(|water| water.footstep.clone())
+        .unwrap_or_else(|| sound.to_string())
+}
+
+// A ring spreading out on the water.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ripple {
+    pub position: Vector3<f32>,
+    pub age: Duration
+}
+
+// Resource with the ripples on the water.
+#[derive(Clone, Debug, Default)]
+pub struct Ripples(pub Vec<Ripple>);
+
+// Check who's in the water, keeping out those who can't go in yet, and leave ripples behind
+// those who are moving in it. Returns true while there are ripples to draw.
+pub fn update_swimming(world: &mut World, delta: Duration) -> bool {
+    let regions = world.resource::<FieldDescriptor>().map(|field| field.water.clone()).unwrap_or_default();
+    let swimmers: Vec<Entity> = world.query::<Swimmer>().map(|(entity, _)| entity).collect();
+    let mut ripples = world.remove_resource::<Ripples>().unwrap_or_default();
+
+    for ripple in &mut ripples.0 {
+        ripple.age += delta;
+    }
+    ripples.0.retain(|ripple| ripple.age < RIPPLE_TIME);
+
+    for entity in swimmers {
+        let mut position = match world.get::<Transform>(entity) {
+            Some(transform) => transform.position,
+            None => continue
+        };
+        let mut water = water_at(&regions, position).cloned();
+        let (last_position, was_swimming) = match world.get::<Swimmer>(entity) {
+            Some(swimmer) => (swimmer.last_position, swimmer.water.is_some()),
+            None => continue
+        };
+        if let (Some(region), Some(last_position)) = (&water, last_position) {
+            if !was_swimming && !region.can_enter(world.resource::<GameFlags>()) {
+                position = last_position;
+                if let Some(transform) = world.get_mut::<Transform>(entity) {
+                    transform.position = position;
+                }
+                water = None;
+            }
+        }
+        let moved = last_position.is_some_and(|last| (position - last).magnitude() > f32::EPSILON);
+
+        let swimmer = match world.get_mut::<Swimmer>(entity) {
+            Some(swimmer) => swimmer,
+            None => continue
+        };
+        // A splash going in, then a ripple every so often while they're moving.
+        let splash = water.is_some() && !was_swimming;
+        swimmer.since_ripple += delta;
+        if splash || (water.is_some() && moved && swimmer.since_ripple >= RIPPLE_INTERVAL) {
+            swimmer.since_ripple = Duration::ZERO;
+            ripples.0.push(Ripple { position, age: Duration::ZERO });
+        }
+        swimmer.water = water;
+        swimmer.last_position = Some(position);
+    }
+
+    let rippling = !ripples.0.is_empty();
+    world.insert_resource(ripples);
+    rippling
+}
+
+// Draw the ripples, growing and fading as they go. `to_screen` is where a point in the world is
+// on the screen, like for emotes.
+pub fn build_ripples(world: &World, batch: &mut UiBatch, to_screen: impl Fn(Vector3<f32>) -> Option<(f32, f32)>) {
+    let ripples = match world.resource::<Ripples>() {
+        Some(ripples) if !ripples.0.is_empty() => ripples,
+        _ => return
+    };
+    let sprite = ui::pixel_sprite(&RIPPLE_SPRITE, &[('#', RIPPLE_COLOR)]);
+    for ripple in &ripples.0 {
+        let (x, y) = match to_screen(ripple.position) {
+            Some(point) => point,
+            None => continue
+        };
+        let t = (ripple.age.as_secs_f32() / RIPPLE_TIME.as_secs_f32()).min(1.0);
+        let scale = RIPPLE_SCALE.0 + (RIPPLE_SCALE.1 - RIPPLE_SCALE.0) * t;
+        let (width, height) = (sprite.width() as f32 * scale, sprite.height() as f32 * scale);
+        batch.sprite(x - width / 2.0, y - height / 2.0, scale, &sprite, 1.0 - t);
+    }
+}
@@ -0,0 +1,116 @@
+// Entry point for the web build. Build with wasm-pack or wasm-bindgen for wasm32-unknown-unknown
+// and serve the output next to the fields directory.
+//
+// Nothing can block on the web, so setup is all async and the event loop is driven by
+// requestAnimationFrame through winit. The canvas is kept the size of the browser window.
+
+use tracing_subscriber::{prelude::*, EnvFilter};
+use wasm_bindgen::prelude::*;
+use winit::{dpi::LogicalSize, event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent}, event_loop::{ControlFlow, EventLoop}, platform::web::{EventLoopExtWebSys, WindowExtWebSys}, window::{Window, WindowBuilder}};
+
+use crate::assets::AssetServer;
+use crate::debug_overlay::{DebugInfo, DebugOverlay, FrameStats};
+use crate::logging::{targets, DEFAULT_FILTER};
+use crate::model::ModelBatch;
+use crate::renderer::Renderer;
+use crate::ui::UiBatch;
+
+#[wasm_bindgen(start)]
+pub fn start() {
+    console_error_panic_hook::set_once();
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(DEFAULT_FILTER))
+        .with(tracing_wasm::WASMLayer::new(tracing_wasm::WASMLayerConfig::default()))
+        .init();
+
+    wasm_bindgen_futures::spawn_local(run());
+}
+
+// The size of the browser window, in CSS pixels.
+fn browser_size() -> Option<LogicalSize<f64>> {
+    let browser_window = web_sys::window()?;
+    let width = browser_window.inner_width().ok()?.as_f64()?;
+    let height = browser_window.inner_height().ok()?.as_f64()?;
+    Some(LogicalSize::new(width, height))
+}
+
+fn fit_canvas(window: &Window, renderer: &mut Renderer) {
+    if let Some(size) = browser_size() {
+        let size = size.to_physical(window.scale_factor());
+        if size != window.inner_size() {
+            window.set_inner_size(size);
+            renderer.resize(size);
+        }
+    }
+}
+
+async fn run() {
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    // Put the canvas on the page.
+    let body = web_sys::window()
+        .and_then(|browser_window| browser_window.document())
+        .and_then(|document| document.body())
+        .expect("The page has no body to put the canvas in");
+    body.append_child(&web_sys::Element::from(window.canvas()))
+        .expect("Couldn't add the canvas to the page");
+
+    let assets = AssetServer::new(".");
+    let mut renderer = Renderer::new(&window, &assets).await;
+    fit_canvas(&window, &mut renderer);
+
+    let mut ui_batch = UiBatch::new();
+    let mut frame_stats = FrameStats::new();
+    let mut debug_overlay = DebugOverlay::new();
+
+    tracing::info!(target: targets::ENGINE, "Started web build.");
+
+    // Unlike run, spawn returns straight away and the browser drives the loop from then on.
+    event_loop.spawn(move |event, _, control_flow| {
+        // Redraws come from requestAnimationFrame, so there's no need to spin.
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                frame_stats.begin_frame();
+
+                ui_batch.clear();
+                debug_overlay.build(&mut ui_batch, &frame_stats, renderer.get_stats(), &DebugInfo {
+                    field: "test_field",
+                    state: "Field",
+                    entities: 0
+                });
+
+                if let Err(e) = renderer.render(&ModelBatch::default(), &ui_batch, None) {
+                    tracing::error!(target: targets::RENDERER, "{:?}", e);
+                }
+
+                frame_stats.end_frame();
+            },
+
+            Event::MainEventsCleared => {
+                // The browser doesn't tell winit when the page changes size.
+                fit_canvas(&window, &mut renderer);
+                window.request_redraw();
+            },
+
+            Event::WindowEvent { ref event, window_id } if window_id == window.id() => match event {
+                WindowEvent::Resized(physical_size) => renderer.resize(*physical_size),
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => renderer.resize(**new_inner_size),
+
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                    ..
+                } => debug_overlay.toggle(),
+
+                _ => {}
+            },
+            _ => {}
+        }
+    });
+}
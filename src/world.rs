@@ -0,0 +1,203 @@
+use std::{any::{Any, TypeId}, collections::{BTreeMap, HashMap}, fmt::Debug};
+
+// A handle to something in the world. On its own it's just an id, all of the data lives in
+// components attached to it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity(u32);
+
+impl Entity {
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+// Anything can be a component or resource as long as it can be printed for debugging.
+pub trait Component: Any + Debug {}
+impl<T: Any + Debug> Component for T {}
+
+// A human readable name for an entity.
+#[derive(Clone, Debug)]
+pub struct Name(pub String);
+
+// Type erased access to the storage for one type of component.
+trait Storage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn describe(&self, entity: Entity) -> Option<String>;
+    fn type_name(&self) -> &'static str;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct ComponentStorage<T: Component> {
+    // Sorted by entity so iteration order is always the same.
+    components: BTreeMap<Entity, T>
+}
+
+impl<T: Component> Storage for ComponentStorage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.components.remove(&entity);
+    }
+
+    fn describe(&self, entity: Entity) -> Option<String> {
+        self.components.get(&entity).map(|component| format!("{:#?}", component))
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Holds all of the entities and their components, as well as global resources.
+pub struct World {
+    next_entity: u32,
+    entities: Vec<Entity>,
+    storages: HashMap<TypeId, Box<dyn Storage>>,
+    resources: HashMap<TypeId, (&'static str, Box<dyn Any>)>,
+    resource_describers: HashMap<TypeId, fn(&dyn Any) -> String>
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            next_entity: 0,
+            entities: Vec::new(),
+            storages: HashMap::new(),
+            resources: HashMap::new(),
+            resource_describers: HashMap::new()
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        let entity = Entity(self.next_entity);
+        self.next_entity += 1;
+        self.entities.push(entity);
+        entity
+    }
+
+    // Remove an entity and all of its components.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.entities.retain(|e| *e != entity);
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    fn storage<T: Component>(&self) -> Option<&ComponentStorage<T>> {
+        self.storages.get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref::<ComponentStorage<T>>())
+    }
+
+    fn storage_mut<T: Component>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.storages.get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<ComponentStorage<T>>())
+    }
+
+    // Attach a component to an entity, replacing any existing component of the same type.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        let storage = self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T> { components: BTreeMap::new() }));
+        storage.as_any_mut().downcast_mut::<ComponentStorage<T>>().unwrap()
+            .components.insert(entity, component);
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>().and_then(|storage| storage.components.remove(&entity))
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>().and_then(|storage| storage.components.get(&entity))
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>().and_then(|storage| storage.components.get_mut(&entity))
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    // Every entity that has a T, along with the T.
+    pub fn query<T: Component>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>().into_iter()
+            .flat_map(|storage| storage.components.iter().map(|(entity, component)| (*entity, component)))
+    }
+
+    pub fn query_mut<T: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.storage_mut::<T>().into_iter()
+            .flat_map(|storage| storage.components.iter_mut().map(|(entity, component)| (*entity, component)))
+    }
+
+    // The first entity with a matching name.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.query::<Name>().find(|(_, n)| n.0 == name).map(|(entity, _)| entity)
+    }
+
+    // The type name and debug output of every component on an entity. Used by the debug tools.
+    pub fn describe_components(&self, entity: Entity) -> Vec<(&'static str, String)> {
+        let mut components: Vec<_> = self.storages.values()
+            .filter_map(|storage| storage.describe(entity).map(|description| (storage.type_name(), description)))
+            .collect();
+        components.sort_by_key(|(name, _)| *name);
+        components
+    }
+
+    // Resources are global singletons, e.g. the camera or the game clock.
+    pub fn insert_resource<T: Component>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), (std::any::type_name::<T>(), Box::new(resource)));
+        self.resource_describers.insert(TypeId::of::<T>(), |resource| {
+            format!("{:#?}", resource.downcast_ref::<T>().unwrap())
+        });
+    }
+
+    pub fn remove_resource<T: Component>(&mut self) -> Option<T> {
+        self.resource_describers.remove(&TypeId::of::<T>());
+        self.resources.remove(&TypeId::of::<T>())
+            .and_then(|(_, resource)| resource.downcast::<T>().ok())
+            .map(|resource| *resource)
+    }
+
+    pub fn resource<T: Component>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())
+            .and_then(|(_, resource)| resource.downcast_ref::<T>())
+    }
+
+    pub fn resource_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut(&TypeId::of::<T>())
+            .and_then(|(_, resource)| resource.downcast_mut::<T>())
+    }
+
+    // The type name and debug output of every resource.
+    pub fn describe_resources(&self) -> Vec<(&'static str, String)> {
+        let mut resources: Vec<_> = self.resources.iter()
+            .map(|(type_id, (name, resource))| (*name, self.resource_describers[type_id](resource.as_ref())))
+            .collect();
+        resources.sort_by_key(|(name, _)| *name);
+        resources
+    }
+}
@@ -0,0 +1,48 @@
+// Accessibility settings, and keeping them in the config.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    accessibility::{Accessibility, TextSpeed, MAX_TEXT_SCALE},
+    config::Config,
+    ui::WindowSkin
+};
+
+#[test]
+fn text_types_out_at_the_chosen_speed() {
+    let half_second = Duration::from_millis(500);
+    assert_eq!(TextSpeed::Slow.visible_chars(half_second, 100), 10);
+    assert_eq!(TextSpeed::Normal.visible_chars(half_second, 100), 20);
+    assert_eq!(TextSpeed::Fast.visible_chars(half_second, 100), 40);
+    assert_eq!(TextSpeed::Fast.visible_chars(Duration::from_secs(10), 100), 100);
+    assert_eq!(TextSpeed::Instant.visible_chars(Duration::ZERO, 100), 100);
+}
+
+#[test]
+fn settings_change_skin_scale_and_effects() {
+    let mut accessibility = Accessibility::new();
+    assert_eq!(accessibility.skin(), WindowSkin::DEFAULT);
+    assert_eq!(accessibility.effect_strength(0.5), 0.5);
+
+    accessibility.high_contrast = true;
+    accessibility.screen_effects = false;
+    accessibility.set_text_scale(10.0);
+    assert_eq!(accessibility.skin(), WindowSkin::HIGH_CONTRAST);
+    assert_eq!(accessibility.effect_strength(0.5), 0.0);
+    assert_eq!(accessibility.text_scale(), MAX_TEXT_SCALE);
+}
+
+#[test]
+fn settings_are_kept_in_the_config() {
+    let path = std::env::temp_dir().join(format!("ps_rpg_engine_accessibility_{}.cfg", std::process::id()));
+    let mut config = Config::default();
+    config.accessibility.set_text_scale(1.5);
+    config.accessibility.text_speed = TextSpeed::Instant;
+    config.accessibility.high_contrast = true;
+    config.accessibility.screen_effects = false;
+    config.save_to(&path).unwrap();
+
+    let loaded = Config::load_from(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.accessibility, config.accessibility);
+}
@@ -0,0 +1,101 @@
+// Achievements: reading their definitions, unlocking them from flags and events, and keeping
+// them between runs.
+
+use std::path::PathBuf;
+
+use ps_rpg_engine::{
+    achievements::{AchievementCondition, AchievementDefinition, Achievements},
+    flags::GameFlags,
+    platform::NullPlatform
+};
+
+const ACHIEVEMENTS: &str = "
+# Story
+[FIRST_STEPS]
+name = First Steps
+description = Finish the introduction.
+flag = intro.complete
+value = 2
+
+[FIRST_BLOOD]
+event = battle.won
+";
+
+fn achievements() -> Achievements {
+    Achievements::new(AchievementDefinition::parse_list(ACHIEVEMENTS).unwrap())
+}
+
+fn save_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("ps_rpg_engine_achievements_{}_{}", name, std::process::id()))
+        .join("achievements.txt")
+}
+
+#[test]
+fn definitions_are_read() {
+    let definitions = AchievementDefinition::parse_list(ACHIEVEMENTS).unwrap();
+    assert_eq!(definitions.len(), 2);
+    assert_eq!((definitions[0].name.as_str(), definitions[0].description.as_str()), ("First Steps", "Finish the introduction."));
+    assert_eq!(definitions[0].condition, AchievementCondition::Flag { flag: "intro.complete".to_string(), value: 2 });
+    // Without a name it's called by its id.
+    assert_eq!(definitions[1].name, "FIRST_BLOOD");
+    assert_eq!(definitions[1].condition, AchievementCondition::Event("battle.won".to_string()));
+
+    assert_eq!(AchievementDefinition::parse_list("name = Nobody"), Err("Line 1: expected an [ACHIEVEMENT_ID] first".to_string()));
+    assert_eq!(AchievementDefinition::parse_list("[A]\nflag"), Err("Line 2: expected \"key = value\"".to_string()));
+    assert_eq!(AchievementDefinition::parse_list("[A]\nflag = a\nvalue = lots"), Err("Line 3: bad value \"lots\"".to_string()));
+    assert_eq!(AchievementDefinition::parse_list("[A]\ncolour = gold"), Err("Line 2: unknown key \"colour\"".to_string()));
+    assert_eq!(AchievementDefinition::parse_list("[A]\nname = A"), Err("A needs either a flag or an event".to_string()));
+    assert_eq!(AchievementDefinition::parse_list("[A]\nflag = a\nevent = b"), Err("A needs either a flag or an event".to_string()));
+}
+
+#[test]
+fn flags_and_events_unlock_them_once() {
+    let mut achievements = achievements();
+    let mut platform = NullPlatform;
+    let mut flags = GameFlags::new();
+
+    // Not far enough yet.
+    flags.set("intro.complete", 1);
+    achievements.update(&flags.take_events(), &mut platform);
+    assert!(!achievements.is_unlocked("FIRST_STEPS"));
+    flags.set("intro.complete", 2);
+    achievements.update(&flags.take_events(), &mut platform);
+    assert!(achievements.is_unlocked("FIRST_STEPS"));
+
+    // Events only unlock the ones waiting for them.
+    achievements.notify_event("battle.lost", &mut platform);
+    assert!(!achievements.is_unlocked("FIRST_BLOOD"));
+    achievements.notify_event("battle.won", &mut platform);
+    assert!(achievements.is_unlocked("FIRST_BLOOD"));
+
+    // Unlocking again, or one that doesn't exist, changes nothing.
+    let path = save_path("once");
+    achievements.load_local(&path, &mut platform);
+    achievements.notify_event("battle.won", &mut platform);
+    flags.set("intro.complete", 3);
+    achievements.update(&flags.take_events(), &mut platform);
+    achievements.unlock("NO_SUCH_THING", &mut platform);
+    assert!(!achievements.is_unlocked("NO_SUCH_THING"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn unlocks_are_kept_between_runs() {
+    let path = save_path("kept");
+    let mut platform = NullPlatform;
+
+    // Nothing saved yet is fine.
+    let mut achievements = achievements();
+    achievements.load_local(&path, &mut platform);
+    assert!(!achievements.is_unlocked("FIRST_BLOOD"));
+    achievements.notify_event("battle.won", &mut platform);
+    achievements.notify_event("battle.won", &mut platform);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "FIRST_BLOOD\n");
+
+    let mut next_run = self::achievements();
+    next_run.load_local(&path, &mut platform);
+    assert!(next_run.is_unlocked("FIRST_BLOOD") && !next_run.is_unlocked("FIRST_STEPS"));
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,75 @@
+// Animation timelines and the events they fire.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    animation::{self, AnimationClip, AnimationEvents, AnimationPlayer},
+    world::World
+};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+fn walk_cycle() -> AnimationClip {
+    AnimationClip::new("walk", ms(1000), true)
+        .with_event(ms(800), "footstep_right")
+        .with_event(ms(300), "footstep_left")
+}
+
+fn advance(player: &mut AnimationPlayer, delta: Duration) -> Vec<String> {
+    let mut names = Vec::new();
+    player.advance(delta, |_, event| names.push(event.name.clone()));
+    names
+}
+
+#[test]
+fn events_fire_once_when_crossed() {
+    let mut player = AnimationPlayer::new();
+    player.play(walk_cycle());
+
+    assert!(advance(&mut player, ms(200)).is_empty());
+    assert_eq!(advance(&mut player, ms(100)), ["footstep_left"]);
+    assert!(advance(&mut player, ms(100)).is_empty());
+    // Round the end of the loop and past the first footstep again, in one frame.
+    assert_eq!(advance(&mut player, ms(1000)), ["footstep_right", "footstep_left"]);
+    assert_eq!(player.time(), ms(400));
+
+    // Half speed takes twice as long to get to the next one.
+    player.speed = 0.5;
+    assert!(advance(&mut player, ms(600)).is_empty());
+    assert_eq!(advance(&mut player, ms(200)), ["footstep_right"]);
+}
+
+#[test]
+fn clips_that_dont_loop_stop_at_the_end() {
+    let attack = AnimationClip::new("attack", ms(500), false)
+        .with_event(Duration::ZERO, "swing")
+        .with_event(ms(250), "hit")
+        .with_event(ms(500), "recover");
+    let mut player = AnimationPlayer::new();
+    player.play(attack);
+
+    assert_eq!(advance(&mut player, ms(10)), ["swing"]);
+    assert_eq!(advance(&mut player, ms(5000)), ["hit", "recover"]);
+    assert!(!player.is_playing());
+    assert!(advance(&mut player, ms(5000)).is_empty());
+}
+
+#[test]
+fn events_go_to_the_world() {
+    let mut world = World::new();
+    world.insert_resource(AnimationEvents::new());
+    let hero = world.spawn();
+    let mut player = AnimationPlayer::new();
+    player.play(AnimationClip::new("attack", ms(500), false).with_event(ms(250), "hit"));
+    world.insert(hero, player);
+
+    assert!(animation::update_animations(&mut world, ms(300)));
+    assert!(!animation::update_animations(&mut world, ms(300)));
+
+    let events = world.resource_mut::<AnimationEvents>().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].entity, events[0].clip.as_str(), events[0].name.as_str()), (hero, "attack", "hit"));
+    assert!(world.resource_mut::<AnimationEvents>().unwrap().take_events().is_empty());
+}
@@ -0,0 +1,96 @@
+// Recording which assets fields and battles need, and checking them against the manifest.
+
+use ps_rpg_engine::{
+    assets::{AssetManifest, AssetServer, MissingAsset},
+    display::{self, WINDOW_ICON},
+    field::{self, FieldDescriptor, FieldMap, FieldProp},
+    model::ModelData,
+    transform::Transform
+};
+
+fn assets() -> AssetServer {
+    AssetServer::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn loads_are_recorded_under_their_owner() {
+    let assets = assets();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(ModelData::load(&assets.for_owner("field/town"), "models/test_prop.gltf")).unwrap();
+    // Loading without an owner isn't recorded.
+    runtime.block_on(ModelData::load(&assets, "models/test_face.gltf")).unwrap();
+    // Even if it fails.
+    assert!(runtime.block_on(assets.for_owner("battle/boss").load_bytes("nothing_here.png")).is_err());
+
+    assert_eq!(assets.dependencies_of("field/town"), vec!["models/test_prop.gltf"]);
+    assert_eq!(assets.dependencies_of("battle/boss"), vec!["nothing_here.png"]);
+    assert!(assets.dependencies_of("field/castle").is_empty());
+}
+
+#[test]
+fn fields_record_what_they_need_before_loading() {
+    let mut fields = FieldMap::new();
+    fields.insert("garden", FieldDescriptor {
+        background: "fields/test_field.png".to_string(),
+        props: vec![FieldProp { model: "models/test_prop.gltf".to_string(), transform: Transform::default(), ..Default::default() }],
+        ..Default::default()
+    });
+    let assets = assets();
+    fields.record_dependencies(&assets);
+    assert_eq!(assets.dependencies_of(&field::owner("garden")), vec!["fields/test_field.png", "models/test_prop.gltf"]);
+}
+
+#[test]
+fn unloading_only_frees_what_nothing_else_needs() {
+    let assets = assets();
+    assets.record_dependency("field/town", "fields/town.png");
+    assets.record_dependency("field/town", "models/villager.gltf");
+    assets.record_dependency("field/inn", "models/villager.gltf");
+
+    assert_eq!(assets.unload("field/town"), vec!["fields/town.png"]);
+    assert!(assets.dependencies_of("field/town").is_empty());
+    assert_eq!(assets.unload("field/inn"), vec!["models/villager.gltf"]);
+    assert!(assets.unload("field/inn").is_empty());
+}
+
+#[test]
+fn manifest_ignores_comments_and_blank_lines() {
+    let manifest = AssetManifest::parse("# Everything\n\nmodels/a.gltf\n  fields/b.png  # the garden\n");
+    assert_eq!(manifest.paths().collect::<Vec<_>>(), vec!["fields/b.png", "models/a.gltf"]);
+    assert!(manifest.contains("models/a.gltf"));
+    assert!(!manifest.contains("# Everything"));
+}
+
+#[test]
+fn report_finds_unused_and_missing_assets() {
+    let assets = assets();
+    let manifest = AssetManifest::parse("fields/town.png\nmodels/villager.gltf\nmodels/old.gltf\n");
+    assets.record_dependency("field/town", "fields/town.png");
+    assets.record_dependency("field/town", "models/villager.gltf");
+    assets.record_dependency("battle/boss", "models/boss.gltf");
+    assets.record_dependency("field/town", "models/boss.gltf");
+
+    let report = assets.report(&manifest);
+    assert_eq!(report.unused, vec!["models/old.gltf"]);
+    assert_eq!(report.missing, vec![
+        MissingAsset { owner: "battle/boss".to_string(), path: "models/boss.gltf".to_string() },
+        MissingAsset { owner: "field/town".to_string(), path: "models/boss.gltf".to_string() }
+    ]);
+}
+
+#[test]
+fn everything_in_the_manifest_exists() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let manifest = AssetManifest::parse(&std::fs::read_to_string(format!("{}/data/assets.manifest", root)).unwrap());
+    assert!(manifest.paths().next().is_some());
+    for path in manifest.paths() {
+        assert!(std::path::Path::new(root).join(path).is_file(), "{} is in the manifest but doesn't exist", path);
+    }
+}
+
+#[test]
+fn the_window_icon_loads() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    assert!(runtime.block_on(display::load_window_icon(&assets(), WINDOW_ICON)).is_ok());
+    assert!(runtime.block_on(display::load_window_icon(&assets(), "nowhere.png")).is_err());
+}
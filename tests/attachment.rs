@@ -0,0 +1,68 @@
+// Entities attached to sockets on other entities.
+
+use cgmath::{Deg, Matrix4, Quaternion, Rotation3, Vector3, InnerSpace};
+
+use ps_rpg_engine::{
+    attachment::{self, Attachment, ModelSockets},
+    transform::Transform,
+    world::World
+};
+
+fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!((actual - expected).magnitude() < 1e-4, "Expected {:?}, got {:?}", expected, actual);
+}
+
+#[test]
+fn attached_entities_follow_their_socket() {
+    let mut world = World::new();
+
+    // A character turned to face along x, with a hand out to their side.
+    let character = world.spawn();
+    world.insert(character, Transform {
+        position: Vector3::new(10.0, 0.0, 0.0),
+        rotation: Quaternion::from_angle_y(Deg(90.0)),
+        scale: Vector3::new(2.0, 2.0, 2.0)
+    });
+    world.insert(character, ModelSockets::new(vec![
+        ("hand_r".to_string(), Matrix4::from_translation(Vector3::new(0.5, 1.0, 0.0)))
+    ]));
+
+    let sword = world.spawn();
+    world.insert(sword, Attachment::new(character, "hand_r"));
+
+    // A flame on the end of the sword, attached to an attachment.
+    let flame = world.spawn();
+    world.insert(flame, Attachment {
+        parent: sword,
+        socket: "tip".to_string(),
+        offset: Transform::from_position(Vector3::new(0.0, 0.0, 1.0))
+    });
+
+    attachment::update_attachments(&mut world);
+    let sword_transform = *world.get::<Transform>(sword).unwrap();
+    assert_near(sword_transform.position, Vector3::new(10.0, 2.0, -1.0));
+    assert_near(sword_transform.scale, Vector3::new(2.0, 2.0, 2.0));
+    // The sword has no "tip", so the flame is offset from its origin.
+    assert_near(world.get::<Transform>(flame).unwrap().position, Vector3::new(12.0, 2.0, -1.0));
+
+    // Moving the character takes everything with it on the next update.
+    world.get_mut::<Transform>(character).unwrap().position.y = 5.0;
+    attachment::update_attachments(&mut world);
+    assert_near(world.get::<Transform>(flame).unwrap().position, Vector3::new(12.0, 7.0, -1.0));
+}
+
+#[test]
+fn attachments_to_despawned_entities_stay_put() {
+    let mut world = World::new();
+    let parent = world.spawn();
+    world.insert(parent, Transform::from_position(Vector3::new(1.0, 2.0, 3.0)));
+    let child = world.spawn();
+    world.insert(child, Attachment::new(parent, "missing"));
+
+    attachment::update_attachments(&mut world);
+    assert_near(world.get::<Transform>(child).unwrap().position, Vector3::new(1.0, 2.0, 3.0));
+
+    world.despawn(parent);
+    attachment::update_attachments(&mut world);
+    assert_near(world.get::<Transform>(child).unwrap().position, Vector3::new(1.0, 2.0, 3.0));
+}
@@ -0,0 +1,108 @@
+// Auto battle rules and speeds.
+
+use std::time::Duration;
+
+use cgmath::Point3;
+
+use ps_rpg_engine::{
+    auto_battle::{self, AutoBattle, AutoCommand, EnemyPick, Gambit, GambitAction, GambitCondition},
+    battle_ui::{Combatant, Side},
+    inventory::{Inventory, Item, ItemEffect},
+    party::{PartyMember, Skill, Stats, TargetType}
+};
+
+fn combatant(name: &str, side: Side, knocked_out: bool) -> Combatant {
+    Combatant { name: name.to_string(), side, speed: 10, position: Point3::new(0.0, 0.0, 0.0), knocked_out, summoned: false }
+}
+
+fn stats(hp: u32, max_hp: u32) -> Stats {
+    Stats { hp, max_hp, mp: 10, ..Default::default() }
+}
+
+// A healer, a fighter who's hurt or not, and two slimes.
+fn battle(fighter_hp: u32) -> (Vec<Combatant>, Vec<Stats>) {
+    let combatants = vec![
+        combatant("Tobin", Side::Party, false),
+        combatant("Aria", Side::Party, fighter_hp == 0),
+        combatant("Slime", Side::Enemies, false),
+        combatant("Big Slime", Side::Enemies, false)
+    ];
+    (combatants, vec![stats(90, 90), stats(fighter_hp, 150), stats(20, 40), stats(80, 80)])
+}
+
+fn healer(gambits: &[&str]) -> PartyMember {
+    let mut tobin = PartyMember::new("Tobin", stats(90, 90));
+    tobin.skills.push(Skill { name: "Cure".to_string(), description: String::new(), mp_cost: 4, costs: Vec::new(), target: TargetType::Ally, field: None });
+    tobin.gambits = gambits.iter().map(|gambit| gambit.parse().unwrap()).collect();
+    tobin
+}
+
+fn command(action: GambitAction, targets: &[usize]) -> Option<AutoCommand> {
+    Some(AutoCommand { action, targets: targets.to_vec() })
+}
+
+#[test]
+fn gambits_are_read() {
+    let gambit: Gambit = "ally hp < 30%: skill Cure".parse().unwrap();
+    assert_eq!(gambit, Gambit { condition: GambitCondition::AllyHpBelow(30), action: GambitAction::Skill("Cure".to_string()) });
+    assert_eq!(gambit.to_string(), "ally hp < 30: skill Cure");
+    assert_eq!("always: attack".parse::<Gambit>().map(|gambit| gambit.action), Ok(GambitAction::Attack(EnemyPick::First)));
+
+    assert!("attack weakest".parse::<Gambit>().unwrap_err().contains("condition: action"));
+    assert!("sometimes: attack".parse::<Gambit>().unwrap_err().contains("Unknown condition"));
+    assert!("always: attack biggest".parse::<Gambit>().unwrap_err().contains("Unknown target"));
+}
+
+#[test]
+fn hurt_allies_are_healed_before_attacking() {
+    let tobin = healer(&["ally hp < 30: skill Cure", "always: attack weakest"]);
+    let inventory = Inventory::new();
+
+    let (combatants, stats) = battle(150);
+    assert_eq!(auto_battle::decide(0, &tobin, &combatants, &stats, &inventory), command(GambitAction::Attack(EnemyPick::Weakest), &[2]));
+
+    let (combatants, stats) = battle(30);
+    assert_eq!(auto_battle::decide(0, &tobin, &combatants, &stats, &inventory), command(GambitAction::Skill("Cure".to_string()), &[1]));
+
+    // Without the MP for it, it's on to the next rule.
+    let mut tired = tobin.clone();
+    tired.stats.mp = 0;
+    assert_eq!(auto_battle::decide(0, &tired, &combatants, &stats, &inventory), command(GambitAction::Attack(EnemyPick::Weakest), &[2]));
+}
+
+#[test]
+fn items_are_only_used_while_there_are_some() {
+    let tobin = healer(&["ally down: item phoenix_down", "always: attack strongest"]);
+    let (combatants, stats) = battle(0);
+    let mut inventory = Inventory::new();
+    assert_eq!(auto_battle::decide(0, &tobin, &combatants, &stats, &inventory), command(GambitAction::Attack(EnemyPick::Strongest), &[3]));
+
+    let phoenix_down = Item { id: "phoenix_down".to_string(), name: "Phoenix Down".to_string(), description: String::new(), effect: ItemEffect::Revive { hp: 10 } };
+    inventory.add(&phoenix_down, 1);
+    assert_eq!(auto_battle::decide(0, &tobin, &combatants, &stats, &inventory), command(GambitAction::Item("phoenix_down".to_string()), &[1]));
+}
+
+#[test]
+fn nothing_is_done_with_no_one_to_fight() {
+    let tobin = healer(&["always: attack weakest"]);
+    let (mut combatants, stats) = battle(150);
+    combatants.truncate(2);
+    assert_eq!(auto_battle::decide(0, &tobin, &combatants, &stats, &Inventory::new()), None);
+}
+
+#[test]
+fn the_whole_party_can_go_on_auto_and_speed_up() {
+    let mut auto = AutoBattle::new();
+    let mut tobin = healer(&[]);
+    assert!(!auto.is_auto(&tobin));
+    tobin.auto_battle = true;
+    assert!(auto.is_auto(&tobin));
+    tobin.auto_battle = false;
+    auto.party = true;
+    assert!(auto.is_auto(&tobin));
+
+    assert_eq!(auto.scale(Duration::from_millis(10)), Duration::from_millis(10));
+    assert_eq!((auto.next_speed(), auto.next_speed(), auto.next_speed()), (2, 4, 1));
+    auto.speed = 4;
+    assert_eq!(auto.scale(Duration::from_millis(10)), Duration::from_millis(40));
+}
@@ -0,0 +1,78 @@
+// What battles report once they're over, for quests and scripts.
+
+use ps_rpg_engine::{
+    battle_report::{self, BattleReport, BattleReports},
+    flags::GameFlags,
+    party::{Skill, TargetType},
+    play_stats::PlayStats,
+    telemetry::BattleOutcome,
+    world::World
+};
+
+fn skill(name: &str, mp_cost: u32) -> Skill {
+    Skill { name: name.to_string(), description: String::new(), mp_cost, costs: Vec::new(), target: TargetType::AllEnemies, field: None }
+}
+
+fn bandits() -> BattleReport {
+    let mut report = BattleReport::new("bandits");
+    for _ in 0..3 {
+        report.end_turn();
+    }
+    report.defeat("bandit");
+    report.defeat("bandit");
+    report.defeat("bandit_leader");
+    report
+}
+
+#[test]
+fn reports_go_in_flags() {
+    let mut flags = GameFlags::new();
+    let mut report = bandits();
+    report.use_item("potion");
+    report.use_skill(&skill("Fire", 4));
+    report.finish(BattleOutcome::Won).apply(&mut flags);
+    bandits().finish(BattleOutcome::Fled).apply(&mut flags);
+
+    assert_eq!(flags.get("battle.won"), 1);
+    assert_eq!(flags.get("battle.fled"), 1);
+    assert_eq!(flags.get("defeated.bandit"), 4);
+    assert_eq!(flags.get("defeated.bandit_leader"), 2);
+    // The last one's.
+    assert_eq!(flags.get("battle.last.outcome"), 3);
+    assert_eq!(flags.get("battle.last.turns"), 3);
+    assert_eq!(flags.get("battle.last.items"), 0);
+    assert_eq!(flags.get("battle.last.magic"), 0);
+}
+
+#[test]
+fn only_skills_that_cost_mp_are_magic() {
+    let mut report = bandits();
+    report.use_skill(&skill("Cleave", 0));
+    let events = report.finish(BattleOutcome::Won).events();
+    assert!(events.contains(&"battle.won".to_string()));
+    assert!(events.contains(&"battle.won.bandits".to_string()));
+    assert!(events.contains(&"battle.defeated.bandit_leader".to_string()));
+    assert!(events.contains(&"battle.won_without_magic".to_string()));
+    assert!(events.contains(&"battle.won_without_items".to_string()));
+
+    let mut report = bandits();
+    report.use_skill(&skill("Fire", 4));
+    assert!(!report.finish(BattleOutcome::Won).events().contains(&"battle.won_without_magic".to_string()));
+    // Losing isn't winning without anything.
+    assert!(!bandits().finish(BattleOutcome::Lost).events().contains(&"battle.won_without_items".to_string()));
+}
+
+#[test]
+fn recorded_reports_are_kept_until_taken() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::new());
+    world.insert_resource(PlayStats::new());
+    battle_report::record(&mut world, bandits().finish(BattleOutcome::Won));
+
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("defeated.bandit_leader"), 1);
+    assert_eq!(world.resource::<PlayStats>().unwrap().battles_won, 1);
+    let reports = world.resource_mut::<BattleReports>().unwrap().take_reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].turns, 3);
+    assert!(world.resource_mut::<BattleReports>().unwrap().take_reports().is_empty());
+}
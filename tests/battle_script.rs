@@ -0,0 +1,95 @@
+// Battle scripts and the triggers that run them.
+
+use ps_rpg_engine::{
+    battle_script::{BattleAction, BattleDirector, BattleScript, BattleScripts, BattleStat, BattleTrigger},
+    formation::{EncounterTable, Formation, Formations, Row},
+    party::Stats
+};
+
+const SCRIPTS: &str = "
+# For testing.
+[ogre]
+on = hp ogre 50
+say = Ogre: You'll pay for that!
+phase = ogre enraged
+stat = ogre strength 150%
+spawn = goblin 2 front
+
+on = turn 3
+backdrop = battles/collapse.png
+music = music/escape.cfg
+";
+
+fn scripts() -> Vec<BattleScript> {
+    BattleScript::parse_list(SCRIPTS).unwrap()
+}
+
+fn hp(hp: u32) -> Stats {
+    Stats { hp, max_hp: 200, strength: 20, ..Default::default() }
+}
+
+#[test]
+fn scripts_are_read() {
+    let script = &scripts()[0];
+    assert_eq!(script.formation, "ogre");
+    assert_eq!(script.rules[0].trigger, BattleTrigger::Hp { enemy: "ogre".to_string(), percent: 50 });
+    assert_eq!(script.rules[0].actions[2], BattleAction::Stat { enemy: "ogre".to_string(), stat: BattleStat::Strength, percent: 150 });
+    match &script.rules[0].actions[3] {
+        BattleAction::Spawn(enemy) => assert_eq!((enemy.enemy.as_str(), enemy.count, enemy.row), ("goblin", (2, 2), Row::Front)),
+        action => panic!("Expected a spawn, not {:?}", action)
+    }
+    assert_eq!(script.rules[1].trigger, BattleTrigger::Turn(3));
+
+    assert!(BattleScript::parse_list("[ogre]\nsay = Hi").unwrap_err().contains("\"on\" trigger first"));
+    assert!(BattleScript::parse_list("[ogre]\non = weather rain").unwrap_err().contains("Line 2"));
+    assert!(BattleScript::parse_list("[ogre]\non = turn 1\nstat = ogre luck 200").unwrap_err().contains("Unknown stat"));
+}
+
+#[test]
+fn scripts_have_to_be_for_real_formations() {
+    let formations = Formations::new(Formation::parse_list("[goblins]\nenemy = goblin 2 front").unwrap(), EncounterTable::parse_list("").unwrap()).unwrap();
+    assert_eq!(BattleScripts::new(scripts(), &formations).unwrap_err(), "There's a battle script for ogre, which isn't a formation");
+}
+
+#[test]
+fn hp_triggers_pause_the_battle_until_everything_has_happened() {
+    let script = &scripts()[0];
+    let mut director = BattleDirector::new(Some(script));
+    let (healthy, hurt) = (hp(150), hp(100));
+    assert!(!director.check(1, &[("ogre", &healthy)]));
+    assert!(!director.is_paused());
+
+    assert!(director.check(2, &[("goblin", &hp(0)), ("ogre", &hurt)]));
+    assert!(director.is_paused());
+    assert_eq!(director.phase("ogre"), Some("enraged"));
+    assert_eq!(director.next_action(), Some(BattleAction::Say("Ogre: You'll pay for that!".to_string())));
+    assert_eq!(director.next_action().map(|action| matches!(action, BattleAction::Phase { .. })), Some(true));
+    director.next_action();
+    director.next_action();
+    assert!(!director.is_paused());
+
+    // Each trigger only goes off once.
+    assert!(!director.check(2, &[("ogre", &hp(10))]));
+}
+
+#[test]
+fn turn_triggers_go_off_on_their_turn() {
+    let script = &scripts()[0];
+    let mut director = BattleDirector::new(Some(script));
+    assert!(!director.check(2, &[]));
+    assert!(director.check(3, &[]));
+    assert_eq!(director.next_action(), Some(BattleAction::Backdrop("battles/collapse.png".to_string())));
+    assert_eq!(director.next_action(), Some(BattleAction::Music("music/escape.cfg".to_string())));
+
+    // Battles without a script never stop.
+    let mut director = BattleDirector::new(None);
+    assert!(!director.check(100, &[("ogre", &hp(0))]));
+}
+
+#[test]
+fn stat_changes_scale_the_stat() {
+    let mut stats = hp(200);
+    BattleStat::Strength.apply(&mut stats, 150);
+    BattleStat::Speed.apply(&mut stats, 50);
+    assert_eq!((stats.strength, stats.speed, stats.hp), (30, 0, 200));
+}
@@ -0,0 +1,75 @@
+// Going into battles: picking the transition, and only showing the battle once both the effect's
+// done and its backdrop and music have loaded.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    assets::{AssetError, AssetServer},
+    battle_scene::BattleScene,
+    battle_transition::{BattleAssets, BattleTransition, ScreenTransition, TransitionStyle, TRANSITION_TIME},
+    formation::BattleSetup,
+    renderer::PostProcessSettings
+};
+
+fn setup(can_escape: bool) -> BattleSetup {
+    BattleSetup {
+        formation: "slimes".to_string(),
+        enemies: Vec::new(),
+        intro: None,
+        can_escape,
+        back_attack: false,
+        scene: BattleScene { backdrop: Some("fields/test_field.png".to_string()), music: None }
+    }
+}
+
+#[test]
+fn transitions_are_picked_by_name_or_battle() {
+    for style in TransitionStyle::ALL {
+        assert_eq!(style.name().parse::<TransitionStyle>(), Ok(style));
+    }
+    assert_eq!(" Shatter ".parse::<TransitionStyle>(), Ok(TransitionStyle::Shatter));
+    assert!("wipe".parse::<TransitionStyle>().is_err());
+
+    // Bosses, that can't be run from, get the dramatic one.
+    assert_eq!(TransitionStyle::for_battle(&setup(true)), TransitionStyle::Swirl);
+    assert_eq!(TransitionStyle::for_battle(&setup(false)), TransitionStyle::Shatter);
+}
+
+#[test]
+fn battles_wait_for_the_effect_and_loading() {
+    let mut settings = PostProcessSettings::default();
+    let mut transition = BattleTransition::start(setup(true), TransitionStyle::Pixelate, &mut settings);
+    assert_eq!(settings.transition, Some(ScreenTransition { style: TransitionStyle::Pixelate, progress: 0.0 }));
+
+    assert!(transition.update(TRANSITION_TIME / 2, &mut settings));
+    assert_eq!(settings.transition.map(|transition| transition.progress), Some(0.5));
+    assert!(!transition.is_ready());
+
+    // Loading that's quicker than the effect still waits for it.
+    transition.loaded(Ok(BattleAssets::default()));
+    assert!(!transition.is_ready());
+    assert!(transition.update(TRANSITION_TIME, &mut settings));
+    assert_eq!(transition.progress(), 1.0);
+    assert!(transition.is_ready());
+    assert!(!transition.update(Duration::from_millis(16), &mut settings));
+
+    // And the other way round, with loading that failed not holding it up.
+    let mut transition = BattleTransition::start(setup(true), TransitionStyle::Swirl, &mut settings);
+    transition.update(TRANSITION_TIME * 2, &mut settings);
+    assert!(!transition.is_ready());
+    transition.loaded(Err(AssetError::Fetch("music/battle.music".to_string(), "Not found".to_string())));
+    assert!(transition.is_ready() && !transition.is_revealed());
+}
+
+#[test]
+fn battle_assets_load_from_the_scene() {
+    let assets = AssetServer::new(env!("CARGO_MANIFEST_DIR"));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let loaded = runtime.block_on(BattleAssets::load(&assets, &setup(true).scene)).unwrap();
+    assert!(loaded.backdrop.is_some());
+    assert!(loaded.music.is_none());
+
+    let missing = BattleScene { backdrop: Some("fields/nowhere.png".to_string()), music: None };
+    assert!(runtime.block_on(BattleAssets::load(&assets, &missing)).is_err());
+}
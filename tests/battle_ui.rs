@@ -0,0 +1,180 @@
+// Turn order, target picking and skill sequences for battles.
+
+use std::time::Duration;
+
+use cgmath::Point3;
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    battle_sequence::{GroupBounds, SkillSequence, TargetHit, STAGGER},
+    battle_ui::{self, Combatant, Side, TargetChoice, TargetCursor, TurnOrder},
+    camera::Camera,
+    combat::{CombatConfig, HitResult, Outcome, StandardRules},
+    party::TargetType,
+    pointer::PointerEvent,
+    renderer::{SCREEN_WIDTH, SCREEN_HEIGHT},
+    timed_hit::{TimedGrade, TimedHit, TimedWindow},
+    ui::UiBatch
+};
+
+fn combatant(name: &str, side: Side, speed: u32, x: f32) -> Combatant {
+    let z = if side == Side::Party { 3.0 } else { -3.0 };
+    Combatant { name: name.to_string(), side, speed, position: Point3::new(x, 0.0, z), knocked_out: false, summoned: false }
+}
+
+fn combatants() -> Vec<Combatant> {
+    vec![
+        combatant("Aria", Side::Party, 10, -1.0),
+        combatant("Tobin", Side::Party, 5, 1.0),
+        combatant("Slime", Side::Enemies, 10, -1.5),
+        combatant("Bat", Side::Enemies, 20, 1.5)
+    ]
+}
+
+// Looking at the battlefield from behind the party.
+fn camera() -> Camera {
+    Camera { eye: Point3::new(0.0, 4.0, 10.0), target: Point3::new(0.0, 1.0, 0.0), ..Default::default() }
+}
+
+#[test]
+fn faster_combatants_act_more_often() {
+    let combatants = combatants();
+    let mut order = TurnOrder::new(&combatants);
+    // The bat's twice as fast as anyone else, and ties go to whoever's first.
+    let preview = order.preview(&combatants, 6);
+    assert_eq!(preview, [3, 0, 2, 3, 3, 0]);
+    // Previewing doesn't take any turns.
+    assert_eq!(order.next(&combatants), Some(3));
+    assert_eq!(order.preview(&combatants, 2), [0, 2]);
+}
+
+#[test]
+fn knocked_out_combatants_are_skipped() {
+    let mut combatants = combatants();
+    combatants[3].knocked_out = true;
+    let order = TurnOrder::new(&combatants);
+    assert!(!order.preview(&combatants, 10).contains(&3));
+
+    for combatant in &mut combatants {
+        combatant.knocked_out = true;
+    }
+    assert!(order.preview(&combatants, 3).is_empty());
+}
+
+#[test]
+fn targets_depend_on_the_skill() {
+    let mut combatants = combatants();
+    combatants[1].knocked_out = true;
+    let valid = |target, combatants: &[Combatant]| TargetCursor::new(target, 0, combatants).map(|cursor| cursor.valid().to_vec());
+    assert_eq!(valid(TargetType::Enemy, &combatants), Some(vec![2, 3]));
+    // Knocked out allies can still be picked, to be brought round.
+    assert_eq!(valid(TargetType::Ally, &combatants), Some(vec![0, 1]));
+    assert_eq!(valid(TargetType::User, &combatants), Some(vec![0]));
+
+    combatants[2].knocked_out = true;
+    combatants[3].knocked_out = true;
+    assert_eq!(valid(TargetType::AllEnemies, &combatants), None);
+}
+
+#[test]
+fn the_cursor_cycles_and_confirms() {
+    let combatants = combatants();
+    let mut cursor = TargetCursor::new(TargetType::Enemy, 0, &combatants).unwrap();
+    assert_eq!(cursor.targets(), [2]);
+    cursor.handle_key(VirtualKeyCode::Right);
+    assert_eq!(cursor.targets(), [3]);
+    // Round to the first again.
+    cursor.handle_key(VirtualKeyCode::Right);
+    cursor.handle_key(VirtualKeyCode::Left);
+    assert_eq!(cursor.handle_key(VirtualKeyCode::Return), Some(TargetChoice::Picked(vec![3])));
+    assert_eq!(cursor.handle_key(VirtualKeyCode::Escape), Some(TargetChoice::Cancelled));
+
+    let cursor = TargetCursor::new(TargetType::AllEnemies, 1, &combatants).unwrap();
+    assert_eq!(cursor.targets(), [2, 3]);
+}
+
+#[test]
+fn clicking_near_someone_picks_them() {
+    let (combatants, camera) = (combatants(), camera());
+    let mut cursor = TargetCursor::new(TargetType::Enemy, 0, &combatants).unwrap();
+    let head = Point3::new(1.5, 1.8, -3.0);
+    let (x, y) = camera.to_screen(head, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32).unwrap();
+    assert!(x > SCREEN_WIDTH as f32 / 2.0 && y < SCREEN_HEIGHT as f32 / 2.0);
+
+    assert_eq!(cursor.handle_pointer(PointerEvent::Click { x: 0.0, y: 0.0 }, &camera, &combatants), None);
+    assert_eq!(cursor.handle_pointer(PointerEvent::Click { x: x + 5.0, y }, &camera, &combatants), Some(TargetChoice::Picked(vec![3])));
+}
+
+#[test]
+fn widgets_draw_something() {
+    let (combatants, camera) = (combatants(), camera());
+    let accessibility = Accessibility::new();
+    let mut batch = UiBatch::new();
+    let turns = TurnOrder::new(&combatants).preview(&combatants, battle_ui::PREVIEW_TURNS);
+    battle_ui::build_turn_order(&mut batch, &accessibility, &combatants, &turns);
+    assert!(!batch.is_empty());
+
+    let mut batch = UiBatch::new();
+    TargetCursor::new(TargetType::Ally, 0, &combatants).unwrap().build(&mut batch, &accessibility, &camera, &combatants);
+    assert!(!batch.is_empty());
+}
+
+fn hit(target: usize, damage: u32) -> TargetHit {
+    TargetHit { target, result: HitResult { outcome: Outcome::Hit, damage, blocked: false, countered: false } }
+}
+
+#[test]
+fn group_effects_cover_everyone_hit() {
+    let combatants = combatants();
+    let one = GroupBounds::new(&[2], &combatants).unwrap();
+    assert_eq!((one.centre, one.effect_scale()), (Point3::new(-1.5, 0.0, -3.0), 1.0));
+
+    let both = GroupBounds::new(&[2, 3], &combatants).unwrap();
+    assert_eq!(both.centre, Point3::new(0.0, 0.0, -3.0));
+    assert_eq!(both.effect_scale(), 2.5);
+    assert_eq!(GroupBounds::new(&[], &combatants), None);
+}
+
+#[test]
+fn hits_on_a_group_land_one_after_another() {
+    let combatants = combatants();
+    // The bat's on the right, so it's hit second.
+    let mut sequence = SkillSequence::new(vec![hit(3, 12), hit(2, 30)], &combatants);
+    assert_eq!(sequence.effect().map(|effect| effect.centre), Some(Point3::new(0.0, 0.0, -3.0)));
+
+    // Nothing lands until the user's animation gets to the hit.
+    assert!(sequence.update(Duration::from_secs(1)).is_empty());
+    sequence.start();
+    assert_eq!(sequence.update(Duration::ZERO), [hit(2, 30)]);
+    assert!(sequence.update(STAGGER / 2).is_empty());
+    assert_eq!(sequence.update(STAGGER / 2), [hit(3, 12)]);
+
+    let mut batch = UiBatch::new();
+    sequence.build(&mut batch, &Accessibility::new(), &camera(), &combatants);
+    assert!(!batch.is_empty());
+    assert!(!sequence.is_finished());
+    sequence.update(Duration::from_secs(1));
+    assert!(sequence.is_finished());
+}
+
+#[test]
+fn timed_hits_wait_to_be_graded() {
+    let combatants = combatants();
+    let mut sequence = SkillSequence::new(vec![hit(2, 30)], &combatants);
+    let window = TimedWindow::default();
+    sequence.request_timed_hit(TimedHit::new(window, &StandardRules(CombatConfig::default())));
+    sequence.update(window.lead);
+    sequence.start();
+    // Not pressed yet, so there's still time to.
+    assert!(sequence.update(Duration::ZERO).is_empty());
+    assert!(sequence.press());
+
+    assert_eq!(sequence.timed_grade(), Some(TimedGrade::Perfect));
+    assert_eq!(sequence.update(Duration::ZERO), [hit(2, 60)]);
+    let mut batch = UiBatch::new();
+    sequence.build(&mut batch, &Accessibility::new(), &camera(), &combatants);
+    assert!(!batch.is_empty());
+    sequence.update(Duration::from_secs(1));
+    assert!(sequence.is_finished());
+}
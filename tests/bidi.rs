@@ -0,0 +1,64 @@
+// Right to left text, Arabic letters joining up, and mirrored menus.
+
+use ps_rpg_engine::{
+    bidi::{self, LanguageLayout, TextDirection},
+    pointer::PointerEvent,
+    renderer::SCREEN_WIDTH,
+    ui::UiBatch
+};
+
+#[test]
+fn right_to_left_runs_are_reversed() {
+    // Hebrew on its own, and inside English, with its spaces.
+    assert_eq!(bidi::visual_order("שלום", TextDirection::RightToLeft), "םולש");
+    assert_eq!(bidi::visual_order("Say שלום עולם now", TextDirection::LeftToRight), "Say םלוע םולש now");
+    // Numbers and English inside right to left text stay the way round they are.
+    assert_eq!(bidi::visual_order("שלום HP 100", TextDirection::RightToLeft), "HP 100 םולש");
+    // Brackets are swapped so they still open and close the right way.
+    assert_eq!(bidi::visual_order("(שלום)", TextDirection::RightToLeft), "(םולש)");
+    // Left to right text on its own is left alone.
+    assert_eq!(bidi::visual_order("Hello (world)", TextDirection::LeftToRight), "Hello (world)");
+}
+
+#[test]
+fn arabic_letters_join() {
+    // Beh, then teh, then beh: initial, medial, final.
+    assert_eq!(bidi::shape("\u{0628}\u{062A}\u{0628}"), "\u{FE91}\u{FE98}\u{FE90}");
+    // Alef doesn't join onwards, so the beh after it starts again.
+    assert_eq!(bidi::shape("\u{0628}\u{0627}\u{0628}"), "\u{FE91}\u{FE8E}\u{FE8F}");
+    // Lam alef is one letter.
+    assert_eq!(bidi::shape("\u{0644}\u{0627}"), "\u{FEFB}");
+    assert_eq!(bidi::shape("\u{0628}\u{0644}\u{0627}"), "\u{FE91}\u{FEFC}");
+    assert_eq!(bidi::shape("Hello"), "Hello");
+}
+
+#[test]
+fn languages_pick_their_layout() {
+    let layouts = LanguageLayout::parse_list("# Layouts\n[ar]\ndirection = rtl\nmirror_menus = true\n\n[he]\ndirection = RTL\n").unwrap();
+    assert_eq!(layouts[0], LanguageLayout { language: "ar".to_string(), direction: TextDirection::RightToLeft, mirror_menus: true });
+    assert_eq!(LanguageLayout::pick(&layouts, "ar-EG").direction, TextDirection::RightToLeft);
+    assert!(!LanguageLayout::pick(&layouts, "he").mirror_menus);
+    assert_eq!(LanguageLayout::pick(&layouts, "en"), LanguageLayout::new("en"));
+
+    assert!(LanguageLayout::parse_list("direction = rtl").unwrap_err().contains("[language]"));
+    assert!(LanguageLayout::parse_list("[ar]\ndirection = up").unwrap_err().contains("Unknown text direction"));
+    assert!(LanguageLayout::parse_list("[ar]\nmirror_menus = maybe").unwrap_err().contains("true or false"));
+    assert_eq!("rtl".parse::<TextDirection>(), Ok(TextDirection::RightToLeft));
+    assert_eq!(TextDirection::LeftToRight.to_string(), "ltr");
+}
+
+#[test]
+fn mirrored_menus_read_from_the_right() {
+    let mut batch = UiBatch::new();
+    batch.set_mirrored(true);
+    // Still as wide, just lined up on the other side.
+    let width = batch.text(10.0, 10.0, 1.0, "Items", [1.0; 4]);
+    assert_eq!(width, UiBatch::measure_text(1.0, "Items").0);
+    assert!(batch.is_mirrored());
+
+    // Clicks come back to where the menu thinks it drew things.
+    let click = PointerEvent::Click { x: 20.0, y: 30.0 };
+    assert_eq!(click.mirrored(), PointerEvent::Click { x: SCREEN_WIDTH as f32 - 20.0, y: 30.0 });
+    assert_eq!(click.mirrored().mirrored(), click);
+    assert_eq!(PointerEvent::Cancel.mirrored(), PointerEvent::Cancel);
+}
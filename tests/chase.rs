@@ -0,0 +1,79 @@
+// NPCs finding their way to whoever they're chasing.
+
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Vector3};
+
+use ps_rpg_engine::{
+    chase::{self, Catch, CatchEvents, Chaser, CELL_SIZE},
+    field::FieldDescriptor,
+    transform::Transform,
+    vision::VisionBlocker,
+    world::{Entity, World}
+};
+
+// A wall across z = 0 from x = -2 to 2, with the way round either end.
+const WALL: VisionBlocker = VisionBlocker { min: [-2.0, -0.25], max: [2.0, 0.25] };
+
+fn at(world: &mut World, x: f32, z: f32) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(x, 0.0, z)));
+    entity
+}
+
+#[test]
+fn paths_go_round_whatever_is_in_the_way() {
+    let (from, to) = (Vector3::new(0.0, 0.0, -2.0), Vector3::new(0.2, 0.0, 2.0));
+    let path = chase::find_path(from, to, CELL_SIZE, |x, z| WALL.contains(x, z)).unwrap();
+    assert_eq!(path.last(), Some(&to));
+    assert!(path.iter().any(|point| point.x.abs() > 2.0));
+
+    // Nothing in the way goes straight there.
+    assert_eq!(chase::find_path(from, to, CELL_SIZE, |_, _| false).unwrap(), [to]);
+
+    // Nor anywhere there's no way to, like inside a box.
+    let walled_in = |x: f32, z: f32| (x.abs() - 3.0).abs() < 0.3 || (z.abs() - 3.0).abs() < 0.3;
+    assert_eq!(chase::find_path(from, Vector3::new(10.0, 0.0, 10.0), CELL_SIZE, walled_in), None);
+}
+
+#[test]
+fn chasers_catch_up() {
+    let mut world = World::new();
+    world.insert_resource(FieldDescriptor { blockers: vec![WALL], ..Default::default() });
+    let guard = at(&mut world, 0.0, -2.0);
+    let player = at(&mut world, 0.0, 2.0);
+    let mut chaser = Chaser::new(player, 4.0);
+    chaser.battle = Some("guards".to_string());
+    world.insert(guard, chaser);
+
+    let mut chasing = true;
+    for _ in 0..100 {
+        chasing = chase::update_chasers(&mut world, Duration::from_millis(50));
+        let position = world.get::<Transform>(guard).unwrap().position;
+        assert!(!WALL.contains(position.x, position.z));
+        if !chasing {
+            break;
+        }
+    }
+    assert!(!chasing && !world.has::<Chaser>(guard));
+    let position = world.get::<Transform>(guard).unwrap().position;
+    assert!((position - Vector3::new(0.0, 0.0, 2.0)).magnitude() <= chase::DEFAULT_CATCH_RADIUS);
+    assert_eq!(world.resource_mut::<CatchEvents>().unwrap().take_events(), [Catch { chaser: guard, target: player, battle: Some("guards".to_string()) }]);
+}
+
+#[test]
+fn chasers_keep_up_with_a_moving_target() {
+    let mut world = World::new();
+    let guard = at(&mut world, 0.0, 0.0);
+    let player = at(&mut world, 5.0, 0.0);
+    world.insert(guard, Chaser::new(player, 2.0));
+    chase::update_chasers(&mut world, Duration::from_millis(100));
+    assert_eq!(world.get::<Chaser>(guard).unwrap().path(), [Vector3::new(5.0, 0.0, 0.0)]);
+
+    // The player runs off the other way, which the guard notices when it next finds its path.
+    world.get_mut::<Transform>(player).unwrap().position = Vector3::new(0.0, 0.0, 5.0);
+    chase::update_chasers(&mut world, Duration::from_millis(100));
+    assert_eq!(world.get::<Chaser>(guard).unwrap().path(), [Vector3::new(5.0, 0.0, 0.0)]);
+    chase::update_chasers(&mut world, chase::REPATH_TIME);
+    assert_eq!(world.get::<Chaser>(guard).unwrap().path(), [Vector3::new(0.0, 0.0, 5.0)]);
+}
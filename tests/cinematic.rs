@@ -0,0 +1,50 @@
+// Cutscene letterboxing, and the HUD staying hidden until it's gone.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    cinematic::{Cinematic, BAR_HEIGHT, BAR_TIME},
+    ui::UiBatch
+};
+
+#[test]
+fn bars_slide_in_and_out() {
+    let mut cinematic = Cinematic::new();
+    assert!(!cinematic.update(Duration::from_millis(16)));
+    let mut batch = UiBatch::new();
+    cinematic.build(&mut batch);
+    assert!(batch.is_empty());
+
+    // The player's locked out straight away, the bars take a moment.
+    cinematic.start();
+    assert!(cinematic.is_playing() && cinematic.hides_hud());
+    assert!(cinematic.update(BAR_TIME / 2));
+    assert!(cinematic.bar_height() > 0.0 && cinematic.bar_height() < BAR_HEIGHT);
+    assert!(cinematic.update(BAR_TIME));
+    assert_eq!(cinematic.bar_height(), BAR_HEIGHT);
+    assert!(!cinematic.update(Duration::from_millis(16)));
+    cinematic.build(&mut batch);
+    assert!(!batch.is_empty());
+
+    // Ending it lets the player go at once, and the HUD's back once the bars are.
+    cinematic.end();
+    assert!(!cinematic.is_playing() && cinematic.hides_hud());
+    assert!(cinematic.update(BAR_TIME / 2));
+    assert!(cinematic.hides_hud());
+    assert!(cinematic.update(BAR_TIME));
+    assert!(!cinematic.hides_hud());
+    assert_eq!(cinematic.bar_height(), 0.0);
+}
+
+#[test]
+fn starting_again_part_way_out_carries_on_from_there() {
+    let mut cinematic = Cinematic::new();
+    cinematic.start();
+    cinematic.update(BAR_TIME);
+    cinematic.end();
+    cinematic.update(BAR_TIME / 4);
+    let height = cinematic.bar_height();
+    cinematic.start();
+    cinematic.update(Duration::from_millis(16));
+    assert!(cinematic.bar_height() > height);
+}
@@ -0,0 +1,41 @@
+// Colour blindness filters.
+
+use cgmath::{Matrix3, SquareMatrix, Vector3};
+
+use ps_rpg_engine::color_filter::{ColorBlindness, ColorFilter};
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a.x - b.x).abs() < 0.01 && (a.y - b.y).abs() < 0.01 && (a.z - b.z).abs() < 0.01
+}
+
+#[test]
+fn filters_keep_greys() {
+    assert_eq!(ColorFilter::None.matrix(), Matrix3::identity());
+    let grey = Vector3::new(0.5, 0.5, 0.5);
+    for filter in ColorFilter::ALL {
+        assert!(close(filter.matrix() * grey, grey), "{} changed grey to {:?}", filter, filter.matrix() * grey);
+    }
+}
+
+#[test]
+fn correction_separates_red_and_green() {
+    let (red, green) = (Vector3::new(0.8, 0.2, 0.0), Vector3::new(0.2, 0.8, 0.0));
+    let simulate = ColorFilter::Simulate(ColorBlindness::Deuteranopia).matrix();
+    let correct = ColorFilter::Correct(ColorBlindness::Deuteranopia).matrix();
+
+    // How far apart the two look to someone with deuteranopia, with and without correction.
+    let apart = |a: Vector3<f32>, b: Vector3<f32>| {
+        let d = simulate * a - simulate * b;
+        (d.x * d.x + d.y * d.y + d.z * d.z).sqrt()
+    };
+    assert!(apart(correct * red, correct * green) > apart(red, green));
+}
+
+#[test]
+fn filters_parse_from_their_names() {
+    for filter in ColorFilter::ALL {
+        assert_eq!(filter.to_string().parse::<ColorFilter>(), Ok(filter));
+    }
+    assert_eq!("Simulate_Tritanopia".parse(), Ok(ColorFilter::Simulate(ColorBlindness::Tritanopia)));
+    assert!("purple".parse::<ColorFilter>().is_err());
+}
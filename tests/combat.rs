@@ -0,0 +1,102 @@
+// Working out attacks.
+
+use ps_rpg_engine::{
+    combat::{self, Attack, CombatConfig, CombatRules, Defence, HitResult, Outcome, StandardRules},
+    formation::{Row, RowModifiers},
+    rng::RngStream
+};
+
+// No randomness at all, to check the sums.
+fn exact() -> StandardRules {
+    StandardRules(CombatConfig { variance: 0.0, critical_chance: 0.0, min_hit: 1.0, ..Default::default() })
+}
+
+fn resolve(rules: &dyn CombatRules, attack: &Attack, defence: &Defence) -> HitResult {
+    combat::resolve(rules, attack, defence, &mut RngStream::new(3, 4))
+}
+
+#[test]
+fn the_config_is_read() {
+    let config = CombatConfig::parse("# Harder.\nvariance = 0.25\n\ncritical_multiplier = 3").unwrap();
+    assert_eq!((config.variance, config.critical_multiplier), (0.25, 3.0));
+    assert_eq!(config.block_multiplier, CombatConfig::default().block_multiplier);
+
+    assert!(CombatConfig::parse("variance").unwrap_err().starts_with("Line 1: expected"));
+    assert!(CombatConfig::parse("\nvariance = 2").unwrap_err().starts_with("Line 2: bad variance \"2\""));
+    assert!(CombatConfig::parse("luck = 1").unwrap_err().contains("unknown key \"luck\""));
+    assert!(CombatConfig::parse("min_hit = 0.9\nmax_hit = 0.5").is_err());
+}
+
+#[test]
+fn damage_comes_from_attack_and_defence() {
+    let rules = exact();
+    let result = resolve(&rules, &Attack::new(10, 0), &Defence { defence: 5, ..Default::default() });
+    assert_eq!(result, HitResult { outcome: Outcome::Hit, damage: 15, blocked: false, countered: false });
+
+    // The back row halves it, and an attack that can't get through still does something.
+    let attack = Attack { row: RowModifiers::new(Row::Back, Row::Front, false), ..Attack::new(10, 0) };
+    assert_eq!(resolve(&rules, &attack, &Defence::default()).damage, 10);
+    assert_eq!(resolve(&rules, &Attack::new(1, 0), &Defence { defence: 50, ..Default::default() }).damage, 1);
+}
+
+#[test]
+fn damage_varies_within_the_config() {
+    let rules = StandardRules(CombatConfig { critical_chance: 0.0, min_hit: 1.0, ..Default::default() });
+    let mut rng = RngStream::new(1, 1);
+    let damage: Vec<u32> = (0..200).map(|_| combat::resolve(&rules, &Attack::new(50, 0), &Defence::default(), &mut rng).damage).collect();
+    assert!(damage.iter().all(|damage| (90..=110).contains(damage)), "{:?}", damage);
+    assert!(damage.iter().any(|damage| *damage < 95) && damage.iter().any(|damage| *damage > 105));
+}
+
+#[test]
+fn criticals_blocks_and_counters() {
+    let config = CombatConfig { critical_chance: 1.0, ..exact().0 };
+    let always = Defence { block_chance: 1.0, counter_chance: 1.0, ..Default::default() };
+    let result = resolve(&StandardRules(config), &Attack::new(10, 0), &always);
+    assert_eq!(result, HitResult { outcome: Outcome::Critical, damage: 20, blocked: true, countered: true });
+
+    // Spells can't be blocked or countered.
+    let spell = Attack { blockable: false, counterable: false, ..Attack::new(10, 0) };
+    let result = resolve(&exact(), &spell, &always);
+    assert_eq!((result.damage, result.blocked, result.countered), (20, false, false));
+}
+
+#[test]
+fn evasion_makes_attacks_miss() {
+    let rules = StandardRules::default();
+    let attack = Attack::new(10, 0);
+    assert_eq!(rules.hit_chance(&attack, &Defence { evasion: 200, ..Default::default() }), rules.0.min_hit);
+    assert_eq!(rules.hit_chance(&Attack::new(10, 10), &Defence::default()), 1.0);
+
+    let mut rng = RngStream::new(5, 6);
+    let dodgy = Defence { evasion: 45, counter_chance: 1.0, ..Default::default() };
+    let results: Vec<HitResult> = (0..200).map(|_| combat::resolve(&rules, &attack, &dodgy, &mut rng)).collect();
+    let misses = results.iter().filter(|result| result.outcome == Outcome::Miss).count();
+    assert!((60..140).contains(&misses), "{}", misses);
+    // Misses aren't countered.
+    assert!(results.iter().all(|result| result.countered != (result.outcome == Outcome::Miss)));
+}
+
+// A game that never misses and doubles everything, keeping the rest.
+struct Brutal(CombatConfig);
+
+impl CombatRules for Brutal {
+    fn config(&self) -> &CombatConfig {
+        &self.0
+    }
+
+    fn hit_chance(&self, _attack: &Attack, _defence: &Defence) -> f32 {
+        1.0
+    }
+
+    fn base_damage(&self, attack: &Attack, _defence: &Defence) -> f32 {
+        attack.attack as f32 * 4.0
+    }
+}
+
+#[test]
+fn games_can_override_steps() {
+    let rules = Brutal(exact().0);
+    let result = resolve(&rules, &Attack::new(10, -500), &Defence { defence: 100, evasion: 100, ..Default::default() });
+    assert_eq!((result.outcome, result.damage), (Outcome::Hit, 40));
+}
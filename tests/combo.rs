@@ -0,0 +1,88 @@
+// Dual and triple techs, used by more than one party member at once.
+
+use std::time::Duration;
+
+use cgmath::Point3;
+
+use ps_rpg_engine::{
+    battle_sequence::{SkillSequence, TargetHit},
+    battle_ui::{Combatant, Side, TurnOrder},
+    combat::{HitResult, Outcome},
+    combo::{ComboTech, ComboTechs},
+    party::{Party, PartyMember, Stats, TargetType}
+};
+
+const COMBOS: &str = "
+# Two of them
+[Cross Slash]
+description = Both at once.
+member = Aria 4
+member = Tobin 2
+target = enemy
+
+[Delta Storm]
+member = Aria 8
+member = Tobin 8
+member = Mira 8
+target = all_enemies
+";
+
+fn party() -> Party {
+    let stats = Stats { hp: 50, max_hp: 50, mp: 10, max_mp: 10, speed: 10, ..Default::default() };
+    Party::new(vec![PartyMember::new("Aria", stats), PartyMember::new("Tobin", Stats { mp: 3, ..stats })])
+}
+
+#[test]
+fn combos_are_parsed() {
+    let combos = ComboTech::parse_list(COMBOS).unwrap();
+    assert_eq!(combos.len(), 2);
+    assert_eq!(combos[0].members[1].name, "Tobin");
+    assert_eq!(combos[0].members[1].mp_cost, 2);
+    assert_eq!(combos[1].target, TargetType::AllEnemies);
+
+    assert!(ComboTech::parse_list("[Solo]\nmember = Aria 2").unwrap_err().contains("two or three"));
+    assert!(ComboTech::parse_list("[Twice]\nmember = Aria 2\nmember = aria 2").unwrap_err().starts_with("Line 3:"));
+    assert!(ComboTech::parse_list("[A]\nmember = Aria 1\nmember = Tobin x").unwrap_err().starts_with("Line 3:"));
+    assert!(ComboTech::parse_list("[A]\nmember = Aria\nmember = Tobin\n[a]").unwrap_err().starts_with("Line 4:"));
+}
+
+#[test]
+fn everyone_in_it_has_to_be_able_to() {
+    let combos = ComboTechs(ComboTech::parse_list(COMBOS).unwrap());
+    let mut party = party();
+    let cross = combos.get("cross slash").unwrap();
+    assert_eq!(cross.check(&party), Ok(vec![0, 1]));
+    // Whatever's wrong first.
+    assert_eq!(combos.get("Delta Storm").unwrap().check(&party), Err("Tobin needs 8 MP".to_string()));
+    assert_eq!(combos.usable_by("Tobin", &party).count(), 1);
+
+    assert_eq!(cross.pay(&mut party), Ok(vec![0, 1]));
+    assert_eq!((party.members[0].stats.mp, party.members[1].stats.mp), (6, 1));
+    assert_eq!(cross.pay(&mut party), Err("Tobin needs 2 MP".to_string()));
+    // Nothing's taken if it can't be used.
+    assert_eq!(party.members[0].stats.mp, 6);
+
+    party.members[1].stats.mp = 10;
+    party.members[1].stats.hp = 0;
+    assert_eq!(cross.check(&party), Err("Tobin is knocked out".to_string()));
+    assert_eq!(combos.usable_by("Aria", &party).count(), 0);
+}
+
+#[test]
+fn combos_take_everyones_turns_and_wait_for_everyone() {
+    let combatant = |name: &str, speed, x| Combatant { name: name.to_string(), side: Side::Party, speed, position: Point3::new(x, 0.0, 3.0), knocked_out: false, summoned: false };
+    let combatants = vec![combatant("Aria", 10, -1.0), combatant("Tobin", 12, 1.0), combatant("Slime", 8, 0.0)];
+    let mut turns = TurnOrder::new(&combatants);
+    // Tobin's fastest, so goes first, and uses it with Aria, who'd have been next.
+    assert_eq!(turns.next(&combatants), Some(1));
+    turns.use_turn(0, &combatants);
+    assert_eq!(turns.next(&combatants), Some(2));
+
+    let hit = TargetHit { target: 2, result: HitResult { outcome: Outcome::Hit, damage: 40, blocked: false, countered: false } };
+    let mut sequence = SkillSequence::new(vec![hit], &combatants).with_users(2);
+    sequence.start();
+    assert!(!sequence.is_started());
+    assert!(sequence.update(Duration::from_secs(1)).is_empty());
+    sequence.start();
+    assert_eq!(sequence.update(Duration::ZERO), [hit]);
+}
@@ -0,0 +1,63 @@
+// Reading console lines, and the list of commands the console knows.
+
+use ps_rpg_engine::{
+    console::{self, CommandInfo, ConsoleCommand, COMMANDS},
+    mods
+};
+
+#[test]
+fn lines_are_split_into_a_name_and_args() {
+    assert_eq!(ConsoleCommand::parse("  LOG renderer=debug  "), Some(ConsoleCommand { name: "log".to_string(), args: "renderer=debug".to_string() }));
+    assert_eq!(ConsoleCommand::parse("stats"), Some(ConsoleCommand { name: "stats".to_string(), args: String::new() }));
+    assert_eq!(ConsoleCommand::parse("   "), None);
+}
+
+#[test]
+fn commands_are_listed_once_each() {
+    for (i, info) in COMMANDS.iter().enumerate() {
+        assert!(!COMMANDS[..i].iter().any(|earlier| earlier.name == info.name), "{} is listed twice", info.name);
+    }
+    let warp = CommandInfo::find("warp").unwrap();
+    assert_eq!(warp.usage(), "warp [field] [spawn]");
+    assert_eq!(CommandInfo::find("stats").unwrap().usage(), "stats");
+    assert!(CommandInfo::find("fly").is_none());
+    assert!(console::help().starts_with("Commands: log [filter], seed [seed],"));
+    assert!(console::help().ends_with(", commands [file], help"));
+    // Everything mods can run is a real command.
+    for name in mods::MOD_COMMANDS {
+        assert!(CommandInfo::find(name).is_some(), "{}", name);
+    }
+}
+
+#[test]
+fn commands_are_dumped_as_json() {
+    let json: serde_json::Value = serde_json::from_str(&console::commands_json()).unwrap();
+    let commands = json.as_array().unwrap();
+    assert_eq!(commands.len(), COMMANDS.len());
+    let warp = commands.iter().find(|command| command["name"] == "warp").unwrap();
+    assert_eq!(warp["args"], "[field] [spawn]");
+    assert_eq!(warp["usage"], "warp [field] [spawn]");
+    assert_eq!(warp["mods"], false);
+    assert_eq!(commands.iter().find(|command| command["name"] == "camera").unwrap()["mods"], true);
+}
+
+#[test]
+fn every_command_the_game_runs_is_listed() {
+    // The names matched on in run_console_command.
+    let main = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/src/main.rs")).unwrap();
+    let start = main.find("fn run_console_command").unwrap();
+    let end = start + main[start..].find("\n}\n").unwrap();
+    let mut handled = Vec::new();
+    for line in main[start..end].lines().filter(|line| line.starts_with("        \"")) {
+        let names = line.trim().split(" =>").next().unwrap().split(" if ").next().unwrap();
+        handled.extend(names.split(" | ").map(|name| name.trim_matches('"').to_string()));
+    }
+
+    assert!(handled.len() > 50);
+    for name in &handled {
+        assert!(CommandInfo::find(name).is_some(), "\"{}\" is run but isn't in COMMANDS", name);
+    }
+    for info in COMMANDS {
+        assert!(handled.iter().any(|name| name == info.name), "\"{}\" is in COMMANDS but nothing runs it", info.name);
+    }
+}
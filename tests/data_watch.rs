@@ -0,0 +1,58 @@
+// Noticing edits to data files, and picking up changed items.
+
+use std::{fs, time::{Duration, Instant, SystemTime}};
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    data_watch::{DataWatcher, POLL_INTERVAL},
+    inventory::{Inventory, Item, ItemCatalog, ItemEffect}
+};
+
+fn touch(path: &std::path::Path, modified: SystemTime) {
+    fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+}
+
+#[test]
+fn edited_files_are_noticed_once() {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_data_watch_{}", std::process::id()));
+    fs::create_dir_all(dir.join("data")).unwrap();
+    let path = dir.join("data/items.cfg");
+    fs::write(&path, "[potion]").unwrap();
+    touch(&path, SystemTime::UNIX_EPOCH);
+
+    let assets = AssetServer::new(&dir);
+    let mut watcher = DataWatcher::new(&assets, &["data/items.cfg", "data/loot.cfg"]);
+    let start = Instant::now();
+    watcher.update(start, &assets);
+    assert!(watcher.take_changed(true).is_empty());
+
+    // Not looked at again until it's been long enough.
+    touch(&path, SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+    watcher.update(start + POLL_INTERVAL / 2, &assets);
+    assert!(watcher.take_changed(true).is_empty());
+    watcher.update(start + POLL_INTERVAL, &assets);
+    // Held back while it isn't a good time.
+    assert!(watcher.take_changed(false).is_empty());
+    assert_eq!(watcher.take_changed(true), ["data/items.cfg"]);
+    assert!(watcher.take_changed(true).is_empty());
+
+    // Files turning up count as a change too.
+    fs::write(dir.join("data/loot.cfg"), "").unwrap();
+    watcher.poll(&assets);
+    assert_eq!(watcher.take_changed(true), ["data/loot.cfg"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reloaded_items_replace_the_ones_carried() {
+    let potion = |hp| Item { id: "potion".to_string(), name: "Potion".to_string(), description: String::new(), effect: ItemEffect::Restore { hp, mp: 0 } };
+    let mut inventory = Inventory::new();
+    inventory.add(&potion(50), 2);
+    inventory.refresh(&ItemCatalog(vec![potion(80)]));
+    assert_eq!(inventory.slots()[0].item.effect, ItemEffect::Restore { hp: 80, mp: 0 });
+    assert_eq!(inventory.count("potion"), 2);
+
+    // Items that have gone are left alone.
+    inventory.refresh(&ItemCatalog::default());
+    assert_eq!(inventory.count("potion"), 2);
+}
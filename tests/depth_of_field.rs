@@ -0,0 +1,60 @@
+// How much depth of field blurs at each distance, and its quality setting.
+
+use cgmath::Point3;
+
+use ps_rpg_engine::{
+    camera::Camera,
+    depth_of_field::{DepthOfField, DofQuality}
+};
+
+#[test]
+fn sharp_around_the_focus_and_blurred_beyond() {
+    let dof = DepthOfField { focus_distance: 10.0, focus_range: 2.0, falloff: 4.0, max_blur: 8.0 };
+    for distance in [8.0, 10.0, 12.0] {
+        assert_eq!(dof.blur_radius(distance), 0.0, "{} should be sharp", distance);
+    }
+    // Halfway through the falloff on either side.
+    assert_eq!(dof.blur_radius(14.0), 4.0);
+    assert_eq!(dof.blur_radius(6.0), 4.0);
+    // Never more than the biggest blur, however far away.
+    assert_eq!(dof.blur_radius(16.0), 8.0);
+    assert_eq!(dof.blur_radius(1000.0), 8.0);
+}
+
+#[test]
+fn no_falloff_is_a_hard_edge() {
+    let dof = DepthOfField { falloff: 0.0, ..DepthOfField::default() };
+    assert_eq!(dof.blur_radius(dof.focus_distance + dof.focus_range), 0.0);
+    assert_eq!(dof.blur_radius(dof.focus_distance + dof.focus_range + 0.01), dof.max_blur);
+}
+
+#[test]
+fn focusing_on_a_point_goes_along_the_view() {
+    let camera = Camera {
+        eye: Point3::new(0.0, 0.0, 10.0),
+        target: Point3::new(0.0, 0.0, 0.0),
+        ..Camera::default()
+    };
+    assert_eq!(DepthOfField::focused_on(&camera, Point3::new(0.0, 0.0, 0.0)).focus_distance, 10.0);
+    // Off to the side is still the same distance away as far as the depth buffer's concerned.
+    assert_eq!(DepthOfField::focused_on(&camera, Point3::new(5.0, 3.0, 0.0)).focus_distance, 10.0);
+    // Behind the camera is as near as it gets.
+    assert_eq!(DepthOfField::focused_on(&camera, Point3::new(0.0, 0.0, 20.0)).focus_distance, 0.0);
+}
+
+#[test]
+fn better_quality_takes_more_samples() {
+    assert_eq!(DofQuality::Off.samples(), 0);
+    for pair in DofQuality::ALL.windows(2) {
+        assert!(pair[0].samples() < pair[1].samples(), "{} should take fewer samples than {}", pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn quality_names_round_trip() {
+    for quality in DofQuality::ALL {
+        assert_eq!(quality.to_string().parse::<DofQuality>(), Ok(quality));
+    }
+    assert_eq!(" HIGH ".parse(), Ok(DofQuality::High));
+    assert!("ultra".parse::<DofQuality>().is_err());
+}
@@ -0,0 +1,84 @@
+// Picking which dialogue tree an NPC runs.
+
+use ps_rpg_engine::{
+    dialogue::{DialogueCondition, DialogueContext, NpcDialogue, NpcDialogues},
+    flags::GameFlags,
+    party::{Party, PartyMember, Stats}
+};
+
+const DIALOGUE: &str = "
+# For testing.
+[Elder]
+tree = elder.after_ogre
+flag = cave.ogre_beaten
+stage = main_quest 2-3
+
+tree = elder.night
+time = 21:00-05:30
+
+tree = elder.tobin
+member = tobin
+flag = !elder.met_tobin
+
+tree = elder.hello
+";
+
+fn dialogue() -> NpcDialogues {
+    NpcDialogues(NpcDialogue::parse_list(DIALOGUE).unwrap())
+}
+
+fn party(names: &[&str]) -> Party {
+    Party::new(names.iter().map(|name| PartyMember::new(name, Stats::default())).collect())
+}
+
+fn choose(flags: &GameFlags, party: &Party, time: &str) -> Option<String> {
+    let minute = ps_rpg_engine::game_clock::parse_time(time).unwrap();
+    dialogue().choose("Elder", &DialogueContext { flags, party, minute }).map(str::to_string)
+}
+
+#[test]
+fn dialogue_is_read() {
+    let elder = &dialogue().0[0];
+    assert_eq!(elder.npc, "Elder");
+    assert_eq!(elder.choices[0].conditions, [
+        DialogueCondition::Flag { flag: "cave.ogre_beaten".to_string(), set: true },
+        DialogueCondition::Stage { flag: "main_quest".to_string(), min: 2, max: 3 }
+    ]);
+    assert_eq!(elder.choices[1].conditions, [DialogueCondition::Time { from: 21 * 60, to: 5 * 60 + 30 }]);
+    assert!(elder.choices[3].conditions.is_empty());
+
+    assert!(NpcDialogue::parse_list("[Elder]\nflag = met").unwrap_err().contains("\"tree\" before"));
+    assert!(NpcDialogue::parse_list("[Elder]\ntree = hi\nmood = happy").unwrap_err().contains("Line 3"));
+    assert!(NpcDialogue::parse_list("[Elder]\ntree = hi\nstage = quest 3-1").unwrap_err().contains("bad stage"));
+    assert!(NpcDialogue::parse_list("[Elder]\ntree = hi\ntime = noon").unwrap_err().contains("bad time"));
+}
+
+#[test]
+fn lines_change_as_the_story_goes_on() {
+    let mut flags = GameFlags::new();
+    let alone = party(&["Aria"]);
+    assert_eq!(choose(&flags, &alone, "12:00").as_deref(), Some("elder.hello"));
+    assert_eq!(choose(&flags, &party(&["Aria", "Tobin"]), "12:00").as_deref(), Some("elder.tobin"));
+    flags.set("elder.met_tobin", 1);
+    assert_eq!(choose(&flags, &party(&["Aria", "Tobin"]), "12:00").as_deref(), Some("elder.hello"));
+
+    // Every condition has to hold.
+    flags.set("cave.ogre_beaten", 1);
+    assert_eq!(choose(&flags, &alone, "12:00").as_deref(), Some("elder.hello"));
+    flags.set("main_quest", 2);
+    assert_eq!(choose(&flags, &alone, "23:00").as_deref(), Some("elder.after_ogre"));
+    flags.set("main_quest", 4);
+    assert_eq!(choose(&flags, &alone, "12:00").as_deref(), Some("elder.hello"));
+}
+
+#[test]
+fn time_ranges_can_go_past_midnight() {
+    let (flags, alone) = (GameFlags::new(), party(&["Aria"]));
+    assert_eq!(choose(&flags, &alone, "21:00").as_deref(), Some("elder.night"));
+    assert_eq!(choose(&flags, &alone, "02:00").as_deref(), Some("elder.night"));
+    assert_eq!(choose(&flags, &alone, "05:30").as_deref(), Some("elder.hello"));
+
+    // NPCs without any dialogue have nothing to say.
+    let minute = 0;
+    assert_eq!(dialogue().choose("Guard", &DialogueContext { flags: &flags, party: &alone, minute }), None);
+}
@@ -0,0 +1,125 @@
+// Traps and darkness in dungeon fields.
+
+use std::time::Duration;
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    darkness::{self, Darkness, FieldDarkness, LIGHT_BOOST},
+    flags::GameFlags,
+    party::{Party, PartyMember, Stats},
+    renderer::PostProcessSettings,
+    transform::Transform,
+    trap::{self, FieldTrap, TrapKind, TRAP_RADIUS},
+    world::{Entity, World}
+};
+
+fn field_trap(id: &str, kind: TrapKind, x: f32, flag: &str) -> FieldTrap {
+    FieldTrap { id: id.to_string(), kind, position: Vector3::new(x, 0.0, 0.0), flag: flag.to_string() }
+}
+
+fn world(traps: &[FieldTrap]) -> (World, Entity) {
+    let mut world = World::new();
+    let stats = Stats { hp: 30, max_hp: 100, ..Default::default() };
+    world.insert_resource(Party::new(vec![PartyMember::new("Aria", stats), PartyMember::new("Tobin", Stats { hp: 5, ..stats })]));
+    let player = world.spawn();
+    world.insert(player, Transform::from_position(Vector3::new(-10.0, 0.0, 0.0)));
+    trap::spawn_traps(&mut world, traps);
+    (world, player)
+}
+
+fn step(world: &mut World, player: Entity, x: f32) -> Vec<String> {
+    world.get_mut::<Transform>(player).unwrap().position = Vector3::new(x, 0.0, 0.0);
+    trap::update_traps(world, player, TRAP_RADIUS).into_iter().map(|event| event.message).collect()
+}
+
+#[test]
+fn spikes_hurt_but_never_knock_anyone_out() {
+    let (mut world, player) = world(&[field_trap("spikes", TrapKind::Spikes { damage: 10 }, 0.0, "")]);
+    assert_eq!(step(&mut world, player, 0.0), vec!["Spikes! Aria and Tobin took 10 damage".to_string()]);
+    let hp: Vec<u32> = world.resource::<Party>().unwrap().members.iter().map(|member| member.stats.hp).collect();
+    assert_eq!(hp, vec![20, 1]);
+
+    // Standing on them doesn't set them off again, stepping off and back on does.
+    assert!(step(&mut world, player, 0.2).is_empty());
+    assert!(step(&mut world, player, 2.0).is_empty());
+    assert_eq!(step(&mut world, player, 0.0).len(), 1);
+}
+
+#[test]
+fn flags_disarm_traps() {
+    let (mut world, player) = world(&[field_trap("spikes", TrapKind::Spikes { damage: 10 }, 0.0, "lever")]);
+    let mut flags = GameFlags::new();
+    flags.set("lever", 1);
+    world.insert_resource(flags);
+    assert!(step(&mut world, player, 0.0).is_empty());
+
+    world.resource_mut::<GameFlags>().unwrap().set("lever", 0);
+    assert!(step(&mut world, player, 2.0).is_empty());
+    assert_eq!(step(&mut world, player, 0.0).len(), 1);
+}
+
+#[test]
+fn teleporters_dont_bounce_back() {
+    let (mut world, player) = world(&[
+        field_trap("there", TrapKind::Teleport { position: Vector3::new(10.0, 0.0, 0.0) }, 0.0, ""),
+        field_trap("back", TrapKind::Teleport { position: Vector3::new(0.0, 0.0, 0.0) }, 10.0, "")
+    ]);
+    assert_eq!(step(&mut world, player, 0.0), vec!["The floor glows and the room shifts".to_string()]);
+    assert_eq!(world.get::<Transform>(player).unwrap().position, Vector3::new(10.0, 0.0, 0.0));
+    // Landing on the other one doesn't send them straight back.
+    assert!(step(&mut world, player, 10.0).is_empty());
+    assert!(step(&mut world, player, 12.0).is_empty());
+    assert_eq!(step(&mut world, player, 10.0).len(), 1);
+    assert_eq!(world.get::<Transform>(player).unwrap().position, Vector3::new(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn pitfalls_drop_into_another_field() {
+    let (mut world, player) = world(&[field_trap("pit", TrapKind::Pitfall { field: "cellar".to_string(), spawn: Some("below".to_string()) }, 0.0, "")]);
+    world.get_mut::<Transform>(player).unwrap().position = Vector3::new(0.0, 0.0, 0.0);
+    let events = trap::update_traps(&mut world, player, TRAP_RADIUS);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message, "The floor gives way!");
+    assert_eq!(events[0].warp, Some(("cellar".to_string(), Some("below".to_string()))));
+    assert!(!events[0].hurt);
+}
+
+#[test]
+fn light_widens_the_circle_for_a_while() {
+    let mut darkness = Darkness::new(FieldDarkness { strength: 0.9, radius: 80.0 });
+    assert!(!darkness.update(Duration::from_millis(16)));
+    darkness.light(LIGHT_BOOST, Duration::from_secs(2));
+    assert_eq!(darkness.target_radius(), 80.0 + LIGHT_BOOST);
+
+    // It grows out rather than jumping.
+    assert!(darkness.update(Duration::from_millis(250)));
+    assert!(darkness.radius() > 80.0 && darkness.radius() < 80.0 + LIGHT_BOOST);
+    assert!(darkness.update(Duration::from_millis(1500)));
+    assert_eq!(darkness.radius(), 80.0 + LIGHT_BOOST);
+
+    // And shrinks back once it's run out.
+    darkness.update(Duration::from_millis(250));
+    assert_eq!((darkness.boost_left(), darkness.target_radius()), (Duration::ZERO, 80.0));
+    for _ in 0..10 {
+        darkness.update(Duration::from_millis(250));
+    }
+    assert_eq!(darkness.radius(), 80.0);
+}
+
+#[test]
+fn the_circle_follows_the_player() {
+    let mut world = World::new();
+    let mut settings = PostProcessSettings::default();
+    darkness::enter_field(&mut world, Some(FieldDarkness { strength: 0.9, radius: 80.0 }), &mut settings);
+    let dark = *world.resource::<Darkness>().unwrap();
+    dark.apply(&mut settings, Some((100.0, 50.0)));
+    assert_eq!((settings.darkness, settings.light_position, settings.light_radius), (0.9, [100.0, 50.0], 80.0));
+    dark.apply(&mut settings, None);
+    assert_eq!(settings.light_radius, 0.0);
+
+    // Fields that aren't dark take it away.
+    darkness::enter_field(&mut world, None, &mut settings);
+    assert!(world.resource::<Darkness>().is_none());
+    assert_eq!(settings.darkness, 0.0);
+}
@@ -0,0 +1,75 @@
+// Emote bubbles over characters' heads.
+
+use std::time::Duration;
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    emote::{self, Emote, EmoteKind, SpottedPlayer, DEFAULT_DURATION},
+    schedule::ScheduledNpc,
+    transform::Transform,
+    ui::UiBatch,
+    world::{Entity, World}
+};
+
+fn npc(world: &mut World, x: f32) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(x, 0.0, 0.0)));
+    world.insert(entity, ScheduledNpc { name: "Shopkeeper".to_string(), entry: 0, behaviour: String::new() });
+    entity
+}
+
+#[test]
+fn every_emote_has_a_bubble() {
+    assert_eq!("Heart".parse(), Ok(EmoteKind::Heart));
+    assert!("wink".parse::<EmoteKind>().unwrap_err().contains("Unknown emote"));
+
+    let sprites: Vec<image::RgbaImage> = EmoteKind::ALL.iter().map(EmoteKind::sprite).collect();
+    assert!(sprites.iter().all(|sprite| sprite.dimensions() == sprites[0].dimensions()));
+    // Told apart by their shape, not just their colour.
+    let shape = |sprite: &image::RgbaImage| sprite.pixels().map(|pixel| pixel[3] > 0 && pixel[0] != pixel[1]).collect::<Vec<_>>();
+    for (index, sprite) in sprites.iter().enumerate() {
+        assert!(sprites[index + 1..].iter().all(|other| shape(other) != shape(sprite)));
+    }
+}
+
+#[test]
+fn emotes_pop_up_then_go() {
+    let mut world = World::new();
+    let entity = npc(&mut world, 0.0);
+    emote::show_emote(&mut world, entity, EmoteKind::Sweat, Duration::from_secs(1));
+    assert_eq!(world.get::<Emote>(entity).unwrap().size_and_alpha(), (0.0, 1.0));
+
+    assert!(emote::update_emotes(&mut world, Duration::from_millis(500)));
+    assert_eq!(world.get::<Emote>(entity).unwrap().size_and_alpha(), (1.0, 1.0));
+    let mut batch = UiBatch::new();
+    emote::build_emotes(&world, &mut batch, |point| Some((point.x, point.z)));
+    assert!(!batch.is_empty());
+
+    // Off the screen isn't drawn.
+    let mut batch = UiBatch::new();
+    emote::build_emotes(&world, &mut batch, |_| None);
+    assert!(batch.is_empty());
+
+    assert!(!emote::update_emotes(&mut world, Duration::from_millis(500)));
+    assert!(world.get::<Emote>(entity).is_none());
+}
+
+#[test]
+fn npcs_spot_the_player_once_until_they_leave() {
+    let mut world = World::new();
+    let player = world.spawn();
+    world.insert(player, Transform::from_position(Vector3::new(10.0, 0.0, 0.0)));
+    let near = npc(&mut world, 8.0);
+    npc(&mut world, -10.0);
+
+    assert_eq!(emote::spot_player(&mut world, player, 3.0), [near]);
+    assert_eq!(world.get::<Emote>(near).map(|emote| (emote.kind, emote.duration)), Some((EmoteKind::Exclamation, DEFAULT_DURATION)));
+    assert!(emote::spot_player(&mut world, player, 3.0).is_empty());
+
+    world.get_mut::<Transform>(player).unwrap().position.x = 0.0;
+    assert!(emote::spot_player(&mut world, player, 3.0).is_empty());
+    assert!(!world.has::<SpottedPlayer>(near));
+    world.get_mut::<Transform>(player).unwrap().position.x = 9.0;
+    assert_eq!(emote::spot_player(&mut world, player, 3.0), [near]);
+}
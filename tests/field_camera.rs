@@ -0,0 +1,160 @@
+// Reading a field's cameras and cutting or blending between them.
+
+use std::time::Duration;
+
+use cgmath::{Point3, Vector3};
+
+use ps_rpg_engine::{
+    camera::Camera,
+    field_camera::{self, CameraCommand, FieldCamera, FieldCameras},
+    screen_effects::Easing
+};
+
+// Two cameras: one at the origin looking down -Z, and one inside a parent that's moved up
+// and turned to look down -X.
+const SCENE: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0, 1] }],
+    "nodes": [
+        { "name": "hall", "camera": 0 },
+        { "translation": [0, 2, 0], "children": [2] },
+        { "name": "door", "camera": 1, "rotation": [0, 0.7071068, 0, 0.7071068], "translation": [3, 0, 0] }
+    ],
+    "cameras": [
+        { "type": "perspective", "perspective": { "yfov": 0.7853982, "znear": 0.1, "zfar": 50 } },
+        { "type": "perspective", "perspective": { "yfov": 1.0471976, "znear": 0.5 } }
+    ]
+}"#;
+
+fn camera(name: &str, eye: [f32; 3], background: &str) -> FieldCamera {
+    FieldCamera {
+        name: name.to_string(),
+        camera: Camera {
+            eye: Point3::new(eye[0], eye[1], eye[2]),
+            target: Point3::new(eye[0], eye[1], eye[2] - 1.0),
+            ..Default::default()
+        },
+        background: background.to_string()
+    }
+}
+
+fn cameras() -> FieldCameras {
+    let mut cameras = FieldCameras::new(vec![
+        camera("hall", [0.0, 0.0, 0.0], "fields/hall.png"),
+        camera("door", [10.0, 0.0, 0.0], "fields/door.png"),
+        camera("door_close", [10.0, 0.0, 2.0], "fields/door.png")
+    ], 0);
+    cameras.insert_background("fields/hall.png", image::RgbaImage::new(1, 1));
+    cameras.insert_background("fields/door.png", image::RgbaImage::new(2, 2));
+    cameras
+}
+
+fn showing(cameras: &FieldCameras) -> &str {
+    &cameras.showing().unwrap().name
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 0.001
+}
+
+#[test]
+fn cameras_come_from_the_scene() {
+    let cameras = field_camera::cameras_from_gltf(SCENE.as_bytes()).unwrap();
+    assert_eq!(cameras.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["hall", "door"]);
+
+    let (_, hall) = &cameras[0];
+    assert_eq!(hall.eye, Point3::new(0.0, 0.0, 0.0));
+    assert_eq!(hall.target, Point3::new(0.0, 0.0, -1.0));
+    assert!(close(hall.fovy, 45.0) && hall.zfar == 50.0);
+
+    let (_, door) = &cameras[1];
+    assert!(close(door.eye.x, 3.0) && close(door.eye.y, 2.0) && close(door.eye.z, 0.0), "{:?}", door.eye);
+    assert!(close(door.target.x, 2.0) && close(door.target.z, 0.0), "{:?}", door.target);
+    assert!(close(door.up.y, 1.0));
+    // No far plane means the default.
+    assert!(close(door.fovy, 60.0) && door.zfar == Camera::default().zfar);
+
+    assert!(field_camera::cameras_from_gltf(b"not a gltf").is_err());
+}
+
+#[test]
+fn commands_take_a_duration_easing_and_swap() {
+    let command: CameraCommand = "door 1.5 easeinout swap=0.5".parse().unwrap();
+    assert_eq!(command, CameraCommand { camera: "door".to_string(), duration: Duration::from_millis(1500), easing: Easing::EaseInOut, swap: 0.5 });
+    let cut: CameraCommand = "door".parse().unwrap();
+    assert_eq!((cut.duration, cut.swap), (Duration::ZERO, 1.0));
+
+    assert!("".parse::<CameraCommand>().is_err());
+    assert!("door swap=2".parse::<CameraCommand>().unwrap_err().contains("Bad swap"));
+    assert!("door wobbly".parse::<CameraCommand>().unwrap_err().contains("Unknown easing"));
+}
+
+#[test]
+fn the_first_background_goes_up_once() {
+    let mut cameras = cameras();
+    assert_eq!(cameras.take_background().map(|image| image.width()), Some(1));
+    assert_eq!(cameras.take_background(), None);
+}
+
+#[test]
+fn cuts_swap_straight_away() {
+    let mut cameras = cameras();
+    cameras.take_background();
+    cameras.run(&"door".parse().unwrap()).unwrap();
+    assert_eq!(showing(&cameras), "door");
+    assert_eq!(cameras.camera().eye, Point3::new(10.0, 0.0, 0.0));
+    assert!(!cameras.is_moving());
+    assert_eq!(cameras.take_background().map(|image| image.width()), Some(2));
+
+    // The same background doesn't need swapping again.
+    cameras.run(&"door_close".parse().unwrap()).unwrap();
+    assert_eq!(showing(&cameras), "door_close");
+    assert_eq!(cameras.take_background(), None);
+
+    assert!(cameras.run(&"attic".parse().unwrap()).is_err());
+}
+
+#[test]
+fn blends_move_then_swap_when_asked() {
+    let mut cameras = cameras();
+    cameras.take_background();
+    cameras.run(&"door 2 linear swap=0.75".parse().unwrap()).unwrap();
+    assert!(cameras.is_moving());
+
+    assert!(cameras.update(Duration::from_secs(1)));
+    assert!(close(cameras.camera().eye.x, 5.0));
+    assert_eq!(showing(&cameras), "hall");
+    assert_eq!(cameras.take_background(), None);
+
+    cameras.update(Duration::from_millis(500));
+    assert_eq!(showing(&cameras), "door");
+    assert!(cameras.take_background().is_some());
+
+    // The last step lands on the camera, then it stops.
+    assert!(cameras.update(Duration::from_secs(1)));
+    assert_eq!(cameras.camera().eye, Point3::new(10.0, 0.0, 0.0));
+    assert!(!cameras.update(Duration::from_secs(1)));
+}
+
+#[test]
+fn finishing_lands_on_the_camera() {
+    let mut cameras = cameras();
+    cameras.run(&"door 5 easein".parse().unwrap()).unwrap();
+    cameras.update(Duration::from_secs(1));
+    cameras.finish();
+    assert!(!cameras.is_moving());
+    assert_eq!(showing(&cameras), "door");
+    assert_eq!(cameras.camera().eye, Point3::new(10.0, 0.0, 0.0));
+}
+
+#[test]
+fn cameras_blend_half_way() {
+    let a = Camera { fovy: 40.0, ..Default::default() };
+    let b = Camera { eye: Point3::new(4.0, 5.0, 10.0), up: Vector3::unit_x(), fovy: 60.0, ..Default::default() };
+    let half = a.lerp(&b, 0.5);
+    assert_eq!(half.eye, Point3::new(2.0, 5.0, 10.0));
+    assert_eq!(half.fovy, 50.0);
+    assert!(close(half.up.x, half.up.y) && close(half.up.x, std::f32::consts::FRAC_1_SQRT_2));
+    assert_eq!(a.lerp(&b, 1.0), Camera { up: Vector3::unit_x(), ..b });
+}
@@ -0,0 +1,93 @@
+// Skills used outside battle, from the menu and the shortcuts, and the events they send to the
+// field.
+
+use ps_rpg_engine::{
+    field_skill::{self, FieldEventResponse, FieldEvents},
+    field_status::FieldStatus,
+    hotbar::{Hotbar, COOLDOWN},
+    inventory::{Inventory, Item, ItemEffect},
+    party::{Party, PartyMember, Skill, Stats, StatusEffect, TargetType},
+    pools::SkillCost,
+    screen_effects::EffectParam
+};
+
+fn skill(name: &str, mp_cost: u32, target: TargetType, field: Option<ItemEffect>) -> Skill {
+    Skill { name: name.to_string(), description: String::new(), mp_cost, costs: Vec::new(), target, field }
+}
+
+fn party() -> Party {
+    let stats = Stats { hp: 100, max_hp: 100, mp: 20, max_mp: 20, ..Default::default() };
+    let mut aria = PartyMember::new("Aria", Stats { hp: 70, ..stats });
+    aria.skills.push(skill("Cleave", 6, TargetType::AllEnemies, None));
+    let mut tobin = PartyMember::new("Tobin", Stats { hp: 40, ..stats });
+    tobin.skills.push(skill("Cure", 4, TargetType::Ally, Some(ItemEffect::Restore { hp: 40, mp: 0 })));
+    tobin.skills.push(skill("Reveal", 2, TargetType::AllAllies, Some(ItemEffect::Event("reveal".to_string()))));
+    Party::new(vec![aria, tobin])
+}
+
+#[test]
+fn skills_heal_like_items() {
+    let (mut party, mut status, mut events, mut inventory) = (party(), FieldStatus::new(), FieldEvents::new(), Inventory::new());
+    assert_eq!(field_skill::use_skill(&mut party, 1, 0, Some(0), &mut status, &mut events, &mut inventory), Ok("Aria recovered 30 HP".to_string()));
+    assert_eq!((party.members[0].stats.hp, party.members[1].stats.mp), (100, 16));
+    // Nothing's paid when it wouldn't do any good.
+    assert_eq!(field_skill::use_skill(&mut party, 1, 0, Some(0), &mut status, &mut events, &mut inventory), Err("It won't do Aria any good".to_string()));
+    assert_eq!(party.members[1].stats.mp, 16);
+    assert_eq!(field_skill::use_skill(&mut party, 0, 0, None, &mut status, &mut events, &mut inventory), Err("Cleave can't be used here".to_string()));
+
+    party.members[1].skills[0].costs.push(SkillCost::Item { item: "herb".to_string(), count: 1 });
+    assert_eq!(field_skill::use_skill(&mut party, 1, 0, None, &mut status, &mut events, &mut inventory), Err("There aren't enough herb for Cure".to_string()));
+
+    // Cures take off anything harmful and leave the rest.
+    let antidote = Item { id: "antidote".to_string(), name: "Antidote".to_string(), description: String::new(), effect: "cure".parse().unwrap() };
+    inventory.add(&antidote, 1);
+    party.members[0].status_effects = vec![StatusEffect::Poison, StatusEffect::Regen];
+    assert_eq!(inventory.use_on(0, &mut party.members[0]), Ok("Aria was cured of poison".to_string()));
+    assert_eq!(party.members[0].status_effects, vec![StatusEffect::Regen]);
+    assert_eq!(inventory.count("antidote"), 0);
+}
+
+#[test]
+fn events_set_the_fields_flags() {
+    let (mut party, mut status, mut events, mut inventory) = (party(), FieldStatus::new(), FieldEvents::new(), Inventory::new());
+    assert_eq!("event reveal".parse(), Ok(ItemEffect::Event("reveal".to_string())));
+    assert_eq!(field_skill::use_skill(&mut party, 1, 1, None, &mut status, &mut events, &mut inventory), Ok("Used Reveal".to_string()));
+    let sent = events.take_events();
+    assert_eq!(sent, vec!["reveal".to_string()]);
+    assert!(events.take_events().is_empty());
+
+    let responses = vec![
+        FieldEventResponse { event: "reveal".to_string(), flag: "cave_passage".to_string(), value: 1 },
+        FieldEventResponse { event: "light".to_string(), flag: "cave_lit".to_string(), value: 1 }
+    ];
+    assert_eq!(field_skill::respond(&responses, &sent[0]).collect::<Vec<_>>(), vec![("cave_passage", 1)]);
+    assert_eq!(field_skill::respond(&responses, "quake").count(), 0);
+
+    let light = field_skill::light_up();
+    assert_eq!((light.param, light.value), (EffectParam::Vignette, 0.0));
+}
+
+#[test]
+fn shortcuts_pick_whoever_needs_it_most() {
+    let (mut party, mut status, mut events, mut inventory) = (party(), FieldStatus::new(), FieldEvents::new(), Inventory::new());
+    // Cleave's only for battles, so it hasn't got one.
+    assert_eq!(field_skill::shortcuts(&party), vec![(1, 0), (1, 1)]);
+    assert_eq!(field_skill::best_target(&party, &ItemEffect::Restore { hp: 40, mp: 0 }), Some(1));
+
+    // The hotbar starts with them, and each use goes to whoever needs it most by then.
+    let mut hotbar = Hotbar::from_shortcuts(&party);
+    let mut use_cure = |party: &mut Party, status: &mut FieldStatus, events: &mut FieldEvents, inventory: &mut Inventory| {
+        hotbar.update(COOLDOWN);
+        hotbar.use_slot(0, party, status, events, inventory)
+    };
+    assert_eq!(use_cure(&mut party, &mut status, &mut events, &mut inventory), Ok("Tobin recovered 40 HP".to_string()));
+    assert_eq!(use_cure(&mut party, &mut status, &mut events, &mut inventory), Ok("Aria recovered 30 HP".to_string()));
+    assert_eq!(use_cure(&mut party, &mut status, &mut events, &mut inventory), Ok("Tobin recovered 20 HP".to_string()));
+    assert_eq!(use_cure(&mut party, &mut status, &mut events, &mut inventory), Err("Nobody needs Cure".to_string()));
+    assert_eq!(hotbar.use_slot(1, &mut party, &mut status, &mut events, &mut inventory), Ok("Used Reveal".to_string()));
+    assert_eq!(hotbar.use_slot(2, &mut party, &mut status, &mut events, &mut inventory), Err("There's nothing in that slot".to_string()));
+
+    party.members[1].stats.hp = 0;
+    hotbar.update(COOLDOWN);
+    assert_eq!(hotbar.use_slot(1, &mut party, &mut status, &mut events, &mut inventory), Err("Tobin is knocked out".to_string()));
+}
@@ -0,0 +1,147 @@
+// Repels, lures and sneaking, from items and skills, and poison and regen ticking as the party
+// walks.
+
+use ps_rpg_engine::{
+    field_skill::{self, FieldEvents},
+    field_status::{self, FieldBoost, FieldEffect, FieldStatus, PartyTick, ENCOUNTER_CHANCE, LURE_RATE, REPEL_RATE, STEP_LENGTH, TICK_STEPS},
+    formation::{BattleSetup, EncounterTable, Formation, Formations},
+    inventory::{Inventory, Item, ItemEffect},
+    party::{Party, PartyMember, Skill, Stats, StatusEffect, TargetType},
+    rng::RngStream,
+    screen_effects::EffectParam
+};
+
+fn boost(effect: FieldEffect, steps: u32) -> FieldBoost {
+    FieldBoost { effect, steps }
+}
+
+#[test]
+fn effects_are_read() {
+    assert_eq!("repel 100".parse(), Ok(boost(FieldEffect::Repel, 100)));
+    assert_eq!("field sneak 30".parse(), Ok(ItemEffect::Field(boost(FieldEffect::Sneak, 30))));
+    assert!("teleport 10".parse::<FieldBoost>().unwrap_err().contains("Unknown field effect"));
+    assert!("lure lots".parse::<FieldBoost>().unwrap_err().contains("Bad number of steps"));
+}
+
+#[test]
+fn effects_count_down_as_the_party_walks() {
+    let mut status = FieldStatus::new();
+    assert_eq!(status.apply(boost(FieldEffect::Repel, 2)), "Repel for 2 steps");
+    status.apply(boost(FieldEffect::Sneak, 3));
+    assert_eq!(status.encounter_chance(), ENCOUNTER_CHANCE * REPEL_RATE);
+
+    // Half steps add up.
+    assert_eq!(status.walk(STEP_LENGTH * 0.5), 0);
+    assert_eq!(status.walk(STEP_LENGTH * 1.75), 2);
+    assert!(status.step().is_empty());
+    assert_eq!(status.step(), [FieldEffect::Repel]);
+    assert_eq!(status.encounter_chance(), ENCOUNTER_CHANCE);
+    assert_eq!(status.step(), [FieldEffect::Sneak]);
+    assert!(status.effects().is_empty());
+}
+
+#[test]
+fn repel_and_lure_cancel_each_other_out() {
+    let mut status = FieldStatus::new();
+    status.apply(boost(FieldEffect::Repel, 100));
+    status.apply(boost(FieldEffect::Lure, 50));
+    assert_eq!(status.effects(), [boost(FieldEffect::Lure, 50)]);
+    assert_eq!(status.encounter_chance(), ENCOUNTER_CHANCE * LURE_RATE);
+
+    // Using the same again starts it over rather than adding up.
+    status.step();
+    status.apply(boost(FieldEffect::Lure, 50));
+    assert_eq!(status.effects(), [boost(FieldEffect::Lure, 50)]);
+}
+
+#[test]
+fn sneaking_stops_back_attacks() {
+    let formations = Formations::new(
+        Formation::parse_list("[slimes]\nenemy = slime 2 front").unwrap(),
+        EncounterTable::parse_list("[field]\nslimes = 1").unwrap()
+    ).unwrap();
+    let table = formations.table("field").unwrap();
+    let mut rng = RngStream::new(3, 1);
+    let mut setups: Vec<BattleSetup> = (0..100).map(|_| BattleSetup::from_table(&formations, table, &mut rng).unwrap()).collect();
+    assert!(setups.iter().any(|setup| setup.back_attack));
+
+    let mut status = FieldStatus::new();
+    status.apply(boost(FieldEffect::Sneak, 10));
+    for setup in &mut setups {
+        status.adjust(setup);
+    }
+    assert!(setups.iter().all(|setup| !setup.back_attack));
+}
+
+#[test]
+fn items_and_skills_start_effects() {
+    let repel = Item { id: "repel".to_string(), name: "Repel".to_string(), description: String::new(), effect: ItemEffect::Field(boost(FieldEffect::Repel, 100)) };
+    let mut inventory = Inventory::new();
+    inventory.add(&repel, 1);
+    let mut status = FieldStatus::new();
+    let mut events = FieldEvents::new();
+    let mut aria = PartyMember::new("Aria", Stats { hp: 10, max_hp: 10, mp: 4, ..Default::default() });
+    assert_eq!(inventory.use_on(0, &mut aria), Err("Repel is for the whole party".to_string()));
+    assert_eq!(inventory.use_on_field(0, &mut status, &mut events), Ok("Repel for 100 steps".to_string()));
+    assert_eq!(inventory.count("repel"), 0);
+    assert!(status.has(FieldEffect::Repel));
+
+    aria.skills.push(Skill { name: "Sneak".to_string(), description: String::new(), mp_cost: 3, costs: Vec::new(), target: TargetType::AllAllies, field: Some(ItemEffect::Field(boost(FieldEffect::Sneak, 60))) });
+    let mut party = Party::new(vec![aria]);
+    assert_eq!(field_skill::use_skill(&mut party, 0, 0, None, &mut status, &mut events, &mut inventory), Ok("Sneak for 60 steps".to_string()));
+    assert_eq!(party.members[0].stats.mp, 1);
+    assert_eq!(field_skill::use_skill(&mut party, 0, 0, None, &mut status, &mut events, &mut inventory), Err("Aria doesn't have enough MP".to_string()));
+}
+
+fn member(name: &str, hp: u32, status_effects: &[StatusEffect]) -> PartyMember {
+    let mut member = PartyMember::new(name, Stats { hp, max_hp: 100, ..Default::default() });
+    member.status_effects = status_effects.to_vec();
+    member
+}
+
+// Walk until poison and regen next tick.
+fn walk_to_tick(status: &mut FieldStatus, party: &mut Party) -> PartyTick {
+    for _ in 1..TICK_STEPS {
+        assert_eq!(status.tick_party(party), PartyTick::default());
+    }
+    status.tick_party(party)
+}
+
+#[test]
+fn poison_and_regen_tick_every_few_steps() {
+    let mut party = Party::new(vec![
+        member("Aria", 50, &[StatusEffect::Poison]),
+        member("Tobin", 50, &[StatusEffect::Regen]),
+        member("Mira", 0, &[StatusEffect::Poison, StatusEffect::Regen])
+    ]);
+    let mut status = FieldStatus::new();
+    let tick = walk_to_tick(&mut status, &mut party);
+    assert!(tick.hurt && tick.knocked_out.is_empty() && !tick.game_over);
+    let hp: Vec<u32> = party.members.iter().map(|member| member.stats.hp).collect();
+    assert_eq!(hp, [45, 55, 0]);
+}
+
+#[test]
+fn poison_knocking_out_the_leader_swaps_them_out() {
+    let mut party = Party::new(vec![member("Aria", 3, &[StatusEffect::Poison]), member("Tobin", 0, &[]), member("Mira", 80, &[])]);
+    let mut status = FieldStatus::new();
+    let tick = walk_to_tick(&mut status, &mut party);
+    assert_eq!(tick.knocked_out, ["Aria"]);
+    assert_eq!(tick.new_leader.as_deref(), Some("Mira"));
+    assert_eq!(party.leader().map(|leader| leader.name.as_str()), Some("Mira"));
+    assert!(!tick.game_over && !status.take_game_over());
+}
+
+#[test]
+fn poison_knocking_everyone_out_is_game_over() {
+    let mut party = Party::new(vec![member("Aria", 1, &[StatusEffect::Poison]), member("Tobin", 2, &[StatusEffect::Poison])]);
+    let mut status = FieldStatus::new();
+    let tick = walk_to_tick(&mut status, &mut party);
+    assert_eq!((tick.knocked_out.len(), tick.new_leader, tick.game_over), (2, None, true));
+    assert!(status.take_game_over());
+    assert!(!status.take_game_over());
+
+    let [flash, fade] = field_status::poison_flash(0.4);
+    assert_eq!((flash.param, flash.value, fade.value), (EffectParam::Fade, 0.4, 0.0));
+    assert!(flash.duration.is_zero() && !fade.duration.is_zero());
+}
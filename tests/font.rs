@@ -0,0 +1,79 @@
+// Fallback fonts for characters the built in font doesn't have, and how wide characters are.
+
+use ps_rpg_engine::{
+    font::{self, FallbackFont, FontAtlas, FontSource, Glyph},
+    ui::UiBatch
+};
+
+const FONTS: &str = "# Fonts\n\n[kana]\nimage = fonts/kana.png\ncharacters = fonts/kana.txt\ncell = 8x8\nlanguage = ja\n\n[hanzi]\nimage = fonts/hanzi.png\ncharacters = fonts/hanzi.txt\nlanguage = zh\n";
+
+// A sheet of solid 8x8 glyphs, one for each character.
+fn font(source: &FontSource, characters: &str) -> FallbackFont {
+    let sheet = image::RgbaImage::from_pixel(8 * characters.chars().count() as u32, 8, image::Rgba([255, 255, 255, 255]));
+    FallbackFont::from_sheet(&FontSource { cell: (8, 8), ..source.clone() }, &sheet, characters)
+}
+
+#[test]
+fn fonts_are_listed_in_order() {
+    let sources = FontSource::parse_list(FONTS).unwrap();
+    assert_eq!(sources.len(), 2);
+    assert_eq!(sources[0], FontSource {
+        name: "kana".to_string(),
+        image: "fonts/kana.png".to_string(),
+        characters: "fonts/kana.txt".to_string(),
+        cell: (8, 8),
+        language: Some("ja".to_string())
+    });
+    assert_eq!(sources[1].cell, (font::GLYPH_WIDTH, font::GLYPH_HEIGHT));
+
+    assert!(FontSource::parse_list("image = fonts/kana.png").unwrap_err().contains("[font name]"));
+    assert!(FontSource::parse_list("[kana]\ncell = big").unwrap_err().contains("cell size"));
+    assert!(FontSource::parse_list("[kana]\nimage = fonts/kana.png").unwrap_err().contains("needs an image and characters"));
+}
+
+#[test]
+fn the_players_language_goes_first() {
+    let sources = FontSource::parse_list(FONTS).unwrap();
+    let kana = font(&sources[0], "あい\n漢");
+    let hanzi = font(&sources[1], "漢字");
+    assert!(kana.contains('漢') && !kana.contains('\n'));
+
+    let names = |language: &str| font::fallback_order(&[kana.clone(), hanzi.clone()], language).iter().map(|font| font.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names("ja"), vec!["kana", "hanzi"]);
+    assert_eq!(names("zh-TW"), vec!["hanzi", "kana"]);
+
+    // Each character comes from the first font that has it.
+    let japanese = FontAtlas::new(&font::fallback_order(&[kana.clone(), hanzi.clone()], "ja"));
+    let chinese = FontAtlas::new(&font::fallback_order(&[kana.clone(), hanzi.clone()], "zh"));
+    assert!(japanese.contains('あ') && japanese.contains('字'));
+    assert_ne!(japanese.glyph('漢'), chinese.glyph('漢'));
+    assert_eq!(japanese.glyph('漢').columns, 2);
+    assert_eq!(japanese.width(), font::FALLBACK_ATLAS_COLUMNS * font::CELL_WIDTH);
+}
+
+#[test]
+fn missing_characters_fall_back() {
+    let builtin = FontAtlas::builtin();
+    assert_eq!((builtin.width(), builtin.height()), (font::ATLAS_WIDTH, font::ATLAS_HEIGHT));
+    assert_eq!(font::build_atlas().len() as u32, font::ATLAS_WIDTH * font::ATLAS_HEIGHT * 4);
+
+    // Full-width letters are drawn with the ASCII ones if nothing has them, anything else as '?'.
+    assert_eq!(builtin.glyph('Ａ'), builtin.glyph('A'));
+    assert_eq!(builtin.glyph('漢'), builtin.glyph('?'));
+    // Where it's always been, the 32nd cell.
+    let (x, y) = font::cell_origin('?' as u32 - ' ' as u32);
+    assert_eq!(builtin.glyph('?'), Glyph { x, y, columns: 1 });
+}
+
+#[test]
+fn wide_characters_take_two_cells() {
+    assert!(font::is_wide('あ') && font::is_wide('漢') && font::is_wide('！') && font::is_wide('한'));
+    assert!(!font::is_wide('a') && !font::is_wide('!') && !font::is_wide('é'));
+    assert_eq!(font::narrow_form('！'), Some('!'));
+    assert_eq!(font::narrow_form('あ'), None);
+
+    let cell = font::CELL_WIDTH as f32;
+    assert_eq!(UiBatch::measure_text(1.0, "ab").0, cell * 2.0);
+    assert_eq!(UiBatch::measure_text(1.0, "あb").0, cell * 3.0);
+    assert_eq!(UiBatch::new().text(0.0, 0.0, 1.0, "ＡＢ", [1.0; 4]), cell * 4.0);
+}
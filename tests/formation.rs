@@ -0,0 +1,162 @@
+// Enemy formations and encounter tables.
+
+use ps_rpg_engine::{
+    battle_scene::{BattleScene, BattleScenes},
+    field::FieldDescriptor,
+    formation::{BattleSetup, EncounterTable, Formation, Formations, Row, RowModifiers, BACK_ROW_ACCURACY, BACK_ROW_DAMAGE},
+    rng::RngStream
+};
+
+const FORMATIONS: &str = "
+# For testing.
+[goblins]
+name = Goblin Ambush
+intro = cave.goblin_ambush
+enemy = goblin 2-3 front
+enemy = shaman 0-1 back reach
+
+[ogre]
+music = music/ogre.cfg
+enemy = ogre 1 back at 0.5,-6 boss
+enemy = goblin 2 front
+";
+
+const TABLES: &str = "
+[cave]
+goblins = 3
+ogre = 1
+";
+
+fn formations() -> Formations {
+    Formations::new(Formation::parse_list(FORMATIONS).unwrap(), EncounterTable::parse_list(TABLES).unwrap()).unwrap()
+}
+
+#[test]
+fn formations_are_read() {
+    let formations = formations();
+    let goblins = formations.get("goblins").unwrap();
+    assert_eq!((goblins.name.as_str(), goblins.intro.as_deref()), ("Goblin Ambush", Some("cave.goblin_ambush")));
+    assert_eq!(goblins.enemies[0].count, (2, 3));
+    assert_eq!((goblins.enemies[1].row, goblins.enemies[1].reach), (Row::Back, true));
+    assert!(!goblins.enemies[0].reach);
+    assert!(!goblins.is_boss());
+
+    let ogre = formations.get("ogre").unwrap();
+    assert_eq!(ogre.name, "ogre");
+    assert_eq!((ogre.enemies[0].position, ogre.enemies[0].boss), (Some([0.5, -6.0]), true));
+    assert!(ogre.is_boss());
+}
+
+#[test]
+fn bad_formations_say_where() {
+    assert!(Formation::parse_list("enemy = goblin 1 front").unwrap_err().starts_with("Line 1"));
+    assert!(Formation::parse_list("[a]\nenemy = goblin 3-1 front").unwrap_err().contains("bad count for goblin"));
+    assert!(Formation::parse_list("[a]\nenemy = goblin 1 middle").unwrap_err().contains("Unknown row \"middle\""));
+    assert!(Formation::parse_list("[a]\n\nenemy = goblin 1 front at 1").unwrap_err().starts_with("Line 3: bad position"));
+    assert!(Formation::parse_list("[a]\nenemy = goblin 1 front sleeping").unwrap_err().contains("unexpected \"sleeping\""));
+    assert!(Formation::parse_list("[a]\nname = Nothing").unwrap_err().contains("doesn't have any enemies"));
+}
+
+#[test]
+fn tables_have_to_name_real_formations() {
+    let tables = EncounterTable::parse_list("[cave]\ngoblins = 1\ntrolls = 1").unwrap();
+    let error = Formations::new(Formation::parse_list(FORMATIONS).unwrap(), tables).unwrap_err();
+    assert_eq!(error, "Encounter table cave has trolls, which isn't a formation");
+    assert!(EncounterTable::parse_list("[cave]\ngoblins = lots").unwrap_err().contains("bad weight"));
+}
+
+#[test]
+fn counts_roll_within_their_range() {
+    let formations = formations();
+    let goblins = formations.get("goblins").unwrap();
+    let mut rng = RngStream::new(1, 2);
+    let mut seen = Vec::new();
+    for _ in 0..50 {
+        let enemies = goblins.assemble(&mut rng);
+        let count = |name: &str| enemies.iter().filter(|enemy| enemy.enemy == name).count();
+        assert!((2..=3).contains(&count("goblin")) && count("shaman") <= 1);
+        seen.push((count("goblin"), count("shaman")));
+    }
+    for expected in [(2, 0), (3, 1)] {
+        assert!(seen.contains(&expected), "{:?}", seen);
+    }
+}
+
+#[test]
+fn rows_are_spread_out_and_placed_enemies_stay_put() {
+    let formations = formations();
+    let enemies = formations.get("ogre").unwrap().assemble(&mut RngStream::new(1, 2));
+    let positions: Vec<(&str, [f32; 2])> = enemies.iter().map(|enemy| (enemy.enemy.as_str(), enemy.position)).collect();
+    assert_eq!(positions, [("ogre", [0.5, -6.0]), ("goblin", [-0.75, Row::Front.depth()]), ("goblin", [0.75, Row::Front.depth()])]);
+}
+
+#[test]
+fn battles_are_set_up_from_formations_or_tables() {
+    let formations = formations();
+    let mut rng = RngStream::new(7, 1);
+    let boss = BattleSetup::new(formations.get("ogre").unwrap(), &mut rng);
+    assert_eq!((boss.formation.as_str(), boss.can_escape), ("ogre", false));
+    assert!(boss.enemies.iter().any(|enemy| enemy.boss));
+
+    let table = formations.table("cave").unwrap();
+    let picked: Vec<String> = (0..40).map(|_| BattleSetup::from_table(&formations, table, &mut rng).unwrap().formation).collect();
+    assert!(picked.iter().any(|id| id == "goblins") && picked.iter().any(|id| id == "ogre"));
+    assert!(picked.iter().filter(|id| *id == "goblins").count() > picked.iter().filter(|id| *id == "ogre").count());
+}
+
+#[test]
+fn the_back_row_weakens_attacks_without_reach() {
+    assert_eq!(RowModifiers::new(Row::Front, Row::Front, false), RowModifiers { damage: 1.0, accuracy: 1.0 });
+    assert_eq!(RowModifiers::new(Row::Back, Row::Front, false), RowModifiers { damage: BACK_ROW_DAMAGE, accuracy: 1.0 });
+    assert_eq!(RowModifiers::new(Row::Front, Row::Back, false), RowModifiers { damage: BACK_ROW_DAMAGE, accuracy: BACK_ROW_ACCURACY });
+    assert_eq!(RowModifiers::new(Row::Back, Row::Back, false).damage, BACK_ROW_DAMAGE * BACK_ROW_DAMAGE);
+    assert_eq!(RowModifiers::new(Row::Back, Row::Back, true), RowModifiers { damage: 1.0, accuracy: 1.0 });
+    assert_eq!(Row::Front.other(), Row::Back);
+}
+
+const SCENES: &str = "
+[default]
+backdrop = battles/plains.png
+music = music/battle.cfg
+
+[cave]
+backdrop = battles/cave.png
+";
+
+fn scene(backdrop: &str, music: &str) -> BattleScene {
+    BattleScene { backdrop: Some(backdrop.to_string()), music: Some(music.to_string()) }
+}
+
+#[test]
+fn battle_scenes_come_from_the_fields_terrain() {
+    let scenes = BattleScenes::parse(SCENES).unwrap();
+    let cave = FieldDescriptor { terrain: "cave".to_string(), ..Default::default() };
+    let mut rng = RngStream::new(1, 0);
+    let setup = BattleSetup::new(formations().get("goblins").unwrap(), &mut rng);
+    assert_eq!(scenes.resolve(&setup.scene, Some(&cave)), scene("battles/cave.png", "music/battle.cfg"));
+
+    // Unknown terrains and battles outside a field get the default.
+    let swamp = FieldDescriptor { terrain: "swamp".to_string(), ..Default::default() };
+    assert_eq!(scenes.resolve(&setup.scene, Some(&swamp)), scene("battles/plains.png", "music/battle.cfg"));
+    assert_eq!(scenes.resolve(&setup.scene, None), scene("battles/plains.png", "music/battle.cfg"));
+
+    assert!(BattleScenes::parse("backdrop = a.png").unwrap_err().contains("Line 1"));
+    assert!(BattleScenes::parse("[cave]\nweather = rain").unwrap_err().contains("unknown key"));
+}
+
+#[test]
+fn fields_and_formations_override_the_terrain() {
+    let scenes = BattleScenes::parse(SCENES).unwrap();
+    let shrine = FieldDescriptor {
+        terrain: "cave".to_string(),
+        battle_scene: BattleScene { backdrop: Some("battles/shrine.png".to_string()), music: None },
+        ..Default::default()
+    };
+    let mut rng = RngStream::new(1, 0);
+    let goblins = BattleSetup::new(formations().get("goblins").unwrap(), &mut rng);
+    assert_eq!(scenes.resolve(&goblins.scene, Some(&shrine)), scene("battles/shrine.png", "music/battle.cfg"));
+
+    // The ogre has a theme of its own wherever it's fought.
+    let ogre = BattleSetup::new(formations().get("ogre").unwrap(), &mut rng);
+    assert_eq!(scenes.resolve(&ogre.scene, Some(&shrine)), scene("battles/shrine.png", "music/ogre.cfg"));
+}
@@ -0,0 +1,102 @@
+// Recording the player's movement and playing it back as a ghost.
+
+use std::time::Duration;
+
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+use ps_rpg_engine::{
+    animation::{AnimationClip, AnimationPlayer},
+    ghost::{self, Ghost, GhostTrack, TICK_RATE},
+    transform::Transform,
+    world::{Entity, World}
+};
+
+fn tick() -> Duration {
+    Duration::from_secs(1) / TICK_RATE
+}
+
+fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!((actual - expected).magnitude() < 1e-2, "Expected {:?}, got {:?}", expected, actual);
+}
+
+// Someone walking 1m along x every tick, then standing still.
+fn record(world: &mut World, runner: Entity) -> GhostTrack {
+    let mut walk = AnimationPlayer::new();
+    walk.play(AnimationClip::new("walk", Duration::from_secs(1), true));
+    world.insert(runner, walk);
+    ghost::start_recording(world, runner, "race_track");
+    for _ in 0..3 {
+        world.get_mut::<Transform>(runner).unwrap().position.x += 1.0;
+        ghost::record(world, tick());
+    }
+    world.get_mut::<AnimationPlayer>(runner).unwrap().play(AnimationClip::new("idle", Duration::from_secs(2), true));
+    // Half a tick doesn't make a new one.
+    ghost::record(world, tick() / 2);
+    ghost::record(world, tick() - tick() / 2);
+    ghost::stop_recording(world).unwrap()
+}
+
+#[test]
+fn recordings_take_a_tick_at_a_time() {
+    let mut world = World::new();
+    let runner = world.spawn();
+    world.insert(runner, Transform { rotation: Quaternion::from_angle_y(Deg(90.0)), ..Default::default() });
+    let track = record(&mut world, runner);
+    assert!(ghost::stop_recording(&mut world).is_none());
+
+    assert_eq!(track.field, "race_track");
+    assert_eq!(track.frames().len(), 5);
+    assert_eq!(track.duration(), Duration::from_secs(4) / TICK_RATE);
+    let clips: Vec<&str> = track.frames().iter().map(|frame| track.clip(frame.clip.unwrap()).unwrap().name.as_str()).collect();
+    assert_eq!(clips, vec!["walk", "walk", "walk", "walk", "idle"]);
+    assert!((track.frames()[0].facing - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+
+    // Between ticks is between where they were.
+    assert_near(track.sample(tick() + tick() / 2).unwrap().position, Vector3::new(1.5, 0.0, 0.0));
+    assert_near(track.sample(Duration::from_secs(10)).unwrap().position, Vector3::new(3.0, 0.0, 0.0));
+}
+
+#[test]
+fn recordings_survive_being_saved() {
+    let mut track = GhostTrack::new("race_track");
+    track.push(Vector3::new(1.25, -0.5, 320.0), 3.0, Some(&AnimationClip::new("run", Duration::from_millis(800), true)));
+    track.push(Vector3::new(1.5, -0.5, 321.0), -1.0, None);
+    let bytes = track.to_bytes();
+    let loaded = GhostTrack::from_bytes(&bytes).unwrap();
+
+    assert_eq!(loaded.field, "race_track");
+    assert_eq!(loaded.clip(0), Some(&AnimationClip::new("run", Duration::from_millis(800), true)));
+    assert_eq!(loaded.frames()[1].clip, None);
+    assert_near(loaded.frames()[0].position, Vector3::new(1.25, -0.5, 320.0));
+    // Facing comes back as the same direction, if not the same number.
+    let turn = (loaded.frames()[1].facing - -1.0).rem_euclid(std::f32::consts::TAU);
+    assert!(turn < 1e-3 || std::f32::consts::TAU - turn < 1e-3);
+
+    assert!(GhostTrack::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(GhostTrack::from_bytes(b"PSRPGSAV").is_err());
+}
+
+#[test]
+fn ghosts_play_back_then_go() {
+    let mut world = World::new();
+    let runner = world.spawn();
+    world.insert(runner, Transform::default());
+    let track = record(&mut world, runner);
+
+    let ghost = ghost::spawn_ghost(&mut world, track.clone(), None, false);
+    assert!(ghost::update_ghosts(&mut world, tick() * 2));
+    assert_near(world.get::<Transform>(ghost).unwrap().position, Vector3::new(2.0, 0.0, 0.0));
+    assert_eq!(world.get::<AnimationPlayer>(ghost).unwrap().clip().unwrap().name, "walk");
+    ghost::update_ghosts(&mut world, tick() * 2);
+    assert_eq!(world.get::<AnimationPlayer>(ghost).unwrap().clip().unwrap().name, "idle");
+    assert!(!ghost::update_ghosts(&mut world, tick()));
+    assert!(!world.contains(ghost));
+
+    // Looping ones go round again.
+    let ghost = ghost::spawn_ghost(&mut world, track, None, true);
+    ghost::update_ghosts(&mut world, tick() * 5);
+    assert!(world.get::<Ghost>(ghost).unwrap().time().abs_diff(tick()) < Duration::from_micros(1));
+    assert_near(world.get::<Transform>(ghost).unwrap().position, Vector3::new(1.0, 0.0, 0.0));
+    ghost::despawn_ghosts(&mut world);
+    assert!(!world.contains(ghost));
+}
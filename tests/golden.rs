@@ -0,0 +1,831 @@
+// Golden image tests. Each scene is rendered with a headless renderer, read back and compared
+// against a reference image in tests/golden, with a little tolerance for differences between GPUs.
+//
+// To update the references after an intended change, run with UPDATE_GOLDEN=1. Missing
+// references are written out the first time a test runs. Failed renders are saved to
+// target/golden for a look.
+//
+// The tests are skipped if there's no adapter to render with.
+
+use std::{path::{Path, PathBuf}, time::Duration};
+
+use cgmath::{Matrix4, Point3, Vector3, Vector4, InnerSpace};
+use image::{Rgba, RgbaImage};
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    battle_transition::{ScreenTransition, TransitionStyle},
+    camera::Camera,
+    model::{AlphaMode, LodPolicy, MaterialData, ModelBatch, ModelData, MorphWeights},
+    color_filter::{ColorBlindness, ColorFilter},
+    depth_of_field::{DepthOfField, DofQuality},
+    field::{FieldOccluder, FieldReflection},
+    renderer::{Renderer, PostProcessSettings, SCREEN_WIDTH, SCREEN_HEIGHT},
+    tilemap::{TileLayer, Tilemap, Tileset},
+    ui::{UiBatch, WHITE}
+};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 200;
+
+// How far apart a channel can be before the pixel counts as different.
+const CHANNEL_TOLERANCE: u8 = 8;
+// How many pixels can be different before the images don't match.
+const MAX_DIFFERENT_PIXELS: f32 = 0.005;
+
+fn headless_renderer() -> Option<Renderer> {
+    let runtime = tokio::runtime::Runtime::new().ok()?;
+    let renderer = runtime.block_on(Renderer::new_headless(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), WIDTH, HEIGHT));
+    if renderer.is_none() {
+        eprintln!("No GPU adapter available, skipping golden image test.");
+    }
+    renderer
+}
+
+fn render(renderer: &mut Renderer, ui_batch: &UiBatch) -> RgbaImage {
+    renderer.render(&ModelBatch::default(), ui_batch, None).expect("Headless render failed");
+    renderer.read_pixels().expect("Headless renderer should be able to read back pixels")
+}
+
+// A background with known colours: a hue gradient across, getting darker going down, with a
+// white border.
+fn gradient_image() -> RgbaImage {
+    RgbaImage::from_fn(64, 80, |x, y| {
+        if x == 0 || y == 0 || x == 63 || y == 79 {
+            return Rgba([255, 255, 255, 255]);
+        }
+        let shade = 255 - y * 2;
+        Rgba([(x * 4 * shade / 255) as u8, ((63 - x) * 4 * shade / 255) as u8, (y * 3) as u8, 255])
+    })
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name))
+}
+
+fn assert_matches_golden(name: &str, actual: &RgbaImage) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() || !path.exists() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&path).unwrap();
+        eprintln!("Wrote golden image {}", path.display());
+        return;
+    }
+
+    let expected = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{} is a different size to its golden image", name);
+
+    let different = expected.pixels().zip(actual.pixels())
+        .filter(|(e, a)| e.0.iter().zip(a.0.iter()).any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE))
+        .count();
+    let allowed = (expected.pixels().len() as f32 * MAX_DIFFERENT_PIXELS) as usize;
+
+    if different > allowed {
+        let failure_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("target").join("golden").join(format!("{}.png", name));
+        std::fs::create_dir_all(failure_path.parent().unwrap()).unwrap();
+        actual.save(&failure_path).unwrap();
+        panic!("{} has {} pixels that don't match its golden image (allowed {}), saved the render to {}",
+            name, different, allowed, failure_path.display());
+    }
+}
+
+#[test]
+fn field_background() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+
+    let image = render(&mut renderer, &UiBatch::new());
+    assert_matches_golden("field_background", &image);
+}
+
+#[test]
+fn known_background() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+
+    let image = render(&mut renderer, &UiBatch::new());
+    assert_matches_golden("known_background", &image);
+}
+
+#[test]
+fn ui_composited_over_background() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+
+    let mut batch = UiBatch::new();
+    batch.rect(20.0, 600.0, 600.0, 180.0, [0.0, 0.0, 0.3, 0.75]);
+    batch.text(40.0, 620.0, 4.0, "Hello, world!", WHITE);
+
+    let image = render(&mut renderer, &batch);
+    assert_matches_golden("ui_composited_over_background", &image);
+
+    // The UI is drawn before post processing, so it should be in the bottom of the screen.
+    let panel = image.get_pixel(WIDTH / 2, HEIGHT - 8);
+    assert!(panel[2] > panel[0] && panel[2] > panel[1], "Expected the blue panel, got {:?}", panel);
+}
+
+#[test]
+fn ui_image_from_screen_capture() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    render(&mut renderer, &UiBatch::new());
+
+    // Draw a shrunk copy of the screen over itself, like a save slot thumbnail.
+    let capture = renderer.capture_screen().expect("Should be able to capture the screen");
+    let thumbnail = renderer.create_ui_image(&image::imageops::thumbnail(&capture, 160, 200));
+    let mut batch = UiBatch::new();
+    batch.rect(20.0, 20.0, 170.0, 210.0, WHITE);
+    batch.image(25.0, 25.0, 160.0, 200.0, thumbnail, WHITE);
+
+    let image = render(&mut renderer, &batch);
+    assert_matches_golden("ui_image_from_screen_capture", &image);
+}
+
+#[test]
+fn model_lods_and_draw_distance() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255])));
+
+    // Give each detail level its own colour so it's obvious which one was drawn.
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    assert_eq!(model.lods.len(), 3);
+    model.materials = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]].into_iter()
+        .map(|base_color| MaterialData { base_color, ..Default::default() })
+        .collect();
+    for (level, meshes) in model.lods.iter_mut().enumerate() {
+        for mesh in meshes {
+            mesh.material = level;
+        }
+    }
+    let model = renderer.create_model(&model);
+
+    // Close enough for the detailed model, then further and further away. The last one is
+    // past the draw distance.
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.set_max_draw_distance(Some(50.0));
+    for position in [Vector3::new(-1.5, 0.0, 5.0), Vector3::new(0.0, 0.0, -5.0), Vector3::new(1.5, 0.0, -25.0), Vector3::new(0.0, 0.0, -70.0)] {
+        batch.add(model, Matrix4::from_translation(position));
+    }
+
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("model_lods_and_draw_distance", &image);
+
+    assert_eq!(renderer.get_stats().models_culled, 1);
+    for channel in 0..3 {
+        let found = image.pixels().any(|pixel| (0..3).all(|other| other == channel || pixel.0[channel] as u32 > pixel.0[other] as u32 + 40));
+        assert!(found, "Expected some of detail level {} on screen", channel);
+    }
+}
+
+#[test]
+fn identical_models_are_instanced() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    let model = renderer.create_model(&model);
+    renderer.set_model_lod_policy(model, LodPolicy::Distance(Vec::new()));
+
+    let mut batch = ModelBatch::new(Camera::default());
+    for x in 0..5 {
+        for z in 0..5 {
+            batch.add(model, Matrix4::from_translation(Vector3::new(x as f32 - 2.0, 0.0, -(z as f32) * 2.0)));
+        }
+    }
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+
+    // One draw for the background, one for all of the models and one for post processing.
+    let stats = renderer.get_stats();
+    assert_eq!(stats.model_instances, 25);
+    assert_eq!(stats.draw_calls, 3);
+
+    // The next frame reuses the instance buffer and should draw the same thing.
+    let first = renderer.read_pixels().unwrap();
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    assert_eq!(renderer.get_stats().model_instances, 25);
+    assert!(first == renderer.read_pixels().unwrap(), "The second frame should match the first");
+}
+
+#[test]
+fn transparent_models_blend_over_opaque_ones() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255])));
+
+    // The same prop as a solid red model and a half see-through blue one.
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData { base_color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }];
+    let red = renderer.create_model(&model);
+    model.materials = vec![MaterialData { base_color: [0.0, 0.0, 1.0, 0.5], alpha_mode: AlphaMode::Blend, ..Default::default() }];
+    let glass = renderer.create_model(&model);
+    for id in [red, glass] {
+        renderer.set_model_lod_policy(id, LodPolicy::Distance(Vec::new()));
+    }
+
+    // The glass goes in the batch first but in front of the red model, so it has to be drawn
+    // after it. The red model on the left is fading out over the background.
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(glass, Matrix4::from_translation(Vector3::new(0.3, 2.0, 2.8)));
+    batch.add(red, Matrix4::from_translation(Vector3::new(0.5, 0.0, -2.0)));
+    batch.add_with_opacity(red, Matrix4::from_translation(Vector3::new(-2.0, 0.0, 0.0)), 0.5);
+    batch.add_with_opacity(red, Matrix4::from_translation(Vector3::new(-2.0, 0.0, -6.0)), 0.0);
+
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("transparent_models_blend_over_opaque_ones", &image);
+
+    // Completely faded out models aren't drawn at all.
+    assert_eq!(renderer.get_stats().model_instances, 3);
+    let purple = image.pixels().any(|pixel| pixel.0[0] > pixel.0[1] + 40 && pixel.0[2] > pixel.0[1] + 40);
+    assert!(purple, "Expected the red model to show through the glass");
+}
+
+#[test]
+fn alpha_cutout_models() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255])));
+
+    // Green stripes with see-through gaps between them, like a fence.
+    let stripes = RgbaImage::from_fn(8, 8, |x, _| if x % 2 == 0 { Rgba([0, 255, 0, 255]) } else { Rgba([0, 255, 0, 0]) });
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData {
+        base_color_texture: Some(stripes),
+        alpha_mode: AlphaMode::Mask(0.5),
+        ..Default::default()
+    }];
+    let fence = renderer.create_model(&model);
+    renderer.set_model_lod_policy(fence, LodPolicy::Distance(Vec::new()));
+
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(fence, Matrix4::from_scale(2.0));
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("alpha_cutout_models", &image);
+
+    // Somewhere between the leftmost and rightmost stripe, across the widest part of the
+    // model, the background shows through.
+    let background = *image.get_pixel(0, 0);
+    let is_stripe = |pixel: &Rgba<u8>| pixel.0[1] > pixel.0[0] + 40;
+    let row: Vec<_> = (0..HEIGHT)
+        .map(|y| (0..WIDTH).map(|x| *image.get_pixel(x, y)).collect::<Vec<_>>())
+        .max_by_key(|row| row.iter().filter(|pixel| is_stripe(pixel)).count())
+        .unwrap();
+    let first = row.iter().position(is_stripe).expect("Expected some of the stripes");
+    let last = row.iter().rposition(is_stripe).unwrap();
+    assert!(row[first..last].contains(&background), "Expected the background through the gaps");
+}
+
+#[test]
+fn field_occluders_hide_models_behind_them() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255])));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData { base_color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }];
+    let red = renderer.create_model(&model);
+    renderer.set_model_lod_policy(red, LodPolicy::Distance(Vec::new()));
+
+    // Blue pillars down each half of the screen, with a see-through edge. The camera is about
+    // 11 from the model, so the left one is in front of it and the right one behind.
+    let pillar = RgbaImage::from_fn(8, 8, |x, _| if x == 0 { Rgba([0, 0, 255, 0]) } else { Rgba([0, 0, 255, 255]) });
+    let half = SCREEN_WIDTH as f32 / 2.0;
+    for (x, depth) in [(0.0, 5.0), (half, 20.0)] {
+        let occluder = FieldOccluder {
+            image: String::new(),
+            x,
+            y: 0.0,
+            width: half,
+            height: SCREEN_HEIGHT as f32,
+            depth
+        };
+        renderer.add_field_occluder(&occluder, &pillar);
+    }
+
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(red, Matrix4::from_scale(2.0));
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("field_occluders_hide_models_behind_them", &image);
+
+    // The model only shows on the right, where it's in front of the pillar.
+    let is_red = |pixel: &Rgba<u8>| pixel.0[0] as u32 > pixel.0[2] as u32 + 40;
+    let red_columns: Vec<u32> = (0..WIDTH).filter(|x| (0..HEIGHT).any(|y| is_red(image.get_pixel(*x, y)))).collect();
+    assert!(!red_columns.is_empty(), "Expected the model in front of the right pillar");
+    assert!(red_columns.iter().all(|x| *x >= WIDTH / 2), "Expected the left pillar to hide the model, found it at {:?}", red_columns);
+
+    renderer.clear_field_occluders();
+}
+
+#[test]
+fn reflections_mirror_models() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_fn(4, 4, |_, y| if y < 2 { Rgba([128, 128, 128, 255]) } else { Rgba([20, 40, 90, 255]) }));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData { base_color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }];
+    let red = renderer.create_model(&model);
+    renderer.set_model_lod_policy(red, LodPolicy::Distance(Vec::new()));
+
+    // The model floats over to the right, above a pond filling the bottom half of the screen.
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(red, Matrix4::from_translation(Vector3::new(2.0, 0.0, 0.0)));
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let plain = renderer.read_pixels().unwrap();
+
+    renderer.set_field_reflections(&[FieldReflection {
+        x: 0.0,
+        y: SCREEN_HEIGHT as f32 / 2.0,
+        width: SCREEN_WIDTH as f32,
+        height: SCREEN_HEIGHT as f32 / 2.0,
+        point: Point3::new(0.0, -2.0, 0.0),
+        normal: Vector3::new(0.0, 1.0, 0.0),
+        strength: 0.75,
+        ripple: 3.0
+    }]);
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    renderer.set_field_reflections(&[]);
+    assert_matches_golden("reflections_mirror_models", &image);
+
+    // Nothing changes outside the pond, and the model shows up in it on the same side.
+    for y in 0..HEIGHT / 2 {
+        for x in 0..WIDTH {
+            assert_eq!(image.get_pixel(x, y), plain.get_pixel(x, y), "Changed outside the reflection at {}, {}", x, y);
+        }
+    }
+    let is_red = |pixel: &Rgba<u8>| pixel.0[0] as u32 > pixel.0[2] as u32 + 40;
+    let reflected: Vec<(u32, u32)> = (HEIGHT / 2..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .filter(|(x, y)| is_red(image.get_pixel(*x, *y)) && !is_red(plain.get_pixel(*x, *y)))
+        .collect();
+    assert!(!reflected.is_empty(), "Expected the model's reflection in the pond");
+    assert!(reflected.iter().all(|(x, _)| *x > WIDTH / 2), "Expected the reflection on the right, like the model");
+
+    // Once the reflections are gone the pond is just background again.
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    assert!(renderer.read_pixels().unwrap() == plain);
+}
+
+#[test]
+fn tilemap_layers_draw_under_and_over_models() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData { base_color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }];
+    let red = renderer.create_model(&model);
+    renderer.set_model_lod_policy(red, LodPolicy::Distance(Vec::new()));
+
+    // Three tiles: grey, yellow on the left and green on the right, and blue.
+    let (tile_width, tile_height) = (80, 100);
+    let atlas = RgbaImage::from_fn(tile_width * 3, tile_height, |x, _| match x / tile_width {
+        0 => Rgba([64, 64, 64, 255]),
+        1 if x % tile_width < tile_width / 2 => Rgba([255, 255, 0, 255]),
+        1 => Rgba([0, 255, 0, 255]),
+        _ => Rgba([0, 0, 255, 255])
+    });
+
+    // A checkerboard of the first two on the ground, with the second flipped on odd rows, and
+    // the blue one over the right half of the middle.
+    let (width, height) = (SCREEN_WIDTH as u32 / tile_width, SCREEN_HEIGHT as u32 / tile_height);
+    let ground = (0..width * height).map(|i| {
+        let (x, y) = (i % width, i / width);
+        match ((x + y) % 2, y % 2) {
+            (0, _) => 1,
+            (_, 0) => 2,
+            _ => 2 | 0x8000_0000
+        }
+    }).collect();
+    let above = (0..width * height).map(|i| {
+        let (x, y) = (i % width, i / width);
+        if x >= width / 2 && (2..6).contains(&y) { 3 } else { 0 }
+    }).collect();
+    let layer = |name: &str, tiles, above| TileLayer { name: name.to_string(), width, height, tiles, visible: true, above };
+    let map = Tilemap {
+        width,
+        height,
+        tile_width,
+        tile_height,
+        tilesets: vec![Tileset {
+            first_gid: 1,
+            image: String::new(),
+            image_width: atlas.width(),
+            image_height: atlas.height(),
+            tile_width,
+            tile_height,
+            columns: 3,
+            tile_count: 3,
+            ..Default::default()
+        }],
+        layers: vec![layer("ground", ground, false), layer("above", above, true)],
+        atlases: vec![atlas],
+        ..Default::default()
+    };
+    renderer.set_tilemap(&map);
+    renderer.set_tilemap_scroll(map.scroll_to(0.0, 0.0));
+
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(red, Matrix4::from_scale(2.0));
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("tilemap_layers_draw_under_and_over_models", &image);
+
+    // The model's over the ground, but under the blue tiles on the right.
+    let is_red = |pixel: &Rgba<u8>| pixel.0[0] as u32 > pixel.0[1] as u32 + 80 && pixel.0[0] as u32 > pixel.0[2] as u32 + 80;
+    let red_columns: Vec<u32> = (0..WIDTH).filter(|x| (0..HEIGHT).any(|y| is_red(image.get_pixel(*x, y)))).collect();
+    assert!(!red_columns.is_empty(), "Expected the model over the ground");
+    assert!(red_columns.iter().all(|x| *x < WIDTH / 2), "Expected the tiles above to hide the model, found it at {:?}", red_columns);
+
+    renderer.clear_tilemap();
+}
+
+#[test]
+fn normal_and_emissive_maps() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([128, 128, 128, 255])));
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    let plain = renderer.create_model(&model);
+
+    // Normals bent hard to one side, which should change the shading.
+    model.materials = vec![MaterialData {
+        normal_texture: Some(RgbaImage::from_pixel(1, 1, Rgba([230, 128, 180, 255]))),
+        ..Default::default()
+    }];
+    let bumpy = renderer.create_model(&model);
+
+    // A black model that glows green in stripes.
+    model.materials = vec![MaterialData {
+        base_color: [0.0, 0.0, 0.0, 1.0],
+        emissive: [0.0, 1.0, 0.0],
+        emissive_texture: Some(RgbaImage::from_fn(8, 8, |x, _| if x % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) })),
+        ..Default::default()
+    }];
+    let glowing = renderer.create_model(&model);
+    for id in [plain, bumpy, glowing] {
+        renderer.set_model_lod_policy(id, LodPolicy::Distance(Vec::new()));
+    }
+
+    let draw = |renderer: &mut Renderer, surface| {
+        let mut batch = ModelBatch::new(Camera::default());
+        batch.add(surface, Matrix4::from_translation(Vector3::new(-1.5, 0.0, 0.0)));
+        batch.add(glowing, Matrix4::from_translation(Vector3::new(1.5, 0.0, 0.0)));
+        renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+        renderer.read_pixels().unwrap()
+    };
+
+    let image = draw(&mut renderer, bumpy);
+    assert_matches_golden("normal_and_emissive_maps", &image);
+    assert!(draw(&mut renderer, plain) != image, "The normal map should change the shading");
+
+    // The glowing model is lit by its emissive map alone.
+    let green = image.pixels().any(|pixel| pixel.0[1] > 200 && pixel.0[0] < 40 && pixel.0[2] < 40);
+    assert!(green, "Expected the emissive stripes on screen");
+}
+
+#[test]
+fn morph_targets() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255])));
+
+    // A square with its top edge raised by "mouth_open" and its sides pushed out by "wide".
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_face.gltf")).unwrap();
+    assert_eq!(model.morph_targets, vec!["mouth_open".to_string(), "wide".to_string()]);
+    let face = renderer.create_model(&model);
+    renderer.set_model_lod_policy(face, LodPolicy::Distance(Vec::new()));
+
+    let mut weights = MorphWeights::new(&model);
+    assert!(weights.set("mouth_open", 1.0));
+    assert!(!weights.set("frown", 1.0));
+    // Halfway through blending to 1.
+    weights.animate("wide", 1.0, Duration::from_secs(2));
+    weights.update(Duration::from_secs(1));
+    assert_eq!(weights.get("wide"), Some(0.5));
+
+    let scale = Matrix4::from_scale(2.0);
+    let mut batch = ModelBatch::new(Camera::default());
+    batch.add(face, Matrix4::from_translation(Vector3::new(-1.8, 0.0, 0.0)) * scale);
+    batch.add_morphed(face, Matrix4::from_translation(Vector3::new(1.8, 0.0, 0.0)) * scale, &weights);
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("morph_targets", &image);
+
+    // The morphed square covers more of the screen than the plain one.
+    let covered = |xs: std::ops::Range<u32>| xs.flat_map(|x| (0..HEIGHT).map(move |y| (x, y)))
+        .filter(|(x, y)| image.get_pixel(*x, *y).0[0] > 20)
+        .count();
+    let (plain, morphed) = (covered(0..WIDTH / 2), covered(WIDTH / 2..WIDTH));
+    assert!(morphed > plain * 3 / 2, "Expected the morphed square to be bigger, {} against {}", morphed, plain);
+}
+
+#[test]
+fn post_process_desaturate() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    *renderer.get_post_process_settings_mut() = PostProcessSettings {
+        saturation: 0.0,
+        ..PostProcessSettings::default()
+    };
+
+    let image = render(&mut renderer, &UiBatch::new());
+    assert_matches_golden("post_process_desaturate", &image);
+
+    // With no saturation every pixel should be grey.
+    for pixel in image.pixels() {
+        let [r, g, b, _] = pixel.0;
+        assert!(r.abs_diff(g) <= 2 && g.abs_diff(b) <= 2, "Expected grey, got {:?}", pixel);
+    }
+}
+
+#[test]
+fn post_process_vignette_and_tint() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    *renderer.get_post_process_settings_mut() = PostProcessSettings {
+        vignette: 1.0,
+        tint: [1.0, 0.5, 0.0],
+        tint_strength: 0.5,
+        ..PostProcessSettings::default()
+    };
+    let image = render(&mut renderer, &UiBatch::new());
+    assert_matches_golden("post_process_vignette_and_tint", &image);
+
+    // The vignette should darken the corners compared to an unprocessed render.
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+    let plain = render(&mut renderer, &UiBatch::new());
+    let brightness = |pixel: &Rgba<u8>| pixel.0[..3].iter().map(|c| *c as u32).sum::<u32>();
+    assert!(brightness(image.get_pixel(1, 1)) < brightness(plain.get_pixel(1, 1)));
+}
+
+#[test]
+fn post_process_color_filter() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    let plain = render(&mut renderer, &UiBatch::new());
+
+    renderer.get_post_process_settings_mut().color_filter = ColorFilter::Correct(ColorBlindness::Deuteranopia);
+    let image = render(&mut renderer, &UiBatch::new());
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+    assert_matches_golden("post_process_color_filter", &image);
+
+    // White has nothing to lose, so it should stay white while the colours change.
+    let (white, plain_white) = (image.get_pixel(0, 0).0, plain.get_pixel(0, 0).0);
+    assert!(white.iter().zip(plain_white.iter()).all(|(a, b)| a.abs_diff(*b) <= 2), "Expected white, got {:?}", white);
+    assert!(image.pixels().zip(plain.pixels()).any(|(filtered, plain)| filtered != plain));
+}
+
+#[test]
+fn post_process_fade_and_offset() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    let plain = render(&mut renderer, &UiBatch::new());
+
+    // Halfway to white, shaken 40 pixels to the right.
+    *renderer.get_post_process_settings_mut() = PostProcessSettings {
+        fade: [1.0, 1.0, 1.0, 0.5],
+        offset: [40.0, 0.0],
+        ..PostProcessSettings::default()
+    };
+    let image = render(&mut renderer, &UiBatch::new());
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+    assert_matches_golden("post_process_fade_and_offset", &image);
+
+    // 40 virtual pixels is 10 read back. Everything moved over and got paler. The fade's
+    // worked out in linear colour, so the halfway point is brighter than it looks.
+    let (x, y) = (WIDTH / 2, HEIGHT / 2);
+    let shifted = plain.get_pixel(x - 10, y).0;
+    let faded = image.get_pixel(x, y).0;
+    let to_linear = |c: u8| ((c as f32 / 255.0 + 0.055) / 1.055).powf(2.4);
+    let to_srgb = |c: f32| ((1.055 * c.powf(1.0 / 2.4) - 0.055) * 255.0).round() as i32;
+    for channel in 0..3 {
+        let expected = to_srgb((to_linear(shifted[channel]) + 1.0) / 2.0);
+        assert!((faded[channel] as i32 - expected).abs() <= 8, "Expected about {} in channel {}, got {:?}", expected, channel, faded);
+    }
+}
+
+#[test]
+fn post_process_darkness() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    let plain = render(&mut renderer, &UiBatch::new());
+
+    // Pitch dark apart from a circle of light a quarter of the way across.
+    *renderer.get_post_process_settings_mut() = PostProcessSettings {
+        darkness: 1.0,
+        light_position: [SCREEN_WIDTH as f32 / 4.0, SCREEN_HEIGHT as f32 / 2.0],
+        light_radius: 150.0,
+        ..PostProcessSettings::default()
+    };
+    let image = render(&mut renderer, &UiBatch::new());
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+    assert_matches_golden("post_process_darkness", &image);
+
+    // The middle of the light's untouched and the far side's black.
+    let (x, y) = (WIDTH / 4, HEIGHT / 2);
+    assert!(image.get_pixel(x, y).0.iter().zip(plain.get_pixel(x, y).0.iter()).all(|(a, b)| a.abs_diff(*b) <= 2));
+    assert_eq!(&image.get_pixel(WIDTH - 2, y).0[..3], &[0, 0, 0]);
+}
+
+#[test]
+fn post_process_battle_transition() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+    let plain = render(&mut renderer, &UiBatch::new());
+    renderer.capture_transition_frame();
+
+    // Half way through a swirl. What's drawn now doesn't matter, it's the kept frame that shows.
+    renderer.set_field_background(&RgbaImage::from_pixel(64, 80, Rgba([0, 0, 255, 255])));
+    renderer.get_post_process_settings_mut().transition = Some(ScreenTransition { style: TransitionStyle::Swirl, progress: 0.5 });
+    let image = render(&mut renderer, &UiBatch::new());
+    assert_matches_golden("post_process_battle_transition", &image);
+
+    // It turns round the middle, so that stays put while what's round it moves.
+    let (x, y) = (WIDTH / 2, HEIGHT / 2);
+    assert!(image.get_pixel(x, y).0.iter().zip(plain.get_pixel(x, y).0.iter()).all(|(a, b)| a.abs_diff(*b) <= 8));
+    assert_ne!(image.get_pixel(x + 20, y + 20), plain.get_pixel(x + 20, y + 20));
+
+    // Every one of them ends on black.
+    for style in TransitionStyle::ALL {
+        renderer.get_post_process_settings_mut().transition = Some(ScreenTransition { style, progress: 1.0 });
+        let image = render(&mut renderer, &UiBatch::new());
+        assert!(image.pixels().all(|pixel| pixel.0[..3] == [0, 0, 0]), "{} didn't end on black", style);
+    }
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+}
+
+#[test]
+fn post_process_depth_of_field() {
+    let mut renderer = match headless_renderer() {
+        Some(renderer) => renderer,
+        None => return
+    };
+    renderer.set_field_background(&gradient_image());
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut model = runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_prop.gltf")).unwrap();
+    for meshes in &mut model.lods {
+        for mesh in meshes {
+            mesh.material = 0;
+        }
+    }
+    model.materials = vec![MaterialData { base_color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }];
+    let red = renderer.create_model(&model);
+    renderer.set_model_lod_policy(red, LodPolicy::Distance(Vec::new()));
+
+    // Focused on the model, with enough range either side to cover all of it, so only the
+    // background behind it blurs.
+    let camera = Camera::default();
+    let mut batch = ModelBatch::new(camera);
+    batch.add(red, Matrix4::from_scale(2.0));
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let plain = renderer.read_pixels().unwrap();
+
+    renderer.get_post_process_settings_mut().depth_of_field = Some(DepthOfField {
+        focus_range: 3.0,
+        ..DepthOfField::focused_on(&camera, Point3::new(0.0, 0.0, 0.0))
+    });
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let image = renderer.read_pixels().unwrap();
+    assert_matches_golden("post_process_depth_of_field", &image);
+
+    // Turning the quality off turns the blur off too.
+    renderer.get_post_process_settings_mut().dof_quality = DofQuality::Off;
+    renderer.render(&batch, &UiBatch::new(), None).expect("Headless render failed");
+    let off = renderer.read_pixels().unwrap();
+    *renderer.get_post_process_settings_mut() = PostProcessSettings::default();
+    assert!(off == plain, "Depth of field should do nothing when its quality is off");
+
+    // The model stays sharp, and the background around it doesn't bleed red. The gradient
+    // is red in the top right corner, so that's left out.
+    let centre = (WIDTH / 2, HEIGHT / 2);
+    assert_eq!(image.get_pixel(centre.0, centre.1), plain.get_pixel(centre.0, centre.1));
+    let is_red = |pixel: &Rgba<u8>| pixel.0[0] as u32 > pixel.0[1] as u32 + pixel.0[2] as u32 + 100;
+    for y in HEIGHT / 4..HEIGHT / 2 {
+        for x in WIDTH / 4..WIDTH * 3 / 4 {
+            assert_eq!(is_red(image.get_pixel(x, y)), is_red(plain.get_pixel(x, y)), "The model's edge moved at {}, {}", x, y);
+        }
+    }
+    // The white border blurs into the gradient.
+    assert_eq!(plain.get_pixel(0, HEIGHT / 2).0, [255, 255, 255, 255]);
+    assert_ne!(image.get_pixel(0, HEIGHT / 2).0, [255, 255, 255, 255], "Expected the background to blur");
+}
+
+#[test]
+fn camera_projects_target_to_centre() {
+    let camera = Camera::default();
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+    let view_projection = camera.view_projection_matrix(aspect);
+
+    let project = |point: Point3<f32>| {
+        let clip = view_projection * Vector4::new(point.x, point.y, point.z, 1.0);
+        clip.truncate() / clip.w
+    };
+
+    // The target ends up in the middle of the screen, in wgpu's 0 to 1 depth range.
+    let target = project(camera.target);
+    assert!(target.x.abs() < 1e-5 && target.y.abs() < 1e-5, "Target projected to {:?}", target);
+    assert!(target.z > 0.0 && target.z < 1.0);
+
+    // The near and far planes map to 0 and 1.
+    let forward = (camera.target - camera.eye) / (camera.target - camera.eye).magnitude();
+    let near = project(camera.eye + forward * camera.znear);
+    let far = project(camera.eye + forward * camera.zfar);
+    assert!(near.z.abs() < 1e-4, "Near plane at depth {}", near.z);
+    assert!((far.z - 1.0).abs() < 1e-4, "Far plane at depth {}", far.z);
+
+    // Things closer to the camera are in front.
+    let closer = project(camera.eye + forward * 2.0);
+    assert!(closer.z < target.z);
+}
@@ -0,0 +1,113 @@
+// Entities moving with their parents.
+
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+use ps_rpg_engine::{
+    attachment::{self, Attachment},
+    hierarchy::{self, Children, LocalTransform, Parent},
+    transform::Transform,
+    world::{Entity, World}
+};
+
+fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!((actual - expected).magnitude() < 1e-4, "Expected {:?}, got {:?}", expected, actual);
+}
+
+fn spawn_at(world: &mut World, x: f32, y: f32, z: f32) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(x, y, z)));
+    entity
+}
+
+fn position(world: &World, entity: Entity) -> Vector3<f32> {
+    world.get::<Transform>(entity).unwrap().position
+}
+
+#[test]
+fn passengers_ride_along() {
+    let mut world = World::new();
+    let boat = spawn_at(&mut world, 10.0, 0.0, 0.0);
+    let passenger = spawn_at(&mut world, 11.0, 1.0, 0.0);
+    let hat = spawn_at(&mut world, 11.0, 2.0, 0.0);
+    hierarchy::set_parent(&mut world, passenger, boat).unwrap();
+    hierarchy::set_parent(&mut world, hat, passenger).unwrap();
+    // Where they were is kept.
+    assert_eq!(world.get::<LocalTransform>(passenger).unwrap().0.position, Vector3::new(1.0, 1.0, 0.0));
+    assert_eq!(world.get::<Children>(boat).unwrap().iter().collect::<Vec<_>>(), vec![passenger]);
+
+    // The boat turns to face along x and sails off.
+    let transform = world.get_mut::<Transform>(boat).unwrap();
+    transform.position = Vector3::new(0.0, 0.0, 5.0);
+    transform.rotation = Quaternion::from_angle_y(Deg(90.0));
+    hierarchy::propagate_transforms(&mut world);
+    assert_near(position(&world, passenger), Vector3::new(0.0, 1.0, 4.0));
+    assert_near(position(&world, hat), Vector3::new(0.0, 2.0, 4.0));
+
+    // Walking about on deck.
+    world.get_mut::<LocalTransform>(passenger).unwrap().0.position.z = 2.0;
+    hierarchy::propagate_transforms(&mut world);
+    assert_near(position(&world, passenger), Vector3::new(2.0, 1.0, 4.0));
+
+    // Getting off leaves them where they are.
+    hierarchy::remove_parent(&mut world, passenger);
+    assert!(world.get::<Children>(boat).is_none());
+    world.get_mut::<Transform>(boat).unwrap().position.x = 50.0;
+    hierarchy::propagate_transforms(&mut world);
+    assert_near(position(&world, passenger), Vector3::new(2.0, 1.0, 4.0));
+    assert_near(position(&world, hat), Vector3::new(2.0, 2.0, 4.0));
+}
+
+#[test]
+fn loops_are_refused() {
+    let mut world = World::new();
+    let a = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let b = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let c = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    hierarchy::set_parent(&mut world, b, a).unwrap();
+    hierarchy::set_parent(&mut world, c, b).unwrap();
+    assert!(hierarchy::set_parent(&mut world, a, c).is_err());
+    assert!(hierarchy::set_parent(&mut world, a, a).is_err());
+    assert_eq!(hierarchy::ancestors(&world, c).collect::<Vec<_>>(), vec![b, a]);
+
+    // Moving c onto a takes it off b.
+    hierarchy::set_parent(&mut world, c, a).unwrap();
+    assert!(world.get::<Children>(b).is_none());
+    assert_eq!(world.get::<Children>(a).unwrap().len(), 2);
+}
+
+#[test]
+fn despawning_goes_down_the_tree() {
+    let mut world = World::new();
+    let platform = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let crate_ = spawn_at(&mut world, 1.0, 0.0, 0.0);
+    let lid = spawn_at(&mut world, 1.0, 1.0, 0.0);
+    let other = spawn_at(&mut world, 2.0, 0.0, 0.0);
+    hierarchy::set_parent(&mut world, crate_, platform).unwrap();
+    hierarchy::set_parent(&mut world, lid, crate_).unwrap();
+    hierarchy::set_parent(&mut world, other, platform).unwrap();
+
+    hierarchy::despawn_recursive(&mut world, crate_);
+    assert!(!world.contains(crate_) && !world.contains(lid));
+    assert_eq!(world.get::<Children>(platform).unwrap().iter().collect::<Vec<_>>(), vec![other]);
+
+    // Despawning a parent the ordinary way leaves its children where they were.
+    world.despawn(platform);
+    hierarchy::propagate_transforms(&mut world);
+    assert!(world.get::<Parent>(other).is_none());
+    assert_near(position(&world, other), Vector3::new(2.0, 0.0, 0.0));
+}
+
+#[test]
+fn attachments_follow_children() {
+    let mut world = World::new();
+    let platform = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let rider = spawn_at(&mut world, 0.0, 1.0, 0.0);
+    hierarchy::set_parent(&mut world, rider, platform).unwrap();
+    let lantern = world.spawn();
+    world.insert(lantern, Attachment::new(rider, "hand"));
+
+    world.get_mut::<Transform>(platform).unwrap().position.y = 3.0;
+    hierarchy::propagate_transforms(&mut world);
+    attachment::update_attachments(&mut world);
+    assert_near(position(&world, lantern), Vector3::new(0.0, 4.0, 0.0));
+}
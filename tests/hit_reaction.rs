@@ -0,0 +1,54 @@
+// Flinching and knockback from hits.
+
+use std::time::Duration;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3, Vector4, InnerSpace};
+
+use ps_rpg_engine::{
+    hit_reaction::{self, HitEvent, HitEvents, HitReaction},
+    transform::Transform,
+    world::World
+};
+
+const FRAME: Duration = Duration::from_millis(16);
+
+#[test]
+fn hits_flinch_and_knock_back() {
+    let mut world = World::new();
+    world.insert_resource(HitEvents::new());
+    let target = world.spawn();
+    world.insert(target, Transform::from_position(Vector3::new(0.0, 0.0, 5.0)));
+    world.insert(target, HitReaction::new());
+
+    // Hit from the front, slightly from above, pushing along +x.
+    world.resource_mut::<HitEvents>().unwrap().send(HitEvent {
+        target,
+        direction: Vector3::new(2.0, -1.0, 0.0),
+        strength: 2.0
+    });
+
+    // Partway through, the top of the target leans the way it was pushed.
+    for _ in 0..5 {
+        assert!(hit_reaction::update_hit_reactions(&mut world, FRAME));
+    }
+    let transform = *world.get::<Transform>(target).unwrap();
+    let head = hit_reaction::posed_matrix(&world, target, &transform) * Vector4::new(0.0, 1.0, 0.0, 1.0);
+    assert!(head.x > transform.position.x + 0.1, "Expected a flinch, the head is at {:?}", head);
+    assert!((head.truncate() - transform.position).magnitude() - 1.0 < 1e-4, "Flinching shouldn't stretch");
+
+    // Once it's over, the target has slid back twice the knockback and is upright again.
+    let frames = (0..100).take_while(|_| hit_reaction::update_hit_reactions(&mut world, FRAME)).count();
+    assert!(frames < 100, "The reaction should finish");
+    let transform = *world.get::<Transform>(target).unwrap();
+    assert!((transform.position - Vector3::new(1.0, 0.0, 5.0)).magnitude() < 1e-4, "Ended up at {:?}", transform.position);
+    let posed = hit_reaction::posed_matrix(&world, target, &transform);
+    assert!(posed == transform.matrix() && posed != Matrix4::identity());
+}
+
+#[test]
+fn hits_straight_down_are_ignored() {
+    let mut reaction = HitReaction::new();
+    reaction.hit(Vector3::new(0.0, -1.0, 0.0), 1.0);
+    reaction.hit(Vector3::new(1.0, 0.0, 0.0), 0.0);
+    assert!(!reaction.is_reacting());
+}
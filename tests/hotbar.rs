@@ -0,0 +1,110 @@
+// The hotbar's quick slots for field items and skills, and keeping them in saves.
+
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    field_skill::FieldEvents,
+    field_status::{FieldBoost, FieldEffect, FieldStatus},
+    hotbar::{Hotbar, QuickSlot, COOLDOWN},
+    inventory::{Inventory, Item, ItemEffect},
+    party::{Party, PartyMember, Skill, Stats, TargetType},
+    save::SaveGame,
+    world::World
+};
+
+fn skill(name: &str, mp_cost: u32, target: TargetType, field: Option<ItemEffect>) -> Skill {
+    Skill { name: name.to_string(), description: String::new(), mp_cost, costs: Vec::new(), target, field }
+}
+
+fn party() -> Party {
+    let stats = Stats { hp: 100, max_hp: 100, mp: 10, max_mp: 20, ..Default::default() };
+    let mut aria = PartyMember::new("Aria", Stats { hp: 50, ..stats });
+    aria.skills.push(skill("Cleave", 6, TargetType::AllEnemies, None));
+    let mut tobin = PartyMember::new("Tobin", stats);
+    tobin.skills.push(skill("Cure", 4, TargetType::Ally, Some(ItemEffect::Restore { hp: 20, mp: 0 })));
+    tobin.skills.push(skill("Sneak", 0, TargetType::AllAllies, Some(ItemEffect::Field(FieldBoost { effect: FieldEffect::Sneak, steps: 30 }))));
+    Party::new(vec![aria, tobin])
+}
+
+fn inventory() -> Inventory {
+    let mut inventory = Inventory::new();
+    let potion = Item { id: "potion".to_string(), name: "Potion".to_string(), description: String::new(), effect: ItemEffect::Restore { hp: 50, mp: 0 } };
+    inventory.add(&potion, 2);
+    inventory
+}
+
+fn cure() -> QuickSlot {
+    QuickSlot::Skill { member: "Tobin".to_string(), skill: "Cure".to_string() }
+}
+
+#[test]
+fn slots_are_read() {
+    assert_eq!("skill Tobin/Call Sprite".parse(), Ok(QuickSlot::Skill { member: "Tobin".to_string(), skill: "Call Sprite".to_string() }));
+    assert_eq!(" item potion ".parse(), Ok(QuickSlot::Item("potion".to_string())));
+    assert_eq!(cure().to_string(), "skill Tobin/Cure");
+    assert!("skill Cure".parse::<QuickSlot>().is_err());
+    assert!("spell Tobin/Cure".parse::<QuickSlot>().is_err());
+    assert_eq!((Hotbar::slot_for_key(VirtualKeyCode::Key1), Hotbar::slot_for_key(VirtualKeyCode::Key9)), (Some(0), Some(8)));
+    assert_eq!(Hotbar::slot_for_key(VirtualKeyCode::Key0), None);
+}
+
+#[test]
+fn new_games_start_with_the_field_skills() {
+    let mut hotbar = Hotbar::from_shortcuts(&party());
+    let slots: Vec<(usize, String)> = hotbar.slots().map(|(slot, quick)| (slot, quick.to_string())).collect();
+    assert_eq!(slots, vec![(0, "skill Tobin/Cure".to_string()), (1, "skill Tobin/Sneak".to_string())]);
+
+    // Putting something in another slot moves it.
+    assert!(hotbar.set(4, Some(cure())));
+    assert_eq!((hotbar.get(0), hotbar.get(4)), (None, Some(&cure())));
+    assert!(!hotbar.set(9, Some(cure())));
+}
+
+#[test]
+fn slots_show_their_charges_and_cool_down() {
+    let (mut party, mut inventory) = (party(), inventory());
+    let (mut status, mut events) = (FieldStatus::new(), FieldEvents::new());
+    let mut hotbar = Hotbar::from_shortcuts(&party);
+    hotbar.set(2, Some(QuickSlot::Item("potion".to_string())));
+    assert_eq!(hotbar.charges(0, &party, &inventory), Some(2));
+    // Sneak's free.
+    assert_eq!(hotbar.charges(1, &party, &inventory), None);
+    assert_eq!(hotbar.charges(2, &party, &inventory), Some(2));
+
+    assert_eq!(hotbar.use_slot(0, &mut party, &mut status, &mut events, &mut inventory), Ok("Aria recovered 20 HP".to_string()));
+    assert_eq!(hotbar.charges(0, &party, &inventory), Some(1));
+    assert_eq!(hotbar.cooldown(0), 1.0);
+    assert_eq!(hotbar.use_slot(0, &mut party, &mut status, &mut events, &mut inventory), Err("Cure isn't ready yet".to_string()));
+    // The others aren't held up.
+    assert_eq!(hotbar.use_slot(2, &mut party, &mut status, &mut events, &mut inventory), Ok("Aria recovered 30 HP".to_string()));
+    assert_eq!(hotbar.use_slot(1, &mut party, &mut status, &mut events, &mut inventory), Ok("Sneak for 30 steps".to_string()));
+
+    assert!(hotbar.update(COOLDOWN / 2));
+    assert_eq!(hotbar.cooldown(0), 0.5);
+    assert!(hotbar.update(COOLDOWN));
+    assert!(!hotbar.update(Duration::from_millis(16)));
+    assert_eq!(hotbar.use_slot(0, &mut party, &mut status, &mut events, &mut inventory), Err("Nobody needs Cure".to_string()));
+    assert_eq!(hotbar.use_slot(5, &mut party, &mut status, &mut events, &mut inventory), Err("There's nothing in that slot".to_string()));
+
+    hotbar.set(3, Some(QuickSlot::Skill { member: "Mira".to_string(), skill: "Cure".to_string() }));
+    assert_eq!(hotbar.use_slot(3, &mut party, &mut status, &mut events, &mut inventory), Err("Mira isn't in the party".to_string()));
+}
+
+#[test]
+fn slots_are_kept_in_saves() {
+    let mut world = World::new();
+    let mut hotbar = Hotbar::new();
+    hotbar.set(0, Some(cure()));
+    hotbar.set(8, Some(QuickSlot::Item("potion".to_string())));
+    world.insert_resource(hotbar.clone());
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&world, "Test Field", None).to_bytes().unwrap()).unwrap();
+    assert_eq!(save_game.hotbar, Some(hotbar));
+
+    // Saves from before there was one leave the hotbar as it is.
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&World::new(), "Test Field", None).to_bytes().unwrap()).unwrap();
+    assert_eq!(save_game.hotbar, None);
+    save_game.apply(&mut world);
+    assert_eq!(world.resource::<Hotbar>().and_then(|hotbar| hotbar.get(0)), Some(&cure()));
+}
@@ -0,0 +1,102 @@
+// Picking what the action button would use when there's more than one thing in reach.
+
+use std::time::Duration;
+
+use cgmath::{Quaternion, Rad, Rotation3, Vector3};
+
+use ps_rpg_engine::{
+    field::FieldExit,
+    interaction::{self, Interactable, InteractionKind, InteractionTarget, INTERACT_RADIUS},
+    inventory::{Item, ItemCatalog},
+    pickup::{self, FieldPickup, Respawn},
+    transform::Transform,
+    world::{Entity, World}
+};
+
+// A player at the origin facing along +z.
+fn world() -> (World, Entity) {
+    let mut world = World::new();
+    let player = world.spawn();
+    world.insert(player, Transform::default());
+    (world, player)
+}
+
+fn thing(world: &mut World, kind: InteractionKind, prompt: &str, x: f32, z: f32) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(x, 0.0, z)));
+    world.insert(entity, Interactable::new(kind, prompt));
+    entity
+}
+
+fn face(world: &mut World, player: Entity, angle: f32) {
+    world.get_mut::<Transform>(player).unwrap().rotation = Quaternion::from_angle_y(Rad(angle));
+}
+
+#[test]
+fn people_come_before_items_before_doors() {
+    let (mut world, player) = world();
+    let door = thing(&mut world, InteractionKind::Door, "Go to town", 0.0, 0.5);
+    let chest = thing(&mut world, InteractionKind::Take, "Take Potion", 0.3, 0.8);
+    assert_eq!(interaction::select(&world, player, INTERACT_RADIUS), Some(chest));
+    let npc = thing(&mut world, InteractionKind::Talk, "Talk to Marle", -0.4, 1.2);
+    assert_eq!(interaction::select(&world, player, INTERACT_RADIUS), Some(npc));
+
+    // Unless the door's been made to matter more.
+    world.get_mut::<Interactable>(door).unwrap().priority = 5;
+    assert_eq!(interaction::select(&world, player, INTERACT_RADIUS), Some(door));
+}
+
+#[test]
+fn only_things_in_front_count_and_the_most_faced_wins() {
+    let (mut world, player) = world();
+    let left = thing(&mut world, InteractionKind::Take, "Take Potion", 0.8, 0.6);
+    let ahead = thing(&mut world, InteractionKind::Take, "Take Ether", 0.0, 1.2);
+    thing(&mut world, InteractionKind::Talk, "Talk to Marle", 0.0, -0.5);
+    // Further, but straight ahead, and the one behind doesn't count however important.
+    assert_eq!(interaction::select(&world, player, INTERACT_RADIUS), Some(ahead));
+
+    face(&mut world, player, 0.8f32.atan2(0.6));
+    assert_eq!(interaction::select(&world, player, INTERACT_RADIUS), Some(left));
+    // Out of reach.
+    assert_eq!(interaction::select(&world, player, 0.5), None);
+}
+
+#[test]
+fn the_prompt_follows_the_player_about() {
+    let (mut world, player) = world();
+    let chest = thing(&mut world, InteractionKind::Take, "Take Potion", 0.0, 1.0);
+    let npc = thing(&mut world, InteractionKind::Talk, "Talk to Marle", 1.0, 0.0);
+
+    assert!(interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::ZERO));
+    let target = world.resource::<InteractionTarget>().unwrap();
+    assert_eq!((target.entity, target.kind, target.prompt.as_str()), (chest, InteractionKind::Take, "Take Potion"));
+    // Nothing changes once it's faded in.
+    assert!(interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::from_secs(1)));
+    assert!(!interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::from_secs(1)));
+
+    face(&mut world, player, std::f32::consts::FRAC_PI_2);
+    assert!(interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::ZERO));
+    assert_eq!(world.resource::<InteractionTarget>().unwrap().entity, npc);
+
+    face(&mut world, player, std::f32::consts::PI);
+    assert!(interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::ZERO));
+    assert!(world.resource::<InteractionTarget>().is_none());
+}
+
+#[test]
+fn pickups_and_exits_can_be_used() {
+    let (mut world, player) = world();
+    world.insert_resource(ItemCatalog(Item::parse_list("[potion]\nname = Potion\neffect = restore 50 0").unwrap()));
+    pickup::spawn_pickups(&mut world, "woods", &[FieldPickup {
+        id: "chest".to_string(), item: "potion".to_string(), count: 1, position: Vector3::new(0.0, 0.0, 1.0), respawn: Respawn::Never
+    }]);
+    interaction::spawn_exits(&mut world, &[FieldExit { target: "town".to_string(), position: Vector3::new(0.0, 0.0, -1.0) }]);
+
+    interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::ZERO);
+    assert_eq!(world.resource::<InteractionTarget>().unwrap().prompt, "Take Potion");
+    face(&mut world, player, std::f32::consts::PI);
+    interaction::update_interaction(&mut world, player, INTERACT_RADIUS, Duration::ZERO);
+    let door = world.resource::<InteractionTarget>().unwrap();
+    assert_eq!((door.kind, door.prompt.as_str()), (InteractionKind::Door, "Go to town"));
+    assert_eq!(world.get::<FieldExit>(door.entity).unwrap().target, "town");
+}
@@ -0,0 +1,83 @@
+// Assets with a version for each language, and falling back when there isn't one.
+
+use std::path::{Path, PathBuf};
+
+use ps_rpg_engine::assets::AssetServer;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_localized_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(dir: &Path, path: &str, text: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+fn load(assets: &AssetServer, path: &str) -> Option<String> {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(assets.load_bytes(path)).ok().map(|bytes| String::from_utf8(bytes).unwrap())
+}
+
+#[test]
+fn the_language_picks_the_version() {
+    let dir = temp_dir("pick");
+    write(&dir, "textures/sign.png", "sign");
+    write(&dir, "textures/grass.png", "grass");
+    write(&dir, "localized/ja/textures/sign.png", "ja sign");
+    write(&dir, "localized/pt/textures/sign.png", "pt sign");
+    write(&dir, "localized/pt-BR/audio/hello.ogg", "pt-BR hello");
+    write(&dir, "data/items.cfg", "[potion]\n");
+    write(&dir, "localized/ja/data/items.cfg", "[poshon]\n");
+
+    let assets = AssetServer::new(&dir);
+    let plain = load(&assets, "textures/sign.png");
+    // Clones share the language.
+    let clone = assets.for_owner("field/town");
+    assets.set_language("ja");
+    let ja = load(&clone, "textures/sign.png");
+    let grass = load(&clone, "textures/grass.png");
+    let items = load(&clone, "data/items.cfg");
+    assets.set_language("pt-BR");
+    let pt = load(&assets, "textures/sign.png");
+    let hello = load(&assets, "audio/hello.ogg");
+    assets.set_language("de");
+    let de = load(&assets, "textures/sign.png");
+    let missing = load(&assets, "audio/hello.ogg");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(plain.as_deref(), Some("sign"));
+    assert_eq!(ja.as_deref(), Some("ja sign"));
+    assert_eq!(grass.as_deref(), Some("grass"));
+    // Data files have their own way of being translated.
+    assert_eq!(items.as_deref(), Some("[potion]\n"));
+    assert_eq!(pt.as_deref(), Some("pt sign"));
+    assert_eq!(hello.as_deref(), Some("pt-BR hello"));
+    assert_eq!(de.as_deref(), Some("sign"));
+    assert!(missing.is_none());
+}
+
+#[test]
+fn mods_can_localize_too() {
+    let dir = temp_dir("mods");
+    let (game, signs, hd) = (dir.join("game"), dir.join("mods/signs"), dir.join("mods/hd"));
+    write(&game, "textures/sign.png", "game sign");
+    write(&game, "localized/ja/textures/sign.png", "game ja sign");
+    write(&game, "textures/door.png", "game door");
+    write(&game, "localized/ja/textures/door.png", "game ja door");
+    write(&signs, "localized/ja/textures/sign.png", "mod ja sign");
+    write(&hd, "textures/door.png", "hd door");
+
+    let assets = AssetServer::new(&game).with_mods(vec![signs, hd]);
+    assets.set_language("ja");
+    assert_eq!(assets.localized_paths("textures/sign.png"), vec!["localized/ja/textures/sign.png", "textures/sign.png"]);
+    let sign = load(&assets, "textures/sign.png");
+    // A mod replacing the plain door replaces it in every language.
+    let door = load(&assets, "textures/door.png");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(sign.as_deref(), Some("mod ja sign"));
+    assert_eq!(door.as_deref(), Some("hd door"));
+}
@@ -0,0 +1,111 @@
+// Item drops, stealing and victory rewards.
+
+use ps_rpg_engine::{
+    inventory::{Inventory, Item, ItemCatalog, ItemEffect},
+    loot::{self, EnemyLoot, Loot, LootTables, StealResult, VictoryRewards},
+    rng::RngStream
+};
+
+const ITEMS: &str = "
+[potion]
+name = Potion
+description = Restores 50 HP.
+effect = restore 50 0
+
+[charm]
+name = Lucky Charm
+effect = none
+";
+
+const LOOT: &str = "
+# A comment
+[goblin]
+drop = potion 1
+rare_drop = charm 0
+
+[thief]
+steal = potion 0
+rare_steal = charm 1
+
+[rat]
+";
+
+fn items() -> ItemCatalog {
+    ItemCatalog(Item::parse_list(ITEMS).unwrap())
+}
+
+fn tables() -> LootTables {
+    LootTables::new(EnemyLoot::parse_list(LOOT).unwrap(), &items()).unwrap()
+}
+
+#[test]
+fn items_are_read() {
+    let items = items();
+    let potion = items.get("potion").unwrap();
+    assert_eq!((potion.name.as_str(), &potion.effect), ("Potion", &ItemEffect::Restore { hp: 50, mp: 0 }));
+    assert_eq!(items.get("charm").unwrap().description, "");
+
+    assert!(Item::parse_list("name = Potion").unwrap_err().contains("Line 1"));
+    assert!(Item::parse_list("[potion]\neffect = heal 5").unwrap_err().contains("Unknown effect"));
+}
+
+#[test]
+fn loot_tables_are_checked() {
+    let tables = tables();
+    let goblin = tables.get("goblin").unwrap();
+    assert_eq!(goblin.drop.as_ref().map(|drop| (drop.item.as_str(), drop.chance)), Some(("potion", 1.0)));
+    assert!(!goblin.can_steal());
+    assert!(tables.get("thief").unwrap().can_steal());
+
+    assert!(EnemyLoot::parse_list("[goblin]\ndrop = potion 2").unwrap_err().contains("bad chance"));
+    assert!(EnemyLoot::parse_list("[goblin]\ndrop = potion").unwrap_err().contains("Line 2"));
+    let typo = EnemyLoot::parse_list("[goblin]\ndrop = potoin 0.5").unwrap();
+    assert_eq!(LootTables::new(typo, &items()).unwrap_err(), "goblin has potoin, which isn't an item");
+}
+
+#[test]
+fn stealing_only_works_once() {
+    let tables = tables();
+    let mut rng = RngStream::new(1, 0);
+    let mut stolen = false;
+    assert_eq!(loot::steal(tables.get("goblin").unwrap(), &mut stolen, 0.0, &mut rng), StealResult::NothingToSteal);
+
+    let thief = tables.get("thief").unwrap();
+    assert_eq!(loot::steal(thief, &mut stolen, 0.0, &mut rng), StealResult::Stole(Loot { item: "charm".to_string(), rare: true }));
+    assert!(stolen);
+    assert_eq!(loot::steal(thief, &mut stolen, 0.0, &mut rng), StealResult::AlreadyStolen);
+}
+
+#[test]
+fn steal_bonuses_add_to_the_chance() {
+    let table = EnemyLoot::parse_list("[miser]\nsteal = potion 0").unwrap().remove(0);
+    let mut rng = RngStream::new(1, 0);
+    assert_eq!(loot::steal(&table, &mut false, 0.0, &mut rng), StealResult::Missed);
+    assert_eq!(loot::steal(&table, &mut false, 1.0, &mut rng), StealResult::Stole(Loot { item: "potion".to_string(), rare: false }));
+}
+
+#[test]
+fn rewards_go_in_the_inventory() {
+    let (tables, items) = (tables(), items());
+    let mut rng = RngStream::new(1, 0);
+    let rewards = VictoryRewards::roll(&tables, ["goblin", "rat", "goblin", "slime"], &mut rng);
+    assert_eq!(rewards.loot.len(), 2);
+
+    let mut inventory = Inventory::new();
+    assert_eq!(rewards.apply(&mut inventory, &items), ["Potion x2"]);
+    assert_eq!(inventory.count("potion"), 2);
+}
+
+#[test]
+fn rare_drops_are_announced() {
+    let items = items();
+    let rewards = VictoryRewards {
+        loot: vec![
+            Loot { item: "potion".to_string(), rare: false },
+            Loot { item: "charm".to_string(), rare: true }
+        ]
+    };
+    let mut inventory = Inventory::new();
+    assert_eq!(rewards.apply(&mut inventory, &items), ["Rare item! Lucky Charm", "Potion", "Lucky Charm"]);
+    assert_eq!(inventory.count("charm"), 1);
+}
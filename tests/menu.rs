@@ -0,0 +1,55 @@
+// What the menus share: their sounds and held arrow keys repeating.
+
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::menu::{KeyRepeat, MenuSound, MenuSounds, REPEAT_DELAY, REPEAT_INTERVAL};
+
+#[test]
+fn held_arrows_repeat_after_a_moment() {
+    let mut keys = KeyRepeat::new();
+    assert!(keys.handle_key(VirtualKeyCode::Down, true));
+    assert!(keys.is_holding());
+    assert_eq!(keys.update(REPEAT_DELAY / 2), None);
+    assert_eq!(keys.update(REPEAT_DELAY / 2), Some(VirtualKeyCode::Down));
+    assert_eq!(keys.update(REPEAT_INTERVAL / 2), None);
+    assert_eq!(keys.update(REPEAT_INTERVAL / 2), Some(VirtualKeyCode::Down));
+    // Only one a frame, however long it was.
+    assert_eq!(keys.update(REPEAT_INTERVAL * 10), Some(VirtualKeyCode::Down));
+    assert_eq!(keys.update(Duration::from_millis(1)), None);
+
+    assert!(keys.handle_key(VirtualKeyCode::Down, false));
+    assert!(!keys.is_holding());
+    assert_eq!(keys.update(REPEAT_DELAY * 2), None);
+
+    // Other keys don't repeat.
+    keys.handle_key(VirtualKeyCode::Return, true);
+    assert_eq!(keys.update(REPEAT_DELAY * 2), None);
+}
+
+#[test]
+fn the_systems_repeats_are_told_apart() {
+    let mut keys = KeyRepeat::new();
+    assert!(keys.handle_key(VirtualKeyCode::Up, true));
+    assert!(!keys.handle_key(VirtualKeyCode::Up, true));
+    assert!(keys.handle_key(VirtualKeyCode::Up, false));
+    assert!(keys.handle_key(VirtualKeyCode::Up, true));
+
+    keys.release_all();
+    assert!(!keys.is_holding());
+    assert!(keys.handle_key(VirtualKeyCode::Up, true));
+}
+
+#[test]
+fn menu_sounds_are_parsed() {
+    let sounds = MenuSounds::parse("# Menus\nmove = sounds/move.wav\nConfirm = sounds/confirm.wav\nerror =\n").unwrap();
+    assert_eq!(sounds.get(MenuSound::Move), Some("sounds/move.wav"));
+    assert_eq!(sounds.get(MenuSound::Confirm), Some("sounds/confirm.wav"));
+    assert_eq!(sounds.get(MenuSound::Cancel), None);
+    assert_eq!(sounds.get(MenuSound::Error), None);
+    assert_eq!(sounds.files(), vec!["sounds/move.wav", "sounds/confirm.wav"]);
+
+    assert!(MenuSounds::parse("beep = a.wav").unwrap_err().starts_with("Line 1:"));
+    assert!(MenuSounds::parse("\nmove").unwrap_err().starts_with("Line 2:"));
+}
@@ -0,0 +1,74 @@
+// Fitting dialogue into the message window: wrapping, pages, hyphens and cutting it short, and
+// paging through it.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    accessibility::{Accessibility, TextSpeed},
+    message::{MessageLayout, MessageWindow, Overflow},
+    subtitles
+};
+
+fn layout(columns: usize, lines_per_page: usize) -> MessageLayout {
+    MessageLayout { columns, lines_per_page, overflow: Overflow::Paginate, hyphenate: false }
+}
+
+#[test]
+fn long_text_goes_onto_more_pages() {
+    let pages = layout(12, 2).paginate("You're safe now, rest a while. The storm passed in the night.");
+    assert_eq!(pages, vec!["You're safe\nnow, rest a", "while. The\nstorm passed", "in the\nnight."]);
+
+    // A blank line always starts a new page, even with room left.
+    assert_eq!(layout(20, 3).paginate("Hello.\n\nGoodbye."), vec!["Hello.", "Goodbye."]);
+    assert!(layout(20, 3).paginate("").is_empty());
+}
+
+#[test]
+fn words_can_be_hyphenated() {
+    // At soft hyphens when hyphenating, and they're left out otherwise.
+    let text = "The Grand\u{AD}master\u{AD}ship";
+    assert_eq!(subtitles::wrap_hyphenated(text, 16), "The Grandmaster-\nship");
+    assert_eq!(subtitles::wrap(text, 16), "The\nGrandmastership");
+    assert_eq!(subtitles::wrap(text, 40), "The Grandmastership");
+    // Words too long for a line get a hyphen where they're split.
+    assert_eq!(subtitles::wrap_hyphenated("Aaaaaaaargh!", 5), "Aaaa-\naaaa-\nrgh!");
+
+    let hyphenated = MessageLayout { hyphenate: true, ..layout(16, 3) };
+    assert_eq!(hyphenated.paginate(text), vec!["The Grandmaster-\nship"]);
+}
+
+#[test]
+fn ellipsis_cuts_it_short() {
+    let sign = MessageLayout { overflow: Overflow::Ellipsis, ..layout(12, 2) };
+    assert_eq!(sign.paginate("You're safe now, rest a while. The storm passed in the night."), vec!["You're safe\nnow, rest..."]);
+    // Text that fits is left alone.
+    assert_eq!(sign.paginate("Keep out!"), vec!["Keep out!"]);
+    assert_eq!("ellipsis".parse::<Overflow>(), Ok(Overflow::Ellipsis));
+    assert!("scroll".parse::<Overflow>().is_err());
+}
+
+#[test]
+fn pages_type_out_then_advance() {
+    let mut accessibility = Accessibility::new();
+    accessibility.text_speed = TextSpeed::Normal;
+    let mut window = MessageWindow::new();
+    window.open("First page.\n\nSecond page.");
+    assert!(window.is_open());
+    assert_eq!(window.pages(&accessibility).len(), 2);
+    assert_eq!(window.visible_text(&accessibility), "");
+
+    // 40 characters a second.
+    assert!(window.update(Duration::from_millis(100), &accessibility));
+    assert_eq!(window.visible_text(&accessibility), "Firs");
+
+    // The button finishes the page, then goes to the next, then closes.
+    window.advance(&accessibility);
+    assert_eq!(window.visible_text(&accessibility), "First page.");
+    assert!(!window.update(Duration::from_millis(100), &accessibility));
+    window.advance(&accessibility);
+    assert_eq!(window.page(), 1);
+    window.update(Duration::from_secs(1), &accessibility);
+    assert_eq!(window.visible_text(&accessibility), "Second page.");
+    window.advance(&accessibility);
+    assert!(!window.is_open());
+}
@@ -0,0 +1,107 @@
+// Mini-games and how they're launched.
+
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    flags::GameFlags,
+    minigame::{MiniGame, MiniGameLaunch, MiniGameParams, MiniGames, TimingGame},
+    pointer::PointerEvent,
+    ui::UiBatch
+};
+
+// Wins straight away, with the number it was given.
+struct Instant(i32);
+
+impl MiniGame for Instant {
+    fn update(&mut self, _delta: Duration) -> Option<i32> {
+        Some(self.0)
+    }
+
+    fn handle_key(&mut self, _key: VirtualKeyCode) {}
+
+    fn handle_pointer(&mut self, _event: PointerEvent, _accessibility: &Accessibility) {}
+
+    fn build(&self, _batch: &mut UiBatch, _accessibility: &Accessibility) {}
+}
+
+fn instant(params: &MiniGameParams) -> Result<Box<dyn MiniGame>, String> {
+    Ok(Box::new(Instant(params.parse_or("value", 1)?)))
+}
+
+fn launch(text: &str) -> MiniGameLaunch {
+    text.parse().unwrap()
+}
+
+#[test]
+fn launches_are_read() {
+    let launch = launch("fishing pond=river time=60 result=quest.fish");
+    assert_eq!(launch.game, "fishing");
+    assert_eq!((launch.params.get("pond"), launch.params.get("time"), launch.params.get("bait")), (Some("river"), Some("60"), None));
+    assert_eq!(launch.result_flag.as_deref(), Some("quest.fish"));
+    assert_eq!(launch.params.parse_or("time", 30), Ok(60));
+    assert_eq!(launch.params.parse_or::<u32>("pond", 0), Err("Bad pond \"river\"".to_string()));
+
+    assert!("".parse::<MiniGameLaunch>().is_err());
+    assert!("fishing river".parse::<MiniGameLaunch>().unwrap_err().contains("Expected key=value"));
+}
+
+#[test]
+fn results_go_back_in_a_flag() {
+    let mut games = MiniGames::new();
+    games.register("instant", instant);
+    games.launch(&launch("instant value=7 result=cards.won")).unwrap();
+    assert_eq!(games.running(), Some("instant"));
+    // Only one at a time.
+    assert_eq!(games.launch(&launch("instant")), Err("instant is still running".to_string()));
+
+    let result = games.update(Duration::ZERO).unwrap();
+    assert_eq!((result.game.as_str(), result.value), ("instant", 7));
+    assert!(!games.is_running());
+    let mut flags = GameFlags::new();
+    result.apply(&mut flags);
+    assert_eq!(flags.get("cards.won"), 7);
+}
+
+#[test]
+fn bad_launches_say_why() {
+    let mut games = MiniGames::new();
+    games.register("instant", instant);
+    assert_eq!(games.launch(&launch("racing")), Err("There's no mini-game called \"racing\"".to_string()));
+    assert_eq!(games.launch(&launch("instant value=lots")), Err("Couldn't start instant: Bad value \"lots\"".to_string()));
+    assert!(!games.is_running());
+}
+
+#[test]
+fn the_timing_game_scores_each_go() {
+    let accessibility = Accessibility::new();
+    let mut games = MiniGames::new();
+    games.register("timing", TimingGame::start);
+    assert!(games.launch(&launch("timing tries=0")).is_err());
+    games.launch(&launch("timing speed=1 tries=2 result=score")).unwrap();
+
+    // Half a sweep puts the marker in the middle, for full marks.
+    assert_eq!(games.update(Duration::from_millis(250)), None);
+    games.handle_key(VirtualKeyCode::Return);
+    let mut batch = UiBatch::new();
+    games.build(&mut batch, &accessibility);
+    assert!(!batch.is_empty());
+
+    // Then at the far end, for nothing.
+    assert_eq!(games.update(Duration::from_millis(250)), None);
+    games.handle_pointer(PointerEvent::Click { x: 0.0, y: 0.0 }, &accessibility);
+    assert_eq!(games.update(Duration::ZERO).map(|result| result.value), Some(100));
+}
+
+#[test]
+fn giving_up_keeps_the_score_so_far() {
+    let mut games = MiniGames::new();
+    games.register("timing", TimingGame::start);
+    games.launch(&launch("timing")).unwrap();
+    games.update(Duration::from_millis(250));
+    games.handle_key(VirtualKeyCode::Space);
+    games.handle_key(VirtualKeyCode::Escape);
+    assert_eq!(games.update(Duration::ZERO).map(|result| result.value), Some(100));
+}
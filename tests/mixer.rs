@@ -0,0 +1,105 @@
+// Mixing buses and the field's effects on them, and keeping the volumes in the config. These
+// don't need audio.
+
+use ps_rpg_engine::{
+    config::Config,
+    mixer::{Bus, BusVolumes, FieldAudio, Mixer, SAMPLE_RATE}
+};
+
+// A tenth of a second of stereo sound.
+const LENGTH: usize = SAMPLE_RATE as usize / 10 * 2;
+
+fn constant(value: f32) -> Vec<f32> {
+    vec![value; LENGTH]
+}
+
+fn loudest(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |loudest, sample| sample.abs().max(loudest))
+}
+
+#[test]
+fn bus_names_round_trip() {
+    for bus in Bus::ALL {
+        assert_eq!(bus.to_string().parse::<Bus>(), Ok(bus));
+    }
+    assert_eq!(" SFX ".parse(), Ok(Bus::Sfx));
+    assert!("speech".parse::<Bus>().is_err());
+}
+
+#[test]
+fn each_bus_has_its_own_volume() {
+    let mut volumes = BusVolumes::default();
+    volumes.set(Bus::Music, 0.5);
+    volumes.set(Bus::Voice, 3.0);
+    assert_eq!((volumes.get(Bus::Music), volumes.get(Bus::Voice), volumes.get(Bus::Sfx)), (0.5, 1.0, 1.0));
+
+    let mut mixer = Mixer::new(SAMPLE_RATE, volumes);
+    mixer.set_volume(Bus::Sfx, 0.25);
+    let (music, sfx) = (constant(0.5), constant(1.0));
+    let mut output = constant(9.0);
+    mixer.mix(&[(Bus::Music, &music), (Bus::Sfx, &sfx), (Bus::Sfx, &sfx)], &mut output);
+    // Buses that aren't given are silent, and several sounds on one bus add up.
+    assert!(output.iter().all(|sample| *sample == 0.5 * 0.5 + 2.0 * 0.25));
+}
+
+#[test]
+fn low_pass_muffles_the_field_but_not_the_music() {
+    // As high as it gets, flipping every sample.
+    let hiss: Vec<f32> = (0..LENGTH).map(|i| if (i / 2) % 2 == 0 { 1.0 } else { -1.0 }).collect();
+    let mut mixer = Mixer::new(SAMPLE_RATE, BusVolumes::default());
+    mixer.set_field(FieldAudio { low_pass: Some(500.0), ..FieldAudio::default() });
+
+    let mut output = constant(0.0);
+    mixer.mix(&[(Bus::Ambient, &hiss)], &mut output);
+    assert!(loudest(&output) < 0.1, "{}", loudest(&output));
+    mixer.mix(&[(Bus::Music, &hiss)], &mut output);
+    assert!(loudest(&output) > 0.9, "{}", loudest(&output));
+
+    // Low notes still get through.
+    let hum = constant(1.0);
+    mixer.mix(&[(Bus::Voice, &hum)], &mut output);
+    assert!(output[LENGTH - 1] > 0.99);
+}
+
+#[test]
+fn reverb_echoes_and_dies_away() {
+    let mut impulse = constant(0.0);
+    impulse[0] = 1.0;
+    impulse[1] = 1.0;
+    let silence = constant(0.0);
+    let mut output = constant(0.0);
+
+    let mut mixer = Mixer::new(SAMPLE_RATE, BusVolumes::default());
+    mixer.set_field(FieldAudio { reverb_send: 0.5, reverb_decay: 0.5, ..FieldAudio::default() });
+    mixer.mix(&[(Bus::Sfx, &impulse)], &mut output);
+    assert_eq!(output[0], 1.0);
+    // The first echo is after the shortest comb's delay.
+    assert!(loudest(&output[2..]) > 0.01);
+    mixer.mix(&[(Bus::Sfx, &silence)], &mut output);
+    assert!(loudest(&output) > 0.001);
+
+    // A thousandth of the way down after the decay.
+    for _ in 0..10 {
+        mixer.mix(&[], &mut output);
+    }
+    assert!(loudest(&output) < 0.001, "{}", loudest(&output));
+
+    // Music doesn't go to the reverb.
+    let mut mixer = Mixer::new(SAMPLE_RATE, BusVolumes::default());
+    mixer.set_field(FieldAudio { reverb_send: 0.5, reverb_decay: 0.5, ..FieldAudio::default() });
+    mixer.mix(&[(Bus::Music, &impulse)], &mut output);
+    assert_eq!(loudest(&output[2..]), 0.0);
+}
+
+#[test]
+fn volumes_are_kept_in_the_config() {
+    let path = std::env::temp_dir().join(format!("ps_rpg_engine_mixer_{}.cfg", std::process::id()));
+    let mut config = Config::default();
+    config.volumes.set(Bus::Music, 0.25);
+    config.volumes.set(Bus::Ambient, 0.0);
+    config.save_to(&path).unwrap();
+
+    let loaded = Config::load_from(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.volumes, config.volumes);
+}
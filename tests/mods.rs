@@ -0,0 +1,116 @@
+// Mods mounted over the game's assets, and their data merged with the game's.
+
+use std::path::{Path, PathBuf};
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    console::ConsoleCommand,
+    inventory::Item,
+    mods::{self, ModCommands, ModInfo, MOD_COMMANDS_PER_TICK, MOD_COMMAND_TIME}
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_mods_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(dir: &Path, path: &str, text: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
+
+fn load(assets: &AssetServer, path: &str) -> Option<String> {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(assets.load_bytes(path)).ok().map(|bytes| String::from_utf8(bytes).unwrap())
+}
+
+#[test]
+fn sections_are_replaced_or_added() {
+    let base = "# Items\n\n[potion]\nname = Potion\n\n[ether]\nname = Ether\n";
+    let over = "[potion]\nname = Hi-Potion\n\n[elixir]\nname = Elixir\n";
+    let items = Item::parse_list(&mods::merge_data(base, over)).unwrap();
+    let names: Vec<(&str, &str)> = items.iter().map(|item| (item.id.as_str(), item.name.as_str())).collect();
+    assert_eq!(names, vec![("potion", "Hi-Potion"), ("ether", "Ether"), ("elixir", "Elixir")]);
+
+    // Settings before any section come after the game's, so they win.
+    assert_eq!(mods::merge_data("variance = 0.1\nmin_hit = 0.05\n", "variance = 0.2\n"), "variance = 0.1\nmin_hit = 0.05\nvariance = 0.2\n");
+}
+
+#[test]
+fn later_mods_win() {
+    let dir = temp_dir("layers");
+    let (game, hd, quests) = (dir.join("game"), dir.join("mods/hd"), dir.join("mods/quests"));
+    write(&game, "textures/grass.png", "game grass");
+    write(&game, "textures/sky.png", "game sky");
+    write(&game, "data/items.cfg", "[potion]\nname = Potion\n");
+    write(&hd, "textures/grass.png", "hd grass");
+    write(&hd, "textures/sky.png", "hd sky");
+    write(&hd, "mod.cfg", "title = HD Textures\ndescription = Sharper everything.\n");
+    write(&quests, "textures/sky.png", "quest sky");
+    write(&quests, "data/items.cfg", "[map]\nname = Treasure Map\n");
+    write(&quests, "data/quests.cfg", "[treasure]\n");
+
+    let found = mods::find_mods(&dir.join("mods"));
+    let enabled = mods::enabled_dirs(&found, &["hd".to_string(), "quests".to_string(), "gone".to_string()]);
+    let assets = AssetServer::new(&game).with_mods(enabled);
+    let grass = load(&assets, "textures/grass.png");
+    let sky = load(&assets, "textures/sky.png");
+    let items = load(&assets, "data/items.cfg");
+    let quests = load(&assets, "data/quests.cfg");
+    let missing = load(&assets, "data/missing.cfg");
+    // Without mods, it's the game's own.
+    let plain = load(&AssetServer::new(&game), "textures/sky.png");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(found.iter().map(|info| info.title.as_str()).collect::<Vec<_>>(), vec!["HD Textures", "quests"]);
+    assert_eq!(found[0], ModInfo { name: "hd".to_string(), title: "HD Textures".to_string(), description: "Sharper everything.".to_string(), dir: dir.join("mods/hd") });
+    assert_eq!(grass.as_deref(), Some("hd grass"));
+    assert_eq!(sky.as_deref(), Some("quest sky"));
+    assert_eq!(items.as_deref(), Some("[potion]\nname = Potion\n[map]\nname = Treasure Map\n"));
+    assert_eq!(quests.as_deref(), Some("[treasure]\n"));
+    assert!(missing.is_none());
+    assert_eq!(plain.as_deref(), Some("game sky"));
+}
+
+#[test]
+fn mods_only_run_allowed_commands_a_few_at_a_time() {
+    let dir = temp_dir("commands");
+    let (quests, hd) = (dir.join("mods/quests"), dir.join("mods/hd"));
+    write(&quests, "commands.txt", "# Start the quest.\nflag quest.treasure 1\n\nexportsave 1 stolen.sav\nwarp town\nsay The map's in the well.\n");
+    let mut commands = ModCommands::load(&[quests, hd]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Comments and blank lines are left out, and nothing runs until the tick starts.
+    assert_eq!(commands.len(), 4);
+    assert_eq!(commands.next_command(), None);
+
+    // The save export and the warp never run.
+    commands.start_tick();
+    let names: Vec<String> = std::iter::from_fn(|| commands.next_command()).map(|command| command.name).collect();
+    assert_eq!(names, vec!["flag", "say"]);
+    assert!(commands.is_empty());
+    assert!(mods::allows_command("camera") && !mods::allows_command("telemetry") && !mods::allows_command("mod"));
+
+    // Long lists run over several ticks.
+    for i in 0..MOD_COMMANDS_PER_TICK + 3 {
+        commands.push("quests", ConsoleCommand::parse(&format!("flag step {}", i)).unwrap());
+    }
+    commands.start_tick();
+    assert_eq!(std::iter::from_fn(|| commands.next_command()).count(), MOD_COMMANDS_PER_TICK);
+    commands.start_tick();
+    assert_eq!(std::iter::from_fn(|| commands.next_command()).count(), 3);
+
+    // And slow ones stop the rest until the next tick.
+    for _ in 0..3 {
+        commands.push("quests", ConsoleCommand::parse("movie intro").unwrap());
+    }
+    commands.start_tick();
+    assert!(commands.next_command().is_some());
+    std::thread::sleep(MOD_COMMAND_TIME);
+    assert_eq!(commands.next_command(), None);
+    assert_eq!(commands.len(), 2);
+    commands.start_tick();
+    assert!(commands.next_command().is_some());
+}
@@ -0,0 +1,131 @@
+// Lifts and the like moving along their tracks and carrying passengers.
+
+use std::time::Duration;
+
+use base64::Engine;
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+use ps_rpg_engine::{
+    mover::{self, Keyframe, LoopMode, Mover, Passenger},
+    transform::Transform,
+    world::{Entity, Name, World}
+};
+
+fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+    assert!((actual - expected).magnitude() < 1e-3, "Expected {:?}, got {:?}", expected, actual);
+}
+
+// A lift going 2m up at 1m a second.
+fn lift(world: &mut World, mode: LoopMode) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Name("lift".to_string()));
+    world.insert(entity, Transform::default());
+    let mut lift = Mover::new(mover::path_keyframes(&[Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0)], 1.0), mode);
+    lift.deck = Some([1.0, 1.0]);
+    world.insert(entity, lift);
+    entity
+}
+
+fn height_after(world: &mut World, entity: Entity, seconds: f32) -> f32 {
+    mover::update_movers(world, Duration::from_secs_f32(seconds));
+    world.get::<Transform>(entity).unwrap().position.y
+}
+
+#[test]
+fn movers_loop_go_back_and_forth_or_stop() {
+    let mut world = World::new();
+    let looping = lift(&mut world, LoopMode::Loop);
+    assert!((height_after(&mut world, looping, 1.5) - 1.5).abs() < 1e-4);
+    assert!((height_after(&mut world, looping, 1.0) - 0.5).abs() < 1e-4);
+
+    let mut world = World::new();
+    let ping_pong = lift(&mut world, LoopMode::PingPong);
+    assert!((height_after(&mut world, ping_pong, 2.5) - 1.5).abs() < 1e-4);
+    assert!((height_after(&mut world, ping_pong, 1.0) - 0.5).abs() < 1e-4);
+
+    let mut world = World::new();
+    let once = lift(&mut world, LoopMode::Once);
+    assert!(mover::update_movers(&mut world, Duration::from_secs(1)));
+    assert!(!mover::update_movers(&mut world, Duration::from_secs(5)));
+    assert!((world.get::<Transform>(once).unwrap().position.y - 2.0).abs() < 1e-4);
+    assert!(!world.get::<Mover>(once).unwrap().playing);
+
+    // Starting it again after it's finished sends it back to the start.
+    assert!(mover::set_playing(&mut world, "lift", true));
+    assert!((height_after(&mut world, once, 0.5) - 0.5).abs() < 1e-4);
+    assert!(mover::set_playing(&mut world, "lift", false));
+    assert!((height_after(&mut world, once, 1.0) - 0.5).abs() < 1e-4);
+    assert!(!mover::set_playing(&mut world, "ferris_wheel", true));
+}
+
+#[test]
+fn passengers_on_the_deck_are_carried() {
+    let mut world = World::new();
+    let lift = lift(&mut world, LoopMode::Once);
+    let rider = world.spawn();
+    world.insert(rider, Transform::from_position(Vector3::new(0.5, 0.0, -0.5)));
+    world.insert(rider, Passenger);
+    let bystander = world.spawn();
+    world.insert(bystander, Transform::from_position(Vector3::new(3.0, 0.0, 0.0)));
+    world.insert(bystander, Passenger);
+    // Stood on it, but not a passenger.
+    let crate_ = world.spawn();
+    world.insert(crate_, Transform::default());
+
+    height_after(&mut world, lift, 1.0);
+    assert_near(world.get::<Transform>(rider).unwrap().position, Vector3::new(0.5, 1.0, -0.5));
+    assert_near(world.get::<Transform>(bystander).unwrap().position, Vector3::new(3.0, 0.0, 0.0));
+    assert_near(world.get::<Transform>(crate_).unwrap().position, Vector3::new(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn passengers_turn_with_the_mover() {
+    let mut world = World::new();
+    let turntable = world.spawn();
+    world.insert(turntable, Transform::default());
+    let mut spin = Mover::new(vec![
+        Keyframe { time: Duration::ZERO, position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_angle_y(Deg(0.0)) },
+        Keyframe { time: Duration::from_secs(1), position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_angle_y(Deg(90.0)) }
+    ], LoopMode::Once);
+    spin.deck = Some([2.0, 2.0]);
+    world.insert(turntable, spin);
+    let rider = world.spawn();
+    world.insert(rider, Transform::from_position(Vector3::new(0.0, 0.0, 1.0)));
+    world.insert(rider, Passenger);
+
+    mover::update_movers(&mut world, Duration::from_secs(1));
+    let transform = world.get::<Transform>(rider).unwrap();
+    assert_near(transform.position, Vector3::new(1.0, 0.0, 0.0));
+    assert_near(transform.rotation * Vector3::unit_z(), Vector3::unit_x());
+}
+
+#[test]
+fn tracks_come_from_gltf_animations() {
+    // Two times, then two translations, for a node going 2m up over a second.
+    let floats: [f32; 8] = [0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0];
+    let bytes: Vec<u8> = floats.iter().flat_map(|f| f.to_le_bytes()).collect();
+    let uri = format!("data:application/octet-stream;base64,{}", base64::engine::general_purpose::STANDARD.encode(&bytes));
+    let gltf = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "scenes": [{{ "nodes": [0] }}],
+        "nodes": [{{ "name": "gondola", "rotation": [0.0, 0.7071068, 0.0, 0.7071068] }}],
+        "buffers": [{{ "byteLength": 32, "uri": "{}" }}],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 8 }}, {{ "buffer": 0, "byteOffset": 8, "byteLength": 24 }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0] }},
+            {{ "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" }}
+        ],
+        "animations": [{{
+            "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "translation" }} }}],
+            "samplers": [{{ "input": 0, "output": 1 }}]
+        }}]
+    }}"#, uri);
+
+    let keyframes = mover::keyframes_from_gltf(gltf.as_bytes(), "gondola").unwrap();
+    assert_eq!(keyframes.len(), 2);
+    assert_eq!(keyframes[1].time, Duration::from_secs(1));
+    assert_near(keyframes[1].position, Vector3::new(0.0, 2.0, 0.0));
+    // Rotation isn't animated, so it's the node's.
+    assert_near(keyframes[1].rotation * Vector3::unit_z(), Vector3::unit_x());
+    assert!(mover::keyframes_from_gltf(gltf.as_bytes(), "wheel").is_err());
+}
@@ -0,0 +1,134 @@
+// Layered music's track files, fades and stingers. These don't need audio.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    flags::GameFlags,
+    music::{self, MusicPlayer, MusicTrack},
+    world::World
+};
+
+// 120 bpm, so a beat is half a second.
+const TRACK: &str = "
+# The field theme.
+file = field.ogg
+bpm = 120
+
+[layer danger]
+file = field_danger.ogg
+flag = field.danger
+fade = 2
+
+[layer night]
+file = field_night.ogg
+volume = 0.5
+
+[stinger found_item]
+file = found_item.ogg
+";
+
+fn track() -> MusicTrack {
+    MusicTrack::parse("music/field.cfg", TRACK).unwrap()
+}
+
+fn beats(count: f32) -> Duration {
+    Duration::from_secs_f32(count * 0.5)
+}
+
+#[test]
+fn tracks_parse_their_layers_and_stingers() {
+    let track = track();
+    assert_eq!((track.file.as_str(), track.bpm), ("field.ogg", 120.0));
+    let danger = track.layer("danger").unwrap();
+    assert_eq!((danger.flag.as_deref(), danger.fade, danger.volume), (Some("field.danger"), 2.0, 0.0));
+    let night = track.layer("night").unwrap();
+    assert_eq!((night.flag.as_deref(), night.fade, night.volume), (None, 4.0, 0.5));
+    assert_eq!(track.stinger("found_item").unwrap().file, "found_item.ogg");
+    assert_eq!(track.files(), ["field.ogg", "field_danger.ogg", "field_night.ogg", "found_item.ogg"]);
+}
+
+#[test]
+fn bad_tracks_say_why() {
+    assert_eq!(MusicTrack::parse("a", "bpm = 100").unwrap_err(), "Missing file");
+    assert_eq!(MusicTrack::parse("a", "file = a.ogg").unwrap_err(), "Missing bpm");
+    assert!(MusicTrack::parse("a", "file = a.ogg\nbpm = 0").unwrap_err().contains("bad bpm"));
+    assert!(MusicTrack::parse("a", "file = a.ogg\nbpm = 90\n[layer drums]\nvolume = 2").unwrap_err().contains("bad volume"));
+    assert!(MusicTrack::parse("a", "file = a.ogg\nbpm = 90\n[layer drums]").unwrap_err().contains("drums is missing a file"));
+    assert!(MusicTrack::parse("a", "file = a.ogg\nbpm = 90\n[drums]").unwrap_err().contains("Line 3"));
+    assert!(MusicTrack::parse("a", "file = a.ogg\nflag = x").unwrap_err().contains("unknown key \"flag\""));
+}
+
+#[test]
+fn fades_wait_for_the_next_beat() {
+    let mut music = MusicPlayer::start(track(), None);
+    assert_eq!(music.layer_volume("night"), Some(0.5));
+
+    // A quarter of the way into the first beat.
+    music.update(beats(0.25), None);
+    music.fade_layer("night", 1.0, 2.0).unwrap();
+    music.update(beats(0.5), None);
+    assert_eq!(music.layer_volume("night"), Some(0.5));
+
+    // Halfway through the fade, which started on beat one.
+    music.update(beats(1.25), None);
+    assert_eq!(music.beat(), 2.0);
+    assert_eq!(music.layer_volume("night"), Some(0.75));
+    music.update(beats(10.0), None);
+    assert_eq!(music.layer_volume("night"), Some(1.0));
+
+    assert!(music.fade_layer("choir", 1.0, 1.0).unwrap_err().contains("doesn't have a layer"));
+}
+
+#[test]
+fn flagged_layers_follow_their_flag() {
+    let mut flags = GameFlags::new();
+    let mut music = MusicPlayer::start(track(), Some(&flags));
+    assert_eq!(music.layer_volume("danger"), Some(0.0));
+
+    music.update(beats(0.25), Some(&flags));
+    flags.set("field.danger", 1);
+    music.update(beats(0.5), Some(&flags));
+    assert_eq!(music.layer_volume("danger"), Some(0.0));
+    music.update(beats(1.25), Some(&flags));
+    assert_eq!(music.layer_volume("danger"), Some(0.5));
+    music.update(beats(1.0), Some(&flags));
+    assert_eq!(music.layer_volume("danger"), Some(1.0));
+
+    // Starting with the flag set plays the layer straight away.
+    assert_eq!(MusicPlayer::start(track(), Some(&flags)).layer_volume("danger"), Some(1.0));
+
+    flags.set("field.danger", 0);
+    music.update(Duration::ZERO, Some(&flags));
+    music.update(beats(3.0), Some(&flags));
+    assert_eq!(music.layer_volume("danger"), Some(0.0));
+}
+
+#[test]
+fn stingers_play_on_the_beat() {
+    let mut music = MusicPlayer::start(track(), None);
+    music.update(beats(0.5), None);
+    music.play_stinger("found_item").unwrap();
+    music.update(beats(0.25), None);
+    assert!(music.take_stingers().is_empty());
+    music.update(beats(0.25), None);
+    assert_eq!(music.take_stingers(), ["found_item.ogg"]);
+    assert!(music.take_stingers().is_empty());
+
+    assert!(music.play_stinger("fanfare").unwrap_err().contains("doesn't have a stinger"));
+}
+
+#[test]
+fn the_world_update_reads_the_flags() {
+    let mut world = World::new();
+    let mut flags = GameFlags::new();
+    flags.set("field.danger", 1);
+    world.insert_resource(MusicPlayer::start(track(), None));
+    world.insert_resource(flags);
+
+    music::update_music(&mut world, beats(1.0));
+    music::update_music(&mut world, beats(2.0));
+    assert_eq!(world.resource::<MusicPlayer>().unwrap().layer_volume("danger"), Some(1.0));
+
+    // Nothing playing is fine too.
+    music::update_music(&mut World::new(), beats(1.0));
+}
@@ -0,0 +1,100 @@
+// Typing names in with the on-screen keyboard.
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    menu::MenuSound,
+    name_entry::{KeyboardLayout, NameEntry, NameKey, NAME_LENGTH},
+    pointer::PointerEvent,
+    ui::UiBatch
+};
+
+fn press(entry: &mut NameEntry, keys: &[VirtualKeyCode]) -> Option<String> {
+    keys.iter().fold(None, |_, key| entry.handle_key(*key))
+}
+
+#[test]
+fn names_are_picked_from_the_grid() {
+    use VirtualKeyCode::*;
+    let mut entry = NameEntry::new();
+    entry.open("Name Aria", "");
+    assert_eq!(entry.selected(), Some(NameKey::Char('A')));
+    // Left wraps round to the end of the row.
+    press(&mut entry, &[Right, Return, Left, Left, Return]);
+    assert_eq!(entry.text(), "BJ");
+    assert_eq!(entry.take_sound(), Some(MenuSound::Confirm));
+
+    // Up from the top goes to the bottom row, keeping to about the same place.
+    press(&mut entry, &[Up]);
+    assert_eq!(entry.selected(), Some(NameKey::Done));
+    press(&mut entry, &[Left, Left, Return]);
+    assert_eq!(entry.text(), "B");
+    press(&mut entry, &[Right, Right]);
+    assert_eq!(entry.selected(), Some(NameKey::Done));
+    assert_eq!(press(&mut entry, &[Return]), Some("B".to_string()));
+    assert!(!entry.is_open());
+}
+
+#[test]
+fn pages_switch_and_names_have_limits() {
+    use VirtualKeyCode::*;
+    let mut entry = NameEntry::new();
+    entry.open("Name", "");
+    press(&mut entry, &[Tab, Return]);
+    assert_eq!(entry.text(), "a");
+
+    // Escape deletes, then closes once there's nothing left.
+    press(&mut entry, &[Escape]);
+    assert_eq!(entry.text(), "");
+    assert!(entry.is_open());
+    press(&mut entry, &[Escape]);
+    assert!(!entry.is_open());
+
+    entry.open("Name", "Tobin");
+    for c in "ellington".chars() {
+        entry.handle_char(c);
+    }
+    assert_eq!(entry.text().chars().count(), NAME_LENGTH);
+    assert_eq!(entry.take_sound(), Some(MenuSound::Error));
+    // Typing goes to done, so enter finishes.
+    assert_eq!(press(&mut entry, &[Return]), Some("Tobinell".to_string()));
+
+    // No empty names.
+    entry.open("Name", "");
+    press(&mut entry, &[Up, Right, Right, Right]);
+    assert_eq!(press(&mut entry, &[Return]), None);
+    assert_eq!(entry.take_sound(), Some(MenuSound::Error));
+}
+
+#[test]
+fn keyboards_are_parsed_by_language() {
+    let layout = KeyboardLayout::parse("# Hiragana\n[あ]\nあ い う え お\nか き く け こ\n[ア]\nア イ ウ\n").unwrap();
+    assert_eq!(layout.pages.len(), 2);
+    assert_eq!(layout.pages[0].rows[1], vec!['か', 'き', 'く', 'け', 'こ']);
+
+    let mut entry = NameEntry::new();
+    entry.set_layout(layout);
+    entry.open("Name", "");
+    entry.handle_key(VirtualKeyCode::Down);
+    entry.handle_key(VirtualKeyCode::Return);
+    assert_eq!(entry.text(), "か");
+
+    assert!(KeyboardLayout::parse("abc").unwrap_err().starts_with("Line 1:"));
+    assert!(KeyboardLayout::parse("[Empty]\n").is_err());
+    assert!(KeyboardLayout::parse("").is_err());
+}
+
+#[test]
+fn keys_can_be_clicked() {
+    let accessibility = Accessibility::default();
+    let mut entry = NameEntry::new();
+    entry.open("Name", "");
+    let mut batch = UiBatch::new();
+    entry.build(&mut batch, &accessibility, None);
+    assert!(!batch.is_empty());
+
+    // Clicking above the keys is like escape.
+    entry.handle_pointer(PointerEvent::Click { x: 100.0, y: 1.0 }, &accessibility);
+    assert!(!entry.is_open());
+}
@@ -0,0 +1,109 @@
+// Picking up items lying about in fields, and them coming back.
+
+use std::time::Duration;
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    flags::GameFlags,
+    game_clock::GameClock,
+    inventory::{Inventory, Item, ItemCatalog},
+    pickup::{self, FieldPickup, Pickup, PickupToasts, Respawn, PICKUP_RADIUS, TOAST_TIME},
+    save::SaveGame,
+    transform::Transform,
+    world::{Entity, World}
+};
+
+fn field_pickup(id: &str, count: u32, x: f32, respawn: Respawn) -> FieldPickup {
+    FieldPickup { id: id.to_string(), item: "potion".to_string(), count, position: Vector3::new(x, 0.0, 0.0), respawn }
+}
+
+fn world() -> (World, Entity) {
+    let mut world = World::new();
+    world.insert_resource(ItemCatalog(Item::parse_list("[potion]\nname = Potion\neffect = restore 50 0").unwrap()));
+    world.insert_resource(Inventory::new());
+    world.insert_resource(GameFlags::new());
+    world.insert_resource(GameClock::new());
+    let player = world.spawn();
+    world.insert(player, Transform::default());
+    (world, player)
+}
+
+// Enter the field, pick up everything there is, and leave again.
+fn visit(world: &mut World, player: Entity, pickups: &[FieldPickup]) -> Vec<String> {
+    pickup::spawn_pickups(world, "woods", pickups);
+    let mut found = Vec::new();
+    while let Some(entity) = pickup::nearest_pickup(world, player, f32::INFINITY) {
+        found.push(pickup::collect(world, entity).unwrap());
+    }
+    found
+}
+
+#[test]
+fn picking_up_fills_the_inventory_and_says_so() {
+    let (mut world, player) = world();
+    pickup::spawn_pickups(&mut world, "woods", &[field_pickup("far", 1, 3.0, Respawn::Never), field_pickup("near", 2, 0.5, Respawn::Never)]);
+    let near = pickup::nearest_pickup(&world, player, PICKUP_RADIUS).unwrap();
+    assert_eq!(pickup::collect(&mut world, near), Ok("Found Potion x2".to_string()));
+    assert_eq!(world.resource::<Inventory>().unwrap().count("potion"), 2);
+    assert!(world.get::<Pickup>(near).is_none());
+    // The other's out of reach.
+    assert_eq!(pickup::nearest_pickup(&world, player, PICKUP_RADIUS), None);
+
+    let toasts = world.resource_mut::<PickupToasts>().unwrap();
+    assert_eq!(toasts.current(), Some("Found Potion x2"));
+    assert!(!toasts.update(TOAST_TIME));
+    assert_eq!(toasts.current(), None);
+}
+
+#[test]
+fn pickups_come_back_by_their_rules() {
+    let (mut world, player) = world();
+    let pickups = [
+        field_pickup("chest", 1, 0.0, Respawn::Never),
+        field_pickup("berries", 1, 1.0, Respawn::EveryVisit),
+        field_pickup("herbs", 1, 2.0, Respawn::Days(2))
+    ];
+    assert_eq!(visit(&mut world, player, &pickups).len(), 3);
+    assert_eq!(world.resource::<GameFlags>().unwrap().get(&pickup::pickup_flag("woods", "chest")), 1);
+    assert_eq!(world.resource::<GameFlags>().unwrap().get(&pickup::pickup_flag("woods", "herbs")), 1);
+
+    // Only the berries are back the same day, and the herbs are back two days later.
+    assert_eq!(visit(&mut world, player, &pickups).len(), 1);
+    let clock = world.resource_mut::<GameClock>().unwrap();
+    clock.advance(Duration::from_secs_f32(24.0 * 60.0 / clock.rate));
+    assert_eq!(visit(&mut world, player, &pickups).len(), 1);
+    let clock = world.resource_mut::<GameClock>().unwrap();
+    clock.advance(Duration::from_secs_f32(24.0 * 60.0 / clock.rate));
+    assert_eq!(visit(&mut world, player, &pickups).len(), 2);
+    assert_eq!(world.resource::<Inventory>().unwrap().count("potion"), 7);
+}
+
+#[test]
+fn pickups_for_items_that_do_not_exist_stay_put() {
+    let (mut world, player) = world();
+    let mut pickup = field_pickup("odd", 1, 0.0, Respawn::Never);
+    pickup.item = "elixir".to_string();
+    pickup::spawn_pickups(&mut world, "woods", &[pickup]);
+    let entity = pickup::nearest_pickup(&world, player, PICKUP_RADIUS).unwrap();
+    assert_eq!(pickup::collect(&mut world, entity), Err("There's no item \"elixir\"".to_string()));
+    assert!(world.get::<Pickup>(entity).is_some());
+    assert!(!world.resource::<GameFlags>().unwrap().is_set(&pickup::pickup_flag("woods", "odd")));
+}
+
+#[test]
+fn saves_keep_both_the_item_and_it_being_gone() {
+    let (mut world, player) = world();
+    visit(&mut world, player, &[field_pickup("chest", 2, 0.0, Respawn::Never)]);
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&world, "Woods", None).to_bytes().unwrap()).unwrap();
+
+    let (mut loaded, player) = self::world();
+    save_game.apply(&mut loaded);
+    assert_eq!(loaded.resource::<Inventory>().unwrap().count("potion"), 2);
+    assert!(visit(&mut loaded, player, &[field_pickup("chest", 2, 0.0, Respawn::Never)]).is_empty());
+
+    // Items that have gone from the game since are left out.
+    loaded.insert_resource(ItemCatalog::default());
+    save_game.apply(&mut loaded);
+    assert_eq!(loaded.resource::<Inventory>().unwrap().slots().len(), 0);
+}
@@ -0,0 +1,56 @@
+// Play time and statistics, and keeping them in saves.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    play_stats::{self, PlayStats, PlayStatsState},
+    save::SaveGame,
+    world::World
+};
+
+#[test]
+fn paused_time_isnt_counted() {
+    let mut stats = PlayStats::restore(&PlayStatsState { playtime_seconds: 60, ..Default::default() });
+    std::thread::sleep(Duration::from_millis(20));
+    stats.set_paused(true);
+    let paused_at = stats.playtime();
+    assert!(paused_at >= Duration::from_millis(60_020));
+
+    std::thread::sleep(Duration::from_millis(20));
+    stats.set_paused(true);
+    assert_eq!(stats.playtime(), paused_at);
+
+    stats.set_paused(false);
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(stats.playtime() >= paused_at + Duration::from_millis(20));
+}
+
+#[test]
+fn stats_are_kept_in_saves() {
+    let mut world = World::new();
+    let mut stats = PlayStats::restore(&PlayStatsState { playtime_seconds: 3725, ..Default::default() });
+    stats.set_paused(true);
+    stats.add_steps(1200);
+    stats.record_battle_won();
+    stats.add_gil(350);
+    world.insert_resource(stats);
+
+    let save = SaveGame::from_bytes(&SaveGame::capture(&world, "Test Field", None).to_bytes().unwrap()).unwrap();
+    let expected = PlayStatsState { playtime_seconds: 3725, steps: 1200, battles_won: 1, gil_earned: 350 };
+    assert_eq!(save.stats, Some(expected));
+
+    let mut loaded = World::new();
+    save.apply(&mut loaded);
+    assert_eq!(loaded.resource::<PlayStats>().unwrap().save_state().steps, 1200);
+    assert_eq!(play_stats::format_playtime(Duration::from_secs(expected.playtime_seconds)), "1:02:05");
+}
+
+#[test]
+fn saves_without_stats_start_from_nothing() {
+    let save = SaveGame::from_bytes(&SaveGame::capture(&World::new(), "Test Field", None).to_bytes().unwrap()).unwrap();
+    assert_eq!(save.stats, None);
+
+    let mut world = World::new();
+    save.apply(&mut world);
+    assert_eq!(world.resource::<PlayStats>().unwrap().save_state(), PlayStatsState::default());
+}
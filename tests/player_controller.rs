@@ -0,0 +1,154 @@
+// Which way and how fast the keys and stick walk the player, going by the camera.
+
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Point3, Vector2, Vector3};
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    animation::{AnimationClip, AnimationPlayer},
+    camera::Camera,
+    field::FieldDescriptor,
+    player_controller::{Gait, MovementMode, PlayerController, RUN_SPEED, RUN_THRESHOLD, STICK_DEADZONE, WALK_SPEED},
+    transform::Transform,
+    vision::VisionBlocker,
+    world::World
+};
+
+// A camera looking at the origin from `eye`.
+fn camera(x: f32, z: f32) -> Camera {
+    Camera { eye: Point3::new(x, 5.0, z), target: Point3::new(0.0, 0.0, 0.0), ..Default::default() }
+}
+
+fn assert_near(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).magnitude() < 1e-4, "{:?} isn't {:?}", a, b);
+}
+
+fn holding(keys: &[VirtualKeyCode]) -> PlayerController {
+    let mut controller = PlayerController::new();
+    for key in keys {
+        assert!(controller.handle_key(*key, true));
+    }
+    controller
+}
+
+#[test]
+fn up_goes_away_from_the_camera() {
+    assert_eq!("World".parse(), Ok(MovementMode::World));
+    assert!("tank".parse::<MovementMode>().unwrap_err().contains("Unknown movement mode"));
+
+    // Looking along +x from the side, up goes along +x and right along +z.
+    let side = camera(-10.0, 0.0);
+    assert_near(holding(&[VirtualKeyCode::Up]).direction(&side, MovementMode::Camera, false), Vector3::unit_x());
+    assert_near(holding(&[VirtualKeyCode::D]).direction(&side, MovementMode::Camera, false), Vector3::unit_z());
+    // Unless it's going by the world, or it's a 2D field.
+    assert_near(holding(&[VirtualKeyCode::Up]).direction(&side, MovementMode::World, false), -Vector3::unit_z());
+    assert_near(holding(&[VirtualKeyCode::Up]).direction(&side, MovementMode::Camera, true), -Vector3::unit_z());
+
+    // Diagonals aren't any faster.
+    assert_near(holding(&[VirtualKeyCode::Up, VirtualKeyCode::Right]).direction(&side, MovementMode::Camera, false).normalize(),
+        holding(&[VirtualKeyCode::Up, VirtualKeyCode::Right]).direction(&side, MovementMode::Camera, false));
+    assert!(!PlayerController::new().handle_key(VirtualKeyCode::Return, true));
+}
+
+#[test]
+fn camera_cuts_dont_turn_the_player_round_mid_walk() {
+    let (front, side) = (camera(0.0, 10.0), camera(-10.0, 0.0));
+    let mut controller = holding(&[VirtualKeyCode::Up]);
+    assert_near(controller.direction(&front, MovementMode::Camera, false), -Vector3::unit_z());
+    // Still the same way after the cut, for as long as up's held.
+    assert_near(controller.direction(&side, MovementMode::Camera, false), -Vector3::unit_z());
+    assert_near(controller.direction(&side, MovementMode::Camera, false), -Vector3::unit_z());
+
+    // Letting go picks up the new camera.
+    controller.handle_key(VirtualKeyCode::Up, false);
+    assert_near(controller.direction(&side, MovementMode::Camera, false), Vector3::new(0.0, 0.0, 0.0));
+    controller.handle_key(VirtualKeyCode::Up, true);
+    assert_near(controller.direction(&side, MovementMode::Camera, false), Vector3::unit_x());
+}
+
+#[test]
+fn camera_pans_are_followed() {
+    let mut controller = holding(&[VirtualKeyCode::Up]);
+    controller.direction(&camera(0.0, 10.0), MovementMode::Camera, false);
+    let panned = camera(-1.0, 10.0);
+    let forward = panned.target - panned.eye;
+    assert_near(controller.direction(&panned, MovementMode::Camera, false), Vector3::new(forward.x, 0.0, forward.z).normalize());
+}
+
+#[test]
+fn walking_stops_at_blockers() {
+    let mut world = World::new();
+    world.insert_resource(FieldDescriptor { blockers: vec![VisionBlocker { min: [-1.0, -3.0], max: [1.0, -2.5] }], ..Default::default() });
+    let player = world.spawn();
+    world.insert(player, Transform::default());
+    let mut controller = holding(&[VirtualKeyCode::Up]);
+    let front = camera(0.0, 10.0);
+
+    let walked = controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(400));
+    assert!((walked - WALK_SPEED * 0.4).abs() < 1e-4);
+    assert!(controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(400)) > 0.0);
+    assert_eq!(controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(400)), 0.0);
+    assert!((world.get::<Transform>(player).unwrap().position.z + 2.0).abs() < 0.01);
+}
+
+#[test]
+fn shift_runs_unless_always_running() {
+    let mut controller = holding(&[VirtualKeyCode::Up]);
+    assert_eq!(controller.pace(false, false), (Gait::Walking, WALK_SPEED));
+    assert_eq!(controller.pace(true, false), (Gait::Running, RUN_SPEED));
+    assert!(controller.handle_key(VirtualKeyCode::LShift, true));
+    assert_eq!(controller.pace(false, false), (Gait::Running, RUN_SPEED));
+    assert_eq!(controller.pace(true, false), (Gait::Walking, WALK_SPEED));
+    controller.handle_key(VirtualKeyCode::Up, false);
+    assert_eq!(controller.pace(false, false), (Gait::Standing, 0.0));
+}
+
+#[test]
+fn the_stick_walks_slower_the_less_it_is_pushed() {
+    let mut controller = PlayerController::new();
+    // Inside the deadzone's nothing.
+    controller.set_stick(Vector2::new(0.0, STICK_DEADZONE * 0.5));
+    assert_eq!(controller.pace(false, false).0, Gait::Standing);
+
+    // Halfway to running is half walking speed.
+    let halfway = STICK_DEADZONE + (1.0 - STICK_DEADZONE) * RUN_THRESHOLD * 0.5;
+    controller.set_stick(Vector2::new(0.0, halfway));
+    let (gait, speed) = controller.pace(false, false);
+    assert_eq!(gait, Gait::Walking);
+    assert!((speed - WALK_SPEED * 0.5).abs() < 1e-4);
+    // Shift doesn't make it run, the stick's push does.
+    controller.handle_key(VirtualKeyCode::LShift, true);
+    assert_eq!(controller.pace(true, false).0, Gait::Walking);
+    controller.set_stick(Vector2::new(1.0, 1.0));
+    assert_eq!(controller.pace(false, false), (Gait::Running, RUN_SPEED));
+
+    // The stick goes the way it's pushed.
+    assert_near(controller.direction(&camera(0.0, 10.0), MovementMode::Camera, false), Vector3::new(1.0, 0.0, -1.0).normalize());
+}
+
+#[test]
+fn clips_keep_in_time_with_the_feet() {
+    let mut world = World::new();
+    let player = world.spawn();
+    world.insert(player, Transform::default());
+    let mut animation = AnimationPlayer::new();
+    animation.play(AnimationClip::new("walk", Duration::from_secs(1), true));
+    world.insert(player, animation);
+    let front = camera(0.0, 10.0);
+
+    let mut controller = PlayerController::new();
+    controller.set_stick(Vector2::new(0.0, STICK_DEADZONE + (1.0 - STICK_DEADZONE) * RUN_THRESHOLD * 0.5));
+    controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(100));
+    assert_eq!(controller.gait(), Gait::Walking);
+    assert!((world.get::<AnimationPlayer>(player).unwrap().speed - 0.5).abs() < 1e-4);
+
+    controller.set_stick(Vector2::new(0.0, 1.0));
+    controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(100));
+    assert_eq!(controller.gait(), Gait::Running);
+    assert!((world.get::<AnimationPlayer>(player).unwrap().speed - 1.0).abs() < 1e-4);
+
+    controller.release_all();
+    controller.update(&mut world, player, &front, MovementMode::Camera, false, Duration::from_millis(100));
+    assert_eq!((controller.gait(), world.get::<AnimationPlayer>(player).unwrap().speed), (Gait::Standing, 1.0));
+}
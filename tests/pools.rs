@@ -0,0 +1,116 @@
+// Skill costs from pools besides MP, and items.
+
+use ps_rpg_engine::{
+    inventory::{Inventory, Item, ItemEffect},
+    party::{PartyMember, Skill, Stats, TargetType},
+    pools::{self, AfterBattle, PoolDefinition, Pools, SkillCost}
+};
+
+const POOLS: &str = "
+[tp]
+name = TP
+max = 100
+start = 0
+per_turn = 5
+on_damage = 1
+after_battle = empty
+
+# Kept between battles
+[ki]
+max = 3
+after_battle = full
+";
+
+fn skill(mp_cost: u32, costs: &[&str]) -> Skill {
+    Skill {
+        name: "Gem Slash".to_string(),
+        description: String::new(),
+        mp_cost,
+        costs: costs.iter().map(|cost| cost.parse().unwrap()).collect(),
+        target: TargetType::Enemy,
+        field: None
+    }
+}
+
+fn gems(count: u32) -> Inventory {
+    let gem = Item { id: "magic_gem".to_string(), name: "Magic Gem".to_string(), description: String::new(), effect: ItemEffect::None };
+    let mut inventory = Inventory::new();
+    inventory.add(&gem, count);
+    inventory
+}
+
+#[test]
+fn pools_and_costs_are_read() {
+    let pools = Pools(PoolDefinition::parse_list(POOLS).unwrap());
+    let tp = pools.get("tp").unwrap();
+    assert_eq!((tp.name.as_str(), tp.max, tp.start, tp.per_turn, tp.after_battle), ("TP", 100, Some(0), 5, AfterBattle::Empty));
+    assert_eq!(pools.get("ki").unwrap().name, "KI");
+
+    assert!(PoolDefinition::parse_list("[mp]").unwrap_err().contains("MP is in everyone's stats"));
+    assert!(PoolDefinition::parse_list("[tp]\nafter_battle = sometimes").unwrap_err().starts_with("Line 2: Unknown after_battle"));
+
+    assert_eq!("TP 50".parse(), Ok(SkillCost::Pool { pool: "tp".to_string(), amount: 50 }));
+    assert_eq!("item magic_gem".parse(), Ok(SkillCost::Item { item: "magic_gem".to_string(), count: 1 }));
+    assert!("tp lots".parse::<SkillCost>().is_err());
+    assert_eq!(pools.cost_label(&skill(4, &["tp 50", "item magic_gem 2"]), &gems(1)), "4 MP, 50 TP, 2 Magic Gem");
+    assert_eq!(pools.cost_label(&skill(0, &[]), &gems(1)), "0 MP");
+}
+
+#[test]
+fn skills_take_everything_they_cost_or_nothing() {
+    let mut member = PartyMember::new("Aria", Stats { mp: 10, max_mp: 10, ..Default::default() });
+    member.pools.insert("tp".to_string(), 60);
+    let mut inventory = gems(2);
+    let costly = skill(4, &["tp 50", "item magic_gem 2"]);
+
+    assert_eq!(pools::pay(&mut member, &costly, &mut inventory), Ok(()));
+    assert_eq!((member.stats.mp, member.pool("tp"), inventory.count("magic_gem")), (6, 10, 0));
+
+    // Short of gems, so nothing's taken.
+    inventory = gems(1);
+    member.pools.insert("tp".to_string(), 100);
+    assert_eq!(pools::pay(&mut member, &costly, &mut inventory), Err("There aren't enough magic_gem for Gem Slash".to_string()));
+    assert_eq!((member.stats.mp, member.pool("tp"), inventory.count("magic_gem")), (6, 100, 1));
+    assert_eq!(pools::can_afford(&member, &skill(0, &["tp 101"]), &inventory), Err("Aria doesn't have enough TP".to_string()));
+}
+
+#[test]
+fn costs_listed_twice_are_added_up() {
+    let mut member = PartyMember::new("Aria", Stats::default());
+    member.pools.insert("tp".to_string(), 40);
+    let mut inventory = gems(3);
+
+    // 30 TP twice is 60, more than there is.
+    let twice = skill(0, &["tp 30", "tp 30"]);
+    assert_eq!(pools::pay(&mut member, &twice, &mut inventory), Err("Aria doesn't have enough TP".to_string()));
+    assert_eq!(member.pool("tp"), 40);
+    assert_eq!(pools::uses_left(&member, &twice, &inventory), Some(0));
+
+    let gems_twice = skill(0, &["item magic_gem 2", "item magic_gem 2"]);
+    assert_eq!(pools::can_afford(&member, &gems_twice, &inventory), Err("There aren't enough magic_gem for Gem Slash".to_string()));
+
+    member.pools.insert("tp".to_string(), 70);
+    assert_eq!(pools::pay(&mut member, &twice, &mut inventory), Ok(()));
+    assert_eq!(member.pool("tp"), 10);
+}
+
+#[test]
+fn pools_fill_through_battles() {
+    let pools = Pools(PoolDefinition::parse_list(POOLS).unwrap());
+    let mut member = PartyMember::new("Aria", Stats { hp: 200, max_hp: 200, ..Default::default() });
+    member.pools.insert("tp".to_string(), 40);
+
+    pools.start_battle(&mut member);
+    assert_eq!(member.pool("tp"), 0);
+    // 50 HP is a quarter of it.
+    pools.take_damage(&mut member, 50);
+    assert_eq!(member.pool("tp"), 25);
+    pools.end_turn(&mut member);
+    assert_eq!(member.pool("tp"), 30);
+    pools.take_damage(&mut member, 1000);
+    assert_eq!(member.pool("tp"), 100);
+    assert_eq!(pools.labels(&member), vec!["TP 100/100", "KI 0/3"]);
+
+    pools.end_battle(&mut member);
+    assert_eq!((member.pool("tp"), member.pool("ki")), (0, 3));
+}
@@ -0,0 +1,71 @@
+// Finding neighbouring fields and loading their assets ahead of time.
+
+use std::time::{Duration, Instant};
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    field::{FieldDescriptor, FieldExit, FieldMap, FieldProp},
+    prefetch::FieldPrefetcher,
+    transform::Transform
+};
+
+fn exit(target: &str) -> FieldExit {
+    FieldExit {
+        target: target.to_string(),
+        position: Vector3::new(0.0, 0.0, 0.0)
+    }
+}
+
+// A hall with doors to a room and a garden, where the garden has the test background and a
+// prop in it.
+fn fields() -> FieldMap {
+    let mut fields = FieldMap::new();
+    fields.insert("hall", FieldDescriptor {
+        exits: vec![exit("room"), exit("garden"), exit("room"), exit("nowhere")],
+        ..Default::default()
+    });
+    fields.insert("room", FieldDescriptor {
+        exits: vec![exit("hall")],
+        ..Default::default()
+    });
+    fields.insert("garden", FieldDescriptor {
+        background: "fields/test_field.png".to_string(),
+        props: vec![FieldProp { model: "models/test_prop.gltf".to_string(), transform: Transform::default(), ..Default::default() }],
+        exits: vec![exit("hall")],
+        ..Default::default()
+    });
+    fields
+}
+
+#[test]
+fn neighbours_come_from_exits() {
+    let fields = fields();
+    let mut neighbours = fields.neighbours("hall");
+    neighbours.sort();
+    assert_eq!(neighbours, vec!["garden", "room"]);
+    assert_eq!(fields.neighbours("room"), vec!["hall"]);
+    assert!(fields.neighbours("nowhere").is_empty());
+}
+
+#[test]
+fn neighbouring_fields_are_prefetched() {
+    let fields = fields();
+    let mut prefetcher = FieldPrefetcher::new(&AssetServer::new(env!("CARGO_MANIFEST_DIR")));
+    prefetcher.prefetch_neighbours(&fields, "hall");
+
+    let started = Instant::now();
+    while !prefetcher.is_done() {
+        assert!(started.elapsed() < Duration::from_secs(10), "Prefetching took too long");
+        std::thread::sleep(Duration::from_millis(5));
+        prefetcher.update();
+    }
+    assert!(prefetcher.assets().contains("fields/test_field.png"));
+    assert!(prefetcher.assets().contains("models/test_prop.gltf"));
+
+    // From the room only the hall is next door, which needs nothing, so the garden's assets
+    // are let go.
+    prefetcher.prefetch_neighbours(&fields, "room");
+    assert!(prefetcher.assets().is_empty());
+}
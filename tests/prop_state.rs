@@ -0,0 +1,92 @@
+// Props changing with their flags, and what they put in the way.
+
+use ps_rpg_engine::{
+    field::{self, FieldDescriptor},
+    flags::GameFlags,
+    prop_state::{self, PropState, PropStates},
+    tilemap::{TileLayer, Tilemap},
+    vision::VisionBlocker,
+    world::{Entity, World}
+};
+
+fn bridge(world: &mut World) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, PropStates::new("bridge_broken", vec![
+        PropState { name: "intact".to_string(), value: 0, layers: vec!["bridge".to_string()], ..Default::default() },
+        PropState {
+            name: "broken".to_string(),
+            value: 1,
+            layers: vec!["rubble".to_string()],
+            blockers: vec![VisionBlocker { min: [0.0, 0.0], max: [1.0, 4.0] }],
+            ..Default::default()
+        }
+    ], vec![None, None]));
+    entity
+}
+
+fn state(world: &World, entity: Entity) -> Option<String> {
+    world.get::<PropStates>(entity).unwrap().current().map(|state| state.name.clone())
+}
+
+fn layer(name: &str) -> TileLayer {
+    TileLayer { name: name.to_string(), visible: true, ..Default::default() }
+}
+
+fn visible(world: &World, name: &str) -> bool {
+    world.resource::<Tilemap>().unwrap().layers.iter().find(|layer| layer.name == name).unwrap().visible
+}
+
+#[test]
+fn props_follow_their_flag() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::default());
+    let bridge = bridge(&mut world);
+    assert_eq!(state(&world, bridge), None);
+
+    assert!(prop_state::update_prop_states(&mut world));
+    assert_eq!(state(&world, bridge).as_deref(), Some("intact"));
+    // Nothing's changed since.
+    assert!(!prop_state::update_prop_states(&mut world));
+
+    world.resource_mut::<GameFlags>().unwrap().set("bridge_broken", 1);
+    assert!(prop_state::update_prop_states(&mut world));
+    assert_eq!(state(&world, bridge).as_deref(), Some("broken"));
+
+    // Values without a state get the first one.
+    world.resource_mut::<GameFlags>().unwrap().set("bridge_broken", 7);
+    assert!(prop_state::update_prop_states(&mut world));
+    assert_eq!(state(&world, bridge).as_deref(), Some("intact"));
+}
+
+#[test]
+fn states_show_and_hide_tile_layers() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::default());
+    world.insert_resource(Tilemap { layers: vec![layer("ground"), layer("bridge"), layer("rubble")], ..Default::default() });
+    bridge(&mut world);
+
+    prop_state::update_prop_states(&mut world);
+    assert!(visible(&world, "ground") && visible(&world, "bridge") && !visible(&world, "rubble"));
+
+    world.resource_mut::<GameFlags>().unwrap().set("bridge_broken", 1);
+    prop_state::update_prop_states(&mut world);
+    assert!(visible(&world, "ground") && !visible(&world, "bridge") && visible(&world, "rubble"));
+}
+
+#[test]
+fn blockers_come_from_the_field_and_its_props() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::default());
+    let wall = VisionBlocker { min: [5.0, 5.0], max: [6.0, 6.0] };
+    world.insert_resource(FieldDescriptor { blockers: vec![wall], ..Default::default() });
+    bridge(&mut world);
+
+    prop_state::update_prop_states(&mut world);
+    assert_eq!(field::blockers(&world), vec![wall]);
+
+    world.resource_mut::<GameFlags>().unwrap().set("bridge_broken", 1);
+    prop_state::update_prop_states(&mut world);
+    let blockers = field::blockers(&world);
+    assert_eq!(blockers.len(), 2);
+    assert!(blockers.iter().any(|blocker| blocker.contains(0.5, 2.0)));
+}
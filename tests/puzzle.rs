@@ -0,0 +1,114 @@
+// Puzzles that stay how they were left in each field, and keeping them in saves.
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    field::FieldEntity,
+    flags::GameFlags,
+    puzzle::{self, FieldPuzzle, FieldPuzzles},
+    save::SaveGame,
+    transform::Transform,
+    world::{Entity, Name, World}
+};
+
+fn cave_puzzle() -> FieldPuzzle {
+    FieldPuzzle { flags: vec!["cave.switch".to_string()], pieces: vec!["cave.block".to_string()] }
+}
+
+// Spawn the cave's block and go in, like entering the field does.
+fn enter_cave(world: &mut World) -> Entity {
+    let block = world.spawn();
+    world.insert(block, Name("cave.block".to_string()));
+    world.insert(block, Transform::from_position(Vector3::new(1.0, 0.0, 1.0)));
+    world.insert(block, FieldEntity);
+    puzzle::enter_field(world, "cave_1", &cave_puzzle());
+    block
+}
+
+// Leave, like warping does, despawning the field's entities.
+fn leave(world: &mut World) {
+    puzzle::leave_field(world);
+    let entities: Vec<Entity> = world.query::<FieldEntity>().map(|(entity, _)| entity).collect();
+    for entity in entities {
+        world.despawn(entity);
+    }
+}
+
+fn position(world: &World, entity: Entity) -> Vector3<f32> {
+    world.get::<Transform>(entity).unwrap().position
+}
+
+#[test]
+fn puzzles_are_how_they_were_left() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::new());
+    let block = enter_cave(&mut world);
+    world.get_mut::<Transform>(block).unwrap().position = Vector3::new(3.0, 0.0, 1.0);
+    world.resource_mut::<GameFlags>().unwrap().set("cave.switch", 2);
+
+    // The switch only means something in the cave.
+    leave(&mut world);
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("cave.switch"), 0);
+    let state = world.resource::<FieldPuzzles>().unwrap().get("cave_1").cloned().unwrap();
+    assert_eq!(state.flags.get("cave.switch"), Some(&2));
+    assert_eq!(state.pieces.get("cave.block"), Some(&Vector3::new(3.0, 0.0, 1.0)));
+
+    // Fields without a puzzle don't touch it.
+    puzzle::enter_field(&mut world, "town", &FieldPuzzle::default());
+    leave(&mut world);
+
+    let block = enter_cave(&mut world);
+    assert_eq!(position(&world, block), Vector3::new(3.0, 0.0, 1.0));
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("cave.switch"), 2);
+}
+
+#[test]
+fn resetting_starts_puzzles_again() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::new());
+    let block = enter_cave(&mut world);
+    world.get_mut::<Transform>(block).unwrap().position = Vector3::new(3.0, 0.0, 1.0);
+    world.resource_mut::<GameFlags>().unwrap().set("cave.switch", 1);
+
+    // The one the player's in goes back there and then.
+    assert_eq!(puzzle::reset(&mut world, "cave*"), vec!["cave_1".to_string()]);
+    assert_eq!(position(&world, block), Vector3::new(1.0, 0.0, 1.0));
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("cave.switch"), 0);
+
+    world.resource_mut::<GameFlags>().unwrap().set("cave.switch", 1);
+    leave(&mut world);
+    assert!(puzzle::reset(&mut world, "cave_2").is_empty());
+    assert!(world.resource::<FieldPuzzles>().unwrap().get("cave_1").is_some());
+    assert_eq!(puzzle::reset(&mut world, "cave_1"), vec!["cave_1".to_string()]);
+    assert!(world.resource::<FieldPuzzles>().unwrap().get("cave_1").is_none());
+
+    enter_cave(&mut world);
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("cave.switch"), 0);
+    assert!(puzzle::matches("cave*", "cave_2") && !puzzle::matches("cave", "cave_2"));
+}
+
+#[test]
+fn puzzles_are_kept_in_saves() {
+    let mut world = World::new();
+    world.insert_resource(GameFlags::new());
+    let block = enter_cave(&mut world);
+    world.get_mut::<Transform>(block).unwrap().position = Vector3::new(-2.5, 0.0, 4.0);
+    world.resource_mut::<GameFlags>().unwrap().set("cave.switch", 1);
+
+    // Saving in the field keeps how it is right now.
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&world, "Cave", None).to_bytes().unwrap()).unwrap();
+    let state = save_game.puzzles.get("cave_1").unwrap();
+    assert_eq!(state.flags.get("cave.switch"), Some(&1));
+    assert_eq!(state.pieces.get("cave.block"), Some(&Vector3::new(-2.5, 0.0, 4.0)));
+
+    // Loading it puts the field back how it was saved.
+    world.get_mut::<Transform>(block).unwrap().position = Vector3::new(0.0, 0.0, 0.0);
+    world.resource_mut::<GameFlags>().unwrap().set("cave.switch", 0);
+    save_game.apply(&mut world);
+    assert_eq!(position(&world, block), Vector3::new(-2.5, 0.0, 4.0));
+    assert_eq!(world.resource::<GameFlags>().unwrap().get("cave.switch"), 1);
+
+    // Saves from before there were puzzles have none started.
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&World::new(), "Cave", None).to_bytes().unwrap()).unwrap();
+    assert!(save_game.puzzles.is_empty());
+}
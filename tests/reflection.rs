@@ -0,0 +1,71 @@
+// Mirroring the camera for field reflections, and where they're drawn.
+
+use cgmath::{Point3, Vector3};
+
+use ps_rpg_engine::{
+    camera::Camera,
+    field::FieldReflection,
+    renderer::{reflection_scissor, SCREEN_WIDTH, SCREEN_HEIGHT}
+};
+
+fn pond(x: f32, y: f32, width: f32, height: f32, ripple: f32) -> FieldReflection {
+    FieldReflection {
+        x,
+        y,
+        width,
+        height,
+        point: Point3::new(0.0, 0.0, 0.0),
+        normal: Vector3::new(0.0, 1.0, 0.0),
+        strength: 0.5,
+        ripple
+    }
+}
+
+#[test]
+fn water_mirrors_the_camera_below_it() {
+    let camera = Camera {
+        eye: Point3::new(1.0, 5.0, 10.0),
+        target: Point3::new(0.0, 1.0, 0.0),
+        ..Camera::default()
+    };
+    // The normal doesn't need to be normalised.
+    let mirrored = camera.reflected(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 3.0, 0.0));
+    assert_eq!(mirrored.eye, Point3::new(1.0, -3.0, 10.0));
+    assert_eq!(mirrored.target, Point3::new(0.0, 1.0, 0.0));
+    assert_eq!(mirrored.up, Vector3::new(0.0, -1.0, 0.0));
+    assert_eq!((mirrored.fovy, mirrored.znear, mirrored.zfar), (camera.fovy, camera.znear, camera.zfar));
+
+    // Mirroring twice gets back to where it started.
+    assert_eq!(mirrored.reflected(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0)), camera);
+}
+
+#[test]
+fn a_wall_mirror_mirrors_sideways() {
+    let camera = Camera {
+        eye: Point3::new(0.0, 2.0, 6.0),
+        target: Point3::new(0.0, 2.0, 0.0),
+        ..Camera::default()
+    };
+    let mirrored = camera.reflected(Point3::new(0.0, 0.0, -2.0), Vector3::new(0.0, 0.0, 1.0));
+    assert_eq!(mirrored.eye, Point3::new(0.0, 2.0, -10.0));
+    assert_eq!(mirrored.target, Point3::new(0.0, 2.0, -4.0));
+    // Up is along the mirror, so it stays the same.
+    assert_eq!(mirrored.up, camera.up);
+}
+
+#[test]
+fn scissor_is_on_the_other_side_of_the_screen() {
+    let width = SCREEN_WIDTH as u32;
+    assert_eq!(reflection_scissor(&pond(0.0, 400.0, 100.0, 200.0, 0.0)), Some([width - 100, 400, 100, 200]));
+    // Ripples need room either side, but not past the edge of the screen.
+    assert_eq!(reflection_scissor(&pond(0.0, 400.0, 100.0, 200.0, 2.5)), Some([width - 103, 397, 103, 206]));
+    // Right across the screen is the whole width.
+    assert_eq!(reflection_scissor(&pond(0.0, 0.0, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32, 0.0)), Some([0, 0, width, SCREEN_HEIGHT as u32]));
+}
+
+#[test]
+fn offscreen_reflections_are_skipped() {
+    assert_eq!(reflection_scissor(&pond(-200.0, 100.0, 100.0, 100.0, 0.0)), None);
+    assert_eq!(reflection_scissor(&pond(100.0, SCREEN_HEIGHT as f32, 100.0, 100.0, 0.0)), None);
+    assert_eq!(reflection_scissor(&pond(100.0, 100.0, 0.0, 100.0, 0.0)), None);
+}
@@ -0,0 +1,89 @@
+// The seeded random number streams that battles, encounters and loot draw from.
+
+use ps_rpg_engine::rng::{streams, Rng, RngStream};
+
+fn draws(stream: &mut RngStream) -> Vec<u32> {
+    (0..8).map(|_| stream.next_u32()).collect()
+}
+
+#[test]
+fn the_same_seed_gives_the_same_numbers() {
+    let (mut a, mut b) = (Rng::new(1234), Rng::new(1234));
+    assert_eq!(draws(a.stream(streams::BATTLE)), draws(b.stream(streams::BATTLE)));
+    assert_ne!(draws(Rng::new(1235).stream(streams::BATTLE)), draws(Rng::new(1234).stream(streams::BATTLE)));
+    // Each stream's different, even from the same seed.
+    assert_ne!(draws(a.stream(streams::LOOT)), draws(a.stream(streams::BATTLE)));
+
+    // Starting one over goes back to its first numbers.
+    let first = draws(Rng::new(1234).stream(streams::BATTLE));
+    a.reset_stream(streams::BATTLE);
+    assert_eq!(draws(a.stream(streams::BATTLE)), first);
+}
+
+#[test]
+fn streams_dont_affect_each_other() {
+    let (mut a, mut b) = (Rng::new(99), Rng::new(99));
+    // Opening chests in one doesn't change what the next battle does.
+    for _ in 0..100 {
+        a.stream(streams::LOOT).next_u32();
+    }
+    assert_eq!(draws(a.stream(streams::BATTLE)), draws(b.stream(streams::BATTLE)));
+}
+
+#[test]
+fn saved_streams_carry_on_where_they_were() {
+    let mut rng = Rng::new(42);
+    draws(rng.stream(streams::BATTLE));
+    draws(rng.stream(streams::ENCOUNTERS));
+    let state = rng.save_state();
+    let next = (draws(rng.stream(streams::BATTLE)), draws(rng.stream(streams::ENCOUNTERS)), draws(rng.stream(streams::LOOT)));
+
+    let mut restored = Rng::new(0);
+    restored.stream(streams::BATTLE).next_u32();
+    restored.restore_state(&state);
+    assert_eq!(restored.seed(), 42);
+    assert_eq!((draws(restored.stream(streams::BATTLE)), draws(restored.stream(streams::ENCOUNTERS)), draws(restored.stream(streams::LOOT))), next);
+}
+
+#[test]
+fn ranges_stay_in_bounds() {
+    let mut stream = RngStream::new(7, 1);
+    for _ in 0..1000 {
+        assert!((10..20).contains(&stream.range(10..20)));
+        assert!((-3..=3).contains(&stream.range_i32(-3, 3)));
+        assert!((2..=3).contains(&stream.range_inclusive(2, 3)));
+        assert!(stream.range_inclusive(u32::MAX - 1, u32::MAX) >= u32::MAX - 1);
+        let value = stream.range_f32(1.0, 2.0);
+        assert!((1.0..2.0).contains(&value));
+    }
+    assert_eq!(stream.range_i32(5, 5), 5);
+    // Backwards is min, and all of i32 doesn't overflow.
+    assert_eq!(stream.range_i32(5, -5), 5);
+    stream.range_i32(i32::MIN, i32::MAX);
+    // Nor does an inclusive range up to u32::MAX, which a formation's count can be.
+    assert_eq!(stream.range_inclusive(u32::MAX, u32::MAX), u32::MAX);
+    assert_eq!(stream.range_inclusive(5, 1), 5);
+    stream.range_inclusive(0, u32::MAX);
+    assert_eq!(stream.pick::<u32>(&[]), None);
+    assert!(!stream.chance(0.0) && stream.chance(1.0));
+}
+
+#[test]
+fn weights_pick_in_proportion() {
+    let mut stream = RngStream::new(3, 5);
+    assert_eq!(stream.weighted_index(&[]), None);
+    assert_eq!(stream.weighted_index(&[0, 0]), None);
+    assert_eq!(stream.weighted_index(&[0, 5, 0]), Some(1));
+
+    let mut counts = [0; 2];
+    for _ in 0..1000 {
+        counts[stream.weighted_index(&[1, 3]).unwrap()] += 1;
+    }
+    assert!(counts[1] > counts[0] * 2, "{:?}", counts);
+
+    // Weights adding up to more than a u32 holds.
+    for _ in 0..100 {
+        assert!(stream.weighted_index(&[u32::MAX, u32::MAX, 1]).unwrap() < 3);
+    }
+    assert_eq!(stream.weighted_index(&[u32::MAX, 0, u32::MAX]).map(|index| index != 1), Some(true));
+}
@@ -0,0 +1,81 @@
+// Exporting saves to files of their own and importing them again.
+
+use std::path::PathBuf;
+
+use ps_rpg_engine::{
+    flags::GameFlags,
+    save::{self, ExportedSave, SaveError, SaveGame},
+    world::World
+};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_export_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn save_bytes() -> Vec<u8> {
+    let mut world = World::new();
+    let mut flags = GameFlags::new();
+    flags.set("boss_beaten", 1);
+    world.insert_resource(flags);
+    SaveGame::capture(&world, "Test Field", None).to_bytes().unwrap()
+}
+
+fn is_format_error(result: Result<ExportedSave, SaveError>) -> bool {
+    matches!(result, Err(SaveError::Format(_)))
+}
+
+#[test]
+fn exports_go_from_one_slot_to_another() {
+    let dir = dir("slots");
+    let (saves, exports) = (dir.join("saves"), dir.join("exports"));
+    std::fs::create_dir_all(&saves).unwrap();
+    std::fs::write(save::slot_path(&saves, 0), save_bytes()).unwrap();
+
+    let export_path = save::export_path(&exports, 0);
+    save::export_save(&save::slot_path(&saves, 0), &export_path).unwrap();
+    let export = save::import_save(&export_path, &save::slot_path(&saves, 2)).unwrap();
+    let imported = SaveGame::read_from(&save::slot_path(&saves, 2));
+    // Empty slots have nothing to export.
+    let empty = save::export_save(&save::slot_path(&saves, 1), &save::export_path(&exports, 1));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(export.game_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(imported.unwrap().flags, vec![("boss_beaten".to_string(), 1)]);
+    assert!(matches!(empty, Err(SaveError::Io(_))));
+}
+
+#[test]
+fn damaged_or_newer_exports_are_refused() {
+    let bytes = ExportedSave::new(save_bytes()).unwrap().to_bytes();
+    assert!(ExportedSave::from_bytes(&bytes).is_ok());
+
+    // A byte of the save changed on the way.
+    let mut damaged = bytes.clone();
+    *damaged.last_mut().unwrap() ^= 1;
+    assert!(is_format_error(ExportedSave::from_bytes(&damaged)));
+    assert!(is_format_error(ExportedSave::from_bytes(&bytes[..bytes.len() / 2])));
+    assert!(is_format_error(ExportedSave::from_bytes(&save_bytes())));
+
+    // A length far past the end, which mustn't overflow on 32-bit targets either.
+    for length in [u32::MAX, u32::MAX - 3, bytes.len() as u32] {
+        let mut forged = bytes.clone();
+        forged[12..16].copy_from_slice(&length.to_le_bytes());
+        assert!(is_format_error(ExportedSave::from_bytes(&forged)));
+    }
+
+    // From a newer kind of export.
+    let mut newer = bytes.clone();
+    newer[8..12].copy_from_slice(&99u32.to_le_bytes());
+    assert!(is_format_error(ExportedSave::from_bytes(&newer)));
+
+    // Or holding a save from a newer version of the game, with its checksum put right.
+    let mut save = save_bytes();
+    save[8..12].copy_from_slice(&99u32.to_le_bytes());
+    let export = ExportedSave { game_version: "9.0.0".to_string(), bytes: save };
+    assert!(is_format_error(ExportedSave::from_bytes(&export.to_bytes())));
+
+    // Broken saves can't be exported in the first place.
+    assert!(ExportedSave::new(b"PSRPGSAV".to_vec()).is_err());
+}
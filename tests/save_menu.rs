@@ -0,0 +1,77 @@
+// Using the save menu with the mouse and touch.
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    pointer::PointerEvent,
+    save_menu::{SaveMenu, SaveMenuAction, SaveMenuMode, SlotInfo}
+};
+
+fn slot() -> Option<SlotInfo> {
+    Some(SlotInfo {
+        location: "Test Field".to_string(),
+        saved_at: 0,
+        playtime: None,
+        thumbnail: None
+    })
+}
+
+// Where a slot is with the default settings, going by the layout: below the title, then a
+// slot every 136 pixels.
+fn slot_y(index: usize) -> f32 {
+    100.0 + 136.0 * index as f32
+}
+
+#[test]
+fn clicking_a_slot_picks_it() {
+    let accessibility = Accessibility::new();
+    let mut menu = SaveMenu::new();
+    menu.open(SaveMenuMode::Load, vec![None, slot(), None]);
+
+    // Nothing to load from an empty slot.
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: slot_y(0) }, &accessibility).action, None);
+    assert!(menu.is_open());
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: slot_y(1) }, &accessibility).action, Some(SaveMenuAction::Load(1)));
+}
+
+#[test]
+fn hovering_and_scrolling_move_the_selection() {
+    let accessibility = Accessibility::new();
+    let mut menu = SaveMenu::new();
+    menu.open(SaveMenuMode::Save, vec![None, None, None]);
+
+    assert!(menu.handle_pointer(PointerEvent::Hover { x: 100.0, y: slot_y(2) }, &accessibility).changed);
+    // Moving about over the same slot doesn't change anything, so there's nothing to redraw.
+    assert!(!menu.handle_pointer(PointerEvent::Hover { x: 120.0, y: slot_y(2) + 4.0 }, &accessibility).changed);
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: slot_y(2) }, &accessibility).action, Some(SaveMenuAction::Save(2)));
+
+    // Scrolling stops at the ends of the list.
+    menu.handle_pointer(PointerEvent::Scroll { steps: -5 }, &accessibility);
+    menu.handle_pointer(PointerEvent::Scroll { steps: 1 }, &accessibility);
+    assert_eq!(menu.handle_key(winit::event::VirtualKeyCode::Return), Some(SaveMenuAction::Save(1)));
+}
+
+#[test]
+fn clicking_outside_the_slots_closes_the_menu() {
+    let accessibility = Accessibility::new();
+    let mut menu = SaveMenu::new();
+    menu.open(SaveMenuMode::Save, vec![None, None, None]);
+    menu.handle_pointer(PointerEvent::Click { x: 100.0, y: 790.0 }, &accessibility);
+    assert!(!menu.is_open());
+
+    menu.open(SaveMenuMode::Save, vec![None, None, None]);
+    menu.handle_pointer(PointerEvent::Cancel, &accessibility);
+    assert!(!menu.is_open());
+}
+
+#[test]
+fn slots_can_be_exported_and_imported() {
+    let mut menu = SaveMenu::new();
+    menu.open(SaveMenuMode::Load, vec![None, slot(), None]);
+
+    // Nothing to export from an empty slot, but anything can be imported into one.
+    assert_eq!(menu.handle_key(winit::event::VirtualKeyCode::E), None);
+    assert_eq!(menu.handle_key(winit::event::VirtualKeyCode::I), Some(SaveMenuAction::Import(0)));
+    menu.handle_key(winit::event::VirtualKeyCode::Down);
+    assert_eq!(menu.handle_key(winit::event::VirtualKeyCode::E), Some(SaveMenuAction::Export(1)));
+    assert!(menu.is_open());
+}
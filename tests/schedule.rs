@@ -0,0 +1,162 @@
+// The game clock and NPC schedules.
+
+use std::time::Duration;
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    field::{FieldDescriptor, FieldExit},
+    game_clock::{self, GameClock},
+    save::SaveGame,
+    schedule::{self, NpcArrival, NpcDefinition, NpcSchedules, ScheduleWalk, ScheduledNpc},
+    transform::Transform,
+    world::World
+};
+
+const NPCS: &str = "
+# For testing.
+[Shopkeeper]
+model = models/shopkeeper.gltf
+speed = 2
+20:00 = house 0 0 1 sleeping
+06:00 = town 4 0 0 shopkeeping
+
+[Guard]
+model = models/guard.gltf
+00:00 = town 0 0 -5 standing
+";
+
+fn world_at(time: &str) -> World {
+    let mut world = World::new();
+    let mut clock = GameClock::new();
+    clock.set_time(game_clock::parse_time(time).unwrap());
+    world.insert_resource(clock);
+    world.insert_resource(NpcSchedules(NpcDefinition::parse_list(NPCS).unwrap()));
+    world.insert_resource(FieldDescriptor {
+        exits: vec![FieldExit { target: "house".to_string(), position: Vector3::new(-6.0, 0.0, 0.0) }],
+        ..Default::default()
+    });
+    world
+}
+
+fn set_time(world: &mut World, time: &str) {
+    world.resource_mut::<GameClock>().unwrap().set_time(game_clock::parse_time(time).unwrap());
+}
+
+// What spawn_npcs does, without the model.
+fn spawn(world: &mut World, arrival: &NpcArrival) {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(arrival.position));
+    world.insert(entity, ScheduledNpc { name: arrival.npc.clone(), entry: arrival.entry, behaviour: String::new() });
+    if let Some(target) = arrival.walk_to {
+        world.insert(entity, ScheduleWalk { target, speed: 2.0, leaving: false });
+    }
+}
+
+#[test]
+fn schedules_are_read_in_time_order() {
+    let npcs = NpcDefinition::parse_list(NPCS).unwrap();
+    assert_eq!(npcs.len(), 2);
+    let shopkeeper = &npcs[0];
+    assert_eq!((shopkeeper.model.as_str(), shopkeeper.speed), ("models/shopkeeper.gltf", 2.0));
+    assert_eq!(shopkeeper.schedule.entries().iter().map(|entry| entry.field.as_str()).collect::<Vec<_>>(), ["town", "house"]);
+    assert_eq!(shopkeeper.schedule.entries()[0].position, Vector3::new(4.0, 0.0, 0.0));
+    assert_eq!(npcs[1].speed, schedule::DEFAULT_WALK_SPEED);
+}
+
+#[test]
+fn bad_schedules_say_where() {
+    assert!(NpcDefinition::parse_list("06:00 = town 0 0 0").unwrap_err().starts_with("Line 1"));
+    assert!(NpcDefinition::parse_list("[A]\n06:00 = town 0 zero 0").unwrap_err().contains("expected \"field x y z behaviour\""));
+    assert!(NpcDefinition::parse_list("[A]\n\n25:00 = town 0 0 0").unwrap_err().starts_with("Line 3: unknown key"));
+    assert!(NpcDefinition::parse_list("[A]\nspeed = fast").unwrap_err().contains("bad speed"));
+}
+
+#[test]
+fn the_last_entry_carries_on_past_midnight() {
+    let npcs = NpcDefinition::parse_list(NPCS).unwrap();
+    let at = |time: &str| npcs[0].schedule.at(game_clock::parse_time(time).unwrap()).unwrap().behaviour.as_str();
+    assert_eq!(at("03:00"), "sleeping");
+    assert_eq!(at("06:00"), "shopkeeping");
+    assert_eq!(at("19:59"), "shopkeeping");
+    assert_eq!(at("23:00"), "sleeping");
+}
+
+#[test]
+fn the_clock_runs_and_can_be_set() {
+    let mut clock = GameClock::new();
+    assert_eq!((clock.day(), game_clock::format_time(clock.minute())), (1, "08:00".to_string()));
+    assert!(clock.take_changed());
+
+    clock.advance(Duration::from_millis(500));
+    assert!(!clock.take_changed());
+    clock.advance(Duration::from_millis(500));
+    assert!(clock.take_changed());
+    assert_eq!(clock.minute(), 8 * 60 + 1);
+
+    clock.set_paused(true);
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(clock.minute(), 8 * 60 + 1);
+
+    // Going back means tomorrow.
+    clock.set_time(6 * 60);
+    assert_eq!((clock.day(), clock.minute()), (2, 6 * 60));
+    clock.set_paused(false);
+    clock.advance(Duration::from_secs(18 * 60 + 30));
+    assert_eq!((clock.day(), game_clock::format_time(clock.minute())), (3, "00:30".to_string()));
+
+    assert_eq!(game_clock::parse_time("7:30"), None);
+    assert_eq!(game_clock::parse_time("24:00"), None);
+}
+
+#[test]
+fn the_clock_is_saved() {
+    let mut world = World::new();
+    world.insert_resource(GameClock::new());
+    world.resource_mut::<GameClock>().unwrap().set_time(6 * 60 + 15);
+    let save = SaveGame::from_bytes(&SaveGame::capture(&world, "Town", None).to_bytes().unwrap()).unwrap();
+
+    let mut loaded = World::new();
+    save.apply(&mut loaded);
+    let clock = loaded.resource::<GameClock>().unwrap();
+    assert_eq!((clock.day(), clock.minute()), (2, 6 * 60 + 15));
+}
+
+#[test]
+fn npcs_are_already_in_place_when_the_field_loads() {
+    let world = world_at("10:00");
+    let arrivals = schedule::arrivals_on_load(&world, "town");
+    assert_eq!(arrivals.iter().map(|arrival| (arrival.npc.as_str(), arrival.position, arrival.walk_to)).collect::<Vec<_>>(), [
+        ("Shopkeeper", Vector3::new(4.0, 0.0, 0.0), None),
+        ("Guard", Vector3::new(0.0, 0.0, -5.0), None)
+    ]);
+    assert!(schedule::arrivals_on_load(&world_at("21:00"), "town").iter().all(|arrival| arrival.npc != "Shopkeeper"));
+}
+
+#[test]
+fn npcs_walk_out_when_their_time_is_up_and_back_in_later() {
+    let mut world = world_at("19:00");
+    for arrival in schedule::arrivals_on_load(&world, "town") {
+        spawn(&mut world, &arrival);
+    }
+    assert!(schedule::update_schedules(&mut world, "town").is_empty());
+    assert_eq!(world.query::<ScheduleWalk>().count(), 0);
+
+    // Off home, through the door to the house.
+    set_time(&mut world, "20:00");
+    assert!(schedule::update_schedules(&mut world, "town").is_empty());
+    let (shopkeeper, walk) = world.query::<ScheduleWalk>().map(|(entity, walk)| (entity, *walk)).next().unwrap();
+    assert_eq!((walk.target, walk.leaving), (Vector3::new(-6.0, 0.0, 0.0), true));
+    assert_eq!(world.get::<ScheduledNpc>(shopkeeper).unwrap().behaviour, "sleeping");
+
+    // Ten metres at two a second.
+    assert!(schedule::update_npc_walks(&mut world, Duration::from_secs(4)).is_empty());
+    assert_eq!(world.get::<Transform>(shopkeeper).unwrap().position, Vector3::new(-4.0, 0.0, 0.0));
+    assert_eq!(schedule::update_npc_walks(&mut world, Duration::from_secs(2)), [shopkeeper]);
+    world.despawn(shopkeeper);
+
+    // And back in the morning, through the same door.
+    set_time(&mut world, "06:00");
+    let arrivals = schedule::update_schedules(&mut world, "town");
+    assert_eq!(arrivals, [NpcArrival { npc: "Shopkeeper".to_string(), entry: 0, position: Vector3::new(-6.0, 0.0, 0.0), walk_to: Some(Vector3::new(4.0, 0.0, 0.0)) }]);
+}
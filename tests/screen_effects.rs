@@ -0,0 +1,146 @@
+// Animating post processing for cutscenes.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    renderer::PostProcessSettings,
+    screen_effects::{self, Easing, EffectCommand, EffectParam, ScreenEffects},
+    world::World
+};
+
+fn run(effects: &mut ScreenEffects, settings: &mut PostProcessSettings, command: &str) {
+    effects.run(&command.parse().unwrap(), settings);
+}
+
+#[test]
+fn easings_start_and_end_in_the_same_place() {
+    for easing in Easing::ALL {
+        assert_eq!(easing.apply(0.0), 0.0, "{}", easing);
+        assert_eq!(easing.apply(1.0), 1.0, "{}", easing);
+        assert_eq!(easing.apply(2.0), 1.0, "{}", easing);
+        assert_eq!(easing.to_string().parse::<Easing>(), Ok(easing));
+    }
+    assert!(Easing::EaseIn.apply(0.25) < 0.25);
+    assert!(Easing::EaseOut.apply(0.25) > 0.25);
+    assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+}
+
+#[test]
+fn commands_take_their_options_in_any_order() {
+    let command: EffectCommand = "fade 1 #ff8000 0.5 easeout".parse().unwrap();
+    assert_eq!(command, EffectCommand {
+        param: EffectParam::Fade,
+        value: 1.0,
+        duration: Duration::from_millis(500),
+        easing: Easing::EaseOut,
+        color: Some([1.0, 128.0 / 255.0, 0.0])
+    });
+
+    let command: EffectCommand = "Saturation 0".parse().unwrap();
+    assert_eq!((command.param, command.duration, command.easing, command.color), (EffectParam::Saturation, Duration::ZERO, Easing::Linear, None));
+}
+
+#[test]
+fn bad_commands_say_why() {
+    assert!("".parse::<EffectCommand>().unwrap_err().contains("Expected an effect"));
+    assert!("sepia 1".parse::<EffectCommand>().unwrap_err().contains("Unknown effect"));
+    assert!("fade".parse::<EffectCommand>().unwrap_err().contains("Expected a value"));
+    assert!("fade lots".parse::<EffectCommand>().unwrap_err().contains("Bad fade"));
+    assert!("fade 1 -2".parse::<EffectCommand>().unwrap_err().contains("Bad duration"));
+    assert!("fade 1 #fff".parse::<EffectCommand>().unwrap_err().contains("Bad colour"));
+    assert!("fade 1 bouncy".parse::<EffectCommand>().unwrap_err().contains("Unknown easing"));
+    assert!("vignette 1 #ffffff".parse::<EffectCommand>().unwrap_err().contains("doesn't have a colour"));
+}
+
+#[test]
+fn tweens_run_from_the_current_value() {
+    let mut effects = ScreenEffects::new();
+    let mut settings = PostProcessSettings::default();
+    run(&mut effects, &mut settings, "saturation 0 2");
+    assert!(effects.is_animating());
+
+    assert!(effects.update(Duration::from_millis(500), &mut settings, 1.0));
+    assert_eq!(settings.saturation, 0.75);
+    assert!(effects.update(Duration::from_secs(1), &mut settings, 1.0));
+    assert_eq!(settings.saturation, 0.25);
+
+    // Overshooting lands on the end and stops.
+    assert!(!effects.update(Duration::from_secs(5), &mut settings, 1.0));
+    assert_eq!(settings.saturation, 0.0);
+
+    // Once it's done, the settings are left alone.
+    settings.saturation = 0.6;
+    effects.update(Duration::from_secs(1), &mut settings, 1.0);
+    assert_eq!(settings.saturation, 0.6);
+}
+
+#[test]
+fn starting_again_replaces_the_last_tween() {
+    let mut effects = ScreenEffects::new();
+    let mut settings = PostProcessSettings::default();
+    run(&mut effects, &mut settings, "vignette 2 1");
+    effects.update(Duration::from_millis(500), &mut settings, 1.0);
+    run(&mut effects, &mut settings, "vignette 0 1");
+    effects.update(Duration::from_millis(500), &mut settings, 1.0);
+    assert_eq!(settings.vignette, 0.5);
+
+    // Without a duration it's set straight away.
+    run(&mut effects, &mut settings, "vignette 1.5");
+    assert_eq!(settings.vignette, 1.5);
+    assert!(!effects.is_animating());
+}
+
+#[test]
+fn fades_set_their_colour_straight_away() {
+    let mut effects = ScreenEffects::new();
+    let mut settings = PostProcessSettings::default();
+    run(&mut effects, &mut settings, "fade 1 4 #ffffff");
+    assert_eq!(settings.fade, [1.0, 1.0, 1.0, 0.0]);
+
+    // Skipping the cutscene jumps to the end.
+    run(&mut effects, &mut settings, "tint 0.5 4 #ff0000");
+    effects.finish(&mut settings);
+    assert_eq!(settings.fade, [1.0, 1.0, 1.0, 1.0]);
+    assert_eq!((settings.tint, settings.tint_strength), ([1.0, 0.0, 0.0], 0.5));
+    assert!(!effects.is_animating());
+}
+
+#[test]
+fn shaking_moves_the_screen_until_it_stops() {
+    let mut effects = ScreenEffects::new();
+    let mut settings = PostProcessSettings::default();
+    run(&mut effects, &mut settings, "shake 4");
+    assert!(effects.update(Duration::from_millis(16), &mut settings, 1.0));
+    assert_ne!(settings.offset, [0.0, 0.0]);
+    assert!(settings.offset.iter().all(|offset| offset.abs() <= 4.0));
+
+    // Screen effects turned off keep it still.
+    effects.update(Duration::from_millis(16), &mut settings, 0.0);
+    assert_eq!(settings.offset, [0.0, 0.0]);
+
+    // Dying down puts the screen back where it was.
+    run(&mut effects, &mut settings, "shake 0 0.5");
+    effects.update(Duration::from_millis(100), &mut settings, 1.0);
+    assert!(effects.update(Duration::from_millis(100), &mut settings, 1.0));
+    assert!(!effects.update(Duration::from_secs(1), &mut settings, 1.0));
+    assert_eq!(settings.offset, [0.0, 0.0]);
+}
+
+#[test]
+fn the_world_update_respects_accessibility() {
+    let mut world = World::new();
+    let mut settings = PostProcessSettings::default();
+    let mut effects = ScreenEffects::new();
+    run(&mut effects, &mut settings, "shake 4");
+    world.insert_resource(effects);
+    let mut accessibility = Accessibility::new();
+    accessibility.screen_effects = false;
+    world.insert_resource(accessibility);
+
+    assert!(screen_effects::update_screen_effects(&mut world, Duration::from_millis(16), &mut settings));
+    assert_eq!(settings.offset, [0.0, 0.0]);
+
+    // Without any effects there's nothing to do.
+    assert!(!screen_effects::update_screen_effects(&mut World::new(), Duration::from_millis(16), &mut settings));
+}
@@ -0,0 +1,89 @@
+// Spring bones swinging sockets about.
+
+use std::time::Duration;
+
+use cgmath::{Vector3, InnerSpace};
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    attachment::{self, Attachment, ModelSockets},
+    field::FieldDescriptor,
+    model::ModelData,
+    spring_bone::{self, SpringBones},
+    transform::Transform,
+    world::World
+};
+
+const FRAME: Duration = Duration::from_millis(16);
+
+// A tail of two bones, each a unit long, sticking out sideways from the origin.
+fn tail() -> ModelData {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(ModelData::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "models/test_tail.gltf")).unwrap()
+}
+
+fn socket_position(world: &World, entity: ps_rpg_engine::world::Entity, name: &str) -> Vector3<f32> {
+    world.get::<ModelSockets>(entity).unwrap().get(name).unwrap().w.truncate()
+}
+
+#[test]
+fn tails_hang_down_and_blow_in_the_wind() {
+    let model = tail();
+    let bones: Vec<_> = model.spring_bones.iter().map(|bone| (bone.socket.as_str(), bone.parent)).collect();
+    assert_eq!(bones, [("tail_1", None), ("tail_2", Some(0))]);
+
+    let mut world = World::new();
+    let entity = world.spawn();
+    world.insert(entity, Transform::default());
+    world.insert(entity, ModelSockets::from_model(&model));
+    world.insert(entity, SpringBones::from_model(&model));
+    let tip = world.spawn();
+    world.insert(tip, Attachment::new(entity, "tail_2"));
+
+    for _ in 0..600 {
+        spring_bone::update_spring_bones(&mut world, FRAME);
+    }
+    attachment::update_attachments(&mut world);
+
+    // With no stiffness, gravity swings the tail round to hang straight down, without stretching.
+    let (first, second) = (socket_position(&world, entity, "tail_1"), socket_position(&world, entity, "tail_2"));
+    assert!((first - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 0.05, "First bone at {:?}", first);
+    assert!((second - Vector3::new(0.0, -2.0, 0.0)).magnitude() < 0.05, "Second bone at {:?}", second);
+    // Whatever's attached to the end goes with it.
+    assert!((world.get::<Transform>(tip).unwrap().position - second).magnitude() < 1e-4);
+
+    // A strong wind along z blows it over that way.
+    world.insert_resource(FieldDescriptor {
+        wind: [0.0, 0.0, 9.8],
+        ..Default::default()
+    });
+    for _ in 0..600 {
+        spring_bone::update_spring_bones(&mut world, FRAME);
+    }
+    let second = socket_position(&world, entity, "tail_2");
+    assert!(second.z > 1.0 && second.y < -1.0, "Second bone at {:?}", second);
+    assert!((second.magnitude() - 2.0).abs() < 0.05, "Second bone at {:?}", second);
+}
+
+#[test]
+fn stiff_bones_stay_near_rest() {
+    let mut model = tail();
+    for bone in &mut model.spring_bones {
+        bone.stiffness = 50.0;
+        bone.drag = 0.5;
+        bone.gravity = 0.1;
+    }
+
+    let mut world = World::new();
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(0.0, 3.0, 0.0)));
+    world.insert(entity, ModelSockets::from_model(&model));
+    world.insert(entity, SpringBones::from_model(&model));
+    for _ in 0..300 {
+        spring_bone::update_spring_bones(&mut world, FRAME);
+    }
+
+    // Sockets are relative to the model, so moving the entity doesn't move them.
+    let second = socket_position(&world, entity, "tail_2");
+    assert!((second - Vector3::new(2.0, 0.0, 0.0)).magnitude() < 0.2, "Second bone at {:?}", second);
+}
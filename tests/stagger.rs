@@ -0,0 +1,62 @@
+// Breaking enemies by hitting their weaknesses.
+
+use ps_rpg_engine::{
+    combat::{self, Attack, CombatConfig, Defence, HitResult, Outcome, StandardRules},
+    rng::RngStream,
+    stagger::BreakGauge
+};
+
+// No randomness, with stagger on.
+fn rules() -> StandardRules {
+    StandardRules(CombatConfig { variance: 0.0, critical_chance: 0.0, min_hit: 1.0, stagger: true, ..Default::default() })
+}
+
+fn hit(damage: u32) -> HitResult {
+    HitResult { outcome: Outcome::Hit, damage, blocked: false, countered: false }
+}
+
+#[test]
+fn stagger_is_read_and_off_unless_asked_for() {
+    let config = CombatConfig::parse("stagger = on\nbreak_turns = 2\nbreak_bonus = 2").unwrap();
+    assert!(config.stagger);
+    assert_eq!((config.break_turns, config.break_bonus), (2, 2.0));
+    assert!(CombatConfig::parse("stagger = maybe").unwrap_err().starts_with("Line 1"));
+    assert!(CombatConfig::parse("\nbreak_turns = -1").unwrap_err().starts_with("Line 2: bad break_turns"));
+
+    assert!(!CombatConfig::default().stagger);
+    assert_eq!(BreakGauge::new(100, &StandardRules::default()), None);
+}
+
+#[test]
+fn weaknesses_fill_the_gauge_faster() {
+    let rules = rules();
+    let weakness = Attack { weakness: true, ..Attack::new(10, 0) };
+    // Half of 100 HP to break.
+    let mut gauge = BreakGauge::new(100, &rules).unwrap();
+    assert!(!gauge.hit(&rules, &Attack::new(10, 0), &hit(40)));
+    assert_eq!(gauge.fraction(), 0.2);
+    assert!(!gauge.hit(&rules, &Attack::new(10, 0), &HitResult { outcome: Outcome::Miss, ..hit(0) }));
+    assert_eq!(gauge.fraction(), 0.2);
+    assert!(gauge.hit(&rules, &weakness, &hit(40)));
+    assert!(gauge.is_broken());
+    // Already broken, so that's it until they recover.
+    assert!(!gauge.hit(&rules, &weakness, &hit(40)));
+}
+
+#[test]
+fn broken_enemies_lose_turns_and_take_more_damage() {
+    let rules = StandardRules(CombatConfig { break_turns: 2, ..rules().0 });
+    let mut gauge = BreakGauge::new(10, &rules).unwrap();
+    assert!(!gauge.take_turn());
+    gauge.hit(&rules, &Attack { weakness: true, ..Attack::new(10, 0) }, &hit(5));
+
+    let defence = Defence { broken: gauge.is_broken(), ..Default::default() };
+    let result = combat::resolve(&rules, &Attack::new(10, 0), &defence, &mut RngStream::new(1, 2));
+    assert_eq!(result.damage, 30);
+
+    assert!(gauge.take_turn());
+    assert!(gauge.take_turn());
+    assert!(!gauge.is_broken());
+    assert_eq!(gauge.fraction(), 0.0);
+    assert!(!gauge.take_turn());
+}
@@ -0,0 +1,277 @@
+// The status menu, the party and the inventory.
+
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    formation::Row,
+    field_status::{FieldBoost, FieldEffect},
+    inventory::{Inventory, Item, ItemEffect},
+    menu::MenuSound,
+    party::{EquipSlot, Equipment, Party, PartyMember, Skill, Stats, TargetType},
+    pointer::PointerEvent,
+    status_menu::{StatusMenu, StatusMenuAction, StatusScreen},
+    tween::SLIDE_TIME,
+    ui::UiBatch
+};
+
+fn party() -> Party {
+    let stats = Stats { level: 3, hp: 40, max_hp: 100, mp: 5, max_mp: 20, ..Default::default() };
+    let mut aria = PartyMember::new("Aria", stats);
+    aria.equip(EquipSlot::Weapon, Some(Equipment::new("Bronze Sword")));
+    aria.skills.push(Skill { name: "Cleave".to_string(), description: "Hits the front row.".to_string(), mp_cost: 6, costs: Vec::new(), target: TargetType::AllEnemies, field: None });
+    let tobin = PartyMember::new("Tobin", Stats { hp: 0, ..stats });
+    Party::new(vec![aria, tobin])
+}
+
+fn item(id: &str, effect: ItemEffect) -> Item {
+    Item { id: id.to_string(), name: id.to_string(), description: String::new(), effect }
+}
+
+fn inventory() -> Inventory {
+    let mut inventory = Inventory::new();
+    inventory.add(&item("potion", ItemEffect::Restore { hp: 50, mp: 0 }), 2);
+    inventory.add(&item("phoenix_down", ItemEffect::Revive { hp: 10 }), 1);
+    inventory.add(&item("key", ItemEffect::None), 1);
+    inventory
+}
+
+#[test]
+fn screens_open_on_top_of_each_other() {
+    let (party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), None);
+    menu.open();
+    assert_eq!(menu.screen(), Some(StatusScreen::Party));
+
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(0)));
+    // Past the equipment to the skills.
+    for _ in EquipSlot::ALL {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Skills(0)));
+
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(0)));
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    assert!(!menu.is_open());
+}
+
+#[test]
+fn items_are_used_on_who_was_picked() {
+    let (mut party, mut inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    // The items come after everyone in the party.
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Items));
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::ItemTarget(0)));
+
+    let action = menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(action, Some(StatusMenuAction::UseItem { slot: 0, member: 0 }));
+    menu.finish_action(inventory.use_on(0, &mut party.members[0]));
+    assert_eq!(menu.screen(), Some(StatusScreen::Items));
+    assert_eq!(menu.message(), Some("Aria recovered 50 HP"));
+    assert_eq!((party.members[0].stats.hp, inventory.count("potion")), (90, 1));
+}
+
+#[test]
+fn key_items_cant_be_used() {
+    let (party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    for key in [VirtualKeyCode::Down, VirtualKeyCode::Down, VirtualKeyCode::Return, VirtualKeyCode::Down, VirtualKeyCode::Down] {
+        menu.handle_key(key, &party, &inventory);
+    }
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), None);
+    assert_eq!(menu.screen(), Some(StatusScreen::Items));
+    assert_eq!(menu.message(), Some("key can't be used here"));
+}
+
+#[test]
+fn items_that_would_do_nothing_arent_used_up() {
+    let (mut party, mut inventory) = (party(), inventory());
+    party.members[0].stats.hp = 100;
+    assert_eq!(inventory.use_on(0, &mut party.members[0]), Err("It won't do Aria any good".to_string()));
+    assert_eq!(inventory.use_on(0, &mut party.members[1]), Err("Tobin is knocked out".to_string()));
+    assert_eq!(inventory.use_on(1, &mut party.members[0]), Err("Aria isn't knocked out".to_string()));
+    assert_eq!(inventory.count("potion"), 2);
+
+    assert_eq!(inventory.use_on(1, &mut party.members[1]), Ok("Tobin came round".to_string()));
+    assert_eq!((party.members[1].stats.hp, inventory.count("phoenix_down")), (10, 0));
+    assert!(!inventory.remove("phoenix_down", 1));
+}
+
+#[test]
+fn rows_are_changed_from_the_character_screen() {
+    let (mut party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    // Past the equipment and the skills.
+    for _ in 0..=EquipSlot::ALL.len() {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), Some(StatusMenuAction::ChangeRow { member: 0 }));
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(0)));
+
+    party.members[0].row = party.members[0].row.other();
+    assert_eq!(party.members[0].row, Row::Back);
+    assert!(!party.members[0].has_reach());
+    party.members[0].equip(EquipSlot::Weapon, Some(Equipment { reach: true, ..Equipment::new("Longbow") }));
+    assert!(party.members[0].has_reach());
+}
+
+#[test]
+fn clicking_a_row_picks_it() {
+    let (party, inventory) = (party(), inventory());
+    let accessibility = Accessibility::new();
+    let mut menu = StatusMenu::new();
+    menu.open();
+
+    let mut batch = UiBatch::new();
+    menu.build(&mut batch, &accessibility, &party, &inventory);
+    assert!(!batch.is_empty());
+
+    // Below the title, in the first row.
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: 60.0 }, &accessibility, &party, &inventory).action, None);
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(0)));
+    // Clicking outside the list goes back.
+    menu.handle_pointer(PointerEvent::Click { x: 100.0, y: 790.0 }, &accessibility, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Party));
+}
+
+#[test]
+fn auto_battle_is_toggled_from_the_character_screen() {
+    let (party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    // Past the equipment, the skills and the row.
+    for _ in 0..EquipSlot::ALL.len() + 2 {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), Some(StatusMenuAction::ToggleAuto { member: 0 }));
+}
+
+#[test]
+fn field_items_and_skills_are_for_the_whole_party() {
+    let (mut party, mut inventory) = (party(), inventory());
+    let sneak = FieldBoost { effect: FieldEffect::Sneak, steps: 30 };
+    inventory.add(&item("repel", ItemEffect::Field(FieldBoost { effect: FieldEffect::Repel, steps: 100 })), 1);
+    party.members[0].skills.push(Skill { name: "Sneak".to_string(), description: String::new(), mp_cost: 2, costs: Vec::new(), target: TargetType::AllAllies, field: Some(ItemEffect::Field(sneak)) });
+    let mut menu = StatusMenu::new();
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    for _ in 0..3 {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    // No one to pick, it's used straight away.
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), Some(StatusMenuAction::UseFieldItem { slot: 3 }));
+    assert_eq!(menu.screen(), Some(StatusScreen::Items));
+
+    menu.open();
+    // It opens where it was left, on the items.
+    menu.handle_key(VirtualKeyCode::Up, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Up, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    for _ in EquipSlot::ALL {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), None);
+    assert_eq!(menu.message(), Some("Cleave can't be used here"));
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), Some(StatusMenuAction::UseSkill { member: 0, skill: 1, target: None }));
+}
+
+#[test]
+fn healing_skills_ask_who_for() {
+    let (mut party, inventory) = (party(), inventory());
+    party.members[0].skills.push(Skill { name: "Cure".to_string(), description: String::new(), mp_cost: 4, costs: Vec::new(), target: TargetType::Ally, field: Some(ItemEffect::Restore { hp: 40, mp: 0 }) });
+    let mut menu = StatusMenu::new();
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    for _ in EquipSlot::ALL {
+        menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    }
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), None);
+    assert_eq!(menu.screen(), Some(StatusScreen::SkillTarget(0, 1)));
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return, &party, &inventory), Some(StatusMenuAction::UseSkill { member: 0, skill: 1, target: Some(1) }));
+    menu.finish_action(Ok("Tobin recovered 40 HP".to_string()));
+    assert_eq!(menu.screen(), Some(StatusScreen::Skills(0)));
+}
+
+#[test]
+fn number_keys_put_things_on_the_hotbar() {
+    let (party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Key3, &party, &inventory), Some(StatusMenuAction::BindItem { quick_slot: 2, slot: 0 }));
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Key1, &party, &inventory), None);
+    assert_eq!(menu.message(), Some("key can't be used here"));
+    assert_eq!(menu.take_sound(), Some(MenuSound::Error));
+}
+
+#[test]
+fn closing_slides_out_before_it_goes() {
+    let (party, inventory) = (party(), inventory());
+    let accessibility = Accessibility::new();
+    let mut menu = StatusMenu::new();
+    menu.open();
+    assert!(menu.update(Duration::from_millis(50)));
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    assert!(!menu.is_open());
+
+    // Still drawn as it slides out, then gone.
+    let mut batch = UiBatch::new();
+    menu.build(&mut batch, &accessibility, &party, &inventory);
+    assert!(!batch.is_empty());
+    assert!(menu.update(SLIDE_TIME));
+    assert!(!menu.update(SLIDE_TIME));
+    batch.clear();
+    menu.build(&mut batch, &accessibility, &party, &inventory);
+    assert!(batch.is_empty());
+}
+
+#[test]
+fn the_cursor_is_where_it_was_left() {
+    let (party, inventory) = (party(), inventory());
+    let mut menu = StatusMenu::new();
+    menu.open();
+    // Nowhere further to go.
+    menu.handle_key(VirtualKeyCode::Up, &party, &inventory);
+    assert_eq!(menu.take_sound(), None);
+    menu.handle_key(VirtualKeyCode::Down, &party, &inventory);
+    assert_eq!(menu.take_sound(), Some(MenuSound::Move));
+
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.take_sound(), Some(MenuSound::Confirm));
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(1)));
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    assert_eq!(menu.take_sound(), Some(MenuSound::Cancel));
+    menu.handle_key(VirtualKeyCode::Escape, &party, &inventory);
+    assert!(!menu.is_open());
+
+    menu.open();
+    menu.handle_key(VirtualKeyCode::Return, &party, &inventory);
+    assert_eq!(menu.screen(), Some(StatusScreen::Character(1)));
+}
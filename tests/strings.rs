@@ -0,0 +1,80 @@
+// Filling in placeholders, plurals and genders in translated strings.
+
+use ps_rpg_engine::{
+    flags::GameFlags,
+    party::{Gender, Party, PartyMember, Stats},
+    strings::{self, PluralCategory, StringContext, StringTable},
+    world::World
+};
+
+fn party() -> Party {
+    let mut aria = PartyMember::new("Aria", Stats::default());
+    aria.gender = Gender::Female;
+    let mut tobin = PartyMember::new("Tobin", Stats::default());
+    tobin.gender = Gender::Male;
+    Party::new(vec![aria, tobin])
+}
+
+#[test]
+fn placeholders_come_from_the_game_and_arguments() {
+    let (party, mut flags) = (party(), GameFlags::new());
+    flags.set("quest.lost_cat", 2);
+    let context = StringContext { flags: Some(&flags), party: Some(&party) };
+    let format = |template: &str| strings::format(template, "en", &context, &[("item", "Potion".into())]);
+
+    assert_eq!(format("{player} and {member.2} found a {item}."), "Aria and Tobin found a Potion.");
+    assert_eq!(format("Stage {flag.quest.lost_cat}"), "Stage 2");
+    // Ones that can't be filled in are left alone.
+    assert_eq!(format("{member.3} has {gold} gold {"), "{member.3} has {gold} gold {");
+    assert_eq!(strings::format("{player}", "en", &StringContext::default(), &[]), "{player}");
+}
+
+#[test]
+fn plurals_follow_the_language() {
+    let potions = "{count, plural, =0 {no potions} one {# potion} other {# potions}}";
+    let format = |language: &str, count: u32| strings::format(potions, language, &StringContext::default(), &[("count", count.into())]);
+    assert_eq!(format("en", 0), "no potions");
+    assert_eq!(format("en", 1), "1 potion");
+    assert_eq!(format("en", 21), "21 potions");
+    assert_eq!(format("ja", 1), "1 potions");
+
+    let russian: Vec<PluralCategory> = [1, 2, 5, 11, 21, 22, 112].iter().map(|n| strings::plural_category("ru-RU", *n)).collect();
+    assert_eq!(russian, [
+        PluralCategory::One, PluralCategory::Few, PluralCategory::Many, PluralCategory::Many,
+        PluralCategory::One, PluralCategory::Few, PluralCategory::Many
+    ]);
+    let arabic: Vec<PluralCategory> = [0, 1, 2, 3, 11, 100].iter().map(|n| strings::plural_category("ar", *n)).collect();
+    assert_eq!(arabic, [
+        PluralCategory::Zero, PluralCategory::One, PluralCategory::Two, PluralCategory::Few, PluralCategory::Many, PluralCategory::Other
+    ]);
+    assert_eq!(strings::plural_category("fr", 0), PluralCategory::One);
+}
+
+#[test]
+fn select_goes_by_gender() {
+    let mut party = party();
+    let context = StringContext { flags: None, party: Some(&party) };
+    let left = "{player, select, female {She} male {He} other {They}} left {count, plural, one {a note} other {# notes}}.";
+    assert_eq!(strings::format(left, "en", &context, &[("count", 3.into())]), "She left 3 notes.");
+    assert_eq!(strings::format("{member.2, select, male {Il} other {Elle}} est parti.", "fr", &context, &[]), "Il est parti.");
+
+    party.members[0].gender = Gender::Neutral;
+    let context = StringContext { flags: None, party: Some(&party) };
+    assert_eq!(strings::format(left, "en", &context, &[("count", 1.into())]), "They left a note.");
+}
+
+#[test]
+fn untranslated_strings_use_the_fallback() {
+    let table = StringTable::parse("fr", "\u{feff}# For testing.\npickup.found = {item} trouvé{count, plural, one {} other { x#}}\n").unwrap();
+    assert_eq!(table.get("pickup.found"), Some("{item} trouvé{count, plural, one {} other { x#}}"));
+    assert!(StringTable::parse("fr", "pickup.found").unwrap_err().starts_with("Line 1:"));
+    assert!(StringTable::parse("fr", "a = 1\na = 2").unwrap_err().starts_with("Line 2:"));
+
+    let mut world = World::new();
+    let args = [("item", "Potion".into()), ("count", 0.into())];
+    assert_eq!(strings::localize(&world, "pickup.found", "Found {item}{count, plural, one {} other { x#}}", &args), "Found Potion x0");
+    world.insert_resource(table);
+    // French counts 0 as one.
+    assert_eq!(strings::localize(&world, "pickup.found", "Found {item}", &args), "Potion trouvé");
+    assert_eq!(strings::localize(&world, "pickup.take", "Take {item}", &args), "Take Potion");
+}
@@ -0,0 +1,110 @@
+// Reading subtitle tracks, picking their language and laying them out.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    config::Config,
+    subtitles::{self, Subtitles},
+    ui::UiBatch
+};
+
+const TRACK: &str = "\u{feff}1\r
+00:00:01,500 --> 00:00:04,000\r
+Where am I?\r
+\r
+2\r
+00:00:04,500 --> 00:00:07,250\r
+You're safe now.\r
+Rest a while.\r
+";
+
+fn seconds(seconds: f32) -> Duration {
+    Duration::from_secs_f32(seconds)
+}
+
+#[test]
+fn segments_show_between_their_times() {
+    let subtitles = Subtitles::parse("en", TRACK).unwrap();
+    assert_eq!(subtitles.segments().len(), 2);
+    assert_eq!(subtitles.segments()[1].end, Duration::from_millis(7250));
+
+    assert_eq!(subtitles.at(seconds(1.0)), None);
+    assert_eq!(subtitles.at(seconds(1.5)), Some("Where am I?"));
+    assert_eq!(subtitles.at(seconds(4.0)), None);
+    assert_eq!(subtitles.at(seconds(5.0)), Some("You're safe now.\nRest a while."));
+    assert_eq!(subtitles.at(seconds(60.0)), None);
+}
+
+#[test]
+fn counters_are_optional_and_overlaps_show_the_latest() {
+    let subtitles = Subtitles::parse("en", "00:00:00.000 --> 00:00:10.000\nNarrator\n\n00:00:02.000 --> 00:00:03.000\nShout!").unwrap();
+    assert_eq!(subtitles.at(seconds(1.0)), Some("Narrator"));
+    assert_eq!(subtitles.at(seconds(2.5)), Some("Shout!"));
+    assert_eq!(subtitles.at(seconds(3.5)), Some("Narrator"));
+}
+
+#[test]
+fn bad_tracks_say_where() {
+    assert!(Subtitles::parse("en", "1\n00:00:01,000 -> 00:00:02,000\nHi").unwrap_err().starts_with("Line 2"));
+    assert!(Subtitles::parse("en", "1\n00:00:01 --> 00:00:02,000\nHi").unwrap_err().contains("bad start time"));
+    assert!(Subtitles::parse("en", "\n\n1\n00:00:01,000 --> 00:01:61,000\nHi").unwrap_err().contains("bad end time"));
+    assert!(Subtitles::parse("en", "00:00:05,000 --> 00:00:02,000\nHi").unwrap_err().contains("ends before it starts"));
+    assert!(Subtitles::parse("en", "1").unwrap_err().contains("expected the times"));
+}
+
+#[test]
+fn the_closest_language_is_picked() {
+    let available: Vec<String> = ["en-GB", "ja", "pt-BR"].iter().map(|language| language.to_string()).collect();
+    assert_eq!(subtitles::pick_language(&available, "ja"), Some("ja"));
+    assert_eq!(subtitles::pick_language(&available, "pt_br"), Some("pt-BR"));
+    assert_eq!(subtitles::pick_language(&available, "en-US"), Some("en-GB"));
+    assert_eq!(subtitles::pick_language(&available, "de"), Some("en-GB"));
+    assert_eq!(subtitles::pick_language(&[], "en"), None);
+}
+
+#[test]
+fn long_lines_wrap_between_words() {
+    assert_eq!(subtitles::wrap("You're safe now, rest a while.", 12), "You're safe\nnow, rest a\nwhile.");
+    assert_eq!(subtitles::wrap("Short\nlines stay", 20), "Short\nlines stay");
+    assert_eq!(subtitles::wrap("Aaaaaaaargh!", 5), "Aaaaa\naaarg\nh!");
+}
+
+#[test]
+fn japanese_wraps_between_characters() {
+    // Each character's two cells wide, so four to a line.
+    assert_eq!(subtitles::wrap("ここはどこですか", 8), "ここはど\nこですか");
+    // Closing punctuation and small kana stay with what's before them...
+    assert_eq!(subtitles::wrap("まってください。", 8), "まってく\nださい。");
+    assert_eq!(subtitles::wrap("あいうえっと", 8), "あいう\nえっと");
+    // ...and opening brackets with what's after them.
+    assert_eq!(subtitles::wrap("あいう「えお」", 8), "あいう\n「えお」");
+    // Words in other scripts still only break at spaces.
+    assert_eq!(subtitles::wrap("これはHPです", 7), "これは\nHPです");
+    assert_eq!(subtitles::wrap("안녕하세요 친구", 10), "안녕하세요\n친구");
+}
+
+#[test]
+fn subtitles_are_drawn_unless_empty() {
+    let accessibility = Accessibility::new();
+    let mut batch = UiBatch::new();
+    subtitles::build_subtitle(&mut batch, "", &accessibility);
+    assert!(batch.is_empty());
+    subtitles::build_subtitle(&mut batch, "Where am I?", &accessibility);
+    assert!(!batch.is_empty());
+}
+
+#[test]
+fn settings_are_kept_in_the_config() {
+    let path = std::env::temp_dir().join(format!("ps_rpg_engine_subtitles_{}.cfg", std::process::id()));
+    let mut config = Config::default();
+    assert!(config.accessibility.subtitles);
+    config.accessibility.subtitles = false;
+    config.language = "pt-BR".to_string();
+    config.save_to(&path).unwrap();
+
+    let loaded = Config::load_from(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!loaded.accessibility.subtitles);
+    assert_eq!(loaded.language, "pt-BR");
+}
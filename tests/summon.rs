@@ -0,0 +1,113 @@
+// Summons and pets that skills bring into battles for a while.
+
+use cgmath::Point3;
+
+use ps_rpg_engine::{
+    auto_battle::GambitAction,
+    battle_ui::{Combatant, Side, TurnOrder},
+    party::Stats,
+    summon::{self, BattleSummons, SummonDefinition, Summons}
+};
+
+const SUMMONS: &str = "
+[sprite]
+name = Sprite
+skill = Call Sprite
+hp = 40
+speed = 20
+turns = 2
+gambit = always: attack strongest
+
+# Stays until it's beaten
+[wolf]
+name = Wolf
+skill = Howl
+hp = 60
+with_summoner = no
+";
+
+fn combatant(name: &str, side: Side, z: f32) -> Combatant {
+    Combatant { name: name.to_string(), side, speed: 10, position: Point3::new(0.0, 0.0, z), knocked_out: false, summoned: false }
+}
+
+fn battle() -> (Vec<Combatant>, Vec<Stats>) {
+    let stats = |hp| Stats { hp, max_hp: 100, speed: 10, ..Stats::default() };
+    (
+        vec![combatant("Aria", Side::Party, 3.0), combatant("Tobin", Side::Party, 3.0), combatant("Slime", Side::Enemies, -3.0), combatant("Bat", Side::Enemies, -3.0)],
+        vec![stats(100), stats(100), stats(20), stats(50)]
+    )
+}
+
+#[test]
+fn summons_are_read_and_found_by_their_skill() {
+    let summons = Summons(SummonDefinition::parse_list(SUMMONS).unwrap());
+    let sprite = summons.for_skill("call sprite").unwrap();
+    assert_eq!(sprite.id, "sprite");
+    assert_eq!((sprite.stats.hp, sprite.stats.max_hp, sprite.stats.speed), (40, 40, 20));
+    assert_eq!(sprite.turns, Some(2));
+    let wolf = summons.get("wolf").unwrap();
+    assert_eq!((wolf.turns, wolf.with_summoner), (None, false));
+    assert_eq!(wolf.gambits.len(), 1);
+
+    assert!(SummonDefinition::parse_list("[imp]\ngambit = always: skill Fire").unwrap_err().contains("can only attack"));
+    assert!(SummonDefinition::parse_list("[imp]\n[imp]").unwrap_err().starts_with("Line 2"));
+}
+
+#[test]
+fn summons_take_their_own_turns_until_they_leave() {
+    let sprite = SummonDefinition::parse_list(SUMMONS).unwrap().remove(0);
+    let (mut combatants, mut stats) = battle();
+    let mut turns = TurnOrder::new(&combatants);
+    let mut summons = BattleSummons::new();
+
+    let index = summons.summon(&sprite, 1, &mut combatants, &mut turns).unwrap();
+    stats.push(sprite.stats);
+    assert_eq!(index, 4);
+    assert_eq!(combatants[index].side, Side::Party);
+    assert!(combatants[index].summoned);
+    // In front of whoever called them.
+    assert!(combatants[index].position.z < combatants[1].position.z);
+    // Twice as fast as everyone else, so it gets in two turns to their one.
+    assert_eq!(turns.preview(&combatants, 6).iter().filter(|turn| **turn == index).count(), 2);
+
+    let command = summons.decide(index, &combatants, &stats).unwrap();
+    assert!(matches!(command.action, GambitAction::Attack(_)));
+    assert_eq!(command.targets, vec![3]);
+
+    assert_eq!(summons.end_turn(index, &mut combatants), None);
+    assert_eq!(summons.end_turn(index, &mut combatants), Some("Sprite".to_string()));
+    assert!(combatants[index].knocked_out);
+    assert!(summons.active().is_empty());
+    // Gone, so nobody tries to bring them back.
+    assert!(!turns.preview(&combatants, 6).contains(&index));
+}
+
+#[test]
+fn summons_leave_with_their_summoner_and_at_the_end() {
+    let definitions = SummonDefinition::parse_list(SUMMONS).unwrap();
+    let (mut combatants, _) = battle();
+    let mut turns = TurnOrder::new(&combatants);
+    let mut summons = BattleSummons::new();
+
+    let sprite = summons.summon(&definitions[0], 1, &mut combatants, &mut turns).unwrap();
+    let wolf = summons.summon(&definitions[1], 0, &mut combatants, &mut turns).unwrap();
+    assert!(summons.update(&mut combatants).is_empty());
+    assert_eq!(summon::party_members(&combatants), vec![0, 1]);
+
+    combatants[1].knocked_out = true;
+    combatants[0].knocked_out = true;
+    assert_eq!(summons.update(&mut combatants), vec!["Sprite".to_string()]);
+    assert!(combatants[sprite].knocked_out);
+    // The wolf stays without Aria.
+    assert!(!combatants[wolf].knocked_out);
+
+    // Calling another sends the last one away.
+    let second = summons.summon(&definitions[1], 0, &mut combatants, &mut turns).unwrap();
+    assert!(combatants[wolf].knocked_out);
+    assert_eq!(summons.active().len(), 1);
+    assert_eq!(summons.get(second).unwrap().summoner, 0);
+
+    summons.end_battle(&mut combatants);
+    assert!(summons.active().is_empty());
+    assert_eq!(combatants.len(), 4);
+}
@@ -0,0 +1,121 @@
+// The suspend save written on quitting, and how it goes once it's loaded.
+
+use std::path::PathBuf;
+
+use ps_rpg_engine::{
+    battle_scene::BattleScene,
+    field_status::{FieldBoost, FieldEffect},
+    flags::GameFlags,
+    formation::{BattleEnemy, BattleSetup, Row},
+    inventory::ItemEffect,
+    party::{EquipSlot, Equipment, Party, PartyMember, Skill, Stats, StatusEffect, TargetType},
+    pools::SkillCost,
+    save::{self, SaveGame, SuspendState},
+    world::World
+};
+
+fn save_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_suspend_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn goblins() -> BattleSetup {
+    let goblin = |row, x: f32| BattleEnemy { enemy: "goblin".to_string(), row, position: [x, 2.0], reach: false, boss: false };
+    BattleSetup {
+        formation: "goblins".to_string(),
+        enemies: vec![goblin(Row::Front, -1.5), BattleEnemy { reach: true, boss: true, ..goblin(Row::Back, 0.25) }],
+        intro: Some("goblins_laugh".to_string()),
+        can_escape: false,
+        back_attack: true,
+        scene: BattleScene { backdrop: Some("fields/cave.png".to_string()), music: None }
+    }
+}
+
+fn party() -> Party {
+    let mut aria = PartyMember::new("Aria", Stats { level: 5, experience: 1240, hp: 96, max_hp: 150, mp: 12, max_mp: 40, strength: 14, magic: 6, defence: 11, speed: 9 });
+    aria.equip(EquipSlot::Weapon, Some(Equipment { name: "Spear, Old".to_string(), reach: true }));
+    aria.row = Row::Back;
+    aria.auto_battle = true;
+    aria.gambits = vec!["ally hp < 30: skill Cure".parse().unwrap(), "always: attack strongest".parse().unwrap()];
+    aria.status_effects.push(StatusEffect::Poison);
+    aria.pools.insert("tp".to_string(), 35);
+    aria.skills.push(Skill {
+        name: "Sneak".to_string(),
+        description: "Creeps about. Quietly = safely.".to_string(),
+        mp_cost: 3,
+        costs: vec![SkillCost::Pool { pool: "tp".to_string(), amount: 10 }, SkillCost::Item { item: "smoke_bomb".to_string(), count: 1 }],
+        target: TargetType::AllAllies,
+        field: Some(ItemEffect::Field(FieldBoost { effect: FieldEffect::Sneak, steps: 60 }))
+    });
+    let mut tobin = PartyMember::new("Tobin", Stats { hp: 0, max_hp: 90, ..Stats::default() });
+    tobin.gambits.clear();
+    Party::new(vec![aria, tobin])
+}
+
+fn suspended(battle: Option<BattleSetup>) -> SaveGame {
+    let mut world = World::new();
+    let mut flags = GameFlags::new();
+    flags.set("gate_open", 1);
+    world.insert_resource(flags);
+    world.insert_resource(party());
+    let mut save_game = SaveGame::capture(&world, "Test Field", None);
+    save_game.suspend = Some(SuspendState {
+        field: "test_field".to_string(),
+        position: [1.5, 0.0, -2.25],
+        facing: 0.5,
+        battle
+    });
+    save_game
+}
+
+#[test]
+fn where_the_player_was_is_kept() {
+    let save_game = SaveGame::from_bytes(&suspended(Some(goblins())).to_bytes().unwrap()).unwrap();
+    let suspend = save_game.suspend.clone().unwrap();
+    assert_eq!(suspend.field, "test_field");
+    assert_eq!(suspend.position, [1.5, 0.0, -2.25]);
+    assert_eq!(suspend.facing, 0.5);
+    // The same enemies, where they were, rather than rolling it again.
+    assert_eq!(suspend.battle, Some(goblins()));
+    assert_eq!(save_game.flags, vec![("gate_open".to_string(), 1)]);
+
+    // And the party as they were, hurt and poisoned.
+    let mut world = World::new();
+    world.insert_resource(Party::default());
+    save_game.apply(&mut world);
+    assert_eq!(world.resource::<Party>(), Some(&party()));
+
+    // Ordinary saves don't have one.
+    let save_game = SaveGame::from_bytes(&SaveGame::capture(&World::new(), "Test Field", None).to_bytes().unwrap()).unwrap();
+    assert!(save_game.suspend.is_none());
+}
+
+#[test]
+fn suspend_saves_only_load_once() {
+    let dir = save_dir("once");
+    assert!(save::take_suspend(&dir).unwrap().is_none());
+    assert!(!save::any_saves(&dir));
+
+    suspended(None).write_to(&save::suspend_path(&dir)).unwrap();
+    // Enough for the title screen to offer continuing.
+    assert!(save::any_saves(&dir));
+    let first = save::take_suspend(&dir).unwrap();
+    let second = save::take_suspend(&dir).unwrap();
+    let left = save::any_saves(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(first.and_then(|save_game| save_game.suspend).is_some_and(|suspend| suspend.battle.is_none()));
+    assert!(second.is_none());
+    assert!(!left);
+}
+
+#[test]
+fn broken_suspend_saves_are_thrown_away() {
+    let dir = save_dir("broken");
+    std::fs::write(save::suspend_path(&dir), b"not a save").unwrap();
+    let taken = save::take_suspend(&dir);
+    let left = save::suspend_path(&dir).is_file();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(taken.is_err());
+    assert!(!left);
+}
@@ -0,0 +1,122 @@
+// Batching and queueing telemetry for a game's backend.
+
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+
+use ps_rpg_engine::{
+    telemetry::{self, BattleOutcome, Telemetry, TelemetryBackend, TelemetryEvent, TelemetryRecord, BATCH_SIZE, FLUSH_INTERVAL},
+    world::World
+};
+
+// Keeps every batch it's sent, or refuses them while it's offline.
+#[derive(Clone, Default)]
+struct Recording {
+    batches: Rc<RefCell<Vec<Vec<TelemetryRecord>>>>,
+    offline: Rc<RefCell<bool>>
+}
+
+impl TelemetryBackend for Recording {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn send(&mut self, batch: &[TelemetryRecord]) -> Result<(), String> {
+        if *self.offline.borrow() {
+            return Err("Offline".to_string());
+        }
+        self.batches.borrow_mut().push(batch.to_vec());
+        Ok(())
+    }
+}
+
+fn queue_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_telemetry_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("queue.txt")
+}
+
+fn entered(field: &str) -> TelemetryEvent {
+    TelemetryEvent::FieldEntered { field: field.to_string() }
+}
+
+#[test]
+fn events_go_in_batches() {
+    let backend = Recording::default();
+    let mut telemetry = Telemetry::new(Box::new(backend.clone()), None, true);
+    let mut world = World::new();
+
+    telemetry::record(&mut world, entered("town"));
+    telemetry.update(&mut world, Duration::from_secs(1));
+    assert!(backend.batches.borrow().is_empty());
+    assert_eq!(telemetry.pending().len(), 1);
+
+    // Sent once it's waited long enough...
+    telemetry.update(&mut world, FLUSH_INTERVAL);
+    assert_eq!(backend.batches.borrow().len(), 1);
+    assert!(telemetry.pending().is_empty());
+
+    // ...or there are enough of them.
+    for _ in 0..BATCH_SIZE {
+        telemetry::record(&mut world, TelemetryEvent::BattleResult { formation: "goblins".to_string(), outcome: BattleOutcome::Won });
+    }
+    telemetry.update(&mut world, Duration::ZERO);
+    assert_eq!(backend.batches.borrow().len(), 2);
+    assert_eq!(backend.batches.borrow()[1].len(), BATCH_SIZE);
+}
+
+#[test]
+fn nothing_is_kept_unless_its_turned_on() {
+    let path = queue_path("off");
+    let backend = Recording::default();
+    let mut telemetry = Telemetry::new(Box::new(backend.clone()), Some(path.clone()), false);
+    let mut world = World::new();
+    telemetry::record(&mut world, entered("town"));
+    telemetry.update(&mut world, FLUSH_INTERVAL);
+    telemetry.flush();
+    assert!(telemetry.pending().is_empty() && backend.batches.borrow().is_empty());
+    assert!(!path.is_file());
+
+    // Turning it off again throws away what hadn't been sent.
+    telemetry.set_enabled(true);
+    telemetry.push(TelemetryRecord::now(entered("castle")));
+    assert!(path.is_file());
+    telemetry.set_enabled(false);
+    let left = path.is_file();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert!(telemetry.pending().is_empty());
+    assert!(!left);
+}
+
+#[test]
+fn unsent_events_wait_on_disk() {
+    let path = queue_path("disk");
+    let backend = Recording::default();
+    *backend.offline.borrow_mut() = true;
+    let mut telemetry = Telemetry::new(Box::new(backend.clone()), Some(path.clone()), true);
+    telemetry.push(TelemetryRecord::now(entered("town")));
+    telemetry.push(TelemetryRecord::now(TelemetryEvent::Crash { message: "Index out of bounds\nat src/party.rs:120:9".to_string() }));
+    telemetry.flush();
+    assert_eq!(telemetry.pending().len(), 2);
+    drop(telemetry);
+
+    // Next time, they're picked up and sent.
+    *backend.offline.borrow_mut() = false;
+    let mut telemetry = Telemetry::new(Box::new(backend.clone()), Some(path.clone()), true);
+    assert_eq!(telemetry.pending().len(), 2);
+    telemetry.flush();
+    let left = path.is_file();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    let batches = backend.batches.borrow();
+    assert_eq!(batches[0][0].event, entered("town"));
+    assert_eq!(batches[0][1].event, TelemetryEvent::Crash { message: "Index out of bounds at src/party.rs:120:9".to_string() });
+    assert!(!left);
+}
+
+#[test]
+fn queue_lines_read_back() {
+    let record = TelemetryRecord { at: 1792252860, event: TelemetryEvent::BattleResult { formation: "goblins".to_string(), outcome: BattleOutcome::Fled } };
+    assert_eq!(record.to_line(), "1792252860 battle_result goblins fled");
+    assert_eq!(TelemetryRecord::parse_line(&record.to_line()), Ok(record));
+    assert!(TelemetryRecord::parse_line("1792252860 battle_result goblins").is_err());
+    assert!(TelemetryRecord::parse_line("soon field_entered town").is_err());
+    assert!(TelemetryRecord::parse_line("1792252860 level_up Aria").is_err());
+}
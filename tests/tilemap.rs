@@ -0,0 +1,165 @@
+// Reading Tiled maps, and their collision and objects.
+
+use ps_rpg_engine::{
+    assets::AssetServer,
+    renderer::{SCREEN_WIDTH, SCREEN_HEIGHT},
+    tilemap::Tilemap
+};
+
+// A 3x2 map with a wall along the top, a flipped tile and a door, saved both ways Tiled can.
+const TMJ: &str = r#"{
+    "orientation": "orthogonal", "infinite": false,
+    "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+    "tilesets": [{
+        "firstgid": 1, "image": "tiles.png", "imagewidth": 32, "imageheight": 32,
+        "tilewidth": 16, "tileheight": 16, "columns": 2, "tilecount": 4,
+        "tiles": [{ "id": 1, "properties": [{ "name": "solid", "type": "bool", "value": true }] }]
+    }],
+    "layers": [
+        { "type": "tilelayer", "name": "ground", "width": 3, "height": 2, "data": [2, 2, 2, 1, 2147483649, 0] },
+        { "type": "group", "name": "hidden", "visible": false, "layers": [
+            { "type": "tilelayer", "name": "roof", "width": 3, "height": 2, "data": [0, 0, 0, 4, 4, 4],
+              "properties": [{ "name": "above", "type": "bool", "value": true }] }
+        ]},
+        { "type": "objectgroup", "name": "objects", "objects": [
+            { "name": "door", "type": "exit", "x": 32, "y": 16, "width": 16, "height": 16,
+              "properties": [{ "name": "target", "type": "string", "value": "town" }] },
+            { "name": "sign", "class": "trigger", "x": 0, "y": 16, "width": 16, "height": 16 }
+        ]}
+    ]
+}"#;
+
+const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="tiles.png" width="32" height="32"/>
+  <tile id="1">
+   <properties>
+    <property name="solid" type="bool" value="true"/>
+   </properties>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+2,2,2,
+1,2147483649,0
+</data>
+ </layer>
+ <group id="2" name="hidden" visible="0">
+  <layer id="3" name="roof" width="3" height="2">
+   <properties>
+    <property name="above" type="bool" value="true"/>
+   </properties>
+   <data encoding="base64">AAAAAAAAAAAAAAAABAAAAAQAAAAEAAAA</data>
+  </layer>
+ </group>
+ <objectgroup id="4" name="objects">
+  <object id="1" name="door" type="exit" x="32" y="16" width="16" height="16">
+   <properties>
+    <property name="target" value="town"/>
+   </properties>
+  </object>
+  <object id="2" name="sign" class="trigger" x="0" y="16" width="16" height="16"/>
+ </objectgroup>
+</map>
+"#;
+
+#[test]
+fn both_formats_read_the_same() {
+    for map in [Tilemap::parse_tmj(TMJ).unwrap(), Tilemap::parse_tmx(TMX).unwrap()] {
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (3, 2, 16, 16));
+        assert_eq!(map.pixel_width(), 48.0);
+        assert_eq!(map.tilesets.len(), 1);
+        assert_eq!(map.tilesets[0].image, "tiles.png");
+
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(map.layers[0].tiles, vec![2, 2, 2, 1, 0x8000_0001, 0]);
+        assert!(map.layers[0].visible && !map.layers[0].above);
+        assert_eq!(map.layers[1].name, "roof");
+        assert_eq!(map.layers[1].tiles, vec![0, 0, 0, 4, 4, 4]);
+        assert!(!map.layers[1].visible && map.layers[1].above);
+
+        assert_eq!(map.objects.len(), 2);
+        assert_eq!(map.objects[1].kind, "trigger");
+    }
+}
+
+#[test]
+fn solid_tiles_and_the_edges_block() {
+    let map = Tilemap::parse_tmj(TMJ).unwrap();
+    assert!(map.is_solid(0, 0));
+    assert!(map.is_solid(2, 0));
+    assert!(!map.is_solid(0, 1));
+    // Flipping a tile doesn't change whether it's solid.
+    assert!(!map.is_solid(1, 1));
+    // Nothing there at all.
+    assert!(!map.is_solid(2, 1));
+    assert!(map.is_solid(-1, 1));
+    assert!(map.is_solid(3, 1));
+    assert!(map.is_solid_at(8.0, 8.0));
+    assert!(!map.is_solid_at(8.0, 24.0));
+}
+
+#[test]
+fn objects_are_found_by_position() {
+    let map = Tilemap::parse_tmx(TMX).unwrap();
+    let names: Vec<&str> = map.objects_at(4.0, 20.0).map(|object| object.name.as_str()).collect();
+    assert_eq!(names, vec!["sign"]);
+    assert_eq!(map.objects_at(20.0, 20.0).count(), 0);
+
+    let exits = map.exits();
+    assert_eq!(exits.len(), 1);
+    assert_eq!(exits[0].target, "town");
+    assert_eq!((exits[0].position.x, exits[0].position.z), (40.0, 24.0));
+}
+
+#[test]
+fn quads_come_from_the_atlas() {
+    let map = Tilemap::parse_tmj(TMJ).unwrap();
+    let quads = map.quads(&map.layers[0]);
+    assert_eq!(quads.len(), 5);
+
+    // Tile 2 is the top right of the atlas.
+    assert_eq!(quads[0].rect, [0.0, 0.0, 16.0, 16.0]);
+    assert_eq!(quads[0].uvs, [[0.5, 0.0], [1.0, 0.0], [1.0, 0.5], [0.5, 0.5]]);
+
+    // Tile 1 flipped horizontally swaps left and right.
+    assert_eq!(quads[4].rect, [16.0, 16.0, 16.0, 16.0]);
+    assert_eq!(quads[4].uvs, [[0.5, 0.0], [0.0, 0.0], [0.0, 0.5], [0.5, 0.5]]);
+}
+
+#[test]
+fn scrolling_stays_inside_the_map() {
+    let mut map = Tilemap::parse_tmj(TMJ).unwrap();
+    // Smaller than the screen, so it goes in the middle.
+    assert_eq!(map.scroll_to(0.0, 0.0), [(48.0 - SCREEN_WIDTH as f32) / 2.0, (32.0 - SCREEN_HEIGHT as f32) / 2.0]);
+
+    // Bigger than the screen, so it follows until it reaches the edge.
+    map.width = 100;
+    map.height = 100;
+    assert_eq!(map.scroll_to(0.0, 0.0), [0.0, 0.0]);
+    assert_eq!(map.scroll_to(800.0, 800.0), [800.0 - SCREEN_WIDTH as f32 / 2.0, 800.0 - SCREEN_HEIGHT as f32 / 2.0]);
+    assert_eq!(map.scroll_to(1600.0, 1600.0), [1600.0 - SCREEN_WIDTH as f32, 1600.0 - SCREEN_HEIGHT as f32]);
+}
+
+#[test]
+fn unsupported_maps_say_why() {
+    let external = TMJ.replace(r#""firstgid": 1, "image""#, r#""firstgid": 1, "source": "tiles.tsj", "image""#);
+    assert!(Tilemap::parse_tmj(&external).unwrap_err().contains("embed it in the map"));
+    let isometric = TMJ.replace("orthogonal", "isometric");
+    assert!(Tilemap::parse_tmj(&isometric).unwrap_err().contains("orthogonal"));
+    let short = TMJ.replace("[2, 2, 2, 1, 2147483649, 0]", "[2, 2, 2]");
+    assert!(Tilemap::parse_tmj(&short).unwrap_err().contains("should have 6 tiles"));
+}
+
+#[test]
+fn test_map_loads_with_its_atlas() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let map = runtime.block_on(Tilemap::load(&AssetServer::new(env!("CARGO_MANIFEST_DIR")), "fields/test_tilemap.tmj")).unwrap();
+    assert_eq!(map.atlases.len(), map.tilesets.len());
+    assert_eq!(map.tilesets[0].image_width, map.atlases[0].width());
+    // Walled in, with a gap at the bottom for the exit.
+    assert!(map.is_solid(0, 0));
+    assert!(!map.is_solid(19, 49));
+    assert_eq!(map.exits()[0].target, "test_field");
+}
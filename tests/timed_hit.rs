@@ -0,0 +1,77 @@
+// Timed hits, pressing just as a skill lands for more damage.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    combat::{CombatConfig, HitResult, Outcome, StandardRules},
+    timed_hit::{TimedGrade, TimedHit, TimedWindow}
+};
+
+fn timed() -> TimedHit {
+    TimedHit::new(TimedWindow::default(), &StandardRules(CombatConfig::default()))
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn presses_are_graded_by_how_close_they_were() {
+    let window = TimedWindow::default();
+    assert_eq!(window.grade(Duration::ZERO), TimedGrade::Perfect);
+    assert_eq!(window.grade(window.great), TimedGrade::Great);
+    assert_eq!(window.grade(window.good), TimedGrade::Good);
+    assert_eq!(window.grade(window.good + ms(1)), TimedGrade::Miss);
+
+    // Early, then the hit comes.
+    let mut hit = timed();
+    hit.update(ms(400));
+    assert!(hit.press());
+    assert_eq!(hit.grade(), None);
+    hit.update(ms(70));
+    hit.hit();
+    assert_eq!(hit.grade(), Some(TimedGrade::Great));
+    assert_eq!(hit.multiplier(), 1.5);
+
+    // A little late.
+    let mut hit = timed();
+    hit.update(ms(500));
+    hit.hit();
+    hit.update(ms(20));
+    hit.press();
+    assert_eq!(hit.grade(), Some(TimedGrade::Perfect));
+    let result = HitResult { outcome: Outcome::Hit, damage: 21, blocked: false, countered: false };
+    assert_eq!(hit.apply(result).damage, 42);
+    let missed = HitResult { outcome: Outcome::Miss, damage: 0, ..result };
+    assert_eq!(hit.apply(missed), missed);
+}
+
+#[test]
+fn mashing_and_waiting_too_long_miss() {
+    let mut hit = timed();
+    assert!(hit.press());
+    assert!(!hit.press());
+    hit.update(ms(500));
+    hit.hit();
+    assert_eq!(hit.grade(), Some(TimedGrade::Miss));
+    assert_eq!(hit.multiplier(), 1.0);
+
+    let mut hit = timed();
+    hit.hit();
+    hit.update(TimedWindow::default().good);
+    assert_eq!(hit.grade(), None);
+    hit.update(ms(1));
+    assert_eq!(hit.grade(), Some(TimedGrade::Miss));
+    assert!(!hit.press());
+    assert!(!hit.is_finished());
+    hit.update(Duration::from_secs(1));
+    assert!(hit.is_finished());
+}
+
+#[test]
+fn grades_multiply_by_the_config() {
+    let config = CombatConfig::parse("timed_good = 1.1\ntimed_perfect = 3").unwrap();
+    assert_eq!((config.timed_good, config.timed_great, config.timed_perfect), (1.1, 1.5, 3.0));
+    assert!(CombatConfig::parse("timed_great = -1").is_err());
+    assert_eq!("Perfect".parse::<TimedGrade>(), Ok(TimedGrade::Perfect));
+}
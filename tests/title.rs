@@ -0,0 +1,116 @@
+// The title screen and its config.
+
+use std::time::Duration;
+
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    pointer::PointerEvent,
+    save,
+    title::{TitleAction, TitleBackground, TitleConfig, TitleOption, TitleScreen},
+    ui::UiBatch
+};
+
+const CONFIG: &str = "
+# For testing.
+title = Test Quest
+field = town
+menu = new_game, continue, settings
+start = cave entrance
+idle = 10
+attract = title.attract
+";
+
+fn title() -> TitleScreen {
+    TitleScreen::new(TitleConfig::parse(CONFIG).unwrap())
+}
+
+#[test]
+fn the_config_is_read() {
+    let config = TitleConfig::parse(CONFIG).unwrap();
+    assert_eq!(config.title, "Test Quest");
+    assert_eq!(config.background, TitleBackground::Field("town".to_string()));
+    assert_eq!(config.menu, TitleOption::ALL);
+    assert_eq!((config.start_field.as_str(), config.start_spawn.as_deref()), ("cave", Some("entrance")));
+    assert_eq!((config.idle_seconds, config.attract.as_deref()), (10.0, Some("title.attract")));
+
+    // Anything left out keeps its default.
+    let config = TitleConfig::parse("image = title.png\nmenu = continue, new_game").unwrap();
+    assert_eq!(config.background, TitleBackground::Image("title.png".to_string()));
+    assert_eq!(config.menu, [TitleOption::Continue, TitleOption::NewGame]);
+    assert_eq!(config.attract, None);
+}
+
+#[test]
+fn bad_configs_say_where() {
+    assert!(TitleConfig::parse("title").unwrap_err().starts_with("Line 1: expected \"key = value\""));
+    assert!(TitleConfig::parse("\nmenu = new_game, quit").unwrap_err().contains("Line 2: Unknown title option \"quit\""));
+    assert!(TitleConfig::parse("image = a.png\nfield = town").unwrap_err().contains("only one of image or field"));
+    assert!(TitleConfig::parse("idle = -1").unwrap_err().contains("bad idle time"));
+    assert!(TitleConfig::parse("colour = red").unwrap_err().contains("unknown key \"colour\""));
+}
+
+#[test]
+fn continue_needs_a_save() {
+    let mut title = title();
+    title.open(false);
+    assert_eq!(title.selected(), Some(TitleOption::NewGame));
+    // Continue is skipped over.
+    title.handle_key(VirtualKeyCode::Down);
+    assert_eq!(title.selected(), Some(TitleOption::Settings));
+    title.handle_key(VirtualKeyCode::Up);
+    assert_eq!(title.handle_key(VirtualKeyCode::Return), Some(TitleAction::NewGame));
+
+    // With a save, it starts on continue.
+    title.open(true);
+    assert_eq!(title.handle_key(VirtualKeyCode::Return), Some(TitleAction::Continue));
+}
+
+#[test]
+fn saves_are_found_in_any_slot() {
+    let dir = std::env::temp_dir().join(format!("ps_rpg_engine_title_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert!(!save::any_saves(&dir));
+    let path = save::slot_path(&dir, save::SLOT_COUNT - 1);
+    std::fs::write(&path, b"").unwrap();
+    let found = save::any_saves(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(found);
+}
+
+#[test]
+fn the_attract_intro_waits_for_idle() {
+    let mut title = title();
+    assert_eq!(title.update(Duration::from_secs(20)), None);
+    title.open(false);
+    assert_eq!(title.update(Duration::from_secs(8)), None);
+    // Pressing anything starts the wait again.
+    title.handle_key(VirtualKeyCode::Down);
+    assert_eq!(title.update(Duration::from_secs(8)), None);
+    assert_eq!(title.update(Duration::from_secs(2)), Some(TitleAction::Attract("title.attract".to_string())));
+    assert_eq!(title.update(Duration::from_secs(2)), None);
+
+    // Without a script there's nothing to wait for.
+    let mut title = TitleScreen::new(TitleConfig::default());
+    title.open(false);
+    assert_eq!(title.update(Duration::from_secs(600)), None);
+}
+
+#[test]
+fn clicking_an_option_picks_it() {
+    let accessibility = Accessibility::new();
+    let mut title = title();
+    title.open(false);
+
+    let mut batch = UiBatch::new();
+    title.build(&mut batch, &accessibility, None);
+    assert!(!batch.is_empty());
+
+    // The menu's in the middle, just below halfway.
+    assert_eq!(title.handle_pointer(PointerEvent::Click { x: 320.0, y: 450.0 }, &accessibility).action, Some(TitleAction::NewGame));
+    // Continue can't be picked without a save, and clicking elsewhere does nothing.
+    assert_eq!(title.handle_pointer(PointerEvent::Click { x: 320.0, y: 480.0 }, &accessibility).action, None);
+    assert_eq!(title.handle_pointer(PointerEvent::Click { x: 20.0, y: 20.0 }, &accessibility).action, None);
+    assert!(title.is_open());
+}
@@ -0,0 +1,80 @@
+// Tweening bits of UI.
+
+use std::time::Duration;
+
+use ps_rpg_engine::{
+    screen_effects::Easing,
+    tween::{MenuSlide, Repeat, Tween, UiPose, SLIDE_TIME}
+};
+
+fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+#[test]
+fn steps_play_one_after_another() {
+    let mut tween = Tween::new(UiPose::REST)
+        .then(UiPose::at(10.0, 0.0), ms(100), Easing::Linear)
+        .hold(ms(100))
+        .then(UiPose { alpha: 0.0, ..UiPose::at(10.0, -20.0) }, ms(200), Easing::Linear);
+    assert_eq!(tween.duration(), ms(400));
+
+    tween.update(ms(50));
+    assert_eq!(tween.pose().offset, [5.0, 0.0]);
+    tween.update(ms(100));
+    assert_eq!(tween.pose().offset, [10.0, 0.0]);
+    tween.update(ms(150));
+    assert_eq!(tween.pose(), UiPose { alpha: 0.5, ..UiPose::at(10.0, -10.0) });
+
+    // Stays at the end.
+    assert!(tween.update(ms(500)));
+    assert!(tween.is_finished());
+    assert!(!tween.update(ms(100)));
+    assert_eq!(tween.pose(), tween.end());
+}
+
+#[test]
+fn repeating_tweens_go_round() {
+    let grow = UiPose { scale: 2.0, ..UiPose::REST };
+    let mut pulse = Tween::new(UiPose::REST).then(grow, ms(100), Easing::Linear).repeat(Repeat::PingPong);
+    pulse.update(ms(100));
+    assert_eq!(pulse.pose().scale, 2.0);
+    pulse.update(ms(50));
+    assert_eq!(pulse.pose().scale, 1.5);
+    // Back to the start and on the way up again.
+    assert!(pulse.update(ms(75)));
+    assert_eq!(pulse.pose().scale, 1.25);
+    assert!(!pulse.is_finished());
+
+    let mut looped = Tween::new(UiPose::REST).then(grow, ms(100), Easing::Linear).repeat(Repeat::Loop);
+    looped.update(ms(1025));
+    assert_eq!(looped.pose().scale, 1.25);
+}
+
+#[test]
+fn menus_slide_in_and_out() {
+    let mut slide = MenuSlide::new();
+    assert!(!slide.is_showing());
+    slide.open();
+    assert_eq!(slide.pose(), MenuSlide::hidden());
+    slide.update(SLIDE_TIME);
+    assert_eq!(slide.pose(), UiPose::REST);
+
+    // Still drawn while it slides out.
+    slide.close();
+    slide.update(SLIDE_TIME / 2);
+    assert!(slide.is_showing());
+    let half_closed = slide.pose();
+    assert!(half_closed.alpha > 0.0 && half_closed.alpha < 1.0);
+    slide.update(SLIDE_TIME);
+    assert!(!slide.is_showing());
+    assert_eq!(slide.pose(), MenuSlide::hidden());
+
+    // Closing part way through opening, or the other way round, goes back from where it got to.
+    slide.open();
+    slide.update(SLIDE_TIME / 2);
+    slide.close();
+    let half_open = slide.pose();
+    slide.open();
+    assert_eq!(slide.pose(), half_open);
+}
@@ -0,0 +1,239 @@
+// Checking glTF files follow the engine's conventions, and that the game's data files agree with
+// each other.
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    assets::AssetManifest,
+    battle_scene::BattleScenes,
+    battle_script::BattleScript,
+    combo::ComboTech,
+    field::{FieldDescriptor, FieldMap},
+    formation::{EncounterTable, Formation},
+    inventory::Item,
+    loot::EnemyLoot,
+    party::{Party, PartyMember, Skill, Stats, TargetType},
+    pickup::{FieldPickup, Respawn},
+    pools::PoolDefinition,
+    puzzle::FieldPuzzle,
+    summon::SummonDefinition,
+    trap::{FieldTrap, TrapKind},
+    validate::{validate_data, validate_gltf, GameData, GltfKind, Severity, ValidationReport}
+};
+
+// A minimal glTF with the given nodes and meshes, and a one triangle buffer for them to use.
+// Meshes get a triangle from `min` to `max` on every axis.
+fn gltf(nodes: &str, meshes: &str, extra: &str) -> Vec<u8> {
+    format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "scene": 0,
+        "scenes": [{{ "nodes": [0, 1, 2] }}],
+        "nodes": [{}],
+        "meshes": [{}],
+        "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [2, 2, 2] }}],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+        "buffers": [{{ "byteLength": 36, "uri": "triangle.bin" }}]
+        {}
+    }}"#, nodes, meshes, extra).into_bytes()
+}
+
+fn mesh(name: &str, mode: u32) -> String {
+    format!(r#"{{ "name": "{}", "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "mode": {} }}] }}"#, name, mode)
+}
+
+const CAMERA: &str = r#", "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }]"#;
+
+fn field(walkmesh_name: &str) -> Vec<u8> {
+    gltf(
+        r#"{ "mesh": 0 }, { "mesh": 1 }, { "name": "Camera", "camera": 0 }"#,
+        &format!("{}, {}", mesh("floor", 4), mesh(walkmesh_name, 4)),
+        CAMERA
+    )
+}
+
+fn messages(bytes: &[u8], kind: GltfKind) -> Vec<String> {
+    validate_gltf(bytes, kind).issues.iter().map(|issue| issue.to_string()).collect()
+}
+
+#[test]
+fn test_models_are_fine() {
+    for path in ["models/test_prop.gltf", "models/test_face.gltf", "models/test_tail.gltf"] {
+        let bytes = std::fs::read(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path)).unwrap();
+        let report = validate_gltf(&bytes, GltfKind::Model);
+        assert!(!report.has_errors(), "{}: {:?}", path, report.issues);
+    }
+}
+
+#[test]
+fn a_well_made_field_is_fine() {
+    assert_eq!(messages(&field("walkmesh"), GltfKind::Field), Vec::<String>::new());
+}
+
+#[test]
+fn garbage_is_an_error() {
+    let report = validate_gltf(b"not a gltf", GltfKind::Model);
+    assert!(report.has_errors());
+    assert!(report.issues[0].message.starts_with("not a valid glTF file"));
+}
+
+#[test]
+fn fields_need_a_camera_and_walkmesh() {
+    let bytes = gltf(r#"{ "mesh": 0 }, { "mesh": 0 }, { "mesh": 0 }"#, &mesh("floor", 4), "");
+    let report = validate_gltf(&bytes, GltfKind::Field);
+    assert!(report.has_errors());
+    let text = report.issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>().join("\n");
+    assert!(text.contains("there's no camera"), "{}", text);
+    assert!(text.contains("there's no walk mesh"), "{}", text);
+
+    // Models don't need either.
+    assert!(!validate_gltf(&bytes, GltfKind::Model).has_errors());
+}
+
+#[test]
+fn fields_with_several_cameras_need_them_named() {
+    let several = |second: &str| gltf(
+        &format!(r#"{{ "mesh": 0 }}, {{ "mesh": 1 }}, {{ "name": "Hall", "camera": 0, "children": [3] }}, {{ {}"camera": 0 }}"#, second),
+        &format!("{}, {}", mesh("floor", 4), mesh("walkmesh", 4)),
+        CAMERA
+    );
+    assert_eq!(messages(&several(r#""name": "Door", "#), GltfKind::Field), Vec::<String>::new());
+    assert_eq!(messages(&several(r#""name": "Hall", "#), GltfKind::Field), ["error: there's more than one camera called Hall, each needs its own name"]);
+    assert!(messages(&several(""), GltfKind::Field)[0].starts_with("error: camera node 3 doesn't have a name"));
+}
+
+#[test]
+fn near_miss_walkmesh_names_suggest_a_rename() {
+    for name in ["WalkMesh", "walk_mesh", "Walk Mesh"] {
+        let found = messages(&field(name), GltfKind::Field);
+        assert_eq!(found, vec![format!("error: there's no walk mesh. If {} is meant to be it, rename it to walkmesh", name)]);
+    }
+}
+
+#[test]
+fn meshes_must_be_triangles() {
+    // Mode 1 is lines.
+    let bytes = gltf(r#"{ "mesh": 0 }, { "mesh": 0 }, { "mesh": 0 }"#, &mesh("wire", 1), "");
+    let report = validate_gltf(&bytes, GltfKind::Model);
+    assert!(report.has_errors());
+    assert!(report.issues[0].message.contains("Triangulate"));
+}
+
+#[test]
+fn centimetre_exports_are_warned_about() {
+    let bytes = gltf(
+        r#"{ "mesh": 0, "scale": [100, 100, 100] }, { "mesh": 0 }, { "mesh": 0 }"#,
+        &mesh("rock", 4),
+        ""
+    );
+    let report = validate_gltf(&bytes, GltfKind::Model);
+    assert!(!report.has_errors());
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].severity, Severity::Warning);
+    assert!(report.issues[0].message.contains("centimetres"));
+}
+
+#[test]
+fn misnamed_detail_levels_are_warned_about() {
+    let bytes = gltf(r#"{ "mesh": 0 }, { "mesh": 0 }, { "mesh": 0 }"#, &mesh("rock_lod1", 4), "");
+    let found = messages(&bytes, GltfKind::Model);
+    assert_eq!(found.len(), 1);
+    assert!(found[0].contains("like rock_LOD1"), "{}", found[0]);
+}
+
+#[test]
+fn errors_come_before_warnings() {
+    let bytes = gltf(
+        r#"{ "mesh": 0, "scale": [100, 100, 100] }, { "mesh": 1 }, { "mesh": 1 }"#,
+        &format!("{}, {}", mesh("rock", 4), mesh("wire", 1)),
+        ""
+    );
+    let report = validate_gltf(&bytes, GltfKind::Model);
+    let severities: Vec<Severity> = report.issues.iter().map(|issue| issue.severity).collect();
+    // The lines are used twice but only complained about once.
+    assert_eq!(severities, vec![Severity::Error, Severity::Warning]);
+}
+
+// Data with one of every mistake in it.
+fn validate_broken_data(manifest: &str) -> ValidationReport {
+    let items = Item::parse_list("[potion]\n[potion]").unwrap();
+    let loot = EnemyLoot::parse_list("[slime]\ndrop = potoin 0.5\n[dragon]\ndrop = potion 1").unwrap();
+    let formations = Formation::parse_list("[slimes]\nenemy = slime 2 front\nmusic = music/slimes.cfg").unwrap();
+    let encounter_tables = EncounterTable::parse_list("[cave]\nslimes = 1\nbats = 1").unwrap();
+    let battle_scripts = BattleScript::parse_list("[slimes]\non = hp slime 50\nphase = king angry\nspawn = king 1 back\nstat = bat speed 200").unwrap();
+    let battle_scenes = BattleScenes::parse("[cave]\nbackdrop = battles/cave.png").unwrap();
+    let combos = ComboTech::parse_list("[Twin Cure]\nmember = Aria 2\nmember = Bram 2").unwrap();
+    let pools = PoolDefinition::parse_list("[tp]").unwrap();
+    let summons = SummonDefinition::parse_list("[imp]\n[golem]\nskill = Stone Call").unwrap();
+    let mut fields = FieldMap::new();
+    let pickup = |id: &str, item: &str| FieldPickup { id: id.to_string(), item: item.to_string(), count: 1, position: Vector3::new(0.0, 0.0, 0.0), respawn: Respawn::Never };
+    let tide_puzzle = |pieces: &[&str]| FieldPuzzle { flags: vec!["tide".to_string()], pieces: pieces.iter().map(|piece| piece.to_string()).collect() };
+    let pit = FieldTrap { id: "pit".to_string(), kind: TrapKind::Pitfall { field: "cellar".to_string(), spawn: None }, position: Vector3::new(0.0, 0.0, 0.0), flag: String::new() };
+    fields.insert("beach", FieldDescriptor { terrain: "sand".to_string(), pickups: vec![pickup("shell", "seashell"), pickup("shell", "potion")], traps: vec![pit], puzzle: tide_puzzle(&["crab"]), ..Default::default() });
+    fields.insert("cove", FieldDescriptor { puzzle: tide_puzzle(&[]), ..Default::default() });
+    let mut member = PartyMember::new("Aria", Stats::default());
+    member.gambits = vec!["always: skill Cure".parse().unwrap(), "ally down: item elixir".parse().unwrap()];
+    member.skills.push(Skill {
+        name: "Gem Blast".to_string(),
+        description: String::new(),
+        mp_cost: 0,
+        costs: vec!["tp 20".parse().unwrap(), "ep 5".parse().unwrap(), "item gem".parse().unwrap()],
+        target: TargetType::Enemy,
+        field: None
+    });
+    let party = Party::new(vec![member]);
+    validate_data(&GameData {
+        items: &items,
+        loot: &loot,
+        formations: &formations,
+        encounter_tables: &encounter_tables,
+        battle_scripts: &battle_scripts,
+        battle_scenes: &battle_scenes,
+        combos: &combos,
+        summons: &summons,
+        pools: &pools,
+        fields: &fields,
+        party: &party,
+        manifest: &AssetManifest::parse(manifest)
+    })
+}
+
+#[test]
+fn every_broken_reference_in_the_data_is_reported() {
+    let report = validate_broken_data("battles/cave.png\nmusic/slimes.cfg");
+    let messages: Vec<String> = report.issues.iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(messages, vec![
+        "error: data/items.cfg: there's more than one potion",
+        "error: data/loot.cfg: slime has potoin, which isn't an item",
+        "error: data/encounters.cfg: cave has bats, which isn't a formation",
+        "error: data/battle_scripts.cfg: slimes has bat, who isn't in the battle",
+        "error: field beach: the pickup shell gives seashell, which isn't an item",
+        "error: field beach: there's more than one pickup called shell, so they'd share a flag",
+        "error: field beach: the pitfall pit drops into cellar, which isn't a field",
+        "error: field beach: the puzzle piece crab isn't one of its props or movers",
+        "error: field cove: the puzzle flag tide is in beach's puzzle too, so leaving either would clear it",
+        "error: Aria: the gambit \"ally down: item elixir\" is for an item that doesn't exist",
+        "error: Aria: Gem Blast costs ep, which isn't in data/pools.cfg",
+        "error: Aria: Gem Blast costs gem, which isn't an item",
+        "error: data/summons.cfg: imp has no skill to call them",
+        "warning: data/loot.cfg: dragon isn't in any formation, so its loot can't be won",
+        "warning: data/battle_scenes.cfg: there's no [default], so terrains without a scene have no backdrop",
+        "warning: field beach: there's no battle scene for its terrain sand, so it gets the default",
+        "warning: Aria: the gambit \"always: skill Cure\" is for a skill they don't have",
+        "warning: data/combos.cfg: Twin Cure needs Bram, who isn't in the party",
+        "warning: data/summons.cfg: golem is called by Stone Call, which nobody in the party knows"
+    ]);
+    assert!(report.has_errors());
+}
+
+#[test]
+fn data_assets_have_to_be_in_the_manifest() {
+    let report = validate_broken_data("");
+    let missing: Vec<&str> = report.issues.iter()
+        .map(|issue| issue.message.as_str())
+        .filter(|message| message.contains("manifest"))
+        .collect();
+    assert_eq!(missing, vec![
+        "data/formations.cfg: music/slimes.cfg isn't in the asset manifest",
+        "data/battle_scenes.cfg: battles/cave.png isn't in the asset manifest"
+    ]);
+}
@@ -0,0 +1,121 @@
+// Guards spotting the player with their vision cones.
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+
+use ps_rpg_engine::{
+    emote::{self, Emote, EmoteKind},
+    field::FieldDescriptor,
+    field_status::{FieldBoost, FieldEffect, FieldStatus},
+    schedule::ScheduledNpc,
+    tilemap::Tilemap,
+    transform::Transform,
+    vision::{self, DetectionEvents, VisionBlocker, VisionCone},
+    world::{Entity, World}
+};
+
+// Standing at the origin, looking along x.
+fn guard(world: &mut World) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform { rotation: Quaternion::from_angle_y(Deg(90.0)), ..Default::default() });
+    world.insert(entity, VisionCone { angle: 90.0, range: 5.0 });
+    entity
+}
+
+fn player(world: &mut World, x: f32, z: f32) -> Entity {
+    let entity = world.spawn();
+    world.insert(entity, Transform::from_position(Vector3::new(x, 0.0, z)));
+    entity
+}
+
+fn move_to(world: &mut World, entity: Entity, x: f32, z: f32) {
+    world.get_mut::<Transform>(entity).unwrap().position = Vector3::new(x, 0.0, z);
+}
+
+#[test]
+fn guards_only_see_in_front_of_them() {
+    let cone = VisionCone { angle: 90.0, range: 5.0 };
+    let eye = Transform { rotation: Quaternion::from_angle_y(Deg(90.0)), ..Default::default() };
+    assert!(cone.contains(&eye, Vector3::new(4.0, 0.0, 0.0)));
+    assert!(cone.contains(&eye, Vector3::new(3.0, 1.5, 2.9)));
+    // Too far, off to the side, and behind.
+    assert!(!cone.contains(&eye, Vector3::new(5.5, 0.0, 0.0)));
+    assert!(!cone.contains(&eye, Vector3::new(2.0, 0.0, 2.5)));
+    assert!(!cone.contains(&eye, Vector3::new(-1.0, 0.0, 0.0)));
+}
+
+#[test]
+fn blockers_get_in_the_way() {
+    let crate_box = VisionBlocker { min: [2.0, -0.5], max: [3.0, 0.5] };
+    assert!(crate_box.blocks(Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 0.0, 0.0)));
+    assert!(!crate_box.blocks(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.5, 0.0, 0.0)));
+    assert!(!crate_box.blocks(Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 0.0, 3.0)));
+
+    let mut world = World::new();
+    let guard = guard(&mut world);
+    let player = player(&mut world, 4.0, 0.0);
+    world.insert_resource(FieldDescriptor { blockers: vec![crate_box], ..Default::default() });
+    assert!(vision::update_vision(&mut world, player).is_empty());
+
+    move_to(&mut world, player, 4.0, 2.0);
+    assert_eq!(vision::update_vision(&mut world, player), [guard]);
+}
+
+#[test]
+fn solid_tiles_get_in_the_way_in_2d_fields() {
+    // A 3x1 map with a wall in the middle.
+    let map = Tilemap::parse_tmj(r#"{
+        "orientation": "orthogonal", "infinite": false,
+        "width": 3, "height": 1, "tilewidth": 16, "tileheight": 16,
+        "tilesets": [{
+            "firstgid": 1, "image": "tiles.png", "imagewidth": 32, "imageheight": 16,
+            "tilewidth": 16, "tileheight": 16, "columns": 2, "tilecount": 2,
+            "tiles": [{ "id": 1, "properties": [{ "name": "solid", "type": "bool", "value": true }] }]
+        }],
+        "layers": [{ "type": "tilelayer", "name": "ground", "width": 3, "height": 1, "data": [1, 2, 1] }]
+    }"#).unwrap();
+    let (eye, player) = (Vector3::new(8.0, 0.0, 8.0), Vector3::new(40.0, 0.0, 8.0));
+    assert!(!vision::line_of_sight(eye, player, &[], Some(&map)));
+    assert!(vision::line_of_sight(eye, Vector3::new(14.0, 0.0, 8.0), &[], Some(&map)));
+    assert!(vision::line_of_sight(eye, player, &[], None));
+}
+
+#[test]
+fn guards_spot_the_player_once_each_time_they_come_into_view() {
+    let mut world = World::new();
+    let guard = guard(&mut world);
+    let player = player(&mut world, -2.0, 0.0);
+    assert!(vision::update_vision(&mut world, player).is_empty());
+
+    move_to(&mut world, player, 2.0, 0.0);
+    assert_eq!(vision::update_vision(&mut world, player), [guard]);
+    assert_eq!(world.get::<Emote>(guard).map(|emote| emote.kind), Some(EmoteKind::Exclamation));
+    move_to(&mut world, player, 3.0, 0.5);
+    assert!(vision::update_vision(&mut world, player).is_empty());
+    let events = world.resource_mut::<DetectionEvents>().unwrap().take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].guard, events[0].position), (guard, Vector3::new(2.0, 0.0, 0.0)));
+
+    move_to(&mut world, player, -2.0, 0.0);
+    assert!(vision::update_vision(&mut world, player).is_empty());
+    move_to(&mut world, player, 2.0, 0.0);
+    assert_eq!(vision::update_vision(&mut world, player), [guard]);
+}
+
+#[test]
+fn sneaking_keeps_guards_short_sighted() {
+    let mut world = World::new();
+    let guard = guard(&mut world);
+    let player = player(&mut world, 4.0, 0.0);
+    let mut status = FieldStatus::new();
+    status.apply(FieldBoost { effect: FieldEffect::Sneak, steps: 10 });
+    world.insert_resource(status);
+    assert!(vision::update_vision(&mut world, player).is_empty());
+    move_to(&mut world, player, 2.0, 0.0);
+    assert_eq!(vision::update_vision(&mut world, player), [guard]);
+
+    // Guards who are also scheduled NPCs don't spot the player just for being close.
+    world.remove::<Emote>(guard);
+    world.insert(guard, ScheduledNpc { name: "Guard".to_string(), entry: 0, behaviour: String::new() });
+    move_to(&mut world, player, -1.0, 0.0);
+    assert!(emote::spot_player(&mut world, player, emote::SPOT_RADIUS).is_empty());
+}
@@ -0,0 +1,121 @@
+// The developer warp menu, its presets and field spawn points.
+
+use cgmath::Vector3;
+use winit::event::VirtualKeyCode;
+
+use ps_rpg_engine::{
+    accessibility::Accessibility,
+    field::{FieldDescriptor, FieldMap, FieldSpawn},
+    flags::GameFlags,
+    pointer::PointerEvent,
+    ui::UiBatch,
+    warp_menu::{WarpChoice, WarpMenu, WarpPreset}
+};
+
+const PRESETS: &str = "
+# For testing.
+[After the introduction]
+intro.complete = 1
+
+[Lost cat found]
+intro.complete = 1
+quest.lost_cat = 2
+";
+
+fn fields() -> FieldMap {
+    let mut fields = FieldMap::new();
+    fields.insert("town", FieldDescriptor {
+        spawns: vec![
+            FieldSpawn { name: "gate".to_string(), position: Vector3::new(1.0, 0.0, 2.0) },
+            FieldSpawn { name: "inn".to_string(), position: Vector3::new(-3.0, 0.0, 0.0) }
+        ],
+        ..Default::default()
+    });
+    fields.insert("cave", FieldDescriptor::default());
+    fields
+}
+
+fn field(name: &str, spawn: Option<&str>) -> WarpChoice {
+    WarpChoice::Field { field: name.to_string(), spawn: spawn.map(str::to_string) }
+}
+
+#[test]
+fn presets_set_their_flags() {
+    let presets = WarpPreset::parse_list(PRESETS).unwrap();
+    assert_eq!(presets.iter().map(|preset| preset.name.as_str()).collect::<Vec<_>>(), ["After the introduction", "Lost cat found"]);
+
+    let mut flags = GameFlags::new();
+    presets[1].apply(&mut flags);
+    assert_eq!((flags.get("intro.complete"), flags.get("quest.lost_cat")), (1, 2));
+}
+
+#[test]
+fn bad_presets_say_where() {
+    assert!(WarpPreset::parse_list("intro.complete = 1").unwrap_err().starts_with("Line 1"));
+    assert!(WarpPreset::parse_list("[A]\nintro.complete").unwrap_err().contains("expected \"flag = value\""));
+    assert!(WarpPreset::parse_list("[A]\n\nintro.complete = yes").unwrap_err().starts_with("Line 3: bad value"));
+}
+
+#[test]
+fn spawn_points_pick_where_to_start() {
+    let fields = fields();
+    let town = fields.get("town").unwrap();
+    assert_eq!(town.spawn_position(None), Ok(Vector3::new(1.0, 0.0, 2.0)));
+    assert_eq!(town.spawn_position(Some("inn")), Ok(Vector3::new(-3.0, 0.0, 0.0)));
+    assert!(town.spawn_position(Some("castle")).is_err());
+    assert_eq!(fields.get("cave").unwrap().spawn_position(None), Ok(Vector3::new(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn every_field_and_spawn_is_listed_then_the_presets() {
+    let presets = WarpPreset::parse_list(PRESETS).unwrap();
+    let choices = WarpChoice::list(&fields(), &presets);
+    assert_eq!(choices, [
+        field("cave", None),
+        field("town", None),
+        field("town", Some("gate")),
+        field("town", Some("inn")),
+        WarpChoice::Preset("After the introduction".to_string()),
+        WarpChoice::Preset("Lost cat found".to_string())
+    ]);
+    assert_eq!(choices[2].label(), "  gate");
+    assert_eq!(choices[4].label(), "Preset: After the introduction");
+}
+
+#[test]
+fn picking_closes_the_menu() {
+    let mut menu = WarpMenu::new();
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return), None);
+    menu.open(WarpChoice::list(&fields(), &[]));
+    assert!(menu.is_open());
+
+    menu.handle_key(VirtualKeyCode::Up);
+    menu.handle_key(VirtualKeyCode::Down);
+    menu.handle_key(VirtualKeyCode::Down);
+    assert_eq!(menu.handle_key(VirtualKeyCode::Return), Some(field("town", Some("gate"))));
+    assert!(!menu.is_open());
+
+    // It remembers where it was.
+    menu.open(WarpChoice::list(&fields(), &[]));
+    assert_eq!(menu.selected(), Some(&field("town", Some("gate"))));
+    menu.handle_key(VirtualKeyCode::Escape);
+    assert!(!menu.is_open());
+}
+
+#[test]
+fn clicking_a_row_picks_it() {
+    let accessibility = Accessibility::new();
+    let mut menu = WarpMenu::new();
+    menu.open(WarpChoice::list(&fields(), &[]));
+
+    let mut batch = UiBatch::new();
+    menu.build(&mut batch, &accessibility);
+    assert!(!batch.is_empty());
+
+    // Below the title, in the first row.
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: 60.0 }, &accessibility).action, Some(field("cave", None)));
+    // Clicking outside the list closes it.
+    menu.open(WarpChoice::list(&fields(), &[]));
+    assert_eq!(menu.handle_pointer(PointerEvent::Click { x: 100.0, y: 790.0 }, &accessibility).action, None);
+    assert!(!menu.is_open());
+}
@@ -0,0 +1,83 @@
+// Swimming in fields' water.
+
+use std::time::Duration;
+
+use cgmath::Vector3;
+
+use ps_rpg_engine::{
+    field::FieldDescriptor,
+    flags::GameFlags,
+    transform::Transform,
+    water::{self, Ripples, Swimmer, WaterRegion, RIPPLE_INTERVAL, RIPPLE_TIME, SWIM_SPEED},
+    world::{Entity, World}
+};
+
+fn pond(requires: Option<&str>) -> WaterRegion {
+    WaterRegion { min: [2.0, -1.0], max: [6.0, 1.0], requires: requires.map(str::to_string), footstep: Some("splash".to_string()), ..Default::default() }
+}
+
+fn world(water: WaterRegion) -> (World, Entity) {
+    let mut world = World::new();
+    world.insert_resource(FieldDescriptor { water: vec![water], ..Default::default() });
+    world.insert_resource(GameFlags::new());
+    let swimmer = world.spawn();
+    world.insert(swimmer, Transform::default());
+    world.insert(swimmer, Swimmer::new());
+    water::update_swimming(&mut world, Duration::ZERO);
+    (world, swimmer)
+}
+
+fn walk_to(world: &mut World, entity: Entity, x: f32) {
+    world.get_mut::<Transform>(entity).unwrap().position = Vector3::new(x, 0.0, 0.0);
+    water::update_swimming(world, Duration::from_millis(100));
+}
+
+fn ripples(world: &World) -> usize {
+    world.resource::<Ripples>().map_or(0, |ripples| ripples.0.len())
+}
+
+#[test]
+fn swimming_changes_speed_clips_and_footsteps() {
+    let (mut world, swimmer) = world(pond(None));
+    assert_eq!((water::speed_scale(&world, swimmer), water::clip_name(&world, swimmer, "walk")), (1.0, "walk".to_string()));
+    assert_eq!(water::footstep_sound(&world, swimmer, "footstep"), "footstep");
+
+    walk_to(&mut world, swimmer, 3.0);
+    assert!(world.get::<Swimmer>(swimmer).unwrap().water().is_some());
+    assert_eq!((water::speed_scale(&world, swimmer), water::clip_name(&world, swimmer, "walk")), (SWIM_SPEED, "swim_walk".to_string()));
+    assert_eq!(water::footstep_sound(&world, swimmer, "footstep"), "splash");
+
+    walk_to(&mut world, swimmer, 7.0);
+    assert_eq!(water::speed_scale(&world, swimmer), 1.0);
+}
+
+#[test]
+fn some_water_needs_a_flag_to_go_in() {
+    let (mut world, swimmer) = world(pond(Some("ability.swim")));
+    walk_to(&mut world, swimmer, 1.5);
+    walk_to(&mut world, swimmer, 3.0);
+    // Put back on the bank.
+    assert_eq!(world.get::<Transform>(swimmer).unwrap().position, Vector3::new(1.5, 0.0, 0.0));
+    assert!(world.get::<Swimmer>(swimmer).unwrap().water().is_none());
+
+    world.resource_mut::<GameFlags>().unwrap().set("ability.swim", 1);
+    walk_to(&mut world, swimmer, 3.0);
+    assert!(world.get::<Swimmer>(swimmer).unwrap().water().is_some());
+}
+
+#[test]
+fn swimmers_leave_ripples() {
+    let (mut world, swimmer) = world(pond(None));
+    walk_to(&mut world, swimmer, 3.0);
+    // A splash going in.
+    assert_eq!(ripples(&world), 1);
+    // Standing still doesn't make any more.
+    water::update_swimming(&mut world, RIPPLE_INTERVAL);
+    assert_eq!(ripples(&world), 1);
+    walk_to(&mut world, swimmer, 3.5);
+    assert_eq!(ripples(&world), 2);
+
+    // They fade away.
+    assert!(!water::update_swimming(&mut world, RIPPLE_TIME));
+    assert_eq!(ripples(&world), 0);
+}